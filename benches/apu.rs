@@ -0,0 +1,78 @@
+use boytacean::{
+    apu::Apu,
+    consts::{
+        NR10_ADDR, NR11_ADDR, NR12_ADDR, NR13_ADDR, NR14_ADDR, NR21_ADDR, NR22_ADDR, NR23_ADDR,
+        NR24_ADDR, NR30_ADDR, NR32_ADDR, NR33_ADDR, NR34_ADDR, NR42_ADDR, NR43_ADDR, NR44_ADDR,
+        NR50_ADDR, NR51_ADDR, NR52_ADDR,
+    },
+    gb::GameBoy,
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+fn build_apu() -> Apu {
+    let mut apu = Apu::default();
+    apu.write(NR52_ADDR, 0x80);
+    apu.write(NR50_ADDR, 0x77);
+    apu.write(NR51_ADDR, 0xff);
+
+    // channel 1 with sweep and envelope
+    apu.write(NR10_ADDR, 0x16);
+    apu.write(NR11_ADDR, 0x80);
+    apu.write(NR12_ADDR, 0xf3);
+    apu.write(NR13_ADDR, 0x00);
+    apu.write(NR14_ADDR, 0x87);
+
+    // channel 2 with envelope
+    apu.write(NR21_ADDR, 0x40);
+    apu.write(NR22_ADDR, 0xf7);
+    apu.write(NR23_ADDR, 0x80);
+    apu.write(NR24_ADDR, 0x86);
+
+    // channel 3 with a saw wave
+    for index in 0..16_u16 {
+        apu.write(0xff30 + index, (index as u8 * 0x11) ^ 0x0f);
+    }
+    apu.write(NR30_ADDR, 0x80);
+    apu.write(NR32_ADDR, 0x20);
+    apu.write(NR33_ADDR, 0x40);
+    apu.write(NR34_ADDR, 0x87);
+
+    // channel 4 with noise
+    apu.write(NR42_ADDR, 0xf2);
+    apu.write(NR43_ADDR, 0x55);
+    apu.write(NR44_ADDR, 0x80);
+    apu
+}
+
+fn benchmark_apu(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apu");
+    group.throughput(Throughput::Elements(GameBoy::LCD_CYCLES as u64));
+
+    let mut apu = build_apu();
+    group.bench_function("clock_frame", |b| {
+        b.iter(|| {
+            for _ in 0..GameBoy::LCD_CYCLES / 4 {
+                apu.clock(black_box(4));
+            }
+            apu.swap_audio_frame();
+            black_box(apu.take_audio_frame());
+        })
+    });
+
+    let mut apu = build_apu();
+    apu.set_batch(true);
+    group.bench_function("clock_frame_batch", |b| {
+        b.iter(|| {
+            for _ in 0..GameBoy::LCD_CYCLES / 4 {
+                apu.clock(black_box(4));
+            }
+            apu.swap_audio_frame();
+            black_box(apu.take_audio_frame());
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_apu);
+criterion_main!(benches);
@@ -1,4 +1,4 @@
-use boytacean::color::rgb888_to_rgb1555_scalar;
+use boytacean::color::{rgb888_to_rgb1555_scalar, rgb888_to_rgb565_scalar};
 use boytacean_common::bench::multiply_array_size;
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
@@ -17,9 +17,18 @@ fn benchmark_rgb_conversion(c: &mut Criterion) {
         })
     });
 
+    c.bench_function("rgb888_to_rgb565_scalar", |b| {
+        b.iter(|| {
+            rgb888_to_rgb565_scalar(
+                black_box(&rgb888_pixels_sized),
+                black_box(&mut rgb1555_pixels),
+            )
+        })
+    });
+
     #[cfg(feature = "simd")]
     {
-        use boytacean::color::rgb888_to_rgb1555_simd;
+        use boytacean::color::{rgb888_to_rgb1555_simd, rgb888_to_rgb565_simd};
         c.bench_function("rgb888_to_rgb1555_simd", |b| {
             b.iter(|| {
                 rgb888_to_rgb1555_simd(
@@ -28,6 +37,14 @@ fn benchmark_rgb_conversion(c: &mut Criterion) {
                 )
             })
         });
+        c.bench_function("rgb888_to_rgb565_simd", |b| {
+            b.iter(|| {
+                rgb888_to_rgb565_simd(
+                    black_box(&rgb888_pixels_sized),
+                    black_box(&mut rgb1555_pixels),
+                )
+            })
+        });
     }
 }
 
@@ -0,0 +1,47 @@
+use boytacean::{cpu::CpuBackend, gb::GameBoy};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+fn build_game_boy(backend: CpuBackend) -> GameBoy {
+    let mut game_boy = GameBoy::default();
+    game_boy.set_cpu_backend(backend);
+    game_boy.load(true).unwrap();
+    game_boy
+        .load_rom_file("res/roms/demo/pocket.gb", None)
+        .unwrap();
+    game_boy
+}
+
+fn benchmark_cpu(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cpu");
+    group.throughput(Throughput::Elements(GameBoy::LCD_CYCLES as u64));
+
+    // runs a complete frame of a demo (all components enabled)
+    // for both the interpreter and the cached backends
+    for backend in [CpuBackend::Interpreter, CpuBackend::Cached] {
+        let mut game_boy = build_game_boy(backend);
+        let name = format!("next_frame_{}", backend.description().to_lowercase());
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                black_box(game_boy.next_frame());
+            })
+        });
+    }
+
+    // runs the CPU in isolation (all other components disabled)
+    // so that the cost of the dispatch is measured
+    for backend in [CpuBackend::Interpreter, CpuBackend::Cached] {
+        let mut game_boy = build_game_boy(backend);
+        game_boy.set_all_enabled(false);
+        let name = format!("clocks_{}", backend.description().to_lowercase());
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                black_box(game_boy.clocks_cycles(GameBoy::LCD_CYCLES as usize));
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_cpu);
+criterion_main!(benches);
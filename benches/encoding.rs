@@ -1,9 +1,9 @@
-use boytacean_common::bench::generate_data;
+use boytacean_common::bench::{generate_data, generate_noise, BENCH_SEED};
 use boytacean_encoding::{
     huffman::{decode_huffman, encode_huffman},
     rc4::{decrypt_rc4, encrypt_rc4},
     rle::{decode_rle, encode_rle},
-    zippy::{decode_zippy, encode_zippy},
+    zippy::{decode_zippy, encode_zippy, ZippyEncoder},
 };
 use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
 
@@ -34,6 +34,29 @@ fn benchmark_encoding(c: &mut Criterion) {
         })
     });
 
+    // the streaming encoder with chunks of 64 KB, keeping
+    // a bounded amount of memory in use
+    group.bench_function("encode_zippy_chunked", |b| {
+        b.iter(|| {
+            let mut encoder =
+                ZippyEncoder::new(vec![], String::new(), String::new(), None, None).unwrap();
+            for chunk in black_box(&data).chunks(0x10000) {
+                encoder.write_chunk(chunk).unwrap();
+            }
+            black_box(encoder.finish().unwrap());
+        })
+    });
+
+    // the (hard to compress) noise is the worst case of the
+    // encoder, with few runs and a flat distribution of symbols
+    let noise = generate_noise(10_000_000_usize, BENCH_SEED);
+    group.bench_function("encode_zippy_noise", |b| {
+        b.iter(|| {
+            let encoded = encode_zippy(black_box(&noise), None, None).unwrap();
+            black_box(encoded);
+        })
+    });
+
     let mut data = generate_data(10_000_000_usize);
 
     group.bench_function("encrypt_rc4", |b| {
@@ -75,6 +98,15 @@ fn benchmark_decoding(c: &mut Criterion) {
         })
     });
 
+    let noise = generate_noise(10_000_000_usize, BENCH_SEED);
+    let encoded_noise = encode_zippy(&noise, None, None).unwrap();
+    group.bench_function("decode_zippy_noise", |b| {
+        b.iter(|| {
+            let decoded = decode_zippy(black_box(&encoded_noise), None).unwrap();
+            black_box(decoded);
+        })
+    });
+
     let mut data = generate_data(10_000_000_usize);
     encrypt_rc4(&mut data, b"testkey").unwrap();
 
@@ -0,0 +1,62 @@
+use boytacean::{
+    color::PixelFormat,
+    gb::GameBoy,
+    ppu::{DISPLAY_SIZE, FRAME_BUFFER_SIZE},
+};
+use boytacean_common::bench::{generate_noise, BENCH_SEED};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+/// The pixel formats into which the frame buffer is converted.
+const FORMATS: [PixelFormat; 3] = [
+    PixelFormat::Xrgb8888,
+    PixelFormat::Rgb1555,
+    PixelFormat::Rgb565,
+];
+
+fn build_game_boy() -> GameBoy {
+    let mut game_boy = GameBoy::default();
+    game_boy.load(true).unwrap();
+    game_boy
+        .load_rom_file("res/roms/demo/pocket.gb", None)
+        .unwrap();
+    for _ in 0..60 {
+        game_boy.next_frame();
+    }
+    game_boy
+}
+
+fn benchmark_frame(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame");
+    group.throughput(Throughput::Elements(DISPLAY_SIZE as u64));
+
+    // converts a noisy (RGB888) frame, so that every pixel
+    // differs from its neighbours
+    let frame = generate_noise(FRAME_BUFFER_SIZE, BENCH_SEED);
+    for format in FORMATS {
+        let mut pixels = vec![0u8; DISPLAY_SIZE * format.pixel_size()];
+        let name = format!("convert_{}", format.description().to_lowercase());
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                format.convert(black_box(&frame), black_box(&mut pixels));
+            })
+        });
+    }
+
+    // converts the (row based) frame buffer of the PPU into a
+    // borrowed buffer, the path used by the frontends
+    let mut game_boy = build_game_boy();
+    for format in FORMATS {
+        let mut pixels = vec![0u8; DISPLAY_SIZE * format.pixel_size()];
+        let name = format!("frame_buffer_into_{}", format.description().to_lowercase());
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                game_boy.frame_buffer_into(format, black_box(&mut pixels));
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_frame);
+criterion_main!(benches);
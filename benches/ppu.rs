@@ -0,0 +1,151 @@
+use boytacean::{
+    consts::{BCPD_ADDR, BCPS_ADDR, LCDC_ADDR, SCX_ADDR, SCY_ADDR, VBK_ADDR},
+    gb::GameBoyMode,
+    ppu::{Ppu, DISPLAY_HEIGHT, OBJ_COUNT},
+};
+use boytacean_common::bench::generate_data;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use std::time::{Duration, Instant};
+
+/// The number of cycles taken by each (visible or not) line.
+const LINE_CYCLES: u16 = 456;
+
+/// The number of lines in a frame (including VBlank).
+const FRAME_LINES: u16 = 154;
+
+fn build_ppu(lcdc: u8) -> Ppu {
+    let mut ppu = Ppu::default();
+    let data = generate_data(0x1800);
+    for (index, value) in data.iter().enumerate() {
+        ppu.write(0x8000 + index as u16, *value);
+    }
+    for index in 0..0x0800_u16 {
+        ppu.write(0x9800 + index, (index * 7) as u8);
+    }
+    ppu.write(0xff47, 0xe4);
+    ppu.write(0xff48, 0xe4);
+    ppu.write(0xff49, 0x1b);
+    ppu.write(LCDC_ADDR, lcdc);
+    ppu
+}
+
+fn build_ppu_cgb(lcdc: u8) -> Ppu {
+    let mut ppu = Ppu::default();
+    ppu.set_gb_mode(GameBoyMode::Cgb);
+    let data = generate_data(0x1800);
+    for bank in 0..2 {
+        ppu.write(VBK_ADDR, bank);
+        for (index, value) in data.iter().enumerate() {
+            ppu.write(0x8000 + index as u16, value.rotate_left(bank as u32));
+        }
+    }
+
+    // the attributes (bank 1) of the maps mix all the palettes,
+    // VRAM banks and flips so that every tile differs from the
+    // previous one (eg: detailed scrolling backgrounds)
+    ppu.write(VBK_ADDR, 1);
+    for index in 0..0x0800_u16 {
+        ppu.write(0x9800 + index, (index * 13) as u8);
+    }
+    ppu.write(VBK_ADDR, 0);
+    for index in 0..0x0800_u16 {
+        ppu.write(0x9800 + index, (index * 7) as u8);
+    }
+    ppu.write(BCPS_ADDR, 0x80);
+    for value in generate_data(64) {
+        ppu.write(BCPD_ADDR, value);
+    }
+    ppu.write(LCDC_ADDR, lcdc);
+    ppu
+}
+
+fn render_frame(ppu: &mut Ppu, scroll: bool) {
+    for line in 0..FRAME_LINES {
+        if scroll {
+            ppu.write(SCX_ADDR, line as u8);
+            ppu.write(SCY_ADDR, (line / 2) as u8);
+        }
+        for _ in 0..LINE_CYCLES / 4 {
+            ppu.clock(4);
+        }
+    }
+}
+
+/// Renders the provided number of visible lines, skipping over the
+/// VBlank period (not measured), returning the time taken by the lines.
+fn render_lines(ppu: &mut Ppu, count: u64) -> Duration {
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..count {
+        while ppu.ly() as usize >= DISPLAY_HEIGHT {
+            ppu.clock(4);
+        }
+        let start = Instant::now();
+        for _ in 0..LINE_CYCLES / 4 {
+            ppu.clock(4);
+        }
+        elapsed += start.elapsed();
+    }
+    elapsed
+}
+
+fn benchmark_ppu(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ppu");
+    group.throughput(Throughput::Elements(DISPLAY_HEIGHT as u64));
+
+    // background and window enabled with the scroll being
+    // changed on every line (eg: parallax effects)
+    let mut ppu = build_ppu(0xf1);
+    group.bench_function("render_frame_scroll", |b| {
+        b.iter(|| {
+            render_frame(black_box(&mut ppu), true);
+        })
+    });
+
+    // CGB background and window with per tile attributes and
+    // the scroll being changed on every line
+    let mut ppu = build_ppu_cgb(0xf1);
+    group.bench_function("render_frame_cgb_scroll", |b| {
+        b.iter(|| {
+            render_frame(black_box(&mut ppu), true);
+        })
+    });
+
+    // the maximum number of objects, 10 of them per line
+    let mut ppu = build_ppu(0x93);
+    for index in 0..OBJ_COUNT as u16 {
+        let base = 0xfe00 + index * 4;
+        ppu.write(base, 16 + (index / 10) as u8 * 36);
+        ppu.write(base + 1, 8 + (index % 10) as u8 * 16);
+        ppu.write(base + 2, index as u8);
+        ppu.write(base + 3, if index % 2 == 0 { 0x00 } else { 0x30 });
+    }
+    group.bench_function("render_frame_objects", |b| {
+        b.iter(|| {
+            render_frame(black_box(&mut ppu), false);
+        })
+    });
+
+    group.finish();
+}
+
+fn benchmark_ppu_line(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ppu_line");
+    group.throughput(Throughput::Elements(1));
+
+    // a single scanline (without the VBlank ones) of the
+    // background and window, in both DMG and CGB modes
+    let mut ppu = build_ppu(0xf1);
+    group.bench_function("render_line", |b| {
+        b.iter_custom(|iters| render_lines(black_box(&mut ppu), iters))
+    });
+
+    let mut ppu = build_ppu_cgb(0xf1);
+    group.bench_function("render_line_cgb", |b| {
+        b.iter_custom(|iters| render_lines(black_box(&mut ppu), iters))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_ppu, benchmark_ppu_line);
+criterion_main!(benches);
@@ -0,0 +1,112 @@
+use boytacean::{
+    gb::GameBoy,
+    state::{BoscCodec, FromGbOptions, SaveStateFormat, StateManager},
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+fn build_game_boy() -> GameBoy {
+    let mut game_boy = GameBoy::default();
+    game_boy.load(true).unwrap();
+    game_boy
+        .load_rom_file("res/roms/demo/pocket.gb", None)
+        .unwrap();
+    for _ in 0..60 {
+        game_boy.next_frame();
+    }
+    game_boy
+}
+
+fn benchmark_state(c: &mut Criterion) {
+    let mut game_boy = build_game_boy();
+    let bosc = StateManager::save(&mut game_boy, Some(SaveStateFormat::Bosc), None).unwrap();
+    let bess = StateManager::save(&mut game_boy, Some(SaveStateFormat::Bess), None).unwrap();
+
+    let mut group = c.benchmark_group("state");
+
+    // the BOSC format is the zippy encoded BOS state
+    group.bench_function("save_bosc", |b| {
+        b.iter(|| {
+            let data =
+                StateManager::save(black_box(&mut game_boy), Some(SaveStateFormat::Bosc), None)
+                    .unwrap();
+            black_box(data);
+        })
+    });
+
+    group.bench_function("load_bosc", |b| {
+        b.iter(|| {
+            StateManager::load(
+                black_box(&bosc),
+                &mut game_boy,
+                Some(SaveStateFormat::Bosc),
+                None,
+            )
+            .unwrap();
+        })
+    });
+
+    // the BOSC format using the (standard) deflate codec
+    group.bench_function("save_bosc_deflate", |b| {
+        b.iter(|| {
+            let data = StateManager::save(
+                black_box(&mut game_boy),
+                Some(SaveStateFormat::Bosc),
+                Some(FromGbOptions::default().with_codec(BoscCodec::Deflate)),
+            )
+            .unwrap();
+            black_box(data);
+        })
+    });
+
+    // the BOSC format using the zstd codec (requires the feature)
+    #[cfg(feature = "zstd")]
+    group.bench_function("save_bosc_zstd", |b| {
+        b.iter(|| {
+            let data = StateManager::save(
+                black_box(&mut game_boy),
+                Some(SaveStateFormat::Bosc),
+                Some(FromGbOptions::default().with_codec(BoscCodec::Zstd)),
+            )
+            .unwrap();
+            black_box(data);
+        })
+    });
+
+    group.bench_function("save_bess", |b| {
+        b.iter(|| {
+            let data =
+                StateManager::save(black_box(&mut game_boy), Some(SaveStateFormat::Bess), None)
+                    .unwrap();
+            black_box(data);
+        })
+    });
+
+    group.bench_function("load_bess", |b| {
+        b.iter(|| {
+            StateManager::load(
+                black_box(&bess),
+                &mut game_boy,
+                Some(SaveStateFormat::Bess),
+                None,
+            )
+            .unwrap();
+        })
+    });
+
+    // the complete round trip (save followed by load) in
+    // both formats, as used by rewind and netplay rollbacks
+    for format in [SaveStateFormat::Bosc, SaveStateFormat::Bess] {
+        let name = format!("round_trip_{}", format.description().to_lowercase());
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let data = StateManager::save(&mut game_boy, Some(format), None).unwrap();
+                StateManager::load(black_box(&data), &mut game_boy, Some(format), None).unwrap();
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_state);
+criterion_main!(benches);
@@ -1,7 +1,7 @@
 //! Build script (https://doc.rust-lang.org/cargo/reference/build-scripts.html)
 //! This script is executed as the first step in the compilation process.
-//! Here we export metadata constants to a `constants/generated.rs` file which is then
-//! imported and used by the remaining crate.
+//! Here we export metadata constants to the `src/gen/build.rs` and `src/gen/_build.rs`
+//! files, which are then exposed by the `gen` module and the typed `info::BuildInfo`.
 //!
 //! # Examples
 //!
@@ -153,6 +153,9 @@ fn main() {
     if cfg!(feature = "cpulog") {
         features.push("cpulog")
     }
+    if cfg!(feature = "cosim") {
+        features.push("cosim")
+    }
 
     write_vec_constant(&mut file, "FEATURES_SEQ", features);
 
@@ -189,13 +192,13 @@ where
         std::any::type_name::<T>(),
         val
     )
-    .unwrap_or_else(|_| panic!("Failed to write '{key}' to 'build_constants.rs'"));
+    .unwrap_or_else(|_| panic!("Failed to write '{key}' to '{BUILD_OUT_FILE}'"));
 }
 
 #[cfg(not(feature = "gen-mock"))]
 fn write_str_constant(file: &mut File, key: &str, val: &str) {
     writeln!(file, "pub const {key}: &str = \"{val}\";")
-        .unwrap_or_else(|_| panic!("Failed to write '{key}' to 'build_constants.rs'"));
+        .unwrap_or_else(|_| panic!("Failed to write '{key}' to '{BUILD_OUT_FILE}'"));
 }
 
 #[cfg(not(feature = "gen-mock"))]
@@ -221,5 +224,5 @@ where
         vec.len(),
         list_str
     )
-    .unwrap_or_else(|_| panic!("Failed to write '{key}' to 'build_constants.rs'"));
+    .unwrap_or_else(|_| panic!("Failed to write '{key}' to '{BUILD_OUT_FILE}'"));
 }
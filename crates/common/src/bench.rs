@@ -2,6 +2,14 @@
 //!
 //! Most of the these function are used to generate data for benchmarking
 //! and used in Criterion benchmarks.
+//!
+//! The generated data depends only on the provided arguments (no
+//! system entropy is used), so that the inputs of the benchmarks are
+//! the same between runs and the results can be compared across commits.
+
+/// The seed used by default for the pseudo random data of the benchmarks,
+/// changing it invalidates any previously saved baseline.
+pub const BENCH_SEED: u32 = 0x2f6b_1d3c;
 
 pub fn generate_data(size: usize) -> Vec<u8> {
     let patterns: [&[u8]; 6] = [
@@ -32,6 +40,22 @@ pub fn generate_data(size: usize) -> Vec<u8> {
     data
 }
 
+/// Generates pseudo random (xorshift) data from the provided seed, hard
+/// to compress data that mimics the least favorable inputs (eg: noisy
+/// frames or the contents of the RAM).
+pub fn generate_noise(size: usize, seed: u32) -> Vec<u8> {
+    let mut state = if seed == 0 { BENCH_SEED } else { seed };
+    let mut data = Vec::with_capacity(size);
+    while data.len() < size {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        data.extend_from_slice(&state.to_le_bytes());
+    }
+    data.truncate(size);
+    data
+}
+
 pub fn multiply_array_size<T: Clone>(arr: &[T], multiplier: usize) -> Vec<T> {
     let mut new_arr = Vec::with_capacity(arr.len() * multiplier);
     for _ in 0..multiplier {
@@ -39,3 +63,26 @@ pub fn multiply_array_size<T: Clone>(arr: &[T], multiplier: usize) -> Vec<T> {
     }
     new_arr
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_data, generate_noise, BENCH_SEED};
+
+    #[test]
+    fn test_generate_data() {
+        let data = generate_data(32);
+        assert_eq!(data.len(), 32);
+        assert_eq!(&data[..5], b"aaaaa");
+        assert_eq!(&data[15..24], b"bbbbbbbbb");
+    }
+
+    #[test]
+    fn test_generate_noise() {
+        let data = generate_noise(1023, BENCH_SEED);
+        assert_eq!(data.len(), 1023);
+        assert_eq!(data, generate_noise(1023, BENCH_SEED));
+        assert_eq!(data, generate_noise(1023, 0));
+        assert_ne!(data, generate_noise(1023, BENCH_SEED + 1));
+        assert_eq!(&data[..4], &generate_noise(4, BENCH_SEED)[..]);
+    }
+}
@@ -159,6 +159,15 @@ pub fn interleave_arrays(a: &[u8], b: &[u8], output: &mut [u8]) {
     }
 }
 
+/// Mixes the provided value using the SplitMix64 finalizer, useful
+/// to derive well distributed values (eg: RNG states) from a seed.
+pub fn splitmix64(value: u64) -> u64 {
+    let mut state = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    state = (state ^ (state >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    state = (state ^ (state >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    state ^ (state >> 31)
+}
+
 #[cfg(not(feature = "wasm"))]
 pub fn timestamp() -> u64 {
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -0,0 +1,60 @@
+//! Deflate codec, using the zlib container (RFC 1950) so that the
+//! encoded data can be decoded by standard tools and libraries
+//! (eg: `zlib.decompress()` in Python).
+
+use boytacean_common::error::Error;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use std::io::{Read, Write};
+
+use crate::codec::Codec;
+
+pub struct Deflate;
+
+impl Codec for Deflate {
+    type EncodeOptions = ();
+    type DecodeOptions = ();
+
+    fn encode(data: &[u8], _options: &Self::EncodeOptions) -> Result<Vec<u8>, Error> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        Ok(encoder.finish()?)
+    }
+
+    fn decode(data: &[u8], _options: &Self::DecodeOptions) -> Result<Vec<u8>, Error> {
+        let mut decoded = Vec::new();
+        ZlibDecoder::new(data)
+            .read_to_end(&mut decoded)
+            .map_err(|_| Error::InvalidData)?;
+        Ok(decoded)
+    }
+}
+
+pub fn encode_deflate(data: &[u8]) -> Result<Vec<u8>, Error> {
+    Deflate::encode(data, &())
+}
+
+pub fn decode_deflate(data: &[u8]) -> Result<Vec<u8>, Error> {
+    Deflate::decode(data, &())
+}
+
+#[cfg(test)]
+mod tests {
+    use boytacean_common::error::Error;
+
+    use super::{decode_deflate, encode_deflate};
+
+    #[test]
+    fn test_deflate() {
+        let data: Vec<u8> = (0..4096_u32).map(|value| (value / 16) as u8).collect();
+        let encoded = encode_deflate(&data).unwrap();
+        assert!(encoded.len() < data.len() / 4);
+        assert_eq!(&encoded[..2], &[0x78, 0x9c]);
+        assert_eq!(decode_deflate(&encoded).unwrap(), data);
+        assert_eq!(decode_deflate(&encode_deflate(&[]).unwrap()).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_deflate_invalid() {
+        assert_eq!(decode_deflate(b"invalid").unwrap_err(), Error::InvalidData);
+    }
+}
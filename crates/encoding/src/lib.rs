@@ -1,6 +1,9 @@
 pub mod cipher;
 pub mod codec;
+pub mod deflate;
 pub mod huffman;
 pub mod rc4;
 pub mod rle;
 pub mod zippy;
+#[cfg(feature = "zstd")]
+pub mod zstd;
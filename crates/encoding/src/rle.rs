@@ -33,6 +33,7 @@ impl Codec for Rle {
         Ok(encoded)
     }
 
+    #[allow(clippy::manual_repeat_n)]
     fn decode(data: &[u8], _options: &Self::DecodeOptions) -> Result<Vec<u8>, Error> {
         let mut decoded = Vec::new();
 
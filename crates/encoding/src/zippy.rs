@@ -2,7 +2,7 @@ use boytacean_common::{
     data::{read_bytes, read_string, read_u32, write_bytes, write_string, write_u32},
     error::Error,
 };
-use boytacean_hashing::crc32c::crc32c;
+use boytacean_hashing::crc32c::{crc32c, Crc32C};
 use std::{
     collections::HashSet,
     convert::TryInto,
@@ -16,7 +16,7 @@ use std::{
 use crate::{
     codec::Codec,
     huffman::{decode_huffman, encode_huffman},
-    rc4::{decrypt_rc4, encrypt_rc4},
+    rc4::{decrypt_rc4, encrypt_rc4, Rc4},
     rle::{decode_rle, encode_rle},
 };
 
@@ -30,6 +30,16 @@ pub const ZIPPY_CIPHER_TEST: &[u8; 22] = b"ZIPPY_CIPHER_SIGNATURE";
 pub enum ZippyFeatures {
     Crc32,
     EncryptedRc4,
+
+    /// The payload is split into independently encoded chunks (see
+    /// [`ZippyEncoder`]), with the CRC-32 stored after the last one.
+    Chunked,
+
+    /// The payload is XORed with a preset dictionary before being
+    /// encoded, a reference payload with the same layout (eg: a
+    /// previous BESS state of the same game) turns most of the bytes
+    /// into runs of zeros.
+    Dictionary,
     Other,
 }
 
@@ -38,6 +48,8 @@ impl From<ZippyFeatures> for &str {
         match value {
             ZippyFeatures::Crc32 => "crc32",
             ZippyFeatures::EncryptedRc4 => "encrypted_rc4",
+            ZippyFeatures::Chunked => "chunked",
+            ZippyFeatures::Dictionary => "dictionary",
             ZippyFeatures::Other => "other",
         }
     }
@@ -48,6 +60,8 @@ impl From<&ZippyFeatures> for &str {
         match value {
             ZippyFeatures::Crc32 => "crc32",
             ZippyFeatures::EncryptedRc4 => "encrypted_rc4",
+            ZippyFeatures::Chunked => "chunked",
+            ZippyFeatures::Dictionary => "dictionary",
             ZippyFeatures::Other => "other",
         }
     }
@@ -58,6 +72,8 @@ impl From<u32> for ZippyFeatures {
         match value {
             0 => Self::Crc32,
             1 => Self::EncryptedRc4,
+            2 => Self::Chunked,
+            3 => Self::Dictionary,
             _ => Self::Other,
         }
     }
@@ -68,6 +84,8 @@ impl From<&str> for ZippyFeatures {
         match value {
             "crc32" => Self::Crc32,
             "encrypted_rc4" => Self::EncryptedRc4,
+            "chunked" => Self::Chunked,
+            "dictionary" => Self::Dictionary,
             _ => Self::Other,
         }
     }
@@ -87,11 +105,23 @@ pub struct Zippy {
 pub struct ZippyOptions {
     crc32: bool,
     key: Option<String>,
+    dictionary: Option<Vec<u8>>,
 }
 
 impl ZippyOptions {
     pub fn new(crc32: bool, key: Option<String>) -> Self {
-        Self { crc32, key }
+        Self {
+            crc32,
+            key,
+            dictionary: None,
+        }
+    }
+
+    /// Sets the preset dictionary used by the [`ZippyFeatures::Dictionary`]
+    /// feature, the same dictionary must be provided when decoding.
+    pub fn with_dictionary(mut self, dictionary: Vec<u8>) -> Self {
+        self.dictionary = Some(dictionary);
+        self
     }
 }
 
@@ -100,6 +130,7 @@ impl default::Default for ZippyOptions {
         Self {
             crc32: true,
             key: None,
+            dictionary: None,
         }
     }
 }
@@ -138,56 +169,48 @@ impl Zippy {
 
     pub fn encode_data(&self) -> Result<Vec<u8>, Error> {
         let mut buffer = Cursor::new(vec![]);
-        let mut encoded = encode_huffman(&encode_rle(&self.data)?)?;
+        let encoded = self.encode_payload(&self.data, 0, &mut self.cipher()?)?;
 
-        if self.has_feature(ZippyFeatures::EncryptedRc4) {
-            encrypt_rc4(&mut encoded, self.key()?)?;
-        }
-
-        write_u32(&mut buffer, ZIPPY_MAGIC_UINT)?;
-
-        Self::write_string(&mut buffer, &self.name)?;
-        Self::write_string(&mut buffer, &self.description)?;
-
-        self.write_features(&mut buffer)?;
+        self.write_header(&mut buffer)?;
 
         Self::write_buffer(&mut buffer, &encoded)?;
 
+        // the chunked layout is ended by an empty chunk followed
+        // by the CRC-32 of the complete data
+        if self.has_feature(ZippyFeatures::Chunked) {
+            write_u32(&mut buffer, 0)?;
+            if self.has_feature(ZippyFeatures::Crc32) {
+                write_u32(&mut buffer, self.crc32)?;
+            }
+        }
+
         Ok(buffer.into_inner())
     }
 
     pub fn decode_data(data: &[u8], options: Option<ZippyOptions>) -> Result<Zippy, Error> {
-        let options = options.unwrap_or_default();
-
         let mut data = Cursor::new(data);
-
-        let magic = read_u32(&mut data)?;
-        if magic != ZIPPY_MAGIC_UINT {
-            return Err(Error::InvalidData);
-        }
-
-        let name = Self::read_string(&mut data)?;
-        let description = Self::read_string(&mut data)?;
-
-        let mut instance = Self {
-            name,
-            description,
-            features: HashSet::new(),
-            options,
-            crc32: 0xffffffff,
-            data: vec![],
-        };
-
-        instance.read_features(&mut data)?;
-
-        let mut buffer = Self::read_buffer(&mut data)?;
-        if instance.has_feature(ZippyFeatures::EncryptedRc4) {
-            decrypt_rc4(&mut buffer, instance.key()?)?;
+        let mut instance = Self::read_header(&mut data, options)?;
+        let mut cipher = instance.cipher()?;
+
+        if instance.has_feature(ZippyFeatures::Chunked) {
+            let mut decoded = vec![];
+            loop {
+                let buffer = Self::read_buffer(&mut data)?;
+                if buffer.is_empty() {
+                    break;
+                }
+                let chunk = instance.decode_payload(buffer, decoded.len(), &mut cipher)?;
+                decoded.extend_from_slice(&chunk);
+            }
+            if instance.has_feature(ZippyFeatures::Crc32) {
+                instance.crc32 = read_u32(&mut data)?;
+            }
+            instance.data = decoded;
+        } else {
+            let buffer = Self::read_buffer(&mut data)?;
+            instance.data = instance.decode_payload(buffer, 0, &mut cipher)?;
         }
 
-        let decoded = decode_rle(&decode_huffman(&buffer)?)?;
-        instance.data = decoded;
-
         Ok(instance)
     }
 
@@ -217,6 +240,89 @@ impl Zippy {
         self.features.contains(&feature)
     }
 
+    fn write_header<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        write_u32(writer, ZIPPY_MAGIC_UINT)?;
+
+        Self::write_string(writer, &self.name)?;
+        Self::write_string(writer, &self.description)?;
+
+        self.write_features(writer)?;
+
+        Ok(())
+    }
+
+    fn read_header<R: Read>(reader: &mut R, options: Option<ZippyOptions>) -> Result<Self, Error> {
+        let options = options.unwrap_or_default();
+
+        let magic = read_u32(reader)?;
+        if magic != ZIPPY_MAGIC_UINT {
+            return Err(Error::InvalidData);
+        }
+
+        let name = Self::read_string(reader)?;
+        let description = Self::read_string(reader)?;
+
+        let mut instance = Self {
+            name,
+            description,
+            features: HashSet::new(),
+            options,
+            crc32: 0xffffffff,
+            data: vec![],
+        };
+
+        instance.read_features(reader)?;
+
+        Ok(instance)
+    }
+
+    /// Encodes a (chunk of the) payload starting at the provided offset
+    /// of the data, the offset is used to align the dictionary.
+    fn encode_payload(
+        &self,
+        data: &[u8],
+        offset: usize,
+        cipher: &mut Option<Rc4>,
+    ) -> Result<Vec<u8>, Error> {
+        let mut encoded = if self.has_feature(ZippyFeatures::Dictionary) {
+            let mut data = data.to_vec();
+            apply_dictionary(&mut data, self.dictionary()?, offset);
+            encode_huffman(&encode_rle(&data)?)?
+        } else {
+            encode_huffman(&encode_rle(data)?)?
+        };
+        if let Some(cipher) = cipher {
+            cipher.process(&mut encoded);
+        }
+        Ok(encoded)
+    }
+
+    fn decode_payload(
+        &self,
+        mut buffer: Vec<u8>,
+        offset: usize,
+        cipher: &mut Option<Rc4>,
+    ) -> Result<Vec<u8>, Error> {
+        if let Some(cipher) = cipher {
+            cipher.process(&mut buffer);
+        }
+        let mut decoded = decode_rle(&decode_huffman(&buffer)?)?;
+        if self.has_feature(ZippyFeatures::Dictionary) {
+            apply_dictionary(&mut decoded, self.dictionary()?, offset);
+        }
+        Ok(decoded)
+    }
+
+    /// Builds the cipher of the payload, a single key stream is used
+    /// for all of the chunks (as if they were a single buffer).
+    fn cipher(&self) -> Result<Option<Rc4>, Error> {
+        if self.has_feature(ZippyFeatures::EncryptedRc4) {
+            Ok(Some(Rc4::new(self.key()?)))
+        } else {
+            Ok(None)
+        }
+    }
+
     #[inline(always)]
     fn read_string<R: Read>(reader: &mut R) -> Result<String, Error> {
         let count = read_u32(reader)?;
@@ -238,6 +344,7 @@ impl Zippy {
             match feature {
                 ZippyFeatures::Crc32 => self.read_crc32_feature(reader)?,
                 ZippyFeatures::EncryptedRc4 => self.read_rc4_feature(reader)?,
+                ZippyFeatures::Dictionary => self.read_dictionary_feature(reader)?,
                 _ => self.read_empty_feature(reader)?,
             };
             self.features.insert(feature);
@@ -248,6 +355,12 @@ impl Zippy {
     #[inline(always)]
     fn read_crc32_feature<R: Read>(&mut self, reader: &mut R) -> Result<(), Error> {
         let payload = Self::read_buffer(reader)?;
+
+        // an empty payload means that the CRC-32 is stored after
+        // the chunks, as it's not known when the header is written
+        if payload.is_empty() {
+            return Ok(());
+        }
         if payload.len() != size_of::<u32>() {
             return Err(Error::InvalidData);
         }
@@ -266,6 +379,21 @@ impl Zippy {
         Ok(())
     }
 
+    #[inline(always)]
+    fn read_dictionary_feature<R: Read>(&mut self, reader: &mut R) -> Result<(), Error> {
+        let payload = Self::read_buffer(reader)?;
+        if payload.len() != size_of::<u32>() {
+            return Err(Error::InvalidData);
+        }
+        let payload: [u8; 4] = payload.try_into().unwrap();
+        if u32::from_le_bytes(payload) != crc32c(self.dictionary()?) {
+            return Err(Error::CustomError(String::from(
+                "Dictionary does not match the one used for encoding",
+            )));
+        }
+        Ok(())
+    }
+
     #[inline(always)]
     fn read_empty_feature<R: Read>(&mut self, reader: &mut R) -> Result<(), Error> {
         Self::read_buffer(reader)?;
@@ -293,6 +421,7 @@ impl Zippy {
             match feature {
                 ZippyFeatures::Crc32 => self.write_crc32_feature(writer)?,
                 ZippyFeatures::EncryptedRc4 => self.write_rc4_feature(writer)?,
+                ZippyFeatures::Dictionary => self.write_dictionary_feature(writer)?,
                 _ => self.write_empty_feature(writer, feature.into())?,
             }
         }
@@ -302,6 +431,10 @@ impl Zippy {
     #[inline(always)]
     fn write_crc32_feature<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
         Self::write_string(writer, ZippyFeatures::Crc32.into())?;
+        if self.has_feature(ZippyFeatures::Chunked) {
+            write_u32(writer, 0)?;
+            return Ok(());
+        }
         write_u32(writer, size_of::<u32>() as u32)?;
         write_u32(writer, self.crc32)?;
         Ok(())
@@ -316,6 +449,14 @@ impl Zippy {
         Ok(())
     }
 
+    #[inline(always)]
+    fn write_dictionary_feature<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        Self::write_string(writer, ZippyFeatures::Dictionary.into())?;
+        write_u32(writer, size_of::<u32>() as u32)?;
+        write_u32(writer, crc32c(self.dictionary()?))?;
+        Ok(())
+    }
+
     #[inline(always)]
     fn write_empty_feature<W: Write>(&self, writer: &mut W, name: &str) -> Result<(), Error> {
         Self::write_string(writer, name)?;
@@ -331,6 +472,14 @@ impl Zippy {
             .ok_or(Error::MissingOption(String::from("key")))?
             .as_bytes())
     }
+
+    fn dictionary(&self) -> Result<&[u8], Error> {
+        Ok(self
+            .options
+            .dictionary
+            .as_ref()
+            .ok_or(Error::MissingOption(String::from("dictionary")))?)
+    }
 }
 
 impl Codec for Zippy {
@@ -375,11 +524,207 @@ pub fn decode_zippy(data: &[u8], options: Option<ZippyOptions>) -> Result<Vec<u8
     Zippy::decode(data, &ZippyDecodeOptions { options })
 }
 
+/// Streaming encoder of the Zippy format, writing the data as a
+/// sequence of chunks (see [`ZippyFeatures::Chunked`]) so that
+/// large payloads are compressed incrementally, without the need
+/// to hold the complete data (or its encoding) in memory.
+///
+/// The output can be decoded using either [`ZippyDecoder`] or
+/// [`decode_zippy`].
+pub struct ZippyEncoder<W: Write> {
+    writer: W,
+    header: Zippy,
+    cipher: Option<Rc4>,
+    crc32: Crc32C,
+    size: usize,
+}
+
+impl<W: Write> ZippyEncoder<W> {
+    /// Creates a new encoder writing the header into the provided
+    /// writer, the [`ZippyFeatures::Chunked`] feature is always set.
+    pub fn new(
+        mut writer: W,
+        name: String,
+        description: String,
+        features: Option<Vec<ZippyFeatures>>,
+        options: Option<ZippyOptions>,
+    ) -> Result<Self, Error> {
+        let mut features = features.unwrap_or(vec![ZippyFeatures::Crc32]);
+        if !features.contains(&ZippyFeatures::Chunked) {
+            features.push(ZippyFeatures::Chunked);
+        }
+        let header = Zippy::build(&[], name, description, Some(features), options)?;
+        header.write_header(&mut writer)?;
+        let cipher = header.cipher()?;
+        Ok(Self {
+            writer,
+            header,
+            cipher,
+            crc32: Crc32C::new(),
+            size: 0,
+        })
+    }
+
+    /// Encodes and writes the provided data as a new chunk, empty
+    /// chunks are ignored as they mark the end of the stream.
+    pub fn write_chunk(&mut self, data: &[u8]) -> Result<(), Error> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        if self.header.options.crc32 {
+            self.crc32.update(data);
+        }
+        let encoded = self
+            .header
+            .encode_payload(data, self.size, &mut self.cipher)?;
+        Zippy::write_buffer(&mut self.writer, &encoded)?;
+        self.size += data.len();
+        Ok(())
+    }
+
+    /// Ends the stream, writing the final (empty) chunk and the
+    /// CRC-32 of the data, returning the underlying writer.
+    pub fn finish(mut self) -> Result<W, Error> {
+        write_u32(&mut self.writer, 0)?;
+        if self.header.has_feature(ZippyFeatures::Crc32) {
+            let crc32 = if self.header.options.crc32 {
+                self.crc32.finalize()
+            } else {
+                0xffffffff
+            };
+            write_u32(&mut self.writer, crc32)?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    /// The number of (uncompressed) bytes written so far.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+/// Streaming decoder of the Zippy format, reading the data one
+/// chunk at a time, the (non chunked) output of [`encode_zippy`]
+/// is read as a single chunk.
+pub struct ZippyDecoder<R: Read> {
+    reader: R,
+    header: Zippy,
+    cipher: Option<Rc4>,
+    crc32: Crc32C,
+    size: usize,
+    finished: bool,
+
+    /// The CRC-32 of the data read, computed once the end of the
+    /// stream has been reached.
+    computed_crc32: Option<u32>,
+}
+
+impl<R: Read> ZippyDecoder<R> {
+    /// Creates a new decoder reading the header from the provided
+    /// reader, validating the key and dictionary if required.
+    pub fn new(mut reader: R, options: Option<ZippyOptions>) -> Result<Self, Error> {
+        let header = Zippy::read_header(&mut reader, options)?;
+        let cipher = header.cipher()?;
+        Ok(Self {
+            reader,
+            header,
+            cipher,
+            crc32: Crc32C::new(),
+            size: 0,
+            finished: false,
+            computed_crc32: None,
+        })
+    }
+
+    /// Reads and decodes the next chunk of the data, returning `None`
+    /// once the end of the stream has been reached.
+    pub fn read_chunk(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        let buffer = Zippy::read_buffer(&mut self.reader)?;
+        let chunked = self.header.has_feature(ZippyFeatures::Chunked);
+        if chunked && buffer.is_empty() {
+            if self.header.has_feature(ZippyFeatures::Crc32) {
+                self.header.crc32 = read_u32(&mut self.reader)?;
+            }
+            self.finish();
+            return Ok(None);
+        }
+
+        let chunk = self
+            .header
+            .decode_payload(buffer, self.size, &mut self.cipher)?;
+        self.crc32.update(&chunk);
+        self.size += chunk.len();
+        if !chunked {
+            self.finish();
+        }
+        Ok(Some(chunk))
+    }
+
+    /// Reads the remaining chunks into a single buffer.
+    pub fn read_all(&mut self) -> Result<Vec<u8>, Error> {
+        let mut data = vec![];
+        while let Some(chunk) = self.read_chunk()? {
+            data.extend_from_slice(&chunk);
+        }
+        Ok(data)
+    }
+
+    /// Verifies the CRC-32 of the data read, only valid once the
+    /// stream has been completely read.
+    pub fn check_crc32(&self) -> bool {
+        self.computed_crc32 == Some(self.header.crc32)
+    }
+
+    /// The CRC-32 of the data as stored in the stream.
+    pub fn crc32(&self) -> u32 {
+        self.header.crc32
+    }
+
+    pub fn name(&self) -> &str {
+        &self.header.name
+    }
+
+    pub fn description(&self) -> &str {
+        &self.header.description
+    }
+
+    pub fn has_feature(&self, feature: ZippyFeatures) -> bool {
+        self.header.has_feature(feature)
+    }
+
+    /// The number of (uncompressed) bytes read so far.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    fn finish(&mut self) {
+        self.finished = true;
+        self.computed_crc32 = Some(std::mem::take(&mut self.crc32).finalize());
+    }
+}
+
+/// XORs the data with the dictionary, with the data starting at the
+/// provided offset of the dictionary, the bytes beyond the end of the
+/// dictionary are left untouched.
+fn apply_dictionary(data: &mut [u8], dictionary: &[u8], offset: usize) {
+    let dictionary = dictionary.get(offset..).unwrap_or(&[]);
+    for (byte, value) in data.iter_mut().zip(dictionary.iter()) {
+        *byte ^= value;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use boytacean_common::error::Error;
 
-    use super::{decode_zippy, encode_zippy, Zippy, ZippyFeatures, ZippyOptions};
+    use super::{
+        decode_zippy, encode_zippy, Zippy, ZippyDecoder, ZippyEncoder, ZippyFeatures, ZippyOptions,
+    };
 
     #[test]
     fn test_zippy_build_and_encode() {
@@ -523,4 +868,105 @@ mod tests {
             Error::MissingOption(String::from("key"))
         );
     }
+
+    #[test]
+    fn test_zippy_streaming() {
+        let data: Vec<u8> = (0..10000_u32).map(|value| (value / 7) as u8).collect();
+
+        let mut encoder =
+            ZippyEncoder::new(vec![], String::from("Test"), String::new(), None, None).unwrap();
+        for chunk in data.chunks(3000) {
+            encoder.write_chunk(chunk).unwrap();
+        }
+        assert_eq!(encoder.size(), data.len());
+        let encoded = encoder.finish().unwrap();
+
+        let mut decoder = ZippyDecoder::new(&encoded[..], None).unwrap();
+        assert_eq!(decoder.name(), "Test");
+        assert!(decoder.has_feature(ZippyFeatures::Chunked));
+        assert_eq!(decoder.read_chunk().unwrap().unwrap(), &data[..3000]);
+        assert!(!decoder.check_crc32());
+        assert_eq!(decoder.read_all().unwrap(), &data[3000..]);
+        assert!(decoder.read_chunk().unwrap().is_none());
+        assert!(decoder.check_crc32());
+        assert_eq!(decoder.size(), data.len());
+
+        let zippy = Zippy::decode_data(&encoded, None).unwrap();
+        assert_eq!(zippy.data(), data);
+        assert!(zippy.check_crc32());
+    }
+
+    #[test]
+    fn test_zippy_streaming_encrypted() {
+        let options = ZippyOptions::new(true, Some(String::from("key")));
+        let mut encoder = ZippyEncoder::new(
+            vec![],
+            String::new(),
+            String::new(),
+            Some(vec![ZippyFeatures::Crc32, ZippyFeatures::EncryptedRc4]),
+            Some(options.clone()),
+        )
+        .unwrap();
+        encoder.write_chunk(b"hello ").unwrap();
+        encoder.write_chunk(b"").unwrap();
+        encoder.write_chunk(b"world").unwrap();
+        let encoded = encoder.finish().unwrap();
+
+        assert_eq!(
+            decode_zippy(&encoded, Some(options)).unwrap(),
+            b"hello world"
+        );
+        assert_eq!(
+            decode_zippy(&encoded, None).unwrap_err(),
+            Error::MissingOption(String::from("key"))
+        );
+    }
+
+    #[test]
+    fn test_zippy_decoder_single() {
+        let encoded = encode_zippy(b"test", None, None).unwrap();
+        let mut decoder = ZippyDecoder::new(&encoded[..], None).unwrap();
+        assert!(!decoder.has_feature(ZippyFeatures::Chunked));
+        assert_eq!(decoder.read_chunk().unwrap().unwrap(), b"test");
+        assert!(decoder.read_chunk().unwrap().is_none());
+        assert!(decoder.check_crc32());
+    }
+
+    #[test]
+    fn test_zippy_dictionary() {
+        let dictionary: Vec<u8> = (0..4096_u32).map(|value| (value * 31) as u8).collect();
+        let mut data = dictionary.clone();
+        data[100] = 0xff;
+        data.extend_from_slice(b"tail");
+
+        let options = ZippyOptions::default().with_dictionary(dictionary.clone());
+        let features = Some(vec![ZippyFeatures::Crc32, ZippyFeatures::Dictionary]);
+        let encoded = encode_zippy(&data, features.clone(), Some(options.clone())).unwrap();
+        assert!(encoded.len() < encode_zippy(&data, None, None).unwrap().len() / 4);
+        assert_eq!(decode_zippy(&encoded, Some(options.clone())).unwrap(), data);
+
+        // the dictionary is aligned with the offset of each chunk
+        let mut encoder = ZippyEncoder::new(
+            vec![],
+            String::new(),
+            String::new(),
+            features,
+            Some(options.clone()),
+        )
+        .unwrap();
+        for chunk in data.chunks(1000) {
+            encoder.write_chunk(chunk).unwrap();
+        }
+        let encoded = encoder.finish().unwrap();
+        let mut decoder = ZippyDecoder::new(&encoded[..], Some(options)).unwrap();
+        assert_eq!(decoder.read_all().unwrap(), data);
+        assert!(decoder.check_crc32());
+
+        assert_eq!(
+            decode_zippy(&encoded, None).unwrap_err(),
+            Error::MissingOption(String::from("dictionary"))
+        );
+        let wrong = ZippyOptions::default().with_dictionary(vec![0x00; 16]);
+        assert!(decode_zippy(&encoded, Some(wrong)).is_err());
+    }
 }
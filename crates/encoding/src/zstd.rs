@@ -0,0 +1,55 @@
+//! Zstandard codec (RFC 8878), only available with the `zstd`
+//! feature as it depends on the native zstd library.
+
+use boytacean_common::error::Error;
+
+use crate::codec::Codec;
+
+/// The compression level used by default, the same as the
+/// one used by the zstd command line tool.
+pub const ZSTD_LEVEL: i32 = 3;
+
+pub struct Zstd;
+
+impl Codec for Zstd {
+    type EncodeOptions = ();
+    type DecodeOptions = ();
+
+    fn encode(data: &[u8], _options: &Self::EncodeOptions) -> Result<Vec<u8>, Error> {
+        Ok(::zstd::stream::encode_all(data, ZSTD_LEVEL)?)
+    }
+
+    fn decode(data: &[u8], _options: &Self::DecodeOptions) -> Result<Vec<u8>, Error> {
+        ::zstd::stream::decode_all(data).map_err(|_| Error::InvalidData)
+    }
+}
+
+pub fn encode_zstd(data: &[u8]) -> Result<Vec<u8>, Error> {
+    Zstd::encode(data, &())
+}
+
+pub fn decode_zstd(data: &[u8]) -> Result<Vec<u8>, Error> {
+    Zstd::decode(data, &())
+}
+
+#[cfg(test)]
+mod tests {
+    use boytacean_common::error::Error;
+
+    use super::{decode_zstd, encode_zstd};
+
+    #[test]
+    fn test_zstd() {
+        let data: Vec<u8> = (0..4096_u32).map(|value| (value / 16) as u8).collect();
+        let encoded = encode_zstd(&data).unwrap();
+        assert!(encoded.len() < data.len() / 4);
+        assert_eq!(&encoded[..4], &[0x28, 0xb5, 0x2f, 0xfd]);
+        assert_eq!(decode_zstd(&encoded).unwrap(), data);
+        assert_eq!(decode_zstd(&encode_zstd(&[]).unwrap()).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_zstd_invalid() {
+        assert_eq!(decode_zstd(b"invalid").unwrap_err(), Error::InvalidData);
+    }
+}
@@ -0,0 +1,84 @@
+//! Minimal embedding of the emulator, running a ROM frame by frame
+//! while feeding the inputs and consuming the video and audio output,
+//! with a save state round trip in the middle.
+//!
+//! Usage: `cargo run --example embed -- [rom_path] [frames]`
+
+use std::env::args;
+
+use boytacean::{
+    gb::{AudioProvider, GameBoy},
+    pad::PadKey,
+    ppu::{DISPLAY_HEIGHT, DISPLAY_WIDTH},
+    rom::Cartridge,
+    state::StateManager,
+};
+use boytacean_common::{error::Error, util::write_file};
+
+/// Provides the mask of the keys pressed for each frame, taps
+/// the Start button every second to move through the menus.
+fn input(frame: u32) -> u8 {
+    if frame % 60 < 5 {
+        PadKey::Start.mask()
+    } else {
+        0x00
+    }
+}
+
+fn main() -> Result<(), Error> {
+    let rom_path = args()
+        .nth(1)
+        .unwrap_or(String::from("res/roms/demo/pocket.gb"));
+    let frames = args()
+        .nth(2)
+        .and_then(|value| value.parse::<u32>().ok())
+        .unwrap_or(600);
+
+    // infers the mode (DMG or CGB) from the cartridge header so
+    // that the system is created with the proper hardware
+    let rom = Cartridge::from_file(&rom_path)?;
+    let mut game_boy = GameBoy::new(Some(rom.gb_mode()));
+    game_boy.load(true)?;
+    game_boy.load_cartridge(rom)?;
+    println!(
+        "Running '{}' in {} mode",
+        game_boy.rom_i().title(),
+        game_boy.mode()
+    );
+
+    let mut audio_frame = vec![];
+    let mut samples = 0;
+    let mut state = vec![];
+
+    for frame in 0..frames {
+        game_boy.set_pad_keys(input(frame));
+        game_boy.next_frame();
+
+        // the frame buffer is RGB (3 bytes per pixel) and would be
+        // uploaded to a texture by a graphical frontend
+        let frame_buffer = game_boy.frame_buffer();
+        assert_eq!(frame_buffer.len(), DISPLAY_WIDTH * DISPLAY_HEIGHT * 3);
+
+        // the audio samples of the frame would be queued into the
+        // audio device, with the buffer being re-used between frames
+        if game_boy.take_audio_frame_into(&mut audio_frame) {
+            samples += audio_frame.len();
+        }
+
+        if frame == frames / 2 {
+            state = StateManager::save(&mut game_boy, None, None)?;
+        }
+    }
+
+    // goes back in time to the middle of the run, the same state
+    // could have been stored in a file and loaded in a new session
+    StateManager::load(&state, &mut game_boy, None, None)?;
+    game_boy.next_frame();
+    write_file("embed.png", &game_boy.screenshot_png(), None)?;
+
+    println!(
+        "Ran {frames} frames ({samples} audio samples), state is {} bytes",
+        state.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,23 @@
+from time import time
+from boytacean import GameBoy, VISUAL_FREQ
+from os.path import dirname, realpath, join
+
+CURRENT_DIR = dirname(realpath(__file__))
+ROM_PATH = join(CURRENT_DIR, "../../res/roms/demo/pocket.gb")
+
+FRAME_COUNT = 6000
+
+gb = GameBoy(apu_enabled=False, serial_enabled=False)
+gb.load_rom(ROM_PATH)
+start = time()
+brightness = 0.0
+for index in range(FRAME_COUNT):
+    # alternates the A key every 30 frames, as an agent would do
+    # after observing the frame, then steps exactly one frame
+    gb.press("a") if index % 60 < 30 else gb.release("a")
+    gb.step_frame()
+    brightness += gb.frame_ndarray().mean()
+total = time() - start
+print(f"Time taken: {total:.2f} seconds")
+print(f"Speedup: {FRAME_COUNT / total / VISUAL_FREQ:.2f}x")
+print(f"Average brightness: {brightness / FRAME_COUNT:.2f}")
@@ -1,22 +1,48 @@
 pub const RETRO_API_VERSION: u32 = 1;
 pub const REGION_NTSC: u32 = 0;
 
+pub const RETRO_ENVIRONMENT_GET_CAN_DUPE: u32 = 3;
 pub const RETRO_ENVIRONMENT_SET_PIXEL_FORMAT: u32 = 10;
 pub const RETRO_ENVIRONMENT_GET_VARIABLE: u32 = 15;
+pub const RETRO_ENVIRONMENT_SET_SYSTEM_AV_INFO: u32 = 32;
 pub const RETRO_ENVIRONMENT_SET_VARIABLES: u32 = 16;
 pub const RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE: u32 = 17;
+pub const RETRO_ENVIRONMENT_SET_SUBSYSTEM_INFO: u32 = 34;
+pub const RETRO_ENVIRONMENT_SET_CONTROLLER_INFO: u32 = 35;
 pub const RETRO_ENVIRONMENT_SET_CONTENT_INFO_OVERRIDE: u32 = 65;
 pub const RETRO_ENVIRONMENT_GET_GAME_INFO_EXT: u32 = 66;
+pub const RETRO_ENVIRONMENT_GET_CORE_OPTIONS_VERSION: u32 = 52;
+pub const RETRO_ENVIRONMENT_SET_CORE_OPTIONS_V2: u32 = 67;
+pub const RETRO_ENVIRONMENT_GET_FASTFORWARDING: u32 = 49;
+
+/// The maximum number of values of a core option (including
+/// the terminating entry).
+pub const RETRO_NUM_CORE_OPTION_VALUES_MAX: usize = 128;
 
 pub const RETRO_PIXEL_FORMAT_0RGB1555: usize = 0;
 pub const RETRO_PIXEL_FORMAT_XRGB8888: usize = 1;
 pub const RETRO_PIXEL_FORMAT_RGB565: usize = 2;
 
 pub const RETRO_MEMORY_SAVE_RAM: u32 = 0;
+pub const RETRO_MEMORY_RTC: u32 = 1;
 pub const RETRO_MEMORY_SYSTEM_RAM: u32 = 0;
 
+/// The game type of the link cable subsystem, with two ROMs
+/// running in two instances connected by a link cable.
+pub const RETRO_GAME_TYPE_LINK: u32 = 0x101;
+
+/// The cartridge RAM of each of the instances of the link
+/// cable subsystem.
+pub const RETRO_MEMORY_LINK_SAVE_RAM_1: u32 = (1 << 8) | RETRO_MEMORY_SAVE_RAM;
+pub const RETRO_MEMORY_LINK_SAVE_RAM_2: u32 = (2 << 8) | RETRO_MEMORY_SAVE_RAM;
+
+pub const RETRO_DEVICE_NONE: usize = 0;
 pub const RETRO_DEVICE_JOYPAD: usize = 1;
 
+/// The number of input ports, one per joypad that can be
+/// multiplexed by the SGB (players 1 to 4).
+pub const RETRO_PORTS: usize = 4;
+
 pub const RETRO_DEVICE_ID_JOYPAD_B: isize = 0;
 pub const RETRO_DEVICE_ID_JOYPAD_Y: isize = 1;
 pub const RETRO_DEVICE_ID_JOYPAD_SELECT: isize = 2;
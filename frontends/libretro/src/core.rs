@@ -5,27 +5,45 @@ use crate::{
         RETRO_DEVICE_ID_JOYPAD_L3, RETRO_DEVICE_ID_JOYPAD_LEFT, RETRO_DEVICE_ID_JOYPAD_R,
         RETRO_DEVICE_ID_JOYPAD_R2, RETRO_DEVICE_ID_JOYPAD_R3, RETRO_DEVICE_ID_JOYPAD_RIGHT,
         RETRO_DEVICE_ID_JOYPAD_SELECT, RETRO_DEVICE_ID_JOYPAD_START, RETRO_DEVICE_ID_JOYPAD_UP,
-        RETRO_DEVICE_ID_JOYPAD_X, RETRO_DEVICE_ID_JOYPAD_Y, RETRO_DEVICE_JOYPAD,
-        RETRO_ENVIRONMENT_GET_GAME_INFO_EXT, RETRO_ENVIRONMENT_GET_VARIABLE,
-        RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE, RETRO_ENVIRONMENT_SET_CONTENT_INFO_OVERRIDE,
-        RETRO_ENVIRONMENT_SET_PIXEL_FORMAT, RETRO_ENVIRONMENT_SET_VARIABLES,
-        RETRO_PIXEL_FORMAT_XRGB8888,
+        RETRO_DEVICE_ID_JOYPAD_X, RETRO_DEVICE_ID_JOYPAD_Y, RETRO_DEVICE_JOYPAD, RETRO_DEVICE_NONE,
+        RETRO_ENVIRONMENT_GET_CAN_DUPE, RETRO_ENVIRONMENT_GET_CORE_OPTIONS_VERSION,
+        RETRO_ENVIRONMENT_GET_FASTFORWARDING, RETRO_ENVIRONMENT_GET_GAME_INFO_EXT,
+        RETRO_ENVIRONMENT_GET_VARIABLE, RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE,
+        RETRO_ENVIRONMENT_SET_CONTENT_INFO_OVERRIDE, RETRO_ENVIRONMENT_SET_CONTROLLER_INFO,
+        RETRO_ENVIRONMENT_SET_CORE_OPTIONS_V2, RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+        RETRO_ENVIRONMENT_SET_SUBSYSTEM_INFO, RETRO_ENVIRONMENT_SET_SYSTEM_AV_INFO,
+        RETRO_ENVIRONMENT_SET_VARIABLES, RETRO_GAME_TYPE_LINK, RETRO_MEMORY_LINK_SAVE_RAM_1,
+        RETRO_MEMORY_LINK_SAVE_RAM_2, RETRO_MEMORY_RTC, RETRO_MEMORY_SAVE_RAM,
+        RETRO_PIXEL_FORMAT_XRGB8888, RETRO_PORTS,
+    },
+    options::{
+        ACCURACY_KEY, AUDIO_CH1_KEY, AUDIO_CH2_KEY, AUDIO_CH3_KEY, AUDIO_CH4_KEY,
+        FAST_FORWARD_AUDIO_KEY, FAST_FORWARD_MULTIPLIER_KEY, MODEL_KEY, OPTION_CATEGORIES,
+        OPTION_DEFINITIONS, PALETTE_KEY, PARALLEL_AUDIO_KEY, SAMPLE_RATE_KEY, VARIABLES,
     },
     palettes::get_palette,
     structs::{
-        RetroGameInfo, RetroGameInfoExt, RetroSystemAvInfo, RetroSystemContentInfoOverride,
-        RetroSystemInfo, RetroVariable,
+        RetroControllerDescription, RetroControllerInfo, RetroCoreOptionV2Category,
+        RetroCoreOptionV2Definition, RetroCoreOptionsV2, RetroGameGeometry, RetroGameInfo,
+        RetroGameInfoExt, RetroSubsystemInfo, RetroSubsystemMemoryInfo, RetroSubsystemRomInfo,
+        RetroSystemAvInfo, RetroSystemContentInfoOverride, RetroSystemInfo, RetroSystemTiming,
+        RetroVariable,
     },
 };
 use boytacean::{
-    color::XRGB8888_SIZE,
+    apu::{FastForwardAudio, FastForwardAudioMode},
+    archive::ArchiveFormat,
+    cheats::manager::CheatManager,
+    color::{rgb888_to_xrgb8888_u32_array, RGB_SIZE, XRGB8888_SIZE},
     debugln,
-    gb::{AudioProvider, GameBoy},
+    devices::link::LinkCable,
+    gb::{AccuracyProfile, AudioProvider, GameBoy, GameBoyMode},
     info::Info,
     infoln,
     pad::PadKey,
     ppu::{DISPLAY_HEIGHT, DISPLAY_WIDTH, FRAME_BUFFER_SIZE},
-    rom::Cartridge,
+    rom::{Cartridge, Rtc, RTC_FOOTER_SIZE},
+    serial::NullDevice,
     state::{SaveStateFormat, StateManager},
     warnln,
 };
@@ -34,8 +52,9 @@ use std::{
     ffi::CStr,
     fmt::{self, Display, Formatter},
     os::raw::{c_char, c_uint, c_void},
-    ptr::{self, addr_of},
+    ptr::{self, addr_of, addr_of_mut},
     slice::from_raw_parts,
+    time::Instant,
 };
 
 /// Represents the information about the LibRetro extension
@@ -49,8 +68,23 @@ struct LibRetroInfo {
 }
 
 static mut EMULATOR: Option<GameBoy> = None;
-static mut KEY_STATES: Option<HashMap<RetroJoypad, bool>> = None;
+static mut KEY_STATES: Option<HashMap<(usize, RetroJoypad), bool>> = None;
+
+/// The device connected to each of the input ports, ports 2 to 4
+/// are routed to the joypads multiplexed by SGB games.
+static mut PORT_DEVICES: [u32; RETRO_PORTS] = [RETRO_DEVICE_JOYPAD as u32; RETRO_PORTS];
+static mut CHEATS: Option<CheatManager> = None;
 static mut FRAME_BUFFER: [u32; FRAME_BUFFER_SIZE] = [0x00; FRAME_BUFFER_SIZE];
+static mut AUDIO_FRAME: Vec<u8> = Vec::new();
+static mut AUDIO_BUFFER: Vec<i16> = Vec::new();
+
+/// The processor of the audio produced while the frontend is fast
+/// forwarding, together with the instant of the previous run and
+/// the (rolling average) interval between runs, used to estimate
+/// the fast-forward speed as it's not provided by the frontend.
+static mut FAST_FORWARD_AUDIO: Option<FastForwardAudio> = None;
+static mut LAST_RUN: Option<Instant> = None;
+static mut RUN_INTERVAL: f32 = 0.0;
 static mut INFO: LibRetroInfo = LibRetroInfo {
     name: "",
     version: "",
@@ -71,7 +105,29 @@ static mut GAME_INFO_EXT: RetroGameInfoExt = RetroGameInfoExt {
     persistent_data: 0,
 };
 
-static mut PENDING_CYCLES: u32 = 0_u32;
+/// The second instance (and the cable connecting it to the first
+/// one) when the link cable subsystem is loaded, together with the
+/// frame buffer with both screens side by side.
+static mut LINKED: Option<GameBoy> = None;
+static mut LINK_CABLE: Option<LinkCable> = None;
+static mut LINK_FRAME_BUFFER: [u32; FRAME_BUFFER_SIZE * 2] = [0x00; FRAME_BUFFER_SIZE * 2];
+
+/// The number of cycles each of the linked instances runs before
+/// handing over to the other one, a fraction of the duration
+/// of a serial transfer (4096 cycles) so that it's exchanged in time.
+const LINK_SLICE_CYCLES: u32 = 256;
+
+/// The RTC footer (as in `.sav` files) exposed to the frontend as
+/// the RTC memory, kept up to date with the cartridge clock.
+static mut RTC_DATA: [u8; RTC_FOOTER_SIZE] = [0x00; RTC_FOOTER_SIZE];
+
+/// If the RTC memory may have been restored by the frontend (after
+/// the game load) and must be applied to the cartridge clock.
+static mut RTC_PENDING: bool = false;
+
+/// If the frontend accepts a repeated (dupe) frame, used when no
+/// frame is produced during a run (eg: LCD off).
+static mut CAN_DUPE: bool = false;
 
 static mut ENVIRONMENT_CALLBACK: Option<extern "C" fn(u32, *const c_void) -> bool> = None;
 static mut VIDEO_REFRESH_CALLBACK: Option<extern "C" fn(*const u8, c_uint, c_uint, usize)> = None;
@@ -82,22 +138,11 @@ static mut INPUT_STATE_CALLBACK: Option<
     extern "C" fn(port: u32, device: u32, index: u32, id: u32) -> i16,
 > = None;
 static mut UPDATED: bool = false;
-static mut VARIABLE: RetroVariable = RetroVariable {
-    key: "palette\0".as_ptr() as *const c_char,
-    value: std::ptr::null(),
-};
 
-const VARIABLES: [RetroVariable; 2] = [
-    RetroVariable {
-        key: "palette\0".as_ptr() as *const c_char,
-        value: "DMG color palette; basic|hogwards|christmas|goldsilver|pacman|mariobros|pokemon\0"
-            .as_ptr() as *const c_char,
-    },
-    RetroVariable {
-        key: std::ptr::null(),
-        value: std::ptr::null(),
-    },
-];
+/// The number of frames emulated per run while the frontend is
+/// fast forwarding (core options).
+static mut FAST_FORWARD_MULTIPLIER: u32 = 1;
+
 const INFO_OVERRIDE: [RetroSystemContentInfoOverride; 2] = [
     RetroSystemContentInfoOverride {
         extensions: "gb|gbc\0".as_ptr() as *const c_char,
@@ -110,6 +155,79 @@ const INFO_OVERRIDE: [RetroSystemContentInfoOverride; 2] = [
         persistent_data: 0,
     },
 ];
+const CONTROLLER_TYPES: [RetroControllerDescription; 1] = [RetroControllerDescription {
+    desc: "Game Boy Joypad\0".as_ptr() as *const c_char,
+    id: RETRO_DEVICE_JOYPAD as c_uint,
+}];
+const CONTROLLER_INFO: [RetroControllerInfo; RETRO_PORTS + 1] = [
+    RetroControllerInfo {
+        types: CONTROLLER_TYPES.as_ptr(),
+        num_types: 1,
+    },
+    RetroControllerInfo {
+        types: CONTROLLER_TYPES.as_ptr(),
+        num_types: 1,
+    },
+    RetroControllerInfo {
+        types: CONTROLLER_TYPES.as_ptr(),
+        num_types: 1,
+    },
+    RetroControllerInfo {
+        types: CONTROLLER_TYPES.as_ptr(),
+        num_types: 1,
+    },
+    RetroControllerInfo {
+        types: std::ptr::null(),
+        num_types: 0,
+    },
+];
+
+const LINK_MEMORY: [[RetroSubsystemMemoryInfo; 1]; 2] = [
+    [RetroSubsystemMemoryInfo {
+        extension: "srm\0".as_ptr() as *const c_char,
+        type_: RETRO_MEMORY_LINK_SAVE_RAM_1,
+    }],
+    [RetroSubsystemMemoryInfo {
+        extension: "srm\0".as_ptr() as *const c_char,
+        type_: RETRO_MEMORY_LINK_SAVE_RAM_2,
+    }],
+];
+const LINK_ROMS: [RetroSubsystemRomInfo; 2] = [
+    RetroSubsystemRomInfo {
+        desc: "Player 1 ROM\0".as_ptr() as *const c_char,
+        valid_extensions: "gb|gbc|zip|7z\0".as_ptr() as *const c_char,
+        need_fullpath: 0,
+        block_extract: 1,
+        required: 1,
+        memory: LINK_MEMORY[0].as_ptr(),
+        num_memory: 1,
+    },
+    RetroSubsystemRomInfo {
+        desc: "Player 2 ROM\0".as_ptr() as *const c_char,
+        valid_extensions: "gb|gbc|zip|7z\0".as_ptr() as *const c_char,
+        need_fullpath: 0,
+        block_extract: 1,
+        required: 1,
+        memory: LINK_MEMORY[1].as_ptr(),
+        num_memory: 1,
+    },
+];
+const SUBSYSTEMS: [RetroSubsystemInfo; 2] = [
+    RetroSubsystemInfo {
+        desc: "Link Cable\0".as_ptr() as *const c_char,
+        ident: "link\0".as_ptr() as *const c_char,
+        roms: LINK_ROMS.as_ptr(),
+        num_roms: 2,
+        id: RETRO_GAME_TYPE_LINK,
+    },
+    RetroSubsystemInfo {
+        desc: std::ptr::null(),
+        ident: std::ptr::null(),
+        roms: std::ptr::null(),
+        num_roms: 0,
+        id: 0,
+    },
+];
 
 const KEYS: [RetroJoypad; 8] = [
     RetroJoypad::RetroDeviceIdJoypadUp,
@@ -180,9 +298,11 @@ pub extern "C" fn retro_api_version() -> c_uint {
 #[no_mangle]
 pub extern "C" fn retro_init() {
     debugln!("retro_init()");
+    debugln!("{}", Info::build_info());
     unsafe {
         EMULATOR = Some(GameBoy::new(None));
         KEY_STATES = Some(HashMap::new());
+        CHEATS = Some(CheatManager::default());
     }
 }
 
@@ -196,6 +316,10 @@ pub extern "C" fn retro_reset() {
     debugln!("retro_reset()");
     let emulator = unsafe { EMULATOR.as_mut().unwrap() };
     emulator.reload();
+    if let Some(linked) = unsafe { LINKED.as_mut() } {
+        linked.reload();
+    }
+    unsafe { apply_cheats() };
 }
 
 /// # Safety
@@ -212,9 +336,9 @@ pub unsafe extern "C" fn retro_get_system_info(info: *mut RetroSystemInfo) {
 
     (*info).library_name = INFO.name.as_ptr() as *const c_char;
     (*info).library_version = INFO.version.as_ptr() as *const c_char;
-    (*info).valid_extensions = "gb|gbc\0".as_ptr() as *const c_char;
+    (*info).valid_extensions = "gb|gbc|zip|7z\0".as_ptr() as *const c_char;
     (*info).need_fullpath = u8::from(false);
-    (*info).block_extract = u8::from(false);
+    (*info).block_extract = u8::from(true);
 }
 
 /// # Safety
@@ -227,13 +351,7 @@ pub unsafe extern "C" fn retro_get_system_av_info(info: *mut RetroSystemAvInfo)
     let emulator = EMULATOR.as_ref().unwrap();
     let environment_cb = ENVIRONMENT_CALLBACK.as_ref().unwrap();
 
-    (*info).geometry.base_width = DISPLAY_WIDTH as u32;
-    (*info).geometry.base_height = DISPLAY_HEIGHT as u32;
-    (*info).geometry.max_width = DISPLAY_WIDTH as u32;
-    (*info).geometry.max_height = DISPLAY_HEIGHT as u32;
-    (*info).geometry.aspect_ratio = DISPLAY_WIDTH as f32 / DISPLAY_HEIGHT as f32;
-    (*info).timing.fps = GameBoy::VISUAL_FREQ as f64;
-    (*info).timing.sample_rate = emulator.audio_sampling_rate() as f64;
+    *info = system_av_info(emulator);
 
     if !environment_cb(
         RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
@@ -251,20 +369,45 @@ pub extern "C" fn retro_set_environment(
     unsafe {
         ENVIRONMENT_CALLBACK = callback;
         let environment_cb = ENVIRONMENT_CALLBACK.as_ref().unwrap();
-        environment_cb(
-            RETRO_ENVIRONMENT_SET_VARIABLES,
-            &VARIABLES as *const _ as *const c_void,
-        );
+        set_options(environment_cb);
         environment_cb(
             RETRO_ENVIRONMENT_SET_CONTENT_INFO_OVERRIDE,
             &INFO_OVERRIDE as *const _ as *const c_void,
         );
+        environment_cb(
+            RETRO_ENVIRONMENT_SET_CONTROLLER_INFO,
+            &CONTROLLER_INFO as *const _ as *const c_void,
+        );
+        environment_cb(
+            RETRO_ENVIRONMENT_SET_SUBSYSTEM_INFO,
+            &SUBSYSTEMS as *const _ as *const c_void,
+        );
     }
 }
 
 #[no_mangle]
-pub extern "C" fn retro_set_controller_port_device() {
+pub extern "C" fn retro_set_controller_port_device(port: c_uint, device: c_uint) {
     debugln!("retro_set_controller_port_device()");
+    let port = port as usize;
+    if port >= RETRO_PORTS {
+        warnln!("Unsupported controller port {}", port + 1);
+        return;
+    }
+    unsafe { PORT_DEVICES[port] = device };
+
+    // in case the device has been disconnected the keys of the
+    // joypad are lifted, avoiding keys stuck in pressed state
+    if device == RETRO_DEVICE_NONE as u32 {
+        match unsafe { (LINKED.as_mut(), EMULATOR.as_mut()) } {
+            (Some(linked), _) if port == 1 => linked.set_player_keys(0, 0x00),
+            (_, Some(emulator)) => emulator.set_player_keys(port, 0x00),
+            _ => (),
+        }
+        if let Some(key_states) = unsafe { KEY_STATES.as_mut() } {
+            key_states.retain(|(key_port, _), _| *key_port != port);
+        }
+        infoln!("Disconnected controller from port {}", port + 1);
+    }
 }
 
 #[no_mangle]
@@ -277,12 +420,7 @@ pub extern "C" fn retro_run() {
     let input_state_cb = unsafe { INPUT_STATE_CALLBACK.as_ref().unwrap() };
     let key_states = unsafe { KEY_STATES.as_mut().unwrap() };
 
-    let mut last_frame = emulator.ppu_frame();
-
-    let mut counter_cycles = unsafe { PENDING_CYCLES };
-    let cycle_limit = (GameBoy::CPU_FREQ as f32 * emulator.multiplier() as f32
-        / GameBoy::VISUAL_FREQ)
-        .round() as u32;
+    let mut cycle_limit = (GameBoy::CPU_FREQ as f32 / GameBoy::VISUAL_FREQ).round() as u32;
 
     // determines if any of the variable has changed value
     // if that's the case all of them must be polled for
@@ -295,71 +433,107 @@ pub extern "C" fn retro_run() {
             warnln!("Failed to get variable update");
         }
         if UPDATED {
-            update_vars();
+            update_vars(true);
             UPDATED = false;
         }
     }
 
-    loop {
-        // limits the number of ticks to the typical number
-        // of cycles expected for the current logic cycle
-        if counter_cycles >= cycle_limit {
-            unsafe { PENDING_CYCLES = counter_cycles - cycle_limit };
-            break;
-        }
+    unsafe { sync_rtc(emulator) };
 
-        // runs the Game Boy clock, this operation should
-        // include the advance of both the CPU, PPU, APU
-        // and any other frequency based component of the system
-        counter_cycles += emulator.clock() as u32;
-
-        // in case a new frame is available in the emulator
-        // then the frame must be pushed into display
-        if emulator.ppu_frame() != last_frame {
-            let frame_buffer = emulator.frame_buffer_xrgb8888_u32();
-            unsafe {
-                FRAME_BUFFER.copy_from_slice(&frame_buffer);
-                video_refresh_cb(
-                    FRAME_BUFFER.as_ptr() as *const u8,
-                    DISPLAY_WIDTH as u32,
-                    DISPLAY_HEIGHT as u32,
-                    DISPLAY_WIDTH * XRGB8888_SIZE,
-                );
-            }
+    // while fast forwarding the fast-forward multiplier option sets
+    // the number of frames emulated in this run, only the last one
+    // of them is presented to the frontend
+    let multiplier = match unsafe { fast_forwarding(environment_cb) } {
+        true => unsafe { FAST_FORWARD_MULTIPLIER },
+        false => 1,
+    };
+    cycle_limit *= multiplier;
 
-            // obtains the index of the current PPU frame, this value
-            // is going to be used to detect for new frame presence
-            last_frame = emulator.ppu_frame();
-        }
+    let frame_sent = if unsafe { LINKED.is_some() } {
+        unsafe { run_linked(emulator, cycle_limit) }
+    } else {
+        // runs the Game Boy for the cycles of the logic cycle, with
+        // each new frame being copied by the frame callback to be pushed
+        // into display and the cycles run in excess being carried over
+        emulator.run_for_cycles_with(cycle_limit, |_| true).frames > 0
+    };
+
+    // the frontend expects exactly one frame per run, in case none
+    // has been produced (eg: LCD off) the previous frame is repeated,
+    // so that the frontend keeps its pacing instead of stalling
+    unsafe {
+        let (frame_buffer, width) = match LINKED.is_some() {
+            true => (LINK_FRAME_BUFFER.as_ptr(), DISPLAY_WIDTH * 2),
+            false => (FRAME_BUFFER.as_ptr(), DISPLAY_WIDTH),
+        };
+        video_refresh_cb(
+            if frame_sent || !CAN_DUPE {
+                frame_buffer as *const u8
+            } else {
+                ptr::null()
+            },
+            width as u32,
+            DISPLAY_HEIGHT as u32,
+            width * XRGB8888_SIZE,
+        );
     }
 
-    // in case there's new audio data available in the emulator
-    // we must handle it by sending it to the audio callback and
-    // clearing the audio buffer
-    if !emulator.audio_buffer().is_empty() {
-        let audio_buffer = emulator
-            .audio_buffer()
-            .iter()
-            .map(|v| *v as i16 * 256)
-            .collect::<Vec<i16>>();
+    // in case there's a new (completed) audio frame available in
+    // the emulator we must handle it by sending it to the audio
+    // callback, the streaming audio buffer is cleared as it's unused,
+    // both audio buffers are re-used between frames to avoid allocations
+    let audio_frame = unsafe { &mut AUDIO_FRAME };
+    if emulator.take_audio_frame_into(audio_frame) {
+        if let Some(fast_forward_audio) = unsafe { FAST_FORWARD_AUDIO.as_mut() } {
+            let speed = unsafe { fast_forward_speed() } * multiplier;
+            fast_forward_audio.process(audio_frame, speed);
+        }
+        let audio_buffer = unsafe { &mut AUDIO_BUFFER };
+        audio_buffer.clear();
+        audio_buffer.extend(audio_frame.iter().map(|v| *v as i16 * 256));
         sample_batch_cb(audio_buffer.as_ptr(), audio_buffer.len() / 2_usize);
-        emulator.clear_audio_buffer();
+    }
+    emulator.clear_audio_buffer();
+
+    // only the audio of the first of the linked instances is
+    // played, the audio of the second one is discarded
+    if let Some(linked) = unsafe { LINKED.as_mut() } {
+        linked.take_audio_frame_into(audio_frame);
+        linked.clear_audio_buffer();
     }
 
     input_poll_cb();
 
-    for key in KEYS {
-        let key_pad = retro_key_to_pad(key).unwrap();
-        let current = input_state_cb(0, RETRO_DEVICE_JOYPAD as u32, 0, key as u32) > 0;
-        let previous = key_states.get(&key).unwrap_or(&false);
-        if current != *previous {
-            if current {
-                emulator.key_press(key_pad);
-            } else {
-                emulator.key_lift(key_pad);
+    // the first port drives the regular joypad, while the remaining
+    // ones are only polled for the joypads requested by SGB games
+    // (or the joypad of the second linked instance), keys of inactive
+    // (or disconnected) ports are lifted
+    let mut linked = unsafe { LINKED.as_mut() };
+    let players = match linked {
+        Some(_) => 2,
+        None => emulator.players() as usize,
+    };
+    let port_devices = unsafe { PORT_DEVICES };
+    for (port, device) in port_devices.iter().enumerate() {
+        let active = port < players && *device != RETRO_DEVICE_NONE as u32;
+        let (instance, player) = match (port, linked.as_deref_mut()) {
+            (1, Some(linked)) => (linked, 0),
+            _ => (&mut *emulator, port),
+        };
+        for key in KEYS {
+            let key_pad = retro_key_to_pad(key).unwrap();
+            let current = active
+                && input_state_cb(port as u32, RETRO_DEVICE_JOYPAD as u32, 0, key as u32) > 0;
+            let previous = key_states.get(&(port, key)).unwrap_or(&false);
+            if current != *previous {
+                if current {
+                    instance.key_press_player(player, key_pad);
+                } else {
+                    instance.key_lift_player(player, key_pad);
+                }
             }
+            key_states.insert((port, key), current);
         }
-        key_states.insert(key, current);
     }
 }
 
@@ -392,63 +566,142 @@ pub unsafe extern "C" fn retro_load_game(game: *const RetroGameInfo) -> bool {
         },
     );
     let instance = EMULATOR.as_mut().unwrap();
-    let data_buffer = from_raw_parts((*game).data as *const u8, (*game).size);
-    let rom = Cartridge::from_data(data_buffer).unwrap();
-    let mode = rom.gb_mode();
-    instance.set_mode(mode);
-    instance.reset();
-    instance.load(true).unwrap();
-    instance.load_cartridge(rom).unwrap();
-    update_vars();
+    load_rom(instance, &*game);
+    instance.set_frame_callback(frame_callback);
+    loaded(instance);
     true
 }
 
+/// # Safety
+///
+/// This function should be called only within Libretro context.
 #[no_mangle]
-pub extern "C" fn retro_load_game_special(
-    _system: u32,
-    _info: *const RetroGameInfo,
-    _num_info: usize,
+pub unsafe extern "C" fn retro_load_game_special(
+    game_type: u32,
+    info: *const RetroGameInfo,
+    num_info: usize,
 ) -> bool {
     debugln!("retro_load_game_special()");
-    false
+    if game_type != RETRO_GAME_TYPE_LINK || num_info != 2 {
+        warnln!("Unsupported game type {} ({} ROMs)", game_type, num_info);
+        #[allow(unreachable_code)]
+        {
+            return false;
+        }
+    }
+    let games = from_raw_parts(info, num_info);
+    infoln!("Loading ROM files in Boytacean connected by link cable...");
+
+    // both instances are loaded and connected by the link cable,
+    // with the second one sharing the options of the first one
+    let instance = EMULATOR.as_mut().unwrap();
+    let mut linked = GameBoy::new(None);
+    load_rom(instance, &games[0]);
+    load_rom(&mut linked, &games[1]);
+    instance.set_frame_callback(link_frame_callback_left);
+    linked.set_frame_callback(link_frame_callback_right);
+    let cable = LinkCable::new();
+    cable.connect(instance, &mut linked);
+    LINKED = Some(linked);
+    LINK_CABLE = Some(cable);
+    loaded(instance);
+    true
+}
+
+/// Loads the ROM of the provided game into the instance, the ROM is
+/// extracted from ZIP and 7z files by the core as the frontend doesn't
+/// extract the archives (block extract).
+unsafe fn load_rom(instance: &mut GameBoy, game: &RetroGameInfo) {
+    let data_buffer = from_raw_parts(game.data as *const u8, game.size);
+    let rom = match ArchiveFormat::from_data(data_buffer) {
+        Some(_) => Cartridge::from_archive(data_buffer, None).unwrap(),
+        None => Cartridge::from_data(data_buffer).unwrap(),
+    };
+    let mode = model_mode().unwrap_or(rom.gb_mode());
+    instance.set_mode(mode);
+    instance.reset();
+    instance.load(true).unwrap();
+    instance.load_cartridge(rom).unwrap();
+    instance.set_lcd_off_frames(true);
+    for warning in instance.rom_warnings() {
+        warnln!("{}", warning);
+    }
+}
+
+/// Sets up the core for the game loaded in the (first) instance.
+unsafe fn loaded(instance: &mut GameBoy) {
+    let environment_cb = ENVIRONMENT_CALLBACK.as_ref().unwrap();
+    RTC_DATA.copy_from_slice(&instance.rom_i().rtc_data());
+    RTC_PENDING = instance.rom_i().has_rtc();
+    CHEATS = Some(CheatManager::new(instance.cheat_list()));
+    if !environment_cb(
+        RETRO_ENVIRONMENT_GET_CAN_DUPE,
+        addr_of_mut!(CAN_DUPE) as *const c_void,
+    ) {
+        CAN_DUPE = false;
+    }
+    update_vars(false);
 }
 
 #[no_mangle]
 pub extern "C" fn retro_unload_game() {
     debugln!("retro_unload_game()");
     let instance = unsafe { EMULATOR.as_mut().unwrap() };
+    instance.set_parallel_audio(false).unwrap();
     instance.reset();
+    if unsafe { LINKED.take().is_some() } {
+        instance.attach_serial(Box::<NullDevice>::default());
+        unsafe { LINK_CABLE = None };
+    }
 }
 
 #[no_mangle]
-pub extern "C" fn retro_get_memory_size(_memory_id: u32) -> usize {
+pub extern "C" fn retro_get_memory_size(memory_id: u32) -> usize {
     debugln!("retro_get_memory_size()");
-    0
+    let (instance, memory_id) = match unsafe { memory_instance(memory_id) } {
+        Some(value) => value,
+        None => return 0,
+    };
+    let rom = instance.rom_i();
+    match memory_id {
+        RETRO_MEMORY_SAVE_RAM if rom.has_battery() => rom.ram_data().len(),
+        RETRO_MEMORY_RTC if rom.has_rtc() => RTC_FOOTER_SIZE,
+        _ => 0,
+    }
 }
 
+/// The memory is read and written directly by the frontend, the
+/// SRAM is restored after the game load and persisted (eg: `.srm`
+/// file) at unload and at the frontend's autosave interval.
 #[no_mangle]
-pub extern "C" fn retro_get_memory_data(_memory_id: u32) -> *mut c_void {
+pub extern "C" fn retro_get_memory_data(memory_id: u32) -> *mut c_void {
     debugln!("retro_get_memory_data()");
-    std::ptr::null_mut()
+    let (instance, memory_id) = match unsafe { memory_instance(memory_id) } {
+        Some(value) => value,
+        None => return std::ptr::null_mut(),
+    };
+    match memory_id {
+        RETRO_MEMORY_SAVE_RAM if instance.rom_i().has_battery() => {
+            instance.rom().ram_data_mut().as_mut_ptr() as *mut c_void
+        }
+        RETRO_MEMORY_RTC if instance.rom_i().has_rtc() => addr_of_mut!(RTC_DATA) as *mut c_void,
+        _ => std::ptr::null_mut(),
+    }
 }
 
 #[no_mangle]
 pub extern "C" fn retro_serialize_size() -> usize {
     debugln!("retro_serialize_size()");
-    let instance = unsafe { EMULATOR.as_mut().unwrap() };
 
     // uses BESS file format for its static nature, meaning that the final
     // size of the serialized state is known in advance
-    StateManager::save(instance, Some(SaveStateFormat::Bess), None)
-        .unwrap()
-        .len()
+    unsafe { save_state() }.unwrap().len()
 }
 
 #[no_mangle]
 pub extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
     debugln!("retro_serialize()");
-    let instance = unsafe { EMULATOR.as_mut().unwrap() };
-    let state = match StateManager::save(instance, Some(SaveStateFormat::Bess), None) {
+    let state = match unsafe { save_state() } {
         Ok(state) => state,
         Err(err) => {
             warnln!("Failed to save state: {}", err);
@@ -478,9 +731,8 @@ pub extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
 #[no_mangle]
 pub extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
     debugln!("retro_unserialize()");
-    let instance = unsafe { EMULATOR.as_mut().unwrap() };
     let state = unsafe { from_raw_parts(data as *const u8, size) };
-    if let Err(err) = StateManager::load(state, instance, None, None) {
+    if let Err(err) = unsafe { load_state(state) } {
         warnln!("Failed to load state: {}", err);
         #[allow(unreachable_code)]
         {
@@ -494,25 +746,25 @@ pub extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
 pub extern "C" fn retro_cheat_reset() {
     debugln!("retro_cheat_reset()");
     let emulator = unsafe { EMULATOR.as_mut().unwrap() };
-    emulator.reset_cheats();
+    let cheats = unsafe { CHEATS.as_mut().unwrap() };
+    cheats.clear(emulator);
 }
 
 /// # Safety
 ///
 /// This function should be called only within Libretro context.
 #[no_mangle]
-pub unsafe extern "C" fn retro_cheat_set(_index: c_uint, enabled: bool, code: *const c_char) {
+pub unsafe extern "C" fn retro_cheat_set(index: c_uint, enabled: bool, code: *const c_char) {
     debugln!("retro_cheat_set()");
-    // we'll just ignore cheats that are not enabled, Boytacean
-    // does not support pre-loading cheats
-    if !enabled {
-        return;
-    }
+    // the cheat is always stored in the cheat manager (even if it's
+    // disabled) so that toggling it in the frontend is reflected
+    // in the set of codes applied to the system
     let emulator = EMULATOR.as_mut().unwrap();
+    let cheats = CHEATS.as_mut().unwrap();
     let code_c = CStr::from_ptr(code);
     let code_s = code_c.to_string_lossy().into_owned();
-    if let Err(err) = emulator.add_cheat_code(&code_s) {
-        warnln!("Failed to add cheat code ({}): {}", code_s, err);
+    if let Err(err) = cheats.set(emulator, index as usize, &code_s, "", enabled) {
+        warnln!("Failed to set cheat code ({}): {}", code_s, err);
     }
 }
 
@@ -560,25 +812,335 @@ pub extern "C" fn retro_set_input_state(
     }
 }
 
-unsafe fn update_vars() {
-    update_palette();
+/// Saves the state of the system, with the states of both instances
+/// (the first one prefixed with its size) for the link cable subsystem.
+unsafe fn save_state() -> Result<Vec<u8>, String> {
+    let instance = EMULATOR.as_mut().unwrap();
+    let state = StateManager::save(instance, Some(SaveStateFormat::Bess), None)?;
+    let linked = match LINKED.as_mut() {
+        Some(linked) => linked,
+        None => return Ok(state),
+    };
+    let mut data = (state.len() as u32).to_le_bytes().to_vec();
+    data.extend(state);
+    data.extend(StateManager::save(
+        linked,
+        Some(SaveStateFormat::Bess),
+        None,
+    )?);
+    Ok(data)
 }
 
-unsafe fn update_palette() {
+/// Loads the state of the system, as saved by [`save_state`].
+unsafe fn load_state(data: &[u8]) -> Result<(), String> {
+    let instance = EMULATOR.as_mut().unwrap();
+    let linked = match LINKED.as_mut() {
+        Some(linked) => linked,
+        None => return Ok(StateManager::load(data, instance, None, None)?),
+    };
+    let size = data
+        .get(..4)
+        .map(|size| u32::from_le_bytes(size.try_into().unwrap()) as usize)
+        .filter(|size| data.len() >= 4 + size)
+        .ok_or_else(|| String::from("Invalid link cable state"))?;
+    StateManager::load(&data[4..4 + size], instance, None, None)?;
+    StateManager::load(&data[4 + size..], linked, None, None)?;
+    Ok(())
+}
+
+/// Obtains the instance (and its memory identifier) of the memory
+/// with the provided identifier, the memory of the link cable
+/// subsystem is mapped to the instance it belongs to.
+unsafe fn memory_instance(memory_id: u32) -> Option<(&'static mut GameBoy, u32)> {
+    match memory_id {
+        RETRO_MEMORY_LINK_SAVE_RAM_1 => EMULATOR
+            .as_mut()
+            .map(|instance| (instance, RETRO_MEMORY_SAVE_RAM)),
+        RETRO_MEMORY_LINK_SAVE_RAM_2 => LINKED
+            .as_mut()
+            .map(|instance| (instance, RETRO_MEMORY_SAVE_RAM)),
+        _ => EMULATOR.as_mut().map(|instance| (instance, memory_id)),
+    }
+}
+
+/// Runs both instances of the link cable subsystem interleaved in
+/// slices of cycles, with the cable being synced after each of the
+/// slices, returns if any of them has produced a new frame.
+unsafe fn run_linked(emulator: &mut GameBoy, cycle_limit: u32) -> bool {
+    let linked = LINKED.as_mut().unwrap();
+    let cable = LINK_CABLE.as_ref().unwrap();
+    let mut cycles = 0;
+    let mut frame_sent = false;
+    while cycles < cycle_limit {
+        let slice = LINK_SLICE_CYCLES.min(cycle_limit - cycles);
+        frame_sent |= emulator.run_for_cycles_with(slice, |_| true).frames > 0;
+        cable.sync(emulator, linked);
+        frame_sent |= linked.run_for_cycles_with(slice, |_| true).frames > 0;
+        cable.sync(emulator, linked);
+        cycles += slice;
+    }
+    frame_sent
+}
+
+/// Copies the provided (RGB) frame buffer into the XRGB8888 target
+/// frame buffer, with its rows `width` pixels apart and starting at
+/// the provided column.
+fn copy_frame_buffer(frame_buffer: &[u8], target: &mut [u32], width: usize, column: usize) {
+    for (row, line) in frame_buffer
+        .chunks_exact(DISPLAY_WIDTH * RGB_SIZE)
+        .enumerate()
+    {
+        let offset = row * width + column;
+        rgb888_to_xrgb8888_u32_array(line, &mut target[offset..offset + DISPLAY_WIDTH]);
+    }
+}
+
+/// Frame callback of the (single) instance, copying each of
+/// its new frames into the frame buffer.
+fn frame_callback(frame_buffer: &[u8]) {
+    let target = unsafe { &mut *addr_of_mut!(FRAME_BUFFER) };
+    copy_frame_buffer(frame_buffer, target, DISPLAY_WIDTH, 0);
+}
+
+/// Frame callbacks of the linked instances, copying each of their
+/// new frames into their screen (left for the first instance and
+/// right for the second one) of the link frame buffer.
+fn link_frame_callback_left(frame_buffer: &[u8]) {
+    let target = unsafe { &mut *addr_of_mut!(LINK_FRAME_BUFFER) };
+    copy_frame_buffer(frame_buffer, target, DISPLAY_WIDTH * 2, 0);
+}
+
+fn link_frame_callback_right(frame_buffer: &[u8]) {
+    let target = unsafe { &mut *addr_of_mut!(LINK_FRAME_BUFFER) };
+    copy_frame_buffer(frame_buffer, target, DISPLAY_WIDTH * 2, DISPLAY_WIDTH);
+}
+
+unsafe fn apply_cheats() {
     let emulator = EMULATOR.as_mut().unwrap();
+    let cheats = CHEATS.as_ref().unwrap();
+    if let Err(err) = cheats.apply(emulator) {
+        warnln!("Failed to apply cheats: {}", err);
+    }
+}
+
+/// Registers the core options in the frontend, using the core options
+/// v2 (with categories) when supported and the legacy variables otherwise.
+unsafe fn set_options(environment_cb: &extern "C" fn(u32, *const c_void) -> bool) {
+    let mut version = 0_u32;
+    if environment_cb(
+        RETRO_ENVIRONMENT_GET_CORE_OPTIONS_VERSION,
+        addr_of_mut!(version) as *const c_void,
+    ) && version >= 2
+    {
+        let options = RetroCoreOptionsV2 {
+            categories: &OPTION_CATEGORIES as *const _ as *const RetroCoreOptionV2Category,
+            definitions: addr_of!(OPTION_DEFINITIONS) as *const RetroCoreOptionV2Definition,
+        };
+        if environment_cb(
+            RETRO_ENVIRONMENT_SET_CORE_OPTIONS_V2,
+            &options as *const _ as *const c_void,
+        ) {
+            return;
+        }
+    }
+    environment_cb(
+        RETRO_ENVIRONMENT_SET_VARIABLES,
+        &VARIABLES as *const _ as *const c_void,
+    );
+}
+
+/// Obtains the current value of the core option with the provided
+/// (NUL terminated) key, `None` in case the value is not available.
+unsafe fn get_option(key: &'static str) -> Option<&'static str> {
     let environment_cb = ENVIRONMENT_CALLBACK.as_ref().unwrap();
+    let variable = RetroVariable {
+        key: key.as_ptr() as *const c_char,
+        value: std::ptr::null(),
+    };
     if !environment_cb(
         RETRO_ENVIRONMENT_GET_VARIABLE,
-        addr_of!(VARIABLE) as *const _ as *const c_void,
+        &variable as *const _ as *const c_void,
     ) {
         warnln!("Failed to get variable");
     }
-    if VARIABLE.value.is_null() {
+    if variable.value.is_null() {
+        return None;
+    }
+    CStr::from_ptr(variable.value).to_str().ok()
+}
+
+unsafe fn update_vars(running: bool) {
+    update_palette();
+    update_sample_rate(running);
+    update_accuracy();
+    update_fast_forward_audio();
+    update_audio_channels();
+    update_parallel_audio();
+    update_fast_forward_multiplier();
+}
+
+/// Obtains the mode (DMG, CGB or SGB) selected in the model
+/// option, `None` in case it should be inferred from the ROM.
+unsafe fn model_mode() -> Option<GameBoyMode> {
+    match get_option(MODEL_KEY) {
+        Some(value @ ("dmg" | "cgb" | "sgb")) => Some(GameBoyMode::from_string(value)),
+        _ => None,
+    }
+}
+
+unsafe fn update_audio_channels() {
+    let emulator = EMULATOR.as_mut().unwrap();
+    let enabled = |key| get_option(key) != Some("disabled");
+    emulator.set_audio_ch1_enabled(enabled(AUDIO_CH1_KEY));
+    emulator.set_audio_ch2_enabled(enabled(AUDIO_CH2_KEY));
+    emulator.set_audio_ch3_enabled(enabled(AUDIO_CH3_KEY));
+    emulator.set_audio_ch4_enabled(enabled(AUDIO_CH4_KEY));
+}
+
+unsafe fn update_parallel_audio() {
+    let emulator = EMULATOR.as_mut().unwrap();
+    if let Err(err) = emulator.set_parallel_audio(get_option(PARALLEL_AUDIO_KEY) == Some("enabled"))
+    {
+        warnln!("Failed to set parallel audio: {}", err);
+    }
+}
+
+unsafe fn update_fast_forward_multiplier() {
+    FAST_FORWARD_MULTIPLIER = get_option(FAST_FORWARD_MULTIPLIER_KEY)
+        .and_then(|value| value.parse::<u32>().ok())
+        .unwrap_or(1)
+        .clamp(1, 8);
+}
+
+/// Updates the fast-forward audio mode from the variable, the
+/// processor is re-created as the sampling rate may have changed.
+unsafe fn update_fast_forward_audio() {
+    let emulator = EMULATOR.as_mut().unwrap();
+    let mode = get_option(FAST_FORWARD_AUDIO_KEY)
+        .and_then(|value| FastForwardAudioMode::from_string(value).ok())
+        .unwrap_or_default();
+    FAST_FORWARD_AUDIO = Some(FastForwardAudio::new(
+        mode,
+        emulator.audio_sampling_rate(),
+        emulator.audio_channels(),
+    ));
+}
+
+/// Applies the RTC memory restored by the frontend (if any) to the
+/// cartridge clock before the first run, from then on the memory
+/// mirrors the clock so that it's persisted up to date.
+unsafe fn sync_rtc(emulator: &mut GameBoy) {
+    if !emulator.rom_i().has_rtc() {
+        return;
+    }
+    if RTC_PENDING {
+        if let Ok(rtc) = Rtc::from_data(&*addr_of!(RTC_DATA)) {
+            emulator.rom().set_rtc(rtc);
+        }
+        RTC_PENDING = false;
+    }
+    RTC_DATA.copy_from_slice(&emulator.rom_i().rtc_data());
+}
+
+/// Estimates the current fast-forward speed multiplier from the
+/// interval between runs, returning 1 when not fast forwarding.
+unsafe fn fast_forward_speed() -> u32 {
+    let environment_cb = ENVIRONMENT_CALLBACK.as_ref().unwrap();
+    let now = Instant::now();
+    let interval = LAST_RUN
+        .replace(now)
+        .map(|last| now.duration_since(last).as_secs_f32())
+        .unwrap_or(0.0);
+    RUN_INTERVAL += (interval - RUN_INTERVAL) * 0.1;
+
+    if !fast_forwarding(environment_cb) || RUN_INTERVAL <= 0.0 {
+        return 1;
+    }
+    ((1.0 / GameBoy::VISUAL_FREQ / RUN_INTERVAL).round() as u32).clamp(1, 16)
+}
+
+/// Checks if the frontend is currently fast forwarding.
+unsafe fn fast_forwarding(environment_cb: &extern "C" fn(u32, *const c_void) -> bool) -> bool {
+    let mut fast_forwarding = false;
+    environment_cb(
+        RETRO_ENVIRONMENT_GET_FASTFORWARDING,
+        addr_of_mut!(fast_forwarding) as *const c_void,
+    ) && fast_forwarding
+}
+
+/// Updates the accuracy profile of the emulator from the variable,
+/// switching the group of behaviours associated with it.
+unsafe fn update_accuracy() {
+    let emulator = EMULATOR.as_mut().unwrap();
+    let accuracy = match get_option(ACCURACY_KEY).map(AccuracyProfile::from_string) {
+        Some(Ok(accuracy)) => accuracy,
+        _ => return,
+    };
+    if accuracy == emulator.accuracy() {
         return;
     }
-    let palette_name = String::from(CStr::from_ptr(VARIABLE.value).to_str().unwrap());
+    emulator.set_accuracy(accuracy);
+    if let Some(linked) = LINKED.as_mut() {
+        linked.set_accuracy(accuracy);
+    }
+}
+
+unsafe fn update_palette() {
+    let emulator = EMULATOR.as_mut().unwrap();
+    let palette_name = match get_option(PALETTE_KEY) {
+        Some(palette_name) => String::from(palette_name),
+        None => return,
+    };
     let palette_info: boytacean::ppu::PaletteInfo = get_palette(palette_name);
     emulator.ppu().set_palette_colors(palette_info.colors());
+    if let Some(linked) = LINKED.as_mut() {
+        linked.ppu().set_palette_colors(palette_info.colors());
+    }
+}
+
+/// Updates the audio sampling rate of the emulator from the variable,
+/// notifying the frontend of the new rate if the game is running.
+unsafe fn update_sample_rate(running: bool) {
+    let emulator = EMULATOR.as_mut().unwrap();
+    let environment_cb = ENVIRONMENT_CALLBACK.as_ref().unwrap();
+    let sample_rate = match get_option(SAMPLE_RATE_KEY).map(|value| value.parse::<u16>()) {
+        Some(Ok(sample_rate)) => sample_rate,
+        _ => return,
+    };
+    if sample_rate == emulator.audio_sampling_rate() {
+        return;
+    }
+    emulator.set_audio_sampling_rate(sample_rate);
+    if running {
+        let info = system_av_info(emulator);
+        if !environment_cb(
+            RETRO_ENVIRONMENT_SET_SYSTEM_AV_INFO,
+            &info as *const _ as *const c_void,
+        ) {
+            warnln!("Failed to set system AV info");
+        }
+    }
+}
+
+fn system_av_info(emulator: &GameBoy) -> RetroSystemAvInfo {
+    // the screens of the link cable subsystem are side by side
+    let width = match unsafe { LINKED.is_some() } {
+        true => DISPLAY_WIDTH * 2,
+        false => DISPLAY_WIDTH,
+    };
+    RetroSystemAvInfo {
+        geometry: RetroGameGeometry {
+            base_width: width as u32,
+            base_height: DISPLAY_HEIGHT as u32,
+            max_width: DISPLAY_WIDTH as u32 * 2,
+            max_height: DISPLAY_HEIGHT as u32,
+            aspect_ratio: width as f32 / DISPLAY_HEIGHT as f32,
+        },
+        timing: RetroSystemTiming {
+            fps: GameBoy::REFRESH_RATE,
+            sample_rate: emulator.audio_sampling_rate() as f64,
+        },
+    }
 }
 
 fn retro_key_to_pad(retro_key: RetroJoypad) -> Option<PadKey> {
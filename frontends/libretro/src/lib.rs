@@ -1,5 +1,6 @@
 #![allow(static_mut_refs, clippy::manual_c_str_literals)]
 pub mod consts;
 pub mod core;
+pub mod options;
 pub mod palettes;
 pub mod structs;
@@ -0,0 +1,281 @@
+//! Core options of the libretro core, defined using the core options
+//! v2 interface (with categories, labels and descriptions) and also
+//! as the legacy variables, used for frontends without v2 support.
+//!
+//! All the strings are NUL terminated as they're handed to the frontend.
+
+use std::{ffi::c_char, ptr::null};
+
+use crate::{
+    consts::RETRO_NUM_CORE_OPTION_VALUES_MAX,
+    structs::{
+        RetroCoreOptionV2Category, RetroCoreOptionV2Definition, RetroCoreOptionValue, RetroVariable,
+    },
+};
+
+pub const PALETTE_KEY: &str = "palette\0";
+pub const MODEL_KEY: &str = "model\0";
+pub const ACCURACY_KEY: &str = "accuracy\0";
+pub const SAMPLE_RATE_KEY: &str = "sample_rate\0";
+pub const AUDIO_CH1_KEY: &str = "audio_ch1\0";
+pub const AUDIO_CH2_KEY: &str = "audio_ch2\0";
+pub const AUDIO_CH3_KEY: &str = "audio_ch3\0";
+pub const AUDIO_CH4_KEY: &str = "audio_ch4\0";
+pub const FAST_FORWARD_AUDIO_KEY: &str = "fast_forward_audio\0";
+pub const FAST_FORWARD_MULTIPLIER_KEY: &str = "fast_forward_multiplier\0";
+pub const PARALLEL_AUDIO_KEY: &str = "parallel_audio\0";
+
+const SYSTEM_CATEGORY: &str = "system\0";
+const VIDEO_CATEGORY: &str = "video\0";
+const AUDIO_CATEGORY: &str = "audio\0";
+
+const TOGGLE_VALUES: [(&str, &str); 2] = [("enabled\0", "Enabled\0"), ("disabled\0", "Disabled\0")];
+
+pub const OPTION_CATEGORIES: [RetroCoreOptionV2Category; 4] = [
+    category(
+        SYSTEM_CATEGORY,
+        "System\0",
+        "Emulated hardware and emulation speed.\0",
+    ),
+    category(VIDEO_CATEGORY, "Video\0", "Colors of the display.\0"),
+    category(AUDIO_CATEGORY, "Audio\0", "Audio output and channels.\0"),
+    RetroCoreOptionV2Category {
+        key: null(),
+        desc: null(),
+        info: null(),
+    },
+];
+
+/// Kept as a static as the fixed size arrays of values make it too
+/// large to be inlined at each use.
+pub static mut OPTION_DEFINITIONS: [RetroCoreOptionV2Definition; 12] = [
+    definition(
+        MODEL_KEY,
+        "Game Boy model\0",
+        "The hardware to be emulated, auto selects it from the cartridge header. Takes effect when the content is loaded.\0",
+        SYSTEM_CATEGORY,
+        &[
+            ("auto\0", "Auto\0"),
+            ("dmg\0", "Game Boy (DMG)\0"),
+            ("cgb\0", "Game Boy Color (CGB)\0"),
+            ("sgb\0", "Super Game Boy (SGB)\0"),
+        ],
+        "auto\0",
+    ),
+    definition(
+        ACCURACY_KEY,
+        "Accuracy profile\0",
+        "Trades emulation accuracy for speed, fast is meant for low-end devices.\0",
+        SYSTEM_CATEGORY,
+        &[
+            ("balanced\0", "Balanced\0"),
+            ("fast\0", "Fast\0"),
+            ("accurate\0", "Accurate\0"),
+        ],
+        "balanced\0",
+    ),
+    definition(
+        FAST_FORWARD_MULTIPLIER_KEY,
+        "Fast-forward multiplier\0",
+        "Number of frames emulated per frontend frame while fast forwarding, on top of the frontend speed.\0",
+        SYSTEM_CATEGORY,
+        &[
+            ("1\0", "1x\0"),
+            ("2\0", "2x\0"),
+            ("3\0", "3x\0"),
+            ("4\0", "4x\0"),
+            ("6\0", "6x\0"),
+            ("8\0", "8x\0"),
+        ],
+        "1\0",
+    ),
+    definition(
+        PALETTE_KEY,
+        "DMG color palette\0",
+        "The colors used for the display of the original Game Boy (DMG) games.\0",
+        VIDEO_CATEGORY,
+        &[
+            ("basic\0", "Basic\0"),
+            ("hogwards\0", "Hogwards\0"),
+            ("christmas\0", "Christmas\0"),
+            ("goldsilver\0", "Gold & Silver\0"),
+            ("pacman\0", "Pac-Man\0"),
+            ("mariobros\0", "Mario Bros\0"),
+            ("pokemon\0", "Pokemon\0"),
+        ],
+        "basic\0",
+    ),
+    definition(
+        SAMPLE_RATE_KEY,
+        "Audio sample rate\0",
+        "The sample rate (in Hz) of the generated audio.\0",
+        AUDIO_CATEGORY,
+        &[
+            ("44100\0", "44100 Hz\0"),
+            ("48000\0", "48000 Hz\0"),
+            ("32000\0", "32000 Hz\0"),
+            ("22050\0", "22050 Hz\0"),
+        ],
+        "44100\0",
+    ),
+    definition(
+        FAST_FORWARD_AUDIO_KEY,
+        "Fast-forward audio\0",
+        "How the audio is played while fast forwarding, pitch keeps the original pitch.\0",
+        AUDIO_CATEGORY,
+        &[
+            ("normal\0", "Normal\0"),
+            ("mute\0", "Mute\0"),
+            ("pitch\0", "Pitch\0"),
+        ],
+        "normal\0",
+    ),
+    definition(
+        PARALLEL_AUDIO_KEY,
+        "Parallel audio\0",
+        "Synthesizes the audio in its own thread, for multi-core devices with slow cores, adds a frame of audio latency.\0",
+        AUDIO_CATEGORY,
+        &[("disabled\0", "Disabled\0"), ("enabled\0", "Enabled\0")],
+        "disabled\0",
+    ),
+    definition(
+        AUDIO_CH1_KEY,
+        "Channel 1 (pulse with sweep)\0",
+        "Enables the output of the first pulse channel.\0",
+        AUDIO_CATEGORY,
+        &TOGGLE_VALUES,
+        "enabled\0",
+    ),
+    definition(
+        AUDIO_CH2_KEY,
+        "Channel 2 (pulse)\0",
+        "Enables the output of the second pulse channel.\0",
+        AUDIO_CATEGORY,
+        &TOGGLE_VALUES,
+        "enabled\0",
+    ),
+    definition(
+        AUDIO_CH3_KEY,
+        "Channel 3 (wave)\0",
+        "Enables the output of the wave channel.\0",
+        AUDIO_CATEGORY,
+        &TOGGLE_VALUES,
+        "enabled\0",
+    ),
+    definition(
+        AUDIO_CH4_KEY,
+        "Channel 4 (noise)\0",
+        "Enables the output of the noise channel.\0",
+        AUDIO_CATEGORY,
+        &TOGGLE_VALUES,
+        "enabled\0",
+    ),
+    RetroCoreOptionV2Definition {
+        key: null(),
+        desc: null(),
+        desc_categorized: null(),
+        info: null(),
+        info_categorized: null(),
+        category_key: null(),
+        values: option_values(&[]),
+        default_value: null(),
+    },
+];
+
+/// The legacy variables, with the first value of each one being
+/// its default value.
+pub const VARIABLES: [RetroVariable; 12] = [
+    variable(MODEL_KEY, "Game Boy model; auto|dmg|cgb|sgb\0"),
+    variable(ACCURACY_KEY, "Accuracy profile; balanced|fast|accurate\0"),
+    variable(
+        FAST_FORWARD_MULTIPLIER_KEY,
+        "Fast-forward multiplier; 1|2|3|4|6|8\0",
+    ),
+    variable(
+        PALETTE_KEY,
+        "DMG color palette; basic|hogwards|christmas|goldsilver|pacman|mariobros|pokemon\0",
+    ),
+    variable(
+        SAMPLE_RATE_KEY,
+        "Audio sample rate; 44100|48000|32000|22050\0",
+    ),
+    variable(
+        FAST_FORWARD_AUDIO_KEY,
+        "Fast-forward audio; normal|mute|pitch\0",
+    ),
+    variable(PARALLEL_AUDIO_KEY, "Parallel audio; disabled|enabled\0"),
+    variable(
+        AUDIO_CH1_KEY,
+        "Channel 1 (pulse with sweep); enabled|disabled\0",
+    ),
+    variable(AUDIO_CH2_KEY, "Channel 2 (pulse); enabled|disabled\0"),
+    variable(AUDIO_CH3_KEY, "Channel 3 (wave); enabled|disabled\0"),
+    variable(AUDIO_CH4_KEY, "Channel 4 (noise); enabled|disabled\0"),
+    RetroVariable {
+        key: null(),
+        value: null(),
+    },
+];
+
+const fn c_str(value: &'static str) -> *const c_char {
+    value.as_ptr() as *const c_char
+}
+
+const fn category(
+    key: &'static str,
+    desc: &'static str,
+    info: &'static str,
+) -> RetroCoreOptionV2Category {
+    RetroCoreOptionV2Category {
+        key: c_str(key),
+        desc: c_str(desc),
+        info: c_str(info),
+    }
+}
+
+const fn definition(
+    key: &'static str,
+    desc: &'static str,
+    info: &'static str,
+    category_key: &'static str,
+    values: &[(&'static str, &'static str)],
+    default_value: &'static str,
+) -> RetroCoreOptionV2Definition {
+    RetroCoreOptionV2Definition {
+        key: c_str(key),
+        desc: c_str(desc),
+        desc_categorized: null(),
+        info: c_str(info),
+        info_categorized: null(),
+        category_key: c_str(category_key),
+        values: option_values(values),
+        default_value: c_str(default_value),
+    }
+}
+
+/// Builds the fixed size array of values of an option, made of
+/// the value and label pairs followed by the terminating entries.
+const fn option_values(
+    values: &[(&'static str, &'static str)],
+) -> [RetroCoreOptionValue; RETRO_NUM_CORE_OPTION_VALUES_MAX] {
+    let mut result = [RetroCoreOptionValue {
+        value: null(),
+        label: null(),
+    }; RETRO_NUM_CORE_OPTION_VALUES_MAX];
+    let mut index = 0;
+    while index < values.len() {
+        result[index] = RetroCoreOptionValue {
+            value: c_str(values[index].0),
+            label: c_str(values[index].1),
+        };
+        index += 1;
+    }
+    result
+}
+
+const fn variable(key: &'static str, value: &'static str) -> RetroVariable {
+    RetroVariable {
+        key: c_str(key),
+        value: c_str(value),
+    }
+}
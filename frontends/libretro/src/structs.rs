@@ -1,5 +1,7 @@
 use std::ffi::{c_char, c_float, c_uchar, c_uint, c_void};
 
+use crate::consts::RETRO_NUM_CORE_OPTION_VALUES_MAX;
+
 #[repr(C)]
 pub struct RetroGameInfo {
     pub path: *const c_char,
@@ -59,9 +61,79 @@ pub struct RetroVariable {
     pub value: *const c_char,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RetroCoreOptionValue {
+    pub value: *const c_char,
+    pub label: *const c_char,
+}
+
+#[repr(C)]
+pub struct RetroCoreOptionV2Category {
+    pub key: *const c_char,
+    pub desc: *const c_char,
+    pub info: *const c_char,
+}
+
+#[repr(C)]
+pub struct RetroCoreOptionV2Definition {
+    pub key: *const c_char,
+    pub desc: *const c_char,
+    pub desc_categorized: *const c_char,
+    pub info: *const c_char,
+    pub info_categorized: *const c_char,
+    pub category_key: *const c_char,
+    pub values: [RetroCoreOptionValue; RETRO_NUM_CORE_OPTION_VALUES_MAX],
+    pub default_value: *const c_char,
+}
+
+#[repr(C)]
+pub struct RetroCoreOptionsV2 {
+    pub categories: *const RetroCoreOptionV2Category,
+    pub definitions: *const RetroCoreOptionV2Definition,
+}
+
 #[repr(C)]
 pub struct RetroSystemContentInfoOverride {
     pub extensions: *const c_char,
     pub need_fullpath: c_uchar,
     pub persistent_data: c_uchar,
 }
+
+#[repr(C)]
+pub struct RetroControllerDescription {
+    pub desc: *const c_char,
+    pub id: c_uint,
+}
+
+#[repr(C)]
+pub struct RetroControllerInfo {
+    pub types: *const RetroControllerDescription,
+    pub num_types: c_uint,
+}
+
+#[repr(C)]
+pub struct RetroSubsystemMemoryInfo {
+    pub extension: *const c_char,
+    pub type_: c_uint,
+}
+
+#[repr(C)]
+pub struct RetroSubsystemRomInfo {
+    pub desc: *const c_char,
+    pub valid_extensions: *const c_char,
+    pub need_fullpath: c_uchar,
+    pub block_extract: c_uchar,
+    pub required: c_uchar,
+    pub memory: *const RetroSubsystemMemoryInfo,
+    pub num_memory: c_uint,
+}
+
+#[repr(C)]
+pub struct RetroSubsystemInfo {
+    pub desc: *const c_char,
+    pub ident: *const c_char,
+    pub roms: *const RetroSubsystemRomInfo,
+    pub num_roms: c_uint,
+    pub id: c_uint,
+}
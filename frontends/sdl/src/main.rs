@@ -1,35 +1,63 @@
 pub mod audio;
 pub mod data;
 pub mod sdl;
+pub mod session;
 pub mod test;
 
 use audio::Audio;
 use boytacean::{
+    apu::{FastForwardAudio, FastForwardAudioMode, HighPassFilter, Resampler},
+    capture::{WavWriter, Y4mWriter},
+    cheats::list::CheatList,
+    conformance::ConformanceSuite,
+    cpu::{CpuBackend, TraceFormat, Tracer},
+    debug::gdb::GdbServer,
     devices::{printer::PrinterDevice, stdout::StdoutDevice},
-    gb::{AudioProvider, GameBoy, GameBoyMode},
+    fuzz::{fuzz_rom_many, FuzzMode, FuzzOptions},
+    gb::{
+        AccuracyProfile, AudioProvider, GameBoy, GameBoyMode, GameBoyModel, StartupMemory,
+        SyncPolicy,
+    },
     info::Info,
-    pad::PadKey,
-    ppu::PaletteInfo,
-    rom::Cartridge,
+    mmu::MemoryPattern,
+    movie::MovieMode,
+    netplay::{
+        session::{NetplayConfig, NetplaySession, SyncMode},
+        transport::{TcpAcceptor, TcpTransport},
+    },
+    pacing::{FramePacer, FramePacing},
+    pad::{AutoFire, PadFilter, PadKey, PAD_KEYS},
+    ppu::{PaletteInfo, RenderMode},
+    rom::{read_rom_file, Cartridge, RomLoadMode},
     serial::{NullDevice, SerialDevice},
-    state::StateManager,
+    state::{FileStateStore, StateCatalog, QUICK_SLOT_COUNT},
+    storage::{CollisionPolicy, SaveLayout, SavePaths},
+    video::{
+        filters::{VideoFilter, VideoOverlay},
+        scalers::Scaler,
+    },
 };
 use boytacean_common::{
     error::Error,
-    util::{replace_ext, write_file},
+    util::{read_file, write_file},
 };
-use chrono::Utc;
-use clap::Parser;
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand};
 use image::{ColorType, ImageBuffer, Rgb};
 use sdl::{surface_from_bytes, SdlSystem};
 use sdl2::{
+    controller::Button,
     event::Event,
     keyboard::{Keycode, Mod},
-    pixels::PixelFormatEnum,
+    pixels::{Color, PixelFormatEnum},
+    rect::Rect,
     Sdl,
 };
+use session::Session;
 use std::{
     cmp::max,
+    fs::{create_dir_all, File},
+    io::BufWriter,
     path::{Path, PathBuf},
     thread,
     time::{Duration, Instant, SystemTime},
@@ -44,14 +72,36 @@ const SCREEN_SCALE: f32 = 3.0;
 /// amplification level of the volume
 const VOLUME: f32 = 64.0;
 
-/// The rate (in seconds) at which the current battery
-/// backed RAM is going to be stored into the file system.
-const STORE_RATE: u8 = 5;
+/// The delay (in seconds) after the first unsaved change to the
+/// battery backed RAM before it's stored into the file system.
+const STORE_DELAY: f32 = 1.0;
+
+/// The rate (in seconds) at which the audio/video statistics
+/// are checked for issues (eg: dropped frames, audio underruns).
+const STATS_RATE: u8 = 5;
+
+/// The maximum amount of audio (in seconds) allowed in the audio
+/// device queue before it's considered an overrun and cleared.
+const AUDIO_QUEUE_MAX: f32 = 0.25;
+
+/// The speed multiplier of the emulation while fast forwarding.
+const FAST_SPEED: u32 = 8;
 
 /// The path to the default ROM file that is going to be
 /// loaded in case no other ROM path is provided.
 const DEFAULT_ROM_PATH: &str = "../../res/roms/demo/pocket.gb";
 
+/// The suite of test ROMs used by the conformance command.
+const DEFAULT_SUITE_PATH: &str = "../../res/roms/test/conformance.ini";
+
+/// The name of the directory (relative to the ROM directory) where
+/// the per-game cheat files are going to be stored.
+const CHEATS_DIR: &str = "cheats";
+
+/// The name of the directory (relative to the ROM directory) where
+/// the per-game auto-fire files are going to be stored.
+const AUTO_FIRE_DIR: &str = "autofire";
+
 pub struct Benchmark {
     count: usize,
     cpu_only: Option<bool>,
@@ -69,6 +119,9 @@ impl Default for Benchmark {
     }
 }
 
+/// The video (Y4M) and audio (WAV) writers of a recording.
+type VideoRecording = (Y4mWriter<BufWriter<File>>, WavWriter<BufWriter<File>>);
+
 pub struct EmulatorOptions {
     auto_mode: Option<bool>,
     unlimited: Option<bool>,
@@ -104,6 +157,14 @@ pub struct Emulator {
     /// to handle the audio output.
     audio: Option<Audio>,
 
+    /// Scratch buffer where the samples of the completed audio
+    /// frame are taken into, re-used between frames.
+    audio_frame: Vec<u8>,
+
+    /// Scratch buffer with the audio samples converted into the
+    /// format of the audio device, re-used between frames.
+    audio_buffer: Vec<f32>,
+
     /// The title of the emulator that is going to be displayed
     /// in the window title.
     title: String,
@@ -112,10 +173,6 @@ pub struct Emulator {
     /// the emulator.
     rom_path: String,
 
-    /// The path to the RAM file (save state) that is going to be
-    /// to load state from.
-    ram_path: String,
-
     /// Path to the directory where storage of files is located, this
     /// value is going to be used to save files.
     ///
@@ -133,6 +190,10 @@ pub struct Emulator {
     /// speed of the visual part of the emulation (eg: 60 FPS).
     visual_frequency: f32,
 
+    /// The pacer that decides the number of frames emulated for
+    /// each refresh of the display (visual tick).
+    pacer: FramePacer,
+
     /// The time at which the next tick is going to be executed, this
     /// value is expressed in milliseconds.
     next_tick_time: f32,
@@ -144,6 +205,10 @@ pub struct Emulator {
     /// speed.
     fast: bool,
 
+    /// The processor of the audio produced while fast forwarding,
+    /// that may mute it or preserve its pitch.
+    fast_forward_audio: FastForwardAudio,
+
     /// Set of features that are going to be enabled in the emulator, this
     /// value is going to be used to control the behavior of the emulator.
     features: Vec<&'static str>,
@@ -154,25 +219,125 @@ pub struct Emulator {
 
     /// Index of the current palette controlling the palette being used.
     palette_index: usize,
+
+    /// The list of cheats for the currently loaded ROM, persisted
+    /// in a per-game cheat file keyed by the ROM fingerprint.
+    cheats: CheatList,
+
+    /// The path to the cheat file of the currently loaded ROM.
+    cheats_path: String,
+
+    /// Flag that controls if the cheats in the cheat list are
+    /// currently being applied to the system.
+    cheats_enabled: bool,
+
+    /// The path to the auto-fire file of the currently loaded ROM,
+    /// storing the auto-fire patterns of its keys.
+    auto_fire_path: String,
+
+    /// Flag that controls if the emulator is currently stepping
+    /// backwards in time (rewinding) instead of running forward.
+    rewinding: bool,
+
+    /// The GDB remote debugging server, if enabled the execution
+    /// of the system is controlled by the attached debugger.
+    gdb: Option<GdbServer>,
+
+    /// The path to the file where the CPU instruction trace is
+    /// going to be written, when tracing is enabled.
+    trace_path: Option<String>,
+
+    /// The format of the lines of the CPU instruction trace.
+    trace_format: TraceFormat,
+
+    /// Controls how the ROM data is loaded, either completely
+    /// into memory or streamed (bank by bank) from the file.
+    rom_load_mode: RomLoadMode,
+
+    /// Path to the IPS or BPS patch that is applied (in memory) to
+    /// the ROM when it's loaded, the original ROM file is kept intact.
+    patch_path: Option<String>,
+
+    /// The templates used to resolve the paths of the battery
+    /// backed RAM and of the save states of the loaded ROM.
+    save_paths: SavePaths,
+
+    /// The number of frames after which the emulation loop exits,
+    /// used for automation (eg: screenshot capture), `None` for
+    /// an unlimited execution.
+    frame_limit: Option<u32>,
+
+    /// The number of frames run by the emulation loop since start,
+    /// used to control the frame limit.
+    frame_count: u32,
+
+    /// The netplay session, if enabled the emulation is advanced
+    /// frame by frame in sync with the peer.
+    netplay: Option<NetplaySession>,
+
+    /// The listener of the netplay host, kept after the peer has
+    /// connected so that spectators can join the session.
+    netplay_acceptor: Option<TcpAcceptor>,
+
+    /// The mask of the keys currently pressed by the local player,
+    /// sent to the peer when under a netplay session.
+    netplay_keys: u8,
+
+    /// Flag that controls if the frame budget profiler overlay is
+    /// drawn on top of the frame, showing the time spent per subsystem.
+    profiler: bool,
+
+    /// The time (in milliseconds) spent presenting the last frame,
+    /// texture update and canvas flush included.
+    present_time: f32,
+
+    /// The rolling average of the time (in milliseconds) spent
+    /// presenting a frame.
+    present_average: f32,
+
+    /// The writers of the video (Y4M) and audio (WAV) files of the
+    /// recording in progress, if any.
+    video_recording: Option<VideoRecording>,
+
+    /// The path to the file where the input movie being recorded
+    /// is going to be written once the emulation loop exits.
+    movie_path: Option<String>,
+
+    /// Flag that controls if the recording is paused while fast
+    /// forwarding, otherwise the fast forwarded frames are recorded
+    /// (at the normal speed) as the recording follows emulated time.
+    record_pause_fast: bool,
+
+    /// The auto-save session, if enabled the state of the emulation
+    /// is saved on exit (and on crashes) to be resumed later.
+    session: Option<Session>,
 }
 
 impl Emulator {
     pub fn new(system: GameBoy, options: EmulatorOptions) -> Self {
+        let fast_forward_audio = FastForwardAudio::new(
+            FastForwardAudioMode::Normal,
+            system.audio_sampling_rate(),
+            system.audio_channels(),
+        );
         Self {
             system,
             auto_mode: options.auto_mode.unwrap_or(true),
             unlimited: options.unlimited.unwrap_or(false),
             sdl: None,
             audio: None,
+            audio_frame: Vec::new(),
+            audio_buffer: Vec::new(),
             title: format!("{} v{}", Info::name(), Info::version()),
             rom_path: String::from("invalid"),
-            ram_path: String::from("invalid"),
             dir_path: String::from("invalid"),
             logic_frequency: GameBoy::CPU_FREQ,
             visual_frequency: GameBoy::VISUAL_FREQ,
+            pacer: FramePacer::default(),
             next_tick_time: 0.0,
             next_tick_time_i: 0,
             fast: false,
+            fast_forward_audio,
             features: options
                 .features
                 .unwrap_or_else(|| vec!["video", "audio", "no-vsync"]),
@@ -242,6 +407,29 @@ impl Emulator {
                 ),
             ],
             palette_index: 0,
+            cheats: CheatList::default(),
+            cheats_path: String::from("invalid"),
+            cheats_enabled: true,
+            auto_fire_path: String::from("invalid"),
+            rewinding: false,
+            gdb: None,
+            trace_path: None,
+            trace_format: TraceFormat::Default,
+            rom_load_mode: RomLoadMode::Full,
+            patch_path: None,
+            save_paths: SavePaths::default(),
+            frame_limit: None,
+            frame_count: 0,
+            netplay: None,
+            netplay_acceptor: None,
+            netplay_keys: 0,
+            profiler: false,
+            present_time: 0.0,
+            present_average: 0.0,
+            video_recording: None,
+            movie_path: None,
+            record_pause_fast: false,
+            session: None,
         }
     }
 
@@ -293,15 +481,34 @@ impl Emulator {
 
     pub fn load_rom(&mut self, path: Option<&str>) -> Result<(), Error> {
         let rom_path: &str = path.unwrap_or(&self.rom_path);
-        let ram_path = replace_ext(rom_path, "sav").unwrap_or_else(|| "invalid".to_string());
-        let rom = self.system.load_rom_file(
-            rom_path,
-            if Path::new(&ram_path).exists() {
-                Some(&ram_path)
-            } else {
-                None
-            },
-        )?;
+        // the RAM may be imported from the layout of another emulator,
+        // being then exported into the configured path from there on
+        let ram_path = self
+            .save_paths
+            .find_ram(rom_path)
+            .map(|path| path.to_string_lossy().to_string());
+        let rom = match &self.patch_path {
+            Some(patch_path) => {
+                // patched ROMs are always loaded into memory as the
+                // complete ROM is required to apply the patch
+                let mut rom = Cartridge::from_data_patched(
+                    &read_rom_file(rom_path)?,
+                    &read_file(patch_path)?,
+                )?;
+                if let Some(ram_path) = &ram_path {
+                    rom.set_save_data(&read_file(ram_path)?);
+                }
+                println!("Applied patch from: {patch_path}");
+                self.system.load_cartridge(rom)?
+            }
+            None => {
+                self.system
+                    .load_rom_file_mode(rom_path, ram_path.as_deref(), self.rom_load_mode)?
+            }
+        };
+        if let Some(ram_path) = &ram_path {
+            println!("Loaded RAM from: {ram_path}");
+        }
         println!(
             "========= Cartridge =========\n{}\n=============================",
             rom
@@ -311,28 +518,377 @@ impl Emulator {
                 .set_title(format!("{} [{}]", self.title, rom.title()).as_str())
                 .unwrap();
         }
+        for warning in self.system.rom_warnings() {
+            println!("Warning: {}", warning);
+        }
         self.rom_path = String::from(rom_path);
-        self.ram_path = ram_path;
         self.dir_path = Path::new(&self.rom_path)
             .parent()
             .unwrap()
             .to_str()
             .unwrap()
             .to_string();
+        for slot in self.state_catalog().list_slots().unwrap_or_default() {
+            let date = slot
+                .timestamp
+                .and_then(|timestamp| DateTime::from_timestamp(timestamp as i64, 0))
+                .map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_else(|| String::from("unknown date"));
+            println!("Save state slot {}: {} ({})", slot.index, slot.path, date);
+        }
+        self.start_session();
+        self.load_cheats();
+        self.load_auto_fire();
         Ok(())
     }
 
     pub fn reset(&mut self) -> Result<(), Error> {
+        self.save_battery();
         self.system.reset();
         self.system.load(true)?;
         self.load_rom(None)?;
         Ok(())
     }
 
+    /// Stores the battery backed RAM into a *.sav file in the file
+    /// system (including the RTC footer), in case it has unsaved changes.
+    pub fn save_battery(&mut self) {
+        if !self.system.battery_dirty() {
+            return;
+        }
+        let save_data = self.system.rom().save_data();
+        self.save_paths
+            .export_ram(&self.rom_path, &save_data, CollisionPolicy::Overwrite)
+            .unwrap();
+        self.system.mark_battery_saved();
+    }
+
+    /// Starts the GDB remote debugging server on the provided port,
+    /// the execution is stopped until a debugger attaches and resumes it.
+    pub fn start_gdb(&mut self, port: u16) -> Result<(), Error> {
+        let gdb = GdbServer::bind(port)?;
+        println!(
+            "GDB server listening on port {}, waiting for debugger...",
+            gdb.port()?
+        );
+        self.gdb = Some(gdb);
+        Ok(())
+    }
+
+    /// Starts a netplay session, either as the host (listening on the
+    /// provided port) or by connecting to the host at the provided
+    /// address, blocks until the peer is connected.
+    pub fn start_netplay(
+        &mut self,
+        port: Option<u16>,
+        addr: Option<&str>,
+        spectate: bool,
+        config: NetplayConfig,
+    ) -> Result<(), Error> {
+        let netplay = match (port, addr) {
+            (_, Some(addr)) if spectate => {
+                println!("Connecting to netplay session at {addr} as spectator...");
+                let mut netplay =
+                    NetplaySession::new(Box::new(TcpTransport::connect(addr)?), config);
+                netplay.spectate(&mut self.system)?;
+                netplay
+            }
+            (_, Some(addr)) => {
+                println!("Connecting to netplay host at {addr}...");
+                let mut netplay =
+                    NetplaySession::new(Box::new(TcpTransport::connect(addr)?), config);
+                netplay.join(&mut self.system)?;
+                netplay
+            }
+            (Some(port), None) => {
+                println!("Waiting for netplay peer on port {port}...");
+                let acceptor = TcpAcceptor::bind(port)?;
+                let mut netplay = NetplaySession::new(Box::new(acceptor.accept()?), config);
+                netplay.host(&mut self.system)?;
+                self.netplay_acceptor = Some(acceptor);
+                netplay
+            }
+            (None, None) => return Err(Error::MissingOption(String::from("netplay port"))),
+        };
+        println!(
+            "Netplay session started ({} mode, {})",
+            config.sync_mode,
+            netplay.role()
+        );
+        self.netplay = Some(netplay);
+        Ok(())
+    }
+
+    /// Presses or lifts the provided key, under a netplay session
+    /// the key is only registered in the local keys mask, to be
+    /// applied (in sync with the peer) by the session.
+    fn set_key(&mut self, key: PadKey, pressed: bool) {
+        if self.netplay.is_some() {
+            let mask = 1 << PAD_KEYS.iter().position(|value| *value == key).unwrap();
+            if pressed {
+                self.netplay_keys |= mask;
+            } else {
+                self.netplay_keys &= !mask;
+            }
+        } else if pressed {
+            self.system.key_press(key);
+        } else {
+            self.system.key_lift(key);
+        }
+    }
+
+    /// Sets the state of the key in the joypad of the provided player,
+    /// with player 1 (index 0) sharing the joypad with the keyboard.
+    fn set_player_key(&mut self, player: usize, key: PadKey, pressed: bool) {
+        if player == 0 {
+            self.set_key(key, pressed)
+        } else if pressed {
+            self.system.key_press_player(player, key);
+        } else {
+            self.system.key_lift_player(player, key);
+        }
+    }
+
+    /// Starts writing the CPU instruction trace to the provided path,
+    /// using the provided format, the path is also used when tracing
+    /// is toggled.
+    pub fn start_trace(&mut self, path: &str, format: TraceFormat) -> Result<(), Error> {
+        self.trace_path = Some(String::from(path));
+        self.trace_format = format;
+        self.system
+            .set_tracer(Some(Tracer::from_file(path, format)?));
+        println!("Tracing CPU instructions to {path} ({format})");
+        Ok(())
+    }
+
+    /// Starts recording the emulation to the provided Y4M file
+    /// (video) and to a WAV file with the same name (audio).
+    ///
+    /// Both are written in emulated time, every frame and audio sample
+    /// produced is recorded, meaning that they remain in sync and that
+    /// fast forwarded sections are recorded at the normal speed.
+    pub fn start_video_recording(&mut self, path: &str) -> Result<(), Error> {
+        let audio_path = Path::new(path).with_extension("wav");
+        let video = Y4mWriter::new(
+            BufWriter::new(File::create(path)?),
+            self.system.display_width() as u32,
+            self.system.display_height() as u32,
+            GameBoy::CPU_FREQ,
+            GameBoy::LCD_CYCLES,
+        )?;
+        let audio = WavWriter::new(
+            BufWriter::new(File::create(&audio_path)?),
+            self.system.audio_sampling_rate() as u32,
+            self.system.audio_channels() as u16,
+        )?;
+        self.video_recording = Some((video, audio));
+        println!(
+            "Recording video to {path} and audio to {}",
+            audio_path.to_string_lossy()
+        );
+        Ok(())
+    }
+
+    /// Stops the recording in progress (if any), finalizing both the
+    /// video and the audio files.
+    pub fn stop_video_recording(&mut self) -> Result<(), Error> {
+        if let Some((video, audio)) = self.video_recording.take() {
+            let frames = video.frames();
+            video.finish()?;
+            audio.finish()?;
+            println!("Stopped recording ({frames} frames)");
+        }
+        Ok(())
+    }
+
+    /// Writes the input movie being recorded (if any) to the file
+    /// selected when the recording was started.
+    pub fn save_movie(&mut self) -> Result<(), Error> {
+        let path = match &self.movie_path {
+            Some(path) => path.clone(),
+            None => return Ok(()),
+        };
+        let recording = self
+            .system
+            .movie()
+            .is_some_and(|movie| movie.mode() == MovieMode::Recording);
+        if !recording {
+            return Ok(());
+        }
+        let movie = self.system.stop_movie().unwrap();
+        write_file(&path, &movie.to_data()?, None)?;
+        println!(
+            "Saved movie to {path} ({} frames, {} rerecords)",
+            movie.frames(),
+            movie.rerecords
+        );
+        Ok(())
+    }
+
+    /// Determines if the frames and audio samples being produced
+    /// should be written to the recording in progress.
+    fn recording_active(&self) -> bool {
+        self.video_recording.is_some() && !(self.record_pause_fast && self.fast)
+    }
+
+    /// Toggles the CPU instruction trace, when no trace path has been
+    /// set, the trace is written to a file named after the ROM.
+    pub fn toggle_trace(&mut self) {
+        if let Some(tracer) = self.system.set_tracer(None) {
+            println!("Stopped CPU tracing ({} instructions)", tracer.count());
+            return;
+        }
+        let path = self.trace_path.clone().unwrap_or_else(|| {
+            Path::new(&self.dir_path)
+                .join(format!("{}.trace", self.rom_name()))
+                .to_string_lossy()
+                .to_string()
+        });
+        if let Err(err) = self.start_trace(&path, self.trace_format) {
+            println!("Failed to start CPU tracing: {err}");
+        }
+    }
+
+    /// Adds the provided cheat codes to the cheat list of the current
+    /// ROM (as enabled) and applies the cheat list to the system.
     pub fn apply_cheats(&mut self, cheats: &Vec<String>) {
         for cheat in cheats {
-            self.system.add_cheat_code(cheat).unwrap();
+            self.cheats.add(cheat, "", true).unwrap();
+        }
+        self.refresh_cheats();
+    }
+
+    /// Loads the cheat list for the currently loaded ROM from its
+    /// cheat file, in case the ROM changed, and applies it.
+    fn load_cheats(&mut self) {
+        let fingerprint = self.system.rom_i().fingerprint();
+        if self.cheats.fingerprint() != fingerprint {
+            let mut cheats = CheatList::new(fingerprint);
+            let mut path_buf = PathBuf::from(&self.dir_path);
+            path_buf.push(CHEATS_DIR);
+            path_buf.push(cheats.file_name());
+            self.cheats_path = path_buf.to_str().unwrap().to_string();
+            if path_buf.exists() {
+                match CheatList::load_file_for(&self.cheats_path, fingerprint) {
+                    Ok(loaded) => {
+                        println!(
+                            "Loaded {} cheat(s) from: {}",
+                            loaded.len(),
+                            self.cheats_path
+                        );
+                        cheats = loaded;
+                    }
+                    Err(message) => println!("Error loading cheats: {}", message),
+                }
+            }
+            self.cheats = cheats;
+        }
+        self.refresh_cheats();
+    }
+
+    /// Saves the cheat list of the current ROM into its cheat file,
+    /// an empty cheat list only overwrites an existing file.
+    pub fn save_cheats(&mut self) {
+        let path = Path::new(&self.cheats_path);
+        if self.cheats.is_empty() && !path.exists() {
+            return;
+        }
+        if let Some(parent) = path.parent() {
+            if let Err(message) = create_dir_all(parent) {
+                println!("Error saving cheats: {}", message);
+                return;
+            }
+        }
+        if let Err(message) = self.cheats.save_file(&self.cheats_path) {
+            println!("Error saving cheats: {}", message)
+        } else {
+            println!("Saved cheats into: {}", self.cheats_path)
+        }
+    }
+
+    /// Loads the auto-fire patterns of the currently loaded ROM from
+    /// its auto-fire file, clearing them if there's no such file.
+    fn load_auto_fire(&mut self) {
+        let fingerprint = self.system.rom_i().fingerprint();
+        let mut path_buf = PathBuf::from(&self.dir_path);
+        path_buf.push(AUTO_FIRE_DIR);
+        path_buf.push(AutoFire::file_name(fingerprint));
+        self.auto_fire_path = path_buf.to_str().unwrap().to_string();
+        let mut auto_fire = AutoFire::default();
+        if path_buf.exists() {
+            match AutoFire::load_file(&self.auto_fire_path) {
+                Ok(loaded) => {
+                    println!(
+                        "Loaded auto-fire ({}) from: {}",
+                        loaded, self.auto_fire_path
+                    );
+                    auto_fire = loaded;
+                }
+                Err(message) => println!("Error loading auto-fire: {}", message),
+            }
+        }
+        self.system.set_auto_fire(auto_fire);
+    }
+
+    /// Sets the auto-fire patterns of the current ROM from their
+    /// textual representation (eg: `a:2/10,b:1/4`).
+    pub fn apply_auto_fire(&mut self, value: &str) {
+        match AutoFire::from_string(value) {
+            Ok(auto_fire) => self.system.set_auto_fire(auto_fire),
+            Err(message) => println!("Error applying auto-fire: {}", message),
+        }
+    }
+
+    /// Saves the auto-fire patterns of the current ROM into its
+    /// auto-fire file, no patterns only overwrite an existing file.
+    pub fn save_auto_fire(&mut self) {
+        let path = Path::new(&self.auto_fire_path);
+        let auto_fire = *self.system.auto_fire();
+        if auto_fire.is_empty() && !path.exists() {
+            return;
+        }
+        if let Some(parent) = path.parent() {
+            if let Err(message) = create_dir_all(parent) {
+                println!("Error saving auto-fire: {}", message);
+                return;
+            }
         }
+        if let Err(message) = auto_fire.save_file(&self.auto_fire_path) {
+            println!("Error saving auto-fire: {}", message)
+        } else {
+            println!("Saved auto-fire into: {}", self.auto_fire_path)
+        }
+    }
+
+    fn refresh_cheats(&mut self) {
+        if !self.cheats_enabled {
+            self.system.reset_cheats();
+            return;
+        }
+        if let Err(message) = self.system.apply_cheats(&self.cheats) {
+            println!("Error applying cheats: {}", message)
+        }
+    }
+
+    pub fn toggle_cheats(&mut self) {
+        self.cheats_enabled = !self.cheats_enabled;
+        self.refresh_cheats();
+        println!(
+            "Cheats {}",
+            if self.cheats_enabled {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+    }
+
+    pub fn print_cheats(&mut self) {
+        if self.cheats.is_empty() {
+            println!("No cheats available for the current ROM");
+            return;
+        }
+        print!("{}", self.cheats);
     }
 
     pub fn benchmark(&mut self, params: &Benchmark) {
@@ -354,35 +910,138 @@ impl Emulator {
 
         let delta = initial.elapsed().unwrap().as_millis() as f64 / 1000.0;
         let frequency_mhz = cycles as f64 / delta / 1000.0 / 1000.0;
-        let speedup = cycles as f64
-            / GameBoy::CPU_FREQ as f64
-            / delta
-            / self.system.speed().multiplier() as f64;
+        let speedup = cycles as f64 / GameBoy::CPU_FREQ as f64 / delta;
         let framerate = speedup * GameBoy::VISUAL_FREQ as f64;
 
         println!(
-            "Took {:.2} seconds to run {} ticks ({} cycles) ({:.2} Mhz, {:.2} speedup, {:.2} FPS)!",
-            delta, count, cycles, frequency_mhz, speedup, framerate
+            "Took {:.2} seconds to run {} ticks ({} cycles) ({:.2} Mhz, {:.2} speedup, {:.2} FPS) with the {} CPU backend!",
+            delta,
+            count,
+            cycles,
+            frequency_mhz,
+            speedup,
+            framerate,
+            self.system.cpu_backend()
         );
     }
 
-    fn save_state(&mut self, file_path: &str) {
-        if let Err(message) = StateManager::save_file(file_path, &mut self.system, None, None) {
-            println!("Error saving state: {}", message)
-        } else {
-            println!("Saved state into: {}", file_path)
+    /// Builds the catalog of the save state slots of the current ROM,
+    /// stored in the file system according to the save paths.
+    fn state_catalog(&self) -> StateCatalog {
+        StateCatalog::new(
+            Box::<FileStateStore>::default(),
+            self.save_paths.clone(),
+            &self.rom_path,
+        )
+    }
+
+    fn save_slot(&mut self, slot: usize) {
+        match self.state_catalog().save_slot(slot, &mut self.system) {
+            Ok(path) => println!("Saved state into slot {}: {}", slot, path),
+            Err(message) => println!("Error saving state into slot {}: {}", slot, message),
+        }
+    }
+
+    fn load_slot(&mut self, slot: usize) {
+        match self.state_catalog().load_slot(slot, &mut self.system) {
+            Ok(path) => println!("Loaded state from slot {}: {}", slot, path),
+            Err(message) => println!("Error loading state from slot {}: {}", slot, message),
         }
     }
 
-    fn load_state(&mut self, file_path: &str) {
-        if let Err(message) = StateManager::load_file(file_path, &mut self.system, None, None) {
-            println!("Error loading state: {}", message)
+    fn quick_save(&mut self) {
+        let slot = self.system.quick_slots().selected();
+        if let Err(message) = self.system.quick_save(None) {
+            println!("Error saving quick slot {}: {}", slot, message)
         } else {
-            println!("Loaded state from: {}", file_path)
+            println!("Saved state into quick slot {}", slot)
+        }
+    }
+
+    fn quick_load(&mut self) {
+        let slot = self.system.quick_slots().selected();
+        if self.netplay.is_some() {
+            println!("Quick slots are not available under netplay");
+            return;
+        }
+        match self.system.quick_load(None) {
+            Ok(true) => println!("Loaded state from quick slot {}", slot),
+            Ok(false) => println!("Quick slot {} is empty", slot),
+            Err(message) => println!("Error loading quick slot {}: {}", slot, message),
+        }
+    }
+
+    fn select_quick_slot(&mut self, next: bool) {
+        let slots = self.system.quick_slots_mut();
+        let slot = match next {
+            true => slots.select_next(),
+            false => slots.select_previous(),
+        };
+        let used = match self.system.quick_slots().is_used(slot) {
+            true => "used",
+            false => "empty",
+        };
+        println!("Selected quick slot {} ({})", slot, used)
+    }
+
+    /// Enables the auto-save session, installing the panic hook
+    /// that stores the latest snapshot of the emulation on crashes.
+    pub fn enable_autosave(&mut self) {
+        let session = Session::new();
+        session.install_panic_hook();
+        self.session = Some(session);
+    }
+
+    fn start_session(&mut self) {
+        let path = Session::autosave_path(&self.save_paths, &self.rom_path);
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+        if let Some(modified) = session.start(path.clone()) {
+            let date: DateTime<Utc> = modified.into();
+            println!(
+                "Auto-saved state found: {} ({}), press F9 to resume it",
+                path.display(),
+                date.format("%Y-%m-%d %H:%M:%S")
+            );
         }
     }
 
-    fn save_image(&mut self, file_path: &str) {
+    fn update_session(&mut self) {
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+        if let Err(message) = session.update(&mut self.system) {
+            println!("Error taking auto-save snapshot: {}", message);
+        }
+    }
+
+    fn save_session(&mut self) {
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+        match session.save(&mut self.system) {
+            Ok(path) => println!("Auto-saved state into: {}", path.display()),
+            Err(message) => println!("Error auto-saving state: {}", message),
+        }
+    }
+
+    fn resume_session(&mut self) {
+        let Some(session) = self.session.as_mut() else {
+            println!("Auto-save is not enabled, use --autosave");
+            return;
+        };
+        if self.netplay.is_some() {
+            println!("Auto-saved states are not available under netplay");
+            return;
+        }
+        match session.resume(&mut self.system) {
+            Ok(path) => println!("Resumed auto-saved state from: {}", path.display()),
+            Err(message) => println!("Error resuming auto-saved state: {}", message),
+        }
+    }
+
+    fn save_image(&mut self, file_path: &str) -> Result<(), Error> {
         let width = self.system.display_width() as u32;
         let height = self.system.display_height() as u32;
         let pixels = self.system.frame_buffer_raw();
@@ -396,7 +1055,7 @@ impl Emulator {
 
         image_buffer
             .save_with_format(file_path, image::ImageFormat::Png)
-            .unwrap();
+            .map_err(|err| Error::CustomError(format!("Error saving image: {err}")))
     }
 
     pub fn toggle_audio(&mut self) {
@@ -428,14 +1087,56 @@ impl Emulator {
         println!("{}", self.system.description_debug());
     }
 
+    /// Sets the frame pacing policy for a display with the provided
+    /// refresh rate (the one of the window's display by default), the
+    /// emulation then ticks at the rate at which frames are presented.
+    pub fn set_frame_pacing(&mut self, pacing: FramePacing, display_rate: Option<f64>) {
+        let display_rate = display_rate.or_else(|| self.display_rate()).unwrap_or(60.0);
+        self.pacer = FramePacer::new(pacing, display_rate);
+        self.visual_frequency = self.pacer.refresh_rate() as f32;
+
+        // when stretched the audio is generated at a sampling rate that
+        // compensates the speed of the emulation, keeping its pitch
+        if self.pacer.stretched() {
+            let sampling_rate = self
+                .pacer
+                .sampling_rate(self.system.audio_sampling_rate() as u32);
+            self.system.set_audio_sampling_rate(sampling_rate as u16);
+        }
+        if let Some(interval) = self.pacer.duplicate_interval() {
+            println!(
+                "Frame pacing {} at {:.2} Hz, repeating a frame every {:.1} seconds",
+                pacing, display_rate, interval
+            );
+        }
+    }
+
+    /// The refresh rate of the display the window is in, if known.
+    fn display_rate(&self) -> Option<f64> {
+        let sdl = self.sdl.as_ref()?;
+        let index = sdl.window().display_index().ok()?;
+        let mode = sdl.video_subsystem.current_display_mode(index).ok()?;
+        (mode.refresh_rate > 0).then_some(mode.refresh_rate as f64)
+    }
+
     pub fn limited(&self) -> bool {
         !self.unlimited
     }
 
     pub fn run(&mut self) {
-        // obtains the dimensions of the display that are going
-        // to be used for the graphics rendering
-        let (width, height) = (self.system.display_width(), self.system.display_height());
+        // obtains the width of the display that is going to be
+        // used for the graphics rendering (eg: profiler)
+        let width = self.system.display_width();
+
+        // obtains the dimensions of the filtered video frames, that
+        // are scaled into the display dimensions (eg: overlays)
+        let (video_width, video_height) = self.system.video_size();
+
+        // without video filters the frames are the ones of the PPU,
+        // so only the lines that changed need to be uploaded into
+        // the texture (eg: static menus)
+        self.system
+            .set_dirty_tracking(self.system.video_filter().is_identity());
 
         // updates the icon of the window to reflect the image
         // and style of the emulator
@@ -453,12 +1154,16 @@ impl Emulator {
         // creates the texture streaming that is going to be used
         // as the target for the pixel buffer
         let mut texture = texture_creator
-            .create_texture_streaming(PixelFormatEnum::RGB24, width as u32, height as u32)
+            .create_texture_streaming(
+                PixelFormatEnum::RGB24,
+                video_width as u32,
+                video_height as u32,
+            )
             .unwrap();
 
-        // calculates the rate as visual cycles that will take from
-        // the current visual frequency to re-save the battery backed RAM
-        let store_count = (self.visual_frequency * STORE_RATE as f32).round() as u32;
+        // calculates the number of frames that the battery backed RAM
+        // is allowed to have unsaved changes before being stored
+        let store_frames = (GameBoy::VISUAL_FREQ * STORE_DELAY).round() as u16;
 
         // starts the variable that will control the number of cycles that
         // are going to move (because of overflow) from one tick to another
@@ -468,6 +1173,16 @@ impl Emulator {
         // iteration cycle
         let mut counter = 0u32;
 
+        // calculates the rate (in visual cycles) at which the audio/video
+        // statistics are checked for issues and the maximum number of
+        // samples allowed in the audio device queue before an overrun
+        let stats_count = (self.visual_frequency * STATS_RATE as f32).round() as u32;
+        let audio_queue_max = (self.system.audio_sampling_rate() as f32
+            * self.system.audio_channels() as f32
+            * AUDIO_QUEUE_MAX) as usize;
+        let mut audio_primed = false;
+        let mut last_stats = self.system.av_stats();
+
         // the main loop to execute the multiple machine clocks, in
         // theory the emulator should keep an infinite loop here
         'main: loop {
@@ -475,12 +1190,29 @@ impl Emulator {
             // on the number of visual ticks since beginning
             counter = counter.wrapping_add(1);
 
-            // in case the current counter is a multiple of the store rate
-            // then we've reached the time to re-save the battery backed RAM
-            // into a *.sav file in the file system
-            if counter % store_count == 0 && self.system.rom().has_battery() {
-                let ram_data = self.system.rom().ram_data();
-                write_file(&self.ram_path, ram_data, None).unwrap();
+            // in case the battery backed RAM has been changed for long
+            // enough then it's stored into the file system, so that
+            // bursts of writes are flushed together
+            if self
+                .system
+                .battery_dirty_frames()
+                .is_some_and(|frames| frames >= store_frames)
+            {
+                self.save_battery();
+            }
+
+            // in case the stats rate has been reached checks the audio/video
+            // statistics for issues since the last check, warning the user
+            // (dropped frames are expected when running above normal speed)
+            #[allow(clippy::manual_is_multiple_of)]
+            let stats_due = counter % stats_count == 0;
+            if stats_due {
+                let stats = self.system.av_stats();
+                let delta = stats.delta(&last_stats);
+                if delta.has_issues() && self.limited() {
+                    println!("Warning: A/V issues in the last {STATS_RATE}s ({delta})");
+                }
+                last_stats = stats;
             }
 
             // obtains an event from the SDL sub-system to be
@@ -503,7 +1235,12 @@ impl Emulator {
                     Event::KeyDown {
                         keycode: Some(Keycode::I),
                         ..
-                    } => self.save_image(&self.image_name(Some("png"), Some(&self.dir_path))),
+                    } => {
+                        let file_path = self.image_name(Some("png"), Some(&self.dir_path));
+                        if let Err(err) = self.save_image(&file_path) {
+                            println!("{err}");
+                        }
+                    }
                     Event::KeyDown {
                         keycode: Some(Keycode::T),
                         ..
@@ -516,6 +1253,21 @@ impl Emulator {
                         keycode: Some(Keycode::C),
                         ..
                     } => self.print_debug(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::L),
+                        ..
+                    } => self.toggle_trace(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::K),
+                        keymod,
+                        ..
+                    } => {
+                        if (keymod & (Mod::LCTRLMOD | Mod::RCTRLMOD)) != Mod::NOMOD {
+                            self.toggle_cheats()
+                        } else {
+                            self.print_cheats()
+                        }
+                    }
                     Event::KeyDown {
                         keycode: Some(Keycode::E),
                         keymod,
@@ -523,7 +1275,7 @@ impl Emulator {
                     } => {
                         if !self.fast && (keymod & (Mod::LCTRLMOD | Mod::RCTRLMOD)) != Mod::NOMOD {
                             self.fast = true;
-                            self.logic_frequency *= 8;
+                            self.logic_frequency *= FAST_SPEED;
                         }
                     }
                     Event::KeyUp {
@@ -532,7 +1284,7 @@ impl Emulator {
                     } => {
                         if self.fast {
                             self.fast = false;
-                            self.logic_frequency /= 8;
+                            self.logic_frequency /= FAST_SPEED;
                         }
                     }
                     Event::KeyUp {
@@ -541,9 +1293,37 @@ impl Emulator {
                     } => {
                         if self.fast {
                             self.fast = false;
-                            self.logic_frequency /= 8;
+                            self.logic_frequency /= FAST_SPEED;
                         }
                     }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Backspace),
+                        ..
+                    } => self.rewinding = self.system.rewind_enabled(),
+                    Event::KeyUp {
+                        keycode: Some(Keycode::Backspace),
+                        ..
+                    } => self.rewinding = false,
+                    Event::KeyDown {
+                        keycode: Some(Keycode::F5),
+                        ..
+                    } => self.quick_save(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::F6),
+                        ..
+                    } => self.select_quick_slot(false),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::F7),
+                        ..
+                    } => self.select_quick_slot(true),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::F8),
+                        ..
+                    } => self.quick_load(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::F9),
+                        ..
+                    } => self.resume_session(),
                     Event::KeyDown {
                         keycode: Some(Keycode::F),
                         keymod,
@@ -577,21 +1357,17 @@ impl Emulator {
                             | Keycode::Num7
                             | Keycode::Num8
                             | Keycode::Num9 => {
-                                let file_path = self.save_name(
-                                    keycode as u8 - Keycode::Num0 as u8,
-                                    None,
-                                    Some(&self.dir_path),
-                                );
+                                let slot = (keycode as u8 - Keycode::Num0 as u8) as usize;
                                 if (keymod & (Mod::LCTRLMOD | Mod::RCTRLMOD)) != Mod::NOMOD {
-                                    self.save_state(&file_path);
+                                    self.save_slot(slot);
                                 } else {
-                                    self.load_state(&file_path);
+                                    self.load_slot(slot);
                                 }
                             }
                             _ => {}
                         }
                         if let Some(key) = key_to_pad(keycode) {
-                            self.system.key_press(key)
+                            self.set_key(key, true)
                         }
                     }
                     Event::KeyUp {
@@ -599,10 +1375,41 @@ impl Emulator {
                         ..
                     } => {
                         if let Some(key) = key_to_pad(keycode) {
-                            self.system.key_lift(key)
+                            self.set_key(key, false)
+                        }
+                    }
+                    Event::ControllerDeviceAdded { which, .. } => {
+                        if let Some(player) = self.sdl.as_mut().unwrap().add_controller(which) {
+                            println!("Connected controller for player {}", player + 1);
+                        }
+                    }
+                    Event::ControllerDeviceRemoved { which, .. } => {
+                        if let Some(player) = self.sdl.as_mut().unwrap().remove_controller(which) {
+                            for key in PAD_KEYS {
+                                self.set_player_key(player, key, false);
+                            }
+                            println!("Disconnected controller for player {}", player + 1);
+                        }
+                    }
+                    Event::ControllerButtonDown { which, button, .. } => {
+                        let player = self.sdl.as_ref().unwrap().controller_player(which);
+                        if let (Some(player), Some(key)) = (player, button_to_pad(button)) {
+                            self.set_player_key(player, key, true)
+                        }
+                    }
+                    Event::ControllerButtonUp { which, button, .. } => {
+                        let player = self.sdl.as_ref().unwrap().controller_player(which);
+                        if let (Some(player), Some(key)) = (player, button_to_pad(button)) {
+                            self.set_player_key(player, key, false)
                         }
                     }
                     Event::DropFile { filename, .. } => {
+                        self.save_session();
+                        self.save_cheats();
+                        self.save_auto_fire();
+                        // the patch is specific to the ROM that was
+                        // loaded from the command line
+                        self.patch_path = None;
                         if self.auto_mode {
                             let mode = Cartridge::from_file(&filename).unwrap().gb_mode();
                             self.system.set_mode(mode);
@@ -626,13 +1433,62 @@ impl Emulator {
                 let mut last_frame = self.system.ppu_frame();
                 let mut frame_dirty = false;
 
+                // calculates the number of ticks (frames) that the emulation is
+                // behind the host clock (1 when on time), this is critical to be
+                // able to properly operate in frame drop situations, where the
+                // system resources are not able to emulate the system on time
+                // (eg: window drag), in such case the sync policy of the system
+                // controls how many of the late frames are emulated (catch-up)
+                // and how many are skipped, re-synchronizing the audio
+                if self.next_tick_time == 0.0 {
+                    self.next_tick_time = current_time as f32;
+                }
+                // under netplay the speed is slightly adjusted by the session
+                // compensating the clock drift between the hosts of the peers
+                let speed = self.netplay.as_ref().map_or(1.0, |netplay| netplay.speed());
+                let tick_time = 1000.0 / (self.visual_frequency * speed as f32);
+                let ticks = max(
+                    ((current_time as f32 - self.next_tick_time) / tick_time).floor() as u32 + 1,
+                    1,
+                );
+                let catch_up = if self.limited()
+                    && !self.rewinding
+                    && self.gdb.is_none()
+                    && self.netplay.is_none()
+                {
+                    self.system.recover_sync(ticks)
+                } else {
+                    1
+                };
+
+                // the frame pacer decides how many frames are emulated for
+                // each of the ticks (display refreshes), with no frames
+                // meaning that the previous frame is presented again
+                let frames = (0..catch_up).map(|_| self.pacer.refresh()).sum::<u32>();
+
                 // calculates the number of cycles that are meant to be the target
                 // for the current "tick" operation this is basically the current
-                // logic frequency divided by the visual one, this operation also
-                // takes into account the current Game Boy speed multiplier (GBC)
-                let cycle_limit = (self.logic_frequency as f32 * self.system.multiplier() as f32
-                    / self.visual_frequency)
-                    .round() as u32;
+                // logic frequency divided by the refresh rate of the LCD, times
+                // the number of frames to be emulated (the cycles are counted
+                // at normal speed, independently of the Game Boy speed)
+                let cycle_limit =
+                    (self.logic_frequency as f64 / GameBoy::REFRESH_RATE).round() as u32 * frames;
+
+                // in case the emulator is rewinding, goes back two frames
+                // so that running the tick (one frame) results in a net
+                // step of one frame backwards in time
+                if self.rewinding {
+                    self.system.rewind(2).unwrap();
+                }
+
+                // handles the pending requests from the GDB client (if any)
+                // disabling the server in case of a communication failure
+                if let Some(gdb) = self.gdb.as_mut() {
+                    if let Err(err) = gdb.poll(&mut self.system) {
+                        println!("GDB server error: {err}");
+                        self.gdb = None;
+                    }
+                }
 
                 loop {
                     // limits the number of ticks to the typical number
@@ -644,39 +1500,144 @@ impl Emulator {
 
                     // runs the Game Boy clock, this operation should
                     // include the advance of both the CPU, PPU, APU
-                    // and any other frequency based component of the system
-                    counter_cycles += self.system.clock() as u32;
+                    // and any other frequency based component of the system,
+                    // under GDB the clock is controlled by the debugger that
+                    // may stop the execution at any instruction
+                    if let Some(gdb) = self.gdb.as_mut() {
+                        if !gdb.running() {
+                            pending_cycles = 0;
+                            break;
+                        }
+                        counter_cycles += gdb.clock(&mut self.system).unwrap_or(0) as u32;
+                    } else if let Some(netplay) = self.netplay.as_mut() {
+                        // under netplay a complete frame is emulated at once
+                        // (or none if stalled waiting for the peer) as the
+                        // inputs are synchronized at the frame boundaries
+                        if let Some(Ok(Some(transport))) = self
+                            .netplay_acceptor
+                            .as_ref()
+                            .map(|acceptor| acceptor.try_accept())
+                        {
+                            match netplay.add_spectator(&mut self.system, Box::new(transport)) {
+                                Ok(()) => println!("Netplay spectator joined"),
+                                Err(err) => println!("Netplay spectator error: {err}"),
+                            }
+                        }
+                        if let Err(err) = netplay.advance(&mut self.system, self.netplay_keys) {
+                            println!("Netplay error: {err}");
+                            self.netplay = None;
+                        }
+                        counter_cycles = cycle_limit;
+                    } else {
+                        counter_cycles += self.system.clock() as u32;
+                    }
 
                     // in case a new frame is available from the emulator
                     // then the frame must be pushed into SDL for display
                     if self.system.ppu_frame() != last_frame {
-                        // obtains the frame buffer of the Game Boy PPU and uses it
-                        // to update the stream texture, that will latter be copied
-                        // to the canvas
-                        let frame_buffer = self.system.frame_buffer().as_ref();
-                        texture.update(None, frame_buffer, width * 3).unwrap();
+                        let update_start = Instant::now();
+
+                        // obtains the (filtered) video frame of the Game Boy and
+                        // uses it to update the stream texture, that will latter
+                        // be copied to the canvas
+                        let pitch = video_width * 3;
+                        if self.system.dirty_tracking() {
+                            if let Some(lines) = self.system.dirty_range() {
+                                let rect = Rect::new(
+                                    0,
+                                    lines.start as i32,
+                                    video_width as u32,
+                                    lines.len() as u32,
+                                );
+                                let video_frame = self.system.video_frame();
+                                texture
+                                    .update(
+                                        rect,
+                                        &video_frame[lines.start * pitch..lines.end * pitch],
+                                        pitch,
+                                    )
+                                    .unwrap();
+                            }
+                            self.system.clear_dirty_lines();
+                        } else {
+                            let video_frame = self.system.video_frame();
+                            texture.update(None, video_frame, pitch).unwrap();
+                        }
+                        self.present_time = update_start.elapsed().as_secs_f32() * 1000.0;
+
+                        // writes the frame to the recording (if any), a failure
+                        // stops the recording keeping the emulation running
+                        if self.recording_active() {
+                            let frame_buffer = self.system.frame_buffer().as_ref();
+                            let (video, _) = self.video_recording.as_mut().unwrap();
+                            if let Err(err) = video.write_frame(frame_buffer) {
+                                println!("Failed to record frame: {err}");
+                                self.video_recording = None;
+                            }
+                        }
 
                         // obtains the index of the current PPU frame, this value
                         // is going to be used to detect for new frame presence
                         last_frame = self.system.ppu_frame();
                         frame_dirty = true;
+
+                        // increments the number of frames run and stops the
+                        // current tick in case the frame limit has been reached
+                        // so that no frame beyond the limit is emulated
+                        self.frame_count += 1;
+                        if self.frame_limit_reached() {
+                            break;
+                        }
                     }
                 }
 
-                // in case there's new audio data available in the emulator we must
-                // handle it, sending it to the audio queue nad clearing the buffer
-                if !self.system.audio_buffer().is_empty() {
-                    if let Some(audio) = self.audio.as_mut() {
-                        let audio_buffer = self
-                            .system
-                            .audio_buffer()
+                // in case there's new (completed) audio frame available in the
+                // emulator we must handle it, sending it to the audio queue, the
+                // streaming audio buffer is cleared as it's not used
+                if self.system.take_audio_frame_into(&mut self.audio_frame) {
+                    if self.recording_active() {
+                        let samples: Vec<i16> = self
+                            .audio_frame
                             .iter()
-                            .map(|v| *v as f32 / VOLUME)
-                            .collect::<Vec<f32>>();
-                        audio.device.queue_audio(&audio_buffer).unwrap();
+                            .map(|v| {
+                                (*v as f32 / VOLUME * i16::MAX as f32).min(i16::MAX as f32) as i16
+                            })
+                            .collect();
+                        let (_, audio) = self.video_recording.as_mut().unwrap();
+                        if let Err(err) = audio.write_samples(&samples) {
+                            println!("Failed to record audio: {err}");
+                            self.video_recording = None;
+                        }
+                    }
+                    // processes the audio produced while fast forwarding,
+                    // after recording as the recording runs in emulated time
+                    self.fast_forward_audio.process(
+                        &mut self.audio_frame,
+                        if self.fast { FAST_SPEED } else { 1 },
+                    );
+
+                    if let Some(audio) = self.audio.as_mut() {
+                        // checks the state of the audio device queue, an empty
+                        // queue means that the device ran out of samples (underrun)
+                        // while a queue beyond the maximum size means that audio
+                        // is being produced faster than consumed, in which case the
+                        // queue is cleared to keep the audio latency bounded
+                        let queue_size = audio.device.size() as usize / std::mem::size_of::<f32>();
+                        if queue_size == 0 && audio_primed {
+                            self.system.report_audio_underrun();
+                        } else if queue_size > audio_queue_max {
+                            audio.device.clear();
+                            self.system.report_audio_overrun(queue_size);
+                        }
+                        audio_primed = true;
+
+                        self.audio_buffer.clear();
+                        self.audio_buffer
+                            .extend(self.audio_frame.iter().map(|v| *v as f32 / VOLUME));
+                        audio.device.queue_audio(&self.audio_buffer).unwrap();
                     }
-                    self.system.clear_audio_buffer();
                 }
+                self.system.clear_audio_buffer();
 
                 // in case there's at least one new frame that was drawn during
                 // during the current tick, then we need to flush it to the canvas,
@@ -684,6 +1645,8 @@ impl Emulator {
                 // resources from being over-used in situations where multiple frames
                 // are generated during the same tick cycle
                 if frame_dirty {
+                    let present_start = Instant::now();
+
                     // clears the graphics canvas, making sure that no garbage
                     // pixel data remaining in the pixel buffer, not doing this would
                     // create visual glitches in OSs like Mac OS X
@@ -698,31 +1661,38 @@ impl Emulator {
                         .copy(&texture, None, None)
                         .unwrap();
 
+                    // draws the frame budget profiler overlay on top of
+                    // the frame, if it has been requested
+                    if self.profiler {
+                        self.draw_profiler(width as u32);
+                    }
+
                     // presents the canvas effectively updating the screen
                     // information presented to the user
                     self.sdl.as_mut().unwrap().canvas.present();
+                    self.present_time += present_start.elapsed().as_secs_f32() * 1000.0;
+                    self.present_average += (self.present_time - self.present_average) * 0.1;
+                    self.system.present_frame(last_frame);
                 }
 
-                // calculates the number of ticks that have elapsed since the
-                // last draw operation, this is critical to be able to properly
-                // operate the clock of the CPU in frame drop situations, meaning
-                // a situation where the system resources are no able to emulate
-                // the system on time and frames must be skipped (ticks > 1)
-                if self.next_tick_time == 0.0 {
-                    self.next_tick_time = current_time as f32;
+                // takes the periodic snapshot of the auto-save session,
+                // stored in case the emulator crashes
+                self.update_session();
+
+                // in case the frame limit has been reached the main loop
+                // is exited, as the automation run is complete
+                if self.frame_limit_reached() {
+                    break 'main;
                 }
-                let mut ticks = ((current_time as f32 - self.next_tick_time)
-                    / ((1.0 / self.visual_frequency) * 1000.0))
-                    .ceil() as u8;
-                ticks = max(ticks, 1);
 
                 // in case the limited (speed) mode is set then we must calculate
                 // a new next tick time reference, this is required to prevent the
-                // machine from running too fast (eg: 50x)
+                // machine from running too fast (eg: 50x), the late ticks are all
+                // accounted, either emulated (catch-up) or skipped
                 if self.limited() {
                     // updates the next update time reference to the current
                     // time so that it can be used from game loop control
-                    self.next_tick_time += (1000.0 / self.visual_frequency) * ticks as f32;
+                    self.next_tick_time += tick_time * ticks as f32;
                     self.next_tick_time_i = self.next_tick_time.ceil() as u32;
                 }
             }
@@ -735,6 +1705,25 @@ impl Emulator {
                 .timer_subsystem
                 .delay(pending_time);
         }
+
+        // finalizes the recording in progress (if any), as the
+        // audio file header is only valid once finished
+        if let Err(err) = self.stop_video_recording() {
+            println!("Failed to stop recording: {err}");
+        }
+
+        // writes the input movie being recorded (if any)
+        if let Err(err) = self.save_movie() {
+            println!("Failed to save movie: {err}");
+        }
+
+        // saves the state of the auto-save session, reached both on
+        // window close and on SIGINT (converted by SDL into a quit)
+        self.save_session();
+
+        // flushes any pending changes to the battery backed RAM so
+        // that they're not lost on a quick exit
+        self.save_battery();
     }
 
     pub fn run_benchmark(&mut self, params: &Benchmark) {
@@ -756,18 +1745,18 @@ impl Emulator {
         let frequency_mhz = cycles as f64 / delta / 1000.0 / 1000.0;
 
         println!(
-            "Took {:.2} seconds to run {} ticks ({} cycles) ({:.2} Mhz)!",
-            delta, count, cycles, frequency_mhz
+            "Took {:.2} seconds to run {} ticks ({} cycles) ({:.2} Mhz) with the {} CPU backend!",
+            delta,
+            count,
+            cycles,
+            frequency_mhz,
+            self.system.cpu_backend()
         );
     }
 
     pub fn run_headless(&mut self, allowed_cycles: Option<u64>) {
         let allowed_cycles = allowed_cycles.unwrap_or(u64::MAX);
 
-        // starts the variable that will control the number of cycles that
-        // are going to move (because of overflow) from one tick to another
-        let mut pending_cycles = 0u32;
-
         // allocates space for the loop ticks counter to be used in each
         // iteration cycle
         let mut counter = 0u32;
@@ -790,31 +1779,18 @@ impl Emulator {
             let current_time = reference.elapsed().as_millis() as u32;
 
             if current_time >= self.next_tick_time_i {
-                // re-starts the counter cycles with the number of pending cycles
-                // from the previous tick
-                let mut counter_cycles = pending_cycles;
-
                 // calculates the number of cycles that are meant to be the target
                 // for the current "tick" operation this is basically the current
-                // logic frequency divided by the visual one, this operation also
-                // takes into account the current Game Boy speed multiplier (GBC)
-                let cycle_limit = (self.logic_frequency as f32 * self.system.multiplier() as f32
-                    / self.visual_frequency)
-                    .round() as u32;
-
-                loop {
-                    // limits the number of ticks to the typical number
-                    // of cycles expected for the current logic cycle
-                    if counter_cycles >= cycle_limit {
-                        pending_cycles = counter_cycles - cycle_limit;
-                        break;
-                    }
+                // logic frequency divided by the visual one (the cycles are
+                // counted at normal speed, independently of the Game Boy speed)
+                let cycle_limit =
+                    (self.logic_frequency as f32 / self.visual_frequency).round() as u32;
 
-                    // runs the Game Boy clock, this operation should
-                    // include the advance of both the CPU, PPU, APU
-                    // and any other frequency based component of the system
-                    counter_cycles += self.system.clock() as u32;
-                }
+                // runs the Game Boy for the cycles of the tick, this operation
+                // should include the advance of both the CPU, PPU, APU and any
+                // other frequency based component of the system, with the cycles
+                // run in excess being carried over to the next tick
+                self.system.run_for_cycles_with(cycle_limit, |_| true);
 
                 // increments the total number of cycles with the cycle limit
                 // fot the current tick an in case the total number of cycles
@@ -855,6 +1831,162 @@ impl Emulator {
         }
     }
 
+    /// Runs the system for the provided number of frames with no
+    /// speed limit, typically used before inspecting the memory.
+    pub fn run_frames(&mut self, frames: u32) {
+        for _ in 0..frames {
+            self.system.next_frame();
+            self.frame_count += 1;
+        }
+    }
+
+    /// Draws the frame budget profiler overlay as two stacked bars
+    /// at the top of the screen, the first for the last frame and
+    /// the second for the rolling average, with the time spent in the
+    /// CPU (red), PPU (green), APU (blue), other devices (yellow) and
+    /// presenting the frame (white).
+    ///
+    /// The full width of the screen corresponds to the time budget
+    /// of a frame (~16.7ms), meaning that a bar reaching the right
+    /// edge signals a frame that could not be emulated in real-time.
+    fn draw_profiler(&mut self, width: u32) {
+        let (last, average) = match self.system.profiler() {
+            Some(profiler) => (profiler.last(), profiler.average()),
+            None => return,
+        };
+        let budget = 1000.0 / GameBoy::VISUAL_FREQ;
+        let bars = [
+            (last, self.present_time, 0),
+            (average, self.present_average, 3),
+        ];
+        let canvas = &mut self.sdl.as_mut().unwrap().canvas;
+        for (profile, present, y) in bars {
+            let slices = [
+                (profile.cpu, Color::RGB(0xe0, 0x40, 0x40)),
+                (profile.ppu, Color::RGB(0x40, 0xc0, 0x40)),
+                (profile.apu, Color::RGB(0x40, 0x60, 0xe0)),
+                (profile.other, Color::RGB(0xe0, 0xc0, 0x40)),
+                (present, Color::RGB(0xff, 0xff, 0xff)),
+            ];
+            let mut x = 0;
+            for (time, color) in slices {
+                let slice_width = ((time / budget) * width as f32).round() as u32;
+                let slice_width = slice_width.min(width.saturating_sub(x));
+                if slice_width == 0 {
+                    continue;
+                }
+                canvas.set_draw_color(color);
+                canvas
+                    .fill_rect(Rect::new(x as i32, y, slice_width, 2))
+                    .unwrap();
+                x += slice_width;
+            }
+        }
+        canvas.set_draw_color(Color::RGB(0x00, 0x00, 0x00));
+    }
+
+    /// Determines if the (optional) frame limit of the emulation
+    /// loop has been reached.
+    fn frame_limit_reached(&self) -> bool {
+        self.frame_limit
+            .is_some_and(|frame_limit| self.frame_count >= frame_limit)
+    }
+
+    /// Searches the provided (inclusive) address range for the pattern,
+    /// printing the address of every match or writing them to a file.
+    pub fn mem_search(
+        &mut self,
+        pattern: &MemoryPattern,
+        start: u16,
+        end: u16,
+        output: Option<&str>,
+    ) -> Result<(), Error> {
+        let matches = self.system.mmu_i().search(pattern, start, end);
+        let lines = matches
+            .iter()
+            .map(|addr| format!("0x{addr:04x}"))
+            .collect::<Vec<String>>();
+        match output {
+            Some(output) => write_file(output, lines.join("\n").as_bytes(), None)?,
+            None => {
+                for line in &lines {
+                    println!("{line}");
+                }
+            }
+        }
+        println!(
+            "Found {} match(es) in 0x{start:04x}-0x{end:04x}",
+            matches.len()
+        );
+        Ok(())
+    }
+
+    /// Dumps the provided (inclusive) address range, either as raw
+    /// bytes to a file or as an hex listing to the standard output.
+    pub fn mem_dump(&mut self, start: u16, end: u16, output: Option<&str>) -> Result<(), Error> {
+        if end < start {
+            return Err(Error::InvalidParameter(format!(
+                "Invalid memory range: 0x{start:04x}-0x{end:04x}"
+            )));
+        }
+        let data = self
+            .system
+            .mmu_i()
+            .peek_many(start, (end - start) as usize + 1);
+        match output {
+            Some(output) => write_file(output, &data, None)?,
+            None => {
+                for (index, chunk) in data.chunks(16).enumerate() {
+                    let bytes = chunk
+                        .iter()
+                        .map(|byte| format!("{byte:02x}"))
+                        .collect::<Vec<String>>()
+                        .join(" ");
+                    println!("0x{:04x}: {bytes}", start as usize + index * 16);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs a set of input fuzzing sessions (one per instance) over the
+    /// currently loaded ROM, printing the report of each session and
+    /// writing the diagnostics of the failed ones to the output directory.
+    pub fn fuzz(
+        &mut self,
+        options: &FuzzOptions,
+        instances: u32,
+        output: Option<&str>,
+    ) -> Result<(), Error> {
+        let rom = read_rom_file(&self.rom_path)?;
+        let reports = fuzz_rom_many(&rom, options, instances);
+        let mut failures = 0;
+        for report in reports {
+            let report = report?;
+            println!("{report}");
+            if report.is_ok() {
+                continue;
+            }
+            failures += 1;
+            if let (Some(output), Some(diagnostics)) = (output, &report.diagnostics) {
+                create_dir_all(output)?;
+                let file_path =
+                    Path::new(output).join(format!("{}-fuzz-{}.txt", self.rom_name(), report.seed));
+                write_file(
+                    file_path.to_str().unwrap(),
+                    format!("{report}\n{diagnostics}\n").as_bytes(),
+                    None,
+                )?;
+            }
+        }
+        if failures > 0 {
+            return Err(Error::CustomError(format!(
+                "{failures} out of {instances} fuzzing sessions failed"
+            )));
+        }
+        Ok(())
+    }
+
     /// Obtains the ROM name (file name without extension) so that
     /// it can be used for derivate file names (eg: save files, screenshots).
     fn rom_name(&self) -> &str {
@@ -871,24 +2003,6 @@ impl Emulator {
         Self::best_name(self.rom_name(), ext, dir_path)
     }
 
-    /// Obtains the best possible save file name (ex: `{ROM_NAME}.s0`) taking
-    /// into consideration the current directory path and the index of the save.
-    fn save_name(&self, index: u8, suffix: Option<&str>, dir_path: Option<&str>) -> String {
-        let suffix = suffix.unwrap_or("s");
-        let dir_path = dir_path.unwrap_or(".");
-        Self::sequence_name(self.rom_name(), index, suffix, dir_path)
-    }
-
-    /// Generates a file name for the provided base name, index and suffix
-    /// taking into consideration the provided directory path.
-    /// The generated file name is `{base}.{suffix}{index}`.
-    fn sequence_name(base: &str, index: u8, suffix: &str, dir_path: &str) -> String {
-        let file_name = format!("{base}.{suffix}{index}");
-        let mut file_buf = PathBuf::from(dir_path);
-        file_buf.push(file_name);
-        file_buf.to_str().unwrap().to_string()
-    }
-
     /// Tries to obtain the best possible file name for the provided base name
     /// and extension avoiding name collisions with existing files in the
     /// same directory.
@@ -926,6 +2040,27 @@ struct Args {
     )]
     no_boot: bool,
 
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "If set DMG games running in CGB mode are not colorized"
+    )]
+    no_colorize: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "If set an overlay with the time spent per subsystem in each frame is displayed"
+    )]
+    profiler: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "If set prints the build information of the emulator and exits"
+    )]
+    build_info: bool,
+
     #[arg(
         long,
         default_value_t = String::from(""),
@@ -962,6 +2097,13 @@ struct Args {
     #[arg(long, default_value_t = false, help = "Run benchmark only for the CPU")]
     benchmark_cpu: bool,
 
+    #[arg(
+        long,
+        default_value_t = String::from("interpreter"),
+        help = "Strategy used by the CPU to execute the instructions (interpreter, cached)"
+    )]
+    cpu_backend: String,
+
     #[arg(
         long,
         default_value_t = false,
@@ -983,17 +2125,404 @@ struct Args {
     )]
     cycles: u64,
 
+    #[arg(
+        long,
+        default_value_t = 0,
+        help = "Number of frames to run before exiting, 0 runs with no limit"
+    )]
+    frames: u32,
+
+    #[arg(
+        long,
+        help = "Path to the PNG file where a screenshot is saved on exit"
+    )]
+    exit_screenshot: Option<String>,
+
     #[arg(
         long,
         help = "Cheat codes to be applied to the ROM, supports both Game Genie and GameShark"
     )]
     cheats: Vec<String>,
 
+    #[arg(
+        long,
+        help = "Auto-fire patterns of the keys while held, in the key:press/period format (ex: a:2/10,b:1/4), saved per game"
+    )]
+    auto_fire: Option<String>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "If set the fast renderer output is compared against the reference renderer"
+    )]
+    compare_render: bool,
+
+    #[arg(
+        long,
+        default_value_t = String::from("scanline"),
+        help = "Strategy used to render the screen (scanline, fifo), fifo is slower but dot accurate"
+    )]
+    render_mode: String,
+
+    #[arg(
+        long,
+        help = "Accuracy profile (fast, balanced, accurate), overrides the render mode, resampler and high-pass options"
+    )]
+    accuracy: Option<String>,
+
+    #[arg(
+        long,
+        default_value_t = String::from("hardware"),
+        help = "Pattern of the RAM contents at power on (hardware, zeros, ones, stripes, random)"
+    )]
+    startup_memory: String,
+
+    #[arg(
+        long,
+        help = "Model whose post boot state is used with --no-boot (dmg0, dmg, mgb, sgb, sgb2, cgb, agb)"
+    )]
+    model: Option<String>,
+
+    #[arg(
+        long,
+        default_value_t = 600,
+        help = "Number of frames kept in the rewind buffer, 0 disables rewind"
+    )]
+    rewind_frames: usize,
+
+    #[arg(
+        long,
+        default_value_t = QUICK_SLOT_COUNT,
+        help = "Number of in-memory quick-save slots (F5 saves, F8 loads, F6/F7 select)"
+    )]
+    quick_slots: usize,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Saves the state on exit (and on crashes) offering to resume it (F9) on the next launch of the ROM"
+    )]
+    autosave: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Streams the ROM banks from the file on demand instead of loading the complete ROM into memory"
+    )]
+    stream_rom: bool,
+
+    #[arg(
+        long,
+        help = "Path to the IPS or BPS patch to be applied to the ROM when it's loaded"
+    )]
+    patch_path: Option<String>,
+
+    #[arg(
+        long,
+        default_value_t = String::from("boytacean"),
+        help = "Layout of the save files (boytacean, retroarch, mgba or folders)"
+    )]
+    save_layout: String,
+
+    #[arg(
+        long,
+        help = "Directory used for the {saves_dir} placeholder of the save file templates, defaults to the ROM directory"
+    )]
+    saves_dir: Option<String>,
+
+    #[arg(
+        long,
+        help = "Template of the battery backed RAM file path (ex: {saves_dir}/{rom_name}.srm), overrides the layout"
+    )]
+    ram_template: Option<String>,
+
+    #[arg(
+        long,
+        help = "Template of the save state file path (ex: {saves_dir}/{rom_name}/{slot}.state), overrides the layout"
+    )]
+    state_template: Option<String>,
+
+    #[arg(
+        long,
+        default_value_t = 0.0,
+        help = "Persistence (0.0 to 1.0) of the previous frame simulating the LCD ghosting, 0.0 disables it"
+    )]
+    ghosting: f32,
+
+    #[arg(
+        long,
+        default_value_t = String::from("none"),
+        help = "Overlay simulating the gaps between the LCD pixels (none, grid, dot-matrix)"
+    )]
+    overlay: String,
+
+    #[arg(
+        long,
+        default_value_t = String::from("none"),
+        help = "Upscaler of the frames (none, scale2x, scale3x), overrides the scale of the overlay"
+    )]
+    scaler: String,
+
+    #[arg(
+        long,
+        default_value_t = 4,
+        help = "Maximum number of late frames emulated to catch-up after a stall, the remaining are skipped"
+    )]
+    max_catch_up: u32,
+
+    #[arg(
+        long,
+        default_value_t = String::from("native"),
+        help = "Frame pacing on the display (native, duplicate, stretch), duplicate repeats a frame every few seconds and stretch runs slightly faster to match the display refresh rate"
+    )]
+    frame_pacing: String,
+
+    #[arg(
+        long,
+        help = "Refresh rate (in Hz) of the display used by the frame pacing, defaults to the one reported for the display"
+    )]
+    refresh_rate: Option<f64>,
+
+    #[arg(
+        long,
+        default_value_t = 44100,
+        help = "Audio sampling rate in Hz (ex: 44100, 48000), the APU output is resampled to it"
+    )]
+    sample_rate: u16,
+
+    #[arg(
+        long,
+        default_value_t = String::from("linear"),
+        help = "Audio resampler to be used (nearest, linear)"
+    )]
+    resampler: String,
+
+    #[arg(
+        long,
+        default_value_t = String::from("off"),
+        help = "Audio high-pass filter to be used, removing the DC offset (off, dmg, cgb)"
+    )]
+    high_pass: String,
+
+    #[arg(
+        long,
+        default_value_t = String::from("normal"),
+        help = "Audio handling while fast forwarding (normal, mute, pitch)"
+    )]
+    fast_forward_audio: String,
+
+    #[arg(long, default_value_t = 1.0, help = "Audio master volume multiplier")]
+    volume: f32,
+
+    #[arg(
+        long,
+        default_value_t = String::from("last-wins"),
+        help = "Handling of opposing directions pressed at the same time (block, last-wins, allow)"
+    )]
+    pad_filter: String,
+
+    #[arg(
+        long,
+        help = "Port of the GDB remote debugging server, the emulation waits for a debugger to attach"
+    )]
+    gdb_port: Option<u16>,
+
+    #[arg(
+        long,
+        help = "Port on which to host a netplay session, waiting for the peer to connect"
+    )]
+    netplay_host: Option<u16>,
+
+    #[arg(long, help = "Address (host:port) of the netplay session to be joined")]
+    netplay_connect: Option<String>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "If set the netplay session is joined as a (read-only) spectator"
+    )]
+    netplay_spectate: bool,
+
+    #[arg(
+        long,
+        default_value_t = String::from("rollback"),
+        help = "Netplay synchronization mode (lockstep, rollback)"
+    )]
+    netplay_mode: String,
+
+    #[arg(
+        long,
+        default_value_t = 2,
+        help = "Number of frames by which the netplay local inputs are delayed"
+    )]
+    netplay_delay: u32,
+
+    #[arg(
+        long,
+        help = "Path to the file where the CPU instruction trace is written, tracing can be toggled with L"
+    )]
+    trace_path: Option<String>,
+
+    #[arg(
+        long,
+        help = "Path to the Y4M file where the video is recorded, the audio is recorded to a WAV file with the same name"
+    )]
+    record_video: Option<String>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "If set the video recording is paused while fast forwarding"
+    )]
+    record_pause_fast: bool,
+
+    #[arg(
+        long,
+        help = "Path to the file where the input movie is recorded, starting from the state after the ROM load"
+    )]
+    movie_record: Option<String>,
+
+    #[arg(long, help = "Path to the input movie file to be played")]
+    movie_play: Option<String>,
+
+    #[arg(
+        long,
+        help = "Seed of the deterministic mode, in which the RTC runs on emulated time"
+    )]
+    seed: Option<u64>,
+
+    #[arg(
+        long,
+        default_value_t = String::from("default"),
+        help = "Format of the CPU instruction trace (default, doctor)"
+    )]
+    trace_format: String,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(default_value_t = String::from(DEFAULT_ROM_PATH), help = "Path to the ROM file to be loaded")]
     rom_path: String,
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Searches the memory for a hex pattern (eg: "3E ?? C9") after running the ROM
+    MemSearch {
+        #[arg(help = "Hex pattern to search for, use ? as a nibble wildcard")]
+        pattern: String,
+
+        #[arg(
+            long,
+            help = "Hex mask to be applied to both the memory and the pattern"
+        )]
+        mask: Option<String>,
+
+        #[arg(long, default_value_t = String::from("0x0000"), help = "Start address of the search")]
+        start: String,
+
+        #[arg(long, default_value_t = String::from("0xffff"), help = "End address (inclusive) of the search")]
+        end: String,
+
+        #[arg(
+            long,
+            default_value_t = 60,
+            help = "Number of frames to run before searching"
+        )]
+        frames: u32,
+
+        #[arg(
+            long,
+            help = "Path to the file where the matches are going to be written"
+        )]
+        output: Option<String>,
+    },
+
+    /// Dumps a memory region after running the ROM
+    MemDump {
+        #[arg(long, default_value_t = String::from("0xc000"), help = "Start address of the dump")]
+        start: String,
+
+        #[arg(long, default_value_t = String::from("0xdfff"), help = "End address (inclusive) of the dump")]
+        end: String,
+
+        #[arg(
+            long,
+            default_value_t = 60,
+            help = "Number of frames to run before dumping"
+        )]
+        frames: u32,
+
+        #[arg(
+            long,
+            help = "Path to the file where the raw memory is going to be written"
+        )]
+        output: Option<String>,
+    },
+
+    /// Feeds random joypad input to the ROM looking for panics and soft-locks
+    Fuzz {
+        #[arg(
+            long,
+            default_value_t = 4,
+            help = "Number of parallel fuzzing instances (one seed each)"
+        )]
+        instances: u32,
+
+        #[arg(
+            long,
+            default_value_t = 3600,
+            help = "Number of frames to run in each instance"
+        )]
+        frames: u32,
+
+        #[arg(long, default_value_t = 0, help = "Seed of the first instance")]
+        seed: u64,
+
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Use fully random input instead of human like (structured) input"
+        )]
+        random: bool,
+
+        #[arg(
+            long,
+            default_value_t = 1800,
+            help = "Frames with an unchanged screen before considering a soft-lock, 0 to disable"
+        )]
+        stall_frames: u32,
+
+        #[arg(
+            long,
+            help = "Directory where the diagnostics of failed instances are going to be written"
+        )]
+        output: Option<String>,
+    },
+
+    /// Runs a suite of test ROMs producing a compatibility report (markdown, JSON or JUnit)
+    Conformance {
+        #[arg(long, default_value_t = String::from(DEFAULT_SUITE_PATH), help = "Path to the suite file listing the test ROMs")]
+        suite: String,
+
+        #[arg(
+            long,
+            help = "Path to the report file, JSON for .json, JUnit for .xml and markdown otherwise"
+        )]
+        output: Option<String>,
+    },
+}
+
 fn run(args: Args, emulator: &mut Emulator) {
+    // in case a command has been provided runs it instead of the
+    // emulation loop, printing the error in case it fails
+    if let Some(command) = &args.command {
+        if let Err(err) = run_command(command, emulator) {
+            println!("Error: {err}");
+        }
+        return;
+    }
+
     // determines if the emulator should run in headless mode or
     // not and runs it accordingly, note that if running in headless
     // mode the number of cycles to be run may be specified
@@ -1002,6 +2531,8 @@ fn run(args: Args, emulator: &mut Emulator) {
             args.benchmark_count,
             Some(args.benchmark_cpu),
         ));
+    } else if args.headless && args.frames > 0 {
+        emulator.run_frames(args.frames);
     } else if args.headless {
         emulator.run_headless(if args.cycles > 0 {
             Some(args.cycles)
@@ -1018,6 +2549,24 @@ fn main() {
     // obtain structured values
     let args = Args::parse();
 
+    if args.build_info {
+        println!("{}", Info::build_info());
+        return;
+    }
+
+    // the conformance command runs its own systems (one per test
+    // ROM) so it's handled before any ROM is loaded
+    if let Some(Command::Conformance { suite, output }) = &args.command {
+        match run_conformance(suite, output.as_deref()) {
+            Ok(true) => return,
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                println!("Error: {err}");
+                std::process::exit(2);
+            }
+        }
+    }
+
     // in case the default ROM path is provided and the file does not
     // exist then fails gracefully
     let path = Path::new(&args.rom_path);
@@ -1047,16 +2596,53 @@ fn main() {
     game_boy.set_apu_enabled(!args.no_apu);
     game_boy.set_dma_enabled(!args.no_dma);
     game_boy.set_timer_enabled(!args.no_timer);
+    game_boy.set_compare_render(args.compare_render);
+    game_boy.set_render_mode(RenderMode::from_string(&args.render_mode).unwrap());
+    game_boy.set_audio_sampling_rate(args.sample_rate);
+    game_boy.set_audio_resampler(Resampler::from_string(&args.resampler).unwrap());
+    game_boy.set_audio_high_pass(HighPassFilter::from_string(&args.high_pass).unwrap());
+    game_boy.set_audio_master_volume(args.volume);
+    game_boy.set_pad_filter(PadFilter::from_string(&args.pad_filter).unwrap());
+    if let Some(accuracy) = &args.accuracy {
+        game_boy.set_accuracy(AccuracyProfile::from_string(accuracy).unwrap());
+    }
+    game_boy.set_startup_memory(StartupMemory::from_string(&args.startup_memory).unwrap());
+    game_boy.set_cpu_backend(CpuBackend::from_string(&args.cpu_backend).unwrap());
+    game_boy.set_auto_colorize(!args.no_colorize);
+    game_boy.set_video_filter(
+        VideoFilter::new(
+            args.ghosting,
+            VideoOverlay::from_string(&args.overlay).unwrap(),
+        )
+        .with_scaler(Scaler::from_string(&args.scaler).unwrap()),
+    );
+    game_boy.set_lcd_off_frames(true);
+    if let Some(seed) = args.seed {
+        game_boy.set_seed(seed);
+    }
     game_boy.attach_serial(device);
     game_boy
         .load(!args.no_boot && args.boot_rom_path.is_empty())
         .unwrap();
     if args.no_boot {
-        game_boy.load_boot_state();
+        match &args.model {
+            Some(model) => game_boy.load_boot_state_for(GameBoyModel::from_string(model).unwrap()),
+            None => game_boy.load_boot_state(),
+        }
     }
     if !args.boot_rom_path.is_empty() {
         game_boy.load_boot_path(&args.boot_rom_path).unwrap();
     }
+    let netplay = args.netplay_host.is_some() || args.netplay_connect.is_some();
+    if args.rewind_frames > 0
+        && !args.headless
+        && !args.benchmark
+        && args.command.is_none()
+        && !netplay
+    {
+        game_boy.enable_rewind(args.rewind_frames);
+    }
+    game_boy.set_quick_slot_count(args.quick_slots);
 
     // prints the current version of the emulator (informational message)
     println!("========= {} =========\n{}", Info::name(), game_boy);
@@ -1067,23 +2653,219 @@ fn main() {
     let options = EmulatorOptions {
         auto_mode: Some(auto_mode),
         unlimited: Some(args.unlimited),
-        features: if args.headless || args.benchmark {
+        features: if args.headless || args.benchmark || args.command.is_some() {
             Some(vec![])
         } else {
             Some(vec!["video", "audio", "no-vsync"])
         },
     };
     let mut emulator = Emulator::new(game_boy, options);
+    if args.profiler {
+        emulator.system.enable_profiler();
+        emulator.profiler = true;
+    }
+    emulator
+        .fast_forward_audio
+        .set_mode(FastForwardAudioMode::from_string(&args.fast_forward_audio).unwrap());
+    if args.stream_rom {
+        emulator.rom_load_mode = RomLoadMode::Streamed;
+    }
+    emulator.patch_path = args.patch_path.clone();
+    let mut save_paths =
+        SavePaths::from_layout(SaveLayout::from_string(&args.save_layout).unwrap());
+    save_paths.set_saves_dir(args.saves_dir.as_deref());
+    if let Some(ram_template) = &args.ram_template {
+        save_paths.set_ram_template(ram_template).unwrap();
+    }
+    if let Some(state_template) = &args.state_template {
+        save_paths.set_state_template(state_template).unwrap();
+    }
+    emulator.save_paths = save_paths;
+    if args.autosave && !args.headless && !args.benchmark && args.command.is_none() {
+        emulator.enable_autosave();
+    }
     emulator.start(SCREEN_SCALE);
     emulator.load_rom(Some(&args.rom_path)).unwrap();
     emulator.apply_cheats(&args.cheats);
+    if let Some(auto_fire) = &args.auto_fire {
+        emulator.apply_auto_fire(auto_fire);
+    }
     emulator.toggle_palette();
+    if let Some(port) = args.gdb_port {
+        emulator.start_gdb(port).unwrap();
+    }
+    emulator.system.set_sync_policy(SyncPolicy::new(
+        args.max_catch_up,
+        SyncPolicy::default().fade_ms,
+    ));
+    emulator.set_frame_pacing(
+        FramePacing::from_string(&args.frame_pacing).unwrap(),
+        args.refresh_rate,
+    );
+    let trace_format = TraceFormat::from_string(&args.trace_format).unwrap();
+    emulator.trace_format = trace_format;
+    if let Some(trace_path) = &args.trace_path {
+        emulator.start_trace(trace_path, trace_format).unwrap();
+    }
+    if let Some(movie_play) = &args.movie_play {
+        emulator.system.play_movie(movie_play).unwrap();
+    } else if let Some(movie_record) = &args.movie_record {
+        emulator.system.start_movie_recording().unwrap();
+        emulator.movie_path = Some(movie_record.clone());
+    }
+    if let Some(record_video) = &args.record_video {
+        emulator.record_pause_fast = args.record_pause_fast;
+        emulator.start_video_recording(record_video).unwrap();
+    }
+    if netplay {
+        let config = NetplayConfig {
+            sync_mode: SyncMode::from_string(&args.netplay_mode).unwrap(),
+            input_delay: args.netplay_delay,
+            ..Default::default()
+        };
+        emulator
+            .start_netplay(
+                args.netplay_host,
+                args.netplay_connect.as_deref(),
+                args.netplay_spectate,
+                config,
+            )
+            .unwrap();
+    }
+
+    let frames = if args.command.is_none() && !args.benchmark {
+        args.frames
+    } else {
+        0
+    };
+    if frames > 0 {
+        emulator.frame_limit = Some(frames);
+    }
+    let exit_screenshot = args.exit_screenshot.clone();
 
     run(args, &mut emulator);
 
+    emulator.system.set_tracer(None);
+    emulator.save_cheats();
+    emulator.save_auto_fire();
+    if let Some(netplay) = &emulator.netplay {
+        println!("Netplay session ended ({})", netplay.stats());
+    }
+
+    // saves the screenshot of the last frame (if requested) and
+    // exits with an error status in case it was not possible to save
+    // it or if the run was interrupted before the frame limit
+    if let Some(exit_screenshot) = exit_screenshot {
+        if let Err(err) = emulator.save_image(&exit_screenshot) {
+            println!("{err}");
+            emulator.stop();
+            std::process::exit(1);
+        }
+        println!("Saved screenshot into: {exit_screenshot}");
+    }
+    if frames > 0 && emulator.frame_count < frames {
+        println!(
+            "Interrupted after {} of {frames} frames",
+            emulator.frame_count
+        );
+        emulator.stop();
+        std::process::exit(2);
+    }
+
     emulator.stop();
 }
 
+fn run_command(command: &Command, emulator: &mut Emulator) -> Result<(), Error> {
+    match command {
+        Command::MemSearch {
+            pattern,
+            mask,
+            start,
+            end,
+            frames,
+            output,
+        } => {
+            let mut pattern = MemoryPattern::from_hex(pattern)?;
+            if let Some(mask) = mask {
+                pattern.apply_mask_hex(mask)?;
+            }
+            emulator.run_frames(*frames);
+            emulator.mem_search(
+                &pattern,
+                parse_address(start)?,
+                parse_address(end)?,
+                output.as_deref(),
+            )
+        }
+        Command::MemDump {
+            start,
+            end,
+            frames,
+            output,
+        } => {
+            emulator.run_frames(*frames);
+            emulator.mem_dump(
+                parse_address(start)?,
+                parse_address(end)?,
+                output.as_deref(),
+            )
+        }
+        Command::Fuzz {
+            instances,
+            frames,
+            seed,
+            random,
+            stall_frames,
+            output,
+        } => {
+            let options = FuzzOptions {
+                frames: *frames,
+                seed: *seed,
+                mode: if *random {
+                    FuzzMode::Random
+                } else {
+                    FuzzMode::Structured
+                },
+                stall_frames: *stall_frames,
+                mode_gb: Some(emulator.system.mode()),
+            };
+            emulator.fuzz(&options, *instances, output.as_deref())
+        }
+        Command::Conformance { .. } => unreachable!("conformance is run before ROM loading"),
+    }
+}
+
+/// Runs the conformance suite writing its report into the output
+/// (or to the standard output), returns if all the tests passed.
+fn run_conformance(suite: &str, output: Option<&str>) -> Result<bool, Error> {
+    let suite = ConformanceSuite::from_file(suite)?;
+    let report = suite.run(|result| println!("{result}"));
+    println!("{report}");
+    match output {
+        Some(output) => {
+            let data = match Path::new(output).extension() {
+                Some(extension) if extension.eq_ignore_ascii_case("json") => report.to_json(),
+                Some(extension) if extension.eq_ignore_ascii_case("xml") => report.to_junit(),
+                _ => report.to_markdown(),
+            };
+            write_file(output, data.as_bytes(), None)?;
+            println!("Saved conformance report into: {output}");
+        }
+        None => println!("\n{}", report.to_markdown()),
+    }
+    Ok(report.is_ok())
+}
+
+fn parse_address(value: &str) -> Result<u16, Error> {
+    let digits = value
+        .trim()
+        .trim_start_matches("0x")
+        .trim_start_matches("0X")
+        .trim_start_matches('$');
+    u16::from_str_radix(digits, 16)
+        .map_err(|_| Error::InvalidParameter(format!("Invalid address: {value}")))
+}
+
 fn build_device(device: &str) -> Result<Box<dyn SerialDevice>, Error> {
     match device {
         "null" => Ok(Box::<NullDevice>::default()),
@@ -1123,3 +2905,17 @@ fn key_to_pad(keycode: Keycode) -> Option<PadKey> {
         _ => None,
     }
 }
+
+fn button_to_pad(button: Button) -> Option<PadKey> {
+    match button {
+        Button::DPadUp => Some(PadKey::Up),
+        Button::DPadDown => Some(PadKey::Down),
+        Button::DPadLeft => Some(PadKey::Left),
+        Button::DPadRight => Some(PadKey::Right),
+        Button::Start => Some(PadKey::Start),
+        Button::Back => Some(PadKey::Select),
+        Button::A => Some(PadKey::A),
+        Button::B => Some(PadKey::B),
+        _ => None,
+    }
+}
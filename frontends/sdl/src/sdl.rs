@@ -1,8 +1,13 @@
 use sdl2::{
-    render::Canvas, rwops::RWops, surface::Surface, sys::image, ttf::Sdl2TtfContext, video::Window,
-    AudioSubsystem, EventPump, Sdl, TimerSubsystem, VideoSubsystem,
+    controller::GameController, render::Canvas, rwops::RWops, surface::Surface, sys::image,
+    ttf::Sdl2TtfContext, video::Window, AudioSubsystem, EventPump, GameControllerSubsystem, Sdl,
+    TimerSubsystem, VideoSubsystem,
 };
 
+/// The maximum number of game controllers in use, one
+/// per joypad that can be multiplexed by the SGB.
+pub const MAX_CONTROLLERS: usize = 4;
+
 /// Structure that provides the complete set of SDL Graphics
 /// and Sound syb-system ready to be used by the overall
 /// emulator infrastructure.
@@ -11,8 +16,13 @@ pub struct SdlSystem {
     pub video_subsystem: VideoSubsystem,
     pub timer_subsystem: TimerSubsystem,
     pub audio_subsystem: AudioSubsystem,
+    pub game_controller_subsystem: GameControllerSubsystem,
     pub event_pump: EventPump,
     pub ttf_context: Sdl2TtfContext,
+
+    /// The game controllers currently open, indexed by the
+    /// player (joypad) they control.
+    pub controllers: [Option<GameController>; MAX_CONTROLLERS],
 }
 
 impl SdlSystem {
@@ -33,6 +43,7 @@ impl SdlSystem {
         let video_subsystem = sdl.video().unwrap();
         let timer_subsystem = sdl.timer().unwrap();
         let audio_subsystem = sdl.audio().unwrap();
+        let game_controller_subsystem = sdl.game_controller().unwrap();
         let event_pump = sdl.event_pump().unwrap();
 
         // initializes the fonts context to be used
@@ -71,8 +82,10 @@ impl SdlSystem {
             video_subsystem,
             timer_subsystem,
             audio_subsystem,
+            game_controller_subsystem,
             event_pump,
             ttf_context,
+            controllers: Default::default(),
         }
     }
 
@@ -83,6 +96,32 @@ impl SdlSystem {
     pub fn window_mut(&mut self) -> &mut Window {
         self.canvas.window_mut()
     }
+
+    /// Opens the game controller with the provided joystick index,
+    /// assigning it to the first player without a controller and
+    /// returning that player, if any is available.
+    pub fn add_controller(&mut self, joystick_index: u32) -> Option<usize> {
+        let player = self.controllers.iter().position(|value| value.is_none())?;
+        let controller = self.game_controller_subsystem.open(joystick_index).ok()?;
+        self.controllers[player] = Some(controller);
+        Some(player)
+    }
+
+    /// Closes the game controller with the provided instance
+    /// identifier, returning the player it was assigned to.
+    pub fn remove_controller(&mut self, instance_id: u32) -> Option<usize> {
+        let player = self.controller_player(instance_id)?;
+        self.controllers[player] = None;
+        Some(player)
+    }
+
+    pub fn controller_player(&self, instance_id: u32) -> Option<usize> {
+        self.controllers.iter().position(|value| {
+            value
+                .as_ref()
+                .is_some_and(|controller| controller.instance_id() == instance_id)
+        })
+    }
 }
 
 /// Creates an SDL2 Surface structure from the provided
@@ -0,0 +1,153 @@
+//! Session management of the SDL frontend, auto-saving the state of
+//! the emulation on exit and on crashes (panics) so that it can be
+//! resumed on the next launch of the same ROM.
+
+use boytacean::{gb::GameBoy, state::StateManager, storage::SavePaths};
+use boytacean_common::{
+    error::Error,
+    util::{read_file, write_file},
+};
+use std::{
+    fs::{create_dir_all, metadata},
+    panic,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// The interval between the in-memory snapshots of the emulation,
+/// the latest snapshot is the one written in case of a crash.
+pub const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The extension of the auto-save state file, stored next to
+/// the battery backed RAM file of the ROM.
+const AUTOSAVE_EXTENSION: &str = "auto.bosc";
+
+/// The latest snapshot of the emulation, shared with the panic
+/// hook, together with the path of the auto-save file.
+#[derive(Default)]
+struct Snapshot {
+    path: Option<PathBuf>,
+    data: Option<Vec<u8>>,
+}
+
+/// The auto-save session of the currently loaded ROM, keeping an
+/// in-memory snapshot of the emulation that is stored in case of
+/// a crash, as the system is not in a consistent state while
+/// unwinding from a panic.
+pub struct Session {
+    snapshot: Arc<Mutex<Snapshot>>,
+    last_snapshot: Instant,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self {
+            snapshot: Arc::new(Mutex::new(Snapshot::default())),
+            last_snapshot: Instant::now(),
+        }
+    }
+
+    /// Obtains the path of the auto-save file of the ROM, derived
+    /// from the path of its battery backed RAM file.
+    pub fn autosave_path(save_paths: &SavePaths, rom_path: &str) -> PathBuf {
+        save_paths
+            .ram_path(rom_path)
+            .with_extension(AUTOSAVE_EXTENSION)
+    }
+
+    /// Installs a panic hook that writes the latest snapshot into
+    /// the auto-save file, chaining the previously installed hook.
+    pub fn install_panic_hook(&self) {
+        let snapshot = self.snapshot.clone();
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            // the lock is only tried as the panic may have been
+            // raised while it was being held (avoids a deadlock)
+            if let Ok(snapshot) = snapshot.try_lock() {
+                if let (Some(path), Some(data)) = (&snapshot.path, &snapshot.data) {
+                    match Self::write(path, data) {
+                        Ok(()) => eprintln!("Auto-saved state into: {}", path.display()),
+                        Err(err) => eprintln!("Failed to auto-save state: {err}"),
+                    }
+                }
+            }
+            previous(info);
+        }));
+    }
+
+    /// Starts the session of a (newly loaded) ROM, discarding the
+    /// snapshot of the previous one, returning the modification time
+    /// of the existing auto-save file (if any), that may be resumed.
+    pub fn start(&mut self, path: PathBuf) -> Option<SystemTime> {
+        let modified = metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let mut snapshot = self.snapshot.lock().unwrap();
+        snapshot.path = Some(path);
+        snapshot.data = None;
+        self.last_snapshot = Instant::now();
+        modified
+    }
+
+    pub fn path(&self) -> Option<PathBuf> {
+        self.snapshot.lock().unwrap().path.clone()
+    }
+
+    /// Takes a new in-memory snapshot of the emulation in case the
+    /// snapshot interval has elapsed since the previous one.
+    pub fn update(&mut self, gb: &mut GameBoy) -> Result<(), Error> {
+        if self.last_snapshot.elapsed() < SNAPSHOT_INTERVAL {
+            return Ok(());
+        }
+        self.last_snapshot = Instant::now();
+        let data = StateManager::snapshot(gb)?;
+        self.snapshot.lock().unwrap().data = Some(data);
+        Ok(())
+    }
+
+    /// Saves the current state of the emulation into the auto-save
+    /// file, returning its path.
+    pub fn save(&mut self, gb: &mut GameBoy) -> Result<PathBuf, Error> {
+        let path = self
+            .path()
+            .ok_or_else(|| Error::CustomError(String::from("No auto-save session started")))?;
+        let data = StateManager::snapshot(gb)?;
+        Self::write(&path, &data)?;
+        self.snapshot.lock().unwrap().data = Some(data);
+        Ok(path)
+    }
+
+    /// Resumes the emulation from the auto-save file, validating
+    /// that it belongs to the loaded ROM, returning its path.
+    pub fn resume(&mut self, gb: &mut GameBoy) -> Result<PathBuf, Error> {
+        let path = self
+            .path()
+            .ok_or_else(|| Error::CustomError(String::from("No auto-save session started")))?;
+        let data = read_file(&path.to_string_lossy())?;
+        let title = gb.rom_i().title();
+        if StateManager::validate(&data, Some(title.clone())).is_err() {
+            return Err(Error::CustomError(format!(
+                "Auto-saved state is not for the loaded ROM ({title})"
+            )));
+        }
+        StateManager::load(&data, gb, None, None)?;
+        self.last_snapshot = Instant::now();
+        Ok(path)
+    }
+
+    fn write(path: &Path, data: &[u8]) -> Result<(), Error> {
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent).map_err(|_| {
+                Error::IoError(format!("Failed to create directory: {}", parent.display()))
+            })?;
+        }
+        write_file(&path.to_string_lossy(), data, Some(true))
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
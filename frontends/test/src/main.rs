@@ -0,0 +1,98 @@
+use boytacean::{
+    conformance::{ConformanceResult, ConformanceSuite, DEFAULT_CYCLES},
+    gb::GameBoyMode,
+    sm83::Sm83Suite,
+};
+use boytacean_common::{error::Error, util::write_file};
+use clap::Parser;
+use std::{path::Path, process::ExitCode};
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    #[arg(help = "Path to the suite file or to the directory with the test ROMs")]
+    path: String,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Run SM83 single instruction tests (JSON file or directory of them) instead of ROMs"
+    )]
+    sm83: bool,
+
+    #[arg(
+        long,
+        value_parser = ["dmg", "cgb", "sgb"],
+        help = "Mode to run the ROMs of a directory on, defaults to CGB for .gbc files"
+    )]
+    mode: Option<String>,
+
+    #[arg(long, default_value_t = DEFAULT_CYCLES, help = "Number of cycles to run the ROMs of a directory for")]
+    cycles: u64,
+
+    #[arg(
+        long,
+        default_value_t = String::from("text"),
+        value_parser = ["text", "markdown", "json", "junit"],
+        help = "Format of the report"
+    )]
+    format: String,
+
+    #[arg(
+        long,
+        help = "Path to the report file, the report is printed to the standard output otherwise"
+    )]
+    output: Option<String>,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Runs the suite (file or directory) writing its report in the
+/// requested format, returns if all the tests passed.
+fn run(args: &Args) -> Result<bool, Error> {
+    // the progress is only printed for the text format or when the
+    // report goes to a file, keeping the standard output parsable
+    let verbose = args.format == "text" || args.output.is_some();
+    let on_result = |result: &ConformanceResult| {
+        if verbose {
+            println!("{result}")
+        }
+    };
+
+    let report = if args.sm83 {
+        Sm83Suite::from_path(&args.path)?.run(on_result)
+    } else if Path::new(&args.path).is_dir() {
+        let mode = args.mode.as_deref().map(GameBoyMode::from_string);
+        ConformanceSuite::from_dir(&args.path, mode, args.cycles)?.run(on_result)
+    } else {
+        ConformanceSuite::from_file(&args.path)?.run(on_result)
+    };
+
+    let data = match args.format.as_str() {
+        "markdown" => report.to_markdown(),
+        "json" => report.to_json(),
+        "junit" => report.to_junit(),
+        _ => format!("{report}\n"),
+    };
+    match &args.output {
+        Some(output) => {
+            write_file(output, data.as_bytes(), None)?;
+            println!("{report}");
+            println!("Saved report into: {output}");
+        }
+        None if args.format == "text" => print!("{data}"),
+        None => println!("{data}"),
+    }
+
+    Ok(report.is_ok())
+}
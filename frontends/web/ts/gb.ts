@@ -133,12 +133,6 @@ export class GameboyEmulator extends EmulatorLogic implements Emulator {
 
     private paletteIndex = 0;
 
-    /**
-     * Number of pending CPU cycles from the previous tick.
-     * This is used to keep track of the overflow cycles.
-     */
-    private pending = 0;
-
     /**
      * The frequency at which the battery backed RAM is going
      * to be flushed to the `localStorage`.
@@ -200,22 +194,21 @@ export class GameboyEmulator extends EmulatorLogic implements Emulator {
         // returns the control flow immediately (not possible to tick)
         if (!this.gameBoy) return;
 
-        // uses the Game Boy multiplier to re-calculate the number
-        // of cycles to be used for the tick
-        const tickCycles = params.cycles * (this.gameBoy?.multiplier() ?? 1);
-
         // calculates the target cycles for clocking in the current
-        // tick operation, this is the ideal value and the concrete
-        // execution should not match this value
-        const targetCycles = tickCycles - this.pending;
-
-        // clocks the system by the target number of cycles (deducted
-        // by the carryover cycles) and then in case there's at least
-        // a frame to be processed triggers the frame event, allowing
-        // the deferred retrieval of the frame buffer
-        this.clockFrame = this.gameBoy.clocks_frame_buffer(
-            Math.max(targetCycles, 0)
+        // tick operation (counted at normal speed, independently of
+        // the Game Boy speed), in case the host stalled (eg: GC pause)
+        // the sync policy of the emulator limits the cycles to catch-up,
+        // skipping the remaining ones
+        const targetCycles = Number(
+            this.gameBoy.recover_sync_cycles(BigInt(params.cycles))
         );
+
+        // runs the system for the target number of cycles (with the
+        // carryover cycles of the previous tick being handled by the
+        // emulator) and then in case there's at least a frame to be
+        // processed triggers the frame event, allowing the deferred
+        // retrieval of the frame buffer
+        this.clockFrame = this.gameBoy.run_for_cycles(targetCycles);
         const executedCycles = Number(this.clockFrame.cycles);
         if (this.clockFrame.frames > 0) {
             this.trigger("frame", { count: this.clockFrame.frames });
@@ -241,10 +234,6 @@ export class GameboyEmulator extends EmulatorLogic implements Emulator {
         // operation has been performed and providing some information
         // about the number of cycles that have been executed
         this.trigger("tick", { cycles: executedCycles });
-
-        // calculates the new number of pending (overflow) cycles
-        // that are going to be added to the next iteration
-        this.pending = executedCycles - targetCycles;
     }
 
     async hardReset() {
@@ -574,7 +563,7 @@ export class GameboyEmulator extends EmulatorLogic implements Emulator {
     }
 
     get audioBuffer(): Float32Array[] {
-        const internalBuffer = this.gameBoy?.audio_buffer_eager(true) ?? [];
+        const internalBuffer = this.gameBoy?.audio_frame_eager() ?? [];
         const leftStream = new Float32Array(internalBuffer.length / 2);
         const rightStream = new Float32Array(internalBuffer.length / 2);
         for (let index = 0; index < internalBuffer.length; index += 2) {
@@ -584,6 +573,19 @@ export class GameboyEmulator extends EmulatorLogic implements Emulator {
         return [leftStream, rightStream];
     }
 
+    /**
+     * Fills the provided buffer with interleaved stereo samples
+     * resampled to the output sampling rate, meant to be called
+     * from the processing callback of an audio worklet.
+     *
+     * @param buffer The buffer to be filled with the samples.
+     * @param frames The number of stereo frames to be filled.
+     * @returns The number of frames filled with audio.
+     */
+    audioFill(buffer: Float32Array, frames: number): number {
+        return this.gameBoy?.audio_fill(buffer, frames) ?? 0;
+    }
+
     get romInfo(): RomInfo {
         return {
             name: this.romName ?? undefined,
@@ -880,6 +882,10 @@ export class GameboyEmulator extends EmulatorLogic implements Emulator {
         this.trigger("speed", { data: speed });
     }
 
+    onPaletteChange(obj: boolean, index: number, palette: Uint8Array) {
+        this.trigger("palette", { obj: obj, index: index, palette: palette });
+    }
+
     onLoggerDevice(data: Uint8Array) {
         this.trigger("logger", { data: data });
     }
@@ -896,7 +902,7 @@ export class GameboyEmulator extends EmulatorLogic implements Emulator {
         if (!this.gameBoy || !this.cartridge || !window.localStorage) return;
         if (!this.cartridge.has_battery()) return;
         const title = this.cartridge.title();
-        const ramData = this.gameBoy.ram_data_eager();
+        const ramData = this.gameBoy.save_data_eager();
         const ramDataB64 = bufferToBase64(ramData);
         localStorage.setItem(title, ramDataB64);
     }
@@ -911,7 +917,7 @@ export class GameboyEmulator extends EmulatorLogic implements Emulator {
         const ramDataB64 = localStorage.getItem(this.cartridge.title());
         if (!ramDataB64) return;
         const ramData = base64ToBuffer(ramDataB64);
-        this.gameBoy.set_ram_data(ramData);
+        this.gameBoy.set_save_data(ramData);
     }
 
     private storeSettings() {
@@ -960,6 +966,7 @@ declare global {
         emulator: GameboyEmulator;
         panic: (message: string) => void;
         speedCallback: (speed: GameBoySpeed) => void;
+        paletteCallback: (obj: boolean, index: number, palette: Uint8Array) => void;
         loggerCallback: (data: Uint8Array) => void;
         printerCallback: (imageBuffer: Uint8Array) => void;
         rumbleCallback: (active: boolean) => void;
@@ -978,6 +985,10 @@ window.speedCallback = (speed: GameBoySpeed) => {
     window.emulator.onSpeedSwitch(speed);
 };
 
+window.paletteCallback = (obj: boolean, index: number, palette: Uint8Array) => {
+    window.emulator.onPaletteChange(obj, index, palette);
+};
+
 window.loggerCallback = (data: Uint8Array) => {
     window.emulator.onLoggerDevice(data);
 };
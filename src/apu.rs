@@ -1,24 +1,34 @@
 //! APU (Audio Processing Unit) functions and structures.
 
-use std::{collections::VecDeque, io::Cursor};
+use std::{
+    collections::VecDeque,
+    fmt::{self, Display, Formatter},
+    io::Cursor,
+};
 
 use boytacean_common::{
     data::{
-        read_i16, read_i32, read_into, read_u16, read_u8, write_bytes, write_i16, write_i32,
-        write_u16, write_u8,
+        read_i16, read_i32, read_into, read_u16, read_u32, read_u8, write_bytes, write_i16,
+        write_i32, write_u16, write_u32, write_u8,
     },
     error::Error,
 };
 
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
 use crate::{
+    apu_thread::{ApuCommand, ApuLog},
     consts::{
         NR10_ADDR, NR11_ADDR, NR12_ADDR, NR13_ADDR, NR14_ADDR, NR20_ADDR, NR21_ADDR, NR22_ADDR,
         NR23_ADDR, NR24_ADDR, NR30_ADDR, NR31_ADDR, NR32_ADDR, NR33_ADDR, NR34_ADDR, NR40_ADDR,
-        NR41_ADDR, NR42_ADDR, NR43_ADDR, NR44_ADDR, NR50_ADDR, NR51_ADDR, NR52_ADDR,
+        NR41_ADDR, NR42_ADDR, NR43_ADDR, NR44_ADDR, NR50_ADDR, NR51_ADDR, NR52_ADDR, WAVE_RAM_END,
+        WAVE_RAM_START,
     },
-    gb::GameBoy,
+    gb::{ConfigChange, ConfigListener, GameBoy},
+    io::{Io, IoComponent, IoRegister},
     mmu::BusComponent,
-    state::{StateComponent, StateFormat},
+    state::{StateComponent, StateFormat, StateMigration},
     warnln,
 };
 
@@ -31,6 +41,19 @@ const DUTY_TABLE: [[u8; 8]; 4] = [
 
 const CH4_DIVISORS: [u8; 8] = [8, 16, 32, 48, 64, 80, 96, 112];
 
+/// The maximum value of an audio sample, the sum of
+/// the maximum output of the four channels.
+pub const SAMPLE_MAX: u8 = 60;
+
+/// The value around which the audio samples are centered
+/// when the high-pass filter is enabled.
+pub const SAMPLE_CENTER: u8 = SAMPLE_MAX / 2;
+
+/// The maximum number of cycles accumulated in batch mode before
+/// they're processed, bounding the latency of the audio samples.
+pub const BATCH_CYCLES: u32 = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Channel {
     Ch1,
     Ch2,
@@ -38,6 +61,428 @@ pub enum Channel {
     Ch4,
 }
 
+/// The strategy used to resample the APU output (running at
+/// the CPU clock frequency) into the audio sampling rate.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Resampler {
+    /// Takes the APU output at the sampling instant, the
+    /// cheapest option but prone to aliasing.
+    Nearest,
+
+    /// Linearly interpolates the APU output between the
+    /// clock steps surrounding the sampling instant.
+    #[default]
+    Linear,
+}
+
+impl Resampler {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Resampler::Nearest => "Nearest",
+            Resampler::Linear => "Linear",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "nearest" => Ok(Resampler::Nearest),
+            "linear" => Ok(Resampler::Linear),
+            _ => Err(Error::CustomError(format!("Invalid resampler: {value}"))),
+        }
+    }
+}
+
+impl Display for Resampler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// The high-pass filter applied to the audio output, modeling
+/// the capacitor of the hardware that removes the DC offset.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HighPassFilter {
+    /// No filtering, the output has a DC offset (legacy).
+    #[default]
+    Off,
+
+    /// The filter of the DMG (original Game Boy).
+    Dmg,
+
+    /// The filter of the CGB (Game Boy Color), with
+    /// a faster charging capacitor.
+    Cgb,
+}
+
+impl HighPassFilter {
+    pub fn description(&self) -> &'static str {
+        match self {
+            HighPassFilter::Off => "Off",
+            HighPassFilter::Dmg => "DMG",
+            HighPassFilter::Cgb => "CGB",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "off" => Ok(HighPassFilter::Off),
+            "dmg" => Ok(HighPassFilter::Dmg),
+            "cgb" => Ok(HighPassFilter::Cgb),
+            _ => Err(Error::CustomError(format!(
+                "Invalid high-pass filter: {value}"
+            ))),
+        }
+    }
+
+    /// The factor by which the capacitor charge is kept
+    /// on each CPU cycle (at the base clock frequency).
+    pub fn charge_factor(&self) -> f64 {
+        match self {
+            HighPassFilter::Off => 0.0,
+            HighPassFilter::Dmg => 0.999958,
+            HighPassFilter::Cgb => 0.998943,
+        }
+    }
+}
+
+impl Display for HighPassFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// The way the audio is handled while the emulation runs faster
+/// than real-time (fast-forward).
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FastForwardAudioMode {
+    /// All the samples are output, the audio is sped up (higher
+    /// pitch) or dropped by the frontend.
+    #[default]
+    Normal,
+
+    /// The audio is replaced by silence (with real-time length).
+    Mute,
+
+    /// The pitch is kept by outputting only one of every N grains
+    /// of audio (for a speed of N), crossfading the grains.
+    Pitch,
+}
+
+impl FastForwardAudioMode {
+    pub fn description(&self) -> &'static str {
+        match self {
+            FastForwardAudioMode::Normal => "Normal",
+            FastForwardAudioMode::Mute => "Mute",
+            FastForwardAudioMode::Pitch => "Pitch",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "normal" => Ok(FastForwardAudioMode::Normal),
+            "mute" => Ok(FastForwardAudioMode::Mute),
+            "pitch" => Ok(FastForwardAudioMode::Pitch),
+            _ => Err(Error::CustomError(format!(
+                "Invalid fast-forward audio mode: {value}"
+            ))),
+        }
+    }
+}
+
+impl Display for FastForwardAudioMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// Processes the (interleaved) audio samples produced while under
+/// fast-forward, according to the [`FastForwardAudioMode`], reducing
+/// them to the length they would have if running in real-time.
+///
+/// The processing is done in a streaming fashion so that the samples
+/// can be provided in chunks of any size (eg: per frame or per tick).
+pub struct FastForwardAudio {
+    mode: FastForwardAudioMode,
+    channels: u8,
+
+    /// The length (in sample frames) of a grain of audio.
+    grain_length: u32,
+
+    /// The length (in sample frames) of the crossfade between
+    /// consecutive output grains.
+    fade_length: u32,
+
+    /// The index of the current grain, counting from the
+    /// last output one.
+    grain_index: u32,
+
+    /// The position (in sample frames) within the current grain.
+    grain_position: u32,
+
+    /// The samples that followed the last output grain, to be
+    /// faded out at the start of the next output grain.
+    tail: Vec<u8>,
+
+    /// The last output sample frame, repeated when muted.
+    last: Vec<u8>,
+
+    /// Fractional number of sample frames of silence pending to be
+    /// output when muted, as the input may not be divisible by N.
+    mute_error: u32,
+}
+
+impl FastForwardAudio {
+    pub fn new(mode: FastForwardAudioMode, sampling_rate: u16, channels: u8) -> Self {
+        // grains of 20ms provide a good trade-off between the
+        // preservation of the pitch and the choppiness of the audio
+        let grain_length = (sampling_rate as u32 / 50).max(4);
+        Self {
+            mode,
+            channels: channels.max(1),
+            grain_length,
+            fade_length: grain_length / 4,
+            grain_index: 0,
+            grain_position: 0,
+            tail: vec![],
+            last: vec![0; channels.max(1) as usize],
+            mute_error: 0,
+        }
+    }
+
+    pub fn mode(&self) -> FastForwardAudioMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, value: FastForwardAudioMode) {
+        self.mode = value;
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        self.grain_index = 0;
+        self.grain_position = 0;
+        self.tail.clear();
+        self.mute_error = 0;
+    }
+
+    /// Processes the provided samples (in place) for the given
+    /// speed multiplier, a speed of 1 (or less) leaves the samples
+    /// untouched resetting the internal state.
+    pub fn process(&mut self, samples: &mut Vec<u8>, speed: u32) {
+        let channels = self.channels as usize;
+        if let Some(last) = samples.rchunks_exact(channels).next() {
+            if speed <= 1 || self.mode == FastForwardAudioMode::Normal {
+                self.last.copy_from_slice(last);
+            }
+        }
+        if speed <= 1 || self.mode == FastForwardAudioMode::Normal {
+            self.reset();
+            return;
+        }
+        match self.mode {
+            FastForwardAudioMode::Normal => (),
+            FastForwardAudioMode::Mute => {
+                let frames = samples.len() / channels + self.mute_error as usize;
+                self.mute_error = (frames % speed as usize) as u32;
+                samples.clear();
+                for _ in 0..frames / speed as usize {
+                    samples.extend_from_slice(&self.last);
+                }
+            }
+            FastForwardAudioMode::Pitch => {
+                let mut output = Vec::with_capacity(samples.len() / speed as usize + channels);
+                for frame in samples.chunks_exact(channels) {
+                    if self.grain_index == 0 {
+                        // output grain, with its start crossfaded from the
+                        // continuation of the previous output grain
+                        if self.grain_position < self.fade_length && !self.tail.is_empty() {
+                            let offset = self.grain_position as usize * channels;
+                            let gain = self.grain_position as f32 / self.fade_length as f32;
+                            for (index, sample) in frame.iter().enumerate() {
+                                let previous = self.tail[offset + index] as f32;
+                                output
+                                    .push((previous + (*sample as f32 - previous) * gain).round()
+                                        as u8);
+                            }
+                        } else {
+                            output.extend_from_slice(frame);
+                        }
+                    } else if self.grain_index == 1 && self.grain_position < self.fade_length {
+                        if self.grain_position == 0 {
+                            self.tail.clear();
+                        }
+                        self.tail.extend_from_slice(frame);
+                    }
+                    self.grain_position += 1;
+                    if self.grain_position == self.grain_length {
+                        self.grain_position = 0;
+                        self.grain_index = (self.grain_index + 1) % speed;
+                    }
+                }
+                if let Some(last) = output.rchunks_exact(channels).next() {
+                    self.last.copy_from_slice(last);
+                }
+                *samples = output;
+            }
+        }
+    }
+}
+
+/// Pull based stream of the audio produced by the emulator, meant
+/// to be consumed from a real-time audio callback (eg: an AudioWorklet)
+/// as interleaved stereo `f32` samples at the output sampling rate.
+///
+/// The audio is resampled (linear interpolation) with a ratio slightly
+/// adjusted according to the amount of buffered audio, so that the
+/// latency is kept around the target in spite of the drift between
+/// the emulation and the output clocks. On underrun the last sample
+/// is faded out to silence, avoiding audible clicks.
+pub struct AudioStream {
+    input_rate: u32,
+    output_rate: u32,
+
+    /// The buffered (stereo) input sample frames.
+    buffer: VecDeque<[f32; 2]>,
+
+    /// The fractional position of the output between the first
+    /// and the second buffered input sample frames.
+    position: f64,
+
+    /// The target and the maximum number of buffered input sample
+    /// frames, the oldest ones are dropped beyond the maximum.
+    target: usize,
+    capacity: usize,
+
+    /// The last output sample frame, faded out on underrun.
+    last: [f32; 2],
+
+    underruns: u64,
+    dropped: u64,
+}
+
+impl AudioStream {
+    /// The maximum adjustment of the resampling ratio (0.5%) used
+    /// to keep the buffered audio around the target latency.
+    pub const MAX_ADJUST: f64 = 0.005;
+
+    /// The gain applied to the last sample for each output sample
+    /// frame on underrun, fading it out in a few milliseconds.
+    const UNDERRUN_FADE: f32 = 0.995;
+
+    /// Creates a stream resampling from the input to the output rate,
+    /// with the provided target latency (in milliseconds) of buffered
+    /// audio, up to four times the latency is buffered.
+    pub fn new(input_rate: u32, output_rate: u32, latency_ms: u32) -> Self {
+        let target = (input_rate as usize * latency_ms.max(1) as usize / 1000).max(2);
+        Self {
+            input_rate: input_rate.max(1),
+            output_rate: output_rate.max(1),
+            buffer: VecDeque::with_capacity(target * 4),
+            position: 0.0,
+            target,
+            capacity: target * 4,
+            last: [0.0; 2],
+            underruns: 0,
+            dropped: 0,
+        }
+    }
+
+    pub fn input_rate(&self) -> u32 {
+        self.input_rate
+    }
+
+    pub fn set_input_rate(&mut self, value: u32) {
+        self.input_rate = value.max(1);
+    }
+
+    pub fn output_rate(&self) -> u32 {
+        self.output_rate
+    }
+
+    /// The number of input sample frames currently buffered.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// The number of times the output ran out of buffered audio.
+    pub fn underruns(&self) -> u64 {
+        self.underruns
+    }
+
+    /// The number of input sample frames dropped as the buffer
+    /// was full (the output is not being pulled fast enough).
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.position = 0.0;
+    }
+
+    /// Pushes the provided (interleaved) samples, as produced by
+    /// the APU with the given number of channels, into the stream,
+    /// returning the number of sample frames dropped (if any).
+    pub fn push(&mut self, samples: &[u8], channels: u8) -> usize {
+        let to_f32 = |sample: u8| (sample as f32 - SAMPLE_CENTER as f32) / SAMPLE_CENTER as f32;
+        let channels = channels.max(1) as usize;
+        for frame in samples.chunks_exact(channels) {
+            let left = to_f32(frame[0]);
+            let right = to_f32(frame[channels - 1]);
+            self.buffer.push_back([left, right]);
+        }
+        let dropped = self.buffer.len().saturating_sub(self.capacity);
+        if dropped > 0 {
+            self.buffer.drain(..dropped);
+            self.position = 0.0;
+            self.dropped += dropped as u64;
+        }
+        dropped
+    }
+
+    /// Fills the provided buffer with `frames` (interleaved stereo)
+    /// sample frames, returning the number of them that come from
+    /// buffered audio, the remaining being the underrun fade out.
+    pub fn fill(&mut self, buffer: &mut [f32], frames: usize) -> usize {
+        let frames = frames.min(buffer.len() / 2);
+        let adjust = ((self.buffer.len() as f64 - self.target as f64) / self.target as f64
+            * Self::MAX_ADJUST)
+            .clamp(-Self::MAX_ADJUST, Self::MAX_ADJUST);
+        let ratio = self.input_rate as f64 / self.output_rate as f64 * (1.0 + adjust);
+
+        let mut filled = 0;
+        for output in buffer[..frames * 2].chunks_exact_mut(2) {
+            if self.buffer.len() < 2 {
+                for (sample, last) in output.iter_mut().zip(self.last.iter_mut()) {
+                    *last *= Self::UNDERRUN_FADE;
+                    *sample = *last;
+                }
+                continue;
+            }
+            let weight = self.position as f32;
+            for (channel, sample) in output.iter_mut().enumerate() {
+                let current = self.buffer[0][channel];
+                *sample = current + (self.buffer[1][channel] - current) * weight;
+                self.last[channel] = *sample;
+            }
+            self.position += ratio;
+            while self.position >= 1.0 && self.buffer.len() >= 2 {
+                self.buffer.pop_front();
+                self.position -= 1.0;
+            }
+            filled += 1;
+        }
+        if filled < frames {
+            self.underruns += 1;
+        }
+        filled
+    }
+}
+
+#[derive(Clone)]
 pub struct Apu {
     ch1_timer: i16,
     ch1_sequence: u8,
@@ -130,14 +575,67 @@ pub struct Apu {
     sequencer: u16,
     sequencer_step: u8,
     output_timer: i16,
+
+    /// The fractional part (in sampling rate units) of the number
+    /// of cycles between samples, accumulated so that the average
+    /// sampling rate is exact and the audio is correctly pitched.
+    output_error: u32,
+
+    resampler: Resampler,
+
+    high_pass: HighPassFilter,
+
+    /// The charge of the high-pass filter capacitor and the factor by
+    /// which it's kept between samples (for the current sampling rate).
+    high_pass_charge: [f32; 2],
+    high_pass_factor: f32,
+
+    /// The volume multiplier applied to the audio output.
+    master_volume: f32,
+
     audio_buffer: VecDeque<u8>,
     audio_buffer_max: usize,
 
+    /// Back buffer of the per-frame audio double buffer, stores
+    /// the samples generated for the frame currently being
+    /// emulated (not yet complete).
+    audio_frame: Vec<u8>,
+
+    /// Front buffer of the per-frame audio double buffer, stores
+    /// the samples of the completed frames that have not yet been
+    /// taken by the frontend, meant to be swapped out atomically.
+    audio_frame_ready: Vec<u8>,
+
+    /// The number of completed audio frames (swaps) since reset,
+    /// can be used to detect new audio frame availability.
+    audio_frame_index: u16,
+
+    /// The number of APU cycles elapsed since the last audio
+    /// frame swap, used to swap frames in case no video frame
+    /// is being generated (eg: LCD is off).
+    audio_frame_cycles: u32,
+
+    /// If the samples are generated in batches, with the clock cycles
+    /// accumulated and only processed once enough of them are pending
+    /// or before the registers are written.
+    batch: bool,
+
+    /// The number of cycles clocked but not yet processed, always
+    /// zero outside of the batch mode.
+    batch_cycles: u32,
+
     clock_freq: u32,
+
+    /// The log of the clock steps and register writes, only set when
+    /// the audio is being synthesized by an APU thread, in which case
+    /// this APU only keeps its registers (and timers) up-to-date.
+    log: Option<ApuLog>,
 }
 
 impl Apu {
     pub fn new(sampling_rate: u16, channels: u8, buffer_size: f32, clock_freq: u32) -> Self {
+        let audio_buffer_max = (sampling_rate as f32 * buffer_size) as usize * channels as usize;
+        let frame_capacity = Self::frame_capacity(sampling_rate, channels, audio_buffer_max);
         Self {
             ch1_timer: 0,
             ch1_sequence: 0,
@@ -219,11 +717,24 @@ impl Apu {
             sequencer: 0,
             sequencer_step: 0,
             output_timer: 0,
+            output_error: 0,
+            resampler: Resampler::default(),
+            high_pass: HighPassFilter::default(),
+            high_pass_charge: [0.0; 2],
+            high_pass_factor: 0.0,
+            master_volume: 1.0,
             audio_buffer: VecDeque::with_capacity(
                 (sampling_rate as f32 * buffer_size) as usize * channels as usize,
             ),
-            audio_buffer_max: (sampling_rate as f32 * buffer_size) as usize * channels as usize,
+            audio_buffer_max,
+            audio_frame: Vec::with_capacity(frame_capacity),
+            audio_frame_ready: Vec::with_capacity(frame_capacity),
+            audio_frame_index: 0,
+            audio_frame_cycles: 0,
+            batch: false,
+            batch_cycles: 0,
             clock_freq,
+            log: None,
         }
     }
 
@@ -298,73 +809,204 @@ impl Apu {
         self.sequencer = 0;
         self.sequencer_step = 0;
         self.output_timer = 0;
+        self.output_error = 0;
+        self.high_pass_charge = [0.0; 2];
+        self.batch_cycles = 0;
 
-        self.clear_audio_buffer()
+        self.clear_audio_buffer();
+        self.clear_audio_frame();
+        self.log_sync();
     }
 
     pub fn clock(&mut self, cycles: u16) {
+        self.audio_frame_cycles += cycles as u32;
+
+        if let Some(log) = self.log.as_mut() {
+            log.clock(cycles);
+        }
+
         if !self.sound_enabled {
             return;
         }
 
+        // in batch mode the cycles are only accumulated, to be processed
+        // at once when enough of them are pending (or the registers are
+        // accessed, with the CPU flushing them before reading any of the
+        // registers), the APU thread log requires the step by step clock
+        if self.batch && self.log.is_none() {
+            self.batch_cycles += cycles as u32;
+            if self.batch_cycles >= BATCH_CYCLES {
+                self.flush_batch();
+            }
+            return;
+        }
+
         self.sequencer += cycles;
         if self.sequencer >= 8192 {
-            // each of these steps runs at 512/8 Hz = 64Hz,
-            // meaning a complete loop runs at 512 Hz
-            match self.sequencer_step {
-                0 => {
-                    self.tick_length_all();
-                }
-                1 => (),
-                2 => {
-                    self.tick_ch1_sweep();
-                    self.tick_length_all();
-                }
-                3 => (),
-                4 => {
-                    self.tick_length_all();
-                }
-                5 => (),
-                6 => {
-                    self.tick_ch1_sweep();
-                    self.tick_length_all();
-                }
-                7 => {
-                    self.tick_envelope_all();
-                }
-                _ => (),
-            }
+            self.tick_sequencer();
+        }
 
-            self.sequencer -= 8192;
-            self.sequencer_step = (self.sequencer_step + 1) & 7;
+        // the samples are synthesized by the APU thread replaying
+        // the log, only the frame sequencer has to run here as it
+        // drives the channel status visible in NR52
+        if self.log.is_some() {
+            return;
         }
 
+        let previous = match self.resampler {
+            Resampler::Nearest => (0, 0),
+            Resampler::Linear => (self.output_left(), self.output_right()),
+        };
+
         self.tick_ch_all(cycles);
 
         self.output_timer = self.output_timer.saturating_sub(cycles as i16);
         if self.output_timer <= 0 {
-            // verifies if we've reached the maximum allowed size for the
-            // audio buffer and if that's the case an item is removed from
-            // the buffer (avoiding overflow) and then then the new audio
-            // volume item is added to the queue
-            if self.audio_buffer.len() >= self.audio_buffer_max {
-                for _ in 0..self.channels {
-                    self.audio_buffer.pop_front();
+            let (left, right) = match self.resampler {
+                Resampler::Nearest => (self.output_left(), self.output_right()),
+                Resampler::Linear => {
+                    // the sampling instant is the number of cycles that
+                    // the timer overshot before the end of this step
+                    let overshoot = (-self.output_timer).min(cycles as i16) as u32;
+                    let interpolate = |previous: u8, current: u8| {
+                        ((previous as u32 * overshoot
+                            + current as u32 * (cycles as u32 - overshoot))
+                            / (cycles as u32).max(1)) as u8
+                    };
+                    (
+                        interpolate(previous.0, self.output_left()),
+                        interpolate(previous.1, self.output_right()),
+                    )
                 }
+            };
+            self.push_sample(left, right);
+        }
+    }
+
+    /// Clocks the APU by the provided number of cycles at once,
+    /// advancing each of the channels by the number of periods
+    /// elapsed (instead of one step per clock) and generating the
+    /// samples at their exact sampling instants.
+    ///
+    /// The result is the same regardless of how the cycles are
+    /// split between calls, as the processing stops at every
+    /// sample and frame sequencer step, meaning that the resampler
+    /// is not needed (samples are never taken between steps).
+    pub fn clock_batch(&mut self, cycles: u32) {
+        if !self.sound_enabled {
+            return;
+        }
+
+        let mut remaining = cycles;
+        while remaining > 0 {
+            let sequencer = 8192 - self.sequencer as u32;
+            let output = self.output_timer.max(1) as u32;
+            let step = remaining.min(sequencer).min(output);
+
+            self.advance_ch1(step);
+            self.advance_ch2(step);
+            self.advance_ch3(step);
+            self.advance_ch4(step);
+
+            self.sequencer += step as u16;
+            if self.sequencer >= 8192 {
+                self.tick_sequencer();
+            }
+
+            self.output_timer -= step as i16;
+            if self.output_timer <= 0 {
+                self.push_sample(self.output_left(), self.output_right());
+            }
+
+            remaining -= step;
+        }
+    }
+
+    /// Processes the cycles that are pending in batch mode, should
+    /// be called before the state of the APU is accessed.
+    pub fn flush_batch(&mut self) {
+        if self.batch_cycles == 0 {
+            return;
+        }
+        let cycles = self.batch_cycles;
+        self.batch_cycles = 0;
+        self.clock_batch(cycles);
+    }
+
+    /// Runs the step of the frame sequencer, each of the steps runs
+    /// at 512/8 Hz = 64Hz, meaning a complete loop runs at 512 Hz.
+    fn tick_sequencer(&mut self) {
+        match self.sequencer_step {
+            0 => {
+                self.tick_length_all();
+            }
+            1 => (),
+            2 => {
+                self.tick_ch1_sweep();
+                self.tick_length_all();
             }
-            if self.left_enabled {
-                self.audio_buffer.push_back(self.output());
+            3 => (),
+            4 => {
+                self.tick_length_all();
             }
-            if self.right_enabled && self.channels > 1 {
-                self.audio_buffer.push_back(self.output());
+            5 => (),
+            6 => {
+                self.tick_ch1_sweep();
+                self.tick_length_all();
             }
+            7 => {
+                self.tick_envelope_all();
+            }
+            _ => (),
+        }
+
+        self.sequencer -= 8192;
+        self.sequencer_step = (self.sequencer_step + 1) & 7;
+    }
+
+    /// Pushes the provided (mixed) terminals output as a new sample
+    /// into the audio buffers, scheduling the next sample.
+    fn push_sample(&mut self, left: u8, right: u8) {
+        // verifies if we've reached the maximum allowed size for the
+        // audio buffer and if that's the case an item is removed from
+        // the buffer (avoiding overflow) and then then the new audio
+        // volume item is added to the queue
+        if self.audio_buffer.len() >= self.audio_buffer_max {
+            for _ in 0..self.channels {
+                self.audio_buffer.pop_front();
+            }
+        }
+        // the samples are interleaved (left then right) in case of
+        // stereo output, otherwise both terminals are averaged so that
+        // the mono output honors the NR50 volume and NR51 panning, this
+        // means that a channel panned to a single terminal is output at
+        // half of its level (as it would be heard from a single speaker)
+        if self.channels > 1 {
+            let left = self.mix(left, 0);
+            let right = self.mix(right, 1);
+            self.audio_buffer.push_back(left);
+            self.audio_buffer.push_back(right);
+            self.audio_frame.push(left);
+            self.audio_frame.push(right);
+        } else {
+            let output = self.mix(((left as u16 + right as u16) / 2) as u8, 0);
+            self.audio_buffer.push_back(output);
+            self.audio_frame.push(output);
+        }
 
-            // calculates the rate at which a new audio sample should be
-            // created based on the (base/CPU) clock frequency and the
-            // sampling rate, this is basically the amount of APU clock
-            // calls that should be performed until an audio sample is created
-            self.output_timer += (self.clock_freq as f32 / self.sampling_rate as f32) as i16;
+        // calculates the rate at which a new audio sample should be
+        // created based on the (base/CPU) clock frequency and the
+        // sampling rate, this is basically the amount of APU clock
+        // calls that should be performed until an audio sample is created,
+        // with the remainder accumulated to avoid drifting from the rate
+        let sampling_rate = self.sampling_rate as u32;
+        self.output_error += self.clock_freq % sampling_rate;
+        let mut period = self.clock_freq / sampling_rate;
+        if self.output_error >= sampling_rate {
+            self.output_error -= sampling_rate;
+            period += 1;
         }
+        self.output_timer += period as i16;
     }
 
     pub fn read(&self, addr: u16) -> u8 {
@@ -463,7 +1105,7 @@ impl Apu {
             }
 
             // 0xFF30-0xFF3F — Wave pattern RAM
-            0xff30..=0xff3f => self.wave_ram[addr as usize & 0x000f],
+            WAVE_RAM_START..=WAVE_RAM_END => self.wave_ram[addr as usize & 0x000f],
 
             _ => {
                 warnln!("Reading from unknown APU location 0x{:04x}", addr);
@@ -474,9 +1116,18 @@ impl Apu {
     }
 
     pub fn write(&mut self, addr: u16, value: u8) {
+        self.flush_batch();
+
+        if let Some(mut log) = self.log.take() {
+            log.write(addr, value);
+            self.write(addr, value);
+            self.log = Some(log);
+            return;
+        }
+
         // in case the sound is disabled then ignores writes
         // to any register aside from the sound on/off
-        if !self.sound_enabled && addr != 0xff26 {
+        if !self.sound_enabled && addr != NR52_ADDR {
             return;
         }
 
@@ -671,13 +1322,15 @@ impl Apu {
             }
 
             // 0xFF30-0xFF3F — Wave pattern RAM
-            0xff30..=0xff3f => self.wave_ram[addr as usize & 0x000f] = value,
+            WAVE_RAM_START..=WAVE_RAM_END => self.wave_ram[addr as usize & 0x000f] = value,
 
             _ => warnln!("Writing in unknown APU location 0x{:04x}", addr),
         }
     }
 
     pub fn read_raw(&mut self, addr: u16) -> u8 {
+        self.flush_batch();
+
         match addr {
             // 0xFF11 — NR11: Channel 1 length timer & duty cycle
             NR11_ADDR => ((64 - self.ch1_length_timer) & 0x3f) | ((self.ch1_wave_duty & 0x03) << 6),
@@ -723,6 +1376,15 @@ impl Apu {
     }
 
     pub fn write_raw(&mut self, addr: u16, value: u8) {
+        self.flush_batch();
+
+        if let Some(mut log) = self.log.take() {
+            log.write_raw(addr, value);
+            self.write_raw(addr, value);
+            self.log = Some(log);
+            return;
+        }
+
         match addr {
             // 0xFF26 — NR52: Sound on/off
             NR52_ADDR => {
@@ -750,6 +1412,48 @@ impl Apu {
         self.ch1_output() + self.ch2_output() + self.ch3_output() + self.ch4_output()
     }
 
+    /// Obtains the output of the left terminal (SO2), mixing the
+    /// channels panned to it (NR51) and applying its volume (NR50).
+    #[inline(always)]
+    pub fn output_left(&self) -> u8 {
+        if !self.left_enabled {
+            return 0;
+        }
+        self.output_terminal(self.glob_panning >> 4, (self.master >> 4) & 0x07)
+    }
+
+    /// Obtains the output of the right terminal (SO1), mixing the
+    /// channels panned to it (NR51) and applying its volume (NR50).
+    #[inline(always)]
+    pub fn output_right(&self) -> u8 {
+        if !self.right_enabled {
+            return 0;
+        }
+        self.output_terminal(self.glob_panning & 0x0f, self.master & 0x07)
+    }
+
+    /// Mixes the channels selected by the provided panning bits
+    /// scaling the result by the terminal volume (0-7), as there's
+    /// no cartridge capable of providing the VIN signal it's
+    /// emulated as silence regardless of its panning bits.
+    #[inline(always)]
+    fn output_terminal(&self, panning: u8, volume: u8) -> u8 {
+        let mut output = 0u16;
+        if panning & 0x01 == 0x01 {
+            output += self.ch1_output() as u16;
+        }
+        if panning & 0x02 == 0x02 {
+            output += self.ch2_output() as u16;
+        }
+        if panning & 0x04 == 0x04 {
+            output += self.ch3_output() as u16;
+        }
+        if panning & 0x08 == 0x08 {
+            output += self.ch4_output() as u16;
+        }
+        (output * (volume as u16 + 1) / 8) as u8
+    }
+
     #[inline(always)]
     pub fn ch1_output(&self) -> u8 {
         if self.ch1_out_enabled {
@@ -791,7 +1495,7 @@ impl Apu {
     }
 
     pub fn set_ch1_out_enabled(&mut self, enabled: bool) {
-        self.ch1_out_enabled = enabled;
+        self.set_channel_enabled(Channel::Ch1, enabled);
     }
 
     pub fn ch2_out_enabled(&self) -> bool {
@@ -799,7 +1503,7 @@ impl Apu {
     }
 
     pub fn set_ch2_out_enabled(&mut self, enabled: bool) {
-        self.ch2_out_enabled = enabled;
+        self.set_channel_enabled(Channel::Ch2, enabled);
     }
 
     pub fn ch3_out_enabled(&self) -> bool {
@@ -807,7 +1511,7 @@ impl Apu {
     }
 
     pub fn set_ch3_out_enabled(&mut self, enabled: bool) {
-        self.ch3_out_enabled = enabled;
+        self.set_channel_enabled(Channel::Ch3, enabled);
     }
 
     pub fn ch4_out_enabled(&self) -> bool {
@@ -815,13 +1519,116 @@ impl Apu {
     }
 
     pub fn set_ch4_out_enabled(&mut self, enabled: bool) {
-        self.ch4_out_enabled = enabled;
+        self.set_channel_enabled(Channel::Ch4, enabled);
     }
 
     pub fn sampling_rate(&self) -> u16 {
         self.sampling_rate
     }
 
+    /// Changes the rate at which audio samples are generated, keeping
+    /// the duration of the audio buffer, the pending samples are
+    /// discarded as they've been generated at the previous rate.
+    pub fn set_sampling_rate(&mut self, value: u16) {
+        if value == 0 || value == self.sampling_rate {
+            return;
+        }
+        self.log_command(ApuCommand::SamplingRate(value));
+        self.audio_buffer_max =
+            self.audio_buffer_max * value as usize / self.sampling_rate as usize;
+        self.sampling_rate = value;
+        self.output_timer = 0;
+        self.output_error = 0;
+        self.update_high_pass();
+        self.clear_audio_buffer();
+        self.audio_frame.clear();
+        self.audio_frame_ready.clear();
+
+        let capacity =
+            Self::frame_capacity(self.sampling_rate, self.channels, self.audio_buffer_max);
+        self.audio_frame.reserve(capacity);
+        self.audio_frame_ready.reserve(capacity);
+    }
+
+    /// The capacity pre-allocated for the audio frame buffers, enough
+    /// for the maximum number of pending samples plus two (video) frames
+    /// worth of samples, so that no allocation is needed while running
+    /// in a steady state (even with the LCD turned off).
+    fn frame_capacity(sampling_rate: u16, channels: u8, buffer_max: usize) -> usize {
+        buffer_max + sampling_rate as usize * channels as usize / 30
+    }
+
+    pub fn channel_enabled(&self, channel: Channel) -> bool {
+        match channel {
+            Channel::Ch1 => self.ch1_out_enabled,
+            Channel::Ch2 => self.ch2_out_enabled,
+            Channel::Ch3 => self.ch3_out_enabled,
+            Channel::Ch4 => self.ch4_out_enabled,
+        }
+    }
+
+    /// Enables (or disables) the output of the provided channel
+    /// in the mix, without affecting the channel emulation.
+    pub fn set_channel_enabled(&mut self, channel: Channel, enabled: bool) {
+        match channel {
+            Channel::Ch1 => self.ch1_out_enabled = enabled,
+            Channel::Ch2 => self.ch2_out_enabled = enabled,
+            Channel::Ch3 => self.ch3_out_enabled = enabled,
+            Channel::Ch4 => self.ch4_out_enabled = enabled,
+        }
+        self.log_command(ApuCommand::ChannelEnabled(channel, enabled));
+    }
+
+    pub fn master_volume(&self) -> f32 {
+        self.master_volume
+    }
+
+    /// Sets the volume multiplier (eg: 0.5 for half volume) of the
+    /// audio output, the samples are clamped to their valid range.
+    pub fn set_master_volume(&mut self, value: f32) {
+        self.master_volume = value.max(0.0);
+        self.log_command(ApuCommand::MasterVolume(self.master_volume));
+    }
+
+    pub fn high_pass(&self) -> HighPassFilter {
+        self.high_pass
+    }
+
+    /// Sets the high-pass filter applied to the audio output, when
+    /// enabled the samples are centered around [`SAMPLE_CENTER`].
+    pub fn set_high_pass(&mut self, value: HighPassFilter) {
+        self.high_pass = value;
+        self.high_pass_charge = [0.0; 2];
+        self.update_high_pass();
+        self.log_command(ApuCommand::HighPass(value));
+    }
+
+    pub fn resampler(&self) -> Resampler {
+        self.resampler
+    }
+
+    pub fn set_resampler(&mut self, value: Resampler) {
+        self.resampler = value;
+        self.log_command(ApuCommand::Resampler(value));
+    }
+
+    pub fn batch(&self) -> bool {
+        self.batch
+    }
+
+    /// Enables or disables the batch mode, in which the samples are
+    /// generated in batches of (up to) [`BATCH_CYCLES`] cycles, see
+    /// [`Apu::clock_batch`] for the differences in the output.
+    pub fn set_batch(&mut self, value: bool) {
+        self.flush_batch();
+        self.batch = value;
+    }
+
+    /// The number of cycles pending to be processed (batch mode only).
+    pub fn batch_cycles(&self) -> u32 {
+        self.batch_cycles
+    }
+
     pub fn channels(&self) -> u8 {
         self.channels
     }
@@ -834,6 +1641,31 @@ impl Apu {
         &mut self.audio_buffer
     }
 
+    /// Obtains the samples of the audio buffer as interleaved
+    /// stereo (left then right) floating point samples.
+    ///
+    /// When the high-pass filter is enabled the samples are in the
+    /// [-1.0, 1.0] range, centered around [`SAMPLE_CENTER`], otherwise
+    /// they're in the [0.0, 1.0] range with 0.0 being silence.
+    ///
+    /// In case the output is mono each sample is duplicated into
+    /// both channels, so that the layout is always the same.
+    pub fn audio_buffer_stereo(&self) -> Vec<f32> {
+        let (offset, scale) = match self.high_pass {
+            HighPassFilter::Off => (0.0, SAMPLE_MAX as f32),
+            _ => (SAMPLE_CENTER as f32, SAMPLE_CENTER as f32),
+        };
+        let to_f32 = |sample: &u8| (*sample as f32 - offset) / scale;
+        if self.channels > 1 {
+            self.audio_buffer.iter().map(to_f32).collect()
+        } else {
+            self.audio_buffer
+                .iter()
+                .flat_map(|sample| [to_f32(sample); 2])
+                .collect()
+        }
+    }
+
     pub fn clear_audio_buffer(&mut self) {
         self.audio_buffer.clear();
     }
@@ -842,12 +1674,179 @@ impl Apu {
         self.audio_buffer_max
     }
 
+    /// Swaps the per-frame audio double buffer, making the samples
+    /// of the frame that has just been completed available to be
+    /// taken by the frontend.
+    ///
+    /// Should be called at the end of each video frame so that the
+    /// audio chunks are aligned with the video frames. In case the
+    /// previous chunk has not been taken yet, the samples are
+    /// appended to it (up to the maximum audio buffer size).
+    ///
+    /// Returns the number of samples that have been dropped because
+    /// the maximum audio buffer size has been reached (overrun).
+    pub fn swap_audio_frame(&mut self) -> usize {
+        self.flush_batch();
+
+        if let Some(log) = self.log.as_mut() {
+            log.frame();
+        }
+        let mut overflow = 0;
+        if self.audio_frame_ready.is_empty() {
+            std::mem::swap(&mut self.audio_frame, &mut self.audio_frame_ready);
+        } else {
+            self.audio_frame_ready.append(&mut self.audio_frame);
+            if self.audio_frame_ready.len() > self.audio_buffer_max {
+                overflow = (self.audio_frame_ready.len() - self.audio_buffer_max)
+                    .next_multiple_of(self.channels as usize);
+                self.audio_frame_ready.drain(..overflow);
+            }
+        }
+        self.audio_frame.clear();
+        self.audio_frame_index = self.audio_frame_index.wrapping_add(1);
+        self.audio_frame_cycles = 0;
+        overflow
+    }
+
+    /// Takes the samples of the completed audio frame(s), leaving
+    /// the front buffer empty, returns `None` in case there's
+    /// no completed audio frame available.
+    pub fn take_audio_frame(&mut self) -> Option<Vec<u8>> {
+        if self.audio_frame_ready.is_empty() {
+            return None;
+        }
+        let capacity = self.audio_frame_ready.capacity();
+        Some(std::mem::replace(
+            &mut self.audio_frame_ready,
+            Vec::with_capacity(capacity),
+        ))
+    }
+
+    /// Takes the samples of the completed audio frame(s) into the
+    /// provided buffer (replacing its contents), exchanging it with
+    /// the front buffer so that no memory is allocated once both
+    /// have grown to the size of a frame.
+    ///
+    /// Returns `false` in case there's no completed audio frame
+    /// available, leaving the buffer empty.
+    pub fn take_audio_frame_into(&mut self, buffer: &mut Vec<u8>) -> bool {
+        buffer.clear();
+        if self.audio_frame_ready.is_empty() {
+            return false;
+        }
+        // makes sure the buffer handed over to the APU is large enough
+        // so that it never has to grow (re-allocate) while in use
+        buffer.reserve(Self::frame_capacity(
+            self.sampling_rate,
+            self.channels,
+            self.audio_buffer_max,
+        ));
+        std::mem::swap(buffer, &mut self.audio_frame_ready);
+        true
+    }
+
+    pub fn audio_frame_ready(&self) -> bool {
+        !self.audio_frame_ready.is_empty()
+    }
+
+    pub fn audio_frame_index(&self) -> u16 {
+        self.audio_frame_index
+    }
+
+    pub fn audio_frame_cycles(&self) -> u32 {
+        self.audio_frame_cycles
+    }
+
+    pub fn clear_audio_frame(&mut self) {
+        self.audio_frame.clear();
+        self.audio_frame_ready.clear();
+        self.audio_frame_index = 0;
+        self.audio_frame_cycles = 0;
+    }
+
     pub fn clock_freq(&self) -> u32 {
         self.clock_freq
     }
 
     pub fn set_clock_freq(&mut self, value: u32) {
         self.clock_freq = value;
+        self.update_high_pass();
+        self.log_command(ApuCommand::ClockFreq(value));
+    }
+
+    /// Starts logging the clock steps and register writes of this
+    /// APU (disabling its synthesis), returning the copy of it that
+    /// should be used to replay the log (eg: in an APU thread).
+    ///
+    /// The samples of the pending audio frames are handed over
+    /// to the returned copy.
+    pub fn start_log(&mut self) -> Apu {
+        let apu = self.snapshot();
+        self.audio_frame.clear();
+        self.audio_frame_ready.clear();
+        self.log = Some(ApuLog::default());
+        apu
+    }
+
+    /// Stops the logging, returning the entries not yet taken.
+    pub fn stop_log(&mut self) -> Option<ApuLog> {
+        self.log.take()
+    }
+
+    pub fn log_mut(&mut self) -> Option<&mut ApuLog> {
+        self.log.as_mut()
+    }
+
+    pub fn logging(&self) -> bool {
+        self.log.is_some()
+    }
+
+    /// Creates a copy of the APU without the log.
+    fn snapshot(&mut self) -> Apu {
+        let log = self.log.take();
+        let apu = self.clone();
+        self.log = log;
+        apu
+    }
+
+    /// Logs the complete state of the APU, required whenever it's
+    /// changed out of the register writes (eg: reset or state load).
+    fn log_sync(&mut self) {
+        if self.log.is_some() {
+            let apu = Box::new(self.snapshot());
+            self.log_command(ApuCommand::Sync(apu));
+        }
+    }
+
+    #[inline(always)]
+    fn log_command(&mut self, command: ApuCommand) {
+        if let Some(log) = self.log.as_mut() {
+            log.command(command);
+        }
+    }
+
+    /// Applies the high-pass filter and the master volume to the
+    /// provided (mixed) output sample of the given terminal, where
+    /// 0 is the left (or mono) terminal and 1 is the right one.
+    #[inline(always)]
+    fn mix(&mut self, output: u8, terminal: usize) -> u8 {
+        let value = match self.high_pass {
+            HighPassFilter::Off => output as f32 * self.master_volume,
+            _ => {
+                let input = output as f32;
+                let filtered = input - self.high_pass_charge[terminal];
+                self.high_pass_charge[terminal] = input - filtered * self.high_pass_factor;
+                filtered * self.master_volume + SAMPLE_CENTER as f32
+            }
+        };
+        value.round().clamp(0.0, SAMPLE_MAX as f32) as u8
+    }
+
+    /// Computes the capacitor charge factor between samples, which
+    /// depends on the number of cycles between each sample.
+    fn update_high_pass(&mut self) {
+        let cycles = self.clock_freq as f64 / self.sampling_rate as f64;
+        self.high_pass_factor = self.high_pass.charge_factor().powf(cycles) as f32;
     }
 
     #[inline(always)]
@@ -1101,6 +2100,101 @@ impl Apu {
             ((CH4_DIVISORS[self.ch4_divisor as usize] as u16) << self.ch4_clock_shift) as i32;
     }
 
+    #[inline(always)]
+    fn advance_ch1(&mut self, cycles: u32) {
+        let period = ((2048 - self.ch1_wave_length) << 2) as u32;
+        let (timer, steps) = advance_timer(self.ch1_timer as i32, cycles, period);
+        self.ch1_timer = timer as i16;
+        if steps == 0 {
+            return;
+        }
+
+        // the output is the one of the last of the steps, with the
+        // sequence position before its increment
+        let sequence = (self.ch1_sequence as u32 + steps - 1) & 7;
+        self.ch1_output = if self.ch1_enabled
+            && DUTY_TABLE[self.ch1_wave_duty as usize][sequence as usize] == 1
+        {
+            self.ch1_volume
+        } else {
+            0
+        };
+        self.ch1_sequence = ((sequence + 1) & 7) as u8;
+    }
+
+    #[inline(always)]
+    fn advance_ch2(&mut self, cycles: u32) {
+        let period = ((2048 - self.ch2_wave_length) << 2) as u32;
+        let (timer, steps) = advance_timer(self.ch2_timer as i32, cycles, period);
+        self.ch2_timer = timer as i16;
+        if steps == 0 {
+            return;
+        }
+
+        let sequence = (self.ch2_sequence as u32 + steps - 1) & 7;
+        self.ch2_output = if self.ch2_enabled
+            && DUTY_TABLE[self.ch2_wave_duty as usize][sequence as usize] == 1
+        {
+            self.ch2_volume
+        } else {
+            0
+        };
+        self.ch2_sequence = ((sequence + 1) & 7) as u8;
+    }
+
+    #[inline(always)]
+    fn advance_ch3(&mut self, cycles: u32) {
+        let period = ((2048 - self.ch3_wave_length) << 1) as u32;
+        let (timer, steps) = advance_timer(self.ch3_timer as i32, cycles, period);
+        self.ch3_timer = timer as i16;
+        if steps == 0 {
+            return;
+        }
+
+        let position = ((self.ch3_position as u32 + steps - 1) & 31) as u8;
+        self.ch3_position = (position + 1) & 31;
+        if !self.ch3_enabled || !self.ch3_dac || self.ch3_output_level == 0 {
+            self.ch3_output = 0;
+            return;
+        }
+        let sample = self.wave_ram[(position >> 1) as usize];
+        let sample = if (position & 0x01) == 0x01 {
+            sample & 0x0f
+        } else {
+            (sample & 0xf0) >> 4
+        };
+        self.ch3_output = sample >> (self.ch3_output_level - 1);
+    }
+
+    #[inline(always)]
+    fn advance_ch4(&mut self, cycles: u32) {
+        let period = (CH4_DIVISORS[self.ch4_divisor as usize] as u32) << self.ch4_clock_shift;
+        let (timer, steps) = advance_timer(self.ch4_timer, cycles, period);
+        self.ch4_timer = timer;
+        if steps == 0 {
+            return;
+        }
+
+        if !self.ch4_enabled {
+            self.ch4_output = 0;
+            return;
+        }
+
+        // the LFSR has no closed form, so it's shifted once per
+        // step, with the output being the one of the last step
+        let mut result = false;
+        for _ in 0..steps {
+            result = ((self.ch4_lfsr & 0x0001) ^ ((self.ch4_lfsr >> 1) & 0x0001)) == 0x0001;
+            self.ch4_lfsr >>= 1;
+            self.ch4_lfsr |= if result { 0x0001 << 14 } else { 0x0 };
+            if self.ch4_width_mode {
+                self.ch4_lfsr &= 0xbf;
+                self.ch4_lfsr |= if result { 0x40 } else { 0x00 };
+            }
+        }
+        self.ch4_output = if result { self.ch4_volume } else { 0 };
+    }
+
     #[inline(always)]
     fn trigger_ch1(&mut self) {
         self.ch1_timer = ((2048 - self.ch1_wave_length) << 2) as i16;
@@ -1157,6 +2251,58 @@ impl Apu {
     }
 }
 
+impl Io for Apu {
+    const IO_REGISTERS: &'static [IoRegister] = &[
+        IoRegister::new(NR10_ADDR, "NR10", IoComponent::Apu, 0x7f, 0x7f),
+        IoRegister::new(NR11_ADDR, "NR11", IoComponent::Apu, 0xc0, 0xff),
+        IoRegister::new(NR12_ADDR, "NR12", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(NR13_ADDR, "NR13", IoComponent::Apu, 0x00, 0xff),
+        IoRegister::new(NR14_ADDR, "NR14", IoComponent::Apu, 0x40, 0xc7),
+        IoRegister::new(NR20_ADDR, "NR20", IoComponent::Apu, 0x00, 0x00),
+        IoRegister::new(NR21_ADDR, "NR21", IoComponent::Apu, 0xc0, 0xff),
+        IoRegister::new(NR22_ADDR, "NR22", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(NR23_ADDR, "NR23", IoComponent::Apu, 0x00, 0xff),
+        IoRegister::new(NR24_ADDR, "NR24", IoComponent::Apu, 0x40, 0xc7),
+        IoRegister::new(NR30_ADDR, "NR30", IoComponent::Apu, 0x80, 0x80),
+        IoRegister::new(NR31_ADDR, "NR31", IoComponent::Apu, 0x00, 0xff),
+        IoRegister::new(NR32_ADDR, "NR32", IoComponent::Apu, 0x60, 0x60),
+        IoRegister::new(NR33_ADDR, "NR33", IoComponent::Apu, 0x00, 0xff),
+        IoRegister::new(NR34_ADDR, "NR34", IoComponent::Apu, 0x40, 0xc7),
+        IoRegister::new(NR40_ADDR, "NR40", IoComponent::Apu, 0x00, 0x00),
+        IoRegister::new(NR41_ADDR, "NR41", IoComponent::Apu, 0x00, 0x3f),
+        IoRegister::new(NR42_ADDR, "NR42", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(NR43_ADDR, "NR43", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(NR44_ADDR, "NR44", IoComponent::Apu, 0x40, 0xc0),
+        IoRegister::new(NR50_ADDR, "NR50", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(NR51_ADDR, "NR51", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(NR52_ADDR, "NR52", IoComponent::Apu, 0x8f, 0x80),
+        IoRegister::new(WAVE_RAM_START, "WAVE0", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(WAVE_RAM_START + 0x1, "WAVE1", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(WAVE_RAM_START + 0x2, "WAVE2", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(WAVE_RAM_START + 0x3, "WAVE3", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(WAVE_RAM_START + 0x4, "WAVE4", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(WAVE_RAM_START + 0x5, "WAVE5", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(WAVE_RAM_START + 0x6, "WAVE6", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(WAVE_RAM_START + 0x7, "WAVE7", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(WAVE_RAM_START + 0x8, "WAVE8", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(WAVE_RAM_START + 0x9, "WAVE9", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(WAVE_RAM_START + 0xa, "WAVEA", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(WAVE_RAM_START + 0xb, "WAVEB", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(WAVE_RAM_START + 0xc, "WAVEC", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(WAVE_RAM_START + 0xd, "WAVED", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(WAVE_RAM_START + 0xe, "WAVEE", IoComponent::Apu, 0xff, 0xff),
+        IoRegister::new(WAVE_RAM_START + 0xf, "WAVEF", IoComponent::Apu, 0xff, 0xff),
+    ];
+}
+
+impl ConfigListener for Apu {
+    fn config_changed(&mut self, change: &ConfigChange) {
+        if let ConfigChange::ClockFreq(value) = *change {
+            self.set_clock_freq(value);
+        }
+    }
+}
+
 impl BusComponent for Apu {
     fn read(&self, addr: u16) -> u8 {
         self.read(addr)
@@ -1168,6 +2314,14 @@ impl BusComponent for Apu {
 }
 
 impl StateComponent for Apu {
+    const STATE_MIGRATIONS: &'static [StateMigration] = &[
+        // v1 to v2, adds the cycles pending in batch mode
+        |mut data, _format| {
+            data.extend_from_slice(&[0x00; 4]);
+            Ok(data)
+        },
+    ];
+
     fn state(&self, _format: Option<StateFormat>) -> Result<Vec<u8>, Error> {
         let mut cursor = Cursor::new(vec![]);
 
@@ -1251,6 +2405,7 @@ impl StateComponent for Apu {
         write_u16(&mut cursor, self.sequencer)?;
         write_u8(&mut cursor, self.sequencer_step)?;
         write_i16(&mut cursor, self.output_timer)?;
+        write_u32(&mut cursor, self.batch_cycles)?;
 
         Ok(cursor.into_inner())
     }
@@ -1338,11 +2493,27 @@ impl StateComponent for Apu {
         self.sequencer = read_u16(&mut cursor)?;
         self.sequencer_step = read_u8(&mut cursor)?;
         self.output_timer = read_i16(&mut cursor)?;
+        self.batch_cycles = read_u32(&mut cursor)?;
+        self.update_high_pass();
+        self.log_sync();
 
         Ok(())
     }
 }
 
+/// Advances a channel timer by the provided number of cycles, returning
+/// the new value of the timer and the number of times it expired (and
+/// was reloaded with the period), without stepping through each period.
+#[inline(always)]
+fn advance_timer(timer: i32, cycles: u32, period: u32) -> (i32, u32) {
+    let (cycles, period) = (cycles as i32, period.max(1) as i32);
+    if timer > cycles {
+        return (timer - cycles, 0);
+    }
+    let overdue = cycles - timer;
+    (period - overdue % period, (1 + overdue / period) as u32)
+}
+
 impl Default for Apu {
     fn default() -> Self {
         Self::new(44100, 2, 1.0, GameBoy::CPU_FREQ)
@@ -1351,9 +2522,19 @@ impl Default for Apu {
 
 #[cfg(test)]
 mod tests {
-    use super::Apu;
-
-    use crate::state::StateComponent;
+    use super::{
+        Apu, AudioStream, Channel, FastForwardAudio, FastForwardAudioMode, HighPassFilter,
+        Resampler, BATCH_CYCLES, SAMPLE_CENTER, SAMPLE_MAX,
+    };
+
+    use crate::{
+        consts::{
+            NR12_ADDR, NR13_ADDR, NR14_ADDR, NR30_ADDR, NR32_ADDR, NR33_ADDR, NR34_ADDR, NR42_ADDR,
+            NR43_ADDR, NR44_ADDR, NR50_ADDR, NR51_ADDR, NR52_ADDR,
+        },
+        gb::GameBoy,
+        state::{StateComponent, StateFormat},
+    };
 
     #[test]
     fn test_trigger_ch1() {
@@ -1406,6 +2587,119 @@ mod tests {
         assert_eq!(apu.ch4_envelope_sequence, 0);
     }
 
+    #[test]
+    fn test_audio_frame() {
+        let mut apu = Apu::default();
+        assert!(apu.take_audio_frame().is_none());
+
+        apu.clock(8192);
+        apu.clock(8192);
+        assert!(!apu.audio_frame_ready());
+        assert_eq!(apu.audio_frame_cycles(), 16384);
+
+        apu.swap_audio_frame();
+        assert!(apu.audio_frame_ready());
+        assert_eq!(apu.audio_frame_index(), 1);
+        assert_eq!(apu.audio_frame_cycles(), 0);
+
+        apu.clock(8192);
+        apu.swap_audio_frame();
+        assert_eq!(apu.audio_frame_index(), 2);
+
+        let frame = apu.take_audio_frame().unwrap();
+        assert_eq!(frame.len(), 6);
+        assert!(!apu.audio_frame_ready());
+        assert!(apu.take_audio_frame().is_none());
+    }
+
+    #[test]
+    fn test_sampling_rate() {
+        let mut apu = Apu::default();
+        apu.set_resampler(Resampler::Nearest);
+        for _ in 0..GameBoy::CPU_FREQ / 4 {
+            apu.clock(4);
+        }
+        // one extra sample as the first one is taken at the start
+        assert_eq!(apu.audio_frame.len(), (44100 + 1) * 2);
+
+        apu.set_sampling_rate(48000);
+        apu.set_resampler(Resampler::Linear);
+        assert_eq!(apu.sampling_rate(), 48000);
+        assert_eq!(apu.audio_buffer_max(), 48000 * 2);
+        assert!(apu.audio_frame.is_empty());
+        for _ in 0..GameBoy::CPU_FREQ / 4 {
+            apu.clock(4);
+        }
+        assert_eq!(apu.audio_frame.len(), (48000 + 1) * 2);
+
+        assert_eq!(Resampler::from_string("linear").unwrap(), Resampler::Linear);
+        assert!(Resampler::from_string("sinc").is_err());
+    }
+
+    #[test]
+    fn test_mix() {
+        let mut apu = Apu {
+            ch1_output: 15,
+            ch2_output: 15,
+            ..Default::default()
+        };
+        assert_eq!(apu.mix(apu.output(), 0), 30);
+
+        apu.set_channel_enabled(Channel::Ch2, false);
+        assert!(!apu.channel_enabled(Channel::Ch2));
+        assert!(!apu.ch2_out_enabled());
+        apu.set_master_volume(0.5);
+        assert_eq!(apu.mix(apu.output(), 0), 8);
+
+        // the constant (DC) output is removed by the filter, with
+        // the samples converging towards the center value
+        apu.set_master_volume(1.0);
+        apu.set_high_pass(HighPassFilter::Cgb);
+        assert_eq!(apu.mix(apu.output(), 0), SAMPLE_CENTER + 15);
+        for _ in 0..1000 {
+            apu.mix(apu.output(), 0);
+        }
+        assert_eq!(apu.mix(apu.output(), 0), SAMPLE_CENTER);
+        apu.ch1_output = 0;
+        assert!(apu.mix(apu.output(), 0) < SAMPLE_CENTER);
+    }
+
+    #[test]
+    fn test_panning() {
+        let mut apu = Apu {
+            ch1_output: 15,
+            ch2_output: 10,
+            master: 0x73,
+            glob_panning: 0x12,
+            ..Default::default()
+        };
+        assert_eq!(apu.output_left(), 15);
+        assert_eq!(apu.output_right(), 5);
+
+        // the VIN panning bits have no effect on the output
+        apu.master = 0xf7;
+        apu.glob_panning = 0x33;
+        assert_eq!(apu.output_left(), 25);
+        assert_eq!(apu.output_right(), 25);
+
+        apu.glob_panning = 0x00;
+        assert_eq!(apu.output_left(), 0);
+        assert_eq!(apu.output_right(), 0);
+
+        // without the high-pass filter silence is 0.0
+        apu.audio_buffer_mut().extend([30, 0]);
+        assert_eq!(apu.audio_buffer_stereo(), vec![0.5, 0.0]);
+
+        // mono samples are duplicated into both channels
+        apu.channels = 1;
+        assert_eq!(apu.audio_buffer_stereo(), vec![0.5, 0.5, 0.0, 0.0]);
+
+        // with the high-pass filter the samples are centered
+        apu.set_high_pass(HighPassFilter::Cgb);
+        apu.channels = 2;
+        assert_eq!(apu.audio_buffer_stereo(), vec![0.0, -1.0]);
+    }
+
     #[test]
     fn test_state_and_set_state() {
         let apu = Apu {
@@ -1480,11 +2774,18 @@ mod tests {
             sequencer: 12345,
             sequencer_step: 6,
             output_timer: 789,
+            output_error: 0,
+            resampler: Resampler::Linear,
+            high_pass: HighPassFilter::Off,
+            high_pass_charge: [0.0; 2],
+            high_pass_factor: 0.0,
+            master_volume: 1.0,
+            batch_cycles: 321,
             ..Default::default()
         };
 
         let state = apu.state(None).unwrap();
-        assert_eq!(state.len(), 100);
+        assert_eq!(state.len(), 104);
 
         let mut new_apu = Apu::default();
         new_apu.set_state(&state, None).unwrap();
@@ -1569,5 +2870,166 @@ mod tests {
         assert_eq!(new_apu.sequencer, 12345);
         assert_eq!(new_apu.sequencer_step, 6);
         assert_eq!(new_apu.output_timer, 789);
+        assert_eq!(new_apu.batch_cycles, 321);
+
+        // states of the first version of the schema (without the
+        // batch cycles) are migrated into the current version
+        assert_eq!(Apu::state_version(), 2);
+        new_apu
+            .set_state_versioned(&state[..100], 1, StateFormat::Partial)
+            .unwrap();
+        assert_eq!(new_apu.output_timer, 789);
+        assert_eq!(new_apu.batch_cycles, 0);
+    }
+
+    #[test]
+    fn test_clock_batch() {
+        let build = || {
+            let mut apu = Apu::default();
+            apu.write(NR52_ADDR, 0x80);
+            apu.write(NR50_ADDR, 0x77);
+            apu.write(NR51_ADDR, 0xff);
+            apu.write(NR12_ADDR, 0xf3);
+            apu.write(NR13_ADDR, 0x40);
+            apu.write(NR14_ADDR, 0x87);
+            apu.write(NR30_ADDR, 0x80);
+            apu.write(NR32_ADDR, 0x20);
+            apu.write(NR33_ADDR, 0x11);
+            apu.write(NR34_ADDR, 0x87);
+            apu.write(NR42_ADDR, 0xf2);
+            apu.write(NR43_ADDR, 0x21);
+            apu.write(NR44_ADDR, 0x80);
+            apu
+        };
+
+        // the output is the same regardless of how the cycles
+        // are split between the batches
+        let mut apu = build();
+        apu.clock_batch(GameBoy::LCD_CYCLES);
+        apu.swap_audio_frame();
+        let frame = apu.take_audio_frame().unwrap();
+
+        let mut split = build();
+        let mut cycles = 0;
+        for step in [1, 7, 93, 4096, 8191, 3].iter().cycle() {
+            let step = (*step).min(GameBoy::LCD_CYCLES - cycles);
+            split.clock_batch(step);
+            cycles += step;
+            if cycles == GameBoy::LCD_CYCLES {
+                break;
+            }
+        }
+        split.swap_audio_frame();
+        assert_eq!(split.take_audio_frame().unwrap(), frame);
+        assert_eq!(split.state(None).unwrap(), apu.state(None).unwrap());
+
+        // in batch mode the cycles are accumulated (up to the limit)
+        // and flushed by the frame swap
+        let mut batch = build();
+        batch.set_batch(true);
+        for _ in 0..GameBoy::LCD_CYCLES / 4 {
+            batch.clock(4);
+            assert!(batch.batch_cycles() < BATCH_CYCLES);
+        }
+        batch.swap_audio_frame();
+        assert_eq!(batch.batch_cycles(), 0);
+        assert_eq!(batch.take_audio_frame().unwrap(), frame);
+
+        // the number of samples matches the step by step clock
+        let mut step = build();
+        for _ in 0..GameBoy::LCD_CYCLES / 4 {
+            step.clock(4);
+        }
+        step.swap_audio_frame();
+        assert_eq!(step.take_audio_frame().unwrap().len(), frame.len());
+    }
+
+    #[test]
+    fn test_fast_forward_audio() {
+        // 50 sample frames per second, meaning grains of 4 frames
+        // (the minimum) and crossfades of a single frame
+        let input: Vec<u8> = (0..32).collect();
+
+        let mut audio = FastForwardAudio::new(FastForwardAudioMode::Normal, 50, 1);
+        let mut samples = input.clone();
+        audio.process(&mut samples, 4);
+        assert_eq!(samples, input);
+
+        let mut audio = FastForwardAudio::new(FastForwardAudioMode::Pitch, 50, 1);
+        let mut samples = input.clone();
+        audio.process(&mut samples, 1);
+        assert_eq!(samples, input);
+
+        // one of every two grains is output, the first frame of the
+        // second output grain is the continuation of the first one
+        let mut samples = input.clone();
+        audio.process(&mut samples, 2);
+        assert_eq!(
+            samples,
+            vec![0, 1, 2, 3, 4, 9, 10, 11, 12, 17, 18, 19, 20, 25, 26, 27]
+        );
+
+        // the result must not depend on the size of the chunks
+        audio.reset();
+        let mut chunked = vec![];
+        for chunk in input.chunks(5) {
+            let mut samples = chunk.to_vec();
+            audio.process(&mut samples, 2);
+            chunked.extend(samples);
+        }
+        assert_eq!(
+            chunked,
+            vec![0, 1, 2, 3, 4, 9, 10, 11, 12, 17, 18, 19, 20, 25, 26, 27]
+        );
+
+        let mut audio = FastForwardAudio::new(FastForwardAudioMode::Mute, 50, 2);
+        let mut samples = vec![1, 2, 3, 4];
+        audio.process(&mut samples, 1);
+        let mut samples = vec![5; 10];
+        audio.process(&mut samples, 4);
+        assert_eq!(samples, vec![3, 4]);
+        let mut samples = vec![5; 6];
+        audio.process(&mut samples, 4);
+        assert_eq!(samples, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_audio_stream() {
+        // upsamples a ramp from 4 to 8 Hz (mono), with the latency
+        // of the buffered audio matching the target (no adjustment)
+        let mut stream = AudioStream::new(4, 8, 1000);
+        let samples = [SAMPLE_CENTER, SAMPLE_MAX, SAMPLE_CENTER, 0];
+        assert_eq!(stream.push(&samples, 1), 0);
+        assert_eq!(stream.buffered(), 4);
+
+        let mut buffer = [0.0; 8];
+        assert_eq!(stream.fill(&mut buffer, 4), 4);
+        assert_eq!(buffer, [0.0, 0.0, 0.5, 0.5, 1.0, 1.0, 0.5, 0.5]);
+        assert_eq!(stream.buffered(), 2);
+        assert_eq!(stream.underruns(), 0);
+
+        // the output runs out of audio, fading out the last sample
+        let mut buffer = [0.0; 8];
+        let filled = stream.fill(&mut buffer, 4);
+        assert!(filled < 4);
+        let last = buffer[filled * 2 - 2];
+        assert!(last < -0.5);
+        assert!(buffer[filled * 2] < 0.0 && buffer[filled * 2] > last);
+        assert_eq!(stream.underruns(), 1);
+
+        // the buffer is limited to four times the latency
+        let mut stream = AudioStream::new(100, 100, 100);
+        assert_eq!(stream.push(&[SAMPLE_CENTER; 60], 2), 0);
+        assert_eq!(stream.push(&[SAMPLE_CENTER; 40], 2), 10);
+        assert_eq!(stream.buffered(), 40);
+        assert_eq!(stream.dropped(), 10);
+
+        // more buffered audio than the target speeds up the
+        // consumption, bringing it back to the target latency
+        let mut stream = AudioStream::new(1000, 1000, 100);
+        stream.push(&[SAMPLE_CENTER; 800], 2);
+        let mut buffer = [0.0; 600];
+        assert_eq!(stream.fill(&mut buffer, 300), 300);
+        assert!(stream.buffered() < 100);
     }
 }
@@ -0,0 +1,382 @@
+//! Parallel audio synthesis, with the APU running on its own thread.
+//!
+//! The APU of the emulated system (CPU thread) logs every clock step
+//! and register write into an [`ApuLog`], keeping only its registers
+//! up-to-date. At the end of each frame the log is handed over to the
+//! [`ApuThread`] that replays it into its own copy of the APU, generating
+//! the very same samples that would have been generated inline, while
+//! the CPU thread is already emulating the next frame.
+//!
+//! The logs (and the audio frames) circulate between both threads in
+//! fixed rings of buffers, so that no memory is allocated once they've
+//! grown to a frame.
+
+use boytacean_common::error::Error;
+use std::{
+    mem::swap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{channel, sync_channel, Receiver, Sender, SyncSender, TrySendError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    apu::{Apu, Channel, HighPassFilter, Resampler},
+    warnln,
+};
+
+/// The tags (most significant byte) of the log entries, the
+/// remaining bytes are either the cycles of the clock step or
+/// the (low byte of the) address and the value of the write.
+const CLOCK: u32 = 0x00;
+const WRITE: u32 = 0x01;
+const WRITE_RAW: u32 = 0x02;
+const FRAME: u32 = 0x03;
+const COMMAND: u32 = 0x04;
+
+/// The changes to the APU that are not register writes, applied
+/// in order with the remaining entries of the log.
+#[derive(Clone)]
+pub enum ApuCommand {
+    ChannelEnabled(Channel, bool),
+    SamplingRate(u16),
+    MasterVolume(f32),
+    HighPass(HighPassFilter),
+    Resampler(Resampler),
+    ClockFreq(u32),
+
+    /// Replaces the complete APU state (eg: reset or state load).
+    Sync(Box<Apu>),
+}
+
+impl ApuCommand {
+    fn apply(self, apu: &mut Apu) {
+        match self {
+            ApuCommand::ChannelEnabled(channel, enabled) => {
+                apu.set_channel_enabled(channel, enabled)
+            }
+            ApuCommand::SamplingRate(value) => apu.set_sampling_rate(value),
+            ApuCommand::MasterVolume(value) => apu.set_master_volume(value),
+            ApuCommand::HighPass(value) => apu.set_high_pass(value),
+            ApuCommand::Resampler(value) => apu.set_resampler(value),
+            ApuCommand::ClockFreq(value) => apu.set_clock_freq(value),
+            ApuCommand::Sync(value) => *apu = *value,
+        }
+    }
+}
+
+/// Timestamped log of the clock steps and register writes of an
+/// APU, with each entry encoded as a single word.
+///
+/// The clock steps are kept as they were run, as the synthesis
+/// depends on their granularity (eg: sampling instants).
+#[derive(Clone, Default)]
+pub struct ApuLog {
+    entries: Vec<u32>,
+    commands: Vec<ApuCommand>,
+}
+
+impl ApuLog {
+    #[inline(always)]
+    pub fn clock(&mut self, cycles: u16) {
+        self.entries.push((CLOCK << 24) | cycles as u32);
+    }
+
+    pub fn write(&mut self, addr: u16, value: u8) {
+        self.entries
+            .push((WRITE << 24) | ((addr & 0x00ff) << 8) as u32 | value as u32);
+    }
+
+    pub fn write_raw(&mut self, addr: u16, value: u8) {
+        self.entries
+            .push((WRITE_RAW << 24) | ((addr & 0x00ff) << 8) as u32 | value as u32);
+    }
+
+    /// Marks the end of an audio frame, at which the replaying
+    /// APU swaps its audio frame buffers.
+    pub fn frame(&mut self) {
+        self.entries.push(FRAME << 24);
+    }
+
+    pub fn command(&mut self, command: ApuCommand) {
+        self.entries.push(COMMAND << 24);
+        self.commands.push(command);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.commands.clear();
+    }
+
+    /// Replays (and drains) the log into the provided APU, calling
+    /// `on_frame` whenever an audio frame has been completed.
+    pub fn replay(&mut self, apu: &mut Apu, mut on_frame: impl FnMut(&mut Apu)) {
+        let mut commands = self.commands.drain(..);
+        for entry in self.entries.drain(..) {
+            let addr = 0xff00 | ((entry >> 8) & 0xff) as u16;
+            let value = entry as u8;
+            match entry >> 24 {
+                CLOCK => apu.clock(entry as u16),
+                WRITE => apu.write(addr, value),
+                WRITE_RAW => apu.write_raw(addr, value),
+                FRAME => {
+                    apu.swap_audio_frame();
+                    on_frame(apu);
+                }
+                COMMAND => {
+                    if let Some(command) = commands.next() {
+                        command.apply(apu);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Thread that synthesizes the audio by replaying the logs of
+/// the APU of the emulated system, frame by frame.
+pub struct ApuThread {
+    logs: Option<SyncSender<ApuLog>>,
+    recycled: Receiver<ApuLog>,
+    frames: Receiver<Vec<u8>>,
+    spares: Sender<Vec<u8>>,
+    dropped: Arc<AtomicUsize>,
+    pool: Vec<ApuLog>,
+    pending: usize,
+    handle: Option<JoinHandle<Apu>>,
+}
+
+impl ApuThread {
+    /// The maximum number of frame logs waiting to be replayed,
+    /// the CPU thread blocks when the APU thread is this late.
+    pub const QUEUE_SIZE: usize = 4;
+
+    /// The maximum number of audio frames waiting to be taken, the
+    /// samples of the frames replayed beyond it are dropped (overrun).
+    pub const FRAMES_SIZE: usize = 16;
+
+    /// Spawns the thread that replays the logs into the provided
+    /// APU, which must not be logging itself.
+    pub fn new(mut apu: Apu) -> Result<Self, Error> {
+        let (logs, logs_receiver) = sync_channel::<ApuLog>(Self::QUEUE_SIZE);
+        let (recycled_sender, recycled) = channel();
+        let (frames_sender, frames) = sync_channel::<Vec<u8>>(Self::FRAMES_SIZE);
+        let (spares, spares_receiver) = channel::<Vec<u8>>();
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let dropped_thread = dropped.clone();
+        let handle = thread::Builder::new()
+            .name(String::from("apu"))
+            .spawn(move || {
+                let mut pool: Vec<Vec<u8>> = vec![];
+                for mut log in logs_receiver {
+                    log.replay(&mut apu, |apu| {
+                        pool.extend(spares_receiver.try_iter());
+                        let mut frame = pool.pop().unwrap_or_default();
+                        if !apu.take_audio_frame_into(&mut frame) {
+                            pool.push(frame);
+                            return;
+                        }
+                        if let Err(TrySendError::Full(frame)) = frames_sender.try_send(frame) {
+                            dropped_thread.fetch_add(frame.len(), Ordering::Relaxed);
+                            pool.push(frame);
+                        }
+                    });
+                    if recycled_sender.send(log).is_err() {
+                        break;
+                    }
+                }
+                apu
+            })
+            .map_err(|err| Error::CustomError(format!("Failed to spawn APU thread: {err}")))?;
+        Ok(Self {
+            logs: Some(logs),
+            recycled,
+            frames,
+            spares,
+            dropped,
+            pool: vec![],
+            pending: 0,
+            handle: Some(handle),
+        })
+    }
+
+    /// Hands over the entries of the provided log to the thread,
+    /// leaving it empty (with a recycled buffer) for the next frame.
+    pub fn submit(&mut self, log: &mut ApuLog) {
+        if log.is_empty() {
+            return;
+        }
+        self.collect();
+        let mut next = self.pool.pop().unwrap_or_default();
+        swap(log, &mut next);
+        match self.logs.as_ref().map(|logs| logs.send(next)) {
+            Some(Ok(())) => self.pending += 1,
+            _ => warnln!("APU thread is not running, audio frame dropped"),
+        }
+    }
+
+    /// Waits for all the submitted logs to be replayed.
+    pub fn flush(&mut self) {
+        while self.pending > 0 {
+            match self.recycled.recv() {
+                Ok(log) => self.recycle(log),
+                Err(_) => break,
+            }
+        }
+    }
+
+    /// Takes the samples of all the audio frames generated so far
+    /// into the provided buffer (replacing its contents), returns
+    /// `false` in case there's no audio frame available.
+    pub fn take_audio_frame_into(&mut self, buffer: &mut Vec<u8>) -> bool {
+        buffer.clear();
+        for frame in self.frames.try_iter() {
+            buffer.extend_from_slice(&frame);
+            let _ = self.spares.send(frame);
+        }
+        !buffer.is_empty()
+    }
+
+    /// Takes the number of samples dropped since the last call,
+    /// because the audio frames were not taken in time (overrun).
+    pub fn take_dropped(&mut self) -> usize {
+        self.dropped.swap(0, Ordering::Relaxed)
+    }
+
+    /// Stops the thread once all the submitted logs have been
+    /// replayed, returning its APU, the samples of the audio
+    /// frames that have not been taken are discarded.
+    pub fn stop(mut self) -> Apu {
+        self.logs = None;
+        self.handle.take().unwrap().join().unwrap()
+    }
+
+    /// Collects the logs that have already been replayed.
+    fn collect(&mut self) {
+        while let Ok(log) = self.recycled.try_recv() {
+            self.recycle(log);
+        }
+    }
+
+    fn recycle(&mut self, log: ApuLog) {
+        self.pending -= 1;
+        self.pool.push(log);
+    }
+}
+
+impl Drop for ApuThread {
+    fn drop(&mut self) {
+        self.logs = None;
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ApuThread;
+
+    use crate::{
+        apu::{Apu, Channel},
+        consts::{NR11_ADDR, NR12_ADDR, NR13_ADDR, NR14_ADDR, NR50_ADDR, NR51_ADDR, NR52_ADDR},
+    };
+
+    /// Runs a frame of a square wave, with a register write midway.
+    fn run_frame(apu: &mut Apu, frame: u16) {
+        apu.write(NR52_ADDR, 0x80);
+        apu.write(NR50_ADDR, 0x77);
+        apu.write(NR51_ADDR, 0xff);
+        apu.write(NR11_ADDR, 0x80);
+        apu.write(NR12_ADDR, 0xf0);
+        apu.write(NR13_ADDR, frame as u8 * 16);
+        apu.write(NR14_ADDR, 0x87);
+        for step in 0..17556_u16 {
+            apu.clock(4 + (step % 3) * 4);
+            if step == 8000 {
+                apu.write(NR12_ADDR, 0x00);
+            }
+        }
+        apu.swap_audio_frame();
+    }
+
+    #[test]
+    fn test_replay() {
+        let mut inline = Apu::default();
+        let mut apu = Apu::default();
+        let mut replay = apu.start_log();
+        apu.set_channel_enabled(Channel::Ch2, false);
+        inline.set_channel_enabled(Channel::Ch2, false);
+
+        let mut expected = vec![];
+        let mut samples = vec![];
+        for frame in 0..3 {
+            run_frame(&mut inline, frame);
+            run_frame(&mut apu, frame);
+            expected.extend(inline.take_audio_frame().unwrap());
+            assert!(apu.take_audio_frame().is_none());
+            assert_eq!(apu.read(NR52_ADDR), inline.read(NR52_ADDR));
+
+            let log = apu.log_mut().unwrap();
+            assert!(!log.is_empty());
+            log.replay(&mut replay, |replay| {
+                samples.extend(replay.take_audio_frame().unwrap());
+            });
+            assert!(log.is_empty());
+        }
+        assert!(!expected.is_empty());
+        assert_eq!(samples, expected);
+    }
+
+    #[test]
+    fn test_apu_thread() {
+        let mut inline = Apu::default();
+        let mut apu = Apu::default();
+        let mut apu_thread = ApuThread::new(apu.start_log()).unwrap();
+
+        let mut expected = vec![];
+        for frame in 0..8 {
+            run_frame(&mut inline, frame);
+            run_frame(&mut apu, frame);
+            expected.extend(inline.take_audio_frame().unwrap());
+            apu_thread.submit(apu.log_mut().unwrap());
+            assert!(apu.log_mut().unwrap().is_empty());
+        }
+        apu_thread.flush();
+
+        let mut samples = vec![];
+        assert!(apu_thread.take_audio_frame_into(&mut samples));
+        assert_eq!(samples, expected);
+        assert!(!apu_thread.take_audio_frame_into(&mut samples));
+
+        // the frames that are not taken in time are dropped
+        for frame in 0..ApuThread::FRAMES_SIZE as u16 + 2 {
+            run_frame(&mut apu, frame % 8);
+            apu_thread.submit(apu.log_mut().unwrap());
+        }
+        apu_thread.flush();
+        assert!(apu_thread.take_audio_frame_into(&mut samples));
+        let dropped = apu_thread.take_dropped();
+        assert!(dropped > 0 && dropped < samples.len());
+        assert_eq!(apu_thread.take_dropped(), 0);
+
+        apu.reset();
+        let mut log = apu.stop_log().unwrap();
+        assert_eq!(log.len(), 1);
+        apu_thread.submit(&mut log);
+        let replay = apu_thread.stop();
+        assert_eq!(replay.read(NR52_ADDR), apu.read(NR52_ADDR));
+        assert!(log.is_empty());
+    }
+}
@@ -0,0 +1,215 @@
+//! Loading of ROMs from compressed archives (ZIP and 7z), as most
+//! of the ROM collections (and the libretro ecosystem) ship them.
+//!
+//! The ROM is either the first entry of the archive with a Game Boy
+//! extension (see [`ROM_EXTENSIONS`]) or a named entry, selected using
+//! the `archive.zip#entry.gb` path syntax.
+
+use core::fmt;
+use std::{
+    fmt::{Display, Formatter},
+    io::{Cursor, Read},
+    path::Path,
+};
+
+use boytacean_common::{error::Error, util::read_file};
+use sevenz_rust::{Password, SevenZReader};
+use zip::ZipArchive;
+
+/// The extensions (lower case) of the entries of an archive that
+/// are considered to be Game Boy ROMs.
+pub const ROM_EXTENSIONS: [&str; 2] = ["gb", "gbc"];
+
+/// The extensions (lower case) of the supported archive files.
+pub const ARCHIVE_EXTENSIONS: [&str; 2] = ["zip", "7z"];
+
+/// The separator between the path of the archive and the name
+/// of the entry to be loaded from it.
+pub const ENTRY_SEPARATOR: char = '#';
+
+const ZIP_MAGIC: &[u8; 4] = b"PK\x03\x04";
+const SEVENZ_MAGIC: &[u8; 6] = b"7z\xbc\xaf\x27\x1c";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    SevenZ,
+}
+
+impl ArchiveFormat {
+    pub fn description(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "ZIP",
+            ArchiveFormat::SevenZ => "7z",
+        }
+    }
+
+    /// Infers the format of the archive from its magic string,
+    /// returning `None` in case the data is not an archive.
+    pub fn from_data(data: &[u8]) -> Option<Self> {
+        if data.starts_with(ZIP_MAGIC) {
+            Some(ArchiveFormat::Zip)
+        } else if data.starts_with(SEVENZ_MAGIC) {
+            Some(ArchiveFormat::SevenZ)
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for ArchiveFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// Extracts the ROM from the provided archive data, the entry with
+/// the provided name or the first one with a ROM extension.
+pub fn extract_rom(data: &[u8], entry: Option<&str>) -> Result<Vec<u8>, Error> {
+    match ArchiveFormat::from_data(data) {
+        Some(ArchiveFormat::Zip) => extract_zip(data, entry),
+        Some(ArchiveFormat::SevenZ) => extract_sevenz(data, entry),
+        None => Err(Error::DataError(String::from("Unknown archive format"))),
+    }
+}
+
+/// Reads the ROM in the provided path, extracting it in case the
+/// file is an archive, supports the `archive.zip#entry.gb` syntax
+/// for loading a named entry of the archive.
+pub fn read_rom_file(path: &str) -> Result<Vec<u8>, Error> {
+    let (path, entry) = match path.rsplit_once(ENTRY_SEPARATOR) {
+        Some((archive, entry)) if !Path::new(path).exists() => (archive, Some(entry)),
+        _ => (path, None),
+    };
+    let data = read_file(path)?;
+    match (ArchiveFormat::from_data(&data), entry) {
+        (Some(_), _) => extract_rom(&data, entry),
+        (None, Some(_)) => Err(Error::DataError(format!("File is not an archive: {path}"))),
+        (None, None) => Ok(data),
+    }
+}
+
+/// Checks if the provided path refers to an archive (or to an entry
+/// inside an archive) from its extension.
+pub fn is_archive_path(path: &str) -> bool {
+    let path = match path.rsplit_once(ENTRY_SEPARATOR) {
+        Some((archive, _)) if !Path::new(path).exists() => archive,
+        _ => path,
+    };
+    Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| ARCHIVE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Checks if the entry name is the one being searched for or, when no
+/// name is provided, if the entry has a Game Boy ROM extension.
+fn is_rom_entry(name: &str, entry: Option<&str>) -> bool {
+    match entry {
+        Some(entry) => name == entry,
+        None => Path::new(name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| ROM_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+            .unwrap_or(false),
+    }
+}
+
+fn missing_entry(entry: Option<&str>) -> Error {
+    match entry {
+        Some(entry) => Error::DataError(format!("Entry not found in archive: {entry}")),
+        None => Error::DataError(String::from("No ROM found in archive")),
+    }
+}
+
+fn extract_zip(data: &[u8], entry: Option<&str>) -> Result<Vec<u8>, Error> {
+    let mut archive =
+        ZipArchive::new(Cursor::new(data)).map_err(|e| Error::DataError(e.to_string()))?;
+    for index in 0..archive.len() {
+        let mut file = archive
+            .by_index(index)
+            .map_err(|e| Error::DataError(e.to_string()))?;
+        if !file.is_file() || !is_rom_entry(file.name(), entry) {
+            continue;
+        }
+        let mut rom = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut rom)?;
+        return Ok(rom);
+    }
+    Err(missing_entry(entry))
+}
+
+fn extract_sevenz(data: &[u8], entry: Option<&str>) -> Result<Vec<u8>, Error> {
+    let mut archive = SevenZReader::new(Cursor::new(data), data.len() as u64, Password::empty())
+        .map_err(|e| Error::DataError(e.to_string()))?;
+    let mut rom = None;
+    archive
+        .for_each_entries(|file, reader| {
+            if file.is_directory() || !is_rom_entry(file.name(), entry) {
+                return Ok(true);
+            }
+            let mut data = Vec::with_capacity(file.size as usize);
+            reader.read_to_end(&mut data)?;
+            rom = Some(data);
+            Ok(false)
+        })
+        .map_err(|e| Error::DataError(e.to_string()))?;
+    rom.ok_or(missing_entry(entry))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use sevenz_rust::{SevenZArchiveEntry, SevenZWriter};
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use super::{extract_rom, ArchiveFormat};
+
+    fn build_zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(vec![]));
+        for (name, data) in entries {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn build_sevenz(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = SevenZWriter::new(Cursor::new(vec![])).unwrap();
+        for (name, data) in entries {
+            let mut entry = SevenZArchiveEntry::new();
+            entry.name = String::from(*name);
+            entry.has_stream = true;
+            writer.push_archive_entry(entry, Some(*data)).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_zip() {
+        let data = build_zip(&[
+            ("readme.txt", b"readme"),
+            ("game.GBC", b"rom1"),
+            ("other.gb", b"rom2"),
+        ]);
+        assert_eq!(ArchiveFormat::from_data(&data), Some(ArchiveFormat::Zip));
+        assert_eq!(extract_rom(&data, None).unwrap(), b"rom1");
+        assert_eq!(extract_rom(&data, Some("other.gb")).unwrap(), b"rom2");
+        assert!(extract_rom(&data, Some("missing.gb")).is_err());
+        assert!(extract_rom(&build_zip(&[("readme.txt", b"readme")]), None).is_err());
+    }
+
+    #[test]
+    fn test_sevenz() {
+        let data = build_sevenz(&[("readme.txt", b"readme"), ("game.gb", b"rom1")]);
+        assert_eq!(ArchiveFormat::from_data(&data), Some(ArchiveFormat::SevenZ));
+        assert_eq!(extract_rom(&data, None).unwrap(), b"rom1");
+        assert_eq!(extract_rom(&data, Some("readme.txt")).unwrap(), b"readme");
+        assert!(extract_rom(&data, Some("missing.gb")).is_err());
+        assert!(extract_rom(b"not an archive", None).is_err());
+    }
+}
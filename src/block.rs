@@ -0,0 +1,293 @@
+//! Cache of pre-decoded basic blocks used by the cached interpreter
+//! backend of the CPU (see [`crate::cpu::CpuBackend`]).
+//!
+//! The blocks are decoded once from the ROM, WRAM or HRAM into the
+//! sequence of instruction handlers (from the tables of [`crate::inst`])
+//! that are then dispatched without the fetch and decode of the opcodes
+//! from the memory bus. The cached code is invalidated by the MMU when
+//! the memory holding it changes, with the blocks of the switchable
+//! banks kept apart by the bank they've been decoded from.
+
+use crate::{
+    cpu::{Instruction, PREFIX},
+    inst::{EXTENDED, INSTRUCTIONS},
+    mmu::Mmu,
+};
+
+/// The number of slots of the (direct mapped) cache of blocks,
+/// must be a power of two.
+pub const BLOCK_SLOTS: usize = 4096;
+
+/// The maximum number of instructions in a single block.
+pub const BLOCK_SIZE: usize = 32;
+
+/// A single pre-decoded instruction of a block.
+#[derive(Clone, Copy)]
+pub struct BlockOp {
+    /// The handler of the instruction.
+    pub inst: Instruction,
+
+    /// The address of the (first byte of the) opcode.
+    pub pc: u16,
+
+    /// The address after the opcode (and prefix), the one
+    /// of the immediate operands if any.
+    pub next: u16,
+
+    /// The immediate operands of the instruction, only pre-fetched
+    /// for the code of the ROM, as the one of the RAM may be changed
+    /// (self-modifying code) without invalidating the block.
+    pub operands: [u8; 2],
+
+    /// The number of bytes of the operands that have been pre-fetched.
+    pub prefetched: u16,
+}
+
+/// A basic block, the sequence of instructions up to (and including)
+/// the first one that changes the flow of execution.
+#[derive(Default)]
+struct Block {
+    pc: u16,
+    bank: u16,
+    generation: u32,
+    ops: Vec<BlockOp>,
+}
+
+impl Block {
+    fn matches(&self, pc: u16, bank: u16, generation: u32) -> bool {
+        !self.ops.is_empty() && self.pc == pc && self.bank == bank && self.generation == generation
+    }
+}
+
+#[derive(Default)]
+pub struct BlockCache {
+    slots: Vec<Block>,
+
+    /// The slot of the block being executed.
+    slot: usize,
+
+    /// The index of the next instruction of the block being
+    /// executed, out of bounds when there's none.
+    index: usize,
+
+    /// The map generation of the MMU when the block being executed
+    /// was entered, as the bank it's been decoded from must be the
+    /// one still mapped.
+    map_generation: u32,
+
+    /// The number of blocks decoded since the cache was created.
+    decoded: u64,
+}
+
+impl BlockCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears the cache, dropping all of the decoded blocks.
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.index = usize::MAX;
+    }
+
+    /// Obtains the pre-decoded instruction at the address, decoding
+    /// the block starting at the address if required, `None` if the
+    /// memory at the address can't be cached and the instruction must
+    /// be fetched.
+    #[inline(always)]
+    pub fn fetch(&mut self, pc: u16, mmu: &mut Mmu) -> Option<BlockOp> {
+        if self.map_generation == mmu.map_generation() {
+            if let Some(block) = self.slots.get(self.slot) {
+                if let Some(op) = block.ops.get(self.index) {
+                    if op.pc == pc && block.generation == mmu.code_generation() {
+                        self.index += 1;
+                        return Some(*op);
+                    }
+                }
+            }
+        }
+        self.enter(pc, mmu)
+    }
+
+    /// The number of blocks decoded since the cache was created,
+    /// useful to evaluate the efficiency of the cache.
+    pub fn decoded(&self) -> u64 {
+        self.decoded
+    }
+
+    /// The number of blocks currently in the cache.
+    pub fn len(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|block| !block.ops.is_empty())
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn enter(&mut self, pc: u16, mmu: &mut Mmu) -> Option<BlockOp> {
+        self.index = usize::MAX;
+        let bank = mmu.code_bank(pc)?;
+        let generation = mmu.code_generation();
+
+        if self.slots.is_empty() {
+            self.slots.resize_with(BLOCK_SLOTS, Block::default);
+        }
+
+        let slot = (pc as usize ^ (bank as usize).wrapping_mul(0x9e5)) & (BLOCK_SLOTS - 1);
+        if !self.slots[slot].matches(pc, bank, generation) {
+            self.decode(slot, pc, bank, mmu);
+        }
+
+        let block = &self.slots[slot];
+        let op = *block.ops.first()?;
+        self.slot = slot;
+        self.index = 1;
+        self.map_generation = mmu.map_generation();
+        Some(op)
+    }
+
+    /// Decodes the block starting at the address into the slot, the
+    /// block is ended before any instruction outside of the page (4 KB)
+    /// or the bank of its start, so that it's mapped as a whole.
+    fn decode(&mut self, slot: usize, pc: u16, bank: u16, mmu: &mut Mmu) {
+        let mut ops = std::mem::take(&mut self.slots[slot].ops);
+        ops.clear();
+
+        let rom = pc < 0x8000;
+        let mut addr = pc;
+        while ops.len() < BLOCK_SIZE {
+            let opcode = mmu.read(addr);
+            let (inst, next) = if opcode == PREFIX {
+                let extended = addr.wrapping_add(1);
+                if !Self::same_bank(pc, extended, bank, mmu) {
+                    break;
+                }
+                (&EXTENDED[mmu.read(extended) as usize], addr.wrapping_add(2))
+            } else {
+                (&INSTRUCTIONS[opcode as usize], addr.wrapping_add(1))
+            };
+
+            let mut mark = addr;
+            while mark != next {
+                mmu.mark_code(mark);
+                mark = mark.wrapping_add(1);
+            }
+
+            let (_, _, inst_str) = inst;
+            let size = operands_size(inst_str);
+            let prefetched =
+                if rom && size > 0 && Self::same_bank(pc, next.wrapping_add(size - 1), bank, mmu) {
+                    size
+                } else {
+                    0
+                };
+            let mut operands = [0x00; 2];
+            for (index, operand) in operands.iter_mut().take(prefetched as usize).enumerate() {
+                *operand = mmu.read(next.wrapping_add(index as u16));
+            }
+            ops.push(BlockOp {
+                inst,
+                pc: addr,
+                next,
+                operands,
+                prefetched,
+            });
+
+            if ends_block(inst_str) {
+                break;
+            }
+            addr = next.wrapping_add(size);
+            if !Self::same_bank(pc, addr, bank, mmu) {
+                break;
+            }
+        }
+
+        let block = &mut self.slots[slot];
+        block.pc = pc;
+        block.bank = bank;
+        block.generation = mmu.code_generation();
+        block.ops = ops;
+        self.decoded += 1;
+    }
+
+    fn same_bank(start: u16, addr: u16, bank: u16, mmu: &Mmu) -> bool {
+        (start ^ addr) & 0xf000 == 0 && mmu.code_bank(addr) == Some(bank)
+    }
+}
+
+/// If the instruction (with the provided mnemonic) ends a basic
+/// block, as it (potentially) changes the flow of execution.
+fn ends_block(inst_str: &str) -> bool {
+    ["JP", "JR", "CALL", "RET", "RST", "HALT", "STOP", "ILLEGAL"]
+        .iter()
+        .any(|prefix| inst_str.starts_with(prefix))
+}
+
+/// The size in bytes of the immediate operands of the instruction
+/// (with the provided mnemonic).
+fn operands_size(inst_str: &str) -> u16 {
+    if inst_str.contains("u16") {
+        2
+    } else if inst_str.contains("u8") || inst_str.contains("i8") {
+        1
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ends_block, operands_size, BlockCache};
+
+    use crate::mmu::Mmu;
+
+    #[test]
+    fn test_operands_size() {
+        assert_eq!(operands_size("NOP"), 0);
+        assert_eq!(operands_size("LD A, u8"), 1);
+        assert_eq!(operands_size("LD HL, SP+i8"), 1);
+        assert_eq!(operands_size("LD [u16], SP"), 2);
+        assert!(ends_block("JR NZ, i8"));
+        assert!(ends_block("RETI"));
+        assert!(!ends_block("LD A, [HL]"));
+    }
+
+    #[test]
+    fn test_fetch() {
+        let mut mmu = Mmu::default();
+        mmu.allocate_default();
+        mmu.set_boot_active(false);
+
+        // LD A, 0x42; INC A; JR -5 (in WRAM)
+        mmu.write_many(0xc000, &[0x3e, 0x42, 0x3c, 0x18, 0xfb]);
+
+        let mut cache = BlockCache::new();
+        let op = cache.fetch(0xc000, &mut mmu).unwrap();
+        assert_eq!(op.inst.2, "LD A, u8");
+        assert_eq!(op.next, 0xc001);
+        assert_eq!(op.prefetched, 0);
+        let op = cache.fetch(0xc002, &mut mmu).unwrap();
+        assert_eq!(op.inst.2, "INC A");
+        assert_eq!(op.next, 0xc003);
+        assert_eq!(cache.fetch(0xc003, &mut mmu).unwrap().inst.2, "JR i8");
+        assert_eq!(cache.decoded(), 1);
+
+        // the block is reused when entered again
+        assert_eq!(cache.fetch(0xc000, &mut mmu).unwrap().inst.2, "LD A, u8");
+        assert_eq!(cache.decoded(), 1);
+
+        // writing the operand keeps the block, as operands are read
+        // on execution, while writing an opcode invalidates it
+        mmu.write(0xc001, 0x24);
+        assert_eq!(cache.fetch(0xc002, &mut mmu).unwrap().inst.2, "INC A");
+        mmu.write(0xc003, 0x00);
+        assert_eq!(cache.fetch(0xc003, &mut mmu).unwrap().inst.2, "NOP");
+        assert_eq!(cache.decoded(), 2);
+
+        // the VRAM is not cached
+        assert!(cache.fetch(0x8000, &mut mmu).is_none());
+    }
+}
@@ -0,0 +1,190 @@
+//! Memory map registration and lookup structures.
+//!
+//! Each component that is mapped into the address space declares
+//! the (page aligned) ranges it handles through the [`BusMap`] trait,
+//! the MMU then dispatches accesses using the resulting table of
+//! pages, which can be overridden at runtime by pluggable components
+//! (eg: cartridge accessories) without changes to the MMU.
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::{mmu::Mmu, ppu::Ppu, rom::Cartridge};
+
+/// Size (in bytes) of each of the pages of the memory map, the
+/// granularity at which regions can be mapped.
+pub const BUS_PAGE_SIZE: usize = 0x100;
+
+/// Number of pages in the memory map, covering the
+/// complete 16 bit address space.
+pub const BUS_TABLE_SIZE: usize = 0x100;
+
+/// The region of the memory map that handles a page, each of the
+/// regions is served by one of the built-in components, except for
+/// the device regions that are served by pluggable components.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BusRegion {
+    /// Boot ROM (while active) and cartridge ROM banks.
+    Rom,
+
+    /// Video RAM (banked in CGB mode).
+    Vram,
+
+    /// External (cartridge) RAM or cartridge registers.
+    ExtRam,
+
+    /// Working RAM bank 0.
+    Wram0,
+
+    /// Working RAM bank 1 (banked in CGB mode).
+    WramX,
+
+    /// Mirror of the working RAM in 0xE000-0xFDFF.
+    Echo,
+
+    /// Object attribute memory plus the not usable area.
+    Oam,
+
+    /// I/O registers, High RAM and the IE register.
+    High,
+
+    /// Pluggable component, with the index of the
+    /// component in the MMU.
+    Device(u8),
+}
+
+impl BusRegion {
+    pub fn description(&self) -> &'static str {
+        match self {
+            BusRegion::Rom => "ROM",
+            BusRegion::Vram => "VRAM",
+            BusRegion::ExtRam => "External RAM",
+            BusRegion::Wram0 => "WRAM 0",
+            BusRegion::WramX => "WRAM X",
+            BusRegion::Echo => "Echo RAM",
+            BusRegion::Oam => "OAM",
+            BusRegion::High => "I/O + HRAM",
+            BusRegion::Device(_) => "Device",
+        }
+    }
+}
+
+impl Display for BusRegion {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            BusRegion::Device(index) => write!(f, "{} #{index}", self.description()),
+            _ => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+/// Range of addresses, aligned to pages, handled
+/// by a region of the memory map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BusRange {
+    pub start: u16,
+    pub end: u16,
+    pub region: BusRegion,
+}
+
+impl BusRange {
+    pub const fn new(start: u16, end: u16, region: BusRegion) -> Self {
+        Self { start, end, region }
+    }
+
+    /// If the range starts and ends at page boundaries.
+    #[allow(clippy::manual_is_multiple_of)]
+    pub const fn aligned(&self) -> bool {
+        (self.start as usize) % BUS_PAGE_SIZE == 0
+            && (self.end as usize + 1) % BUS_PAGE_SIZE == 0
+            && self.start <= self.end
+    }
+
+    pub fn contains(&self, addr: u16) -> bool {
+        (self.start..=self.end).contains(&addr)
+    }
+}
+
+impl Display for BusRange {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "0x{:04x}-0x{:04x} {}", self.start, self.end, self.region)
+    }
+}
+
+/// Trait to be implemented by the components that are
+/// mapped into the address space.
+pub trait BusMap {
+    const BUS_RANGES: &'static [BusRange];
+}
+
+/// Table with the region of each of the pages of the memory map,
+/// built at compile time so that any overlapping or missing range
+/// fails the build.
+pub static BUS_TABLE: [BusRegion; BUS_TABLE_SIZE] =
+    build_bus_table(&[Cartridge::BUS_RANGES, Ppu::BUS_RANGES, Mmu::BUS_RANGES]);
+
+/// Obtains the index of the page of the provided address.
+#[inline(always)]
+pub const fn bus_page(addr: u16) -> usize {
+    (addr >> 8) as usize
+}
+
+const fn build_bus_table(components: &[&[BusRange]]) -> [BusRegion; BUS_TABLE_SIZE] {
+    let mut table = [None; BUS_TABLE_SIZE];
+    let mut component = 0;
+    while component < components.len() {
+        let ranges = components[component];
+        let mut range = 0;
+        while range < ranges.len() {
+            if !ranges[range].aligned() {
+                panic!("Bus range not aligned to pages");
+            }
+            let mut page = bus_page(ranges[range].start);
+            while page <= bus_page(ranges[range].end) {
+                if table[page].is_some() {
+                    panic!("Bus page mapped more than once");
+                }
+                table[page] = Some(ranges[range].region);
+                page += 1;
+            }
+            range += 1;
+        }
+        component += 1;
+    }
+
+    let mut regions = [BusRegion::Rom; BUS_TABLE_SIZE];
+    let mut page = 0;
+    while page < BUS_TABLE_SIZE {
+        regions[page] = match table[page] {
+            Some(region) => region,
+            None => panic!("Bus page not mapped"),
+        };
+        page += 1;
+    }
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bus_page, BusRange, BusRegion, BUS_TABLE};
+
+    #[test]
+    fn test_bus_table() {
+        assert_eq!(BUS_TABLE[bus_page(0x0000)], BusRegion::Rom);
+        assert_eq!(BUS_TABLE[bus_page(0x7fff)], BusRegion::Rom);
+        assert_eq!(BUS_TABLE[bus_page(0x9abc)], BusRegion::Vram);
+        assert_eq!(BUS_TABLE[bus_page(0xa000)], BusRegion::ExtRam);
+        assert_eq!(BUS_TABLE[bus_page(0xc123)], BusRegion::Wram0);
+        assert_eq!(BUS_TABLE[bus_page(0xd000)], BusRegion::WramX);
+        assert_eq!(BUS_TABLE[bus_page(0xfdff)], BusRegion::Echo);
+        assert_eq!(BUS_TABLE[bus_page(0xfea0)], BusRegion::Oam);
+        assert_eq!(BUS_TABLE[bus_page(0xffff)], BusRegion::High);
+
+        assert!(BusRange::new(0xa000, 0xbfff, BusRegion::ExtRam).aligned());
+        assert!(!BusRange::new(0xa000, 0xa0fe, BusRegion::ExtRam).aligned());
+        assert!(!BusRange::new(0xa080, 0xa0ff, BusRegion::ExtRam).aligned());
+        assert_eq!(
+            format!("{}", BusRange::new(0xa000, 0xa0ff, BusRegion::Device(1))),
+            "0xa000-0xa0ff Device #1"
+        );
+    }
+}
@@ -0,0 +1,579 @@
+//! Screenshot and video capture of the emulator output.
+//!
+//! Encodes PNG images and animated PNG (APNG) recordings directly
+//! in the core, using the deflate codec of the encoding crate, so
+//! that no image crate is required by the frontends (eg: WASM).
+//! The (8 bit RGB and RGBA) PNG images can be decoded as well, so
+//! that reference images can be compared against the frame buffer.
+//!
+//! Also provides streaming writers of uncompressed video (Y4M) and
+//! audio (WAV) files, that can be muxed by external tools.
+
+use boytacean_common::error::Error;
+use boytacean_encoding::deflate::{decode_deflate, encode_deflate};
+use boytacean_hashing::crc32::crc32;
+use std::{
+    collections::VecDeque,
+    io::{Seek, SeekFrom, Write},
+};
+
+/// The PNG file signature.
+const PNG_SIGNATURE: [u8; 8] = [0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a];
+
+/// Accumulates the frames of a recording, merging consecutive
+/// equal frames, to be encoded as an animated PNG (APNG).
+pub struct FrameRecorder {
+    width: u32,
+    height: u32,
+
+    /// The number of frames per second of the recording, used
+    /// to compute the delay of each of the frames.
+    fps: u16,
+
+    /// The maximum number of (unique) frames kept, the oldest
+    /// frames are discarded once the limit is reached.
+    max_frames: usize,
+
+    /// The RGB888 frames and the number of times (ticks) each
+    /// of them is repeated.
+    frames: VecDeque<(Vec<u8>, u16)>,
+}
+
+impl FrameRecorder {
+    pub fn new(width: u32, height: u32, fps: u16) -> Self {
+        Self {
+            width,
+            height,
+            fps,
+            max_frames: 3600,
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Adds a new RGB888 frame to the recording, a frame equal to
+    /// the previous one only extends the duration of the latter.
+    pub fn push(&mut self, frame: &[u8]) {
+        if let Some((last, ticks)) = self.frames.back_mut() {
+            if last.as_slice() == frame && *ticks < u16::MAX {
+                *ticks += 1;
+                return;
+            }
+        }
+        while self.frames.len() >= self.max_frames {
+            self.frames.pop_front();
+        }
+        self.frames.push_back((frame.to_vec(), 1));
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// The number of unique frames in the recording.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// The total number of frames (ticks) in the recording,
+    /// including the repeated ones.
+    pub fn ticks(&self) -> u32 {
+        self.frames.iter().map(|(_, ticks)| *ticks as u32).sum()
+    }
+
+    pub fn fps(&self) -> u16 {
+        self.fps
+    }
+
+    pub fn set_fps(&mut self, value: u16) {
+        self.fps = value;
+    }
+
+    pub fn max_frames(&self) -> usize {
+        self.max_frames
+    }
+
+    pub fn set_max_frames(&mut self, value: usize) {
+        self.max_frames = value.max(1);
+    }
+
+    /// Encodes the recording as an (infinitely looping) animated
+    /// PNG (APNG), returning an empty buffer for an empty recording.
+    pub fn encode_apng(&self) -> Vec<u8> {
+        if self.frames.is_empty() {
+            return vec![];
+        }
+
+        let mut buffer = PNG_SIGNATURE.to_vec();
+        write_chunk(&mut buffer, b"IHDR", &ihdr(self.width, self.height));
+
+        let mut actl = Vec::with_capacity(8);
+        actl.extend_from_slice(&(self.frames.len() as u32).to_be_bytes());
+        actl.extend_from_slice(&0u32.to_be_bytes());
+        write_chunk(&mut buffer, b"acTL", &actl);
+
+        let mut sequence = 0u32;
+        for (index, (frame, ticks)) in self.frames.iter().enumerate() {
+            let mut fctl = Vec::with_capacity(26);
+            fctl.extend_from_slice(&sequence.to_be_bytes());
+            fctl.extend_from_slice(&self.width.to_be_bytes());
+            fctl.extend_from_slice(&self.height.to_be_bytes());
+            fctl.extend_from_slice(&0u32.to_be_bytes());
+            fctl.extend_from_slice(&0u32.to_be_bytes());
+            fctl.extend_from_slice(&ticks.to_be_bytes());
+            fctl.extend_from_slice(&self.fps.to_be_bytes());
+            fctl.extend_from_slice(&[0x00, 0x00]);
+            write_chunk(&mut buffer, b"fcTL", &fctl);
+            sequence += 1;
+
+            let data = zlib(&scanlines(frame, self.width, self.height));
+            if index == 0 {
+                write_chunk(&mut buffer, b"IDAT", &data);
+            } else {
+                let mut fdat = Vec::with_capacity(data.len() + 4);
+                fdat.extend_from_slice(&sequence.to_be_bytes());
+                fdat.extend_from_slice(&data);
+                write_chunk(&mut buffer, b"fdAT", &fdat);
+                sequence += 1;
+            }
+        }
+
+        write_chunk(&mut buffer, b"IEND", &[]);
+        buffer
+    }
+}
+
+/// Streaming writer of raw (uncompressed) video in the YUV4MPEG2
+/// (Y4M) format, using full resolution (4:4:4) chroma and the
+/// BT.601 (limited range) conversion of the RGB888 frames.
+pub struct Y4mWriter<W: Write> {
+    writer: W,
+    width: u32,
+    height: u32,
+
+    /// The number of frames written so far.
+    frames: u32,
+
+    /// The Y, Cb and Cr planes of the frame being written,
+    /// kept to avoid allocations per frame.
+    planes: Vec<u8>,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    /// Creates a new writer writing the stream header, the frame
+    /// rate is defined as a fraction (eg: 262144/4389 for the
+    /// ~59.73 Hz of the Game Boy) so that it's exact.
+    pub fn new(
+        mut writer: W,
+        width: u32,
+        height: u32,
+        fps_num: u32,
+        fps_den: u32,
+    ) -> Result<Self, Error> {
+        writer.write_all(
+            format!("YUV4MPEG2 W{width} H{height} F{fps_num}:{fps_den} Ip A1:1 C444\n").as_bytes(),
+        )?;
+        Ok(Self {
+            writer,
+            width,
+            height,
+            frames: 0,
+            planes: vec![0; (width * height * 3) as usize],
+        })
+    }
+
+    /// Writes a new RGB888 frame to the stream.
+    pub fn write_frame(&mut self, frame: &[u8]) -> Result<(), Error> {
+        let size = (self.width * self.height) as usize;
+        if frame.len() != size * 3 {
+            return Err(Error::CustomError(format!(
+                "Invalid frame size: {} (expected {})",
+                frame.len(),
+                size * 3
+            )));
+        }
+        for (index, pixel) in frame.chunks_exact(3).enumerate() {
+            let (r, g, b) = (pixel[0] as i32, pixel[1] as i32, pixel[2] as i32);
+            self.planes[index] = (((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8;
+            self.planes[size + index] = (((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8;
+            self.planes[size * 2 + index] = (((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8;
+        }
+        self.writer.write_all(b"FRAME\n")?;
+        self.writer.write_all(&self.planes)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Flushes the stream returning the underlying writer.
+    pub fn finish(mut self) -> Result<W, Error> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Streaming writer of 16 bit PCM audio in the WAV (RIFF) format,
+/// the sizes in the header are only valid once finished.
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+
+    /// The number of (per channel) samples written so far.
+    samples: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    /// The size of the RIFF header, before the samples data.
+    const HEADER_SIZE: u32 = 44;
+
+    pub fn new(mut writer: W, sampling_rate: u32, channels: u16) -> Result<Self, Error> {
+        let block_align = channels as u32 * 2;
+        let mut header = Vec::with_capacity(Self::HEADER_SIZE as usize);
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&(Self::HEADER_SIZE - 8).to_le_bytes());
+        header.extend_from_slice(b"WAVEfmt ");
+        header.extend_from_slice(&16u32.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&channels.to_le_bytes());
+        header.extend_from_slice(&sampling_rate.to_le_bytes());
+        header.extend_from_slice(&(sampling_rate * block_align).to_le_bytes());
+        header.extend_from_slice(&(block_align as u16).to_le_bytes());
+        header.extend_from_slice(&16u16.to_le_bytes());
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&0u32.to_le_bytes());
+        writer.write_all(&header)?;
+        Ok(Self { writer, samples: 0 })
+    }
+
+    /// Writes the provided (interleaved in case of multiple
+    /// channels) samples to the stream.
+    pub fn write_samples(&mut self, samples: &[i16]) -> Result<(), Error> {
+        let data: Vec<u8> = samples.iter().flat_map(|v| v.to_le_bytes()).collect();
+        self.writer.write_all(&data)?;
+        self.samples += samples.len() as u32;
+        Ok(())
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Updates the sizes in the header (using the number of samples
+    /// written) and flushes the stream returning the underlying writer.
+    pub fn finish(mut self) -> Result<W, Error> {
+        let data_size = self.samples * 2;
+        self.writer.seek(SeekFrom::Start(4))?;
+        self.writer
+            .write_all(&(Self::HEADER_SIZE - 8 + data_size).to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(40))?;
+        self.writer.write_all(&data_size.to_le_bytes())?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Encodes the provided RGB888 pixels as a PNG image.
+pub fn encode_png(pixels: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let mut buffer = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut buffer, b"IHDR", &ihdr(width, height));
+    write_chunk(
+        &mut buffer,
+        b"IDAT",
+        &zlib(&scanlines(pixels, width, height)),
+    );
+    write_chunk(&mut buffer, b"IEND", &[]);
+    buffer
+}
+
+fn ihdr(width: u32, height: u32) -> Vec<u8> {
+    let mut data = Vec::with_capacity(13);
+    data.extend_from_slice(&width.to_be_bytes());
+    data.extend_from_slice(&height.to_be_bytes());
+
+    // 8 bits per channel, RGB color type, default compression,
+    // filter and no interlace
+    data.extend_from_slice(&[0x08, 0x02, 0x00, 0x00, 0x00]);
+    data
+}
+
+fn write_chunk(buffer: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    buffer.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = buffer.len();
+    buffer.extend_from_slice(kind);
+    buffer.extend_from_slice(data);
+    let crc = crc32(&buffer[start..]);
+    buffer.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Prefixes each of the RGB888 rows with the PNG filter type,
+/// using no filtering as the deflate matches already take care
+/// of the (very common) repeated content.
+fn scanlines(pixels: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let stride = width as usize * 3;
+    let mut data = Vec::with_capacity((stride + 1) * height as usize);
+    for row in pixels.chunks(stride).take(height as usize) {
+        data.push(0x00);
+        data.extend_from_slice(row);
+    }
+    data
+}
+
+/// Compresses the data into a zlib stream, as expected by the
+/// PNG image data chunks.
+fn zlib(data: &[u8]) -> Vec<u8> {
+    // the encoding is done in memory so it's not expected to fail
+    encode_deflate(data).expect("Failed to deflate PNG data")
+}
+
+/// Decodes a (non interlaced, 8 bits per channel) RGB or RGBA PNG
+/// image, returning its RGB888 pixels (alpha is dropped), width
+/// and height.
+pub fn decode_png(data: &[u8]) -> Result<(Vec<u8>, u32, u32), Error> {
+    let invalid = |message: &str| Error::DataError(format!("Invalid PNG: {message}"));
+
+    if data.len() < 8 || data[..8] != PNG_SIGNATURE {
+        return Err(invalid("bad signature"));
+    }
+
+    let mut header = None;
+    let mut compressed = vec![];
+    let mut offset = 8;
+    while offset + 12 <= data.len() {
+        let length = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        if offset + 12 + length > data.len() {
+            return Err(invalid("truncated chunk"));
+        }
+        let kind = &data[offset + 4..offset + 8];
+        let body = &data[offset + 8..offset + 8 + length];
+        let crc = u32::from_be_bytes(
+            data[offset + 8 + length..offset + 12 + length]
+                .try_into()
+                .unwrap(),
+        );
+        if crc32(&data[offset + 4..offset + 8 + length]) != crc {
+            return Err(invalid("bad chunk CRC"));
+        }
+        match kind {
+            b"IHDR" if length == 13 => header = Some(body.to_vec()),
+            b"IDAT" => compressed.extend_from_slice(body),
+            b"IEND" => break,
+            _ => (),
+        }
+        offset += 12 + length;
+    }
+
+    let header = header.ok_or_else(|| invalid("missing header"))?;
+    let width = u32::from_be_bytes(header[0..4].try_into().unwrap());
+    let height = u32::from_be_bytes(header[4..8].try_into().unwrap());
+    let channels = match (header[8], header[9], header[12]) {
+        (0x08, 0x02, 0x00) => 3,
+        (0x08, 0x06, 0x00) => 4,
+        _ => return Err(invalid("unsupported format")),
+    };
+
+    if compressed.is_empty() {
+        return Err(invalid("missing data"));
+    }
+    let filtered = decode_deflate(&compressed).map_err(|_| invalid("bad compressed data"))?;
+    let stride = width as usize * channels;
+    if filtered.len() < (stride + 1) * height as usize {
+        return Err(invalid("truncated data"));
+    }
+
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 3);
+    let mut previous = vec![0u8; stride];
+    let mut row = vec![0u8; stride];
+    for line in filtered.chunks(stride + 1).take(height as usize) {
+        unfilter(line[0], &line[1..], &previous, &mut row, channels)?;
+        for pixel in row.chunks(channels) {
+            pixels.extend_from_slice(&pixel[..3]);
+        }
+        std::mem::swap(&mut previous, &mut row);
+    }
+
+    Ok((pixels, width, height))
+}
+
+/// Reverts the PNG filter applied to a row, using the previous
+/// (already reconstructed) row as reference.
+fn unfilter(
+    filter: u8,
+    line: &[u8],
+    previous: &[u8],
+    row: &mut [u8],
+    channels: usize,
+) -> Result<(), Error> {
+    for index in 0..line.len() {
+        let left = if index >= channels {
+            row[index - channels]
+        } else {
+            0
+        };
+        let up = previous[index];
+        let up_left = if index >= channels {
+            previous[index - channels]
+        } else {
+            0
+        };
+        let predictor = match filter {
+            0 => 0,
+            1 => left,
+            2 => up,
+            3 => ((left as u16 + up as u16) / 2) as u8,
+            4 => paeth(left, up, up_left),
+            _ => return Err(Error::DataError(format!("Invalid PNG filter: {filter}"))),
+        };
+        row[index] = line[index].wrapping_add(predictor);
+    }
+    Ok(())
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let left_distance = (estimate - left as i16).abs();
+    let up_distance = (estimate - up as i16).abs();
+    let up_left_distance = (estimate - up_left as i16).abs();
+    if left_distance <= up_distance && left_distance <= up_left_distance {
+        left
+    } else if up_distance <= up_left_distance {
+        up
+    } else {
+        up_left
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use boytacean_hashing::crc32::crc32;
+    use std::io::Cursor;
+
+    use super::{decode_png, encode_png, FrameRecorder, WavWriter, Y4mWriter, PNG_SIGNATURE};
+
+    /// Iterates over the (kind, data) chunks of a PNG file,
+    /// verifying the CRC of each of them.
+    fn chunks(data: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+        let mut chunks = vec![];
+        let mut offset = 8;
+        while offset < data.len() {
+            let length = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+            let kind: [u8; 4] = data[offset + 4..offset + 8].try_into().unwrap();
+            let body = data[offset + 8..offset + 8 + length].to_vec();
+            let crc = u32::from_be_bytes(
+                data[offset + 8 + length..offset + 12 + length]
+                    .try_into()
+                    .unwrap(),
+            );
+            assert_eq!(crc32(&data[offset + 4..offset + 8 + length]), crc);
+            chunks.push((kind, body));
+            offset += 12 + length;
+        }
+        chunks
+    }
+
+    #[test]
+    fn test_encode_png() {
+        let pixels: Vec<u8> = (0..160 * 144 * 3)
+            .map(|index| (index / 480) as u8)
+            .collect();
+        let png = encode_png(&pixels, 160, 144);
+        assert_eq!(png[..8], PNG_SIGNATURE);
+
+        let chunks = chunks(&png);
+        let kinds: Vec<&[u8; 4]> = chunks.iter().map(|(kind, _)| kind).collect();
+        assert_eq!(kinds, vec![b"IHDR", b"IDAT", b"IEND"]);
+        assert_eq!(
+            chunks[0].1,
+            [0, 0, 0, 160, 0, 0, 0, 144, 0x08, 0x02, 0x00, 0x00, 0x00]
+        );
+
+        // the repeated rows must be compressed by the deflate matches
+        assert!(chunks[1].1.len() < pixels.len() / 10);
+    }
+
+    #[test]
+    fn test_decode_png() {
+        let pixels: Vec<u8> = (0..160 * 144 * 3)
+            .map(|index| (index % 251) as u8)
+            .collect();
+        let (decoded, width, height) = decode_png(&encode_png(&pixels, 160, 144)).unwrap();
+        assert_eq!((width, height), (160, 144));
+        assert_eq!(decoded, pixels);
+
+        let mut png = encode_png(&pixels, 160, 144);
+        png[40] ^= 0xff;
+        assert!(decode_png(&png).is_err());
+        assert!(decode_png(&[0x00; 16]).is_err());
+    }
+
+    #[test]
+    fn test_frame_recorder() {
+        let mut recorder = FrameRecorder::new(2, 2, 60);
+        assert!(recorder.encode_apng().is_empty());
+
+        recorder.push(&[0xff; 12]);
+        recorder.push(&[0xff; 12]);
+        recorder.push(&[0x00; 12]);
+        assert_eq!(recorder.len(), 2);
+        assert_eq!(recorder.ticks(), 3);
+
+        let apng = recorder.encode_apng();
+        let chunks = chunks(&apng);
+        let kinds: Vec<&[u8; 4]> = chunks.iter().map(|(kind, _)| kind).collect();
+        assert_eq!(
+            kinds,
+            vec![b"IHDR", b"acTL", b"fcTL", b"IDAT", b"fcTL", b"fdAT", b"IEND"]
+        );
+        assert_eq!(chunks[1].1, [0, 0, 0, 2, 0, 0, 0, 0]);
+
+        // the first frame lasts for two ticks (2/60 seconds)
+        assert_eq!(chunks[2].1[20..24], [0, 2, 0, 60]);
+        assert_eq!(chunks[4].1[0..4], [0, 0, 0, 1]);
+        assert_eq!(chunks[5].1[0..4], [0, 0, 0, 2]);
+
+        recorder.set_max_frames(1);
+        recorder.push(&[0x80; 12]);
+        assert_eq!(recorder.len(), 1);
+        recorder.clear();
+        assert!(recorder.is_empty());
+    }
+
+    #[test]
+    fn test_y4m_writer() {
+        let mut writer = Y4mWriter::new(vec![], 2, 1, 262144, 4389).unwrap();
+        writer
+            .write_frame(&[0xff, 0xff, 0xff, 0x00, 0x00, 0x00])
+            .unwrap();
+        assert!(writer.write_frame(&[0x00; 3]).is_err());
+        assert_eq!(writer.frames(), 1);
+
+        let data = writer.finish().unwrap();
+        let header = b"YUV4MPEG2 W2 H1 F262144:4389 Ip A1:1 C444\n";
+        assert_eq!(data[..header.len()], header[..]);
+        assert_eq!(
+            data[header.len()..],
+            [b"FRAME\n".as_slice(), &[235, 16, 128, 128, 128, 128]].concat()
+        );
+    }
+
+    #[test]
+    fn test_wav_writer() {
+        let mut writer = WavWriter::new(Cursor::new(vec![]), 44100, 2).unwrap();
+        writer.write_samples(&[0, 1, -1, i16::MAX]).unwrap();
+        assert_eq!(writer.samples(), 4);
+
+        let data = writer.finish().unwrap().into_inner();
+        assert_eq!(data.len(), 44 + 8);
+        assert_eq!(data[0..4], *b"RIFF");
+        assert_eq!(data[4..8], 44u32.to_le_bytes());
+        assert_eq!(data[24..28], 44100u32.to_le_bytes());
+        assert_eq!(data[28..32], (44100u32 * 4).to_le_bytes());
+        assert_eq!(data[40..44], 8u32.to_le_bytes());
+        assert_eq!(data[44..], [0, 0, 1, 0, 0xff, 0xff, 0xff, 0x7f]);
+    }
+}
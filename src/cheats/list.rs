@@ -0,0 +1,397 @@
+//! Persistent cheat lists, stored per game and keyed by the
+//! fingerprint (CRC32) of the ROM contents.
+//!
+//! The file format is compatible with the RetroArch `.cht`
+//! format, meaning that files can be exchanged with other
+//! emulators that support that format.
+
+use boytacean_common::{
+    error::Error,
+    util::{read_file, write_file},
+};
+use boytacean_hashing::crc32::crc32;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+};
+
+use crate::warnln;
+
+use super::{genie::GameGenie, shark::GameShark};
+
+/// The extension used for the cheat list files.
+pub const CHEAT_EXTENSION: &str = "cht";
+
+/// The key used in the cheat file to store the fingerprint of
+/// the ROM, ignored by RetroArch as it's not a known key.
+const FINGERPRINT_KEY: &str = "boytacean_fingerprint";
+
+/// Single entry in a cheat list, represents a code (Game Genie
+/// or GameShark) with a description and an enable flag.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheatEntry {
+    code: String,
+    description: String,
+    enabled: bool,
+}
+
+impl CheatEntry {
+    pub fn new(code: &str, description: &str, enabled: bool) -> Self {
+        Self {
+            code: code.trim().to_uppercase(),
+            description: String::from(description),
+            enabled,
+        }
+    }
+
+    /// Checks if the code of the entry is a valid Game Genie
+    /// or GameShark code (format wise).
+    pub fn is_valid(&self) -> bool {
+        GameGenie::is_code(&self.code) || GameShark::is_code(&self.code)
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn set_description(&mut self, description: &str) {
+        self.description = String::from(description);
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+}
+
+impl Display for CheatEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let status = if self.enabled { "on" } else { "off" };
+        if self.description.is_empty() {
+            write!(f, "{} [{}]", self.code, status)
+        } else {
+            write!(f, "{} ({}) [{}]", self.code, self.description, status)
+        }
+    }
+}
+
+/// List of cheats associated with a specific game, identified
+/// by the fingerprint of its ROM data.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CheatList {
+    fingerprint: u32,
+    entries: Vec<CheatEntry>,
+}
+
+impl CheatList {
+    pub fn new(fingerprint: u32) -> Self {
+        Self {
+            fingerprint,
+            entries: vec![],
+        }
+    }
+
+    /// Creates an empty cheat list for the provided ROM data,
+    /// computing the ROM fingerprint in the process.
+    pub fn from_rom_data(data: &[u8]) -> Self {
+        Self::new(Self::fingerprint_rom(data))
+    }
+
+    /// Computes the fingerprint (CRC32) of the provided ROM data.
+    pub fn fingerprint_rom(data: &[u8]) -> u32 {
+        crc32(data)
+    }
+
+    pub fn fingerprint(&self) -> u32 {
+        self.fingerprint
+    }
+
+    /// Obtains the default file name for the cheat list, which
+    /// is based on the fingerprint of the ROM (eg: `0a1b2c3d.cht`).
+    pub fn file_name(&self) -> String {
+        format!("{:08x}.{CHEAT_EXTENSION}", self.fingerprint)
+    }
+
+    pub fn entries(&self) -> &Vec<CheatEntry> {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&CheatEntry> {
+        self.entries.get(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut CheatEntry> {
+        self.entries.get_mut(index)
+    }
+
+    /// Iterates over the codes of the entries that are currently
+    /// enabled, in the order they have been added.
+    pub fn enabled_codes(&self) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.enabled)
+            .map(|entry| entry.code())
+    }
+
+    /// Adds a new code to the list, in case the code already
+    /// exists its description and enable flag are updated instead.
+    ///
+    /// Returns the index of the entry in the list.
+    pub fn add(&mut self, code: &str, description: &str, enabled: bool) -> Result<usize, Error> {
+        let entry = CheatEntry::new(code, description, enabled);
+        if !entry.is_valid() {
+            return Err(Error::InvalidParameter(format!(
+                "Not a valid cheat code: {code}"
+            )));
+        }
+        if let Some(index) = self.index_of(entry.code()) {
+            self.entries[index] = entry;
+            return Ok(index);
+        }
+        self.entries.push(entry);
+        Ok(self.entries.len() - 1)
+    }
+
+    /// Sets the entry at the provided index, in case the index is
+    /// beyond the end of the list the entry is appended instead.
+    ///
+    /// Returns the index of the entry in the list.
+    pub fn set(
+        &mut self,
+        index: usize,
+        code: &str,
+        description: &str,
+        enabled: bool,
+    ) -> Result<usize, Error> {
+        let entry = CheatEntry::new(code, description, enabled);
+        if !entry.is_valid() {
+            return Err(Error::InvalidParameter(format!(
+                "Not a valid cheat code: {code}"
+            )));
+        }
+        if index < self.entries.len() {
+            self.entries[index] = entry;
+            return Ok(index);
+        }
+        self.entries.push(entry);
+        Ok(self.entries.len() - 1)
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<CheatEntry> {
+        if index >= self.entries.len() {
+            return None;
+        }
+        Some(self.entries.remove(index))
+    }
+
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) -> Result<(), Error> {
+        match self.entries.get_mut(index) {
+            Some(entry) => {
+                entry.set_enabled(enabled);
+                Ok(())
+            }
+            None => Err(Error::InvalidParameter(format!(
+                "Invalid cheat index: {index}"
+            ))),
+        }
+    }
+
+    /// Toggles the enable flag of the entry at the provided index
+    /// returning the new value of the flag.
+    pub fn toggle(&mut self, index: usize) -> Result<bool, Error> {
+        let enabled = match self.entries.get(index) {
+            Some(entry) => !entry.enabled(),
+            None => {
+                return Err(Error::InvalidParameter(format!(
+                    "Invalid cheat index: {index}"
+                )))
+            }
+        };
+        self.set_enabled(index, enabled)?;
+        Ok(enabled)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn index_of(&self, code: &str) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.code == code)
+    }
+
+    /// Parses the contents of a RetroArch compatible `.cht` file,
+    /// entries without a code are ignored, as are unknown keys,
+    /// entries with an invalid code are skipped with a warning.
+    pub fn from_cht(data: &str) -> Result<Self, Error> {
+        let mut values: HashMap<String, String> = HashMap::new();
+        for line in data.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or(Error::InvalidData)?;
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(value);
+            values.insert(String::from(key.trim()), String::from(value));
+        }
+
+        let fingerprint = match values.get(FINGERPRINT_KEY) {
+            Some(value) => u32::from_str_radix(value.trim_start_matches("0x"), 16)
+                .map_err(|_| Error::InvalidData)?,
+            None => 0,
+        };
+        let count = match values.get("cheats") {
+            Some(value) => value.parse::<usize>().map_err(|_| Error::InvalidData)?,
+            None => 0,
+        };
+
+        let mut list = Self::new(fingerprint);
+        for index in 0..count {
+            let code = match values.get(&format!("cheat{index}_code")) {
+                Some(code) if !code.is_empty() => code,
+                _ => continue,
+            };
+            let description = values
+                .get(&format!("cheat{index}_desc"))
+                .map(|value| value.as_str())
+                .unwrap_or("");
+            let enabled = values
+                .get(&format!("cheat{index}_enable"))
+                .map(|value| value == "true")
+                .unwrap_or(false);
+            let entry = CheatEntry::new(code, description, enabled);
+            if !entry.is_valid() {
+                warnln!("Skipping invalid cheat code: {}", code);
+                continue;
+            }
+            list.entries.push(entry);
+        }
+
+        Ok(list)
+    }
+
+    /// Serializes the cheat list into the RetroArch compatible
+    /// `.cht` file format.
+    pub fn to_cht(&self) -> String {
+        let mut buffer = format!(
+            "{FINGERPRINT_KEY} = \"{:08x}\"\ncheats = {}\n",
+            self.fingerprint,
+            self.entries.len()
+        );
+        for (index, entry) in self.entries.iter().enumerate() {
+            buffer.push_str(&format!(
+                "\ncheat{index}_desc = \"{}\"\ncheat{index}_code = \"{}\"\ncheat{index}_enable = {}\n",
+                entry.description.replace('"', "'"),
+                entry.code,
+                entry.enabled
+            ));
+        }
+        buffer
+    }
+
+    /// Loads the cheat list from the file in the provided path.
+    pub fn load_file(path: &str) -> Result<Self, Error> {
+        let data = read_file(path)?;
+        Self::from_cht(&String::from_utf8(data)?)
+    }
+
+    /// Loads the cheat list from the file in the provided path
+    /// making sure that it's meant for the ROM with the provided
+    /// fingerprint, files without fingerprint are accepted.
+    pub fn load_file_for(path: &str, fingerprint: u32) -> Result<Self, Error> {
+        let mut list = Self::load_file(path)?;
+        if list.fingerprint != 0 && list.fingerprint != fingerprint {
+            return Err(Error::CustomError(format!(
+                "Cheat file fingerprint mismatch, expected {fingerprint:08x} got {:08x}",
+                list.fingerprint
+            )));
+        }
+        list.fingerprint = fingerprint;
+        Ok(list)
+    }
+
+    /// Saves the cheat list into the file in the provided path.
+    pub fn save_file(&self, path: &str) -> Result<(), Error> {
+        write_file(path, self.to_cht().as_bytes(), None)
+    }
+}
+
+impl Display for CheatList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (index, entry) in self.entries.iter().enumerate() {
+            writeln!(f, "{index}: {entry}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CheatList;
+
+    #[test]
+    fn test_add() {
+        let mut list = CheatList::new(0x12345678);
+        assert_eq!(list.add("00a-17b-c49", "Lives", true).unwrap(), 0);
+        assert_eq!(list.add("010138CD", "", false).unwrap(), 1);
+        assert_eq!(list.add("00A-17B-C49", "Infinite lives", false).unwrap(), 0);
+        assert!(list.add("invalid", "", true).is_err());
+        assert_eq!(list.len(), 2);
+        assert_eq!(list.get(0).unwrap().code(), "00A-17B-C49");
+        assert_eq!(list.get(0).unwrap().description(), "Infinite lives");
+        assert!(!list.get(0).unwrap().enabled());
+        assert!(list.toggle(1).unwrap());
+        assert_eq!(
+            list.enabled_codes().collect::<Vec<&str>>(),
+            vec!["010138CD"]
+        );
+        assert_eq!(list.file_name(), "12345678.cht");
+    }
+
+    #[test]
+    fn test_cht() {
+        let mut list = CheatList::new(0xdeadbeef);
+        list.add("00A-17B-C49", "Infinite \"lives\"", true).unwrap();
+        list.add("010138CD", "Max money", false).unwrap();
+
+        let data = list.to_cht();
+        assert!(data.contains("cheats = 2"));
+        assert!(data.contains("cheat0_code = \"00A-17B-C49\""));
+        assert!(data.contains("cheat1_enable = false"));
+
+        let loaded = CheatList::from_cht(&data).unwrap();
+        assert_eq!(loaded.fingerprint(), 0xdeadbeef);
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.get(0).unwrap().description(), "Infinite 'lives'");
+        assert!(loaded.get(0).unwrap().enabled());
+        assert_eq!(loaded.get(1).unwrap().code(), "010138CD");
+        assert!(!loaded.get(1).unwrap().enabled());
+    }
+
+    #[test]
+    fn test_cht_retroarch() {
+        let data = "cheats = 3\n\ncheat0_desc = \"A\"\ncheat0_code = \"010138CD\"\ncheat0_enable = true\n\ncheat1_desc = \"B\"\ncheat1_code = \"\"\ncheat1_enable = false\n\ncheat2_desc = \"C\"\ncheat2_code = \"invalid\"\ncheat2_enable = true\n";
+        let list = CheatList::from_cht(data).unwrap();
+        assert_eq!(list.fingerprint(), 0);
+        assert_eq!(list.len(), 1);
+        assert_eq!(list.get(0).unwrap().description(), "A");
+    }
+}
@@ -0,0 +1,171 @@
+//! Runtime management of the cheats of a running system, keeping
+//! a cheat list and the codes registered in the system in sync.
+//!
+//! Every change to the list (adding, removing, enabling or disabling
+//! an entry) is immediately reflected in the system, allowing the
+//! individual cheats to be toggled while the game is running.
+
+use boytacean_common::error::Error;
+
+use crate::gb::GameBoy;
+
+use super::list::{CheatEntry, CheatList};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CheatManager {
+    list: CheatList,
+}
+
+impl CheatManager {
+    pub fn new(list: CheatList) -> Self {
+        Self { list }
+    }
+
+    pub fn list(&self) -> &CheatList {
+        &self.list
+    }
+
+    pub fn into_list(self) -> CheatList {
+        self.list
+    }
+
+    /// Replaces the cheat list, applying its enabled entries
+    /// to the system.
+    pub fn load(&mut self, system: &mut GameBoy, list: CheatList) -> Result<usize, Error> {
+        self.list = list;
+        self.apply(system)
+    }
+
+    /// Registers the enabled entries of the list in the system,
+    /// replacing any previously registered cheat codes.
+    ///
+    /// Returns the number of codes that have been applied.
+    pub fn apply(&self, system: &mut GameBoy) -> Result<usize, Error> {
+        system.apply_cheats(&self.list)
+    }
+
+    pub fn add(
+        &mut self,
+        system: &mut GameBoy,
+        code: &str,
+        description: &str,
+        enabled: bool,
+    ) -> Result<usize, Error> {
+        let index = self.list.add(code, description, enabled)?;
+        self.apply(system)?;
+        Ok(index)
+    }
+
+    pub fn set(
+        &mut self,
+        system: &mut GameBoy,
+        index: usize,
+        code: &str,
+        description: &str,
+        enabled: bool,
+    ) -> Result<usize, Error> {
+        let index = self.list.set(index, code, description, enabled)?;
+        self.apply(system)?;
+        Ok(index)
+    }
+
+    /// Enables or disables the cheat at the provided index, with
+    /// the change taking effect immediately in the system.
+    pub fn set_enabled(
+        &mut self,
+        system: &mut GameBoy,
+        index: usize,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        self.list.set_enabled(index, enabled)?;
+        self.apply(system)?;
+        Ok(())
+    }
+
+    pub fn toggle(&mut self, system: &mut GameBoy, index: usize) -> Result<bool, Error> {
+        let enabled = self.list.toggle(index)?;
+        self.apply(system)?;
+        Ok(enabled)
+    }
+
+    pub fn remove(&mut self, system: &mut GameBoy, index: usize) -> Result<CheatEntry, Error> {
+        let entry = self
+            .list
+            .remove(index)
+            .ok_or(Error::InvalidParameter(format!(
+                "Invalid cheat index: {index}"
+            )))?;
+        self.apply(system)?;
+        Ok(entry)
+    }
+
+    pub fn clear(&mut self, system: &mut GameBoy) {
+        self.list.clear();
+        system.reset_cheats();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CheatManager;
+
+    use crate::gb::GameBoy;
+
+    #[test]
+    fn test_set_enabled() {
+        let mut game_boy = GameBoy::default();
+        game_boy.load(false).unwrap();
+        game_boy.load_rom_empty().unwrap();
+        let mut manager = CheatManager::new(game_boy.cheat_list());
+
+        manager
+            .add(&mut game_boy, "01420AC1", "Infinite lives", true)
+            .unwrap();
+        manager.add(&mut game_boy, "F0C95001", "", false).unwrap();
+        game_boy.mmu().vblank();
+        assert_eq!(game_boy.read_memory(0xc10a), 0x42);
+        assert_eq!(game_boy.read_memory(0x0150), 0x00);
+
+        manager.set_enabled(&mut game_boy, 1, true).unwrap();
+        assert_eq!(game_boy.read_memory(0x0150), 0xc9);
+
+        manager.set_enabled(&mut game_boy, 0, false).unwrap();
+        game_boy.write_memory(0xc10a, 0x01);
+        game_boy.mmu().vblank();
+        assert_eq!(game_boy.read_memory(0xc10a), 0x01);
+        assert!(manager.set_enabled(&mut game_boy, 2, true).is_err());
+
+        manager.clear(&mut game_boy);
+        assert!(manager.list().is_empty());
+        assert_eq!(game_boy.read_memory(0x0150), 0x00);
+    }
+
+    #[test]
+    fn test_apply_invalid() {
+        let mut game_boy = GameBoy::default();
+        game_boy.load(false).unwrap();
+        game_boy.load_rom_empty().unwrap();
+        let mut manager = CheatManager::new(game_boy.cheat_list());
+
+        manager
+            .add(&mut game_boy, "F0C95001", "Valid", true)
+            .unwrap();
+        assert_eq!(game_boy.read_memory(0x0150), 0xc9);
+
+        // the code has a valid format but can't be parsed, so the
+        // list is not applied and the previous cheats are kept
+        assert!(manager.add(&mut game_boy, "ZZC95001", "", true).is_err());
+        assert_eq!(manager.list().len(), 2);
+        assert_eq!(
+            game_boy
+                .rom_i()
+                .game_shark()
+                .as_ref()
+                .unwrap()
+                .cheats()
+                .len(),
+            1
+        );
+        assert_eq!(game_boy.read_memory(0x0150), 0xc9);
+    }
+}
@@ -4,4 +4,7 @@
 //! and [GameShark](https://en.wikipedia.org/wiki/GameShark) systems.
 
 pub mod genie;
+pub mod list;
+pub mod manager;
+pub mod search;
 pub mod shark;
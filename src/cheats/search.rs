@@ -0,0 +1,414 @@
+//! RAM search (scanner) engine used to find the addresses of
+//! the values (eg: lives, health, money) to be patched by cheats.
+//!
+//! The search works over consecutive snapshots of the searchable
+//! memory (see [`SEARCH_RANGES`]), the first scan considers every
+//! address a candidate and each of the following scans narrows
+//! the candidates down by comparing their current value against
+//! a constant or against the value in the previous snapshot.
+
+use boytacean_common::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// The memory ranges (start address and length) covered by the
+/// search, the WRAM (current bank) followed by the HRAM.
+pub const SEARCH_RANGES: [(u16, u16); 2] = [(0xc000, 0x2000), (0xff80, 0x007f)];
+
+/// The size of a snapshot of the searchable memory, the sum of
+/// the lengths of every one of the [`SEARCH_RANGES`].
+pub const SEARCH_MEMORY_SIZE: usize = 0x2000 + 0x007f;
+
+/// The size of the values being searched, with words being
+/// read in little endian (as stored by the CPU).
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SearchSize {
+    #[default]
+    Byte,
+    Word,
+}
+
+impl SearchSize {
+    pub fn description(&self) -> &'static str {
+        match self {
+            SearchSize::Byte => "Byte",
+            SearchSize::Word => "Word",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "byte" | "8" => Ok(SearchSize::Byte),
+            "word" | "16" => Ok(SearchSize::Word),
+            _ => Err(Error::CustomError(format!("Invalid search size: {value}"))),
+        }
+    }
+
+    pub fn mask(&self) -> u16 {
+        match self {
+            SearchSize::Byte => 0x00ff,
+            SearchSize::Word => 0xffff,
+        }
+    }
+
+    pub fn bytes(&self) -> usize {
+        match self {
+            SearchSize::Byte => 1,
+            SearchSize::Word => 2,
+        }
+    }
+}
+
+impl Display for SearchSize {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// The comparison used to filter the candidates of a scan, the
+/// current value is compared against the value provided to the
+/// scan or, if none is provided, against the previous value.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SearchOperator {
+    #[default]
+    Equal,
+    NotEqual,
+    Greater,
+    Less,
+
+    /// The value changed from the previous one by exactly the
+    /// provided (signed) amount, wrapping around the size.
+    ChangedBy,
+}
+
+impl SearchOperator {
+    pub fn description(&self) -> &'static str {
+        match self {
+            SearchOperator::Equal => "Equal",
+            SearchOperator::NotEqual => "Not Equal",
+            SearchOperator::Greater => "Greater",
+            SearchOperator::Less => "Less",
+            SearchOperator::ChangedBy => "Changed By",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "equal" | "==" => Ok(SearchOperator::Equal),
+            "not_equal" | "!=" => Ok(SearchOperator::NotEqual),
+            "greater" | ">" => Ok(SearchOperator::Greater),
+            "less" | "<" => Ok(SearchOperator::Less),
+            "changed_by" => Ok(SearchOperator::ChangedBy),
+            _ => Err(Error::CustomError(format!(
+                "Invalid search operator: {value}"
+            ))),
+        }
+    }
+}
+
+impl Display for SearchOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// RAM search engine, keeping the snapshot of the searchable
+/// memory taken by the last scan and the addresses that are
+/// still candidates.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Debug, Default)]
+pub struct CheatSearch {
+    size: SearchSize,
+
+    /// The snapshot of the searchable memory taken by the last
+    /// scan, empty if no scan has been made.
+    memory: Vec<u8>,
+
+    /// The addresses whose values matched every scan so far.
+    candidates: Vec<u16>,
+
+    /// The number of scans made since the first scan.
+    scans: u32,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl CheatSearch {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(size: SearchSize) -> Self {
+        Self {
+            size,
+            ..Default::default()
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.memory.clear();
+        self.candidates.clear();
+        self.scans = 0;
+    }
+
+    pub fn size(&self) -> SearchSize {
+        self.size
+    }
+
+    pub fn scans(&self) -> u32 {
+        self.scans
+    }
+
+    pub fn started(&self) -> bool {
+        !self.memory.is_empty()
+    }
+
+    pub fn count(&self) -> usize {
+        self.candidates.len()
+    }
+
+    pub fn results(&self) -> Vec<u16> {
+        self.candidates.clone()
+    }
+
+    /// The value of the provided address in the snapshot taken
+    /// by the last scan, if the address is searchable.
+    pub fn value(&self, addr: u16) -> Option<u16> {
+        if !self.started() {
+            return None;
+        }
+        Self::read(&self.memory, addr, self.size)
+    }
+
+    /// Builds the GameShark code that sets the provided address
+    /// (in the WRAM) to the provided value, to be added to the
+    /// list of cheats once the search has found the address.
+    pub fn shark_code(addr: u16, value: u8) -> String {
+        format!("01{:02X}{:02X}{:02X}", value, addr & 0x00ff, addr >> 8)
+    }
+}
+
+impl CheatSearch {
+    /// Starts a new search from the provided snapshot of the
+    /// searchable memory, every address is a candidate unless
+    /// a value is provided for the candidates to be compared
+    /// against.
+    ///
+    /// Returns the number of candidates.
+    pub fn first_scan(
+        &mut self,
+        memory: &[u8],
+        operator: SearchOperator,
+        value: Option<i32>,
+    ) -> Result<usize, Error> {
+        Self::validate(memory)?;
+        self.memory = memory.to_vec();
+        self.candidates = SEARCH_RANGES
+            .iter()
+            .flat_map(|(start, length)| *start..=*start + (*length - self.size.bytes() as u16))
+            .collect();
+        self.scans = 1;
+        if value.is_some() {
+            self.filter(memory, operator, value)?;
+        }
+        Ok(self.count())
+    }
+
+    /// Narrows down the candidates of the search keeping only
+    /// the ones whose value in the provided snapshot matches the
+    /// comparison, then stores the snapshot for the next scan.
+    ///
+    /// Returns the number of candidates.
+    pub fn next_scan(
+        &mut self,
+        memory: &[u8],
+        operator: SearchOperator,
+        value: Option<i32>,
+    ) -> Result<usize, Error> {
+        if !self.started() {
+            return Err(Error::CustomError(String::from(
+                "Search not started, a first scan is required",
+            )));
+        }
+        Self::validate(memory)?;
+        self.filter(memory, operator, value)?;
+        self.memory = memory.to_vec();
+        self.scans += 1;
+        Ok(self.count())
+    }
+
+    fn filter(
+        &mut self,
+        memory: &[u8],
+        operator: SearchOperator,
+        value: Option<i32>,
+    ) -> Result<(), Error> {
+        if operator == SearchOperator::ChangedBy && value.is_none() {
+            return Err(Error::CustomError(String::from(
+                "Changed by operator requires a value",
+            )));
+        }
+        let size = self.size;
+        let mask = size.mask();
+        let previous = &self.memory;
+        self.candidates.retain(|addr| {
+            let current = Self::read(memory, *addr, size).unwrap();
+            let previous = Self::read(previous, *addr, size).unwrap();
+            let target = value.map(|value| value as u16 & mask).unwrap_or(previous);
+            match operator {
+                SearchOperator::Equal => current == target,
+                SearchOperator::NotEqual => current != target,
+                SearchOperator::Greater => current > target,
+                SearchOperator::Less => current < target,
+                SearchOperator::ChangedBy => current.wrapping_sub(previous) & mask == target,
+            }
+        });
+        Ok(())
+    }
+
+    fn validate(memory: &[u8]) -> Result<(), Error> {
+        if memory.len() != SEARCH_MEMORY_SIZE {
+            return Err(Error::CustomError(format!(
+                "Invalid search memory size, expected {} got {}",
+                SEARCH_MEMORY_SIZE,
+                memory.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Obtains the offset of the provided address in a snapshot
+    /// of the searchable memory.
+    fn offset(addr: u16) -> Option<usize> {
+        let mut offset = 0;
+        for (start, length) in SEARCH_RANGES {
+            if addr >= start && addr - start < length {
+                return Some(offset + (addr - start) as usize);
+            }
+            offset += length as usize;
+        }
+        None
+    }
+
+    fn read(memory: &[u8], addr: u16, size: SearchSize) -> Option<u16> {
+        let offset = Self::offset(addr)?;
+        match size {
+            SearchSize::Byte => Some(memory[offset] as u16),
+            SearchSize::Word => {
+                Self::offset(addr.checked_add(1)?).filter(|next| *next == offset + 1)?;
+                Some(u16::from_le_bytes([memory[offset], memory[offset + 1]]))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl CheatSearch {
+    pub fn first_scan_wa(
+        &mut self,
+        memory: &[u8],
+        operator: SearchOperator,
+        value: Option<i32>,
+    ) -> Result<usize, String> {
+        Ok(self.first_scan(memory, operator, value)?)
+    }
+
+    pub fn next_scan_wa(
+        &mut self,
+        memory: &[u8],
+        operator: SearchOperator,
+        value: Option<i32>,
+    ) -> Result<usize, String> {
+        Ok(self.next_scan(memory, operator, value)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CheatSearch, SearchOperator, SearchSize, SEARCH_MEMORY_SIZE};
+
+    fn memory(values: &[(usize, u8)]) -> Vec<u8> {
+        let mut memory = vec![0u8; SEARCH_MEMORY_SIZE];
+        for (offset, value) in values {
+            memory[*offset] = *value;
+        }
+        memory
+    }
+
+    #[test]
+    fn test_cheat_search() {
+        let mut search = CheatSearch::new(SearchSize::Byte);
+        assert!(search
+            .next_scan(&memory(&[]), SearchOperator::Equal, None)
+            .is_err());
+        assert!(search
+            .first_scan(&[0u8; 16], SearchOperator::Equal, None)
+            .is_err());
+
+        let count = search
+            .first_scan(&memory(&[(0x10, 3)]), SearchOperator::Equal, None)
+            .unwrap();
+        assert_eq!(count, SEARCH_MEMORY_SIZE);
+
+        let count = search
+            .next_scan(
+                &memory(&[(0x10, 2), (0x2000, 0xff)]),
+                SearchOperator::Less,
+                None,
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(search.results(), vec![0xc010]);
+        assert_eq!(search.value(0xc010), Some(2));
+
+        search
+            .first_scan(
+                &memory(&[(0x10, 3), (0x2000, 0xff)]),
+                SearchOperator::NotEqual,
+                Some(0),
+            )
+            .unwrap();
+        assert_eq!(search.results(), vec![0xc010, 0xff80]);
+        let count = search
+            .next_scan(
+                &memory(&[(0x10, 3), (0x2000, 0x01)]),
+                SearchOperator::ChangedBy,
+                Some(2),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(search.results(), vec![0xff80]);
+        assert_eq!(search.scans(), 2);
+        assert!(search
+            .next_scan(&memory(&[]), SearchOperator::ChangedBy, None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_cheat_search_word() {
+        let mut search = CheatSearch::new(SearchSize::Word);
+        search
+            .first_scan(
+                &memory(&[(0x1ffe, 0x34), (0x1fff, 0x12)]),
+                SearchOperator::Equal,
+                Some(0x1234),
+            )
+            .unwrap();
+        assert_eq!(search.results(), vec![0xdffe]);
+        assert_eq!(search.value(0xdfff), None);
+        search
+            .next_scan(
+                &memory(&[(0x1ffe, 0x33), (0x1fff, 0x12)]),
+                SearchOperator::ChangedBy,
+                Some(-1),
+            )
+            .unwrap();
+        assert_eq!(search.results(), vec![0xdffe]);
+    }
+
+    #[test]
+    fn test_shark_code() {
+        assert_eq!(CheatSearch::shark_code(0xc0a2, 0x63), "0163A2C0");
+    }
+}
@@ -9,26 +9,50 @@ use crate::rom::RomType;
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
+/// The separator used between the lines of a multi-line
+/// GameShark code (as used by RetroArch).
+pub const LINE_SEPARATOR: char = '+';
+
 /// Implementation of the GameShark cheat code system
 /// that "patches" RAM entries, making use of the V-Blank
 /// time to do that.
 ///
 /// The codes in the GameShark system are in an hexadecimal
 /// ASCII format in the form of "ABCDGHEF" where:
-/// AB = RAM bank
+/// AB = RAM bank (or code type)
 /// CD = New data
 /// GH = Address LSB
 /// EF = Address MSB
 ///
+/// Besides the plain RAM writes the following code types are
+/// supported, with multi-line codes having their lines joined
+/// by a `+` (eg: "D0050AC1+01FF0AC1"):
+///
+/// | Type    | Description                                          |
+/// |---------|------------------------------------------------------|
+/// | `D0-D7` | Conditional, the next line only runs if the byte at  |
+/// |         | the address compares (see [`GameSharkCondition`])    |
+/// |         | with the data                                        |
+/// | `50`    | Slide, in the form "50NNAAVV", the write in the next |
+/// |         | line is repeated NN times incrementing the address   |
+/// |         | by AA and the data by VV on each write               |
+/// | `F0`    | ROM patch, the byte at the address (0x0000-0x7FFF)   |
+/// |         | is replaced by the data on every read                |
+///
 /// [Wikipedia - GameShark](https://en.wikipedia.org/wiki/GameShark)
 #[derive(Clone)]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct GameShark {
-    /// Hash map that contains the complete set of GameShark
-    /// codes that have been registered for the current ROM.
+    /// The complete set of GameShark cheats (each one with one
+    /// or more codes) that have been registered for the current
+    /// ROM, in the order they have been added.
     /// These codes are going to apply a series of patches to
     /// the RAM effectively allowing the user to cheat.
-    codes: HashMap<u16, GameSharkCode>,
+    cheats: Vec<GameSharkCheat>,
+
+    /// The ROM patches of the enabled cheats indexed by address,
+    /// cached as they are checked on every ROM read.
+    rom_patches: HashMap<u16, u8>,
 
     /// The kind of ROM (Cartridge) that is going to be patched.
     /// Relevant for some operations.
@@ -38,7 +62,8 @@ pub struct GameShark {
 impl GameShark {
     pub fn new() -> Self {
         Self {
-            codes: HashMap::new(),
+            cheats: vec![],
+            rom_patches: HashMap::new(),
             rom_type: RomType::RomOnly,
         }
     }
@@ -48,50 +73,127 @@ impl GameShark {
     }
 
     pub fn is_code(code: &str) -> bool {
-        if code.len() != 8 {
-            return false;
-        }
-        if code.contains('-') || code.contains('+') {
-            return false;
-        }
-        true
+        code.split(LINE_SEPARATOR)
+            .all(|line| line.len() == 8 && !line.contains('-'))
     }
 
     pub fn reset(&mut self) {
-        self.codes.clear();
+        self.cheats.clear();
+        self.rom_patches.clear();
     }
 
-    pub fn get_addr(&self, addr: u16) -> Result<&GameSharkCode, Error> {
-        match self.codes.get(&addr) {
-            Some(code) => Ok(code),
-            None => Err(Error::CustomError(format!("Invalid address: 0x{addr:04x}"))),
+    pub fn cheats(&self) -> &Vec<GameSharkCheat> {
+        &self.cheats
+    }
+
+    pub fn get(&self, index: usize) -> Result<&GameSharkCheat, Error> {
+        match self.cheats.get(index) {
+            Some(cheat) => Ok(cheat),
+            None => Err(Error::CustomError(format!("Invalid cheat index: {index}"))),
         }
     }
 
-    pub fn add_code(&mut self, code: &str) -> Result<&GameSharkCode, Error> {
-        let shark_code = GameSharkCode::from_code(code, &self.rom_type)?;
-        let addr = shark_code.addr;
-        self.codes.insert(addr, shark_code);
-        self.get_addr(addr)
+    /// Adds the provided (possibly multi-line) code, replacing
+    /// the cheat with the same code in case it already exists.
+    pub fn add_code(&mut self, code: &str) -> Result<&GameSharkCheat, Error> {
+        let cheat = GameSharkCheat::from_code(code, &self.rom_type)?;
+        let index = match self.cheats.iter().position(|c| c.code == cheat.code) {
+            Some(index) => {
+                self.cheats[index] = cheat;
+                index
+            }
+            None => {
+                self.cheats.push(cheat);
+                self.cheats.len() - 1
+            }
+        };
+        self.update_rom_patches();
+        self.get(index)
+    }
+
+    /// Enables or disables the cheat at the provided index at
+    /// runtime, keeping it registered.
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) -> Result<(), Error> {
+        match self.cheats.get_mut(index) {
+            Some(cheat) => cheat.enabled = enabled,
+            None => {
+                return Err(Error::CustomError(format!("Invalid cheat index: {index}")));
+            }
+        }
+        self.update_rom_patches();
+        Ok(())
     }
 
-    pub fn writes(&self) -> Vec<(u16, u16, u8)> {
+    /// Obtains the RAM writes of the enabled cheats, as tuples of
+    /// base address (0xA000 or 0xC000), offset and value, using the
+    /// provided function to read the memory checked by conditionals.
+    pub fn writes(&self, mut read: impl FnMut(u16) -> u8) -> Vec<(u16, u16, u8)> {
         let mut writes = vec![];
-        for code in self.codes.values() {
-            // calculates the real RAM address using both
-            // the base RAM address and the RAM bank offset
-            let (base_addr, addr) = if code.addr <= 0xc000 {
-                (
-                    0xa000,
-                    code.addr - 0xa000 + (0x1000 * (code.ram_bank - 1) as u16),
-                )
-            } else {
-                (0xc000, code.addr - 0xc000)
-            };
-            writes.push((base_addr, addr, code.new_data));
+        for cheat in self.cheats.iter().filter(|cheat| cheat.enabled) {
+            let mut lines = cheat.lines.iter();
+            while let Some(code) = lines.next() {
+                match code.kind {
+                    GameSharkKind::Write => writes.push(code.write(0, 0)),
+                    GameSharkKind::Conditional(condition) => {
+                        if !condition.test(read(code.addr), code.new_data) {
+                            Self::skip(&mut lines);
+                        }
+                    }
+                    GameSharkKind::Slide {
+                        count,
+                        addr_step,
+                        value_step,
+                    } => {
+                        let code = lines.next().unwrap();
+                        for index in 0..count {
+                            writes.push(code.write(
+                                index as u16 * addr_step as u16,
+                                index.wrapping_mul(value_step),
+                            ));
+                        }
+                    }
+                    GameSharkKind::RomPatch => (),
+                }
+            }
         }
         writes
     }
+
+    /// Obtains the patched value for the byte in the provided ROM
+    /// address, if any of the enabled cheats patches it.
+    pub fn patch_rom(&self, addr: u16) -> Option<u8> {
+        if self.rom_patches.is_empty() {
+            return None;
+        }
+        self.rom_patches.get(&addr).copied()
+    }
+
+    /// Skips the next code to be run, including the codes it
+    /// applies to (eg: the write of a slide).
+    fn skip<'a>(lines: &mut impl Iterator<Item = &'a GameSharkCode>) {
+        let mut pending = 1;
+        while pending > 0 {
+            pending -= 1;
+            match lines.next().map(|code| code.kind) {
+                Some(GameSharkKind::Conditional(_)) | Some(GameSharkKind::Slide { .. }) => {
+                    pending += 1
+                }
+                Some(_) => (),
+                None => break,
+            }
+        }
+    }
+
+    fn update_rom_patches(&mut self) {
+        self.rom_patches = self
+            .cheats
+            .iter()
+            .filter(|cheat| cheat.enabled)
+            .flat_map(|cheat| cheat.lines.iter())
+            .filter(|code| code.kind == GameSharkKind::RomPatch)
+            .map(|code| (code.addr, code.new_data))
+            .collect();
+    }
 }
 
 impl Default for GameShark {
@@ -100,11 +202,207 @@ impl Default for GameShark {
     }
 }
 
+/// The comparison of a conditional code (D0-D7) between the
+/// byte in memory and the data of the code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameSharkCondition {
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+
+    /// Any of the bits set in the data is set in memory.
+    AnySet,
+
+    /// All of the bits set in the data are clear in memory.
+    AllClear,
+}
+
+impl GameSharkCondition {
+    pub fn description(&self) -> &'static str {
+        match self {
+            GameSharkCondition::Equal => "Equal",
+            GameSharkCondition::NotEqual => "Not Equal",
+            GameSharkCondition::Less => "Less",
+            GameSharkCondition::Greater => "Greater",
+            GameSharkCondition::LessEqual => "Less or Equal",
+            GameSharkCondition::GreaterEqual => "Greater or Equal",
+            GameSharkCondition::AnySet => "Any Set",
+            GameSharkCondition::AllClear => "All Clear",
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0xd0 => Some(GameSharkCondition::Equal),
+            0xd1 => Some(GameSharkCondition::NotEqual),
+            0xd2 => Some(GameSharkCondition::Less),
+            0xd3 => Some(GameSharkCondition::Greater),
+            0xd4 => Some(GameSharkCondition::LessEqual),
+            0xd5 => Some(GameSharkCondition::GreaterEqual),
+            0xd6 => Some(GameSharkCondition::AnySet),
+            0xd7 => Some(GameSharkCondition::AllClear),
+            _ => None,
+        }
+    }
+
+    pub fn test(&self, value: u8, data: u8) -> bool {
+        match self {
+            GameSharkCondition::Equal => value == data,
+            GameSharkCondition::NotEqual => value != data,
+            GameSharkCondition::Less => value < data,
+            GameSharkCondition::Greater => value > data,
+            GameSharkCondition::LessEqual => value <= data,
+            GameSharkCondition::GreaterEqual => value >= data,
+            GameSharkCondition::AnySet => value & data != 0,
+            GameSharkCondition::AllClear => value & data == 0,
+        }
+    }
+}
+
+impl Display for GameSharkCondition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameSharkKind {
+    /// Plain write of the data to the address in RAM.
+    Write,
+
+    /// Runs the next code only if the condition holds.
+    Conditional(GameSharkCondition),
+
+    /// Repeats the write of the next code incrementing both
+    /// the address and the data on each write.
+    Slide {
+        count: u8,
+        addr_step: u8,
+        value_step: u8,
+    },
+
+    /// Replaces the byte at the address in the ROM.
+    RomPatch,
+}
+
+impl GameSharkKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            GameSharkKind::Write => "Write",
+            GameSharkKind::Conditional(_) => "Conditional",
+            GameSharkKind::Slide { .. } => "Slide",
+            GameSharkKind::RomPatch => "ROM Patch",
+        }
+    }
+}
+
+impl Display for GameSharkKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// A GameShark cheat, made of one or more codes (lines) and
+/// that can be enabled or disabled at runtime.
+#[derive(Clone)]
+pub struct GameSharkCheat {
+    /// The complete (multi-line) code of the cheat.
+    code: String,
+
+    /// The codes (lines) of the cheat, in order.
+    lines: Vec<GameSharkCode>,
+
+    enabled: bool,
+}
+
+impl GameSharkCheat {
+    /// Creates a new GameShark cheat from the provided string, with
+    /// the lines (in the ABCDGHEF format) joined by a `+`.
+    pub fn from_code(code: &str, rom_type: &RomType) -> Result<Self, Error> {
+        let lines = code
+            .split(LINE_SEPARATOR)
+            .map(|line| GameSharkCode::from_code(line.trim(), rom_type))
+            .collect::<Result<Vec<GameSharkCode>, Error>>()?;
+
+        // validates that the codes that apply to the next line
+        // (conditionals and slides) are followed by a valid one
+        for (index, line) in lines.iter().enumerate() {
+            let next = lines.get(index + 1).map(|next| next.kind);
+            match (line.kind, next) {
+                (GameSharkKind::Conditional(_), Some(GameSharkKind::RomPatch)) => {
+                    return Err(Error::CustomError(String::from(
+                        "ROM patch codes can't be conditional",
+                    )))
+                }
+                (GameSharkKind::Conditional(_), None) => {
+                    return Err(Error::CustomError(String::from(
+                        "Conditional code without a following code",
+                    )))
+                }
+                (
+                    GameSharkKind::Slide {
+                        count, addr_step, ..
+                    },
+                    Some(GameSharkKind::Write),
+                ) => {
+                    let next = &lines[index + 1];
+                    let last = next.addr as u32 + count.saturating_sub(1) as u32 * addr_step as u32;
+                    if last > GameSharkCode::region_end(next.addr) as u32 {
+                        return Err(Error::CustomError(format!(
+                            "Slide code beyond the end of RAM: 0x{last:04x}"
+                        )));
+                    }
+                }
+                (GameSharkKind::Slide { .. }, _) => {
+                    return Err(Error::CustomError(String::from(
+                        "Slide code must be followed by a write code",
+                    )))
+                }
+                _ => (),
+            }
+        }
+
+        Ok(Self {
+            code: lines
+                .iter()
+                .map(|line| line.code())
+                .collect::<Vec<&str>>()
+                .join(&LINE_SEPARATOR.to_string()),
+            lines,
+            enabled: true,
+        })
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn lines(&self) -> &Vec<GameSharkCode> {
+        &self.lines
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl Display for GameSharkCheat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code)
+    }
+}
+
 #[derive(Clone)]
 pub struct GameSharkCode {
     /// The GameShark code that is going to be applied to the ROM.
     code: String,
 
+    /// The kind of the code, defined by its first byte.
+    kind: GameSharkKind,
+
     /// The RAM bank that the cheat code is going to be applied to,
     /// allowing advanced MBCs to be patched.
     ram_bank: u8,
@@ -130,11 +428,9 @@ impl GameSharkCode {
 
         let code_u = code.to_uppercase();
 
-        let ram_bank_slice = &code_u[0..=1];
-        let mut ram_bank = u8::from_str_radix(ram_bank_slice, 16)
-            .map_err(|e| Error::CustomError(format!("Invalid RAM bank: {e}")))?
-            & rom_type.mbc_type().ram_bank_mask();
-        ram_bank = if ram_bank == 0x00 { 0x01 } else { ram_bank };
+        let type_slice = &code_u[0..=1];
+        let code_type = u8::from_str_radix(type_slice, 16)
+            .map_err(|e| Error::CustomError(format!("Invalid RAM bank: {e}")))?;
 
         let new_data_slice = &code_u[2..=3];
         let new_data = u8::from_str_radix(new_data_slice, 16)
@@ -144,14 +440,39 @@ impl GameSharkCode {
         let addr = u16::from_str_radix(&addr_slice, 16)
             .map_err(|e| Error::CustomError(format!("Invalid address: {e}")))?;
 
-        if !(0xa000..=0xdfff).contains(&addr) {
+        let kind = match code_type {
+            0xd0..=0xd7 => {
+                GameSharkKind::Conditional(GameSharkCondition::from_u8(code_type).unwrap())
+            }
+            0x50 => GameSharkKind::Slide {
+                count: new_data,
+                addr_step: (addr & 0x00ff) as u8,
+                value_step: (addr >> 8) as u8,
+            },
+            0xf0 => GameSharkKind::RomPatch,
+            _ => GameSharkKind::Write,
+        };
+
+        let valid_addr = match kind {
+            GameSharkKind::Write => (0xa000..=0xdfff).contains(&addr),
+            GameSharkKind::RomPatch => addr <= 0x7fff,
+            GameSharkKind::Conditional(_) | GameSharkKind::Slide { .. } => true,
+        };
+        if !valid_addr {
             return Err(Error::CustomError(format!(
                 "Invalid cheat address: 0x{addr:04x}",
             )));
         }
 
+        let mut ram_bank = match kind {
+            GameSharkKind::Write => code_type & rom_type.mbc_type().ram_bank_mask(),
+            _ => 0x00,
+        };
+        ram_bank = if ram_bank == 0x00 { 0x01 } else { ram_bank };
+
         Ok(Self {
             code: code_u,
+            kind,
             ram_bank,
             new_data,
             addr,
@@ -168,13 +489,48 @@ impl GameSharkCode {
         self.new_data()
     }
 
+    /// Obtains the RAM write of the code as a tuple of base address
+    /// (0xA000 or 0xC000), offset and value, with the provided address
+    /// and data increments (slides) applied.
+    pub fn write(&self, addr_offset: u16, data_offset: u8) -> (u16, u16, u8) {
+        let addr = self.addr + addr_offset;
+        let data = self.new_data.wrapping_add(data_offset);
+
+        // calculates the real RAM address using both
+        // the base RAM address and the RAM bank offset
+        if addr < 0xc000 {
+            (
+                0xa000,
+                addr - 0xa000 + (0x1000 * (self.ram_bank - 1) as u16),
+                data,
+            )
+        } else {
+            (0xc000, addr - 0xc000, data)
+        }
+    }
+
+    /// The last address of the RAM region (SRAM or WRAM) that
+    /// contains the provided address.
+    fn region_end(addr: u16) -> u16 {
+        if addr < 0xc000 {
+            0xbfff
+        } else {
+            0xdfff
+        }
+    }
+
     pub fn code(&self) -> &str {
         &self.code
     }
+
     pub fn set_code(&mut self, code: String) {
         self.code = code;
     }
 
+    pub fn kind(&self) -> GameSharkKind {
+        self.kind
+    }
+
     pub fn ram_bank(&self) -> u8 {
         self.ram_bank
     }
@@ -205,8 +561,8 @@ impl GameSharkCode {
 
     pub fn description(&self) -> String {
         format!(
-            "Code: {}, RAM Bank: 0x{:02x}, New Data: 0x{:02x}, Address: 0x{:04x}",
-            self.code, self.ram_bank, self.new_data, self.addr
+            "Code: {}, Kind: {}, RAM Bank: 0x{:02x}, New Data: 0x{:02x}, Address: 0x{:04x}",
+            self.code, self.kind, self.ram_bank, self.new_data, self.addr
         )
     }
 }
@@ -216,3 +572,76 @@ impl Display for GameSharkCode {
         write!(f, "{}", self.short_description())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{GameShark, GameSharkCondition, GameSharkKind};
+
+    use crate::rom::RomType;
+
+    #[test]
+    fn test_is_code() {
+        assert!(GameShark::is_code("010138CD"));
+        assert!(GameShark::is_code("D0050AC1+01FF0AC1"));
+        assert!(!GameShark::is_code("00A-17B-C49"));
+        assert!(!GameShark::is_code("ABC+DEF+GHI"));
+        assert!(!GameShark::is_code("010138CD+"));
+    }
+
+    #[test]
+    fn test_conditional() {
+        let mut game_shark = GameShark::new();
+        let cheat = game_shark.add_code("d0050ac1+01ff0bc1").unwrap();
+        assert_eq!(cheat.code(), "D0050AC1+01FF0BC1");
+        assert_eq!(
+            cheat.lines()[0].kind(),
+            GameSharkKind::Conditional(GameSharkCondition::Equal)
+        );
+        assert_eq!(game_shark.writes(|_| 0x05), vec![(0xc000, 0x010b, 0xff)]);
+        assert_eq!(game_shark.writes(|_| 0x04), vec![]);
+
+        game_shark.set_enabled(0, false).unwrap();
+        assert_eq!(game_shark.writes(|_| 0x05), vec![]);
+        assert!(game_shark.set_enabled(1, false).is_err());
+
+        assert!(game_shark.add_code("D0050AC1").is_err());
+        assert!(game_shark.add_code("D0050AC1+F0000100").is_err());
+    }
+
+    #[test]
+    fn test_slide() {
+        let mut game_shark = GameShark::new();
+        game_shark.add_code("50030201+011000C0").unwrap();
+        assert_eq!(
+            game_shark.writes(|_| 0x00),
+            vec![
+                (0xc000, 0x0000, 0x10),
+                (0xc000, 0x0002, 0x11),
+                (0xc000, 0x0004, 0x12)
+            ]
+        );
+        assert!(game_shark.add_code("50030201").is_err());
+        assert!(game_shark.add_code("50030201+0110FEDF").is_err());
+
+        // a failed conditional skips the complete slide
+        game_shark.reset();
+        game_shark
+            .add_code("D1000AC1+50030201+011000C0+01200AC1")
+            .unwrap();
+        assert_eq!(game_shark.writes(|_| 0x00), vec![(0xc000, 0x010a, 0x20)]);
+        assert_eq!(game_shark.writes(|_| 0x01).len(), 4);
+    }
+
+    #[test]
+    fn test_rom_patch() {
+        let mut game_shark = GameShark::new();
+        game_shark.set_rom_type(RomType::Mbc1);
+        game_shark.add_code("F0C95001").unwrap();
+        assert_eq!(game_shark.patch_rom(0x0150), Some(0xc9));
+        assert_eq!(game_shark.patch_rom(0x0151), None);
+        assert_eq!(game_shark.writes(|_| 0x00), vec![]);
+        game_shark.set_enabled(0, false).unwrap();
+        assert_eq!(game_shark.patch_rom(0x0150), None);
+        assert!(game_shark.add_code("F0C90080").is_err());
+    }
+}
@@ -1,6 +1,10 @@
 //! Color manipulation functions and constants.
 
-use boytacean_common::util::copy_fast;
+use boytacean_common::{error::Error, util::copy_fast};
+use std::fmt::{self, Display, Formatter};
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
 
 pub const RGB_SIZE: usize = 3;
 pub const RGBA_SIZE: usize = 4;
@@ -25,6 +29,74 @@ pub type PixelRgb1555 = [u8; RGB1555_SIZE];
 /// green channel which uses 6 bits.
 pub type PixelRgb565 = [u8; RGB565_SIZE];
 
+/// The formats into which the (RGB888) frame buffer can be
+/// converted, matching the ones expected by the frontends.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PixelFormat {
+    #[default]
+    Rgb888,
+
+    /// 32 bit pixels stored in little endian (BGRX in memory),
+    /// with the padding byte set to `0xff`.
+    Xrgb8888,
+
+    /// 16 bit pixels stored in little endian, with the top
+    /// bit (alpha) always set.
+    Rgb1555,
+
+    /// 16 bit pixels stored in little endian.
+    Rgb565,
+}
+
+impl PixelFormat {
+    pub fn description(&self) -> &'static str {
+        match self {
+            PixelFormat::Rgb888 => "RGB888",
+            PixelFormat::Xrgb8888 => "XRGB8888",
+            PixelFormat::Rgb1555 => "RGB1555",
+            PixelFormat::Rgb565 => "RGB565",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "rgb888" => Ok(PixelFormat::Rgb888),
+            "xrgb8888" => Ok(PixelFormat::Xrgb8888),
+            "rgb1555" => Ok(PixelFormat::Rgb1555),
+            "rgb565" => Ok(PixelFormat::Rgb565),
+            _ => Err(Error::CustomError(format!("Invalid pixel format: {value}"))),
+        }
+    }
+
+    /// The size in bytes of each of the pixels.
+    pub fn pixel_size(&self) -> usize {
+        match self {
+            PixelFormat::Rgb888 => RGB888_SIZE,
+            PixelFormat::Xrgb8888 => XRGB8888_SIZE,
+            PixelFormat::Rgb1555 => RGB1555_SIZE,
+            PixelFormat::Rgb565 => RGB565_SIZE,
+        }
+    }
+
+    /// Converts the provided RGB888 pixels into this format, the
+    /// target must have room for exactly the same number of pixels.
+    pub fn convert(&self, rgb888_pixels: &[u8], pixels: &mut [u8]) {
+        match self {
+            PixelFormat::Rgb888 => pixels.copy_from_slice(rgb888_pixels),
+            PixelFormat::Xrgb8888 => rgb888_to_xrgb8888_array(rgb888_pixels, pixels),
+            PixelFormat::Rgb1555 => rgb888_to_rgb1555_array(rgb888_pixels, pixels),
+            PixelFormat::Rgb565 => rgb888_to_rgb565_array(rgb888_pixels, pixels),
+        }
+    }
+}
+
+impl Display for PixelFormat {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
 pub fn rgb555_to_rgb888(first: u8, second: u8) -> Pixel {
     let r = (first & 0x1f) << 3;
     let g = (((first & 0xe0) >> 5) | ((second & 0x03) << 3)) << 3;
@@ -68,166 +140,287 @@ pub fn rgb888_to_rgb1555_array(rgb888_pixels: &[u8], rgb1555_pixels: &mut [u8])
     }
 }
 
-/// Converts an array of RGB888 pixels to RGB565 format using a scalar implementation.
-///
-/// This method should provide the same results as the SIMD implementation.
-pub fn rgb888_to_rgb1555_scalar(rgb888_pixels: &[u8], rgb1555_pixels: &mut [u8]) {
-    assert!(
-        rgb888_pixels.len() % 3 == 0,
-        "Length of rgb888_pixels must be a multiple of 3"
-    );
-    assert!(
-        rgb1555_pixels.len() % 2 == 0,
-        "Length of rgb1555_pixels must be a multiple of 2"
-    );
+pub fn rgb888_to_rgb565_array(rgb888_pixels: &[u8], rgb565_pixels: &mut [u8]) {
+    #[cfg(feature = "simd")]
+    {
+        rgb888_to_rgb565_simd(rgb888_pixels, rgb565_pixels);
+    }
+    #[cfg(not(feature = "simd"))]
+    {
+        rgb888_to_rgb565_scalar(rgb888_pixels, rgb565_pixels);
+    }
+}
+
+/// Converts an array of RGB888 pixels to XRGB8888 format, stored
+/// in little endian (BGRX in memory).
+pub fn rgb888_to_xrgb8888_array(rgb888_pixels: &[u8], xrgb8888_pixels: &mut [u8]) {
     assert!(
-        rgb888_pixels.len() / 3 == rgb1555_pixels.len() / 2,
-        "Length of rgb1555_pixels must be two thirds the length of rgb888_pixels"
+        rgb888_pixels.len() / RGB_SIZE == xrgb8888_pixels.len() / XRGB8888_SIZE,
+        "Length of xrgb8888_pixels must be four thirds the length of rgb888_pixels"
     );
-    for index in 0..rgb888_pixels.len() / RGB_SIZE {
-        let (r, g, b) = (
-            rgb888_pixels[index * RGB_SIZE],
-            rgb888_pixels[index * RGB_SIZE + 1],
-            rgb888_pixels[index * RGB_SIZE + 2],
-        );
-        let rgb1555 = rgb888_to_rgb1555(r, g, b);
-        let output_offset = index * RGB1555_SIZE;
+    for (rgb888, xrgb8888) in rgb888_pixels
+        .chunks_exact(RGB_SIZE)
+        .zip(xrgb8888_pixels.chunks_exact_mut(XRGB8888_SIZE))
+    {
         copy_fast(
-            &rgb1555,
-            &mut rgb1555_pixels[output_offset..output_offset + RGB1555_SIZE],
-            RGB1555_SIZE,
-        )
+            &[rgb888[2], rgb888[1], rgb888[0], 0xff],
+            xrgb8888,
+            XRGB8888_SIZE,
+        );
     }
 }
 
+/// Converts an array of RGB888 pixels to XRGB8888 (`u32`) pixels,
+/// with the padding byte unset.
+pub fn rgb888_to_xrgb8888_u32_array(rgb888_pixels: &[u8], xrgb8888_pixels: &mut [u32]) {
+    for (rgb888, pixel) in rgb888_pixels
+        .chunks_exact(RGB_SIZE)
+        .zip(xrgb8888_pixels.iter_mut())
+    {
+        *pixel = ((rgb888[0] as u32) << 16) | ((rgb888[1] as u32) << 8) | rgb888[2] as u32;
+    }
+}
+
+/// Converts an array of RGB888 pixels to RGB1555 format using a scalar implementation.
+///
+/// This method should provide the same results as the SIMD implementation.
+pub fn rgb888_to_rgb1555_scalar(rgb888_pixels: &[u8], rgb1555_pixels: &mut [u8]) {
+    check_packing(rgb888_pixels, rgb1555_pixels);
+    pack_scalar(rgb888_pixels, rgb1555_pixels, Packing::Rgb1555);
+}
+
+/// Converts an array of RGB888 pixels to RGB565 format using a scalar implementation.
+///
+/// This method should provide the same results as the SIMD implementation.
+pub fn rgb888_to_rgb565_scalar(rgb888_pixels: &[u8], rgb565_pixels: &mut [u8]) {
+    check_packing(rgb888_pixels, rgb565_pixels);
+    pack_scalar(rgb888_pixels, rgb565_pixels, Packing::Rgb565);
+}
+
 /// Converts an array of RGB888 pixels to RGB1555 format using SIMD.
 ///
-/// This method is only available when the `simd` feature is enabled.
+/// This method is only available when the `simd` feature is enabled,
+/// using SSE2 (x86_64) or NEON (aarch64) when supported by the CPU
+/// (detected at runtime) and the scalar implementation otherwise.
 ///
 /// Note: The length of `rgb888_pixels` must be a multiple of 3, and
 /// `rgb1555_pixels` must be a multiple of 2.
 #[cfg(feature = "simd")]
 pub fn rgb888_to_rgb1555_simd(rgb888_pixels: &[u8], rgb1555_pixels: &mut [u8]) {
-    use boytacean_common::util::interleave_arrays;
-    use std::simd::u8x16;
+    check_packing(rgb888_pixels, rgb1555_pixels);
+    pack_simd(rgb888_pixels, rgb1555_pixels, Packing::Rgb1555);
+}
 
-    const SIMD_WIDTH: usize = 16;
+/// Converts an array of RGB888 pixels to RGB565 format using SIMD.
+///
+/// This method is only available when the `simd` feature is enabled,
+/// see [`rgb888_to_rgb1555_simd`] for the supported instruction sets.
+#[cfg(feature = "simd")]
+pub fn rgb888_to_rgb565_simd(rgb888_pixels: &[u8], rgb565_pixels: &mut [u8]) {
+    check_packing(rgb888_pixels, rgb565_pixels);
+    pack_simd(rgb888_pixels, rgb565_pixels, Packing::Rgb565);
+}
 
+/// The packings of the RGB888 pixels into 16 bit pixels (stored
+/// in little endian), shared by the scalar and SIMD conversions.
+#[derive(Clone, Copy)]
+enum Packing {
+    Rgb1555,
+    Rgb565,
+}
+
+impl Packing {
+    fn pack(&self, r: u8, g: u8, b: u8) -> [u8; 2] {
+        match self {
+            Packing::Rgb1555 => rgb888_to_rgb1555(r, g, b),
+            Packing::Rgb565 => rgb888_to_rgb565(r, g, b),
+        }
+    }
+}
+
+#[allow(clippy::manual_is_multiple_of)]
+fn check_packing(rgb888_pixels: &[u8], packed_pixels: &[u8]) {
     assert!(
         rgb888_pixels.len() % 3 == 0,
         "Length of rgb888_pixels must be a multiple of 3"
     );
     assert!(
-        rgb1555_pixels.len() % 2 == 0,
-        "Length of rgb1555_pixels must be a multiple of 2"
+        packed_pixels.len() % 2 == 0,
+        "Length of packed pixels must be a multiple of 2"
     );
     assert!(
-        rgb888_pixels.len() / 3 == rgb1555_pixels.len() / 2,
-        "Length of rgb1555_pixels must be two thirds the length of rgb888_pixels"
+        rgb888_pixels.len() / 3 == packed_pixels.len() / 2,
+        "Length of packed pixels must be two thirds the length of rgb888_pixels"
     );
+}
 
-    let num_pixels = rgb888_pixels.len() / 3;
-    let simd_chunks = num_pixels / SIMD_WIDTH;
-
-    for index in 0..simd_chunks {
-        let offset = index * SIMD_WIDTH * 3;
-        let r = u8x16::from_slice(&[
-            rgb888_pixels[offset],
-            rgb888_pixels[offset + 3],
-            rgb888_pixels[offset + 6],
-            rgb888_pixels[offset + 9],
-            rgb888_pixels[offset + 12],
-            rgb888_pixels[offset + 15],
-            rgb888_pixels[offset + 18],
-            rgb888_pixels[offset + 21],
-            rgb888_pixels[offset + 24],
-            rgb888_pixels[offset + 27],
-            rgb888_pixels[offset + 30],
-            rgb888_pixels[offset + 33],
-            rgb888_pixels[offset + 36],
-            rgb888_pixels[offset + 39],
-            rgb888_pixels[offset + 42],
-            rgb888_pixels[offset + 45],
-        ]);
-        let g = u8x16::from_slice(&[
-            rgb888_pixels[offset + 1],
-            rgb888_pixels[offset + 4],
-            rgb888_pixels[offset + 7],
-            rgb888_pixels[offset + 10],
-            rgb888_pixels[offset + 13],
-            rgb888_pixels[offset + 16],
-            rgb888_pixels[offset + 19],
-            rgb888_pixels[offset + 22],
-            rgb888_pixels[offset + 25],
-            rgb888_pixels[offset + 28],
-            rgb888_pixels[offset + 31],
-            rgb888_pixels[offset + 34],
-            rgb888_pixels[offset + 37],
-            rgb888_pixels[offset + 40],
-            rgb888_pixels[offset + 43],
-            rgb888_pixels[offset + 46],
-        ]);
-        let b = u8x16::from_slice(&[
-            rgb888_pixels[offset + 2],
-            rgb888_pixels[offset + 5],
-            rgb888_pixels[offset + 8],
-            rgb888_pixels[offset + 11],
-            rgb888_pixels[offset + 14],
-            rgb888_pixels[offset + 17],
-            rgb888_pixels[offset + 20],
-            rgb888_pixels[offset + 23],
-            rgb888_pixels[offset + 26],
-            rgb888_pixels[offset + 29],
-            rgb888_pixels[offset + 32],
-            rgb888_pixels[offset + 35],
-            rgb888_pixels[offset + 38],
-            rgb888_pixels[offset + 41],
-            rgb888_pixels[offset + 44],
-            rgb888_pixels[offset + 47],
-        ]);
-
-        let r_shifted_high = (r >> 1) & u8x16::splat(0x7c);
-        let g_shifted_high = (g >> 6) & u8x16::splat(0x03);
-        let g_shifted_low = (g << 2) & u8x16::splat(0xe0);
-        let b_shifted = (b >> 3) & u8x16::splat(0x3f);
-
-        let high_byte = u8x16::splat(0x80) | r_shifted_high | g_shifted_high;
-        let low_byte = g_shifted_low | b_shifted;
+fn pack_scalar(rgb888_pixels: &[u8], packed_pixels: &mut [u8], packing: Packing) {
+    for (rgb888, packed) in rgb888_pixels
+        .chunks_exact(RGB_SIZE)
+        .zip(packed_pixels.chunks_exact_mut(RGB1555_SIZE))
+    {
+        let pixel = packing.pack(rgb888[0], rgb888[1], rgb888[2]);
+        copy_fast(&pixel, packed, RGB1555_SIZE);
+    }
+}
+
+/// Packs the pixels using the SIMD implementation available for
+/// the running CPU, with the remaining pixels (that do not fill a
+/// complete SIMD chunk) packed by the scalar implementation.
+#[cfg(feature = "simd")]
+fn pack_simd(rgb888_pixels: &[u8], packed_pixels: &mut [u8], packing: Packing) {
+    #[allow(unused_mut)]
+    let mut pixels = 0;
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if std::arch::is_x86_feature_detected!("sse2") {
+            pixels = unsafe { pack_sse2(rgb888_pixels, packed_pixels, packing) };
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            pixels = unsafe { pack_neon(rgb888_pixels, packed_pixels, packing) };
+        }
+    }
+
+    pack_scalar(
+        &rgb888_pixels[pixels * RGB_SIZE..],
+        &mut packed_pixels[pixels * RGB1555_SIZE..],
+        packing,
+    );
+}
+
+/// Packs the complete chunks of 16 pixels using SSE2, returning
+/// the number of pixels packed.
+///
+/// As SSE2 has no byte shuffles the channels are de-interleaved
+/// in scalar code, and as it has no 8 bit shifts the 16 bit ones
+/// are used, masking the bits shifted from the neighbouring byte.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+#[target_feature(enable = "sse2")]
+unsafe fn pack_sse2(rgb888_pixels: &[u8], packed_pixels: &mut [u8], packing: Packing) -> usize {
+    use std::arch::x86_64::{
+        __m128i, _mm_and_si128, _mm_loadu_si128, _mm_or_si128, _mm_set1_epi8, _mm_slli_epi16,
+        _mm_srli_epi16, _mm_storeu_si128, _mm_unpackhi_epi8, _mm_unpacklo_epi8,
+    };
+
+    const SIMD_WIDTH: usize = 16;
+
+    let mask = |value: u8| _mm_set1_epi8(value as i8);
+    let chunks = rgb888_pixels.len() / RGB_SIZE / SIMD_WIDTH;
+
+    for index in 0..chunks {
+        let offset = index * SIMD_WIDTH * RGB_SIZE;
+        let mut channels = [[0u8; SIMD_WIDTH]; RGB_SIZE];
+        for (pixel, rgb888) in rgb888_pixels[offset..offset + SIMD_WIDTH * RGB_SIZE]
+            .chunks_exact(RGB_SIZE)
+            .enumerate()
+        {
+            channels[0][pixel] = rgb888[0];
+            channels[1][pixel] = rgb888[1];
+            channels[2][pixel] = rgb888[2];
+        }
+        let r = _mm_loadu_si128(channels[0].as_ptr() as *const __m128i);
+        let g = _mm_loadu_si128(channels[1].as_ptr() as *const __m128i);
+        let b = _mm_loadu_si128(channels[2].as_ptr() as *const __m128i);
+
+        let (high, low) = match packing {
+            Packing::Rgb1555 => (
+                _mm_or_si128(
+                    mask(0x80),
+                    _mm_or_si128(
+                        _mm_and_si128(_mm_srli_epi16(r, 1), mask(0x7c)),
+                        _mm_and_si128(_mm_srli_epi16(g, 6), mask(0x03)),
+                    ),
+                ),
+                _mm_or_si128(
+                    _mm_and_si128(_mm_slli_epi16(g, 2), mask(0xe0)),
+                    _mm_and_si128(_mm_srli_epi16(b, 3), mask(0x1f)),
+                ),
+            ),
+            Packing::Rgb565 => (
+                _mm_or_si128(
+                    _mm_and_si128(r, mask(0xf8)),
+                    _mm_and_si128(_mm_srli_epi16(g, 5), mask(0x07)),
+                ),
+                _mm_or_si128(
+                    _mm_and_si128(_mm_slli_epi16(g, 3), mask(0xe0)),
+                    _mm_and_si128(_mm_srli_epi16(b, 3), mask(0x1f)),
+                ),
+            ),
+        };
 
         let output_offset = index * SIMD_WIDTH * RGB1555_SIZE;
-        interleave_arrays(
-            low_byte.as_array(),
-            high_byte.as_array(),
-            &mut rgb1555_pixels[output_offset..output_offset + SIMD_WIDTH * RGB1555_SIZE],
-        );
+        let output = packed_pixels[output_offset..output_offset + SIMD_WIDTH * RGB1555_SIZE]
+            .as_mut_ptr() as *mut __m128i;
+        _mm_storeu_si128(output, _mm_unpacklo_epi8(low, high));
+        _mm_storeu_si128(output.add(1), _mm_unpackhi_epi8(low, high));
     }
 
-    let remainder = num_pixels % SIMD_WIDTH;
-    let offset = simd_chunks * SIMD_WIDTH * RGB_SIZE;
-    let offset_rgb1555 = simd_chunks * SIMD_WIDTH * RGB1555_SIZE;
+    chunks * SIMD_WIDTH
+}
 
-    for index in 0..remainder {
-        let current_offset = offset + index * RGB_SIZE;
-        let (r, g, b) = (
-            rgb888_pixels[current_offset],
-            rgb888_pixels[current_offset + 1],
-            rgb888_pixels[current_offset + 2],
-        );
-        let rgb1555 = rgb888_to_rgb1555(r, g, b);
-        let output_offset = offset_rgb1555 + index * RGB1555_SIZE;
-        copy_fast(
-            &rgb1555,
-            &mut rgb1555_pixels[output_offset..output_offset + RGB1555_SIZE],
-            RGB1555_SIZE,
+/// Packs the complete chunks of 16 pixels using NEON, returning
+/// the number of pixels packed.
+#[cfg(all(feature = "simd", target_arch = "aarch64"))]
+#[target_feature(enable = "neon")]
+unsafe fn pack_neon(rgb888_pixels: &[u8], packed_pixels: &mut [u8], packing: Packing) -> usize {
+    use std::arch::aarch64::{
+        uint8x16x2_t, vandq_u8, vdupq_n_u8, vld3q_u8, vorrq_u8, vshlq_n_u8, vshrq_n_u8, vst2q_u8,
+    };
+
+    const SIMD_WIDTH: usize = 16;
+
+    let chunks = rgb888_pixels.len() / RGB_SIZE / SIMD_WIDTH;
+
+    for index in 0..chunks {
+        let offset = index * SIMD_WIDTH * RGB_SIZE;
+        let rgb = vld3q_u8(rgb888_pixels[offset..offset + SIMD_WIDTH * RGB_SIZE].as_ptr());
+        let (r, g, b) = (rgb.0, rgb.1, rgb.2);
+
+        let (high, low) = match packing {
+            Packing::Rgb1555 => (
+                vorrq_u8(
+                    vdupq_n_u8(0x80),
+                    vorrq_u8(
+                        vandq_u8(vshrq_n_u8(r, 1), vdupq_n_u8(0x7c)),
+                        vshrq_n_u8(g, 6),
+                    ),
+                ),
+                vorrq_u8(
+                    vandq_u8(vshlq_n_u8(g, 2), vdupq_n_u8(0xe0)),
+                    vshrq_n_u8(b, 3),
+                ),
+            ),
+            Packing::Rgb565 => (
+                vorrq_u8(vandq_u8(r, vdupq_n_u8(0xf8)), vshrq_n_u8(g, 5)),
+                vorrq_u8(
+                    vandq_u8(vshlq_n_u8(g, 3), vdupq_n_u8(0xe0)),
+                    vshrq_n_u8(b, 3),
+                ),
+            ),
+        };
+
+        let output_offset = index * SIMD_WIDTH * RGB1555_SIZE;
+        vst2q_u8(
+            packed_pixels[output_offset..output_offset + SIMD_WIDTH * RGB1555_SIZE].as_mut_ptr(),
+            uint8x16x2_t(low, high),
         );
     }
+
+    chunks * SIMD_WIDTH
 }
 
 #[cfg(test)]
 mod tests {
     #![allow(clippy::zero_prefixed_literal)]
 
-    use super::{rgb888_to_rgb1555, rgb888_to_rgb1555_scalar};
+    use super::{
+        rgb888_to_rgb1555, rgb888_to_rgb1555_scalar, rgb888_to_rgb565_scalar,
+        rgb888_to_xrgb8888_array, rgb888_to_xrgb8888_u32_array, PixelFormat,
+    };
 
     #[test]
     fn test_rgb888_to_rgb1555() {
@@ -346,4 +539,76 @@ mod tests {
 
         assert_eq!(rgb1555_pixels, expected_rgb1555);
     }
+
+    #[test]
+    fn test_rgb888_to_rgb565_scalar() {
+        let rgb888_pixels: Vec<u8> = vec![
+            255, 000, 000, // Red
+            000, 255, 000, // Green
+            000, 000, 255, // Blue
+        ];
+        let mut rgb565_pixels: Vec<u8> = vec![0; 6];
+
+        rgb888_to_rgb565_scalar(&rgb888_pixels, &mut rgb565_pixels);
+
+        let expected_rgb565: Vec<u8> = vec![
+            0b00000000, 0b11111000, // Red
+            0b11100000, 0b00000111, // Green
+            0b00011111, 0b00000000, // Blue
+        ];
+
+        assert_eq!(rgb565_pixels, expected_rgb565);
+    }
+
+    #[test]
+    fn test_rgb888_to_xrgb8888() {
+        let rgb888_pixels = [0x10, 0x20, 0x30, 0x40, 0x50, 0x60];
+        let mut result = [0u8; 8];
+        rgb888_to_xrgb8888_array(&rgb888_pixels, &mut result);
+        assert_eq!(result, [0x30, 0x20, 0x10, 0xff, 0x60, 0x50, 0x40, 0xff]);
+
+        let mut result = [0u32; 2];
+        rgb888_to_xrgb8888_u32_array(&rgb888_pixels, &mut result);
+        assert_eq!(result, [0x102030, 0x405060]);
+    }
+
+    #[test]
+    fn test_pixel_format() {
+        assert_eq!(
+            PixelFormat::from_string("xrgb8888").unwrap(),
+            PixelFormat::Xrgb8888
+        );
+        assert!(PixelFormat::from_string("invalid").is_err());
+        assert_eq!(PixelFormat::Rgb888.pixel_size(), 3);
+        assert_eq!(PixelFormat::Rgb565.pixel_size(), 2);
+
+        let rgb888_pixels = [0xff, 0x00, 0x00];
+        let mut result = [0u8; 2];
+        PixelFormat::Rgb565.convert(&rgb888_pixels, &mut result);
+        assert_eq!(result, [0x00, 0xf8]);
+        PixelFormat::Rgb1555.convert(&rgb888_pixels, &mut result);
+        assert_eq!(result, [0x00, 0xfc]);
+    }
+
+    #[test]
+    #[cfg(feature = "simd")]
+    fn test_simd_matches_scalar() {
+        use super::{rgb888_to_rgb1555_simd, rgb888_to_rgb565_simd, RGB_SIZE};
+
+        // spread of channel values, with a number of pixels that
+        // leaves a remainder for the scalar path
+        let rgb888_pixels: Vec<u8> = (0..37 * RGB_SIZE)
+            .map(|index| (index * 97 + index / 3) as u8)
+            .collect();
+        let mut expected = vec![0u8; 37 * 2];
+        let mut result = vec![0u8; 37 * 2];
+
+        rgb888_to_rgb1555_scalar(&rgb888_pixels, &mut expected);
+        rgb888_to_rgb1555_simd(&rgb888_pixels, &mut result);
+        assert_eq!(result, expected);
+
+        rgb888_to_rgb565_scalar(&rgb888_pixels, &mut expected);
+        rgb888_to_rgb565_simd(&rgb888_pixels, &mut result);
+        assert_eq!(result, expected);
+    }
 }
@@ -0,0 +1,654 @@
+//! Conformance (compatibility) reports built on top of test ROM suites.
+//!
+//! A suite is a plain text (INI like) file listing the test ROMs to be
+//! run and the expected outcome of each of them, either a message
+//! printed through the serial port (`serial`), the CRC32 of the frame
+//! buffer (`frame_crc`), the contents of memory (`memory`) or one of
+//! the known pass signatures (`signature = mooneye|auto`):
+//!
+//! ```text
+//! # paths are relative to the directory of the suite file
+//! [blargg/cpu_instrs]
+//! rom = blargg/cpu/cpu_instrs.gb
+//! cycles = 300000000
+//! serial = Passed all tests
+//!
+//! [dmg_acid2]
+//! rom = dmg_acid2.gb
+//! mode = dmg
+//! cycles = 50000000
+//! frame_crc = 0x4aa0a83a
+//!
+//! [blargg/dmg_sound/01-registers]
+//! rom = blargg/dmg_sound/01-registers.gb
+//! memory = 0xa000:00deb061
+//! ```
+//!
+//! A directory of test ROMs can also be run as a suite, with the
+//! outcome of each ROM being detected from the pass signatures of
+//! the most common suites (see [`Expectation::Auto`]).
+//!
+//! Running the suite produces a [`ConformanceReport`] that can be
+//! rendered as markdown, JSON or JUnit XML, so that the accuracy status
+//! can be published with each release and compared between versions.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::read_dir,
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use boytacean_common::{error::Error, util::read_file};
+use boytacean_hashing::crc32::crc32;
+
+use crate::{
+    fuzz::panic_message,
+    gb::{GameBoy, GameBoyMode},
+    info::Info,
+    test::{run_test, TestOptions},
+};
+
+/// The number of cycles a test runs for when not specified.
+pub const DEFAULT_CYCLES: u64 = 100_000_000;
+
+/// The registers B, C, D, E, H and L (Fibonacci numbers) sent through
+/// the serial port by the Mooneye test ROMs on success.
+const MOONEYE_SIGNATURE: &str = "\x03\x05\x08\x0d\x15\x22";
+
+/// The signature written at 0xA001 by the Blargg test ROMs, meaning
+/// that the result code at 0xA000 is valid.
+const BLARGG_SIGNATURE: [u8; 3] = [0xde, 0xb0, 0x61];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expectation {
+    /// The serial output must contain the provided message.
+    Serial(String),
+
+    /// The CRC32 of the (RGB) frame buffer must match.
+    FrameCrc(u32),
+
+    /// The memory starting at the address must contain the bytes.
+    Memory(u16, Vec<u8>),
+
+    /// The Mooneye pass signature (Fibonacci numbers) must have
+    /// been sent through the serial port.
+    Mooneye,
+
+    /// Any of the known pass signatures must be found, either the
+    /// Blargg "Passed" serial message or result code in memory, or
+    /// the Mooneye serial signature.
+    Auto,
+}
+
+impl Expectation {
+    pub fn description(&self) -> String {
+        match self {
+            Expectation::Serial(message) => format!("serial contains \"{message}\""),
+            Expectation::FrameCrc(crc) => format!("frame CRC 0x{crc:08x}"),
+            Expectation::Memory(addr, data) => {
+                format!("memory at 0x{addr:04x} is {}", hex_string(data))
+            }
+            Expectation::Mooneye => String::from("Mooneye pass signature"),
+            Expectation::Auto => String::from("any pass signature"),
+        }
+    }
+
+    /// Checks the expectation against the system after the
+    /// test ROM has run, returning the failure reason if any.
+    fn check(&self, game_boy: &mut GameBoy, serial: &str) -> Result<(), String> {
+        let passed = match self {
+            Expectation::Serial(message) => serial.contains(message.as_str()),
+            Expectation::FrameCrc(crc) => {
+                let frame_crc = crc32(game_boy.frame_buffer());
+                if *crc != frame_crc {
+                    return Err(format!("{self}, got 0x{frame_crc:08x}"));
+                }
+                true
+            }
+            Expectation::Memory(addr, data) => {
+                let memory = game_boy.read_memory_range(*addr, data.len());
+                if memory != *data {
+                    return Err(format!("{self}, got {}", hex_string(&memory)));
+                }
+                true
+            }
+            Expectation::Mooneye => serial.contains(MOONEYE_SIGNATURE),
+            Expectation::Auto => {
+                serial.contains("Passed")
+                    || serial.contains(MOONEYE_SIGNATURE)
+                    || game_boy.read_memory_range(0xa000, 4)[..] == [0x00, 0xde, 0xb0, 0x61]
+            }
+        };
+        if !passed {
+            let output = serial.trim().replace('\n', " ");
+            let mut reason = format!("{self}, got \"{output}\"");
+            if game_boy.read_memory_range(0xa001, 3) == BLARGG_SIGNATURE {
+                let code = game_boy.read_memory(0xa000);
+                reason.push_str(&format!(" (result code 0x{code:02x})"));
+            }
+            return Err(reason);
+        }
+        Ok(())
+    }
+}
+
+impl Display for Expectation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConformanceTest {
+    pub name: String,
+    pub rom_path: String,
+
+    /// The mode the test is run on, the test harness default
+    /// (DMG) is used in case it's not provided.
+    pub mode: Option<GameBoyMode>,
+
+    /// The number of cycles the test ROM runs for before
+    /// its outcome is checked.
+    pub cycles: u64,
+
+    pub expectations: Vec<Expectation>,
+}
+
+impl ConformanceTest {
+    /// Runs the test, any error or panic while running it
+    /// is reported as a failure.
+    pub fn run(&self) -> ConformanceResult {
+        let start = Instant::now();
+        let outcome = catch_unwind(AssertUnwindSafe(|| self.check()))
+            .unwrap_or_else(|payload| Err(format!("panic ({})", panic_message(payload))));
+        ConformanceResult {
+            name: self.name.clone(),
+            rom_path: self.rom_path.clone(),
+            passed: outcome.is_ok(),
+            detail: outcome.err(),
+            duration: start.elapsed(),
+        }
+    }
+
+    fn check(&self) -> Result<(), String> {
+        let options = TestOptions {
+            mode: self.mode,
+            ..Default::default()
+        };
+        let mut game_boy =
+            run_test(&self.rom_path, Some(self.cycles), options).map_err(|e| e.to_string())?;
+        let serial = game_boy.serial().device().state();
+        for expectation in &self.expectations {
+            expectation.check(&mut game_boy, &serial)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConformanceSuite {
+    pub tests: Vec<ConformanceTest>,
+}
+
+impl ConformanceSuite {
+    /// Parses the suite from its text representation, with the ROM
+    /// paths being relative to the provided base directory.
+    pub fn from_text(data: &str, base_dir: &Path) -> Result<Self, Error> {
+        let mut tests: Vec<ConformanceTest> = vec![];
+        for (index, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            let invalid =
+                |message: &str| Error::DataError(format!("{message} at line {}", index + 1));
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                tests.push(ConformanceTest {
+                    name: String::from(name.trim()),
+                    rom_path: String::new(),
+                    mode: None,
+                    cycles: DEFAULT_CYCLES,
+                    expectations: vec![],
+                });
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| invalid("Invalid line"))?;
+            let test = tests
+                .last_mut()
+                .ok_or_else(|| invalid("Property outside of a test"))?;
+            match key {
+                "rom" => test.rom_path = String::from(base_dir.join(value).to_string_lossy()),
+                "mode" => {
+                    test.mode = match value {
+                        "dmg" | "cgb" | "sgb" => Some(GameBoyMode::from_string(value)),
+                        _ => return Err(invalid("Invalid mode")),
+                    }
+                }
+                "cycles" => {
+                    test.cycles = value
+                        .replace('_', "")
+                        .parse()
+                        .map_err(|_| invalid("Invalid cycles"))?
+                }
+                "serial" => test
+                    .expectations
+                    .push(Expectation::Serial(String::from(value))),
+                "frame_crc" => test.expectations.push(Expectation::FrameCrc(
+                    u32::from_str_radix(value.trim_start_matches("0x"), 16)
+                        .map_err(|_| invalid("Invalid frame CRC"))?,
+                )),
+                "memory" => {
+                    let (addr, data) = value
+                        .split_once(':')
+                        .ok_or_else(|| invalid("Invalid memory"))?;
+                    let addr = u16::from_str_radix(addr.trim().trim_start_matches("0x"), 16)
+                        .map_err(|_| invalid("Invalid memory address"))?;
+                    let data = data.trim();
+                    let data = (0..data.len())
+                        .step_by(2)
+                        .map(|index| u8::from_str_radix(data.get(index..index + 2)?, 16).ok())
+                        .collect::<Option<Vec<u8>>>()
+                        .filter(|data| !data.is_empty())
+                        .ok_or_else(|| invalid("Invalid memory data"))?;
+                    test.expectations.push(Expectation::Memory(addr, data))
+                }
+                "signature" => test.expectations.push(match value {
+                    "mooneye" => Expectation::Mooneye,
+                    "auto" => Expectation::Auto,
+                    _ => return Err(invalid("Invalid signature")),
+                }),
+                _ => return Err(invalid(&format!("Unknown property '{key}'"))),
+            }
+        }
+        for test in &tests {
+            if test.rom_path.is_empty() || test.expectations.is_empty() {
+                return Err(Error::DataError(format!(
+                    "Test '{}' requires a ROM and at least one expectation",
+                    test.name
+                )));
+            }
+        }
+        Ok(Self { tests })
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let data = read_file(path)?;
+        let data = String::from_utf8(data).map_err(|e| Error::DataError(e.to_string()))?;
+        Self::from_text(&data, Path::new(path).parent().unwrap_or(Path::new(".")))
+    }
+
+    /// Builds a suite with every ROM (.gb and .gbc) found in the
+    /// directory (recursively), named after their relative paths and
+    /// expected to produce any of the known pass signatures.
+    ///
+    /// The CGB ROMs (.gbc) are run in CGB mode unless a mode is provided.
+    pub fn from_dir(path: &str, mode: Option<GameBoyMode>, cycles: u64) -> Result<Self, Error> {
+        let base_dir = Path::new(path);
+        let mut rom_paths = vec![];
+        Self::find_roms(base_dir, &mut rom_paths)?;
+        rom_paths.sort();
+        let tests = rom_paths
+            .into_iter()
+            .map(|rom_path| {
+                let cgb = rom_path.extension().is_some_and(|ext| ext == "gbc");
+                let name = rom_path
+                    .strip_prefix(base_dir)
+                    .unwrap_or(&rom_path)
+                    .with_extension("")
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                ConformanceTest {
+                    name,
+                    rom_path: String::from(rom_path.to_string_lossy()),
+                    mode: mode.or(if cgb { Some(GameBoyMode::Cgb) } else { None }),
+                    cycles,
+                    expectations: vec![Expectation::Auto],
+                }
+            })
+            .collect();
+        Ok(Self { tests })
+    }
+
+    fn find_roms(dir: &Path, rom_paths: &mut Vec<PathBuf>) -> Result<(), Error> {
+        let entries = read_dir(dir).map_err(|e| Error::IoError(e.to_string()))?;
+        for entry in entries {
+            let path = entry.map_err(|e| Error::IoError(e.to_string()))?.path();
+            if path.is_dir() {
+                Self::find_roms(&path, rom_paths)?;
+            } else if path
+                .extension()
+                .is_some_and(|ext| ext == "gb" || ext == "gbc")
+            {
+                rom_paths.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs all the tests of the suite (sequentially, so that the
+    /// timings are comparable), calling `on_result` as they finish.
+    pub fn run(&self, mut on_result: impl FnMut(&ConformanceResult)) -> ConformanceReport {
+        let start = Instant::now();
+        let results = self
+            .tests
+            .iter()
+            .map(|test| {
+                let result = test.run();
+                on_result(&result);
+                result
+            })
+            .collect();
+        ConformanceReport {
+            version: Info::version(),
+            results,
+            duration: start.elapsed(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConformanceResult {
+    pub name: String,
+    pub rom_path: String,
+    pub passed: bool,
+
+    /// The reason of the failure, if the test failed.
+    pub detail: Option<String>,
+
+    pub duration: Duration,
+}
+
+impl ConformanceResult {
+    pub fn description(&self) -> String {
+        let status = if self.passed { "pass" } else { "FAIL" };
+        let mut description = format!(
+            "{status} {} ({:.2}s)",
+            self.name,
+            self.duration.as_secs_f32()
+        );
+        if let Some(detail) = &self.detail {
+            description.push_str(&format!(": {detail}"));
+        }
+        description
+    }
+}
+
+impl Display for ConformanceResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConformanceReport {
+    /// The version of the emulator that produced the report.
+    pub version: String,
+
+    pub results: Vec<ConformanceResult>,
+    pub duration: Duration,
+}
+
+impl ConformanceReport {
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|result| result.passed).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.results.len() - self.passed()
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.failed() == 0
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut buffer = format!(
+            "# {} {} Conformance Report\n\n**{}/{}** tests passed in {:.2}s\n\n",
+            Info::name(),
+            self.version,
+            self.passed(),
+            self.results.len(),
+            self.duration.as_secs_f32()
+        );
+        buffer.push_str("| Test | ROM | Result | Time | Details |\n");
+        buffer.push_str("| --- | --- | --- | --- | --- |\n");
+        for result in &self.results {
+            buffer.push_str(&format!(
+                "| {} | `{}` | {} | {:.2}s | {} |\n",
+                result.name,
+                result.rom_path,
+                if result.passed {
+                    "✅ Pass"
+                } else {
+                    "❌ Fail"
+                },
+                result.duration.as_secs_f32(),
+                result.detail.as_deref().unwrap_or("").replace('|', "\\|")
+            ));
+        }
+        buffer
+    }
+
+    pub fn to_json(&self) -> String {
+        let results = self
+            .results
+            .iter()
+            .map(|result| {
+                format!(
+                    "    {{\"name\": {}, \"rom\": {}, \"passed\": {}, \"time\": {:.3}, \"detail\": {}}}",
+                    json_string(&result.name),
+                    json_string(&result.rom_path),
+                    result.passed,
+                    result.duration.as_secs_f64(),
+                    result.detail.as_deref().map_or(String::from("null"), json_string)
+                )
+            })
+            .collect::<Vec<_>>();
+        format!(
+            "{{\n  \"name\": {},\n  \"version\": {},\n  \"passed\": {},\n  \"failed\": {},\n  \"time\": {:.3},\n  \"results\": [\n{}\n  ]\n}}\n",
+            json_string(&Info::name()),
+            json_string(&self.version),
+            self.passed(),
+            self.failed(),
+            self.duration.as_secs_f64(),
+            results.join(",\n")
+        )
+    }
+
+    /// Renders the report as JUnit XML, the format understood by
+    /// most of the CI systems to display the test results.
+    pub fn to_junit(&self) -> String {
+        let mut buffer = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuite name=\"{} {}\" tests=\"{}\" failures=\"{}\" errors=\"0\" time=\"{:.3}\">\n",
+            xml_string(&Info::name()),
+            xml_string(&self.version),
+            self.results.len(),
+            self.failed(),
+            self.duration.as_secs_f64()
+        );
+        for result in &self.results {
+            buffer.push_str(&format!(
+                "  <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                xml_string(&result.name),
+                xml_string(&result.rom_path),
+                result.duration.as_secs_f64()
+            ));
+            match &result.detail {
+                Some(detail) if !result.passed => buffer.push_str(&format!(
+                    ">\n    <failure message=\"{}\"/>\n  </testcase>\n",
+                    xml_string(detail)
+                )),
+                _ => buffer.push_str("/>\n"),
+            }
+        }
+        buffer.push_str("</testsuite>\n");
+        buffer
+    }
+}
+
+impl Display for ConformanceReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} tests passed ({} failed)",
+            self.passed(),
+            self.results.len(),
+            self.failed()
+        )
+    }
+}
+
+fn hex_string(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn xml_string(value: &str) -> String {
+    let mut buffer = String::with_capacity(value.len());
+    for char in value.chars() {
+        match char {
+            '&' => buffer.push_str("&amp;"),
+            '<' => buffer.push_str("&lt;"),
+            '>' => buffer.push_str("&gt;"),
+            '"' => buffer.push_str("&quot;"),
+            '\'' => buffer.push_str("&apos;"),
+            char if (char as u32) < 0x20 && char != '\n' && char != '\t' => {
+                buffer.push_str(&format!("&#x{:x};", char as u32))
+            }
+            char => buffer.push(char),
+        }
+    }
+    buffer
+}
+
+fn json_string(value: &str) -> String {
+    let mut buffer = String::with_capacity(value.len() + 2);
+    buffer.push('"');
+    for char in value.chars() {
+        match char {
+            '"' => buffer.push_str("\\\""),
+            '\\' => buffer.push_str("\\\\"),
+            '\n' => buffer.push_str("\\n"),
+            '\r' => buffer.push_str("\\r"),
+            '\t' => buffer.push_str("\\t"),
+            char if (char as u32) < 0x20 => buffer.push_str(&format!("\\u{:04x}", char as u32)),
+            char => buffer.push(char),
+        }
+    }
+    buffer.push('"');
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{ConformanceSuite, Expectation, DEFAULT_CYCLES};
+
+    use crate::gb::GameBoyMode;
+
+    #[test]
+    fn test_from_text() {
+        let suite = ConformanceSuite::from_text(
+            "# comment\n[cpu]\nrom = cpu.gb\nserial = Passed\n\n[acid]\nrom = acid.gb\nmode = cgb\ncycles = 1_000\nframe_crc = 0x0000abcd\n",
+            Path::new("roms"),
+        )
+        .unwrap();
+        assert_eq!(suite.tests.len(), 2);
+        assert_eq!(suite.tests[0].name, "cpu");
+        assert_eq!(
+            Path::new(&suite.tests[0].rom_path),
+            Path::new("roms").join("cpu.gb")
+        );
+        assert_eq!(suite.tests[0].mode, None);
+        assert_eq!(suite.tests[0].cycles, DEFAULT_CYCLES);
+        assert_eq!(
+            suite.tests[0].expectations,
+            vec![Expectation::Serial(String::from("Passed"))]
+        );
+        assert_eq!(suite.tests[1].mode, Some(GameBoyMode::Cgb));
+        assert_eq!(suite.tests[1].cycles, 1000);
+        assert_eq!(
+            suite.tests[1].expectations,
+            vec![Expectation::FrameCrc(0xabcd)]
+        );
+
+        let suite = ConformanceSuite::from_text(
+            "[sound]\nrom = sound.gb\nmemory = 0xa000:00deb061\n\n[mooneye]\nrom = mooneye.gb\nsignature = mooneye\n",
+            Path::new("."),
+        )
+        .unwrap();
+        assert_eq!(
+            suite.tests[0].expectations,
+            vec![Expectation::Memory(0xa000, vec![0x00, 0xde, 0xb0, 0x61])]
+        );
+        assert_eq!(suite.tests[1].expectations, vec![Expectation::Mooneye]);
+
+        assert!(ConformanceSuite::from_text("rom = cpu.gb\n", Path::new(".")).is_err());
+        assert!(ConformanceSuite::from_text("[cpu]\nrom = cpu.gb\n", Path::new(".")).is_err());
+        assert!(
+            ConformanceSuite::from_text("[cpu]\nrom = a.gb\nspeed = 2\n", Path::new(".")).is_err()
+        );
+        assert!(ConformanceSuite::from_text(
+            "[cpu]\nrom = a.gb\nmemory = 0xa000:0\n",
+            Path::new(".")
+        )
+        .is_err());
+        assert!(ConformanceSuite::from_text(
+            "[cpu]\nrom = a.gb\nsignature = other\n",
+            Path::new(".")
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_from_dir() {
+        let suite = ConformanceSuite::from_dir("res/roms/test/blargg", None, 50_000_000).unwrap();
+        let test = suite
+            .tests
+            .iter()
+            .find(|test| test.name == "instr_timing/instr_timing")
+            .unwrap();
+        assert_eq!(test.mode, None);
+        assert_eq!(test.cycles, 50_000_000);
+        assert_eq!(test.expectations, vec![Expectation::Auto]);
+        assert!(test.run().passed);
+
+        assert_eq!(suite.tests[0].name, "cgb_sound/cgb_sound");
+        assert!(suite.tests.iter().any(|test| test.name == "cpu/01-special"));
+
+        assert!(ConformanceSuite::from_dir("res/roms/test/missing", None, DEFAULT_CYCLES).is_err());
+    }
+
+    #[test]
+    fn test_run() {
+        let suite = ConformanceSuite::from_text(
+            "[instr_timing]\nrom = blargg/instr_timing/instr_timing.gb\ncycles = 50000000\nserial = Passed\n\n[missing]\nrom = missing.gb\nserial = Passed\n",
+            Path::new("res/roms/test"),
+        )
+        .unwrap();
+        let mut count = 0;
+        let report = suite.run(|_| count += 1);
+        assert_eq!(count, 2);
+        assert_eq!(report.passed(), 1);
+        assert_eq!(report.failed(), 1);
+        assert!(!report.is_ok());
+        assert!(report.results[0].passed);
+        assert!(report.results[1].detail.is_some());
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("**1/2** tests passed"));
+        assert!(markdown.contains("| instr_timing |"));
+        let json = report.to_json();
+        assert!(json.contains("\"passed\": 1,"));
+        assert!(json.contains("\"name\": \"missing\""));
+        let junit = report.to_junit();
+        assert!(junit.contains("tests=\"2\" failures=\"1\""));
+        assert!(junit.contains("<testcase name=\"instr_timing\""));
+        assert!(junit.contains("<failure message="));
+    }
+}
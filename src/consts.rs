@@ -1,5 +1,8 @@
 //! Game Boy specific hardware constants.
 
+// Joypad registers
+pub const P1_ADDR: u16 = 0xff00;
+
 // Timer registers
 pub const DIV_ADDR: u16 = 0xff04;
 pub const TIMA_ADDR: u16 = 0xff05;
@@ -17,8 +20,14 @@ pub const LYC_ADDR: u16 = 0xff45;
 pub const BGP_ADDR: u16 = 0xff47;
 pub const OBP0_ADDR: u16 = 0xff48;
 pub const OBP1_ADDR: u16 = 0xff49;
-pub const WX_ADDR: u16 = 0xff4a;
-pub const WY_ADDR: u16 = 0xff4b;
+pub const WY_ADDR: u16 = 0xff4a;
+pub const WX_ADDR: u16 = 0xff4b;
+pub const VBK_ADDR: u16 = 0xff4f;
+pub const BCPS_ADDR: u16 = 0xff68;
+pub const BCPD_ADDR: u16 = 0xff69;
+pub const OCPS_ADDR: u16 = 0xff6a;
+pub const OCPD_ADDR: u16 = 0xff6b;
+pub const OPRI_ADDR: u16 = 0xff6c;
 
 // APU registers
 pub const NR10_ADDR: u16 = 0xff10;
@@ -44,6 +53,8 @@ pub const NR44_ADDR: u16 = 0xff23;
 pub const NR50_ADDR: u16 = 0xff24;
 pub const NR51_ADDR: u16 = 0xff25;
 pub const NR52_ADDR: u16 = 0xff26;
+pub const WAVE_RAM_START: u16 = 0xff30;
+pub const WAVE_RAM_END: u16 = 0xff3f;
 
 // DMA registers
 pub const DMA_ADDR: u16 = 0xff46;
@@ -56,3 +67,11 @@ pub const HDMA5_ADDR: u16 = 0xff55;
 // Serial registers
 pub const SB_ADDR: u16 = 0xff01;
 pub const SC_ADDR: u16 = 0xff02;
+
+// System registers
+pub const KEY0_ADDR: u16 = 0xff4c;
+pub const KEY1_ADDR: u16 = 0xff4d;
+pub const BOOT_ADDR: u16 = 0xff50;
+pub const RP_ADDR: u16 = 0xff56;
+pub const SVBK_ADDR: u16 = 0xff70;
+pub const IE_ADDR: u16 = 0xffff;
@@ -12,14 +12,19 @@ use boytacean_common::{
 };
 use std::{
     fmt::{self, Display, Formatter},
-    io::Cursor,
+    fs::File,
+    io::{BufWriter, Cursor, Write},
     sync::Mutex,
 };
 
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
 use crate::{
     apu::Apu,
     assert_pedantic_gb,
-    consts::LCDC_ADDR,
+    block::BlockCache,
+    consts::{LCDC_ADDR, NR50_ADDR, NR51_ADDR},
     debugln,
     dma::Dma,
     gb::GameBoyConfig,
@@ -30,12 +35,184 @@ use crate::{
     serial::Serial,
     state::{StateComponent, StateFormat},
     timer::Timer,
+    warnln,
 };
 
 pub const PREFIX: u8 = 0xcb;
 
 pub type Instruction = &'static (fn(&mut Cpu), u8, &'static str);
 
+/// Format of the lines written by the instruction [`Tracer`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// Boytacean's own format, with the address, mnemonic and
+    /// cycles of the instruction, the registers and the number
+    /// of cycles executed (by the traced instructions) so far.
+    Default,
+
+    /// Format compatible with the [Gameboy Doctor](https://github.com/robert/gameboy-doctor)
+    /// logs, supported by many other emulators, making it possible
+    /// to diff traces. Registers are logged before the instruction
+    /// executes, together with the 4 bytes of memory at PC.
+    Doctor,
+}
+
+impl TraceFormat {
+    pub fn description(&self) -> &'static str {
+        match self {
+            TraceFormat::Default => "Default",
+            TraceFormat::Doctor => "Gameboy Doctor",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "default" => Ok(TraceFormat::Default),
+            "doctor" => Ok(TraceFormat::Doctor),
+            _ => Err(Error::InvalidParameter(format!(
+                "Invalid trace format: {value}"
+            ))),
+        }
+    }
+}
+
+impl Display for TraceFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// The destination of the lines written by the [`Tracer`].
+pub enum TraceTarget {
+    File(BufWriter<File>),
+    Callback(fn(line: &str)),
+}
+
+/// Instruction level execution tracer, writes a line per executed
+/// instruction (before its execution) to the target.
+pub struct Tracer {
+    format: TraceFormat,
+    target: TraceTarget,
+
+    /// The number of cycles executed by the traced instructions.
+    cycles: u64,
+
+    /// The number of instructions that have been traced.
+    count: u64,
+}
+
+impl Tracer {
+    pub fn new(target: TraceTarget, format: TraceFormat) -> Self {
+        Self {
+            format,
+            target,
+            cycles: 0,
+            count: 0,
+        }
+    }
+
+    /// Creates a tracer that writes to the file in the provided path,
+    /// the file is truncated in case it already exists.
+    pub fn from_file(path: &str, format: TraceFormat) -> Result<Self, Error> {
+        let file = File::create(path)?;
+        Ok(Self::new(TraceTarget::File(BufWriter::new(file)), format))
+    }
+
+    pub fn from_callback(callback: fn(line: &str), format: TraceFormat) -> Self {
+        Self::new(TraceTarget::Callback(callback), format)
+    }
+
+    pub fn format(&self) -> TraceFormat {
+        self.format
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Flushes any buffered lines to the underlying target.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        if let TraceTarget::File(writer) = &mut self.target {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn line(&self, cpu: &Cpu, inst: Instruction, inst_pc: u16) -> String {
+        match self.format {
+            TraceFormat::Default => format!("{} CY={}", cpu.description(inst, inst_pc), self.cycles),
+            TraceFormat::Doctor => format!(
+                "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X} PCMEM:{:02X},{:02X},{:02X},{:02X}",
+                cpu.a,
+                cpu.f(),
+                cpu.b,
+                cpu.c,
+                cpu.d,
+                cpu.e,
+                cpu.h,
+                cpu.l,
+                cpu.sp,
+                inst_pc,
+                cpu.mmu.peek(inst_pc),
+                cpu.mmu.peek(inst_pc.wrapping_add(1)),
+                cpu.mmu.peek(inst_pc.wrapping_add(2)),
+                cpu.mmu.peek(inst_pc.wrapping_add(3)),
+            ),
+        }
+    }
+
+    fn write(&mut self, line: &str) -> Result<(), Error> {
+        match &mut self.target {
+            TraceTarget::File(writer) => writeln!(writer, "{line}")?,
+            TraceTarget::Callback(callback) => callback(line),
+        }
+        self.count += 1;
+        Ok(())
+    }
+}
+
+/// Strategy used by the CPU to execute the instructions.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CpuBackend {
+    /// Fetches and decodes each of the instructions from the
+    /// memory bus before executing it.
+    #[default]
+    Interpreter,
+
+    /// Dispatches the instructions of pre-decoded basic blocks kept
+    /// in a cache (see [`BlockCache`]), falling back to the fetch
+    /// of the instructions for the memory that can't be cached.
+    Cached,
+}
+
+impl CpuBackend {
+    pub fn description(&self) -> &'static str {
+        match self {
+            CpuBackend::Interpreter => "Interpreter",
+            CpuBackend::Cached => "Cached",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "interpreter" => Ok(CpuBackend::Interpreter),
+            "cached" => Ok(CpuBackend::Cached),
+            _ => Err(Error::CustomError(format!("Invalid CPU backend: {value}"))),
+        }
+    }
+}
+
+impl Display for CpuBackend {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
 pub struct Cpu {
     pub pc: u16,
     pub sp: u16,
@@ -71,6 +248,31 @@ pub struct Cpu {
     /// Game Boy emulator, that can be used to control the behaviour
     /// of Game Boy emulation.
     gbc: SharedThread<GameBoyConfig>,
+
+    /// The optional instruction tracer, that when set logs every
+    /// instruction executed by the CPU.
+    tracer: Option<Box<Tracer>>,
+
+    /// The optional callback called after every executed instruction
+    /// with the PC, opcode (0xcbXX for prefixed ones) and cycles, to be
+    /// used for co-simulation (lock-stepping) against other emulators
+    /// or hardware traces.
+    #[cfg(feature = "cosim")]
+    step_callback: Option<fn(pc: u16, opcode: u16, cycles: u8)>,
+
+    /// The strategy used to execute the instructions.
+    backend: CpuBackend,
+
+    /// The cache of the pre-decoded blocks of code, only used
+    /// by the cached backend.
+    blocks: BlockCache,
+
+    /// The immediate operands of the current instruction pre-fetched
+    /// by the cached backend, read in place of the memory from the
+    /// `imm_pc` address onwards.
+    imm: [u8; 2],
+    imm_pc: u16,
+    imm_size: u16,
 }
 
 impl Cpu {
@@ -95,6 +297,14 @@ impl Cpu {
             cycles: 0,
             ppc: 0x0,
             gbc,
+            tracer: None,
+            #[cfg(feature = "cosim")]
+            step_callback: None,
+            backend: CpuBackend::Interpreter,
+            blocks: BlockCache::new(),
+            imm: [0x00; 2],
+            imm_pc: 0x0,
+            imm_size: 0,
         }
     }
 
@@ -115,6 +325,8 @@ impl Cpu {
         self.carry = false;
         self.halted = false;
         self.cycles = 0;
+        self.blocks.clear();
+        self.imm_size = 0;
     }
 
     /// Sets the CPU registers and some of the memory space to the
@@ -139,9 +351,13 @@ impl Cpu {
 
         // updates part of the MMU state, disabling the
         // boot memory overlap and setting the LCD control
-        // register to enabled (required by some ROMs)
+        // register to enabled (required by some ROMs), the
+        // sound output is routed to both terminals at full
+        // volume as done by the boot ROM
         self.mmu.set_boot_active(false);
         self.mmu.write(LCDC_ADDR, 0x91);
+        self.mmu.write(NR50_ADDR, 0x77);
+        self.mmu.write(NR51_ADDR, 0xf3);
     }
 
     pub fn clock(&mut self) -> u8 {
@@ -162,6 +378,13 @@ impl Cpu {
             pc
         );
 
+        // in case a DMA transfer is stealing cycles from the CPU
+        // those are spent before anything else can be executed
+        let stall = self.mmu.dma().take_stall_cycles();
+        if stall > 0 {
+            return stall as u8;
+        }
+
         // @TODO this is so bad, need to improve this by an order
         // of magnitude, to be able to have better performance
         // in case the CPU execution halted and there's an interrupt
@@ -295,13 +518,25 @@ impl Cpu {
         // (Program Counter) according to the final value returned
         // by the fetch operation (we may need to fetch instruction
         // more than one byte of length)
-        let (inst, pc) = self.fetch(self.pc);
+        let (inst, pc) = match self.backend {
+            CpuBackend::Interpreter => self.fetch(self.pc),
+            CpuBackend::Cached => self.fetch_cached(self.pc),
+        };
         self.ppc = self.pc;
         self.pc = pc;
 
         #[allow(unused_variables)]
         let (inst_fn, inst_time, inst_str) = inst;
 
+        // reads the opcode of the instruction before its execution
+        // (as it may change memory), prefixed ones are 0xcbXX
+        #[cfg(feature = "cosim")]
+        let opcode = if self.step_callback.is_some() {
+            self.opcode(self.ppc)
+        } else {
+            0x0000
+        };
+
         #[cfg(feature = "cpulog")]
         if *inst_str == "! UNIMP !" || *inst_str == "HALT" {
             if *inst_str == "HALT" {
@@ -321,6 +556,19 @@ impl Cpu {
             println!("{}", self.description(inst, self.ppc));
         }
 
+        // in case the tracer is enabled, logs the instruction that
+        // is going to be executed, with the current CPU state
+        if self.tracer.is_some() {
+            self.trace(inst);
+        }
+
+        // in case the APU has cycles pending (batch mode) and the
+        // instruction may read one of its registers, processes them
+        // so that the CPU reads up to date values (eg: NR52 status)
+        if self.mmu.apu_i().batch_cycles() > 0 && self.reads_apu(self.ppc) {
+            self.mmu.apu().flush_batch();
+        }
+
         // calls the current instruction and increments the number of
         // cycles executed by the instruction time of the instruction
         // that has just been executed
@@ -328,6 +576,15 @@ impl Cpu {
         inst_fn(self);
         self.cycles = self.cycles.wrapping_add(*inst_time);
 
+        if let Some(tracer) = &mut self.tracer {
+            tracer.cycles += self.cycles as u64;
+        }
+
+        #[cfg(feature = "cosim")]
+        if let Some(step_callback) = self.step_callback {
+            step_callback(self.ppc, opcode, self.cycles);
+        }
+
         // returns the number of cycles that the operation
         // that has been executed has taken
         self.cycles
@@ -360,6 +617,24 @@ impl Cpu {
         (inst, pc)
     }
 
+    /// Fetches the instruction from the cache of pre-decoded blocks,
+    /// falling back to the memory bus if it can't be cached.
+    #[inline(always)]
+    fn fetch_cached(&mut self, pc: u16) -> (Instruction, u16) {
+        match self.blocks.fetch(pc, &mut self.mmu) {
+            Some(op) => {
+                self.imm = op.operands;
+                self.imm_pc = op.next;
+                self.imm_size = op.prefetched;
+                (op.inst, op.next)
+            }
+            None => {
+                self.imm_size = 0;
+                self.fetch(pc)
+            }
+        }
+    }
+
     #[inline(always)]
     pub fn mmu(&mut self) -> &mut Mmu {
         &mut self.mmu
@@ -547,7 +822,14 @@ impl Cpu {
 
     #[inline(always)]
     pub fn read_u8(&mut self) -> u8 {
-        let byte = self.mmu.read(self.pc);
+        // uses the operands pre-fetched by the cached backend
+        // if available, avoiding the memory bus access
+        let offset = self.pc.wrapping_sub(self.imm_pc);
+        let byte = if offset < self.imm_size {
+            self.imm[offset as usize & 0x01]
+        } else {
+            self.mmu.read(self.pc)
+        };
         self.pc = self.pc.wrapping_add(1);
         byte
     }
@@ -651,6 +933,20 @@ impl Cpu {
         self.gbc = value;
     }
 
+    pub fn backend(&self) -> CpuBackend {
+        self.backend
+    }
+
+    pub fn set_backend(&mut self, value: CpuBackend) {
+        self.backend = value;
+        self.blocks.clear();
+        self.imm_size = 0;
+    }
+
+    pub fn blocks(&self) -> &BlockCache {
+        &self.blocks
+    }
+
     pub fn description(&self, inst: Instruction, inst_pc: u16) -> String {
         let (_, inst_time, inst_str) = inst;
         let title_str: String = format!("[0x{inst_pc:04x}] {inst_str}");
@@ -664,6 +960,84 @@ impl Cpu {
         let (inst, _) = self.fetch(self.ppc);
         self.description(inst, self.ppc)
     }
+
+    pub fn tracer(&self) -> Option<&Tracer> {
+        self.tracer.as_deref()
+    }
+
+    pub fn tracing(&self) -> bool {
+        self.tracer.is_some()
+    }
+
+    /// Sets (or unsets) the instruction tracer, the previous tracer
+    /// (if any) is flushed and returned.
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) -> Option<Tracer> {
+        let mut previous = self.tracer.take().map(|tracer| *tracer);
+        if let Some(previous) = &mut previous {
+            if let Err(err) = previous.flush() {
+                warnln!("Failed to flush the CPU trace: {}", err);
+            }
+        }
+        self.tracer = tracer.map(Box::new);
+        previous
+    }
+
+    /// Sets (or unsets) the callback called after every executed
+    /// instruction with the PC, opcode and number of cycles taken.
+    #[cfg(feature = "cosim")]
+    pub fn set_step_callback(&mut self, callback: Option<fn(pc: u16, opcode: u16, cycles: u8)>) {
+        self.step_callback = callback;
+    }
+
+    /// Checks if the instruction at the provided address may read
+    /// one of the APU registers, resolving the address to be read
+    /// from the instruction operands and the current registers.
+    fn reads_apu(&self, pc: u16) -> bool {
+        let addr = match self.mmu.peek(pc) {
+            0xf0 => 0xff00 | self.mmu.peek(pc.wrapping_add(1)) as u16,
+            0xf2 => 0xff00 | self.c as u16,
+            0xfa => u16::from_le_bytes([
+                self.mmu.peek(pc.wrapping_add(1)),
+                self.mmu.peek(pc.wrapping_add(2)),
+            ]),
+            0x0a => self.bc(),
+            0x1a => self.de(),
+            0x2a | 0x3a | 0x34 | 0x35 => self.hl(),
+            0x76 => return false,
+            opcode if (0x40..=0xbf).contains(&opcode) && opcode & 0x07 == 0x06 => self.hl(),
+            PREFIX if self.mmu.peek(pc.wrapping_add(1)) & 0x07 == 0x06 => self.hl(),
+            _ => return false,
+        };
+        (0xff10..=0xff3f).contains(&addr)
+    }
+
+    #[cfg(feature = "cosim")]
+    fn opcode(&self, pc: u16) -> u16 {
+        // uses peek so that inspecting the opcode never
+        // triggers (or records) a watchpoint hit
+        let opcode = self.mmu.peek(pc);
+        if opcode == PREFIX {
+            0xcb00 | self.mmu.peek(pc.wrapping_add(1)) as u16
+        } else {
+            opcode as u16
+        }
+    }
+
+    #[cold]
+    fn trace(&mut self, inst: Instruction) {
+        let mut tracer = match self.tracer.take() {
+            Some(tracer) => tracer,
+            None => return,
+        };
+        let line = tracer.line(self, inst, self.ppc);
+
+        // in case there's an error writing the trace (eg: disk full)
+        // the tracer is disabled, to avoid flooding the output
+        match tracer.write(&line) {
+            Ok(_) => self.tracer = Some(tracer),
+            Err(err) => warnln!("Failed to write the CPU trace, disabling it: {}", err),
+        }
+    }
 }
 
 impl StateComponent for Cpu {
@@ -708,6 +1082,8 @@ impl StateComponent for Cpu {
         self.halted = read_u8(&mut cursor)? != 0;
         self.cycles = read_u8(&mut cursor)?;
         self.ppc = read_u16(&mut cursor)?;
+        self.blocks.clear();
+        self.imm_size = 0;
         Ok(())
     }
 }
@@ -733,7 +1109,7 @@ mod tests {
 
     use crate::{gb::GameBoyConfig, mmu::Mmu, state::StateComponent};
 
-    use super::Cpu;
+    use super::{BlockCache, Cpu, CpuBackend, TraceFormat, Tracer};
 
     #[test]
     fn test_cpu_clock() {
@@ -888,6 +1264,14 @@ mod tests {
             cycles: 0x78,
             ppc: 0x9abc,
             gbc: SharedThread::new(Mutex::new(GameBoyConfig::default())),
+            tracer: None,
+            #[cfg(feature = "cosim")]
+            step_callback: None,
+            backend: CpuBackend::Interpreter,
+            blocks: BlockCache::new(),
+            imm: [0x00; 2],
+            imm_pc: 0x0,
+            imm_size: 0,
         };
 
         let state = cpu.state(None).unwrap();
@@ -914,4 +1298,63 @@ mod tests {
         assert_eq!(new_cpu.cycles, 0x78);
         assert_eq!(new_cpu.ppc, 0x9abc);
     }
+
+    #[test]
+    fn test_trace() {
+        static LINES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+        let mut cpu = Cpu::default();
+        cpu.boot();
+        cpu.mmu.allocate_default();
+        cpu.set_tracer(Some(Tracer::from_callback(
+            |line| LINES.lock().unwrap().push(String::from(line)),
+            TraceFormat::Doctor,
+        )));
+
+        // runs a NOP followed by LD A, 0x42
+        cpu.pc = 0xc000;
+        cpu.mmu.write(0xc000, 0x00);
+        cpu.mmu.write(0xc001, 0x3e);
+        cpu.mmu.write(0xc002, 0x42);
+        cpu.clock();
+        cpu.clock();
+
+        let lines = LINES.lock().unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            "A:01 F:00 B:FF C:13 D:00 E:C1 H:84 L:03 SP:FFFE PC:C000 PCMEM:00,3E,42,00"
+        );
+        assert!(lines[1].starts_with("A:01 F:00"));
+        assert!(lines[1].contains("PC:C001 PCMEM:3E,42"));
+
+        let tracer = cpu.set_tracer(None).unwrap();
+        assert_eq!(tracer.count(), 2);
+        assert_eq!(tracer.cycles(), 12);
+        assert!(!cpu.tracing());
+    }
+
+    #[cfg(feature = "cosim")]
+    #[test]
+    fn test_step_callback() {
+        static STEPS: Mutex<Vec<(u16, u16, u8)>> = Mutex::new(Vec::new());
+
+        let mut cpu = Cpu::default();
+        cpu.boot();
+        cpu.mmu.allocate_default();
+        cpu.set_step_callback(Some(|pc, opcode, cycles| {
+            STEPS.lock().unwrap().push((pc, opcode, cycles))
+        }));
+
+        // runs a NOP followed by SWAP A (prefixed)
+        cpu.pc = 0xc000;
+        cpu.mmu.write(0xc000, 0x00);
+        cpu.mmu.write(0xc001, 0xcb);
+        cpu.mmu.write(0xc002, 0x37);
+        cpu.clock();
+        cpu.clock();
+
+        let steps = STEPS.lock().unwrap();
+        assert_eq!(*steps, vec![(0xc000, 0x0000, 4), (0xc001, 0xcb37, 8)]);
+    }
 }
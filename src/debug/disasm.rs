@@ -0,0 +1,201 @@
+//! Disassembler for the SM83 (LR35902) instruction set.
+//!
+//! Decodes memory into structured instruction records, built on top
+//! of the instruction tables of [`crate::inst`], so that debugger
+//! front-ends can list the code around an address.
+
+use std::fmt::{self, Display, Formatter};
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    cpu::PREFIX,
+    inst::{EXTENDED, INSTRUCTIONS},
+};
+
+/// A single decoded (disassembled) instruction.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisasmInstruction {
+    address: u16,
+    bytes: Vec<u8>,
+    mnemonic: String,
+    operands: Vec<String>,
+    cycles: u8,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl DisasmInstruction {
+    /// The address of the first byte of the instruction.
+    pub fn address(&self) -> u16 {
+        self.address
+    }
+
+    /// The raw bytes of the instruction, including the
+    /// prefix (for extended instructions) and immediates.
+    pub fn bytes(&self) -> Vec<u8> {
+        self.bytes.clone()
+    }
+
+    pub fn mnemonic(&self) -> String {
+        self.mnemonic.clone()
+    }
+
+    /// The operands of the instruction, with the immediate values
+    /// resolved, relative jumps are resolved to their target address.
+    pub fn operands(&self) -> Vec<String> {
+        self.operands.clone()
+    }
+
+    /// The number of cycles taken by the instruction, for conditional
+    /// instructions this is the value for the branch not taken.
+    pub fn cycles(&self) -> u8 {
+        self.cycles
+    }
+
+    pub fn length(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// The textual representation of the instruction, eg: `LD A, 0x42`.
+    pub fn text(&self) -> String {
+        if self.operands.is_empty() {
+            self.mnemonic.clone()
+        } else {
+            format!("{} {}", self.mnemonic, self.operands.join(", "))
+        }
+    }
+}
+
+impl Display for DisasmInstruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let bytes = self
+            .bytes
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<Vec<String>>()
+            .join(" ");
+        write!(
+            f,
+            "0x{:04x}: {: <8} {: <20} ({} cycles)",
+            self.address,
+            bytes,
+            self.text(),
+            self.cycles
+        )
+    }
+}
+
+/// Disassembles the instruction at the provided address, using the
+/// `read` function to access the memory.
+pub fn disassemble_one(read: &impl Fn(u16) -> u8, addr: u16) -> DisasmInstruction {
+    let mut bytes = vec![read(addr)];
+    let (_, cycles, inst_str) = if bytes[0] == PREFIX {
+        bytes.push(read(addr.wrapping_add(1)));
+        &EXTENDED[bytes[1] as usize]
+    } else {
+        &INSTRUCTIONS[bytes[0] as usize]
+    };
+
+    let inst_str = inst_str.trim();
+    let (mnemonic, operands) = inst_str.split_once(' ').unwrap_or((inst_str, ""));
+
+    let operands = operands
+        .split(',')
+        .map(|operand| operand.trim())
+        .filter(|operand| !operand.is_empty())
+        .map(|operand| {
+            let next = addr.wrapping_add(bytes.len() as u16);
+            if operand.contains("u16") {
+                let value = read(next) as u16 | (read(next.wrapping_add(1)) as u16) << 8;
+                bytes.extend([value as u8, (value >> 8) as u8]);
+                operand.replace("u16", &format!("0x{value:04x}"))
+            } else if operand.contains("u8") {
+                let value = read(next);
+                bytes.push(value);
+                operand.replace("u8", &format!("0x{value:02x}"))
+            } else if operand.contains("i8") {
+                let value = read(next) as i8;
+                bytes.push(value as u8);
+                if mnemonic == "JR" {
+                    let target = addr
+                        .wrapping_add(bytes.len() as u16)
+                        .wrapping_add(value as u16);
+                    format!("0x{target:04x}")
+                } else if operand == "i8" {
+                    format!("{value}")
+                } else {
+                    operand.replace("+i8", &format!("{value:+}"))
+                }
+            } else {
+                String::from(operand)
+            }
+        })
+        .collect();
+
+    DisasmInstruction {
+        address: addr,
+        bytes,
+        mnemonic: String::from(mnemonic),
+        operands,
+        cycles: *cycles,
+    }
+}
+
+/// Disassembles `count` consecutive instructions starting at the
+/// provided address, using the `read` function to access the memory.
+pub fn disassemble(read: impl Fn(u16) -> u8, addr: u16, count: usize) -> Vec<DisasmInstruction> {
+    let mut addr = addr;
+    let mut instructions = Vec::with_capacity(count);
+    for _ in 0..count {
+        let instruction = disassemble_one(&read, addr);
+        addr = addr.wrapping_add(instruction.length() as u16);
+        instructions.push(instruction);
+    }
+    instructions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::disassemble;
+
+    #[test]
+    fn test_disassemble() {
+        let data = [
+            0x00, // NOP
+            0x3e, 0x42, // LD A, 0x42
+            0xea, 0x00, 0xc0, // LD [0xc000], A
+            0xcb, 0x7c, // BIT 7, H
+            0x20, 0xfc, // JR NZ, -4
+            0xf8, 0xfe, // LD HL, SP-2
+            0xe0, 0x40, // LD [FF00+0x40], A
+        ];
+        let read = |addr: u16| data.get(addr as usize).copied().unwrap_or(0x00);
+        let instructions = disassemble(read, 0x0000, 7);
+
+        assert_eq!(instructions[0].text(), "NOP");
+        assert_eq!(instructions[0].bytes(), vec![0x00]);
+        assert_eq!(instructions[0].cycles(), 4);
+
+        assert_eq!(instructions[1].address(), 0x0001);
+        assert_eq!(instructions[1].mnemonic(), "LD");
+        assert_eq!(instructions[1].operands(), vec!["A", "0x42"]);
+        assert_eq!(instructions[1].cycles(), 8);
+
+        assert_eq!(instructions[2].text(), "LD [0xc000], A");
+        assert_eq!(instructions[2].bytes(), vec![0xea, 0x00, 0xc0]);
+
+        assert_eq!(instructions[3].address(), 0x0006);
+        assert_eq!(instructions[3].text(), "BIT 7, H");
+        assert_eq!(instructions[3].bytes(), vec![0xcb, 0x7c]);
+
+        assert_eq!(instructions[4].text(), "JR NZ, 0x0006");
+        assert_eq!(instructions[5].text(), "LD HL, SP-2");
+        assert_eq!(instructions[6].text(), "LD [FF00+0x40], A");
+        assert_eq!(
+            format!("{}", instructions[2]),
+            "0x0003: ea 00 c0 LD [0xc000], A       (16 cycles)"
+        );
+    }
+}
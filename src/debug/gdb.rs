@@ -0,0 +1,697 @@
+//! GDB remote serial protocol (RSP) server implementation.
+//!
+//! Allows `gdb` (or any other RSP compatible tool) to attach to the
+//! emulated SM83 (LR35902) CPU over TCP, supporting register and memory
+//! access, breakpoints, watchpoints, single-stepping and continue.
+//!
+//! The registers are exposed as six 16 bit little endian values in the
+//! following order: AF, BC, DE, HL, SP and PC.
+
+use std::{
+    collections::HashSet,
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+use boytacean_common::error::Error;
+
+use crate::{
+    gb::GameBoy,
+    mmu::{WatchHit, WatchKind, Watchpoint},
+};
+
+/// The number of registers exposed to the GDB client.
+pub const GDB_REGISTER_COUNT: usize = 6;
+
+/// The maximum size of a packet accepted by the server, as
+/// announced to the client in the `qSupported` response.
+pub const GDB_PACKET_SIZE: usize = 0x1000;
+
+/// The reason for the execution of the CPU to be stopped,
+/// reported to the client using a stop reply packet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GdbStop {
+    /// Stopped by a signal, eg: SIGINT (0x02) on user
+    /// interruption or SIGTRAP (0x05) after a step.
+    Signal(u8),
+
+    /// Stopped by a software breakpoint at the current PC.
+    Breakpoint,
+
+    /// Stopped by a memory access matching a watchpoint.
+    Watchpoint(WatchHit),
+}
+
+impl GdbStop {
+    /// Obtains the stop reply packet payload for the stop reason.
+    pub fn reply(&self) -> String {
+        match self {
+            GdbStop::Signal(signal) => format!("S{signal:02x}"),
+            GdbStop::Breakpoint => String::from("T05swbreak:;"),
+            GdbStop::Watchpoint(hit) => {
+                let name = match hit.watchpoint.kind {
+                    WatchKind::Read => "rwatch",
+                    WatchKind::Write => "watch",
+                    WatchKind::Access => "awatch",
+                };
+                format!("T05{name}:{:04x};", hit.addr)
+            }
+        }
+    }
+}
+
+/// Protocol level state of the GDB server, handles the packet
+/// payloads independently of the underlying transport.
+pub struct GdbStub {
+    /// The set of addresses with an active (software) breakpoint.
+    breakpoints: HashSet<u16>,
+
+    /// If the CPU is currently running (continue) or stopped
+    /// waiting for commands from the client.
+    running: bool,
+
+    /// If the acknowledgment of packets has been disabled by
+    /// the client (`QStartNoAckMode`).
+    no_ack: bool,
+
+    /// Set when the client requested the end of the session
+    /// (detach or kill).
+    detached: bool,
+}
+
+impl GdbStub {
+    pub fn new() -> Self {
+        Self {
+            breakpoints: HashSet::new(),
+            running: false,
+            no_ack: false,
+            detached: false,
+        }
+    }
+
+    pub fn running(&self) -> bool {
+        self.running
+    }
+
+    pub fn no_ack(&self) -> bool {
+        self.no_ack
+    }
+
+    pub fn detached(&self) -> bool {
+        self.detached
+    }
+
+    pub fn breakpoints(&self) -> &HashSet<u16> {
+        &self.breakpoints
+    }
+
+    /// Starts a new debugging session, stopping the CPU so that
+    /// the client can inspect it right after attaching.
+    pub fn attach(&mut self) {
+        self.running = false;
+        self.no_ack = false;
+        self.detached = false;
+    }
+
+    /// Ends the debugging session, removing every breakpoint and
+    /// watchpoint and resuming the normal execution of the CPU.
+    pub fn detach(&mut self, gb: &mut GameBoy) {
+        self.breakpoints.clear();
+        gb.mmu().clear_watchpoints();
+        self.running = true;
+        self.no_ack = false;
+        self.detached = true;
+    }
+
+    /// Stops the CPU as requested by the client (Ctrl+C), returning
+    /// the stop reply to be sent if the CPU was running.
+    pub fn interrupt(&mut self) -> Option<String> {
+        if !self.running {
+            return None;
+        }
+        self.running = false;
+        Some(GdbStop::Signal(0x02).reply())
+    }
+
+    /// Runs a single CPU instruction (clocking the rest of the system)
+    /// and checks for breakpoints and watchpoints, stopping the CPU
+    /// if any of them has been hit.
+    pub fn clock(&mut self, gb: &mut GameBoy) -> (u16, Option<GdbStop>) {
+        let cycles = gb.clock();
+        let stop = if let Some(hit) = gb.mmu().take_watch_hit() {
+            Some(GdbStop::Watchpoint(hit))
+        } else if self.breakpoints.contains(&gb.cpu_i().pc()) {
+            Some(GdbStop::Breakpoint)
+        } else {
+            None
+        };
+        if stop.is_some() {
+            self.running = false;
+        }
+        (cycles, stop)
+    }
+
+    /// Handles the payload of a packet sent by the client, returning
+    /// the payload of the response, `None` means that no response is
+    /// to be sent now (eg: on continue, where the response is the stop
+    /// reply sent once the CPU stops).
+    pub fn handle(&mut self, gb: &mut GameBoy, packet: &str) -> Option<String> {
+        let command = match packet.as_bytes().first() {
+            Some(byte) if byte.is_ascii() => *byte as char,
+            _ => return Some(String::new()),
+        };
+        let args = &packet[1..];
+        let reply = match command {
+            '?' => GdbStop::Signal(0x05).reply(),
+            'g' => self.read_registers(gb),
+            'G' => self.write_registers(gb, args),
+            'p' => self.read_register(gb, args),
+            'P' => self.write_register(gb, args),
+            'm' => self.read_memory(gb, args),
+            'M' => self.write_memory(gb, args),
+            'c' => return self.resume(gb, args, false),
+            's' => return self.resume(gb, args, true),
+            'Z' => self.set_point(gb, args, true),
+            'z' => self.set_point(gb, args, false),
+            'H' | 'T' => String::from("OK"),
+            'k' => {
+                self.detach(gb);
+                return None;
+            }
+            'D' => {
+                self.detach(gb);
+                String::from("OK")
+            }
+            'q' | 'Q' => self.query(packet),
+            'v' => return self.handle_v(gb, packet),
+            _ => String::new(),
+        };
+        Some(reply)
+    }
+
+    fn handle_v(&mut self, gb: &mut GameBoy, packet: &str) -> Option<String> {
+        if packet == "vCont?" {
+            return Some(String::from("vCont;c;C;s;S"));
+        }
+        if let Some(actions) = packet.strip_prefix("vCont;") {
+            let step = actions.starts_with('s') || actions.starts_with('S');
+            return self.resume(gb, "", step);
+        }
+        Some(String::new())
+    }
+
+    fn query(&mut self, packet: &str) -> String {
+        match packet.split(':').next().unwrap_or_default() {
+            "qSupported" => format!(
+                "PacketSize={GDB_PACKET_SIZE:x};QStartNoAckMode+;swbreak+;hwbreak+;vContSupported+"
+            ),
+            "QStartNoAckMode" => {
+                self.no_ack = true;
+                String::from("OK")
+            }
+            "qAttached" => String::from("1"),
+            "qC" => String::from("QC1"),
+            "qfThreadInfo" => String::from("m1"),
+            "qsThreadInfo" => String::from("l"),
+            "qOffsets" => String::from("Text=0;Data=0;Bss=0"),
+            "qSymbol" => String::from("OK"),
+            _ => String::new(),
+        }
+    }
+
+    fn resume(&mut self, gb: &mut GameBoy, args: &str, step: bool) -> Option<String> {
+        if !args.is_empty() {
+            match u16::from_str_radix(args, 16) {
+                Ok(addr) => gb.cpu().set_pc(addr),
+                Err(_) => return Some(String::from("E01")),
+            }
+        }
+        if step {
+            self.running = true;
+            let (_, stop) = self.clock(gb);
+            self.running = false;
+            return Some(stop.unwrap_or(GdbStop::Signal(0x05)).reply());
+        }
+        self.running = true;
+        None
+    }
+
+    fn registers(gb: &GameBoy) -> [u16; GDB_REGISTER_COUNT] {
+        let cpu = gb.cpu_i();
+        [cpu.af(), cpu.bc(), cpu.de(), cpu.hl(), cpu.sp(), cpu.pc()]
+    }
+
+    fn set_register(gb: &mut GameBoy, index: usize, value: u16) -> bool {
+        let cpu = gb.cpu();
+        match index {
+            0 => cpu.set_af(value),
+            1 => cpu.set_bc(value),
+            2 => cpu.set_de(value),
+            3 => cpu.set_hl(value),
+            4 => cpu.set_sp(value),
+            5 => cpu.set_pc(value),
+            _ => return false,
+        }
+        true
+    }
+
+    fn read_registers(&self, gb: &GameBoy) -> String {
+        Self::registers(gb)
+            .iter()
+            .map(|value| encode_hex(&value.to_le_bytes()))
+            .collect()
+    }
+
+    fn write_registers(&self, gb: &mut GameBoy, args: &str) -> String {
+        let data = match decode_hex(args) {
+            Some(data) if data.len() >= GDB_REGISTER_COUNT * 2 => data,
+            _ => return String::from("E01"),
+        };
+        for (index, value) in data.chunks(2).take(GDB_REGISTER_COUNT).enumerate() {
+            Self::set_register(gb, index, u16::from_le_bytes([value[0], value[1]]));
+        }
+        String::from("OK")
+    }
+
+    fn read_register(&self, gb: &GameBoy, args: &str) -> String {
+        match usize::from_str_radix(args, 16) {
+            Ok(index) if index < GDB_REGISTER_COUNT => {
+                encode_hex(&Self::registers(gb)[index].to_le_bytes())
+            }
+            _ => String::from("E01"),
+        }
+    }
+
+    fn write_register(&self, gb: &mut GameBoy, args: &str) -> String {
+        let (index, value) = match args.split_once('=') {
+            Some(value) => value,
+            None => return String::from("E01"),
+        };
+        let index = usize::from_str_radix(index, 16).ok();
+        let value = decode_hex(value).filter(|value| value.len() == 2);
+        match (index, value) {
+            (Some(index), Some(value))
+                if Self::set_register(gb, index, u16::from_le_bytes([value[0], value[1]])) =>
+            {
+                String::from("OK")
+            }
+            _ => String::from("E01"),
+        }
+    }
+
+    fn read_memory(&self, gb: &GameBoy, args: &str) -> String {
+        match parse_range(args) {
+            Some((addr, length)) => encode_hex(&gb.mmu_i().peek_many(addr, length)),
+            None => String::from("E01"),
+        }
+    }
+
+    fn write_memory(&self, gb: &mut GameBoy, args: &str) -> String {
+        let (range, data) = match args.split_once(':') {
+            Some(value) => value,
+            None => return String::from("E01"),
+        };
+        match (parse_range(range), decode_hex(data)) {
+            (Some((addr, length)), Some(data)) if data.len() == length => {
+                gb.mmu().write_many(addr, &data);
+
+                // the writes from the debugger must not be
+                // reported as watchpoint hits
+                gb.mmu().take_watch_hit();
+
+                String::from("OK")
+            }
+            _ => String::from("E01"),
+        }
+    }
+
+    fn set_point(&mut self, gb: &mut GameBoy, args: &str, insert: bool) -> String {
+        let mut parts = args.split(',');
+        let kind = parts.next().unwrap_or_default();
+        let addr = parts
+            .next()
+            .and_then(|value| u16::from_str_radix(value, 16).ok());
+        let length = parts
+            .next()
+            .and_then(|value| u16::from_str_radix(value, 16).ok())
+            .unwrap_or(1);
+        let addr = match addr {
+            Some(addr) => addr,
+            None => return String::from("E01"),
+        };
+        let watch_kind = match kind {
+            "0" | "1" => {
+                if insert {
+                    self.breakpoints.insert(addr);
+                } else {
+                    self.breakpoints.remove(&addr);
+                }
+                return String::from("OK");
+            }
+            "2" => WatchKind::Write,
+            "3" => WatchKind::Read,
+            "4" => WatchKind::Access,
+            _ => return String::new(),
+        };
+        let watchpoint = Watchpoint::new(addr, length, watch_kind);
+        if insert {
+            gb.mmu().add_watchpoint(watchpoint);
+        } else {
+            gb.mmu().remove_watchpoint(&watchpoint);
+        }
+        String::from("OK")
+    }
+}
+
+impl Default for GdbStub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// TCP server for the GDB remote serial protocol, accepts a single
+/// client at a time and runs in a non-blocking fashion so that it
+/// can be polled from the main loop of a frontend.
+pub struct GdbServer {
+    listener: TcpListener,
+    stream: Option<TcpStream>,
+    buffer: Vec<u8>,
+    stub: GdbStub,
+}
+
+impl GdbServer {
+    /// Creates a new server listening on the provided port of
+    /// the local host (port 0 picks an available port).
+    pub fn bind(port: u16) -> Result<Self, Error> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            stream: None,
+            buffer: vec![],
+            stub: GdbStub::new(),
+        })
+    }
+
+    pub fn port(&self) -> Result<u16, Error> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    pub fn connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// If the emulation should be running, either because the client
+    /// requested so or because the client has detached.
+    pub fn running(&self) -> bool {
+        self.stub.running()
+    }
+
+    pub fn stub(&self) -> &GdbStub {
+        &self.stub
+    }
+
+    /// Accepts new connections and handles the packets sent by the
+    /// client, should be called periodically from the main loop.
+    pub fn poll(&mut self, gb: &mut GameBoy) -> Result<(), Error> {
+        if self.stream.is_none() {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true)?;
+                    stream.set_nodelay(true)?;
+                    self.stream = Some(stream);
+                    self.buffer.clear();
+                    self.stub.attach();
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let mut data = [0u8; 1024];
+        loop {
+            let stream = match self.stream.as_mut() {
+                Some(stream) => stream,
+                None => return Ok(()),
+            };
+            match stream.read(&mut data) {
+                Ok(0) => {
+                    self.disconnect(gb);
+                    return Ok(());
+                }
+                Ok(count) => self.buffer.extend_from_slice(&data[..count]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => {
+                    self.disconnect(gb);
+                    return Ok(());
+                }
+            }
+        }
+
+        self.process(gb)
+    }
+
+    /// Runs a single CPU instruction, sending the stop reply to
+    /// the client in case a breakpoint or watchpoint is hit.
+    pub fn clock(&mut self, gb: &mut GameBoy) -> Result<u16, Error> {
+        let (cycles, stop) = self.stub.clock(gb);
+        if let Some(stop) = stop {
+            self.send_packet(&stop.reply())?;
+        }
+        Ok(cycles)
+    }
+
+    fn process(&mut self, gb: &mut GameBoy) -> Result<(), Error> {
+        while !self.buffer.is_empty() {
+            match self.buffer[0] {
+                b'+' | b'-' => {
+                    self.buffer.remove(0);
+                }
+                0x03 => {
+                    self.buffer.remove(0);
+                    if let Some(reply) = self.stub.interrupt() {
+                        self.send_packet(&reply)?;
+                    }
+                }
+                b'$' => {
+                    let end = match self.buffer.iter().position(|byte| *byte == b'#') {
+                        Some(end) if self.buffer.len() >= end + 3 => end,
+                        _ => return Ok(()),
+                    };
+                    let packet: Vec<u8> = self.buffer.drain(..end + 3).collect();
+                    let payload = &packet[1..end];
+                    let expected = std::str::from_utf8(&packet[end + 1..])
+                        .ok()
+                        .and_then(|value| u8::from_str_radix(value, 16).ok());
+                    if expected != Some(checksum(payload)) {
+                        if !self.stub.no_ack() {
+                            self.send_raw(b"-")?;
+                        }
+                        continue;
+                    }
+                    if !self.stub.no_ack() {
+                        self.send_raw(b"+")?;
+                    }
+                    let payload = String::from_utf8_lossy(payload).to_string();
+                    if let Some(reply) = self.stub.handle(gb, &payload) {
+                        self.send_packet(&reply)?;
+                    }
+                    if self.stub.detached() {
+                        self.disconnect(gb);
+                        return Ok(());
+                    }
+                }
+                _ => {
+                    self.buffer.remove(0);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn disconnect(&mut self, gb: &mut GameBoy) {
+        self.stream = None;
+        self.buffer.clear();
+        self.stub.detach(gb);
+    }
+
+    fn send_packet(&mut self, payload: &str) -> Result<(), Error> {
+        let packet = format!("${payload}#{:02x}", checksum(payload.as_bytes()));
+        self.send_raw(packet.as_bytes())
+    }
+
+    fn send_raw(&mut self, data: &[u8]) -> Result<(), Error> {
+        let stream = match self.stream.as_mut() {
+            Some(stream) => stream,
+            None => return Ok(()),
+        };
+        let mut offset = 0;
+        while offset < data.len() {
+            match stream.write(&data[offset..]) {
+                Ok(count) => offset += count,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => thread::yield_now(),
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+fn encode_hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[allow(clippy::manual_is_multiple_of)]
+fn decode_hex(data: &str) -> Option<Vec<u8>> {
+    if data.len() % 2 != 0 {
+        return None;
+    }
+    (0..data.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(data.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+/// Parses an `addr,length` memory range argument.
+fn parse_range(args: &str) -> Option<(u16, usize)> {
+    let (addr, length) = args.split_once(',')?;
+    let addr = u16::from_str_radix(addr, 16).ok()?;
+    let length = usize::from_str_radix(length, 16).ok()?;
+    if length > GDB_PACKET_SIZE / 2 {
+        return None;
+    }
+    Some((addr, length))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+    };
+
+    use crate::test::build_pocket_test;
+
+    use super::{checksum, GdbServer, GdbStub};
+
+    #[test]
+    fn test_registers() {
+        let mut gb = build_pocket_test();
+        let mut stub = GdbStub::new();
+        gb.cpu().set_bc(0x1234);
+        gb.cpu().set_pc(0x0150);
+
+        let registers = stub.handle(&mut gb, "g").unwrap();
+        assert_eq!(registers.len(), 24);
+        assert_eq!(&registers[4..8], "3412");
+        assert_eq!(&registers[20..24], "5001");
+        assert_eq!(stub.handle(&mut gb, "p5").unwrap(), "5001");
+
+        assert_eq!(stub.handle(&mut gb, "P2=cdab").unwrap(), "OK");
+        assert_eq!(gb.cpu_i().de(), 0xabcd);
+        assert_eq!(stub.handle(&mut gb, "P9=cdab").unwrap(), "E01");
+    }
+
+    #[test]
+    fn test_memory() {
+        let mut gb = build_pocket_test();
+        let mut stub = GdbStub::new();
+        assert_eq!(stub.handle(&mut gb, "Mc000,3:0a0b0c").unwrap(), "OK");
+        assert_eq!(stub.handle(&mut gb, "mc000,3").unwrap(), "0a0b0c");
+        assert_eq!(stub.handle(&mut gb, "Mc000,2:0a").unwrap(), "E01");
+        assert_eq!(stub.handle(&mut gb, "mzz,1").unwrap(), "E01");
+    }
+
+    #[test]
+    fn test_invalid_packet() {
+        let mut gb = build_pocket_test();
+        let mut stub = GdbStub::new();
+        assert_eq!(stub.handle(&mut gb, "").unwrap(), "");
+        assert_eq!(stub.handle(&mut gb, "\u{e9}g").unwrap(), "");
+        assert_eq!(stub.handle(&mut gb, "\u{fffd}").unwrap(), "");
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut gb = build_pocket_test();
+        let mut stub = GdbStub::new();
+        gb.step_to(0x0100);
+        let pc = gb.cpu_i().pc();
+        assert_eq!(stub.handle(&mut gb, "s").unwrap(), "S05");
+        let next = gb.cpu_i().pc();
+        assert_ne!(pc, next);
+
+        gb.cpu().set_pc(pc);
+        assert_eq!(
+            stub.handle(&mut gb, &format!("Z0,{next:x},1")).unwrap(),
+            "OK"
+        );
+        assert_eq!(stub.handle(&mut gb, "c"), None);
+        assert!(stub.running());
+        let (_, stop) = stub.clock(&mut gb);
+        assert_eq!(stop.unwrap().reply(), "T05swbreak:;");
+        assert!(!stub.running());
+        assert_eq!(gb.cpu_i().pc(), next);
+
+        assert_eq!(
+            stub.handle(&mut gb, &format!("z0,{next:x},1")).unwrap(),
+            "OK"
+        );
+        assert!(stub.breakpoints().is_empty());
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        let mut gb = build_pocket_test();
+        let mut stub = GdbStub::new();
+        assert_eq!(stub.handle(&mut gb, "Z2,ff40,1").unwrap(), "OK");
+        assert_eq!(stub.handle(&mut gb, "c"), None);
+        let stop = loop {
+            let (_, stop) = stub.clock(&mut gb);
+            if let Some(stop) = stop {
+                break stop;
+            }
+        };
+        assert_eq!(stop.reply(), "T05watch:ff40;");
+        assert_eq!(stub.handle(&mut gb, "z2,ff40,1").unwrap(), "OK");
+        assert!(gb.mmu_i().watchpoints().is_empty());
+    }
+
+    #[test]
+    fn test_server() {
+        let mut gb = build_pocket_test();
+        let mut server = GdbServer::bind(0).unwrap();
+        let mut client = TcpStream::connect(("127.0.0.1", server.port().unwrap())).unwrap();
+        let packet = format!("$?#{:02x}", checksum(b"?"));
+        client.write_all(packet.as_bytes()).unwrap();
+
+        let mut response = vec![];
+        let mut data = [0u8; 64];
+        while !response.ends_with(format!("#{:02x}", checksum(b"S05")).as_bytes()) {
+            server.poll(&mut gb).unwrap();
+            client.set_nonblocking(true).unwrap();
+            if let Ok(count) = client.read(&mut data) {
+                response.extend_from_slice(&data[..count]);
+            }
+        }
+        assert!(server.connected());
+        assert!(!server.running());
+        assert_eq!(
+            String::from_utf8(response).unwrap(),
+            format!("+$S05#{:02x}", checksum(b"S05"))
+        );
+
+        let packet = format!("$D#{:02x}", checksum(b"D"));
+        client.write_all(packet.as_bytes()).unwrap();
+        while server.connected() {
+            server.poll(&mut gb).unwrap();
+        }
+        assert!(server.running());
+    }
+}
@@ -0,0 +1,9 @@
+//! Debugging facilities for the emulated system.
+//!
+//! Includes a [GDB](https://www.sourceware.org/gdb/) remote serial
+//! protocol server (see [`gdb`]) that allows external debuggers to
+//! control the execution of the emulated CPU, and a disassembler
+//! (see [`disasm`]) for listing the code in memory.
+
+pub mod disasm;
+pub mod gdb;
@@ -1,3 +1,5 @@
+use boytacean_common::error::Error;
+
 use crate::serial::SerialDevice;
 
 use std::fmt::{self, Display, Formatter};
@@ -47,6 +49,19 @@ impl SerialDevice for BufferDevice {
         let buffer = self.buffer.clone();
         String::from_utf8(buffer).unwrap()
     }
+
+    fn id(&self) -> &'static str {
+        "buffer"
+    }
+
+    fn device_state(&self) -> Option<Vec<u8>> {
+        Some(self.buffer.clone())
+    }
+
+    fn set_device_state(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.buffer = data.to_vec();
+        Ok(())
+    }
 }
 
 impl Default for BufferDevice {
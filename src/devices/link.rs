@@ -0,0 +1,177 @@
+//! Link cable connecting the serial ports of two Game Boy instances
+//! running in the same process (eg: two player games).
+
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::{Arc, Mutex},
+};
+
+use crate::{gb::GameBoy, serial::SerialDevice};
+
+/// The end of the cable plugged into one of the instances, as
+/// seen by the other instance.
+#[derive(Clone, Copy)]
+struct LinkPort {
+    /// The byte in the transfer register (SB) of the instance.
+    data: u8,
+
+    /// The byte shifted out by the other instance (acting as master)
+    /// waiting to be shifted into this one.
+    inbox: Option<u8>,
+}
+
+impl Default for LinkPort {
+    fn default() -> Self {
+        Self {
+            data: 0xff,
+            inbox: None,
+        }
+    }
+}
+
+/// Cable connecting two instances, each of them attached to one
+/// of the ends of the cable using a [`LinkDevice`].
+///
+/// The instance acting as master (internal clock) exchanges its byte
+/// with the last one published by the other instance, which has its
+/// (externally clocked) transfer completed on the next [`Self::sync`],
+/// meaning that both instances should be run interleaved in slices of
+/// a fraction of the duration of a transfer (4096 cycles).
+#[derive(Clone, Default)]
+pub struct LinkCable {
+    ports: Arc<Mutex<[LinkPort; 2]>>,
+}
+
+impl LinkCable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the device for the provided end of the cable (0 or 1).
+    pub fn device(&self, side: usize) -> LinkDevice {
+        LinkDevice {
+            ports: self.ports.clone(),
+            side,
+        }
+    }
+
+    /// Attaches both instances to the ends of the cable, replacing
+    /// their current serial devices.
+    pub fn connect(&self, first: &mut GameBoy, second: &mut GameBoy) {
+        *self.ports.lock().unwrap() = Default::default();
+        first.attach_serial(Box::new(self.device(0)));
+        second.attach_serial(Box::new(self.device(1)));
+        self.sync(first, second);
+    }
+
+    /// Exchanges the pending data between both instances, to be
+    /// called whenever any of them has run for a slice.
+    pub fn sync(&self, first: &mut GameBoy, second: &mut GameBoy) {
+        let mut ports = self.ports.lock().unwrap();
+        for (port, gb) in ports.iter_mut().zip([first, second]) {
+            let serial = gb.serial();
+            if let Some(byte) = port.inbox.take() {
+                serial.complete_external(byte);
+            }
+            port.data = serial.data();
+        }
+    }
+}
+
+/// Serial device at one of the ends of a [`LinkCable`].
+pub struct LinkDevice {
+    ports: Arc<Mutex<[LinkPort; 2]>>,
+    side: usize,
+}
+
+impl LinkDevice {
+    pub fn side(&self) -> usize {
+        self.side
+    }
+}
+
+impl SerialDevice for LinkDevice {
+    fn send(&mut self) -> u8 {
+        self.ports.lock().unwrap()[1 - self.side].data
+    }
+
+    fn receive(&mut self, byte: u8) {
+        self.ports.lock().unwrap()[1 - self.side].inbox = Some(byte);
+    }
+
+    fn allow_slave(&self) -> bool {
+        true
+    }
+
+    fn description(&self) -> String {
+        format!("Link ({})", self.side + 1)
+    }
+
+    fn state(&self) -> String {
+        let ports = self.ports.lock().unwrap();
+        format!(
+            "Local: 0x{:02x}, Remote: 0x{:02x}",
+            ports[self.side].data,
+            ports[1 - self.side].data
+        )
+    }
+
+    fn id(&self) -> &'static str {
+        "link"
+    }
+}
+
+impl Display for LinkDevice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Link")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        consts::{SB_ADDR, SC_ADDR},
+        gb::GameBoy,
+    };
+
+    use super::LinkCable;
+
+    #[test]
+    fn test_link_cable() {
+        let mut master = GameBoy::default();
+        let mut slave = GameBoy::default();
+        let cable = LinkCable::new();
+        cable.connect(&mut master, &mut slave);
+
+        // the slave waits for the transfer with the external clock
+        // and the master starts it afterwards, with both bytes
+        // being exchanged once the transfer is completed
+        slave.serial().write(SB_ADDR, 0x42);
+        slave.serial().write(SC_ADDR, 0x80);
+        cable.sync(&mut master, &mut slave);
+        master.serial().write(SB_ADDR, 0x24);
+        master.serial().write(SC_ADDR, 0x81);
+        for _ in 0..8 {
+            master.serial_clock(512);
+            cable.sync(&mut master, &mut slave);
+        }
+        assert!(!master.serial().transferring());
+        assert!(!slave.serial().transferring());
+        assert!(master.serial().int_serial());
+        assert!(slave.serial().int_serial());
+        assert_eq!(master.serial().read(SB_ADDR), 0x42);
+        assert_eq!(slave.serial().read(SB_ADDR), 0x24);
+
+        // a slave that's not waiting for a transfer keeps its byte
+        slave.serial().ack_serial();
+        master.serial().write(SB_ADDR, 0x11);
+        master.serial().write(SC_ADDR, 0x81);
+        for _ in 0..8 {
+            master.serial_clock(512);
+            cable.sync(&mut master, &mut slave);
+        }
+        assert_eq!(master.serial().read(SB_ADDR), 0x24);
+        assert_eq!(slave.serial().read(SB_ADDR), 0x24);
+        assert!(!slave.serial().int_serial());
+    }
+}
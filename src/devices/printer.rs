@@ -1,4 +1,12 @@
-use std::fmt::{self, Display, Formatter};
+use std::{
+    fmt::{self, Display, Formatter},
+    io::Cursor,
+};
+
+use boytacean_common::{
+    data::{read_into, read_u16, read_u8, write_bytes, write_u16, write_u8},
+    error::Error,
+};
 
 use crate::{ppu::PaletteAlpha, serial::SerialDevice, warnln};
 
@@ -341,6 +349,46 @@ impl SerialDevice for PrinterDevice {
     fn state(&self) -> String {
         self.command.to_string()
     }
+
+    fn id(&self) -> &'static str {
+        "printer"
+    }
+
+    fn device_state(&self) -> Option<Vec<u8>> {
+        let mut cursor = Cursor::new(vec![]);
+        write_u8(&mut cursor, self.state as u8).ok()?;
+        write_u8(&mut cursor, self.command as u8).ok()?;
+        write_u8(&mut cursor, self.compression as u8).ok()?;
+        write_u16(&mut cursor, self.command_length).ok()?;
+        write_u16(&mut cursor, self.length_left).ok()?;
+        write_u16(&mut cursor, self.checksum).ok()?;
+        write_u8(&mut cursor, self.status).ok()?;
+        write_u8(&mut cursor, self.byte_out).ok()?;
+        write_bytes(&mut cursor, &self.data).ok()?;
+        write_u16(&mut cursor, self.image_offset).ok()?;
+        write_bytes(&mut cursor, &self.image[..self.image_offset as usize]).ok()?;
+        Some(cursor.into_inner())
+    }
+
+    fn set_device_state(&mut self, data: &[u8]) -> Result<(), Error> {
+        let mut cursor = Cursor::new(data);
+        self.state = PrinterState::from_u8(read_u8(&mut cursor)?);
+        self.command = PrinterCommand::from_u8(read_u8(&mut cursor)?);
+        self.compression = read_u8(&mut cursor)? != 0;
+        self.command_length = read_u16(&mut cursor)?;
+        self.length_left = read_u16(&mut cursor)?;
+        self.checksum = read_u16(&mut cursor)?;
+        self.status = read_u8(&mut cursor)?;
+        self.byte_out = read_u8(&mut cursor)?;
+        read_into(&mut cursor, &mut self.data)?;
+        self.image_offset = read_u16(&mut cursor)?;
+        if self.image_offset as usize > self.image.len() {
+            return Err(Error::InvalidData);
+        }
+        self.image = [0x00; 160 * 200];
+        read_into(&mut cursor, &mut self.image[..self.image_offset as usize])?;
+        Ok(())
+    }
 }
 
 impl Default for PrinterDevice {
@@ -48,6 +48,10 @@ impl SerialDevice for StdoutDevice {
     fn state(&self) -> String {
         String::from("")
     }
+
+    fn id(&self) -> &'static str {
+        "stdout"
+    }
 }
 
 impl Default for StdoutDevice {
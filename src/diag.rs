@@ -4,10 +4,197 @@
 //! a global instance of the emulator, which is going to be used
 //! in panic diagnostics
 
-use std::ptr::null;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    ptr::null,
+    time::{Duration, Instant},
+};
 
 use crate::gb::GameBoy;
 
+thread_local! {
+    /// Number of heap allocations made by the current thread
+    /// (only counted under the [`CountingAllocator`]).
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Global allocator that counts the heap allocations made by each
+/// thread, allowing the verification that the hot path of the
+/// emulation (eg: running a frame) is allocation free once in a
+/// steady state, which reduces jitter in low-end and WASM targets.
+///
+/// # Usage
+///
+/// ```rust
+/// use boytacean::diag::CountingAllocator;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator;
+/// ```
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[cfg(test)]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn count_allocation() {
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+/// Obtains the number of heap allocations made so far by the current
+/// thread, always zero unless [`CountingAllocator`] is the global allocator.
+pub fn allocations() -> u64 {
+    ALLOCATIONS.with(|count| count.get())
+}
+
+/// Runs the provided function returning its result together with the
+/// number of heap allocations it made (see [`allocations()`]).
+pub fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, u64) {
+    let start = allocations();
+    let result = f();
+    (result, allocations() - start)
+}
+
+/// The subsystems of the emulator whose time is measured
+/// by the [`Profiler`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfileSlot {
+    Cpu = 0,
+    Ppu = 1,
+    Apu = 2,
+
+    /// Any other device (DMA, timer, serial, SGB, etc.).
+    Other = 3,
+}
+
+/// Time (in milliseconds) spent by each of the subsystems of
+/// the emulator in a frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameProfile {
+    pub cpu: f32,
+    pub ppu: f32,
+    pub apu: f32,
+    pub other: f32,
+}
+
+impl FrameProfile {
+    pub fn total(&self) -> f32 {
+        self.cpu + self.ppu + self.apu + self.other
+    }
+}
+
+/// Frame budget profiler that measures the time spent by each
+/// of the subsystems of the emulator, keeping the profile of the
+/// last frame and a rolling (exponential) average.
+///
+/// The measurement itself has a cost (two clock reads per device
+/// clock), so it should only be enabled while being displayed, it's
+/// also not available in WASM targets where there's no monotonic clock.
+pub struct Profiler {
+    mark: Instant,
+    current: [Duration; 4],
+    last: FrameProfile,
+    average: FrameProfile,
+    frames: u64,
+}
+
+impl Profiler {
+    /// Weight of the last frame in the rolling average.
+    const AVERAGE_WEIGHT: f32 = 0.1;
+
+    pub fn new() -> Self {
+        Self {
+            mark: Instant::now(),
+            current: [Duration::ZERO; 4],
+            last: FrameProfile::default(),
+            average: FrameProfile::default(),
+            frames: 0,
+        }
+    }
+
+    /// Starts a new measurement, the time elapsed since the last
+    /// mark (spent outside of the emulator) is ignored.
+    #[inline(always)]
+    pub fn start(&mut self) {
+        self.mark = Instant::now();
+    }
+
+    /// Accounts the time elapsed since the last mark (or start) to
+    /// the provided subsystem.
+    #[inline(always)]
+    pub fn mark(&mut self, slot: ProfileSlot) {
+        let now = Instant::now();
+        self.current[slot as usize] += now - self.mark;
+        self.mark = now;
+    }
+
+    /// Closes the profile of the current frame, updating the
+    /// last frame profile and the rolling average.
+    pub fn end_frame(&mut self) {
+        let [cpu, ppu, apu, other] = self.current.map(|value| value.as_secs_f32() * 1000.0);
+        self.last = FrameProfile {
+            cpu,
+            ppu,
+            apu,
+            other,
+        };
+        self.average = if self.frames == 0 {
+            self.last
+        } else {
+            let weight = Self::AVERAGE_WEIGHT;
+            FrameProfile {
+                cpu: self.average.cpu + (cpu - self.average.cpu) * weight,
+                ppu: self.average.ppu + (ppu - self.average.ppu) * weight,
+                apu: self.average.apu + (apu - self.average.apu) * weight,
+                other: self.average.other + (other - self.average.other) * weight,
+            }
+        };
+        self.current = [Duration::ZERO; 4];
+        self.frames += 1;
+    }
+
+    pub fn last(&self) -> FrameProfile {
+        self.last
+    }
+
+    pub fn average(&self) -> FrameProfile {
+        self.average
+    }
+
+    /// The number of frames profiled so far.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Static mutable reference to the global instance of the
 /// Game Boy emulator, going to be used for global diagnostics.
 static mut GLOBAL_INSTANCE: *const GameBoy = null();
@@ -159,3 +346,62 @@ macro_rules! assert_pedantic_gb {
         ()
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::gb::{AudioProvider, GameBoy, GameBoyMode};
+
+    use super::count_allocations;
+
+    #[test]
+    fn test_frame_allocations() {
+        for (mode, path) in [
+            (GameBoyMode::Dmg, "res/roms/test/dmg_acid2.gb"),
+            (GameBoyMode::Cgb, "res/roms/test/cgb_acid2.gbc"),
+        ] {
+            let mut gb = GameBoy::new(Some(mode));
+            gb.load(true).unwrap();
+            gb.load_rom_file(path, None).unwrap();
+            let mut audio = vec![];
+            for _ in 0..60 {
+                gb.next_frame();
+                gb.take_audio_frame_into(&mut audio);
+                gb.clear_audio_buffer();
+            }
+
+            // once in the steady state running a frame and consuming
+            // its audio samples must not allocate heap memory
+            for _ in 0..60 {
+                let (_, count) = count_allocations(|| {
+                    gb.next_frame();
+                    gb.frame_buffer();
+                    gb.take_audio_frame_into(&mut audio);
+                    gb.clear_audio_buffer();
+                });
+                assert_eq!(count, 0, "Frame {} allocated", gb.ppu_frame());
+            }
+        }
+    }
+
+    #[test]
+    fn test_profiler() {
+        let mut gb = GameBoy::new(Some(GameBoyMode::Dmg));
+        gb.load(true).unwrap();
+        gb.load_rom_file("res/roms/test/dmg_acid2.gb", None)
+            .unwrap();
+        assert!(gb.profiler().is_none());
+
+        gb.enable_profiler();
+        for _ in 0..10 {
+            gb.next_frame();
+        }
+        let profiler = gb.profiler().unwrap();
+        assert_eq!(profiler.frames(), 10);
+        assert!(profiler.last().cpu > 0.0);
+        assert!(profiler.last().ppu > 0.0);
+        assert!(profiler.average().total() > 0.0);
+
+        gb.disable_profiler();
+        assert!(gb.profiler().is_none());
+    }
+}
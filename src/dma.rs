@@ -15,12 +15,21 @@ use boytacean_common::{
 
 use crate::{
     consts::{DMA_ADDR, HDMA1_ADDR, HDMA2_ADDR, HDMA3_ADDR, HDMA4_ADDR, HDMA5_ADDR},
+    io::{Io, IoComponent, IoRegister},
     mmu::BusComponent,
-    panic_gb,
-    state::{StateComponent, StateFormat},
+    state::{StateComponent, StateFormat, StateMigration},
     warnln,
 };
 
+/// The maximum number of cycles the CPU is stalled for in a single
+/// clock, long transfers (eg: GDMA) are spread over multiple clocks
+/// so that the remaining components are clocked in small steps.
+pub const STALL_CHUNK: u16 = 64;
+
+/// The number of (normal speed) cycles taken by the transfer of a
+/// block of 0x10 bytes in a VRAM DMA, 8 μs in both speed modes.
+pub const BLOCK_CYCLES: u16 = 32;
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum DmaMode {
     General = 0x00,
@@ -79,6 +88,19 @@ pub struct Dma {
     cycles_dma: u16,
     active_dma: bool,
     active_hdma: bool,
+
+    /// If the block of the HBlank DMA for the current HBlank
+    /// period has already been transferred, cleared once the
+    /// PPU leaves the HBlank mode.
+    hblank_done: bool,
+
+    /// The number of (CPU) cycles the CPU is going to be stalled
+    /// for, as a result of the VRAM DMA block transfers.
+    stall_cycles: u16,
+
+    /// If the DMA transfers stall the CPU (cycle stealing), can
+    /// be disabled for performance or compatibility.
+    cycle_stealing: bool,
 }
 
 impl Dma {
@@ -93,6 +115,9 @@ impl Dma {
             cycles_dma: 0x0,
             active_dma: false,
             active_hdma: false,
+            hblank_done: false,
+            stall_cycles: 0,
+            cycle_stealing: true,
         }
     }
 
@@ -106,6 +131,8 @@ impl Dma {
         self.cycles_dma = 0x0;
         self.active_dma = false;
         self.active_hdma = false;
+        self.hblank_done = false;
+        self.stall_cycles = 0;
     }
 
     pub fn clock(&mut self, _cycles: u16) {}
@@ -145,25 +172,22 @@ impl Dma {
             // 0xFF55 — HDMA5: VRAM DMA length/mode/start (CGB only)
             HDMA5_ADDR => {
                 // in case there's an active HDMA transfer and the
-                // bit 7 is set to 0, the transfer is stopped
+                // bit 7 is set to 0, the transfer is stopped, keeping
+                // the remaining length readable from the register
                 if value & 0x80 == 0x00 && self.active_hdma && self.mode == DmaMode::HBlank {
-                    self.pending = 0;
                     self.active_hdma = false;
                 } else {
                     // ensures destination is set within VRAM range
                     // required for compatibility with some games (know bug)
                     self.destination = 0x8000 | (self.destination & 0x1fff);
                     self.length = (((value & 0x7f) + 0x1) as u16) << 4;
-                    self.mode = ((value & 80) >> 7).into();
+                    self.mode = ((value & 0x80) >> 7).into();
                     self.pending = self.length;
                     self.active_hdma = true;
 
-                    // @TODO: implement HBlank DMA using the proper timing
-                    // and during the HBlank period as described in the
-                    // https://gbdev.io/pandocs/CGB_Registers.html#lcd-vram-dma-transfers
-                    if self.mode == DmaMode::HBlank {
-                        panic_gb!("HBlank DMA not implemented");
-                    }
+                    // the first block of an HBlank DMA is transferred right
+                    // away in case the transfer is started during HBlank
+                    self.hblank_done = false;
                 }
             }
             _ => warnln!("Writing to unknown DMA location 0x{:04x}", addr),
@@ -242,6 +266,53 @@ impl Dma {
         self.active_hdma = value;
     }
 
+    pub fn hblank_done(&self) -> bool {
+        self.hblank_done
+    }
+
+    pub fn set_hblank_done(&mut self, value: bool) {
+        self.hblank_done = value;
+    }
+
+    pub fn stall_cycles(&self) -> u16 {
+        self.stall_cycles
+    }
+
+    pub fn set_stall_cycles(&mut self, value: u16) {
+        self.stall_cycles = value;
+    }
+
+    pub fn cycle_stealing(&self) -> bool {
+        self.cycle_stealing
+    }
+
+    pub fn set_cycle_stealing(&mut self, value: bool) {
+        self.cycle_stealing = value;
+        self.stall_cycles = 0;
+    }
+
+    /// Adds the stall of the transfer of the provided number of
+    /// blocks (of 0x10 bytes), with the stall (in CPU cycles) being
+    /// multiplied by the speed multiplier, as each block takes 8 μs.
+    pub fn stall_blocks(&mut self, blocks: u16, multiplier: u8) {
+        if !self.cycle_stealing {
+            return;
+        }
+        self.stall_cycles = self
+            .stall_cycles
+            .saturating_add(blocks * BLOCK_CYCLES * multiplier as u16);
+    }
+
+    /// Takes (a chunk of) the number of cycles the CPU should be
+    /// stalled for because of DMA transfers, at most `STALL_CHUNK`
+    /// cycles at a time.
+    #[inline(always)]
+    pub fn take_stall_cycles(&mut self) -> u16 {
+        let cycles = self.stall_cycles.min(STALL_CHUNK);
+        self.stall_cycles -= cycles;
+        cycles
+    }
+
     pub fn active(&self) -> bool {
         self.active_dma || self.active_hdma
     }
@@ -269,6 +340,17 @@ impl Dma {
     }
 }
 
+impl Io for Dma {
+    const IO_REGISTERS: &'static [IoRegister] = &[
+        IoRegister::new(DMA_ADDR, "DMA", IoComponent::Dma, 0xff, 0xff),
+        IoRegister::new(HDMA1_ADDR, "HDMA1", IoComponent::Dma, 0x00, 0xff),
+        IoRegister::new(HDMA2_ADDR, "HDMA2", IoComponent::Dma, 0x00, 0xf0),
+        IoRegister::new(HDMA3_ADDR, "HDMA3", IoComponent::Dma, 0x00, 0x1f),
+        IoRegister::new(HDMA4_ADDR, "HDMA4", IoComponent::Dma, 0x00, 0xf0),
+        IoRegister::new(HDMA5_ADDR, "HDMA5", IoComponent::Dma, 0xff, 0xff),
+    ];
+}
+
 impl BusComponent for Dma {
     fn read(&self, addr: u16) -> u8 {
         self.read(addr)
@@ -280,6 +362,14 @@ impl BusComponent for Dma {
 }
 
 impl StateComponent for Dma {
+    const STATE_MIGRATIONS: &'static [StateMigration] = &[
+        // v1 to v2, adds the HBlank done flag and the stall cycles
+        |mut data, _format| {
+            data.extend_from_slice(&[0x00, 0x00, 0x00]);
+            Ok(data)
+        },
+    ];
+
     fn state(&self, _format: Option<StateFormat>) -> Result<Vec<u8>, Error> {
         let mut cursor = Cursor::new(vec![]);
         write_u16(&mut cursor, self.source)?;
@@ -291,6 +381,8 @@ impl StateComponent for Dma {
         write_u16(&mut cursor, self.cycles_dma)?;
         write_u8(&mut cursor, self.active_dma as u8)?;
         write_u8(&mut cursor, self.active_hdma as u8)?;
+        write_u8(&mut cursor, self.hblank_done as u8)?;
+        write_u16(&mut cursor, self.stall_cycles)?;
         Ok(cursor.into_inner())
     }
 
@@ -305,6 +397,8 @@ impl StateComponent for Dma {
         self.cycles_dma = read_u16(&mut cursor)?;
         self.active_dma = read_u8(&mut cursor)? != 0;
         self.active_hdma = read_u8(&mut cursor)? != 0;
+        self.hblank_done = read_u8(&mut cursor)? != 0;
+        self.stall_cycles = read_u16(&mut cursor)?;
         Ok(())
     }
 }
@@ -325,7 +419,7 @@ impl Display for Dma {
 mod tests {
     use super::{Dma, DmaMode};
 
-    use crate::state::StateComponent;
+    use crate::state::{StateComponent, StateFormat};
 
     #[test]
     fn test_dma_default() {
@@ -381,10 +475,13 @@ mod tests {
             cycles_dma: 0x0012,
             active_dma: true,
             active_hdma: true,
+            hblank_done: true,
+            stall_cycles: 96,
+            cycle_stealing: true,
         };
 
         let state = dma.state(None).unwrap();
-        assert_eq!(state.len(), 14);
+        assert_eq!(state.len(), 17);
 
         let mut new_dma = Dma::new();
         new_dma.set_state(&state, None).unwrap();
@@ -398,5 +495,37 @@ mod tests {
         assert_eq!(new_dma.cycles_dma, 0x0012);
         assert!(new_dma.active_dma);
         assert!(new_dma.active_hdma);
+        assert!(new_dma.hblank_done);
+        assert_eq!(new_dma.stall_cycles, 96);
+
+        // states of the first version of the schema (without the
+        // stall state) are migrated into the current version
+        assert_eq!(Dma::state_version(), 2);
+        new_dma
+            .set_state_versioned(&state[..14], 1, StateFormat::Partial)
+            .unwrap();
+        assert_eq!(new_dma.source, 0x1234);
+        assert!(new_dma.active_hdma);
+        assert!(!new_dma.hblank_done);
+        assert_eq!(new_dma.stall_cycles, 0);
+
+        // states of newer (unknown) versions are rejected
+        assert!(new_dma
+            .set_state_versioned(&state, 3, StateFormat::Partial)
+            .is_err());
+    }
+
+    #[test]
+    fn test_stall_cycles() {
+        let mut dma = Dma::new();
+        dma.stall_blocks(4, 2);
+        assert_eq!(dma.take_stall_cycles(), 64);
+        assert_eq!(dma.stall_cycles(), 192);
+        while dma.take_stall_cycles() > 0 {}
+        assert_eq!(dma.stall_cycles(), 0);
+
+        dma.set_cycle_stealing(false);
+        dma.stall_blocks(4, 1);
+        assert_eq!(dma.take_stall_cycles(), 0);
     }
 }
@@ -0,0 +1,371 @@
+//! Sharing of a single cartridge RAM image between instances.
+//!
+//! Useful to run two instances of the same game (eg: for testing
+//! multiplayer adjacent features such as SRAM based trading) as if
+//! both were using the same physical cartridge, see [`DuoSession`].
+
+use std::sync::{
+    mpsc::{channel, Receiver, Sender},
+    Arc, Mutex, MutexGuard,
+};
+
+use boytacean_common::error::Error;
+
+use crate::gb::GameBoy;
+
+/// Notification of a change to the shared RAM image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SharedRamEvent {
+    /// The identifier of the client that committed the change.
+    pub client: usize,
+
+    /// The version of the RAM image after the change.
+    pub version: u64,
+}
+
+/// The outcome of the synchronization of a client.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SharedRamSync {
+    /// If the local changes have been committed to the image.
+    pub pushed: bool,
+
+    /// If the changes of other clients have been applied locally.
+    pub pulled: bool,
+
+    /// If the local changes have been discarded as the image
+    /// is locked by another client.
+    pub rejected: bool,
+}
+
+struct SharedRamState {
+    data: Vec<u8>,
+    version: u64,
+    owner: Option<usize>,
+    clients: usize,
+    listeners: Vec<Sender<SharedRamEvent>>,
+}
+
+/// Cartridge RAM image shared between multiple instances, each of
+/// them accessing it through its own [`SharedRamClient`].
+///
+/// The local RAM of every instance is kept in sync with the image at
+/// explicit synchronization points, with the changes being merged at
+/// the byte level (the latest commit wins for the same byte). A client
+/// may lock the image for exclusive write access, having the changes
+/// of the remaining clients discarded while the lock is held.
+#[derive(Clone)]
+pub struct SharedRam {
+    state: Arc<Mutex<SharedRamState>>,
+}
+
+impl SharedRam {
+    pub fn new(data: &[u8]) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SharedRamState {
+                data: data.to_vec(),
+                version: 0,
+                owner: None,
+                clients: 0,
+                listeners: vec![],
+            })),
+        }
+    }
+
+    /// Creates a new client of the shared image, with its own
+    /// unique identifier.
+    pub fn client(&self) -> SharedRamClient {
+        let mut state = self.lock();
+        let client = SharedRamClient {
+            shared: self.clone(),
+            id: state.clients,
+            base: state.data.clone(),
+            version: state.version,
+        };
+        state.clients += 1;
+        client
+    }
+
+    /// Subscribes to the changes of the image, an event is sent
+    /// for every commit made by any of the clients.
+    pub fn subscribe(&self) -> Receiver<SharedRamEvent> {
+        let (sender, receiver) = channel();
+        self.lock().listeners.push(sender);
+        receiver
+    }
+
+    pub fn data(&self) -> Vec<u8> {
+        self.lock().data.clone()
+    }
+
+    pub fn version(&self) -> u64 {
+        self.lock().version
+    }
+
+    /// The identifier of the client currently holding the
+    /// exclusive write lock of the image, if any.
+    pub fn owner(&self) -> Option<usize> {
+        self.lock().owner
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SharedRamState> {
+        self.state.lock().unwrap()
+    }
+}
+
+/// Access of a single instance to a [`SharedRam`] image.
+pub struct SharedRamClient {
+    shared: SharedRam,
+    id: usize,
+
+    /// The contents of the image (and its version) at the time
+    /// of the last synchronization, used to find the local changes.
+    base: Vec<u8>,
+    version: u64,
+}
+
+impl SharedRamClient {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn shared(&self) -> &SharedRam {
+        &self.shared
+    }
+
+    /// The version of the image at the time of the last sync.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Acquires the exclusive write lock of the image, failing in
+    /// case it's already held by another client.
+    pub fn acquire(&self) -> Result<(), Error> {
+        let mut state = self.shared.lock();
+        match state.owner {
+            Some(owner) if owner != self.id => Err(Error::CustomError(format!(
+                "Shared RAM locked by client {owner}"
+            ))),
+            _ => {
+                state.owner = Some(self.id);
+                Ok(())
+            }
+        }
+    }
+
+    /// Releases the exclusive write lock, in case it's held
+    /// by this client.
+    pub fn release(&self) {
+        let mut state = self.shared.lock();
+        if state.owner == Some(self.id) {
+            state.owner = None;
+        }
+    }
+
+    /// Synchronizes the provided local RAM with the image, committing
+    /// the local changes (only if `dirty`) and then applying the
+    /// changes made by the other clients since the last sync.
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn sync(&mut self, ram: &mut [u8], dirty: bool) -> Result<SharedRamSync, Error> {
+        let mut state = self.shared.lock();
+        if ram.len() != state.data.len() {
+            return Err(Error::CustomError(format!(
+                "Shared RAM size mismatch ({} != {})",
+                ram.len(),
+                state.data.len()
+            )));
+        }
+
+        let mut result = SharedRamSync::default();
+        if dirty && ram != self.base.as_slice() {
+            if state.owner.map_or(true, |owner| owner == self.id) {
+                for (index, value) in ram.iter().enumerate() {
+                    if *value != self.base[index] {
+                        state.data[index] = *value;
+                    }
+                }
+                state.version += 1;
+                let event = SharedRamEvent {
+                    client: self.id,
+                    version: state.version,
+                };
+                state
+                    .listeners
+                    .retain(|listener| listener.send(event).is_ok());
+                result.pushed = true;
+            } else {
+                result.rejected = true;
+            }
+        }
+
+        if ram != state.data.as_slice() {
+            ram.copy_from_slice(&state.data);
+            result.pulled = true;
+        }
+        self.base.copy_from_slice(&state.data);
+        self.version = state.version;
+        Ok(result)
+    }
+
+    /// Synchronizes the cartridge RAM of the provided system with
+    /// the image, clearing its dirty flag.
+    pub fn sync_gb(&mut self, gb: &mut GameBoy) -> Result<SharedRamSync, Error> {
+        let rom = gb.rom();
+        let dirty = rom.ram_dirty();
+        let result = self.sync(rom.ram_data_mut(), dirty)?;
+        rom.set_ram_dirty(false);
+        Ok(result)
+    }
+}
+
+impl Drop for SharedRamClient {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// Two instances of the same game running in the same process
+/// and sharing a single cartridge RAM, synchronized (hard sync)
+/// at the end of every frame.
+pub struct DuoSession {
+    instances: [(GameBoy, SharedRamClient); 2],
+}
+
+impl DuoSession {
+    /// Creates the session from two systems with the same ROM
+    /// loaded, the RAM of the first one is used as the initial
+    /// contents of the shared image.
+    pub fn new(mut first: GameBoy, mut second: GameBoy) -> Result<Self, Error> {
+        if first.rom_i().fingerprint() != second.rom_i().fingerprint() {
+            return Err(Error::CustomError(String::from(
+                "Both instances must have the same ROM loaded",
+            )));
+        }
+        let shared = SharedRam::new(first.rom_i().ram_data());
+        let mut first_client = shared.client();
+        let mut second_client = shared.client();
+        first_client.sync_gb(&mut first)?;
+        second_client.sync_gb(&mut second)?;
+        Ok(Self {
+            instances: [(first, first_client), (second, second_client)],
+        })
+    }
+
+    /// Runs a frame in each of the instances, synchronizing their
+    /// RAM with the shared image after each frame.
+    pub fn next_frame(&mut self) -> Result<[SharedRamSync; 2], Error> {
+        let mut results = [SharedRamSync::default(); 2];
+        for (index, (gb, client)) in self.instances.iter_mut().enumerate() {
+            gb.next_frame();
+            results[index] = client.sync_gb(gb)?;
+        }
+        Ok(results)
+    }
+
+    pub fn gb(&mut self, index: usize) -> &mut GameBoy {
+        &mut self.instances[index].0
+    }
+
+    pub fn gb_i(&self, index: usize) -> &GameBoy {
+        &self.instances[index].0
+    }
+
+    pub fn client(&self, index: usize) -> &SharedRamClient {
+        &self.instances[index].1
+    }
+
+    pub fn shared(&self) -> &SharedRam {
+        self.instances[0].1.shared()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gb::GameBoy;
+
+    use super::{DuoSession, SharedRam, SharedRamEvent};
+
+    #[test]
+    fn test_shared_ram() {
+        let shared = SharedRam::new(&[0x00; 4]);
+        let events = shared.subscribe();
+        let mut a = shared.client();
+        let mut b = shared.client();
+        let mut ram_a = vec![0x00; 4];
+        let mut ram_b = vec![0x00; 4];
+
+        ram_a[0] = 0x11;
+        ram_b[3] = 0x22;
+        let result = a.sync(&mut ram_a, true).unwrap();
+        assert!(result.pushed && !result.pulled);
+        let result = b.sync(&mut ram_b, true).unwrap();
+        assert!(result.pushed && result.pulled);
+        assert_eq!(ram_b, vec![0x11, 0x00, 0x00, 0x22]);
+        assert!(a.sync(&mut ram_a, false).unwrap().pulled);
+        assert_eq!(ram_a, ram_b);
+        assert_eq!(a.version(), 2);
+        assert_eq!(shared.version(), 2);
+        assert_eq!(
+            events.try_iter().collect::<Vec<SharedRamEvent>>(),
+            vec![
+                SharedRamEvent {
+                    client: 0,
+                    version: 1
+                },
+                SharedRamEvent {
+                    client: 1,
+                    version: 2
+                }
+            ]
+        );
+
+        // while locked by the first client the changes of
+        // the second one are discarded
+        a.acquire().unwrap();
+        assert!(b.acquire().is_err());
+        ram_b[1] = 0x33;
+        let result = b.sync(&mut ram_b, true).unwrap();
+        assert!(result.rejected && !result.pushed);
+        assert_eq!(ram_b, vec![0x11, 0x00, 0x00, 0x22]);
+        assert_eq!(shared.owner(), Some(0));
+        drop(a);
+        assert_eq!(shared.owner(), None);
+        b.acquire().unwrap();
+
+        assert!(b.sync(&mut [0x00; 2], true).is_err());
+    }
+
+    #[test]
+    fn test_duo_session() {
+        let mut first = GameBoy::default();
+        first.load(true).unwrap();
+        first
+            .load_rom_file("res/roms/test/firstwhite.gb", None)
+            .unwrap();
+        let mut second = GameBoy::default();
+        second.load(true).unwrap();
+        second
+            .load_rom_file("res/roms/test/firstwhite.gb", None)
+            .unwrap();
+
+        let mut duo = DuoSession::new(first, second).unwrap();
+        duo.next_frame().unwrap();
+        assert_eq!(duo.client(0).id(), 0);
+        assert_eq!(duo.client(1).id(), 1);
+        assert_eq!(
+            duo.gb_i(0).rom_i().ram_data(),
+            duo.gb_i(1).rom_i().ram_data()
+        );
+
+        let mut other = GameBoy::default();
+        other.load(true).unwrap();
+        other
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+        let mut first = GameBoy::default();
+        first.load(true).unwrap();
+        first
+            .load_rom_file("res/roms/test/firstwhite.gb", None)
+            .unwrap();
+        assert!(DuoSession::new(first, other).is_err());
+    }
+}
@@ -0,0 +1,378 @@
+//! Input fuzzing utilities for crash and soft-lock hunting.
+//!
+//! Feeds random (or structured) joypad sequences into the emulator
+//! for a number of frames, capturing any panic together with a
+//! diagnostics bundle so that the issue can be reproduced from the
+//! seed of the fuzzing session.
+
+use std::{
+    any::Any,
+    collections::VecDeque,
+    fmt::{self, Display, Formatter},
+    panic::{catch_unwind, AssertUnwindSafe},
+    thread,
+};
+
+use boytacean_common::{error::Error, util::splitmix64};
+use boytacean_hashing::crc32::crc32;
+
+use crate::{
+    gb::{GameBoy, GameBoyMode},
+    pad::PadKey,
+    rom::Cartridge,
+};
+
+/// The keys that can be pressed by the fuzzer, the index of each
+/// key is the bit used for it in the keys mask.
+pub const FUZZ_KEYS: [PadKey; 8] = [
+    PadKey::Up,
+    PadKey::Down,
+    PadKey::Left,
+    PadKey::Right,
+    PadKey::Start,
+    PadKey::Select,
+    PadKey::A,
+    PadKey::B,
+];
+
+/// The number of frames of input history that are included
+/// in the diagnostics of a failed fuzzing session.
+const INPUT_HISTORY: usize = 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FuzzMode {
+    /// Completely random key masks in every frame, including
+    /// combinations that are impossible on a real pad.
+    Random,
+
+    /// Human like input, with keys held for a number of frames
+    /// and no opposing directions pressed at the same time.
+    Structured,
+}
+
+impl FuzzMode {
+    pub fn description(&self) -> &'static str {
+        match self {
+            FuzzMode::Random => "Random",
+            FuzzMode::Structured => "Structured",
+        }
+    }
+}
+
+impl Display for FuzzMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FuzzOptions {
+    /// The number of frames to run each fuzzing session for.
+    pub frames: u32,
+
+    /// The seed of the pseudo random generator, sessions with the
+    /// same seed (and ROM) run exactly the same input sequence.
+    pub seed: u64,
+
+    pub mode: FuzzMode,
+
+    /// The number of consecutive frames with an unchanged frame
+    /// buffer after which the game is considered soft-locked,
+    /// zero disables the soft-lock detection.
+    pub stall_frames: u32,
+
+    /// The Game Boy mode to be used, if not set the mode is
+    /// inferred from the ROM's header.
+    pub mode_gb: Option<GameBoyMode>,
+}
+
+impl Default for FuzzOptions {
+    fn default() -> Self {
+        Self {
+            frames: 3600,
+            seed: 0,
+            mode: FuzzMode::Structured,
+            stall_frames: 1800,
+            mode_gb: None,
+        }
+    }
+}
+
+/// Result of a fuzzing session.
+#[derive(Clone, Debug, Default)]
+pub struct FuzzReport {
+    pub seed: u64,
+
+    /// The number of frames that have been run.
+    pub frames: u32,
+
+    /// The message of the panic that stopped the session (if any).
+    pub panic: Option<String>,
+
+    /// If the session was stopped because the game is considered
+    /// to be soft-locked (unchanged frame buffer).
+    pub stalled: bool,
+
+    /// The diagnostics bundle of a failed session, including the
+    /// state of the system and the latest inputs.
+    pub diagnostics: Option<String>,
+}
+
+impl FuzzReport {
+    pub fn is_ok(&self) -> bool {
+        self.panic.is_none() && !self.stalled
+    }
+
+    pub fn description(&self) -> String {
+        let status = match (&self.panic, self.stalled) {
+            (Some(panic), _) => format!("panic ({panic})"),
+            (None, true) => String::from("stalled"),
+            (None, false) => String::from("ok"),
+        };
+        format!(
+            "seed={} frames={} status={}",
+            self.seed, self.frames, status
+        )
+    }
+}
+
+impl Display for FuzzReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// Generator of joypad key masks (one per frame) based on
+/// a xorshift pseudo random number generator.
+pub struct InputFuzzer {
+    state: u64,
+    mode: FuzzMode,
+    keys: u8,
+    hold: u32,
+}
+
+impl InputFuzzer {
+    pub fn new(seed: u64, mode: FuzzMode) -> Self {
+        // scrambles the seed (splitmix64) so that similar seeds
+        // generate unrelated sequences, the xorshift state must
+        // never be zero so the lowest bit is always set
+        Self {
+            state: splitmix64(seed) | 0x01,
+            mode,
+            keys: 0x00,
+            hold: 0,
+        }
+    }
+
+    /// Obtains the mask of the keys to be pressed in the next
+    /// frame, each bit maps to the key at the same index in
+    /// [`FUZZ_KEYS`].
+    pub fn next_keys(&mut self) -> u8 {
+        match self.mode {
+            FuzzMode::Random => (self.next() >> 24) as u8,
+            FuzzMode::Structured => {
+                if self.hold > 0 {
+                    self.hold -= 1;
+                    return self.keys;
+                }
+                let value = self.next();
+                self.keys = match value % 20 {
+                    // single direction, possibly with A or B
+                    0..=9 => (1 << ((value >> 8) % 4)) | ((value >> 16) as u8 & 0xc0),
+                    // single action button (A or B)
+                    10..=14 => 1 << (6 + (value >> 8) % 2),
+                    15 | 16 => 1 << 4,
+                    17 => 1 << 5,
+                    _ => 0x00,
+                };
+                self.hold = ((value >> 24) % 20) as u32;
+                self.keys
+            }
+        }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+}
+
+/// Runs a fuzzing session on the provided ROM, returning the report
+/// of the session, panics in the emulator are captured and reported.
+pub fn fuzz_rom(rom: &[u8], options: &FuzzOptions) -> Result<FuzzReport, Error> {
+    let mode = match options.mode_gb {
+        Some(mode) => mode,
+        None => Cartridge::from_data(rom)?.gb_mode(),
+    };
+    let mut gb = GameBoy::new(Some(mode));
+    gb.load(true)?;
+    gb.load_rom(rom, None)?;
+
+    let mut fuzzer = InputFuzzer::new(options.seed, options.mode);
+    let mut report = FuzzReport {
+        seed: options.seed,
+        ..Default::default()
+    };
+    let mut history = VecDeque::with_capacity(INPUT_HISTORY);
+    let mut pressed = 0u8;
+    let mut last_hash = 0u32;
+    let mut stall_count = 0u32;
+
+    for _ in 0..options.frames {
+        let keys = fuzzer.next_keys();
+        if history.len() == INPUT_HISTORY {
+            history.pop_front();
+        }
+        history.push_back(keys);
+
+        // runs the frame (in cycles, so that frames with the LCD
+        // off are also accounted) capturing any panic
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            for (index, key) in FUZZ_KEYS.iter().enumerate() {
+                let mask = 1 << index;
+                if keys & mask != pressed & mask {
+                    if keys & mask == mask {
+                        gb.key_press(*key);
+                    } else {
+                        gb.key_lift(*key);
+                    }
+                }
+            }
+            gb.clock_frame();
+        }));
+        pressed = keys;
+
+        if let Err(payload) = result {
+            report.panic = Some(panic_message(payload));
+            report.diagnostics = Some(diagnostics(&gb, &history));
+            break;
+        }
+        report.frames += 1;
+
+        if options.stall_frames > 0 {
+            let hash = crc32(gb.frame_buffer());
+            stall_count = if hash == last_hash {
+                stall_count + 1
+            } else {
+                0
+            };
+            last_hash = hash;
+            if stall_count >= options.stall_frames {
+                report.stalled = true;
+                report.diagnostics = Some(diagnostics(&gb, &history));
+                break;
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Runs multiple fuzzing sessions (instances) in parallel, each
+/// of them with a different seed (starting at the options' seed).
+pub fn fuzz_rom_many(
+    rom: &[u8],
+    options: &FuzzOptions,
+    instances: u32,
+) -> Vec<Result<FuzzReport, Error>> {
+    thread::scope(|scope| {
+        let handles = (0..instances as u64)
+            .map(|index| {
+                let options = FuzzOptions {
+                    seed: options.seed.wrapping_add(index),
+                    ..options.clone()
+                };
+                scope.spawn(move || fuzz_rom(rom, &options))
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|payload| Err(Error::CustomError(panic_message(payload))))
+            })
+            .collect()
+    })
+}
+
+pub(crate) fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        String::from(*message)
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("Unknown panic")
+    }
+}
+
+/// Builds the diagnostics bundle for the current state of the system,
+/// the system may be in an inconsistent state (after a panic) so any
+/// panic while building the bundle is also captured.
+fn diagnostics(gb: &GameBoy, history: &VecDeque<u8>) -> String {
+    let description =
+        catch_unwind(AssertUnwindSafe(|| gb.description_debug())).unwrap_or_else(|payload| {
+            format!("Failed to obtain diagnostics: {}", panic_message(payload))
+        });
+    let inputs = history
+        .iter()
+        .map(|keys| format!("{keys:02x}"))
+        .collect::<Vec<String>>()
+        .join(" ");
+    format!("{description}\nLast inputs (oldest first): {inputs}")
+}
+
+#[cfg(test)]
+mod tests {
+    use boytacean_common::util::read_file;
+
+    use super::{fuzz_rom, fuzz_rom_many, FuzzMode, FuzzOptions, InputFuzzer};
+
+    #[test]
+    fn test_input_fuzzer() {
+        let mut first = InputFuzzer::new(42, FuzzMode::Structured);
+        let mut second = InputFuzzer::new(42, FuzzMode::Structured);
+        let keys = (0..600).map(|_| first.next_keys()).collect::<Vec<u8>>();
+        assert_eq!(
+            keys,
+            (0..600).map(|_| second.next_keys()).collect::<Vec<u8>>()
+        );
+        assert!(keys
+            .iter()
+            .all(|keys| keys & 0x03 != 0x03 && keys & 0x0c != 0x0c));
+        assert!(keys.iter().any(|keys| *keys != 0x00));
+
+        let mut random = InputFuzzer::new(0, FuzzMode::Random);
+        assert_ne!(random.next_keys(), random.next_keys());
+    }
+
+    #[test]
+    fn test_fuzz_rom() {
+        let rom = read_file("res/roms/demo/pocket.gb").unwrap();
+        let options = FuzzOptions {
+            frames: 120,
+            seed: 7,
+            mode: FuzzMode::Random,
+            ..Default::default()
+        };
+        let report = fuzz_rom(&rom, &options).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.frames, 120);
+        assert!(report.diagnostics.is_none());
+
+        let options = FuzzOptions {
+            stall_frames: 1,
+            ..options
+        };
+        let reports = fuzz_rom_many(&rom, &options, 2);
+        assert_eq!(reports.len(), 2);
+        for (index, report) in reports.iter().enumerate() {
+            let report = report.as_ref().unwrap();
+            assert_eq!(report.seed, 7 + index as u64);
+            assert!(report.stalled);
+            assert!(report.diagnostics.as_ref().unwrap().contains("Last inputs"));
+        }
+    }
+}
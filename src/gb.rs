@@ -19,42 +19,65 @@
 
 use boytacean_common::{
     error::Error,
-    util::{read_file, SharedThread},
+    util::{read_file, splitmix64, SharedThread},
 };
 use std::{
-    collections::VecDeque,
+    collections::{HashSet, VecDeque},
     fmt::{self, Display, Formatter},
     io::Read,
+    ops::Range,
     sync::{Arc, Mutex},
 };
 
 use crate::{
-    apu::Apu,
+    apu::{Apu, AudioStream, HighPassFilter, Resampler},
+    apu_thread::ApuThread,
+    capture::{encode_png, FrameRecorder},
     cheats::{
         genie::{GameGenie, GameGenieCode},
-        shark::{GameShark, GameSharkCode},
+        list::CheatList,
+        search::{SEARCH_MEMORY_SIZE, SEARCH_RANGES},
+        shark::{GameShark, GameSharkCheat},
     },
-    cpu::Cpu,
-    data::{BootRom, CGB_BOOT, CGB_BOYTACEAN, DMG_BOOT, DMG_BOOTIX, MGB_BOOTIX, SGB_BOOT},
+    color::PixelFormat,
+    consts::{BGP_ADDR, IF_ADDR, KEY0_ADDR, TAC_ADDR},
+    cpu::{Cpu, CpuBackend, Tracer},
+    data::{
+        BootRom, CGB_BOOT, CGB_BOYTACEAN, CGB_COMPAT_GRAYSCALE, DMG_BOOT, DMG_BOOTIX, MGB_BOOTIX,
+        SGB_BOOT,
+    },
+    debug::disasm::{disassemble, DisasmInstruction},
     devices::{printer::PrinterDevice, stdout::StdoutDevice},
+    diag::{ProfileSlot, Profiler},
     dma::Dma,
     info::Info,
     mmu::Mmu,
-    pad::{Pad, PadKey},
+    movie::{Movie, MovieMode, MovieSession},
+    pad::{AutoFire, Pad, PadFilter, PadKey},
     ppu::{
-        Ppu, PpuMode, Tile, DISPLAY_HEIGHT, DISPLAY_WIDTH, FRAME_BUFFER_RGB1555_SIZE,
-        FRAME_BUFFER_RGB565_SIZE, FRAME_BUFFER_SIZE, FRAME_BUFFER_XRGB8888_SIZE,
+        ObjectData, Palette, Ppu, PpuMode, RenderDiff, RenderMode, Tile, DISPLAY_HEIGHT,
+        DISPLAY_WIDTH, FRAME_BUFFER_RGB1555_SIZE, FRAME_BUFFER_RGB565_SIZE, FRAME_BUFFER_SIZE,
+        FRAME_BUFFER_XRGB8888_SIZE, HRAM_SIZE, VRAM_SIZE_DMG,
+    },
+    rom::{
+        read_rom_file, BatteryEvent, Cartridge, CgbMode, RamSize, RomLoadMode, RomWarning, SgbMode,
     },
-    rom::{Cartridge, RamSize},
     serial::{NullDevice, Serial, SerialDevice},
+    sgb::{Sgb, FRAME_BUFFER_SGB_SIZE},
+    state::{
+        FromGbOptions, QuickSlots, RewindBuffer, SaveStateFormat, StateFormat, StateManager,
+        ToGbOptions,
+    },
     timer::Timer,
+    video::filters::{FrameFilter, VideoFilter},
+    warnln,
 };
 
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
 #[cfg(feature = "wasm")]
-use crate::{color::Pixel, ppu::Palette};
+use crate::color::Pixel;
 
 #[cfg(feature = "wasm")]
 use std::{
@@ -177,6 +200,18 @@ impl GameBoySpeed {
         }
     }
 
+    /// Converts a number of cycles of the CPU clock domain into
+    /// the equivalent number of cycles of the system clock domain.
+    pub fn system_cycles(&self, cycles: u16) -> u16 {
+        cycles / self.multiplier() as u16
+    }
+
+    /// Converts a number of cycles of the system clock domain into
+    /// the equivalent number of cycles of the CPU clock domain.
+    pub fn cpu_cycles(&self, cycles: u32) -> u32 {
+        cycles * self.multiplier() as u32
+    }
+
     pub fn from_u8(value: u8) -> Self {
         match value {
             0 => GameBoySpeed::Normal,
@@ -192,6 +227,46 @@ impl Display for GameBoySpeed {
     }
 }
 
+/// The clock domains of the system, the components of the CPU
+/// domain run twice as fast in double speed mode (CGB) while the
+/// ones in the system domain always run at normal speed.
+///
+/// Cycle counts exposed by [`GameBoy`] (eg: the value returned by
+/// `clock()`) are T-cycles of the system domain, unless stated
+/// otherwise, so that they map to a fixed amount of time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockDomain {
+    /// CPU, timer, serial and DMA.
+    Cpu,
+
+    /// PPU, APU and cartridge RTC, at [`GameBoy::CPU_FREQ`].
+    System,
+}
+
+impl ClockDomain {
+    pub fn description(&self) -> &'static str {
+        match self {
+            ClockDomain::Cpu => "CPU",
+            ClockDomain::System => "System",
+        }
+    }
+
+    /// Obtains the frequency (in hz) of the clock domain
+    /// for the provided speed.
+    pub fn freq(&self, speed: GameBoySpeed) -> u32 {
+        match self {
+            ClockDomain::Cpu => speed.cpu_cycles(GameBoy::CPU_FREQ),
+            ClockDomain::System => GameBoy::CPU_FREQ,
+        }
+    }
+}
+
+impl Display for ClockDomain {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
 impl From<u8> for GameBoySpeed {
     fn from(value: u8) -> Self {
         Self::from_u8(value)
@@ -274,6 +349,372 @@ impl From<GameBoyDevice> for u8 {
     }
 }
 
+/// Profile that switches a group of emulation behaviours at once,
+/// trading accuracy for performance, so that frontends can expose
+/// a single option instead of the individual toggles.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AccuracyProfile {
+    /// Scanline rendering, no STAT quirks, no DMA cycle stealing,
+    /// no open bus values, nearest audio resampling and batched
+    /// audio generation, for low end devices.
+    Fast,
+
+    /// Scanline rendering with the STAT quirks, DMA cycle stealing
+    /// and linear audio resampling, the default behaviour.
+    #[default]
+    Balanced,
+
+    /// Pixel FIFO rendering and the high-pass filter of the running
+    /// model on top of the balanced behaviours.
+    Accurate,
+}
+
+impl AccuracyProfile {
+    pub fn description(&self) -> &'static str {
+        match self {
+            AccuracyProfile::Fast => "Fast",
+            AccuracyProfile::Balanced => "Balanced",
+            AccuracyProfile::Accurate => "Accurate",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "fast" => Ok(AccuracyProfile::Fast),
+            "balanced" => Ok(AccuracyProfile::Balanced),
+            "accurate" => Ok(AccuracyProfile::Accurate),
+            _ => Err(Error::CustomError(format!(
+                "Invalid accuracy profile: {value}"
+            ))),
+        }
+    }
+
+    pub fn render_mode(&self) -> RenderMode {
+        match self {
+            AccuracyProfile::Fast | AccuracyProfile::Balanced => RenderMode::Scanline,
+            AccuracyProfile::Accurate => RenderMode::Fifo,
+        }
+    }
+
+    pub fn stat_quirks(&self) -> bool {
+        *self != AccuracyProfile::Fast
+    }
+
+    pub fn cycle_stealing(&self) -> bool {
+        *self != AccuracyProfile::Fast
+    }
+
+    pub fn open_bus(&self) -> bool {
+        *self != AccuracyProfile::Fast
+    }
+
+    pub fn resampler(&self) -> Resampler {
+        match self {
+            AccuracyProfile::Fast => Resampler::Nearest,
+            AccuracyProfile::Balanced | AccuracyProfile::Accurate => Resampler::Linear,
+        }
+    }
+
+    pub fn audio_batch(&self) -> bool {
+        *self == AccuracyProfile::Fast
+    }
+
+    /// The high-pass filter of the profile, which for the accurate
+    /// profile depends on the model being emulated.
+    pub fn high_pass(&self, mode: GameBoyMode) -> HighPassFilter {
+        match (self, mode) {
+            (AccuracyProfile::Accurate, GameBoyMode::Cgb) => HighPassFilter::Cgb,
+            (AccuracyProfile::Accurate, _) => HighPassFilter::Dmg,
+            _ => HighPassFilter::Off,
+        }
+    }
+}
+
+impl Display for AccuracyProfile {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// Pattern of the (uninitialized) contents of the RAM at power on,
+/// some titles (eg: copy protection checks) rely on these contents
+/// or seed their random number generators from them.
+///
+/// The random patterns are derived from the seed of the emulator
+/// (zero when not in deterministic mode), keeping the contents the
+/// same across runs.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StartupMemory {
+    /// The pattern observed on hardware for the running model,
+    /// stripes for the DMG (and SGB) and random for the CGB.
+    #[default]
+    Hardware = 0,
+
+    /// Every byte set to 0x00, the behaviour of most emulators.
+    Zeros = 1,
+
+    /// Every byte set to 0xff.
+    Ones = 2,
+
+    /// Alternating runs of 8 bytes of 0x00 and 0xff, with the phase
+    /// flipped every 256 bytes, as found on the DMG WRAM.
+    Stripes = 3,
+
+    /// Random bytes, as found on the CGB WRAM.
+    Random = 4,
+}
+
+impl StartupMemory {
+    pub fn description(&self) -> &'static str {
+        match self {
+            StartupMemory::Hardware => "Hardware",
+            StartupMemory::Zeros => "Zeros",
+            StartupMemory::Ones => "Ones",
+            StartupMemory::Stripes => "Stripes",
+            StartupMemory::Random => "Random",
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Result<Self, Error> {
+        match value {
+            0 => Ok(StartupMemory::Hardware),
+            1 => Ok(StartupMemory::Zeros),
+            2 => Ok(StartupMemory::Ones),
+            3 => Ok(StartupMemory::Stripes),
+            4 => Ok(StartupMemory::Random),
+            _ => Err(Error::DataError(format!(
+                "Invalid startup memory pattern: {value}"
+            ))),
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "hardware" => Ok(StartupMemory::Hardware),
+            "zeros" => Ok(StartupMemory::Zeros),
+            "ones" => Ok(StartupMemory::Ones),
+            "stripes" => Ok(StartupMemory::Stripes),
+            "random" => Ok(StartupMemory::Random),
+            _ => Err(Error::CustomError(format!(
+                "Invalid startup memory pattern: {value}"
+            ))),
+        }
+    }
+
+    /// The concrete pattern used for the provided mode, resolving
+    /// the hardware pattern into the one of the model.
+    pub fn resolve(&self, mode: GameBoyMode) -> Self {
+        match (self, mode) {
+            (StartupMemory::Hardware, GameBoyMode::Cgb) => StartupMemory::Random,
+            (StartupMemory::Hardware, _) => StartupMemory::Stripes,
+            _ => *self,
+        }
+    }
+
+    /// Fills the provided memory with the pattern for the mode, the
+    /// seed is only used by the random pattern.
+    pub fn fill(&self, data: &mut [u8], mode: GameBoyMode, seed: u64) {
+        match self.resolve(mode) {
+            StartupMemory::Zeros | StartupMemory::Hardware => data.fill(0x00),
+            StartupMemory::Ones => data.fill(0xff),
+            StartupMemory::Stripes => {
+                for (index, byte) in data.iter_mut().enumerate() {
+                    *byte = if (index >> 3 ^ index >> 8) & 0x01 == 0 {
+                        0x00
+                    } else {
+                        0xff
+                    };
+                }
+            }
+            StartupMemory::Random => {
+                let mut state = seed;
+                for chunk in data.chunks_mut(8) {
+                    state = splitmix64(state);
+                    chunk.copy_from_slice(&state.to_le_bytes()[..chunk.len()]);
+                }
+            }
+        }
+    }
+}
+
+impl Display for StartupMemory {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// Typed change to the runtime configuration of the emulator, applied
+/// with `GameBoy::update_config()` which propagates it to the components
+/// (see [`ConfigListener`]) so that it takes effect immediately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigChange {
+    Mode(GameBoyMode),
+    PpuEnabled(bool),
+    ApuEnabled(bool),
+    DmaEnabled(bool),
+    TimerEnabled(bool),
+    SerialEnabled(bool),
+    ClockFreq(u32),
+    StatQuirks(bool),
+    LcdOffFrames(bool),
+    AutoColorize(bool),
+    OpenBus(bool),
+    Accuracy(AccuracyProfile),
+    StartupMemory(StartupMemory),
+    Seed(Option<u64>),
+    CpuBackend(CpuBackend),
+}
+
+impl ConfigChange {
+    pub fn description(&self) -> &'static str {
+        match self {
+            ConfigChange::Mode(_) => "Mode",
+            ConfigChange::PpuEnabled(_) => "PPU Enabled",
+            ConfigChange::ApuEnabled(_) => "APU Enabled",
+            ConfigChange::DmaEnabled(_) => "DMA Enabled",
+            ConfigChange::TimerEnabled(_) => "Timer Enabled",
+            ConfigChange::SerialEnabled(_) => "Serial Enabled",
+            ConfigChange::ClockFreq(_) => "Clock Frequency",
+            ConfigChange::StatQuirks(_) => "STAT Quirks",
+            ConfigChange::LcdOffFrames(_) => "LCD Off Frames",
+            ConfigChange::AutoColorize(_) => "Auto Colorize",
+            ConfigChange::OpenBus(_) => "Open Bus",
+            ConfigChange::Accuracy(_) => "Accuracy",
+            ConfigChange::StartupMemory(_) => "Startup Memory",
+            ConfigChange::Seed(_) => "Seed",
+            ConfigChange::CpuBackend(_) => "CPU Backend",
+        }
+    }
+}
+
+impl Display for ConfigChange {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let description = self.description();
+        match self {
+            ConfigChange::Mode(value) => write!(f, "{description}: {value}"),
+            ConfigChange::PpuEnabled(value)
+            | ConfigChange::ApuEnabled(value)
+            | ConfigChange::DmaEnabled(value)
+            | ConfigChange::TimerEnabled(value)
+            | ConfigChange::SerialEnabled(value)
+            | ConfigChange::StatQuirks(value)
+            | ConfigChange::LcdOffFrames(value)
+            | ConfigChange::AutoColorize(value)
+            | ConfigChange::OpenBus(value) => write!(f, "{description}: {value}"),
+            ConfigChange::ClockFreq(value) => write!(f, "{description}: {value} Hz"),
+            ConfigChange::Accuracy(value) => write!(f, "{description}: {value}"),
+            ConfigChange::StartupMemory(value) => write!(f, "{description}: {value}"),
+            ConfigChange::Seed(Some(value)) => write!(f, "{description}: {value}"),
+            ConfigChange::Seed(None) => write!(f, "{description}: None"),
+            ConfigChange::CpuBackend(value) => write!(f, "{description}: {value}"),
+        }
+    }
+}
+
+/// Trait to be implemented by the components that react to
+/// changes of the runtime configuration.
+pub trait ConfigListener {
+    /// Called whenever a change of the configuration is applied,
+    /// even if the value of the configuration is unchanged.
+    fn config_changed(&mut self, change: &ConfigChange);
+}
+
+/// Hardware model (revision) of the Game Boy, used to select the
+/// register state left behind by the boot ROM of that model when
+/// running without a boot ROM.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameBoyModel {
+    Dmg0,
+    Dmg,
+    Mgb,
+    Sgb,
+    Sgb2,
+    Cgb,
+    Agb,
+}
+
+impl GameBoyModel {
+    pub fn description(&self) -> &'static str {
+        match self {
+            GameBoyModel::Dmg0 => "Game Boy (DMG0)",
+            GameBoyModel::Dmg => "Game Boy (DMG)",
+            GameBoyModel::Mgb => "Game Boy Pocket (MGB)",
+            GameBoyModel::Sgb => "Super Game Boy (SGB)",
+            GameBoyModel::Sgb2 => "Super Game Boy 2 (SGB2)",
+            GameBoyModel::Cgb => "Game Boy Color (CGB)",
+            GameBoyModel::Agb => "Game Boy Advance (AGB)",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "dmg0" => Ok(GameBoyModel::Dmg0),
+            "dmg" => Ok(GameBoyModel::Dmg),
+            "mgb" => Ok(GameBoyModel::Mgb),
+            "sgb" => Ok(GameBoyModel::Sgb),
+            "sgb2" => Ok(GameBoyModel::Sgb2),
+            "cgb" => Ok(GameBoyModel::Cgb),
+            "agb" => Ok(GameBoyModel::Agb),
+            _ => Err(Error::CustomError(format!(
+                "Invalid Game Boy model: {value}"
+            ))),
+        }
+    }
+
+    /// The default model for the provided running mode.
+    pub fn from_mode(mode: GameBoyMode) -> Self {
+        match mode {
+            GameBoyMode::Dmg => GameBoyModel::Dmg,
+            GameBoyMode::Cgb => GameBoyModel::Cgb,
+            GameBoyMode::Sgb => GameBoyModel::Sgb,
+        }
+    }
+
+    pub fn is_cgb(&self) -> bool {
+        matches!(self, GameBoyModel::Cgb | GameBoyModel::Agb)
+    }
+
+    /// The AF, BC, DE and HL register values at the end of the boot
+    /// ROM execution of the model, where `dmg_cart` indicates that a
+    /// CGB model is running a DMG only cartridge and `checksum` is the
+    /// header checksum of the cartridge (affects the DMG flags).
+    pub fn boot_registers(&self, dmg_cart: bool, checksum: u8) -> (u16, u16, u16, u16) {
+        let dmg_flags = if checksum == 0x00 { 0x80 } else { 0xb0 };
+        match (self, dmg_cart) {
+            (GameBoyModel::Dmg0, _) => (0x0100, 0xff13, 0x00c1, 0x8403),
+            (GameBoyModel::Dmg, _) => (0x0100 | dmg_flags, 0x0013, 0x00d8, 0x014d),
+            (GameBoyModel::Mgb, _) => (0xff00 | dmg_flags, 0x0013, 0x00d8, 0x014d),
+            (GameBoyModel::Sgb, _) => (0x0100, 0x0014, 0x0000, 0xc060),
+            (GameBoyModel::Sgb2, _) => (0xff00, 0x0014, 0x0000, 0xc060),
+            (GameBoyModel::Cgb, false) => (0x1180, 0x0000, 0xff56, 0x000d),
+            (GameBoyModel::Cgb, true) => (0x1180, 0x0000, 0x0008, 0x007c),
+            (GameBoyModel::Agb, false) => (0x1100, 0x0100, 0xff56, 0x000d),
+            (GameBoyModel::Agb, true) => (0x1100, 0x0100, 0x0008, 0x007c),
+        }
+    }
+
+    /// The DIV register value at the end of the boot ROM execution,
+    /// for the SGB and CGB models the value depends on the duration
+    /// of the boot animation and is not deterministic, in which case
+    /// zero is used.
+    pub fn boot_div(&self) -> u8 {
+        match self {
+            GameBoyModel::Dmg0 => 0x18,
+            GameBoyModel::Dmg | GameBoyModel::Mgb => 0xab,
+            _ => 0x00,
+        }
+    }
+}
+
+impl Display for GameBoyModel {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct GameBoyConfig {
@@ -304,6 +745,35 @@ pub struct GameBoyConfig {
     /// the APU will adjust its internal clock to match
     /// this hint.
     clock_freq: u32,
+
+    /// If the STAT quirks (rising edge triggered STAT interrupt
+    /// and the DMG "STAT write bug") are emulated by the PPU.
+    stat_quirks: bool,
+
+    /// If blank (synthetic) frames are generated by the PPU at the
+    /// normal frame rate while the LCD is off.
+    lcd_off_frames: bool,
+
+    /// If DMG games running in CGB mode are colorized using the
+    /// compatibility palettes of the CGB boot ROM.
+    auto_colorize: bool,
+
+    /// If the reads from unmapped areas return the values
+    /// observed on hardware (open bus).
+    open_bus: bool,
+
+    /// The accuracy profile last applied to the emulator.
+    accuracy: AccuracyProfile,
+
+    /// The pattern of the contents of the RAM at power on.
+    startup_memory: StartupMemory,
+
+    /// The seed from which every source of nondeterminism (eg: the
+    /// RTC time) is derived, `None` for a non deterministic mode.
+    seed: Option<u64>,
+
+    /// The strategy used by the CPU to execute the instructions.
+    cpu_backend: CpuBackend,
 }
 
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
@@ -375,6 +845,104 @@ impl GameBoyConfig {
     pub fn set_clock_freq(&mut self, value: u32) {
         self.clock_freq = value;
     }
+
+    pub fn stat_quirks(&self) -> bool {
+        self.stat_quirks
+    }
+
+    pub fn set_stat_quirks(&mut self, value: bool) {
+        self.stat_quirks = value;
+    }
+
+    pub fn lcd_off_frames(&self) -> bool {
+        self.lcd_off_frames
+    }
+
+    pub fn set_lcd_off_frames(&mut self, value: bool) {
+        self.lcd_off_frames = value;
+    }
+
+    pub fn auto_colorize(&self) -> bool {
+        self.auto_colorize
+    }
+
+    pub fn set_auto_colorize(&mut self, value: bool) {
+        self.auto_colorize = value;
+    }
+
+    pub fn open_bus(&self) -> bool {
+        self.open_bus
+    }
+
+    pub fn set_open_bus(&mut self, value: bool) {
+        self.open_bus = value;
+    }
+
+    pub fn accuracy(&self) -> AccuracyProfile {
+        self.accuracy
+    }
+
+    pub fn set_accuracy(&mut self, value: AccuracyProfile) {
+        self.accuracy = value;
+    }
+
+    pub fn startup_memory(&self) -> StartupMemory {
+        self.startup_memory
+    }
+
+    pub fn set_startup_memory(&mut self, value: StartupMemory) {
+        self.startup_memory = value;
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn set_seed(&mut self, value: u64) {
+        self.seed = Some(value);
+    }
+
+    pub fn clear_seed(&mut self) {
+        self.seed = None;
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        self.seed.is_some()
+    }
+
+    pub fn cpu_backend(&self) -> CpuBackend {
+        self.cpu_backend
+    }
+
+    pub fn set_cpu_backend(&mut self, value: CpuBackend) {
+        self.cpu_backend = value;
+    }
+}
+
+impl GameBoyConfig {
+    /// Applies the change to the configuration, returning if
+    /// any of its values has changed.
+    pub fn apply(&mut self, change: &ConfigChange) -> bool {
+        let previous = *self;
+        match *change {
+            ConfigChange::Mode(value) => self.mode = value,
+            ConfigChange::PpuEnabled(value) => self.ppu_enabled = value,
+            ConfigChange::ApuEnabled(value) => self.apu_enabled = value,
+            ConfigChange::DmaEnabled(value) => self.dma_enabled = value,
+            ConfigChange::TimerEnabled(value) => self.timer_enabled = value,
+            ConfigChange::SerialEnabled(value) => self.serial_enabled = value,
+            ConfigChange::ClockFreq(value) => self.clock_freq = value,
+            ConfigChange::StatQuirks(value) => self.stat_quirks = value,
+            ConfigChange::LcdOffFrames(value) => self.lcd_off_frames = value,
+            ConfigChange::AutoColorize(value) => self.auto_colorize = value,
+            ConfigChange::OpenBus(value) => self.open_bus = value,
+            ConfigChange::Accuracy(value) => self.accuracy = value,
+            ConfigChange::StartupMemory(value) => self.startup_memory = value,
+            ConfigChange::Seed(value) => self.seed = value,
+            ConfigChange::CpuBackend(value) => self.cpu_backend = value,
+        }
+        *self != previous
+    }
 }
 
 impl Default for GameBoyConfig {
@@ -387,6 +955,14 @@ impl Default for GameBoyConfig {
             timer_enabled: true,
             serial_enabled: true,
             clock_freq: GameBoy::CPU_FREQ,
+            stat_quirks: true,
+            lcd_off_frames: false,
+            auto_colorize: true,
+            open_bus: true,
+            accuracy: AccuracyProfile::default(),
+            startup_memory: StartupMemory::default(),
+            seed: None,
+            cpu_backend: CpuBackend::default(),
         }
     }
 }
@@ -426,6 +1002,195 @@ pub trait AudioProvider {
     fn audio_output(&self) -> u8;
     fn audio_buffer(&self) -> &VecDeque<u8>;
     fn clear_audio_buffer(&mut self);
+
+    /// Takes the audio samples of the completed video frame(s),
+    /// returning `None` in case no complete frame is available.
+    ///
+    /// Prefer this method over reading the audio buffer directly
+    /// as it never returns the partial buffer of a frame that is
+    /// still being generated.
+    fn take_audio_frame(&mut self) -> Option<Vec<u8>>;
+
+    /// Allocation free version of [`Self::take_audio_frame`] that
+    /// takes the audio samples into the provided (reusable) buffer,
+    /// returning `false` in case no complete frame is available.
+    fn take_audio_frame_into(&mut self, buffer: &mut Vec<u8>) -> bool;
+}
+
+/// Statistics on the production of audio and video frames by
+/// the emulator and their consumption by the frontend, allowing
+/// the detection of dropped/duplicated frames and audio queue
+/// underruns/overruns (eg: "audio crackles" or "video stutters").
+///
+/// The production counters are updated by the emulator while the
+/// consumption ones are reported by the frontend.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AvStats {
+    /// Number of video frames produced by the PPU.
+    pub video_frames: u64,
+
+    /// Number of video frames presented by the frontend.
+    pub video_presented: u64,
+
+    /// Number of produced video frames that were never presented.
+    pub video_dropped: u64,
+
+    /// Number of times the same video frame was presented again.
+    pub video_duplicated: u64,
+
+    /// Number of audio frames (chunks) produced by the APU.
+    pub audio_frames: u64,
+
+    /// Number of audio frames taken by the frontend.
+    pub audio_taken: u64,
+
+    /// Number of times the audio queue was full and samples
+    /// had to be dropped, either in the emulator or frontend.
+    pub audio_overruns: u64,
+
+    /// Total number of audio samples dropped on overruns.
+    pub audio_dropped_samples: u64,
+
+    /// Number of times the frontend ran out of audio samples.
+    pub audio_underruns: u64,
+
+    /// Number of times the emulation fell too far behind the host
+    /// and had to be re-synchronized (see [`SyncPolicy`]).
+    pub sync_recoveries: u64,
+
+    /// Number of video frames skipped (never emulated) on the
+    /// re-synchronizations.
+    pub sync_skipped_frames: u64,
+
+    /// The index of the last video frame presented.
+    last_presented: Option<u16>,
+}
+
+impl AvStats {
+    /// Obtains the difference between the counters of the current
+    /// and of a previous snapshot of the statistics.
+    ///
+    /// The counters saturate at zero, as the previous snapshot may
+    /// have been taken before the statistics were reset (eg: on a
+    /// reset of the emulator).
+    pub fn delta(&self, previous: &AvStats) -> AvStats {
+        AvStats {
+            video_frames: self.video_frames.saturating_sub(previous.video_frames),
+            video_presented: self
+                .video_presented
+                .saturating_sub(previous.video_presented),
+            video_dropped: self.video_dropped.saturating_sub(previous.video_dropped),
+            video_duplicated: self
+                .video_duplicated
+                .saturating_sub(previous.video_duplicated),
+            audio_frames: self.audio_frames.saturating_sub(previous.audio_frames),
+            audio_taken: self.audio_taken.saturating_sub(previous.audio_taken),
+            audio_overruns: self.audio_overruns.saturating_sub(previous.audio_overruns),
+            audio_dropped_samples: self
+                .audio_dropped_samples
+                .saturating_sub(previous.audio_dropped_samples),
+            audio_underruns: self
+                .audio_underruns
+                .saturating_sub(previous.audio_underruns),
+            sync_recoveries: self
+                .sync_recoveries
+                .saturating_sub(previous.sync_recoveries),
+            sync_skipped_frames: self
+                .sync_skipped_frames
+                .saturating_sub(previous.sync_skipped_frames),
+            last_presented: self.last_presented,
+        }
+    }
+
+    /// Checks if any of the problem counters (drops, duplicates,
+    /// underruns, overruns or re-synchronizations) is non zero.
+    pub fn has_issues(&self) -> bool {
+        self.video_dropped > 0
+            || self.video_duplicated > 0
+            || self.audio_overruns > 0
+            || self.audio_underruns > 0
+            || self.sync_recoveries > 0
+    }
+
+    fn present_frame(&mut self, frame_index: u16) {
+        self.video_presented += 1;
+        if let Some(last_presented) = self.last_presented {
+            match frame_index.wrapping_sub(last_presented) {
+                0 => self.video_duplicated += 1,
+                delta => self.video_dropped += delta as u64 - 1,
+            }
+        }
+        self.last_presented = Some(frame_index);
+    }
+
+    fn audio_overrun(&mut self, samples: usize) {
+        self.audio_overruns += 1;
+        self.audio_dropped_samples += samples as u64;
+    }
+}
+
+impl Display for AvStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "video: {} frames, {} presented, {} dropped, {} duplicated | audio: {} frames, {} taken, {} overruns ({} samples dropped), {} underruns | sync: {} recoveries ({} frames skipped)",
+            self.video_frames,
+            self.video_presented,
+            self.video_dropped,
+            self.video_duplicated,
+            self.audio_frames,
+            self.audio_taken,
+            self.audio_overruns,
+            self.audio_dropped_samples,
+            self.audio_underruns,
+            self.sync_recoveries,
+            self.sync_skipped_frames
+        )
+    }
+}
+
+/// Policy used to recover from audio/video desync after the host
+/// stalls (eg: GC pause, window drag), in which case the emulation
+/// falls behind the host clock.
+///
+/// Instead of emulating all the missed frames (that may spiral and
+/// produce a "fast audio burst"), up to `max_catch_up` frames are
+/// emulated and the remaining ones are skipped, with the audio being
+/// re-synchronized and faded in during `fade_ms` milliseconds.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SyncPolicy {
+    /// The maximum number of frames emulated to catch up with
+    /// the host clock, the remaining frames are skipped.
+    pub max_catch_up: u32,
+
+    /// The duration (in milliseconds) of the fade in applied
+    /// to the audio after a re-synchronization, zero disables it.
+    pub fade_ms: u32,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl SyncPolicy {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(max_catch_up: u32, fade_ms: u32) -> Self {
+        Self {
+            max_catch_up: max_catch_up.max(1),
+            fade_ms,
+        }
+    }
+
+    /// Obtains the number of frames that should be emulated
+    /// when the emulation is `behind` frames late.
+    pub fn catch_up(&self, behind: u32) -> u32 {
+        behind.clamp(1, self.max_catch_up.max(1))
+    }
+}
+
+impl Default for SyncPolicy {
+    fn default() -> Self {
+        Self::new(4, 50)
+    }
 }
 
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
@@ -521,25 +1286,115 @@ pub struct GameBoy {
     /// If performance is required (may value access)
     /// the values should be cloned and stored locally.
     gbc: SharedThread<GameBoyConfig>,
-}
 
-#[cfg_attr(feature = "wasm", wasm_bindgen)]
-impl GameBoy {
-    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
-    pub fn new(mode: Option<GameBoyMode>) -> Self {
-        let mode = mode.unwrap_or(GameBoyMode::Dmg);
-        let gbc = Arc::new(Mutex::new(GameBoyConfig {
-            mode,
-            ppu_enabled: true,
-            apu_enabled: true,
-            dma_enabled: true,
-            timer_enabled: true,
-            serial_enabled: true,
-            clock_freq: GameBoy::CPU_FREQ,
-        }));
+    /// Optional rewind buffer that if set will store a
+    /// (delta compressed) snapshot of the system state at
+    /// the end of every frame.
+    rewind: Option<RewindBuffer>,
 
-        let components = Components {
-            ppu: Ppu::new(mode, gbc.clone()),
+    /// The in-memory quick-save slots, cleared whenever
+    /// a new cartridge is loaded.
+    quick_slots: QuickSlots,
+
+    /// Optional recorder that if set will accumulate the
+    /// frame buffer at the end of every frame, to be later
+    /// encoded as an animated PNG.
+    recorder: Option<FrameRecorder>,
+
+    /// Optional profiler that if set will measure the time
+    /// spent by each of the subsystems in every frame.
+    profiler: Option<Profiler>,
+
+    /// Optional input movie being recorded or played, with the
+    /// pad inputs latched at the frame boundaries.
+    movie: Option<MovieSession>,
+
+    /// The maximum number of (normal speed) cycles run by a single
+    /// `next_frame()` call, preventing it from running forever while
+    /// no frame is produced (eg: LCD off), zero for no limit.
+    watchdog_cycles: u32,
+
+    /// If the watchdog limit has been reached by the last call
+    /// to `next_frame()`, meaning that no frame was produced.
+    watchdog_triggered: bool,
+
+    /// The number of cycles run in excess by the last call to
+    /// `run_for_cycles()`, discounted from the next call.
+    pending_cycles: u32,
+
+    /// The seed of the deterministic mode (if enabled), from which
+    /// every source of nondeterminism is derived.
+    seed: Option<u64>,
+
+    /// Statistics on the production and consumption of the
+    /// audio and video frames.
+    av_stats: AvStats,
+
+    /// The policy used to recover from audio/video desync.
+    sync_policy: SyncPolicy,
+
+    /// The number of audio samples (remaining, total) of the
+    /// fade in applied after a re-synchronization.
+    audio_fade: (u32, u32),
+
+    /// The Super Game Boy component, that handles the SGB
+    /// commands, only set when running in SGB mode.
+    sgb: Option<Sgb>,
+
+    /// Optional thread that synthesizes the audio in parallel,
+    /// replaying the APU log submitted at the end of every frame.
+    apu_thread: Option<ApuThread>,
+
+    /// Optional pull based stream of the audio, resampled to the
+    /// output sampling rate of the frontend (see `audio_fill()`).
+    audio_stream: Option<AudioStream>,
+
+    /// Callback to be called whenever the runtime configuration
+    /// changes, see `update_config()`.
+    config_callback: fn(change: &ConfigChange),
+
+    /// Optional callback to be called with the frame buffer (RGB) of
+    /// each of the frames, as soon as they are produced by the PPU.
+    frame_callback: Option<fn(frame_buffer: &[u8])>,
+
+    /// Optional callback to be called with the (16 bit) samples of
+    /// each of the audio frames, as soon as they are completed.
+    audio_callback: Option<fn(samples: &[i16])>,
+
+    /// Buffers re-used to deliver the audio frames to the audio
+    /// callback, avoiding allocations once they've grown.
+    audio_callback_buffers: (Vec<u8>, Vec<i16>),
+
+    /// Optional filter applied to each of the completed frames
+    /// (eg: LCD ghosting), see `video_frame()`.
+    video_filter: Option<FrameFilter>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl GameBoy {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(mode: Option<GameBoyMode>) -> Self {
+        let mode = mode.unwrap_or(GameBoyMode::Dmg);
+        let gbc = Arc::new(Mutex::new(GameBoyConfig {
+            mode,
+            ppu_enabled: true,
+            apu_enabled: true,
+            dma_enabled: true,
+            timer_enabled: true,
+            serial_enabled: true,
+            clock_freq: GameBoy::CPU_FREQ,
+            stat_quirks: true,
+            lcd_off_frames: false,
+            auto_colorize: true,
+            open_bus: true,
+            accuracy: AccuracyProfile::default(),
+            startup_memory: StartupMemory::default(),
+            seed: None,
+            cpu_backend: CpuBackend::default(),
+        }));
+
+        let components = Components {
+            ppu: Ppu::new(mode, gbc.clone()),
             apu: Apu::default(),
             dma: Dma::default(),
             pad: Pad::default(),
@@ -560,6 +1415,26 @@ impl GameBoy {
             clock_freq: GameBoy::CPU_FREQ,
             cpu,
             gbc,
+            rewind: None,
+            quick_slots: QuickSlots::default(),
+            recorder: None,
+            profiler: None,
+            movie: None,
+            watchdog_cycles: Self::WATCHDOG_CYCLES,
+            watchdog_triggered: false,
+            pending_cycles: 0,
+            seed: None,
+            av_stats: AvStats::default(),
+            sync_policy: SyncPolicy::default(),
+            audio_fade: (0, 0),
+            sgb: None,
+            apu_thread: None,
+            audio_stream: None,
+            config_callback: |_| {},
+            frame_callback: None,
+            audio_callback: None,
+            audio_callback_buffers: (vec![], vec![]),
+            video_filter: None,
         }
     }
 
@@ -575,6 +1450,19 @@ impl GameBoy {
         self.mmu().reset();
         self.cpu.reset();
         self.reset_cheats();
+        if let Some(rewind) = self.rewind.as_mut() {
+            rewind.clear();
+        }
+        self.av_stats = AvStats::default();
+        self.audio_fade = (0, 0);
+        self.pending_cycles = 0;
+        if let Some(sgb) = self.sgb.as_mut() {
+            sgb.reset();
+        }
+        if let Some(video_filter) = self.video_filter.as_mut() {
+            video_filter.reset();
+        }
+        self.pad().set_players(1);
     }
 
     pub fn reload(&mut self) {
@@ -590,16 +1478,19 @@ impl GameBoy {
     /// amount of cycles.
     ///
     /// This method takes into account the current speed of the
-    /// system (single or double) and will execute the required
-    /// amount of cycles in the other components of the system
-    /// accordingly.
+    /// system (single or double), running the components of the
+    /// CPU clock domain (CPU, timer, serial and DMA) at the speed
+    /// of the CPU and the ones of the system clock domain (PPU and
+    /// APU) always at normal speed, see [`ClockDomain`].
     ///
-    /// The amount of cycles executed by the CPU is returned.
+    /// The amount of T-cycles run in the system clock domain is
+    /// returned, meaning that in double speed mode it's half of
+    /// the cycles executed by the CPU.
     pub fn clock(&mut self) -> u16 {
+        self.profile_start();
         let cycles = self.cpu_clock() as u16;
-        let cycles_n = cycles / self.multiplier() as u16;
-        self.clock_devices(cycles, cycles_n);
-        cycles
+        self.profile_mark(ProfileSlot::Cpu);
+        self.clock_devices(cycles)
     }
 
     /// Risky function that will clock the CPU multiple times
@@ -614,12 +1505,12 @@ impl GameBoy {
     /// may arise, so use with caution.
     pub fn clock_many(&mut self, count: usize) -> u16 {
         let mut cycles = 0u16;
+        self.profile_start();
         for _ in 0..count {
             cycles += self.cpu_clock() as u16;
         }
-        let cycles_n = cycles / self.multiplier() as u16;
-        self.clock_devices(cycles, cycles_n);
-        cycles
+        self.profile_mark(ProfileSlot::Cpu);
+        self.clock_devices(cycles)
     }
 
     /// Function equivalent to `clock()` but that allows pre-emptive
@@ -627,13 +1518,13 @@ impl GameBoy {
     /// reaches the provided address, making sure that in such a situation
     /// the devices are not clocked.
     pub fn clock_step(&mut self, addr: u16) -> u16 {
+        self.profile_start();
         let cycles = self.cpu_clock() as u16;
         if self.cpu_i().pc() == addr {
-            return cycles;
+            return self.speed().system_cycles(cycles);
         }
-        let cycles_n = cycles / self.multiplier() as u16;
-        self.clock_devices(cycles, cycles_n);
-        cycles
+        self.profile_mark(ProfileSlot::Cpu);
+        self.clock_devices(cycles)
     }
 
     /// Equivalent to `clock()` but allows the execution of multiple
@@ -657,6 +1548,16 @@ impl GameBoy {
         cycles
     }
 
+    /// Runs the system for the duration of a frame of the LCD
+    /// (`LCD_CYCLES` cycles of the system clock domain), whether or
+    /// not a frame is produced, returning the number of cycles run.
+    ///
+    /// As the cycles are counted in the system clock domain the
+    /// duration is the same in both normal and double speed modes.
+    pub fn clock_frame(&mut self) -> u32 {
+        self.clocks_cycles(GameBoy::LCD_CYCLES as usize) as u32
+    }
+
     /// Clocks the emulator until the limit of cycles that has been
     /// provided and returns the amount of cycles that have been
     /// clocked together with the frame buffer of the PPU.
@@ -688,15 +1589,67 @@ impl GameBoy {
         }
     }
 
+    /// Runs the system for the provided number of cycles (of the
+    /// system clock domain), discounting the cycles run in excess
+    /// by the previous call, so that the average number of cycles
+    /// run matches the requested one.
+    ///
+    /// The frame buffer of the last frame produced (if any) is
+    /// returned together with the number of cycles and frames run.
+    pub fn run_for_cycles(&mut self, cycles: u32) -> ClockFrame {
+        let mut frame_buffer: Option<Vec<u8>> = None;
+        let mut clock_frame = self.run_for_cycles_with(cycles, |gb| {
+            frame_buffer = Some(gb.frame_buffer().to_vec());
+            true
+        });
+        clock_frame.frame_buffer = frame_buffer;
+        clock_frame
+    }
+
+    pub fn pending_cycles(&self) -> u32 {
+        self.pending_cycles
+    }
+
+    pub fn set_pending_cycles(&mut self, value: u32) {
+        self.pending_cycles = value;
+    }
+
+    /// Runs the system until a new frame is produced by the PPU,
+    /// returning the number of cycles run.
+    ///
+    /// In case no frame is produced within the watchdog limit (eg:
+    /// the LCD is off) the execution is stopped, with the condition
+    /// being signaled by `watchdog_triggered()`.
     pub fn next_frame(&mut self) -> u32 {
         let mut cycles = 0u32;
         let current_frame = self.ppu_frame();
+        let limit = self.watchdog_cycles;
+        self.watchdog_triggered = false;
         while self.ppu_frame() == current_frame {
             cycles += self.clock() as u32;
+            if limit > 0 && cycles >= limit {
+                self.watchdog_triggered = true;
+                break;
+            }
         }
         cycles
     }
 
+    pub fn watchdog_cycles(&self) -> u32 {
+        self.watchdog_cycles
+    }
+
+    /// Sets the maximum number of (system domain) cycles run by
+    /// `next_frame()` without a frame being produced, zero to
+    /// disable the watchdog.
+    pub fn set_watchdog_cycles(&mut self, value: u32) {
+        self.watchdog_cycles = value;
+    }
+
+    pub fn watchdog_triggered(&self) -> bool {
+        self.watchdog_triggered
+    }
+
     pub fn step_to(&mut self, addr: u16) -> u32 {
         let mut cycles = 0u32;
         while self.cpu_i().pc() != addr {
@@ -705,13 +1658,69 @@ impl GameBoy {
         cycles
     }
 
+    /// Clocks the devices by the provided number of cycles of the
+    /// CPU clock domain, returning the equivalent number of cycles
+    /// of the system clock domain (the ones run by the PPU and APU).
     #[inline(always)]
-    fn clock_devices(&mut self, cycles: u16, cycles_n: u16) {
+    fn clock_devices(&mut self, cycles: u16) -> u16 {
+        let cycles_n = self.speed().system_cycles(cycles);
+        let frame_index = self.ppu_i().frame_index();
         if self.ppu_enabled {
             self.ppu_clock(cycles_n);
         }
+        let frame_end = self.ppu_i().frame_index() != frame_index;
+        self.profile_mark(ProfileSlot::Ppu);
         if self.apu_enabled {
             self.apu_clock(cycles_n);
+
+            // swaps the audio frame buffers whenever a new video frame
+            // has been completed (keeping audio and video aligned) or
+            // when the equivalent time of a frame has passed without
+            // any video frame being generated (eg: LCD is off)
+            if frame_end || self.apu_i().audio_frame_cycles() >= GameBoy::LCD_CYCLES {
+                let mut dropped = self.apu().swap_audio_frame();
+                if let Some(apu_thread) = self.apu_thread.as_mut() {
+                    apu_thread.submit(self.cpu.apu().log_mut().unwrap());
+                    dropped += apu_thread.take_dropped();
+                }
+                self.av_stats.audio_frames += 1;
+                if dropped > 0 {
+                    self.av_stats.audio_overrun(dropped);
+                }
+                if self.audio_callback.is_some() {
+                    self.deliver_audio();
+                }
+            }
+        }
+        self.profile_mark(ProfileSlot::Apu);
+        if frame_end {
+            self.av_stats.video_frames += 1;
+            if let Some(video_filter) = self.video_filter.as_mut() {
+                video_filter.process(self.cpu.ppu().frame_buffer());
+            }
+            if let Some(frame_callback) = self.frame_callback {
+                frame_callback(self.frame_buffer());
+            }
+        }
+        if self.sgb.is_some() {
+            self.sgb_clock();
+        }
+        if self.seed.is_some() {
+            self.mmu().rom().clock_time(cycles_n);
+        }
+        if frame_end && self.rewind.is_some() {
+            self.snapshot_rewind();
+        }
+        if frame_end && self.recorder.is_some() {
+            self.record_frame();
+        }
+        if let Some(movie) = self.movie.as_mut() {
+            if let Some(keys) = movie.clock(cycles_n as u32, frame_end) {
+                self.pad().set_keys(keys);
+            }
+        }
+        if frame_end && self.pad_i().auto_fire_active() {
+            self.pad().clock_auto_fire();
         }
         if self.dma_enabled {
             self.dma_clock(cycles);
@@ -722,16 +1731,80 @@ impl GameBoy {
         if self.serial_enabled {
             self.serial_clock(cycles);
         }
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.mark(ProfileSlot::Other);
+            if frame_end {
+                profiler.end_frame();
+            }
+        }
+        cycles_n
     }
 
     pub fn key_press(&mut self, key: PadKey) {
+        if let Some(movie) = self.active_movie() {
+            movie.set_keys(movie.keys() | key.mask());
+            return;
+        }
         self.pad().key_press(key);
     }
 
     pub fn key_lift(&mut self, key: PadKey) {
+        if let Some(movie) = self.active_movie() {
+            movie.set_keys(movie.keys() & !key.mask());
+            return;
+        }
         self.pad().key_lift(key);
     }
 
+    /// Presses the key in the joypad of the provided player (0 to 3),
+    /// players 2 to 4 are only read by SGB games that request
+    /// multiple joypads (using `MLT_REQ`).
+    pub fn key_press_player(&mut self, player: usize, key: PadKey) {
+        self.pad().key_press_player(player, key);
+    }
+
+    pub fn key_lift_player(&mut self, player: usize, key: PadKey) {
+        self.pad().key_lift_player(player, key);
+    }
+
+    pub fn player_keys(&self, player: usize) -> u8 {
+        self.pad_i().player_keys(player)
+    }
+
+    pub fn set_player_keys(&mut self, player: usize, keys: u8) {
+        self.pad().set_player_keys(player, keys);
+    }
+
+    /// The number of joypads currently multiplexed by the SGB,
+    /// being 1 for any other (non SGB) system.
+    pub fn players(&self) -> u8 {
+        self.pad_i().players()
+    }
+
+    /// Obtains the mask of the keys currently pressed, with
+    /// one bit per key in the order of [`crate::pad::PAD_KEYS`].
+    pub fn pad_keys(&self) -> u8 {
+        self.pad_i().keys()
+    }
+
+    pub fn set_pad_keys(&mut self, keys: u8) {
+        if let Some(movie) = self.active_movie() {
+            movie.set_keys(keys);
+            return;
+        }
+        self.pad().set_keys(keys);
+    }
+
+    pub fn pad_filter(&self) -> PadFilter {
+        self.pad_i().filter()
+    }
+
+    /// Sets how impossible inputs (Left + Right or Up + Down)
+    /// are handled by the pad.
+    pub fn set_pad_filter(&mut self, value: PadFilter) {
+        self.pad().set_filter(value);
+    }
+
     pub fn cpu_clock(&mut self) -> u8 {
         self.cpu.clock()
     }
@@ -788,9 +1861,58 @@ impl GameBoy {
     /// Should allow the machine to jump to the cartridge (ROM) execution
     /// directly, skipping the boot sequence.
     ///
-    /// Currently supports only DMG machines.
+    /// Uses the default model for the current running mode, see
+    /// [`GameBoy::load_boot_state_for`] for a specific model.
     pub fn load_boot_state(&mut self) {
+        self.load_boot_state_for(GameBoyModel::from_mode(self.mode));
+    }
+
+    /// Loads the machine directly to the post boot state of the
+    /// provided model, initializing the CPU registers, the DIV
+    /// counter and the IO registers to the values left behind by
+    /// the boot ROM of that model.
+    pub fn load_boot_state_for(&mut self, model: GameBoyModel) {
+        let (dmg_cart, checksum) = {
+            let rom = self.rom_i();
+            if rom.rom_data().len() > 0x014d {
+                (rom.gb_mode() == GameBoyMode::Dmg, rom.checksum())
+            } else {
+                (true, 0x00)
+            }
+        };
+        let (af, bc, de, hl) = model.boot_registers(model.is_cgb() && dmg_cart, checksum);
+
         self.cpu.boot();
+        self.cpu.set_af(af);
+        self.cpu.set_bc(bc);
+        self.cpu.set_de(de);
+        self.cpu.set_hl(hl);
+
+        self.timer().set_div(model.boot_div());
+        self.timer().set_div_clock(0);
+
+        // a DMG cartridge on a CGB is run in the compatibility mode,
+        // with the palettes that the CGB boot ROM would have selected
+        if self.mode == GameBoyMode::Cgb && model.is_cgb() && dmg_cart {
+            let palettes = if self.auto_colorize() {
+                self.rom_i().cgb_compat_palettes()
+            } else {
+                [CGB_COMPAT_GRAYSCALE; 3]
+            };
+            self.mmu().write(KEY0_ADDR, 0x04);
+            self.ppu().set_compat_palettes(&palettes);
+        }
+
+        let mmu = self.mmu();
+        mmu.write(TAC_ADDR, 0xf8);
+        mmu.write(IF_ADDR, 0xe1);
+        mmu.write(BGP_ADDR, 0xfc);
+    }
+
+    /// Disassembles `count` instructions starting at the provided
+    /// address, reading the memory without side effects.
+    pub fn disassemble(&self, addr: u16, count: usize) -> Vec<DisasmInstruction> {
+        disassemble(|addr| self.mmu_i().peek(addr), addr, count)
     }
 
     pub fn vram_eager(&mut self) -> Vec<u8> {
@@ -809,6 +1931,84 @@ impl GameBoy {
         self.frame_buffer_raw().to_vec()
     }
 
+    pub fn frame_buffer_format_eager(&mut self, format: PixelFormat) -> Vec<u8> {
+        self.frame_buffer_format(format).to_vec()
+    }
+
+    /// Obtains the CGB color palettes as a flat RGB888 buffer with
+    /// the 8 background palettes followed by the 8 object palettes.
+    pub fn palettes_color_rgb_eager(&self) -> Vec<u8> {
+        self.palettes_color_rgb()
+            .iter()
+            .flatten()
+            .flatten()
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    /// Renders all the tiles in VRAM as an RGB888 buffer for debug
+    /// views, see [`Ppu::render_tiles_buffer`] for the layout.
+    pub fn render_tiles_buffer_eager(&self) -> Vec<u8> {
+        self.ppu_i().render_tiles_buffer()
+    }
+
+    /// Renders the complete 256x256 background map with the provided
+    /// index (0 or 1) as an RGB888 buffer for debug views.
+    pub fn render_background_map_eager(&self, map_index: u8) -> Vec<u8> {
+        self.ppu_i().render_background_map(map_index)
+    }
+
+    /// Renders the frame as it looks up to the provided scanline as
+    /// an RGB888 buffer, see [`Ppu::render_up_to_line`].
+    pub fn render_up_to_line_eager(&self, ly: u8) -> Vec<u8> {
+        self.ppu_i().render_up_to_line(ly)
+    }
+
+    /// Obtains the complete 256x224 SGB screen as an RGB888 buffer,
+    /// empty in case the emulator is not running in SGB mode.
+    pub fn frame_buffer_sgb_eager(&mut self) -> Vec<u8> {
+        self.frame_buffer_sgb()
+            .map(|buffer| buffer.to_vec())
+            .unwrap_or_default()
+    }
+
+    /// Captures the current frame buffer as a PNG image.
+    pub fn screenshot_png(&mut self) -> Vec<u8> {
+        encode_png(
+            self.frame_buffer(),
+            DISPLAY_WIDTH as u32,
+            DISPLAY_HEIGHT as u32,
+        )
+    }
+
+    /// Starts recording the frame buffer at the end of every
+    /// frame, discarding any previous (not stopped) recording.
+    pub fn start_recording(&mut self) {
+        self.recorder = Some(FrameRecorder::new(
+            DISPLAY_WIDTH as u32,
+            DISPLAY_HEIGHT as u32,
+            60,
+        ));
+    }
+
+    /// Stops the current recording, returning it encoded as an
+    /// animated PNG (APNG), empty if no frame was recorded.
+    pub fn stop_recording(&mut self) -> Vec<u8> {
+        self.recorder
+            .take()
+            .map(|recorder| recorder.encode_apng())
+            .unwrap_or_default()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    pub fn object_table_eager(&self) -> Vec<ObjectData> {
+        self.ppu_i().object_table()
+    }
+
     pub fn audio_buffer_eager(&mut self, clear: bool) -> Vec<u8> {
         let buffer = Vec::from(self.audio_buffer().clone());
         if clear {
@@ -817,6 +2017,130 @@ impl GameBoy {
         buffer
     }
 
+    /// Obtains the audio buffer as interleaved stereo (left then
+    /// right) `f32` samples, regardless of the number of channels.
+    pub fn audio_buffer_stereo(&self) -> Vec<f32> {
+        self.apu_i().audio_buffer_stereo()
+    }
+
+    /// Takes the audio samples of the completed video frame(s),
+    /// returning an empty buffer in case none is available.
+    pub fn audio_frame_eager(&mut self) -> Vec<u8> {
+        AudioProvider::take_audio_frame(self).unwrap_or_default()
+    }
+
+    /// Starts the pull based audio stream (see `audio_fill()`) at the
+    /// output sampling rate, with the provided target latency (in
+    /// milliseconds) of buffered audio.
+    pub fn start_audio_stream(&mut self, output_rate: u32, latency_ms: u32) {
+        self.audio_stream = Some(AudioStream::new(
+            self.audio_sampling_rate() as u32,
+            output_rate,
+            latency_ms,
+        ));
+    }
+
+    pub fn stop_audio_stream(&mut self) {
+        self.audio_stream = None;
+    }
+
+    /// Fills the buffer with `frames` interleaved stereo sample frames
+    /// of the audio stream (started at the sampling rate of the APU if
+    /// needed), resampled from the completed audio frames, returns the
+    /// number of them with audio, the remaining are an underrun fade out.
+    ///
+    /// Meant to be called from the audio callback of the frontend (eg:
+    /// an AudioWorklet) for each block of audio to be played.
+    pub fn audio_fill(&mut self, buffer: &mut [f32], frames: usize) -> usize {
+        let sampling_rate = self.audio_sampling_rate() as u32;
+        let mut stream = self.audio_stream.take().unwrap_or_else(|| {
+            AudioStream::new(sampling_rate, sampling_rate, Self::AUDIO_STREAM_LATENCY)
+        });
+        stream.set_input_rate(sampling_rate);
+        if let Some(samples) = AudioProvider::take_audio_frame(self) {
+            let dropped = stream.push(&samples, self.audio_channels());
+            if dropped > 0 {
+                self.av_stats.audio_overrun(dropped);
+            }
+        }
+        let filled = stream.fill(buffer, frames);
+        if filled < frames.min(buffer.len() / 2) {
+            self.av_stats.audio_underruns += 1;
+        }
+        self.audio_stream = Some(stream);
+        filled
+    }
+
+    pub fn av_stats(&self) -> AvStats {
+        self.av_stats
+    }
+
+    pub fn reset_av_stats(&mut self) {
+        self.av_stats = AvStats::default();
+    }
+
+    /// Reports the presentation of the video frame with the provided
+    /// index by the frontend, used to detect dropped/duplicated frames.
+    pub fn present_frame(&mut self, frame_index: u16) {
+        self.av_stats.present_frame(frame_index);
+    }
+
+    /// Reports that the frontend audio queue ran out of samples.
+    pub fn report_audio_underrun(&mut self) {
+        self.av_stats.audio_underruns += 1;
+    }
+
+    /// Reports that the frontend audio queue was full and that the
+    /// provided number of samples had to be dropped.
+    pub fn report_audio_overrun(&mut self, samples: usize) {
+        self.av_stats.audio_overrun(samples);
+    }
+
+    pub fn sync_policy(&self) -> SyncPolicy {
+        self.sync_policy
+    }
+
+    pub fn set_sync_policy(&mut self, policy: SyncPolicy) {
+        self.sync_policy = policy;
+    }
+
+    /// Applies the sync policy when the emulation is `behind` frames
+    /// late (1 meaning on time), returning the number of frames that
+    /// should be emulated.
+    ///
+    /// In case frames have to be skipped the pending audio samples are
+    /// discarded and the following ones faded in, so that the frontend
+    /// does not play a burst of (late) audio.
+    pub fn recover_sync(&mut self, behind: u32) -> u32 {
+        let frames = self.sync_policy.catch_up(behind);
+        if frames < behind {
+            self.av_stats.sync_recoveries += 1;
+            self.av_stats.sync_skipped_frames += (behind - frames) as u64;
+            self.clear_audio_buffer();
+            self.apu().clear_audio_frame();
+            let fade = self.audio_sampling_rate() as u32
+                * self.audio_channels() as u32
+                * self.sync_policy.fade_ms
+                / 1000;
+            self.audio_fade = (fade, fade);
+        }
+        frames
+    }
+
+    /// Cycle based variant of [`Self::recover_sync`], for frontends
+    /// that clock the system by cycles, returns the number of cycles
+    /// that should be emulated out of the provided (late) ones.
+    pub fn recover_sync_cycles(&mut self, cycles: u64) -> u64 {
+        let frame_cycles = GameBoy::LCD_CYCLES as u64;
+        let behind = cycles.div_ceil(frame_cycles).min(u32::MAX as u64) as u32;
+        let frames = self.recover_sync(behind);
+        cycles.min(frames as u64 * frame_cycles)
+    }
+
+    pub fn audio_frame_index(&self) -> u16 {
+        self.apu_i().audio_frame_index()
+    }
+
     pub fn audio_output(&self) -> u8 {
         self.apu_i().output()
     }
@@ -883,6 +2207,48 @@ impl GameBoy {
         self.apu_i().sampling_rate()
     }
 
+    /// Changes the audio sampling rate (eg: 44.1kHz or 48kHz), the
+    /// APU output is resampled to it using the current resampler.
+    pub fn set_audio_sampling_rate(&mut self, value: u16) {
+        self.apu().set_sampling_rate(value);
+    }
+
+    pub fn audio_master_volume(&self) -> f32 {
+        self.apu_i().master_volume()
+    }
+
+    pub fn set_audio_master_volume(&mut self, value: f32) {
+        self.apu().set_master_volume(value);
+    }
+
+    pub fn audio_high_pass(&self) -> HighPassFilter {
+        self.apu_i().high_pass()
+    }
+
+    pub fn set_audio_high_pass(&mut self, value: HighPassFilter) {
+        self.apu().set_high_pass(value);
+    }
+
+    pub fn audio_resampler(&self) -> Resampler {
+        self.apu_i().resampler()
+    }
+
+    pub fn set_audio_resampler(&mut self, value: Resampler) {
+        self.apu().set_resampler(value);
+    }
+
+    pub fn audio_batch(&self) -> bool {
+        self.apu_i().batch()
+    }
+
+    /// Enables the generation of the audio samples in batches of
+    /// cycles (instead of at every clock), reducing the CPU usage
+    /// of the APU (eg: at high fast-forward speeds), the pending
+    /// cycles are processed before any APU register is accessed.
+    pub fn set_audio_batch(&mut self, value: bool) {
+        self.apu().set_batch(value);
+    }
+
     pub fn audio_channels(&self) -> u8 {
         self.apu_i().channels()
     }
@@ -899,6 +2265,27 @@ impl GameBoy {
         self.mmu().rom().set_ram_data(&ram_data)
     }
 
+    pub fn save_data_eager(&mut self) -> Vec<u8> {
+        self.mmu().rom().save_data()
+    }
+
+    pub fn set_save_data(&mut self, save_data: Vec<u8>) {
+        self.mmu().rom().set_save_data(&save_data)
+    }
+
+    /// Obtains the contents of the battery backed RAM (SRAM) of the
+    /// cartridge, including the RTC footer for MBC3 cartridges, as
+    /// the bytes of a `.sav` file.
+    pub fn sram_bytes(&mut self) -> Vec<u8> {
+        self.mmu().rom().save_data()
+    }
+
+    /// Restores the battery backed RAM (SRAM) of the cartridge from
+    /// the bytes of a `.sav` file.
+    pub fn set_sram_bytes(&mut self, data: &[u8]) {
+        self.mmu().rom().set_save_data(data)
+    }
+
     pub fn registers(&mut self) -> Registers {
         let ppu_registers = self.ppu().registers();
         Registers {
@@ -960,10 +2347,7 @@ impl GameBoy {
     }
 
     pub fn set_mode(&mut self, value: GameBoyMode) {
-        self.mode = value;
-        (*self.gbc).lock().unwrap().set_mode(value);
-        self.mmu().set_mode(value);
-        self.ppu().set_gb_mode(value);
+        self.update_config(ConfigChange::Mode(value));
     }
 
     pub fn ppu_enabled(&self) -> bool {
@@ -971,8 +2355,119 @@ impl GameBoy {
     }
 
     pub fn set_ppu_enabled(&mut self, value: bool) {
-        self.ppu_enabled = value;
-        (*self.gbc).lock().unwrap().set_ppu_enabled(value);
+        self.update_config(ConfigChange::PpuEnabled(value));
+    }
+
+    pub fn stat_quirks(&self) -> bool {
+        self.ppu_i().stat_quirks()
+    }
+
+    pub fn accuracy(&self) -> AccuracyProfile {
+        (*self.gbc).lock().unwrap().accuracy()
+    }
+
+    /// Applies the accuracy profile, switching the PPU render mode,
+    /// the STAT quirks, the DMA cycle stealing and the audio resampler
+    /// and high-pass filter in a single call.
+    pub fn set_accuracy(&mut self, value: AccuracyProfile) {
+        self.update_config(ConfigChange::Accuracy(value));
+    }
+
+    /// Enables or disables the emulation of the STAT quirks (rising
+    /// edge triggered STAT interrupt and DMG "STAT write bug"), an
+    /// accuracy toggle that may be disabled for compatibility.
+    pub fn set_stat_quirks(&mut self, value: bool) {
+        self.update_config(ConfigChange::StatQuirks(value));
+    }
+
+    pub fn lcd_off_frames(&self) -> bool {
+        self.ppu_i().lcd_off_frames()
+    }
+
+    /// Enables or disables the generation of blank (synthetic) frames
+    /// at the normal frame rate while the LCD is off, so that frame
+    /// driven loops (eg: `next_frame()`) keep running at ~60 fps with
+    /// the audio in sync.
+    pub fn set_lcd_off_frames(&mut self, value: bool) {
+        self.update_config(ConfigChange::LcdOffFrames(value));
+    }
+
+    /// If the last frame produced was a synthetic (blank) one,
+    /// generated while the LCD is off.
+    pub fn frame_synthetic(&self) -> bool {
+        self.ppu_i().frame_synthetic()
+    }
+
+    pub fn auto_colorize(&self) -> bool {
+        self.mmu_i().auto_colorize()
+    }
+
+    /// Enables or disables the colorization of DMG games running in
+    /// CGB mode using the compatibility palettes of the CGB boot ROM
+    /// (selected from the title checksum), using grayscale otherwise.
+    pub fn set_auto_colorize(&mut self, value: bool) {
+        self.update_config(ConfigChange::AutoColorize(value));
+    }
+
+    pub fn open_bus(&self) -> bool {
+        self.mmu_i().open_bus()
+    }
+
+    /// Enables or disables the hardware (open bus) values for the
+    /// reads from unmapped areas, eg: the not usable area at
+    /// 0xFEA0-0xFEFF and the external RAM of cartridges without RAM.
+    pub fn set_open_bus(&mut self, value: bool) {
+        self.update_config(ConfigChange::OpenBus(value));
+    }
+
+    pub fn startup_memory(&self) -> StartupMemory {
+        (*self.gbc).lock().unwrap().startup_memory()
+    }
+
+    /// Sets the pattern of the contents of the RAM at power on, only
+    /// applied on the next load of the system (eg: reload).
+    pub fn set_startup_memory(&mut self, value: StartupMemory) {
+        self.update_config(ConfigChange::StartupMemory(value));
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Enables the deterministic mode, in which every source of
+    /// nondeterminism is derived from the provided seed instead of
+    /// the host (eg: the RTC runs on emulated time, starting at a
+    /// date derived from the seed), keeping replays and netplay
+    /// sessions in sync across platforms.
+    pub fn set_seed(&mut self, value: u64) {
+        self.update_config(ConfigChange::Seed(Some(value)));
+    }
+
+    /// Disables the deterministic mode, going back to the host
+    /// as the source of the RTC time.
+    pub fn clear_seed(&mut self) {
+        self.update_config(ConfigChange::Seed(None));
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        self.seed.is_some()
+    }
+
+    pub fn cpu_backend(&self) -> CpuBackend {
+        self.cpu_i().backend()
+    }
+
+    /// Sets the strategy used by the CPU to execute the instructions,
+    /// the cached backend dispatches pre-decoded blocks of code, with
+    /// the same behaviour of the (default) interpreter.
+    pub fn set_cpu_backend(&mut self, value: CpuBackend) {
+        self.update_config(ConfigChange::CpuBackend(value));
+    }
+
+    /// The UNIX time at which the deterministic clock starts for
+    /// the provided seed, a date between 2000 and 2020.
+    pub fn seed_epoch(seed: u64) -> u64 {
+        Self::SEED_EPOCH + splitmix64(seed) % Self::SEED_EPOCH_SPAN
     }
 
     pub fn apu_enabled(&self) -> bool {
@@ -980,8 +2475,7 @@ impl GameBoy {
     }
 
     pub fn set_apu_enabled(&mut self, value: bool) {
-        self.apu_enabled = value;
-        (*self.gbc).lock().unwrap().set_apu_enabled(value);
+        self.update_config(ConfigChange::ApuEnabled(value));
     }
 
     pub fn dma_enabled(&self) -> bool {
@@ -989,8 +2483,7 @@ impl GameBoy {
     }
 
     pub fn set_dma_enabled(&mut self, value: bool) {
-        self.dma_enabled = value;
-        (*self.gbc).lock().unwrap().set_dma_enabled(value);
+        self.update_config(ConfigChange::DmaEnabled(value));
     }
 
     pub fn timer_enabled(&self) -> bool {
@@ -998,8 +2491,7 @@ impl GameBoy {
     }
 
     pub fn set_timer_enabled(&mut self, value: bool) {
-        self.timer_enabled = value;
-        (*self.gbc).lock().unwrap().set_timer_enabled(value);
+        self.update_config(ConfigChange::TimerEnabled(value));
     }
 
     pub fn serial_enabled(&self) -> bool {
@@ -1007,8 +2499,7 @@ impl GameBoy {
     }
 
     pub fn set_serial_enabled(&mut self, value: bool) {
-        self.serial_enabled = value;
-        (*self.gbc).lock().unwrap().set_serial_enabled(value);
+        self.update_config(ConfigChange::SerialEnabled(value));
     }
 
     pub fn set_all_enabled(&mut self, value: bool) {
@@ -1024,9 +2515,7 @@ impl GameBoy {
     }
 
     pub fn set_clock_freq(&mut self, value: u32) {
-        self.clock_freq = value;
-        (*self.gbc).lock().unwrap().set_clock_freq(value);
-        self.apu().set_clock_freq(value);
+        self.update_config(ConfigChange::ClockFreq(value));
     }
 
     pub fn clock_freq_s(&self) -> String {
@@ -1110,29 +2599,75 @@ impl GameBoy {
 
     pub fn description_debug(&self) -> String {
         format!(
-            "{}\nCPU:\n{}\nDMA:\n{}",
+            "{}\nCPU:\n{}\nDMA:\n{}\nI/O:\n{}",
             self.description(12),
             self.cpu_i().description_default(),
-            self.dma_i().description()
+            self.dma_i().description(),
+            self.description_io()
         )
     }
+
+    /// Obtains a textual dump of the I/O register map with the
+    /// current value of each of the registers.
+    pub fn description_io(&self) -> String {
+        self.mmu_i()
+            .io_map()
+            .iter()
+            .map(|(register, value)| format!("{register} = 0x{value:02x}"))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Takes a snapshot of the memory covered by the RAM search
+    /// (WRAM and HRAM), to be provided to a [`crate::cheats::search::CheatSearch`] scan.
+    pub fn search_memory(&mut self) -> Vec<u8> {
+        let mut memory = Vec::with_capacity(SEARCH_MEMORY_SIZE);
+        for (start, length) in SEARCH_RANGES {
+            for addr in start..start + length {
+                memory.push(self.read_memory(addr));
+            }
+        }
+        memory
+    }
 }
 
 /// Gameboy implementations that are meant with performance
 /// in mind and that do not support WASM interface of copy.
 impl GameBoy {
-    /// The logic frequency of the Game Boy
-    /// CPU in hz.
+    /// The logic frequency of the Game Boy CPU (in normal
+    /// speed) in hz, the frequency of the system clock domain.
     pub const CPU_FREQ: u32 = 4194304;
 
     /// The visual frequency (refresh rate)
     /// of the Game Boy, close to 60 hz.
     pub const VISUAL_FREQ: f32 = 59.7275;
 
-    /// The cycles taken to run a complete frame
-    /// loop in the Game Boy's PPU (in CPU cycles).
+    /// The default target latency (in milliseconds) of the
+    /// audio buffered by the pull based audio stream.
+    pub const AUDIO_STREAM_LATENCY: u32 = 50;
+
+    /// The exact refresh rate of the LCD (in hz), with a frame
+    /// taking `LCD_CYCLES` cycles of the CPU (~59.7275 hz), see
+    /// [`crate::pacing::FramePacer`] to present it on other rates.
+    pub const REFRESH_RATE: f64 = Self::CPU_FREQ as f64 / Self::LCD_CYCLES as f64;
+
+    /// The cycles taken to run a complete frame loop in the
+    /// Game Boy's PPU (in cycles of the system clock domain).
     pub const LCD_CYCLES: u32 = 70224;
 
+    /// The default watchdog limit of `next_frame()`, the equivalent
+    /// time of a second (60 frames), long enough for the periods in
+    /// which the LCD is turned off by the boot ROM and most games.
+    pub const WATCHDOG_CYCLES: u32 = Self::LCD_CYCLES * 60;
+
+    /// The UNIX time (2000-01-01) from which the start of the
+    /// deterministic clock is derived.
+    pub const SEED_EPOCH: u64 = 946684800;
+
+    /// The range (in seconds, 20 years) of the start of the
+    /// deterministic clock after the seed epoch.
+    pub const SEED_EPOCH_SPAN: u64 = 631152000;
+
     pub fn cpu(&mut self) -> &mut Cpu {
         &mut self.cpu
     }
@@ -1165,22 +2700,78 @@ impl GameBoy {
         self.cpu.apu_i()
     }
 
-    pub fn dma(&mut self) -> &mut Dma {
-        self.cpu.dma()
-    }
-
-    pub fn dma_i(&self) -> &Dma {
-        self.cpu.dma_i()
+    pub fn parallel_audio(&self) -> bool {
+        self.apu_thread.is_some()
     }
 
-    pub fn pad(&mut self) -> &mut Pad {
-        self.cpu.pad()
+    /// Enables (or disables) the synthesis of the audio in its own
+    /// thread, removing it from the emulation (CPU) thread, with the
+    /// samples of each frame being available once the APU thread has
+    /// replayed it (usually by the end of the following frame).
+    ///
+    /// While enabled the audio is only available through the audio
+    /// frames (see [`AudioProvider::take_audio_frame_into`]) and the
+    /// channel outputs of the APU are no longer updated.
+    ///
+    /// Fails in case the thread can't be spawned (eg: no threads
+    /// available in the platform), keeping the inline synthesis.
+    pub fn set_parallel_audio(&mut self, value: bool) -> Result<(), Error> {
+        if value == self.apu_thread.is_some() {
+            return Ok(());
+        }
+        if value {
+            let apu = self.apu().start_log();
+            match ApuThread::new(apu) {
+                Ok(apu_thread) => self.apu_thread = Some(apu_thread),
+                Err(err) => {
+                    self.apu().stop_log();
+                    return Err(err);
+                }
+            }
+        } else if let Some(mut apu_thread) = self.apu_thread.take() {
+            if let Some(mut log) = self.apu().stop_log() {
+                apu_thread.submit(&mut log);
+            }
+            *self.apu() = apu_thread.stop();
+        }
+        Ok(())
+    }
+
+    /// Waits for the APU thread to replay all the frames emulated so
+    /// far, so that their audio frames can be taken, no-op in case
+    /// the audio is not being synthesized in parallel.
+    pub fn flush_parallel_audio(&mut self) {
+        if let Some(apu_thread) = self.apu_thread.as_mut() {
+            apu_thread.flush();
+        }
+    }
+
+    pub fn dma(&mut self) -> &mut Dma {
+        self.cpu.dma()
+    }
+
+    pub fn dma_i(&self) -> &Dma {
+        self.cpu.dma_i()
+    }
+
+    pub fn pad(&mut self) -> &mut Pad {
+        self.cpu.pad()
     }
 
     pub fn pad_i(&self) -> &Pad {
         self.cpu.pad_i()
     }
 
+    pub fn auto_fire(&self) -> &AutoFire {
+        self.pad_i().auto_fire()
+    }
+
+    /// Sets the auto-fire patterns followed by the keys of the
+    /// pad while held, advanced once per video frame.
+    pub fn set_auto_fire(&mut self, value: AutoFire) {
+        self.pad().set_auto_fire(value);
+    }
+
     pub fn timer(&mut self) -> &mut Timer {
         self.cpu.timer()
     }
@@ -1205,6 +2796,22 @@ impl GameBoy {
         self.mmu_i().rom_i()
     }
 
+    /// Obtains the compatibility issues found for the loaded
+    /// cartridge, including the ones of running it in the
+    /// current mode, empty if no issue was found.
+    pub fn rom_warnings(&self) -> Vec<RomWarning> {
+        let rom = self.rom_i();
+        let mut warnings = rom.diagnostics();
+        let mode = self.mode();
+        if rom.cgb_flag() == CgbMode::CgbOnly && mode != GameBoyMode::Cgb {
+            warnings.push(RomWarning::CgbOnly(mode));
+        }
+        if rom.sgb_flag() == SgbMode::SgbFunctions && mode != GameBoyMode::Sgb {
+            warnings.push(RomWarning::SgbUnsupported(mode));
+        }
+        warnings
+    }
+
     pub fn frame_buffer(&mut self) -> &[u8; FRAME_BUFFER_SIZE] {
         self.ppu().frame_buffer()
     }
@@ -1233,6 +2840,28 @@ impl GameBoy {
         self.ppu().frame_buffer_rgb565_u16()
     }
 
+    /// Obtains the frame buffer converted into the provided pixel
+    /// format, see [`Ppu::frame_buffer_format`] for the caching.
+    pub fn frame_buffer_format(&mut self, format: PixelFormat) -> &[u8] {
+        self.ppu().frame_buffer_format(format)
+    }
+
+    pub fn frame_buffer_into(&mut self, format: PixelFormat, buffer: &mut [u8]) {
+        self.ppu().frame_buffer_into(format, buffer)
+    }
+
+    pub fn frame_buffer_xrgb8888_into(&mut self, buffer: &mut [u8]) {
+        self.ppu().frame_buffer_xrgb8888_into(buffer)
+    }
+
+    pub fn frame_buffer_rgb1555_into(&mut self, buffer: &mut [u8]) {
+        self.ppu().frame_buffer_rgb1555_into(buffer)
+    }
+
+    pub fn frame_buffer_rgb565_into(&mut self, buffer: &mut [u8]) {
+        self.ppu().frame_buffer_rgb565_into(buffer)
+    }
+
     pub fn frame_buffer_raw(&mut self) -> [u8; FRAME_BUFFER_SIZE] {
         self.ppu().frame_buffer_raw()
     }
@@ -1254,11 +2883,33 @@ impl GameBoy {
         match self.mode() {
             GameBoyMode::Dmg => self.load_dmg(boot, boot_rom)?,
             GameBoyMode::Cgb => self.load_cgb(boot, boot_rom)?,
-            GameBoyMode::Sgb => unimplemented!("SGB is not supported"),
+            GameBoyMode::Sgb => self.load_sgb(boot, boot_rom)?,
         }
+        self.fill_startup_memory(boot);
+        let sgb = self.is_sgb();
+        self.sgb = if sgb { Some(Sgb::new()) } else { None };
+        self.pad().set_sgb(sgb);
         Ok(())
     }
 
+    /// Fills the WRAM and HRAM with the startup memory pattern, as
+    /// well as the first VRAM bank when booting (as the boot ROM is
+    /// going to clear it), each with its own random seed.
+    fn fill_startup_memory(&mut self, boot: bool) {
+        let pattern = self.startup_memory();
+        let mode = self.mode();
+        let seed = self.seed.unwrap_or(0);
+        pattern.fill(self.mmu().ram(), mode, seed);
+        let mut hram = [0u8; HRAM_SIZE];
+        pattern.fill(&mut hram, mode, splitmix64(seed ^ 0xff80));
+        self.ppu().set_hram(&hram);
+        if boot {
+            let mut vram = [0u8; VRAM_SIZE_DMG];
+            pattern.fill(&mut vram, mode, splitmix64(seed ^ 0x8000));
+            self.ppu().set_vram(&vram);
+        }
+    }
+
     pub fn load_dmg(&mut self, boot: bool, boot_rom: Option<BootRom>) -> Result<(), Error> {
         self.mmu().allocate_dmg();
         if boot {
@@ -1267,6 +2918,14 @@ impl GameBoy {
         Ok(())
     }
 
+    pub fn load_sgb(&mut self, boot: bool, boot_rom: Option<BootRom>) -> Result<(), Error> {
+        self.mmu().allocate_dmg();
+        if boot {
+            self.load_boot_sgb(boot_rom)?;
+        }
+        Ok(())
+    }
+
     pub fn load_cgb(&mut self, boot: bool, boot_rom: Option<BootRom>) -> Result<(), Error> {
         self.mmu().allocate_cgb();
         if boot {
@@ -1321,7 +2980,7 @@ impl GameBoy {
         match self.mode() {
             GameBoyMode::Dmg => self.load_boot_dmg(boot_rom)?,
             GameBoyMode::Cgb => self.load_boot_cgb(boot_rom)?,
-            GameBoyMode::Sgb => unimplemented!("SGB is not supported"),
+            GameBoyMode::Sgb => self.load_boot_sgb(boot_rom)?,
         }
         Ok(())
     }
@@ -1344,6 +3003,15 @@ impl GameBoy {
         Ok(())
     }
 
+    pub fn load_boot_sgb(&mut self, boot_rom: Option<BootRom>) -> Result<(), Error> {
+        let boot_rom = boot_rom.unwrap_or(BootRom::Sgb);
+        if !boot_rom.is_dmg_compat() {
+            return Err(Error::IncompatibleBootRom);
+        }
+        self.load_boot_static(boot_rom);
+        Ok(())
+    }
+
     pub fn load_boot_default_f(&mut self, boot_rom: Option<BootRom>) -> Result<(), Error> {
         self.load_boot_dmg_f(boot_rom)?;
         Ok(())
@@ -1353,7 +3021,7 @@ impl GameBoy {
         match self.mode() {
             GameBoyMode::Dmg => self.load_boot_dmg_f(boot_rom)?,
             GameBoyMode::Cgb => self.load_boot_cgb_f(boot_rom)?,
-            GameBoyMode::Sgb => unimplemented!("SGB is not supported"),
+            GameBoyMode::Sgb => self.load_boot_sgb_f(boot_rom)?,
         }
         Ok(())
     }
@@ -1376,8 +3044,21 @@ impl GameBoy {
         Ok(())
     }
 
-    pub fn load_cartridge(&mut self, rom: Cartridge) -> Result<&mut Cartridge, Error> {
+    pub fn load_boot_sgb_f(&mut self, boot_rom: Option<BootRom>) -> Result<(), Error> {
+        let boot_rom = boot_rom.unwrap_or(BootRom::Sgb);
+        if !boot_rom.is_dmg_compat() {
+            return Err(Error::IncompatibleBootRom);
+        }
+        self.load_boot_file(boot_rom)?;
+        Ok(())
+    }
+
+    pub fn load_cartridge(&mut self, mut rom: Cartridge) -> Result<&mut Cartridge, Error> {
+        if let Some(seed) = self.seed {
+            rom.set_clock(Some(Self::seed_epoch(seed)));
+        }
         self.mmu().set_rom(rom);
+        self.quick_slots.clear();
         Ok(self.mmu().rom())
     }
 
@@ -1388,7 +3069,7 @@ impl GameBoy {
     ) -> Result<&mut Cartridge, Error> {
         let mut rom = Cartridge::from_data(data)?;
         if let Some(ram_data) = ram_data {
-            rom.set_ram_data(ram_data)
+            rom.set_save_data(ram_data)
         }
         self.load_cartridge(rom)
     }
@@ -1398,7 +3079,7 @@ impl GameBoy {
         path: &str,
         ram_path: Option<&str>,
     ) -> Result<&mut Cartridge, Error> {
-        let data = read_file(path)?;
+        let data = read_rom_file(path)?;
         match ram_path {
             Some(ram_path) => {
                 let ram_data = read_file(ram_path)?;
@@ -1408,6 +3089,22 @@ impl GameBoy {
         }
     }
 
+    /// Loads the ROM file in the provided path using the provided load
+    /// mode, the streamed mode reads the ROM banks on demand, keeping
+    /// the memory usage low for very large ROMs.
+    pub fn load_rom_file_mode(
+        &mut self,
+        path: &str,
+        ram_path: Option<&str>,
+        mode: RomLoadMode,
+    ) -> Result<&mut Cartridge, Error> {
+        let mut rom = Cartridge::open(path, mode)?;
+        if let Some(ram_path) = ram_path {
+            rom.set_save_data(&read_file(ram_path)?);
+        }
+        self.load_cartridge(rom)
+    }
+
     pub fn load_rom_reader<R: Read>(
         &mut self,
         reader: &mut R,
@@ -1442,10 +3139,583 @@ impl GameBoy {
         self.mmu().write(addr, value);
     }
 
+    /// Reads `length` bytes from the memory bus starting at `addr`,
+    /// wrapping around at the end of the address space.
+    pub fn read_memory_range(&mut self, addr: u16, length: usize) -> Vec<u8> {
+        (0..length)
+            .map(|offset| self.mmu().read(addr.wrapping_add(offset as u16)))
+            .collect()
+    }
+
+    /// Writes the provided bytes into the memory bus starting at
+    /// `addr`, wrapping around at the end of the address space.
+    pub fn write_memory_range(&mut self, addr: u16, data: &[u8]) {
+        for (offset, value) in data.iter().enumerate() {
+            self.mmu().write(addr.wrapping_add(offset as u16), *value);
+        }
+    }
+
+    /// Runs the system until a new frame is produced by the PPU (as
+    /// in `next_frame()`), returning the frame buffer of that frame.
+    pub fn run_frame(&mut self) -> &[u8; FRAME_BUFFER_SIZE] {
+        self.next_frame();
+        self.frame_buffer()
+    }
+
+    /// Equivalent to `run_for_cycles()` but calling `on_frame` as
+    /// soon as each frame is produced by the PPU (while the frame
+    /// buffer holds it), the run stopping right away in case it
+    /// returns `false`, with no cycles carried to the next call.
+    pub fn run_for_cycles_with(
+        &mut self,
+        cycles: u32,
+        mut on_frame: impl FnMut(&mut GameBoy) -> bool,
+    ) -> ClockFrame {
+        let mut counter = std::mem::take(&mut self.pending_cycles);
+        let mut run = 0_u64;
+        let mut frames = 0_u16;
+        let mut last_frame = self.ppu_frame();
+        while counter < cycles {
+            let clock_cycles = self.clock() as u32;
+            counter += clock_cycles;
+            run += clock_cycles as u64;
+            if self.ppu_frame() != last_frame {
+                last_frame = self.ppu_frame();
+                frames += 1;
+                if !on_frame(self) {
+                    counter = cycles;
+                    break;
+                }
+            }
+        }
+        self.pending_cycles = counter - cycles;
+        ClockFrame {
+            cycles: run,
+            frames,
+            frame_buffer: None,
+        }
+    }
+
+    /// Runs the system until the next frame (as in `next_frame()`)
+    /// or until the PC reaches any of the provided breakpoints (before
+    /// executing it), returning the cycles run and the breakpoint
+    /// reached, `None` meaning that the frame has been completed.
+    ///
+    /// When `resume` is set the breakpoint at the current PC is not
+    /// checked, so that the execution continues from a reached one
+    /// without completing the frame in between.
+    pub fn next_frame_breakpoints(
+        &mut self,
+        breakpoints: &HashSet<u16>,
+        resume: bool,
+    ) -> (u32, Option<u16>) {
+        let mut cycles = 0u32;
+        let mut check = !resume;
+        let current_frame = self.ppu_frame();
+        let limit = self.watchdog_cycles;
+        self.watchdog_triggered = false;
+        while self.ppu_frame() == current_frame {
+            let pc = self.cpu_i().pc();
+            if check && breakpoints.contains(&pc) {
+                return (cycles, Some(pc));
+            }
+            check = true;
+            cycles += self.clock() as u32;
+            if limit > 0 && cycles >= limit {
+                self.watchdog_triggered = true;
+                break;
+            }
+        }
+        (cycles, None)
+    }
+
     pub fn set_speed_callback(&mut self, callback: fn(speed: GameBoySpeed)) {
         self.mmu().set_speed_callback(callback);
     }
 
+    /// Obtains a snapshot of the current runtime configuration.
+    pub fn config(&self) -> GameBoyConfig {
+        *(*self.gbc).lock().unwrap()
+    }
+
+    /// Applies a change to the runtime configuration, propagating it
+    /// to the components so that it takes effect immediately (without
+    /// the need to recreate the emulator). Returns if the configuration
+    /// has changed, in which case the config callback is called.
+    pub fn update_config(&mut self, change: ConfigChange) -> bool {
+        let changed = (*self.gbc).lock().unwrap().apply(&change);
+        match change {
+            ConfigChange::Mode(value) => self.mode = value,
+            ConfigChange::PpuEnabled(value) => self.ppu_enabled = value,
+            ConfigChange::ApuEnabled(value) => self.apu_enabled = value,
+            ConfigChange::DmaEnabled(value) => self.dma_enabled = value,
+            ConfigChange::TimerEnabled(value) => self.timer_enabled = value,
+            ConfigChange::SerialEnabled(value) => self.serial_enabled = value,
+            ConfigChange::ClockFreq(value) => self.clock_freq = value,
+            ConfigChange::Accuracy(value) => {
+                self.set_render_mode(value.render_mode());
+                self.set_stat_quirks(value.stat_quirks());
+                self.dma().set_cycle_stealing(value.cycle_stealing());
+                self.set_open_bus(value.open_bus());
+                self.set_audio_resampler(value.resampler());
+                self.set_audio_batch(value.audio_batch());
+                self.set_audio_high_pass(value.high_pass(self.mode));
+            }
+            ConfigChange::Seed(value) => {
+                self.seed = value;
+                self.mmu().rom().set_clock(value.map(Self::seed_epoch));
+            }
+            ConfigChange::CpuBackend(value) => self.cpu.set_backend(value),
+            _ => {}
+        }
+        self.mmu().config_changed(&change);
+        if changed {
+            (self.config_callback)(&change);
+        }
+        changed
+    }
+
+    /// Sets the callback to be called whenever the runtime
+    /// configuration changes, eg: to persist the settings.
+    pub fn set_config_callback(&mut self, callback: fn(change: &ConfigChange)) {
+        self.config_callback = callback;
+    }
+
+    /// Sets the callback to be called with the frame buffer (RGB) of
+    /// each of the frames as soon as they're produced by the PPU, an
+    /// alternative to the polling of `ppu_frame()`.
+    pub fn set_frame_callback(&mut self, callback: fn(frame_buffer: &[u8])) {
+        self.frame_callback = Some(callback);
+    }
+
+    pub fn clear_frame_callback(&mut self) {
+        self.frame_callback = None;
+    }
+
+    /// Sets the filter applied to each of the completed frames
+    /// (eg: LCD ghosting, dot-matrix overlay), with the filtered
+    /// frames available through `video_frame()`, while the frame
+    /// buffer of the PPU (see `frame_buffer()`) is kept untouched.
+    pub fn set_video_filter(&mut self, filter: VideoFilter) {
+        if filter.is_identity() {
+            self.video_filter = None;
+            return;
+        }
+        let mut video_filter = FrameFilter::new(filter);
+        video_filter.process(self.frame_buffer());
+        self.video_filter = Some(video_filter);
+    }
+
+    pub fn video_filter(&self) -> VideoFilter {
+        self.video_filter
+            .as_ref()
+            .map(|video_filter| *video_filter.filter())
+            .unwrap_or_default()
+    }
+
+    /// Obtains the last completed frame (RGB) with the video filter
+    /// applied, the same as `frame_buffer()` when there's no filter.
+    pub fn video_frame(&mut self) -> &[u8] {
+        match self.video_filter {
+            Some(ref video_filter) => video_filter.output(),
+            None => self.frame_buffer(),
+        }
+    }
+
+    /// The size (width and height) of the frames returned by
+    /// `video_frame()`, that depends on the video filter.
+    pub fn video_size(&self) -> (usize, usize) {
+        self.video_filter().output_size()
+    }
+
+    /// Sets the callback to be called with the (16 bit) samples of
+    /// each of the audio frames as soon as they're completed, the
+    /// samples are consumed so they are no longer available through
+    /// the [`AudioProvider`] methods (eg: `take_audio_frame()`).
+    pub fn set_audio_callback(&mut self, callback: fn(samples: &[i16])) {
+        self.audio_callback = Some(callback);
+    }
+
+    pub fn clear_audio_callback(&mut self) {
+        self.audio_callback = None;
+    }
+
+    /// Delivers the completed audio frame (if any) to the audio
+    /// callback, converting its samples into 16 bit ones.
+    fn deliver_audio(&mut self) {
+        let Some(audio_callback) = self.audio_callback else {
+            return;
+        };
+        let (mut frame, mut samples) = std::mem::take(&mut self.audio_callback_buffers);
+        if AudioProvider::take_audio_frame_into(self, &mut frame) {
+            samples.clear();
+            samples.extend(
+                frame
+                    .iter()
+                    .map(|sample| (*sample as i16).saturating_mul(256)),
+            );
+            audio_callback(&samples);
+        }
+        self.audio_callback_buffers = (frame, samples);
+    }
+
+    /// Sets the callback to be called whenever the battery backed
+    /// RAM is changed (after being saved) or saved, useful to drive
+    /// a saving indicator in the frontend.
+    pub fn set_battery_callback(&mut self, callback: fn(event: BatteryEvent, frame: u16)) {
+        self.mmu().set_battery_callback(callback);
+    }
+
+    /// If the battery backed RAM has changes that have not been
+    /// saved (flushed) yet.
+    pub fn battery_dirty(&self) -> bool {
+        self.mmu_i().rom_i().battery_dirty()
+    }
+
+    /// The number of frames since the battery backed RAM has been
+    /// first changed after being saved, `None` in case there are no
+    /// unsaved changes, allowing the flushing to be scheduled.
+    pub fn battery_dirty_frames(&self) -> Option<u16> {
+        self.mmu_i()
+            .rom_i()
+            .battery_frame()
+            .map(|frame| self.ppu_i().frame_index().wrapping_sub(frame))
+    }
+
+    /// Marks the battery backed RAM as saved, should be called
+    /// once its contents have been flushed (eg: to a file).
+    pub fn mark_battery_saved(&mut self) {
+        self.mmu().mark_battery_saved();
+    }
+
+    pub fn tracing(&self) -> bool {
+        self.cpu_i().tracing()
+    }
+
+    /// Sets (or unsets) the CPU instruction tracer, returning the
+    /// previous one (if any) after flushing it.
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) -> Option<Tracer> {
+        self.cpu().set_tracer(tracer)
+    }
+
+    /// Sets (or unsets) the callback called after each CPU instruction
+    /// with its PC, opcode and cycles, for co-simulation purposes.
+    #[cfg(feature = "cosim")]
+    pub fn set_step_callback(&mut self, callback: Option<fn(pc: u16, opcode: u16, cycles: u8)>) {
+        self.cpu().set_step_callback(callback);
+    }
+
+    pub fn sgb(&mut self) -> Option<&mut Sgb> {
+        self.sgb.as_mut()
+    }
+
+    pub fn sgb_i(&self) -> Option<&Sgb> {
+        self.sgb.as_ref()
+    }
+
+    /// Renders the complete 256x224 SGB screen, with the Game Boy
+    /// screen colorized by the SGB palettes and surrounded by the
+    /// SGB border, as an RGB888 buffer (SGB only).
+    pub fn frame_buffer_sgb(&mut self) -> Option<&[u8; FRAME_BUFFER_SGB_SIZE]> {
+        let shade_buffer = &self.cpu.mmu_i().ppu_i().shade_buffer;
+        self.sgb.as_mut().map(|sgb| sgb.frame_buffer(shade_buffer))
+    }
+
+    /// Processes the SGB command packets that have been
+    /// received through the pad.
+    fn sgb_clock(&mut self) {
+        while let Some(packet) = self.cpu.mmu().pad().sgb_packet() {
+            if let Some(sgb) = self.sgb.as_mut() {
+                sgb.process(&packet, self.cpu.mmu_i().ppu_i());
+
+                // propagates the number of joypads requested by the
+                // game (using `MLT_REQ`) to the pad multiplexing
+                let players = sgb.players();
+                if players != self.cpu.mmu_i().pad_i().players() {
+                    self.cpu.mmu().pad().set_players(players);
+                }
+            }
+        }
+    }
+
+    /// Obtains the 8 background and 8 object CGB color palettes
+    /// as RGB888 colors (CGB only).
+    pub fn palettes_color_rgb(&self) -> [[Palette; 8]; 2] {
+        self.ppu_i().palettes_color_rgb()
+    }
+
+    pub fn set_palette_callback(&mut self, callback: fn(obj: bool, index: u8, palette: &Palette)) {
+        self.ppu().set_palette_callback(callback);
+    }
+
+    pub fn compare_render(&self) -> bool {
+        self.ppu_i().compare_render()
+    }
+
+    /// Enables the render comparison (pixel-perfect) mode, in which
+    /// the output of the fast renderer is verified line by line against
+    /// the reference renderer, with differences logged per frame.
+    pub fn set_compare_render(&mut self, value: bool) {
+        self.ppu().set_compare_render(value);
+    }
+
+    pub fn render_diff(&self) -> RenderDiff {
+        *self.ppu_i().render_diff()
+    }
+
+    pub fn dirty_tracking(&self) -> bool {
+        self.ppu_i().dirty_tracking()
+    }
+
+    /// Enables the tracking of the lines of the screen that changed
+    /// between frames, so that only the changed part of the screen
+    /// is updated by the frontend (eg: texture uploads).
+    pub fn set_dirty_tracking(&mut self, value: bool) {
+        self.ppu().set_dirty_tracking(value);
+    }
+
+    pub fn dirty_lines(&self) -> &[bool; DISPLAY_HEIGHT] {
+        self.ppu_i().dirty_lines()
+    }
+
+    /// Obtains the range of lines that changed since the last
+    /// call to `clear_dirty_lines()`, see [`Ppu::dirty_range`].
+    pub fn dirty_range(&self) -> Option<Range<usize>> {
+        self.ppu_i().dirty_range()
+    }
+
+    pub fn clear_dirty_lines(&mut self) {
+        self.ppu().clear_dirty_lines();
+    }
+
+    pub fn render_mode(&self) -> RenderMode {
+        self.ppu_i().render_mode()
+    }
+
+    /// Sets the strategy used by the PPU to render the screen, the
+    /// pixel FIFO mode is slower but accurate to the dot, handling
+    /// mid-line register changes (eg: status bars).
+    pub fn set_render_mode(&mut self, value: RenderMode) {
+        self.ppu().set_render_mode(value);
+    }
+
+    /// Enables the rewind feature, storing a snapshot of the
+    /// system state at the end of each frame, up to the provided
+    /// number of frames.
+    pub fn enable_rewind(&mut self, capacity_frames: usize) {
+        self.rewind = Some(RewindBuffer::new(capacity_frames, None));
+    }
+
+    pub fn disable_rewind(&mut self) {
+        self.rewind = None;
+    }
+
+    pub fn rewind_enabled(&self) -> bool {
+        self.rewind.is_some()
+    }
+
+    pub fn rewind_buffer(&self) -> Option<&RewindBuffer> {
+        self.rewind.as_ref()
+    }
+
+    /// Rewinds the system by the provided number of frames, going
+    /// back to the state at the end of that frame.
+    ///
+    /// Returns `false` in case there's no state to rewind to.
+    pub fn rewind(&mut self, n_frames: usize) -> Result<bool, Error> {
+        let rewind = self
+            .rewind
+            .as_mut()
+            .ok_or(Error::CustomError(String::from("Rewind is not enabled")))?;
+        let state = match rewind.rewind(n_frames)? {
+            Some(state) => state,
+            None => return Ok(false),
+        };
+        StateManager::load(
+            &state,
+            self,
+            Some(SaveStateFormat::Bos),
+            Some(ToGbOptions::new(false)),
+        )?;
+        if let Some(movie) = self.movie.as_mut() {
+            movie.rewind(n_frames as u32);
+            let keys = movie.frame_keys();
+            self.pad().set_keys(keys);
+        }
+        Ok(true)
+    }
+
+    pub fn quick_slots(&self) -> &QuickSlots {
+        &self.quick_slots
+    }
+
+    pub fn quick_slots_mut(&mut self) -> &mut QuickSlots {
+        &mut self.quick_slots
+    }
+
+    /// Changes the number of quick-save slots available,
+    /// discarding the currently stored states.
+    pub fn set_quick_slot_count(&mut self, count: usize) {
+        self.quick_slots = QuickSlots::new(count);
+    }
+
+    /// Saves the state of the system into the provided quick-save
+    /// slot (or the selected one), in the minimal (uncompressed)
+    /// format so that no compression or filesystem is involved.
+    pub fn quick_save(&mut self, slot: Option<usize>) -> Result<(), Error> {
+        let slot = slot.unwrap_or(self.quick_slots.selected());
+        let options = FromGbOptions::new(false, Some(StateFormat::Minimal), None, None);
+        let state = StateManager::save(self, Some(SaveStateFormat::Bos), Some(options))?;
+        self.quick_slots.set(slot, state)
+    }
+
+    /// Loads the state of the system from the provided quick-save
+    /// slot (or the selected one).
+    ///
+    /// Returns `false` in case the slot has no state stored.
+    pub fn quick_load(&mut self, slot: Option<usize>) -> Result<bool, Error> {
+        let slot = slot.unwrap_or(self.quick_slots.selected());
+        let state = match self.quick_slots.get(slot) {
+            Some(state) => state.to_vec(),
+            None => return Ok(false),
+        };
+        StateManager::load(
+            &state,
+            self,
+            Some(SaveStateFormat::Bos),
+            Some(ToGbOptions::new(false)),
+        )?;
+        Ok(true)
+    }
+
+    /// Saves the complete state of the system into an in-memory
+    /// buffer (BOSC format), so that no filesystem is required.
+    pub fn save_state_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        StateManager::save(self, None, None)
+    }
+
+    /// Loads the state of the system from an in-memory buffer,
+    /// with the format (BOSC, BOS or BESS) being auto-detected.
+    pub fn load_state_bytes(&mut self, data: &[u8]) -> Result<(), Error> {
+        StateManager::load(data, self, None, None)
+    }
+
+    /// Starts recording an input movie from the current state of
+    /// the system, with the inputs of the frontend being latched
+    /// at the frame boundaries from now on.
+    pub fn start_movie_recording(&mut self) -> Result<(), Error> {
+        let options = FromGbOptions::new(false, None, None, None);
+        let state = StateManager::save(self, Some(SaveStateFormat::Bos), Some(options))?;
+        let keys = self.pad_i().keys();
+        let movie = Movie {
+            title: self.rom_i().title(),
+            rom_crc32: self.rom_i().fingerprint(),
+            rerecords: 0,
+            state,
+            inputs: vec![keys],
+        };
+        self.movie = Some(MovieSession::new(movie, MovieMode::Recording, keys));
+        Ok(())
+    }
+
+    /// Stops the recording or playback of the current movie (if
+    /// any), returning it.
+    pub fn stop_movie(&mut self) -> Option<Movie> {
+        self.movie.take().map(|movie| movie.into_movie())
+    }
+
+    /// Starts the playback of the provided movie, loading its
+    /// initial state, failing if it was recorded with another ROM.
+    pub fn play_movie_data(&mut self, movie: Movie) -> Result<(), Error> {
+        let fingerprint = self.rom_i().fingerprint();
+        if movie.rom_crc32 != fingerprint {
+            return Err(Error::CustomError(format!(
+                "Movie ROM mismatch: 0x{:08x} (expected 0x{:08x})",
+                movie.rom_crc32, fingerprint
+            )));
+        }
+        StateManager::load(
+            &movie.state,
+            self,
+            Some(SaveStateFormat::Bos),
+            Some(ToGbOptions::new(false)),
+        )?;
+        let keys = self.pad_i().keys();
+        let session = MovieSession::new(movie, MovieMode::Playing, keys);
+        self.pad().set_keys(session.frame_keys());
+        self.movie = Some(session);
+        Ok(())
+    }
+
+    /// Starts the playback of the movie in the provided file.
+    pub fn play_movie(&mut self, path: &str) -> Result<(), Error> {
+        let data = read_file(path)?;
+        self.play_movie_data(Movie::from_data(&data)?)
+    }
+
+    pub fn movie(&self) -> Option<&MovieSession> {
+        self.movie.as_ref()
+    }
+
+    /// The movie session (if any) that is recording or playing,
+    /// taking over the inputs of the frontend.
+    fn active_movie(&mut self) -> Option<&mut MovieSession> {
+        self.movie
+            .as_mut()
+            .filter(|movie| movie.mode() != MovieMode::Finished)
+    }
+
+    /// Enables the frame budget profiler, measuring the time spent
+    /// by each of the subsystems in every frame.
+    pub fn enable_profiler(&mut self) {
+        self.profiler = Some(Profiler::new());
+    }
+
+    pub fn disable_profiler(&mut self) {
+        self.profiler = None;
+    }
+
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    #[inline(always)]
+    fn profile_start(&mut self) {
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.start();
+        }
+    }
+
+    #[inline(always)]
+    fn profile_mark(&mut self, slot: ProfileSlot) {
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.mark(slot);
+        }
+    }
+
+    fn record_frame(&mut self) {
+        let mut recorder = self.recorder.take().unwrap();
+        recorder.push(self.frame_buffer());
+        self.recorder = Some(recorder);
+    }
+
+    fn snapshot_rewind(&mut self) {
+        let options = FromGbOptions::new(false, None, None, None);
+        let state = match StateManager::save(self, Some(SaveStateFormat::Bos), Some(options)) {
+            Ok(state) => state,
+            Err(err) => {
+                warnln!("Failed to snapshot state for rewind: {}", err);
+                #[allow(unreachable_code)]
+                {
+                    return;
+                }
+            }
+        };
+        if let Err(err) = self.rewind.as_mut().unwrap().push(&state) {
+            warnln!("Failed to store state for rewind: {}", err);
+        }
+    }
+
     pub fn reset_cheats(&mut self) {
         self.reset_game_genie();
         self.reset_game_shark();
@@ -1463,7 +3733,59 @@ impl GameBoy {
         Err(Error::CustomError(String::from("Not a valid cheat code")))
     }
 
+    /// Applies the provided cheat list to the system, replacing any
+    /// previously registered cheat codes with the enabled entries
+    /// of the list.
+    ///
+    /// Returns the number of codes that have been applied.
+    pub fn apply_cheats(&mut self, cheats: &CheatList) -> Result<usize, Error> {
+        if cheats.fingerprint() != 0 && cheats.fingerprint() != self.rom_i().fingerprint() {
+            return Err(Error::CustomError(format!(
+                "Cheat list fingerprint mismatch, expected {:08x} got {:08x}",
+                self.rom_i().fingerprint(),
+                cheats.fingerprint()
+            )));
+        }
+
+        // builds the new set of cheats apart from the registered ones
+        // so that an invalid code leaves the current cheats untouched
+        let mut game_genie = GameGenie::default();
+        let mut game_shark = GameShark::default();
+        game_shark.set_rom_type(self.rom_i().rom_type());
+        let (mut genie_count, mut shark_count) = (0, 0);
+        for code in cheats.enabled_codes() {
+            if GameGenie::is_code(code) {
+                game_genie.add_code(code)?;
+                genie_count += 1;
+            } else if GameShark::is_code(code) {
+                game_shark.add_code(code)?;
+                shark_count += 1;
+            } else {
+                return Err(Error::CustomError(format!(
+                    "Not a valid cheat code: {code}"
+                )));
+            }
+        }
+
+        self.mmu().invalidate_code();
+        let rom = self.rom();
+        if genie_count > 0 || rom.game_genie().is_some() {
+            rom.attach_genie(game_genie);
+        }
+        if shark_count > 0 || rom.game_shark().is_some() {
+            rom.attach_shark(game_shark);
+        }
+        Ok(genie_count + shark_count)
+    }
+
+    /// Builds an empty cheat list for the currently loaded ROM,
+    /// keyed by the ROM fingerprint.
+    pub fn cheat_list(&self) -> CheatList {
+        CheatList::new(self.rom_i().fingerprint())
+    }
+
     pub fn add_game_genie_code(&mut self, code: &str) -> Result<&GameGenieCode, Error> {
+        self.mmu().invalidate_code();
         let rom = self.mmu().rom();
         if rom.game_genie().is_none() {
             let game_genie = GameGenie::default();
@@ -1473,7 +3795,8 @@ impl GameBoy {
         game_genie.add_code(code)
     }
 
-    pub fn add_game_shark_code(&mut self, code: &str) -> Result<&GameSharkCode, Error> {
+    pub fn add_game_shark_code(&mut self, code: &str) -> Result<&GameSharkCheat, Error> {
+        self.mmu().invalidate_code();
         let rom = self.rom();
         if rom.game_shark().is_none() {
             let game_shark = GameShark::default();
@@ -1521,6 +3844,9 @@ impl GameBoy {
         self.set_speed_callback(|speed| {
             speed_callback(speed);
         });
+        self.set_palette_callback(|obj, index, palette| {
+            palette_callback(obj, index, palette.concat());
+        });
     }
 
     pub fn load_null_wa(&mut self) {
@@ -1551,6 +3877,33 @@ impl GameBoy {
     /// This can be an expensive operation as it will require
     /// cartridge data parsing to obtain the CGB flag.
     /// It will also have to clone the data buffer.
+    pub fn save_state_bytes_wa(&mut self) -> Result<Vec<u8>, String> {
+        Ok(self.save_state_bytes()?)
+    }
+
+    pub fn load_state_bytes_wa(&mut self, data: &[u8]) -> Result<(), String> {
+        Ok(self.load_state_bytes(data)?)
+    }
+
+    /// Obtains the compatibility warnings of the loaded
+    /// cartridge, as a newline separated string.
+    pub fn rom_warnings_wa(&self) -> String {
+        self.rom_warnings()
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    pub fn auto_fire_wa(&self) -> String {
+        self.auto_fire().to_string()
+    }
+
+    pub fn set_auto_fire_wa(&mut self, value: &str) -> Result<(), String> {
+        self.set_auto_fire(AutoFire::from_string(value)?);
+        Ok(())
+    }
+
     pub fn infer_mode_wa(&mut self, data: &[u8]) -> Result<(), String> {
         let mode = Cartridge::from_data(data)?.gb_mode();
         self.set_mode(mode);
@@ -1591,6 +3944,9 @@ extern "C" {
     #[wasm_bindgen(js_namespace = window, js_name = speedCallback)]
     fn speed_callback(speed: GameBoySpeed);
 
+    #[wasm_bindgen(js_namespace = window, js_name = paletteCallback)]
+    fn palette_callback(obj: bool, index: u8, palette: Vec<u8>);
+
     #[wasm_bindgen(js_namespace = window, js_name = loggerCallback)]
     fn logger_callback(data: Vec<u8>);
 
@@ -1619,6 +3975,46 @@ impl AudioProvider for GameBoy {
     fn clear_audio_buffer(&mut self) {
         self.apu().clear_audio_buffer()
     }
+
+    fn take_audio_frame(&mut self) -> Option<Vec<u8>> {
+        let mut audio_frame = vec![];
+        if self.take_audio_frame_into(&mut audio_frame) {
+            Some(audio_frame)
+        } else {
+            None
+        }
+    }
+
+    fn take_audio_frame_into(&mut self, buffer: &mut Vec<u8>) -> bool {
+        let taken = match self.apu_thread.as_mut() {
+            Some(apu_thread) => apu_thread.take_audio_frame_into(buffer),
+            None => self.apu().take_audio_frame_into(buffer),
+        };
+        if !taken {
+            return false;
+        }
+        self.av_stats.audio_taken += 1;
+
+        // applies the (linear) fade in of the audio that follows
+        // a re-synchronization, if there's one in progress
+        let (remaining, total) = self.audio_fade;
+        if remaining > 0 {
+            let channels = self.audio_channels() as usize;
+            let mut remaining = remaining;
+            for samples in buffer.chunks_mut(channels) {
+                if remaining == 0 {
+                    break;
+                }
+                let gain = (total - remaining) as f32 / total as f32;
+                for sample in samples {
+                    *sample = (*sample as f32 * gain) as u8;
+                }
+                remaining = remaining.saturating_sub(channels as u32);
+            }
+            self.audio_fade = (remaining, total);
+        }
+        true
+    }
 }
 
 impl Default for GameBoy {
@@ -1,8 +1,12 @@
 //! General information about the crate and the emulator.
 
 use boytacean_common::util::capitalize;
+use std::fmt::{self, Display, Formatter};
 
-use crate::gen::{COMPILATION_DATE, COMPILATION_TIME, COMPILER, COMPILER_VERSION, NAME, VERSION};
+use crate::gen::{
+    COMPILATION_DATE, COMPILATION_TIME, COMPILER, COMPILER_VERSION, DEPENDENCIES, FEATURES_SEQ,
+    HOST, NAME, OPT_LEVEL, PROFILE, TARGET, VERSION,
+};
 
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
@@ -10,6 +14,107 @@ use wasm_bindgen::prelude::*;
 #[cfg(feature = "wasm")]
 use crate::gen::dependencies_map;
 
+/// Typed view over the build metadata generated at compile time,
+/// so that every frontend and binding reports the same values.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BuildInfo {
+    pub name: String,
+    pub version: String,
+    pub compiler: String,
+    pub compiler_version: String,
+    pub compilation_date: String,
+    pub compilation_time: String,
+    pub target: String,
+    pub host: String,
+    pub profile: String,
+    pub opt_level: String,
+
+    /// The emulator features enabled in the build (eg: `cpu`, `wasm`).
+    pub features: Vec<String>,
+
+    /// The dependencies of the build as (name, version) pairs.
+    pub dependencies: Vec<(String, String)>,
+}
+
+impl BuildInfo {
+    /// Obtains the build information of the current build.
+    pub fn current() -> Self {
+        Self {
+            name: String::from(NAME),
+            version: String::from(VERSION),
+            compiler: String::from(COMPILER),
+            compiler_version: String::from(COMPILER_VERSION),
+            compilation_date: String::from(COMPILATION_DATE),
+            compilation_time: String::from(COMPILATION_TIME),
+            target: String::from(TARGET),
+            host: String::from(HOST),
+            profile: String::from(PROFILE),
+            opt_level: String::from(OPT_LEVEL),
+            features: FEATURES_SEQ.iter().map(|v| String::from(*v)).collect(),
+            dependencies: DEPENDENCIES
+                .iter()
+                .map(|(name, version)| (String::from(*name), String::from(*version)))
+                .collect(),
+        }
+    }
+
+    /// The build information as a sequence of (key, value) pairs,
+    /// with the list values joined by commas, useful for bindings
+    /// that only handle plain string maps.
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("name", self.name.clone()),
+            ("version", self.version.clone()),
+            ("compiler", self.compiler.clone()),
+            ("compiler_version", self.compiler_version.clone()),
+            ("compilation_date", self.compilation_date.clone()),
+            ("compilation_time", self.compilation_time.clone()),
+            ("target", self.target.clone()),
+            ("host", self.host.clone()),
+            ("profile", self.profile.clone()),
+            ("opt_level", self.opt_level.clone()),
+            ("features", self.features.join(",")),
+            (
+                "dependencies",
+                self.dependencies
+                    .iter()
+                    .map(|(name, version)| format!("{name}/{version}"))
+                    .collect::<Vec<String>>()
+                    .join(","),
+            ),
+        ]
+    }
+
+    pub fn description(&self) -> String {
+        format!(
+            "{} v{}\nCompiler    {} {}\nCompiled    {} {}\nTarget      {} ({}, opt-level {})\nFeatures    {}\nDeps        {} crates",
+            capitalize(&self.name),
+            self.version,
+            self.compiler,
+            self.compiler_version,
+            self.compilation_date,
+            self.compilation_time,
+            self.target,
+            self.profile,
+            self.opt_level,
+            self.features.join(", "),
+            self.dependencies.len()
+        )
+    }
+}
+
+impl Default for BuildInfo {
+    fn default() -> Self {
+        Self::current()
+    }
+}
+
+impl Display for BuildInfo {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct Info;
 
@@ -54,6 +159,30 @@ impl Info {
     pub fn compilation_time() -> String {
         String::from(COMPILATION_TIME)
     }
+
+    /// Obtains the target triple the library was built for.
+    pub fn target() -> String {
+        String::from(TARGET)
+    }
+
+    /// Obtains the emulator features enabled in the build,
+    /// separated by commas.
+    pub fn features() -> String {
+        FEATURES_SEQ.join(",")
+    }
+
+    /// Obtains a textual description of the complete build
+    /// information, see [`BuildInfo`].
+    pub fn build_description() -> String {
+        BuildInfo::current().description()
+    }
+}
+
+impl Info {
+    /// Obtains the typed build information of the library.
+    pub fn build_info() -> BuildInfo {
+        BuildInfo::current()
+    }
 }
 
 #[cfg(feature = "wasm")]
@@ -70,3 +199,23 @@ impl Info {
         )))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{BuildInfo, Info};
+
+    #[test]
+    fn test_build_info() {
+        let build_info = Info::build_info();
+        assert_eq!(build_info.name, Info::name_lower());
+        assert_eq!(build_info.version, Info::version());
+        assert_eq!(build_info.target, Info::target());
+        assert!(build_info.features.contains(&String::from("cpu")));
+        assert_eq!(build_info, BuildInfo::default());
+
+        let entries = build_info.entries();
+        assert_eq!(entries.len(), 12);
+        assert_eq!(entries[1], ("version", Info::version()));
+        assert!(build_info.description().contains(&Info::version()));
+    }
+}
@@ -269,7 +269,7 @@ pub const INSTRUCTIONS: [Instruction; 256] = [
     (rst_30h, 16, "RST 30h"),
     (ld_hl_spi8, 12, "LD HL, SP+i8"),
     (ld_sp_hl, 8, "LD SP, HL"),
-    (ld_a_mu16, 16, "LD A [u16]"),
+    (ld_a_mu16, 16, "LD A, [u16]"),
     (ei, 4, "EI"),
     (illegal, 4, "ILLEGAL"),
     (illegal, 4, "ILLEGAL"),
@@ -0,0 +1,182 @@
+//! I/O register registration and lookup structures.
+//!
+//! Each component that exposes registers in the 0xFF00-0xFF7F
+//! range (plus IE at 0xFFFF) declares them through the [`Io`]
+//! trait, the MMU then dispatches accesses using the resulting
+//! table and applies the read/write masks centrally.
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::{apu::Apu, dma::Dma, mmu::Mmu, pad::Pad, ppu::Ppu, serial::Serial, timer::Timer};
+
+/// Number of entries in the I/O table, covering the
+/// 0xFF00-0xFF7F range plus the IE register.
+pub const IO_TABLE_SIZE: usize = 0x81;
+
+/// The component responsible for handling the accesses
+/// to a certain I/O register.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IoComponent {
+    Mmu,
+    Pad,
+    Serial,
+    Timer,
+    Apu,
+    Ppu,
+    Dma,
+}
+
+impl IoComponent {
+    pub fn description(&self) -> &'static str {
+        match self {
+            IoComponent::Mmu => "MMU",
+            IoComponent::Pad => "Pad",
+            IoComponent::Serial => "Serial",
+            IoComponent::Timer => "Timer",
+            IoComponent::Apu => "APU",
+            IoComponent::Ppu => "PPU",
+            IoComponent::Dma => "DMA",
+        }
+    }
+}
+
+impl Display for IoComponent {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// Description of a memory mapped I/O register.
+///
+/// The read mask identifies the bits that are read back from
+/// the component (the remaining ones always read as 1) and the
+/// write mask the bits that can be written, a register with an
+/// empty mask is never read from/written to its component.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IoRegister {
+    pub addr: u16,
+    pub name: &'static str,
+    pub component: IoComponent,
+    pub read_mask: u8,
+    pub write_mask: u8,
+}
+
+impl IoRegister {
+    pub const fn new(
+        addr: u16,
+        name: &'static str,
+        component: IoComponent,
+        read_mask: u8,
+        write_mask: u8,
+    ) -> Self {
+        Self {
+            addr,
+            name,
+            component,
+            read_mask,
+            write_mask,
+        }
+    }
+
+    pub fn readable(&self) -> bool {
+        self.read_mask != 0x00
+    }
+
+    pub fn writable(&self) -> bool {
+        self.write_mask != 0x00
+    }
+}
+
+impl Display for IoRegister {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "0x{:04x} {} ({})", self.addr, self.name, self.component)
+    }
+}
+
+/// Trait to be implemented by the components that expose
+/// memory mapped I/O registers.
+pub trait Io {
+    const IO_REGISTERS: &'static [IoRegister];
+}
+
+/// Table with the complete set of I/O registers indexed by their
+/// offset, built at compile time so that any register registered
+/// by more than one component fails the build.
+pub static IO_TABLE: [Option<IoRegister>; IO_TABLE_SIZE] = build_io_table(&[
+    Mmu::IO_REGISTERS,
+    Pad::IO_REGISTERS,
+    Serial::IO_REGISTERS,
+    Timer::IO_REGISTERS,
+    Apu::IO_REGISTERS,
+    Ppu::IO_REGISTERS,
+    Dma::IO_REGISTERS,
+]);
+
+/// Obtains the I/O register mapped at the provided address,
+/// returning `None` for unmapped or non I/O addresses.
+pub fn io_register(addr: u16) -> Option<&'static IoRegister> {
+    match io_index(addr) {
+        Some(index) => IO_TABLE[index].as_ref(),
+        None => None,
+    }
+}
+
+/// Iterates over the complete set of mapped I/O registers,
+/// sorted by address.
+pub fn io_registers() -> impl Iterator<Item = &'static IoRegister> {
+    IO_TABLE.iter().flatten()
+}
+
+const fn io_index(addr: u16) -> Option<usize> {
+    match addr {
+        0xff00..=0xff7f => Some((addr - 0xff00) as usize),
+        0xffff => Some(0x80),
+        _ => None,
+    }
+}
+
+const fn build_io_table(components: &[&[IoRegister]]) -> [Option<IoRegister>; IO_TABLE_SIZE] {
+    let mut table = [None; IO_TABLE_SIZE];
+    let mut component = 0;
+    while component < components.len() {
+        let registers = components[component];
+        let mut register = 0;
+        while register < registers.len() {
+            let index = match io_index(registers[register].addr) {
+                Some(index) => index,
+                None => panic!("I/O register out of the I/O range"),
+            };
+            if table[index].is_some() {
+                panic!("I/O register registered more than once");
+            }
+            table[index] = Some(registers[register]);
+            register += 1;
+        }
+        component += 1;
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{io_register, io_registers, IoComponent};
+    use crate::consts::{BCPD_ADDR, IE_ADDR, LY_ADDR, WX_ADDR, WY_ADDR};
+
+    #[test]
+    fn test_io_register() {
+        let register = io_register(WY_ADDR).unwrap();
+        assert_eq!(register.name, "WY");
+        assert_eq!(register.component, IoComponent::Ppu);
+        assert_eq!(io_register(WX_ADDR).unwrap().name, "WX");
+        assert_eq!(io_register(BCPD_ADDR).unwrap().name, "BCPD");
+        assert_eq!(io_register(IE_ADDR).unwrap().component, IoComponent::Mmu);
+        assert!(!io_register(LY_ADDR).unwrap().writable());
+        assert!(io_register(0xff03).is_none());
+        assert!(io_register(0xff80).is_none());
+
+        let addrs = io_registers().map(|r| r.addr).collect::<Vec<u16>>();
+        assert!(addrs.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(addrs.first(), Some(&0xff00));
+        assert_eq!(addrs.last(), Some(&IE_ADDR));
+    }
+}
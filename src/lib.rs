@@ -1,28 +1,45 @@
-#![cfg_attr(feature = "simd", feature(portable_simd))]
-
 pub mod apu;
+pub mod apu_thread;
+#[cfg(feature = "archive")]
+pub mod archive;
+pub mod block;
+pub mod bus;
+pub mod capture;
 pub mod cheats;
 pub mod color;
+pub mod conformance;
 pub mod consts;
 pub mod cpu;
 pub mod data;
+pub mod debug;
 pub mod devices;
 pub mod diag;
 pub mod dma;
+pub mod duo;
+pub mod fuzz;
 pub mod gb;
 pub mod gen;
 pub mod info;
 pub mod inst;
+pub mod io;
 pub mod licensee;
 pub mod macros;
 pub mod mmu;
+pub mod movie;
+pub mod netplay;
+pub mod pacing;
 pub mod pad;
+pub mod patch;
 pub mod ppu;
 pub mod rom;
 pub mod serial;
+pub mod sgb;
+pub mod sm83;
 pub mod state;
+pub mod storage;
 pub mod test;
 pub mod timer;
+pub mod video;
 
 #[cfg(feature = "python")]
 pub mod py;
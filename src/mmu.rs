@@ -1,17 +1,25 @@
 //! MMU (Memory Management Unit) functions and structures.
 
-use boytacean_common::util::SharedThread;
-use std::sync::Mutex;
+use boytacean_common::{error::Error, util::SharedThread};
+use std::{
+    cell::Cell,
+    fmt::{self, Display, Formatter},
+    sync::Mutex,
+};
 
 use crate::{
     apu::Apu,
     assert_pedantic_gb,
-    dma::Dma,
-    gb::{Components, GameBoyConfig, GameBoyMode, GameBoySpeed},
+    bus::{bus_page, BusMap, BusRange, BusRegion, BUS_TABLE, BUS_TABLE_SIZE},
+    consts::{BOOT_ADDR, HDMA5_ADDR, IE_ADDR, IF_ADDR, KEY0_ADDR, KEY1_ADDR, RP_ADDR, SVBK_ADDR},
+    data::CGB_COMPAT_GRAYSCALE,
+    dma::{Dma, DmaMode},
+    gb::{Components, ConfigChange, ConfigListener, GameBoyConfig, GameBoyMode, GameBoySpeed},
+    io::{io_register, io_registers, Io, IoComponent, IoRegister},
     pad::Pad,
     panic_gb,
-    ppu::Ppu,
-    rom::Cartridge,
+    ppu::{Ppu, PpuMode},
+    rom::{BatteryEvent, Cartridge, MbcType},
     serial::Serial,
     timer::Timer,
     warnln,
@@ -23,6 +31,35 @@ pub const BOOT_SIZE_CGB: usize = 2304;
 pub const RAM_SIZE_DMG: usize = 8192;
 pub const RAM_SIZE_CGB: usize = 32768;
 
+/// The size of the HRAM (0xFF80-0xFFFE), tracked after the WRAM
+/// in the marks of the cached code.
+pub const HRAM_SIZE: usize = 127;
+
+/// The bank reported for the code of the boot ROM while it's
+/// mapped over the cartridge ROM.
+pub const BOOT_BANK: u16 = 0xffff;
+
+impl Io for Mmu {
+    const IO_REGISTERS: &'static [IoRegister] = &[
+        IoRegister::new(IF_ADDR, "IF", IoComponent::Mmu, 0x1f, 0x1f),
+        IoRegister::new(KEY0_ADDR, "KEY0", IoComponent::Mmu, 0xff, 0xff),
+        IoRegister::new(KEY1_ADDR, "KEY1", IoComponent::Mmu, 0x81, 0x01),
+        IoRegister::new(BOOT_ADDR, "BOOT", IoComponent::Mmu, 0xff, 0xff),
+        IoRegister::new(RP_ADDR, "RP", IoComponent::Mmu, 0x00, 0x00),
+        IoRegister::new(SVBK_ADDR, "SVBK", IoComponent::Mmu, 0x07, 0x07),
+        IoRegister::new(IE_ADDR, "IE", IoComponent::Mmu, 0xff, 0xff),
+    ];
+}
+
+impl BusMap for Mmu {
+    const BUS_RANGES: &'static [BusRange] = &[
+        BusRange::new(0xc000, 0xcfff, BusRegion::Wram0),
+        BusRange::new(0xd000, 0xdfff, BusRegion::WramX),
+        BusRange::new(0xe000, 0xfdff, BusRegion::Echo),
+        BusRange::new(0xff00, 0xffff, BusRegion::High),
+    ];
+}
+
 pub trait BusComponent {
     fn read(&self, addr: u16) -> u8;
     fn write(&mut self, addr: u16, value: u8);
@@ -38,6 +75,178 @@ pub trait BusComponent {
     }
 }
 
+/// Byte pattern that can be searched for in the memory map,
+/// each byte of the pattern is compared against memory after
+/// both values are masked, allowing for (nibble) wildcards.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryPattern {
+    bytes: Vec<u8>,
+    mask: Vec<u8>,
+}
+
+impl MemoryPattern {
+    pub fn new(bytes: Vec<u8>, mask: Option<Vec<u8>>) -> Result<Self, Error> {
+        let mask = mask.unwrap_or_else(|| vec![0xff; bytes.len()]);
+        if mask.len() != bytes.len() {
+            return Err(Error::InvalidParameter(format!(
+                "Mask length ({}) does not match pattern length ({})",
+                mask.len(),
+                bytes.len()
+            )));
+        }
+        Ok(Self { bytes, mask })
+    }
+
+    /// Parses a pattern from its hex string representation (eg:
+    /// `3E ?? C9` or `3e??c9`), where the `?` character is used
+    /// as a wildcard for a nibble.
+    pub fn from_hex(pattern: &str) -> Result<Self, Error> {
+        let nibbles = pattern
+            .chars()
+            .filter(|char| !char.is_whitespace())
+            .collect::<Vec<char>>();
+        if nibbles.is_empty() || nibbles.len() % 2 != 0 {
+            return Err(Error::InvalidParameter(format!(
+                "Invalid hex pattern: {pattern}"
+            )));
+        }
+
+        let mut bytes = Vec::with_capacity(nibbles.len() / 2);
+        let mut mask = Vec::with_capacity(nibbles.len() / 2);
+        for pair in nibbles.chunks(2) {
+            let mut byte = 0u8;
+            let mut byte_mask = 0u8;
+            for nibble in pair {
+                byte <<= 4;
+                byte_mask <<= 4;
+                if *nibble == '?' {
+                    continue;
+                }
+                let value = nibble.to_digit(16).ok_or(Error::InvalidParameter(format!(
+                    "Invalid hex pattern: {pattern}"
+                )))?;
+                byte |= value as u8;
+                byte_mask |= 0x0f;
+            }
+            bytes.push(byte);
+            mask.push(byte_mask);
+        }
+
+        Self::new(bytes, Some(mask))
+    }
+
+    /// Parses a mask from its hex string representation and
+    /// combines it with the current mask of the pattern.
+    pub fn apply_mask_hex(&mut self, mask: &str) -> Result<(), Error> {
+        let mask = Self::from_hex(mask)?;
+        if mask.mask.iter().any(|value| *value != 0xff) {
+            return Err(Error::InvalidParameter(String::from(
+                "Wildcards are not allowed in masks",
+            )));
+        }
+        if mask.len() != self.len() {
+            return Err(Error::InvalidParameter(format!(
+                "Mask length ({}) does not match pattern length ({})",
+                mask.len(),
+                self.len()
+            )));
+        }
+        for (value, mask) in self.mask.iter_mut().zip(mask.bytes.iter()) {
+            *value &= mask;
+        }
+        Ok(())
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn mask(&self) -> &[u8] {
+        &self.mask
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Checks if the provided data (that should have at least
+    /// the size of the pattern) matches the pattern.
+    pub fn matches(&self, data: &[u8]) -> bool {
+        data.len() >= self.len()
+            && self
+                .bytes
+                .iter()
+                .zip(self.mask.iter())
+                .zip(data.iter())
+                .all(|((byte, mask), value)| byte & mask == value & mask)
+    }
+}
+
+/// The kind of memory access that triggers a watchpoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    Access,
+}
+
+impl WatchKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            WatchKind::Read => "Read",
+            WatchKind::Write => "Write",
+            WatchKind::Access => "Access",
+        }
+    }
+
+    fn triggers(&self, kind: WatchKind) -> bool {
+        *self == WatchKind::Access || *self == kind
+    }
+}
+
+impl Display for WatchKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// Memory range that is watched for accesses, typically used
+/// by debuggers to stop execution on data reads or writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub addr: u16,
+    pub length: u16,
+    pub kind: WatchKind,
+}
+
+impl Watchpoint {
+    pub fn new(addr: u16, length: u16, kind: WatchKind) -> Self {
+        Self {
+            addr,
+            length: length.max(1),
+            kind,
+        }
+    }
+
+    #[inline(always)]
+    fn contains(&self, addr: u16) -> bool {
+        addr.wrapping_sub(self.addr) < self.length
+    }
+}
+
+/// Memory access that triggered a watchpoint, the kind is
+/// the one of the access (either read or write).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchHit {
+    pub addr: u16,
+    pub kind: WatchKind,
+    pub watchpoint: Watchpoint,
+}
+
 pub struct Mmu {
     /// Register that controls the interrupts that are considered
     /// to be enabled and should be triggered.
@@ -62,6 +271,20 @@ pub struct Mmu {
     /// should provide visibility over the current speed of the system.
     speed_callback: fn(speed: GameBoySpeed),
 
+    /// Callback to be called when the battery backed RAM of the
+    /// cartridge is changed or saved, receiving the (PPU) frame
+    /// index at which the event took place.
+    battery_callback: fn(event: BatteryEvent, frame: u16),
+
+    /// If the CGB compatibility palettes should be kept for DMG games
+    /// running in CGB mode, otherwise grayscale palettes are used.
+    auto_colorize: bool,
+
+    /// If the reads from unmapped areas (eg: 0xFEA0-0xFEFF and the
+    /// external RAM of cartridges without RAM) return the values
+    /// observed on hardware, instead of the memory contents.
+    open_bus: bool,
+
     /// Reference to the PPU (Pixel Processing Unit) that is going
     /// to be used both for VRAM reading/writing and to forward
     /// some of the access operations.
@@ -130,6 +353,41 @@ pub struct Mmu {
     /// Game Boy emulator, that can be used to control the behaviour
     /// of Game Boy emulation.
     gbc: SharedThread<GameBoyConfig>,
+
+    /// The memory watchpoints currently active, checked on every
+    /// read and write operation if not empty.
+    watchpoints: Vec<Watchpoint>,
+
+    /// The first watchpoint hit since the last time the hit was
+    /// taken, uses a cell as reads are non mutable operations.
+    watch_hit: Cell<Option<WatchHit>>,
+
+    /// Flat 64 KB memory that (when set) replaces the complete memory
+    /// map, with no component being accessed, used to run the CPU in
+    /// isolation (eg: single instruction tests).
+    flat: Option<Box<[u8; 0x10000]>>,
+
+    /// The region that handles each of the pages of the memory
+    /// map, the built-in map (see `BUS_TABLE`) with the ranges of
+    /// the pluggable components applied on top of it.
+    bus_map: [BusRegion; BUS_TABLE_SIZE],
+
+    /// The pluggable components mapped into the address space,
+    /// indexed by the device regions of the memory map.
+    devices: Vec<(BusRange, Box<dyn BusComponent + Send>)>,
+
+    /// Marks the bytes of the WRAM (followed by the HRAM) that hold
+    /// opcodes of the cached (decoded) code, allocated on the first
+    /// mark, a write to any of them invalidates the cached code.
+    code_marks: Vec<bool>,
+
+    /// Incremented every time the contents of the memory that may
+    /// hold cached code change, invalidating the cached code.
+    code_generation: u32,
+
+    /// Incremented every time the banks mapped into the address space
+    /// (ROM, WRAM and boot ROM) may have changed.
+    map_generation: u32,
 }
 
 impl Mmu {
@@ -156,8 +414,19 @@ impl Mmu {
             speed: GameBoySpeed::Normal,
             switching: false,
             speed_callback: |_| {},
+            battery_callback: |_, _| {},
+            auto_colorize: true,
+            open_bus: true,
             mode,
             gbc,
+            watchpoints: vec![],
+            watch_hit: Cell::new(None),
+            flat: None,
+            bus_map: BUS_TABLE,
+            devices: vec![],
+            code_marks: vec![],
+            code_generation: 0,
+            map_generation: 0,
         }
     }
 
@@ -172,6 +441,7 @@ impl Mmu {
         self.key0 = 0x0;
         self.speed = GameBoySpeed::Normal;
         self.switching = false;
+        self.invalidate_code();
     }
 
     pub fn allocate_default(&mut self) {
@@ -181,25 +451,32 @@ impl Mmu {
     pub fn allocate_dmg(&mut self) {
         self.boot = vec![0x00; BOOT_SIZE_DMG];
         self.ram = vec![0x00; RAM_SIZE_DMG];
+        self.invalidate_code();
     }
 
     pub fn allocate_cgb(&mut self) {
         self.boot = vec![0x00; BOOT_SIZE_CGB];
         self.ram = vec![0x00; RAM_SIZE_CGB];
+        self.invalidate_code();
     }
 
     /// Notifies the system that a VBlank interrupt has been
     /// triggered, would usually be the perfect time to update
     /// some of the internal memory structures.
     pub fn vblank(&mut self) {
-        let writes = self.rom.vblank();
-        if let Some(writes) = writes {
-            for (base_addr, addr, value) in writes {
-                match base_addr {
-                    0xa000 => self.rom.ram_data_mut()[addr as usize] = value,
-                    0xc000 => self.ram[addr as usize] = value,
-                    _ => panic_gb!("Invalid base address for write: 0x{:04x}", base_addr),
+        let writes = match self.rom.game_shark() {
+            Some(game_shark) => game_shark.writes(|addr| self.read(addr)),
+            None => return,
+        };
+        for (base_addr, addr, value) in writes {
+            match base_addr {
+                0xa000 => {
+                    if let Some(data) = self.rom.ram_data_mut().get_mut(addr as usize) {
+                        *data = value;
+                    }
                 }
+                0xc000 => self.ram[addr as usize] = value,
+                _ => panic_gb!("Invalid base address for write: 0x{:04x}", base_addr),
             }
         }
     }
@@ -224,6 +501,35 @@ impl Mmu {
         self.speed_callback = callback;
     }
 
+    pub fn set_battery_callback(&mut self, callback: fn(event: BatteryEvent, frame: u16)) {
+        self.battery_callback = callback;
+    }
+
+    pub fn auto_colorize(&self) -> bool {
+        self.auto_colorize
+    }
+
+    pub fn set_auto_colorize(&mut self, value: bool) {
+        self.auto_colorize = value;
+    }
+
+    pub fn open_bus(&self) -> bool {
+        self.open_bus
+    }
+
+    pub fn set_open_bus(&mut self, value: bool) {
+        self.open_bus = value;
+    }
+
+    /// Marks the battery backed RAM of the cartridge as saved,
+    /// to be called by the frontend once the RAM is flushed.
+    pub fn mark_battery_saved(&mut self) {
+        if self.rom.battery_dirty() {
+            self.rom.set_battery_frame(None);
+            (self.battery_callback)(BatteryEvent::Saved, self.ppu.frame_index());
+        }
+    }
+
     pub fn ppu(&mut self) -> &mut Ppu {
         &mut self.ppu
     }
@@ -278,6 +584,16 @@ impl Mmu {
 
     pub fn set_boot_active(&mut self, value: bool) {
         self.boot_active = value;
+        self.map_generation = self.map_generation.wrapping_add(1);
+    }
+
+    /// If the boot ROM is mapped at the address, over the
+    /// cartridge ROM.
+    #[inline(always)]
+    fn boot_mapped(&self, addr: u16) -> bool {
+        self.boot_active
+            && (addr <= 0x00ff
+                || (self.mode == GameBoyMode::Cgb && (0x0200..=0x08ff).contains(&addr)))
     }
 
     pub fn clock_dma(&mut self, cycles: u16) {
@@ -295,18 +611,11 @@ impl Mmu {
             self.dma.set_cycles_dma(cycles_dma);
         }
 
-        // @TODO: Implement DMA transfer in a better way, meaning that
-        // the DMA transfer should respect the timings
-        //
-        // In both Normal Speed and Double Speed Mode it takes about 8 μs
-        // to transfer a block of $10 bytes. That is, 8 M-cycles in Normal
-        // Speed Mode [1], and 16 “fast” M-cycles in Double Speed Mode [2].
-        // Older MBC controllers (like MBC1-3) and slower ROMs are not guaranteed
-        // to support General Purpose or HBlank DMA, that’s because there are
-        // always 2 bytes transferred per microsecond (even if the itself
-        // program runs it Normal Speed Mode).
-        //
-        // we should also respect General-Purpose DMA vs HBlank DMA
+        // in both normal speed and double speed mode it takes 8 μs
+        // to transfer a block of 0x10 bytes, that is 8 M-cycles in
+        // normal speed mode and 16 "fast" M-cycles in double speed
+        // mode, with the CPU stalled during the transfer (the data
+        // itself is transferred at once, at the start of the stall)
         if self.dma.active_hdma() {
             // runs a series of pre-validation on the HDMA transfer in
             // pedantic mode is currently active (performance hit)
@@ -322,170 +631,436 @@ impl Mmu {
                 self.dma.destination()
             );
 
-            // only runs the DMA transfer if the system is in CGB mode
-            // this avoids issues when writing to DMG unmapped registers
-            // that would otherwise cause the system to crash
-            if self.mode == GameBoyMode::Cgb {
-                let data = self.read_many(self.dma.source(), self.dma.pending());
-                self.write_many(self.dma.destination(), &data);
+            match self.dma.mode() {
+                DmaMode::General => self.clock_gdma(),
+                DmaMode::HBlank => self.clock_hdma(),
             }
-            self.dma.set_pending(0);
+        }
+    }
+
+    /// Runs the General-Purpose DMA, transferring all of the blocks at
+    /// once and stalling the CPU for the duration of the transfer, which
+    /// stops prematurely if the destination overflows the VRAM.
+    fn clock_gdma(&mut self) {
+        let source = self.dma.source();
+        let destination = self.dma.destination();
+        let length = self.dma.pending().min(0xa000 - destination);
+
+        // only runs the DMA transfer if the system is in CGB mode
+        // this avoids issues when writing to DMG unmapped registers
+        // that would otherwise cause the system to crash
+        if self.mode == GameBoyMode::Cgb {
+            let data = self.read_hdma(source, length);
+            self.write_many(destination, &data);
+            self.dma.stall_blocks(length >> 4, self.speed.multiplier());
+        }
+        self.dma.set_source(source.wrapping_add(length));
+        self.dma.set_destination(destination.wrapping_add(length));
+        self.dma.set_pending(0);
+        self.dma.set_active_hdma(false);
+    }
+
+    /// Reads the source data of a VRAM DMA transfer, with the sources
+    /// in the 0xE000-0xFFFF range reading from 0xA000-0xBFFF and the
+    /// VRAM (an invalid source) reading as 0xFF.
+    fn read_hdma(&self, addr: u16, count: u16) -> Vec<u8> {
+        (0..count)
+            .map(|offset| match addr.wrapping_add(offset) {
+                0x8000..=0x9fff => 0xff,
+                addr @ 0xe000..=0xffff => self.read(addr - 0x4000),
+                addr => self.read(addr),
+            })
+            .collect()
+    }
+
+    /// Runs the HBlank DMA, transferring one block of 0x10 bytes at the
+    /// start of each HBlank period (even with the CPU halted), if the
+    /// transfer is started with the LCD off a single block is transferred.
+    ///
+    /// The CPU is stalled during the transfer of each block, for 8 μs
+    /// meaning that the stall (in CPU cycles) doubles in double speed.
+    fn clock_hdma(&mut self) {
+        if self.ppu.mode() != PpuMode::HBlank {
+            self.dma.set_hblank_done(false);
+            return;
+        }
+        if self.dma.hblank_done() {
+            return;
+        }
+
+        let source = self.dma.source();
+        let destination = self.dma.destination();
+        if self.mode == GameBoyMode::Cgb {
+            let data = self.read_hdma(source, 0x10);
+            self.write_many(destination, &data);
+            self.dma.stall_blocks(1, self.speed.multiplier());
+        }
+        self.dma.set_source(source.wrapping_add(0x10));
+        self.dma.set_destination(destination.wrapping_add(0x10));
+        self.dma
+            .set_pending(self.dma.pending().saturating_sub(0x10));
+        self.dma.set_hblank_done(true);
+
+        // the transfer is complete once all the blocks are transferred
+        // or stops prematurely if the destination overflows the VRAM
+        if self.dma.pending() == 0 || destination >= 0x9ff0 {
             self.dma.set_active_hdma(false);
         }
     }
 
     pub fn read(&self, addr: u16) -> u8 {
-        match addr {
+        if !self.watchpoints.is_empty() {
+            self.check_watchpoints(addr, WatchKind::Read);
+        }
+        if let Some(flat) = &self.flat {
+            return flat[addr as usize];
+        }
+        match self.bus_map[bus_page(addr)] {
             // 0x0000-0x0FFF - BOOT (256 B) + ROM0 (4 KB/16 KB)
-            0x0000..=0x0fff => {
+            // 0x1000-0x3FFF - ROM 0 (12 KB/16 KB)
+            // 0x4000-0x7FFF - ROM 1 (Banked) (16 KB)
+            BusRegion::Rom => {
                 // in case the boot mode is active and the
                 // address is withing boot memory reads from it
-                if self.boot_active && addr <= 0x00ff {
+                if self.boot_mapped(addr) {
                     return self.boot[addr as usize];
                 }
-                if self.boot_active
-                    && self.mode == GameBoyMode::Cgb
-                    && (0x0200..=0x08ff).contains(&addr)
-                {
-                    return self.boot[addr as usize];
+                self.rom.read(addr)
+            }
+
+            // 0x8000-0x9FFF - Graphics: VRAM (8 KB)
+            BusRegion::Vram => self.ppu.read(addr),
+
+            // 0xA000-0xBFFF - External RAM (8 KB)
+            BusRegion::ExtRam => {
+                if self.ext_ram_unmapped() {
+                    return 0xff;
                 }
                 self.rom.read(addr)
             }
 
+            // 0xC000-0xCFFF - Working RAM 0 (4 KB)
+            BusRegion::Wram0 => self.ram[(addr & 0x0fff) as usize],
+
+            // 0xD000..=0xDFFF - Working RAM 1 (Banked) (4KB)
+            BusRegion::WramX => self.ram[(self.ram_offset + (addr & 0x0fff)) as usize],
+
+            // 0xE000..=0xFDFF - Working RAM Shadow, mirrors both
+            // the WRAM 0 and the currently selected WRAM bank
+            BusRegion::Echo => self.ram[self.echo_index(addr)],
+
+            // 0xFE00-0xFE9F - Object attribute memory (OAM)
+            // 0xFEA0-0xFEFF - Not Usable
+            BusRegion::Oam => match addr {
+                0xfe00..=0xfe9f => self.ppu.read(addr),
+                _ => self.read_unusable(addr),
+            },
+
+            // 0xFF00-0xFF7F - I/O registers
+            // 0xFF80-0xFFFE - High RAM (HRAM)
+            // 0xFFFF — IE: Interrupt enable
+            BusRegion::High => match addr {
+                0xff80..=0xfffe => self.ppu.read(addr),
+                _ => self.read_io(addr),
+            },
+
+            BusRegion::Device(index) => self.devices[index as usize].1.read(addr),
+        }
+    }
+
+    pub fn write(&mut self, addr: u16, value: u8) {
+        if !self.watchpoints.is_empty() {
+            self.check_watchpoints(addr, WatchKind::Write);
+        }
+        if let Some(flat) = &mut self.flat {
+            flat[addr as usize] = value;
+            return;
+        }
+        match self.bus_map[bus_page(addr)] {
+            // 0x0000-0x0FFF - BOOT (256 B) + ROM0 (4 KB/16 KB)
             // 0x1000-0x3FFF - ROM 0 (12 KB/16 KB)
             // 0x4000-0x7FFF - ROM 1 (Banked) (16 KB)
-            0x1000..=0x7fff => self.rom.read(addr),
+            BusRegion::Rom => {
+                self.rom.write(addr, value);
+
+                // the MBC may have switched the ROM bank
+                self.map_generation = self.map_generation.wrapping_add(1);
+            }
 
             // 0x8000-0x9FFF - Graphics: VRAM (8 KB)
-            0x8000..=0x9fff => self.ppu.read(addr),
+            BusRegion::Vram => self.ppu.write(addr, value),
 
             // 0xA000-0xBFFF - External RAM (8 KB)
-            0xa000..=0xbfff => self.rom.read(addr),
+            BusRegion::ExtRam => {
+                if self.ext_ram_unmapped() {
+                    return;
+                }
+                // the first (accepted) write since the last save marks the
+                // battery backed RAM as dirty, stamping it with the current
+                // frame, writes dropped by the MBC (RAM disabled) are ignored
+                if self.rom.write_ram(addr, value)
+                    && !self.rom.battery_dirty()
+                    && self.rom.has_battery()
+                {
+                    let frame = self.ppu.frame_index();
+                    self.rom.set_battery_frame(Some(frame));
+                    (self.battery_callback)(BatteryEvent::Changed, frame);
+                }
+            }
 
             // 0xC000-0xCFFF - Working RAM 0 (4 KB)
-            0xc000..=0xcfff => self.ram[(addr & 0x0fff) as usize],
+            BusRegion::Wram0 => {
+                let index = (addr & 0x0fff) as usize;
+                self.ram[index] = value;
+                self.write_code(index);
+            }
 
             // 0xD000..=0xDFFF - Working RAM 1 (Banked) (4KB)
-            0xd000..=0xdfff => self.ram[(self.ram_offset + (addr & 0x0fff)) as usize],
+            BusRegion::WramX => {
+                let index = (self.ram_offset + (addr & 0x0fff)) as usize;
+                self.ram[index] = value;
+                self.write_code(index);
+            }
 
             // 0xE000..=0xFDFF - Working RAM Shadow
-            0xe000..=0xfdff => self.ram[(addr & 0x1fff) as usize],
+            BusRegion::Echo => {
+                let index = self.echo_index(addr);
+                self.ram[index] = value;
+                self.write_code(index);
+            }
 
             // 0xFE00-0xFE9F - Object attribute memory (OAM)
-            0xfe00..=0xfe9f => self.ppu.read(addr),
-
             // 0xFEA0-0xFEFF - Not Usable
-            0xfea0..=0xfeff => 0xff,
+            BusRegion::Oam => {
+                if addr <= 0xfe9f {
+                    self.ppu.write(addr, value)
+                }
+            }
+
+            // 0xFF00-0xFF7F - I/O registers
+            // 0xFF80-0xFFFE - High RAM (HRAM)
+            // 0xFFFF — IE: Interrupt enable
+            BusRegion::High => match addr {
+                0xff80..=0xfffe => {
+                    self.ppu.write(addr, value);
+                    self.write_code(RAM_SIZE_CGB + (addr - 0xff80) as usize);
+                }
+                _ => self.write_io(addr, value),
+            },
+
+            BusRegion::Device(index) => self.devices[index as usize].1.write(addr, value),
+        }
+    }
+
+    /// Obtains the bank mapped at the address for the purpose of caching
+    /// the code decoded from it (see [`crate::block::BlockCache`]), `None`
+    /// for the areas that can't be cached (eg: VRAM, cartridge RAM) or
+    /// while the reads may be intercepted (watchpoints, flat memory or
+    /// cheats).
+    pub fn code_bank(&self, addr: u16) -> Option<u16> {
+        if self.flat.is_some() || !self.watchpoints.is_empty() {
+            return None;
+        }
+        match self.bus_map[bus_page(addr)] {
+            BusRegion::Rom => {
+                if self.rom.has_cheats() {
+                    return None;
+                }
+                if self.boot_mapped(addr) {
+                    return Some(BOOT_BANK);
+                }
+                match addr {
+                    0x0000..=0x3fff => Some(0),
+                    _ => Some(self.rom.rom_bank()),
+                }
+            }
+            BusRegion::Wram0 => Some(0),
+            BusRegion::WramX => Some(self.ram_bank as u16),
+            BusRegion::High if (0xff80..=0xfffe).contains(&addr) => Some(0),
+            _ => None,
+        }
+    }
 
-            // 0xFF00 - Joypad input
-            0xff00 => self.pad.read(addr),
+    /// Marks the byte at the address as holding an opcode of the
+    /// cached code, so that writing it invalidates the cached code,
+    /// the bytes of the ROM are never written so they're not marked.
+    pub fn mark_code(&mut self, addr: u16) {
+        let index = match self.bus_map[bus_page(addr)] {
+            BusRegion::Wram0 => (addr & 0x0fff) as usize,
+            BusRegion::WramX => (self.ram_offset + (addr & 0x0fff)) as usize,
+            BusRegion::High if (0xff80..=0xfffe).contains(&addr) => {
+                RAM_SIZE_CGB + (addr - 0xff80) as usize
+            }
+            _ => return,
+        };
+        if self.code_marks.is_empty() {
+            self.code_marks = vec![false; RAM_SIZE_CGB + HRAM_SIZE];
+        }
+        self.code_marks[index] = true;
+    }
+
+    /// Invalidates all of the cached code, to be called whenever the
+    /// memory is changed without going through the bus (eg: loading
+    /// a state or attaching cheats).
+    pub fn invalidate_code(&mut self) {
+        self.code_generation = self.code_generation.wrapping_add(1);
+        self.map_generation = self.map_generation.wrapping_add(1);
+        self.code_marks.fill(false);
+    }
+
+    pub fn code_generation(&self) -> u32 {
+        self.code_generation
+    }
+
+    pub fn map_generation(&self) -> u32 {
+        self.map_generation
+    }
+
+    /// Invalidates the cached code in case the byte of the RAM at the
+    /// index (WRAM followed by the HRAM) holds an opcode of it.
+    #[inline(always)]
+    fn write_code(&mut self, index: usize) {
+        if matches!(self.code_marks.get(index), Some(true)) {
+            self.invalidate_code();
+        }
+    }
+
+    /// Obtains the index in the WRAM of an echo RAM address, taking
+    /// into account the WRAM bank selected for the 0xF000 range.
+    #[inline(always)]
+    fn echo_index(&self, addr: u16) -> usize {
+        match addr {
+            0xe000..=0xefff => (addr & 0x0fff) as usize,
+            _ => (self.ram_offset + (addr & 0x0fff)) as usize,
+        }
+    }
+
+    /// Reads from the not usable area (0xFEA0-0xFEFF), returning
+    /// 0xFF while the OAM is blocked by the PPU and otherwise zero on
+    /// the DMG (the OAM corruption caused by these reads is not
+    /// emulated) or the high nibble of the lower address byte twice
+    /// on the CGB (eg: 0xFEAx reads as 0xAA), as the later revisions.
+    fn read_unusable(&self, addr: u16) -> u8 {
+        if !self.open_bus || self.ppu.oam_blocked() {
+            return 0xff;
+        }
+        match self.mode {
+            GameBoyMode::Cgb => {
+                let nibble = (addr as u8) >> 4;
+                (nibble << 4) | nibble
+            }
+            _ => 0x00,
+        }
+    }
 
-            // 0xFF01-0xFF02 - Serial data transfer
-            0xff01..=0xff02 => self.serial.read(addr),
+    /// If the external RAM area is unmapped, for the cartridges with
+    /// no RAM and no other use of the area, in which case reads
+    /// return the open bus value (0xFF) and writes are ignored.
+    #[inline(always)]
+    fn ext_ram_unmapped(&self) -> bool {
+        self.open_bus
+            && !self.rom.has_ram()
+            && matches!(
+                self.rom.rom_type().mbc_type(),
+                MbcType::NoMbc | MbcType::Mbc1 | MbcType::Mbc5
+            )
+    }
+
+    /// Reads an I/O register by dispatching the access to the
+    /// component that registered it, the bits outside of the
+    /// register's read mask are always read as 1.
+    fn read_io(&self, addr: u16) -> u8 {
+        let register = match io_register(addr) {
+            Some(register) => register,
+            None => {
+                warnln!("Reading from unknown location 0x{:04x}", addr);
+                #[allow(unreachable_code)]
+                return 0xff;
+            }
+        };
+        if !register.readable() {
+            return 0xff;
+        }
+        let value = match register.component {
+            IoComponent::Mmu => self.read_system(addr),
+            IoComponent::Pad => self.pad.read(addr),
+            IoComponent::Serial => self.serial.read(addr),
+            IoComponent::Timer => self.timer.read(addr),
+            IoComponent::Apu => self.apu.read(addr),
+            IoComponent::Ppu => self.ppu.read(addr),
+            // the VRAM DMA registers are not available in DMG mode
+            IoComponent::Dma if addr == HDMA5_ADDR && self.mode != GameBoyMode::Cgb => 0xff,
+            IoComponent::Dma => self.dma.read(addr),
+        };
+        value | !register.read_mask
+    }
 
-            // 0xFF04-0xFF07 - Timer and divider
-            0xff04..=0xff07 => self.timer.read(addr),
+    /// Writes an I/O register by dispatching the access to the
+    /// component that registered it, with the value restricted
+    /// to the register's write mask.
+    fn write_io(&mut self, addr: u16, value: u8) {
+        let register = match io_register(addr) {
+            Some(register) => register,
+            None => {
+                warnln!("Writing to unknown location 0x{:04x}", addr);
+                return;
+            }
+        };
+        if !register.writable() {
+            return;
+        }
+        let value = value & register.write_mask;
+        match register.component {
+            IoComponent::Mmu => self.write_system(addr, value),
+            IoComponent::Pad => self.pad.write(addr, value),
+            IoComponent::Serial => self.serial.write(addr, value),
+            IoComponent::Timer => self.timer.write(addr, value),
+            IoComponent::Apu => self.apu.write(addr, value),
+            IoComponent::Ppu => self.ppu.write(addr, value),
+            IoComponent::Dma => self.dma.write(addr, value),
+        }
+    }
 
+    fn read_system(&self, addr: u16) -> u8 {
+        match addr {
             // 0xFF0F — IF: Interrupt flag
-            0xff0f =>
+            IF_ADDR =>
             {
                 #[allow(clippy::bool_to_int_with_if)]
                 (if self.ppu.int_vblank() { 0x01 } else { 0x00 }
                     | if self.ppu.int_stat() { 0x02 } else { 0x00 }
                     | if self.timer.int_tima() { 0x04 } else { 0x00 }
                     | if self.serial.int_serial() { 0x08 } else { 0x00 }
-                    | if self.pad.int_pad() { 0x10 } else { 0x00 }
-                    | 0xe0)
+                    | if self.pad.int_pad() { 0x10 } else { 0x00 })
             }
 
-            // 0xFF10-0xFF26 — Audio
-            // 0xFF10-0xFF26 — Wave pattern
-            0xff10..=0xff26 | 0xff30..=0xff3f => self.apu.read(addr),
-
-            // 0xFF40-0xFF45 - PPU registers
-            // 0xFF47-0xFF4B - PPU registers
-            0xff40..=0xff45 | 0xff47..=0xff4b => self.ppu.read(addr),
-
-            // 0xFF46 — DMA: OAM DMA source address & start
-            0xff46 => self.dma.read(addr),
-
             // 0xFF4C - KEY0: Compatibility flag (CGB only)
-            0xff4c => self.key0,
+            KEY0_ADDR => self.key0,
 
             // 0xFF4D - KEY1: Speed switching (CGB only)
-            0xff4d => (if self.switching { 0x01 } else { 0x00 }) | ((self.speed as u8) << 7) | 0x7e,
-
-            // 0xFF4F - VRAM Bank Select (CGB only)
-            0xff4f => self.ppu.read(addr),
+            KEY1_ADDR => (if self.switching { 0x01 } else { 0x00 }) | ((self.speed as u8) << 7),
 
             // 0xFF50 - BOOT: Boot active flag
-            0xff50 => u8::from(!self.boot_active),
-
-            // 0xFF51-0xFF55 - VRAM DMA (HDMA) (CGB only)
-            0xff51..=0xff55 => self.dma.read(addr),
-
-            // 0xFF56 - RP: Infrared communications port (CGB only)
-            0xff56 => 0xff,
-
-            // 0xFF68-0xFF6B - BG / OBJ Palettes (CGB only)
-            0xff68..=0xff6b => self.ppu.read(addr),
+            BOOT_ADDR => u8::from(!self.boot_active),
 
             // 0xFF70 - SVBK: WRAM bank (CGB only)
-            0xff70 => (self.ram_bank & 0x07) | 0xf8,
-
-            // 0xFF80-0xFFFE - High RAM (HRAM)
-            0xff80..=0xfffe => self.ppu.read(addr),
+            SVBK_ADDR => self.ram_bank,
 
             // 0xFFFF — IE: Interrupt enable
-            0xffff => self.ie,
+            IE_ADDR => self.ie,
 
-            addr => {
-                warnln!("Reading from unknown location 0x{:04x}", addr);
+            _ => {
+                warnln!("Reading from unknown MMU location 0x{:04x}", addr);
                 #[allow(unreachable_code)]
                 0xff
             }
         }
     }
 
-    pub fn write(&mut self, addr: u16, value: u8) {
+    fn write_system(&mut self, addr: u16, value: u8) {
         match addr {
-            // 0x0000-0x0FFF - BOOT (256 B) + ROM0 (4 KB/16 KB)
-            // 0x1000-0x3FFF - ROM 0 (12 KB/16 KB)
-            // 0x4000-0x7FFF - ROM 1 (Banked) (16 KB)
-            0x0000..=0x7fff => self.rom.write(addr, value),
-
-            // 0x8000-0x9FFF - Graphics: VRAM (8 KB)
-            0x8000..=0x9fff => self.ppu.write(addr, value),
-
-            // 0xA000-0xBFFF - External RAM (8 KB)
-            0xa000..=0xbfff => self.rom.write(addr, value),
-
-            // 0xC000-0xCFFF - Working RAM 0 (4 KB)
-            0xc000..=0xcfff => self.ram[(addr & 0x0fff) as usize] = value,
-
-            // 0xD000..=0xDFFF - Working RAM 1 (Banked) (4KB)
-            0xd000..=0xdfff => self.ram[(self.ram_offset + (addr & 0x0fff)) as usize] = value,
-
-            // 0xE000..=0xFDFF - Working RAM Shadow
-            0xe000..=0xfdff => self.ram[(addr & 0x1fff) as usize] = value,
-
-            // 0xFE00-0xFE9F - Object attribute memory (OAM)
-            0xfe00..=0xfe9f => self.ppu.write(addr, value),
-
-            // 0xFEA0-0xFEFF - Not Usable
-            0xfea0..=0xfeff => {}
-
-            // 0xFF00 - Joypad input
-            0xff00 => self.pad.write(addr, value),
-
-            // 0xFF01-0xFF02 - Serial data transfer
-            0xff01..=0xff02 => self.serial.write(addr, value),
-
-            // 0xFF04-0xFF07 - Timer and divider
-            0xff04..=0xff07 => self.timer.write(addr, value),
-
             // 0xFF0F — IF: Interrupt flag
-            0xff0f => {
+            IF_ADDR => {
                 self.ppu.set_int_vblank(value & 0x01 == 0x01);
                 self.ppu.set_int_stat(value & 0x02 == 0x02);
                 self.timer.set_int_tima(value & 0x04 == 0x04);
@@ -493,19 +1068,8 @@ impl Mmu {
                 self.pad.set_int_pad(value & 0x10 == 0x10);
             }
 
-            // 0xFF10-0xFF26 — Audio
-            // 0xFF10-0xFF26 — Wave pattern
-            0xff10..=0xff26 | 0xff30..=0xff3f => self.apu.write(addr, value),
-
-            // 0xFF40-0xFF45 - PPU registers
-            // 0xFF47-0xFF4B - PPU registers
-            0xff40..=0xff45 | 0xff47..=0xff4b => self.ppu.write(addr, value),
-
-            // 0xFF46 — DMA: OAM DMA source address & start
-            0xff46 => self.dma.write(addr, value),
-
             // 0xFF4C - KEY0: Compatibility flag (CGB only)
-            0xff4c => {
+            KEY0_ADDR => {
                 self.key0 = value;
                 if value == 0x04 {
                     self.ppu().set_dmg_compat(true);
@@ -513,43 +1077,47 @@ impl Mmu {
             }
 
             // 0xFF4D - KEY1: Speed switching (CGB only)
-            0xff4d => self.switching = value & 0x01 == 0x01,
-
-            // 0xFF4F - VRAM Bank Select (CGB only)
-            0xff4f => self.ppu.write(addr, value),
+            KEY1_ADDR => self.switching = value & 0x01 == 0x01,
 
             // 0xFF50 - BOOT: Boot active flag
-            0xff50 => self.boot_active = value == 0x00,
-
-            // 0xFF51-0xFF55 - VRAM DMA (HDMA) (CGB only)
-            0xff51..=0xff55 => self.dma.write(addr, value),
-
-            // 0xFF56 - RP: Infrared communications port (CGB only)
-            0xff56 => {}
-
-            // 0xFF68-0xFF6B - BG / OBJ Palettes (CGB only)
-            0xff68..=0xff6b => self.ppu.write(addr, value),
+            BOOT_ADDR => {
+                self.boot_active = value == 0x00;
+                self.map_generation = self.map_generation.wrapping_add(1);
+
+                // the palettes set by the boot ROM for a DMG game running
+                // in CGB mode are replaced by grayscale ones in case the
+                // colorization is disabled
+                if !self.boot_active && !self.auto_colorize && self.ppu.dmg_compat() {
+                    self.ppu.set_compat_palettes(&[CGB_COMPAT_GRAYSCALE; 3]);
+                }
+            }
 
             // 0xFF70 - SVBK: WRAM bank (CGB only)
-            0xff70 => {
+            SVBK_ADDR => {
                 let mut ram_bank = value & 0x07;
                 if ram_bank == 0x0 {
                     ram_bank = 0x1;
                 }
                 self.ram_bank = ram_bank;
                 self.ram_offset = self.ram_bank as u16 * 0x1000;
+                self.map_generation = self.map_generation.wrapping_add(1);
             }
 
-            // 0xFF80-0xFFFE - High RAM (HRAM)
-            0xff80..=0xfffe => self.ppu.write(addr, value),
-
             // 0xFFFF — IE: Interrupt enable
-            0xffff => self.ie = value,
+            IE_ADDR => self.ie = value,
 
-            addr => warnln!("Writing to unknown location 0x{:04x}", addr),
+            _ => warnln!("Writing to unknown MMU location 0x{:04x}", addr),
         }
     }
 
+    /// Obtains the current value of every mapped I/O register,
+    /// without changing the state of the system.
+    pub fn io_map(&self) -> Vec<(&'static IoRegister, u8)> {
+        io_registers()
+            .map(|register| (register, self.peek(register.addr)))
+            .collect()
+    }
+
     /// Reads a byte from a certain memory address, without the typical
     /// Game Boy verifications, allowing deep read of values.
     pub fn read_raw(&mut self, addr: u16) -> u8 {
@@ -604,15 +1172,175 @@ impl Mmu {
         }
     }
 
+    /// Reads a byte from a certain memory address without changing
+    /// the state of the system, suitable for inspection purposes.
+    pub fn peek(&self, addr: u16) -> u8 {
+        // restores the watchpoint hit state after the read so
+        // that inspection never triggers a watchpoint
+        let watch_hit = self.watch_hit.get();
+        let value = self.read(addr);
+        self.watch_hit.set(watch_hit);
+        value
+    }
+
+    /// Reads a sequence of bytes starting at the provided address
+    /// without changing the state of the system, wrapping around
+    /// at the end of the address space.
+    pub fn peek_many(&self, addr: u16, count: usize) -> Vec<u8> {
+        (0..count)
+            .map(|offset| self.peek(addr.wrapping_add(offset as u16)))
+            .collect()
+    }
+
+    /// Maps a pluggable component into the provided (page aligned)
+    /// range of the address space, taking precedence over the
+    /// built-in components and the previously mapped ones.
+    pub fn map_component(
+        &mut self,
+        start: u16,
+        end: u16,
+        component: Box<dyn BusComponent + Send>,
+    ) -> Result<(), Error> {
+        if self.devices.len() > u8::MAX as usize {
+            return Err(Error::CustomError(String::from(
+                "Too many components mapped",
+            )));
+        }
+        let range = BusRange::new(start, end, BusRegion::Device(self.devices.len() as u8));
+        if !range.aligned() {
+            return Err(Error::InvalidParameter(format!(
+                "Range not aligned to pages: 0x{start:04x}-0x{end:04x}"
+            )));
+        }
+        self.devices.push((range, component));
+        self.update_bus_map();
+        self.invalidate_code();
+        Ok(())
+    }
+
+    /// Unmaps the last component mapped at the provided address,
+    /// restoring the previous handler of its range.
+    pub fn unmap_component(&mut self, addr: u16) -> Option<Box<dyn BusComponent + Send>> {
+        let index = self
+            .devices
+            .iter()
+            .rposition(|(range, _)| range.contains(addr))?;
+        let (_, component) = self.devices.remove(index);
+        for (index, (range, _)) in self.devices.iter_mut().enumerate() {
+            range.region = BusRegion::Device(index as u8);
+        }
+        self.update_bus_map();
+        self.invalidate_code();
+        Some(component)
+    }
+
+    /// The ranges of the pluggable components currently mapped.
+    pub fn components(&self) -> Vec<BusRange> {
+        self.devices.iter().map(|(range, _)| *range).collect()
+    }
+
+    /// The region that handles the provided address.
+    pub fn bus_region(&self, addr: u16) -> BusRegion {
+        self.bus_map[bus_page(addr)]
+    }
+
+    fn update_bus_map(&mut self) {
+        self.bus_map = BUS_TABLE;
+        for (range, _) in &self.devices {
+            for page in bus_page(range.start)..=bus_page(range.end) {
+                self.bus_map[page] = range.region;
+            }
+        }
+    }
+
+    pub fn flat(&self) -> bool {
+        self.flat.is_some()
+    }
+
+    /// Enables (or disables) the flat memory mode, in which the
+    /// complete address space is plain (zeroed) RAM.
+    pub fn set_flat(&mut self, value: bool) {
+        self.flat = if value {
+            Some(vec![0x00; 0x10000].into_boxed_slice().try_into().unwrap())
+        } else {
+            None
+        };
+        self.invalidate_code();
+    }
+
+    pub fn watchpoints(&self) -> &Vec<Watchpoint> {
+        &self.watchpoints
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        if !self.watchpoints.contains(&watchpoint) {
+            self.watchpoints.push(watchpoint);
+        }
+    }
+
+    /// Removes the watchpoint that matches the provided one,
+    /// returning if a watchpoint has been removed.
+    pub fn remove_watchpoint(&mut self, watchpoint: &Watchpoint) -> bool {
+        let count = self.watchpoints.len();
+        self.watchpoints.retain(|value| value != watchpoint);
+        self.watchpoints.len() != count
+    }
+
+    pub fn clear_watchpoints(&mut self) {
+        self.watchpoints.clear();
+        self.watch_hit.set(None);
+    }
+
+    /// Obtains (and clears) the first watchpoint hit that occurred
+    /// since the last call to this method.
+    pub fn take_watch_hit(&mut self) -> Option<WatchHit> {
+        self.watch_hit.take()
+    }
+
+    #[cold]
+    fn check_watchpoints(&self, addr: u16, kind: WatchKind) {
+        if self.watch_hit.get().is_some() {
+            return;
+        }
+        if let Some(watchpoint) = self
+            .watchpoints
+            .iter()
+            .find(|watchpoint| watchpoint.kind.triggers(kind) && watchpoint.contains(addr))
+        {
+            self.watch_hit.set(Some(WatchHit {
+                addr,
+                kind,
+                watchpoint: *watchpoint,
+            }));
+        }
+    }
+
+    /// Searches the (inclusive) address range for the provided
+    /// pattern, returning the starting address of every match.
+    pub fn search(&self, pattern: &MemoryPattern, start: u16, end: u16) -> Vec<u16> {
+        if pattern.is_empty() || end < start {
+            return vec![];
+        }
+        let data = self.peek_many(start, (end - start) as usize + 1);
+        data.windows(pattern.len())
+            .enumerate()
+            .filter(|(_, window)| pattern.matches(window))
+            .map(|(index, _)| start + index as u16)
+            .collect()
+    }
+
     pub fn write_boot(&mut self, addr: u16, buffer: &[u8]) {
         self.boot[addr as usize..addr as usize + buffer.len()].clone_from_slice(buffer);
+        self.invalidate_code();
     }
 
     pub fn write_ram(&mut self, addr: u16, buffer: &[u8]) {
         self.ram[addr as usize..addr as usize + buffer.len()].clone_from_slice(buffer);
+        self.invalidate_code();
     }
 
     pub fn ram(&mut self) -> &mut Vec<u8> {
+        self.invalidate_code();
         &mut self.ram
     }
 
@@ -622,6 +1350,7 @@ impl Mmu {
 
     pub fn set_ram(&mut self, value: Vec<u8>) {
         self.ram = value;
+        self.invalidate_code();
     }
 
     pub fn rom(&mut self) -> &mut Cartridge {
@@ -634,6 +1363,7 @@ impl Mmu {
 
     pub fn set_rom(&mut self, rom: Cartridge) {
         self.rom = rom;
+        self.invalidate_code();
     }
 
     pub fn mode(&self) -> GameBoyMode {
@@ -649,6 +1379,19 @@ impl Mmu {
     }
 }
 
+impl ConfigListener for Mmu {
+    fn config_changed(&mut self, change: &ConfigChange) {
+        self.ppu.config_changed(change);
+        self.apu.config_changed(change);
+        match *change {
+            ConfigChange::Mode(value) => self.set_mode(value),
+            ConfigChange::AutoColorize(value) => self.set_auto_colorize(value),
+            ConfigChange::OpenBus(value) => self.set_open_bus(value),
+            _ => {}
+        }
+    }
+}
+
 impl Default for Mmu {
     fn default() -> Self {
         let mode = GameBoyMode::Dmg;
@@ -664,3 +1407,316 @@ impl Default for Mmu {
         Mmu::new(components, mode, gbc)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU8, Ordering};
+
+    use super::{BusComponent, MemoryPattern, Mmu, WatchKind, Watchpoint};
+    use crate::{
+        bus::BusRegion,
+        consts::{
+            HDMA1_ADDR, HDMA2_ADDR, HDMA3_ADDR, HDMA4_ADDR, HDMA5_ADDR, LCDC_ADDR, LY_ADDR,
+            P1_ADDR, SC_ADDR, SVBK_ADDR, WX_ADDR, WY_ADDR,
+        },
+        gb::{GameBoyMode, GameBoySpeed},
+        rom::{BatteryEvent, Cartridge},
+    };
+
+    #[test]
+    fn test_memory_pattern() {
+        let pattern = MemoryPattern::from_hex("3E ?? c9 1?").unwrap();
+        assert_eq!(pattern.bytes(), &[0x3e, 0x00, 0xc9, 0x10]);
+        assert_eq!(pattern.mask(), &[0xff, 0x00, 0xff, 0xf0]);
+        assert!(pattern.matches(&[0x3e, 0x42, 0xc9, 0x1a]));
+        assert!(!pattern.matches(&[0x3e, 0x42, 0xc9, 0x2a]));
+        assert!(!pattern.matches(&[0x3e, 0x42, 0xc9]));
+
+        let mut pattern = MemoryPattern::from_hex("ff00").unwrap();
+        pattern.apply_mask_hex("0f ff").unwrap();
+        assert!(pattern.matches(&[0x0f, 0x00]));
+        assert!(pattern.apply_mask_hex("0f").is_err());
+
+        assert!(MemoryPattern::from_hex("3e c").is_err());
+        assert!(MemoryPattern::from_hex("zz").is_err());
+        assert!(MemoryPattern::new(vec![0x00], Some(vec![])).is_err());
+    }
+
+    #[test]
+    fn test_search() {
+        let mut mmu = Mmu::default();
+        mmu.allocate_default();
+        mmu.write_many(0xc010, &[0xde, 0xad, 0xbe, 0xef]);
+        mmu.write_many(0xc100, &[0xde, 0xad, 0x00, 0xef]);
+
+        let pattern = MemoryPattern::from_hex("dead??ef").unwrap();
+        assert_eq!(mmu.search(&pattern, 0xc000, 0xdfff), vec![0xc010, 0xc100]);
+        assert_eq!(mmu.search(&pattern, 0xc011, 0xdfff), vec![0xc100]);
+        assert_eq!(mmu.search(&pattern, 0xc000, 0xc012), vec![]);
+        assert_eq!(mmu.peek_many(0xc010, 4), vec![0xde, 0xad, 0xbe, 0xef]);
+    }
+
+    #[test]
+    fn test_flat() {
+        let mut mmu = Mmu::default();
+        mmu.allocate_default();
+        mmu.write(0xc000, 0x12);
+        assert!(!mmu.flat());
+
+        mmu.set_flat(true);
+        assert!(mmu.flat());
+        assert_eq!(mmu.read(0xc000), 0x00);
+        mmu.write(0x0100, 0x3e);
+        mmu.write(0xff44, 0x42);
+        assert_eq!(mmu.read(0x0100), 0x3e);
+        assert_eq!(mmu.read(0xff44), 0x42);
+
+        mmu.set_flat(false);
+        assert!(!mmu.flat());
+        assert_eq!(mmu.read(0xc000), 0x12);
+    }
+
+    #[test]
+    fn test_map_component() {
+        struct Register(u8);
+
+        impl BusComponent for Register {
+            fn read(&self, _addr: u16) -> u8 {
+                self.0
+            }
+
+            fn write(&mut self, _addr: u16, value: u8) {
+                self.0 = value;
+            }
+        }
+
+        let mut mmu = Mmu::default();
+        mmu.allocate_default();
+        mmu.write(0xc000, 0x12);
+        assert_eq!(mmu.bus_region(0xc000), BusRegion::Wram0);
+
+        mmu.map_component(0xc000, 0xc0ff, Box::new(Register(0x34)))
+            .unwrap();
+        assert_eq!(mmu.bus_region(0xc0ff), BusRegion::Device(0));
+        assert_eq!(mmu.bus_region(0xc100), BusRegion::Wram0);
+        assert_eq!(mmu.read(0xc000), 0x34);
+        mmu.write(0xc042, 0x56);
+        assert_eq!(mmu.read(0xc0ff), 0x56);
+        assert_eq!(mmu.read(0xe000), 0x12);
+
+        mmu.map_component(0xa000, 0xbfff, Box::new(Register(0x78)))
+            .unwrap();
+        assert_eq!(mmu.read(0xbfff), 0x78);
+        assert!(mmu
+            .map_component(0xa000, 0xa07f, Box::new(Register(0x00)))
+            .is_err());
+
+        assert!(mmu.unmap_component(0xc000).is_some());
+        assert!(mmu.unmap_component(0xc000).is_none());
+        assert_eq!(mmu.read(0xc000), 0x12);
+        assert_eq!(mmu.bus_region(0xa000), BusRegion::Device(0));
+        assert_eq!(mmu.components().len(), 1);
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        let mut mmu = Mmu::default();
+        mmu.allocate_default();
+        mmu.add_watchpoint(Watchpoint::new(0xc000, 2, WatchKind::Write));
+        mmu.add_watchpoint(Watchpoint::new(0xc100, 1, WatchKind::Read));
+
+        mmu.read(0xc000);
+        mmu.write(0xc002, 0x12);
+        assert_eq!(mmu.take_watch_hit(), None);
+
+        mmu.write(0xc001, 0x12);
+        let hit = mmu.take_watch_hit().unwrap();
+        assert_eq!(hit.addr, 0xc001);
+        assert_eq!(hit.kind, WatchKind::Write);
+        assert_eq!(mmu.take_watch_hit(), None);
+
+        mmu.peek(0xc100);
+        assert_eq!(mmu.take_watch_hit(), None);
+        mmu.read(0xc100);
+        assert_eq!(mmu.take_watch_hit().unwrap().kind, WatchKind::Read);
+
+        assert!(mmu.remove_watchpoint(&Watchpoint::new(0xc100, 1, WatchKind::Read)));
+        assert!(!mmu.remove_watchpoint(&Watchpoint::new(0xc100, 1, WatchKind::Read)));
+        mmu.clear_watchpoints();
+        mmu.write(0xc000, 0x12);
+        assert_eq!(mmu.take_watch_hit(), None);
+    }
+
+    #[test]
+    fn test_io_registers() {
+        let mut mmu = Mmu::default();
+        mmu.allocate_default();
+
+        mmu.write(WY_ADDR, 0x10);
+        mmu.write(WX_ADDR, 0x27);
+        assert_eq!(mmu.ppu_i().wy(), 0x10);
+        assert_eq!(mmu.ppu_i().wx(), 0x27);
+        assert_eq!(mmu.read(WY_ADDR), 0x10);
+        assert_eq!(mmu.read(WX_ADDR), 0x27);
+
+        mmu.write(LY_ADDR, 0x42);
+        assert_eq!(mmu.read(LY_ADDR), 0x00);
+
+        mmu.write(P1_ADDR, 0x20);
+        assert_eq!(mmu.read(P1_ADDR) & 0xf0, 0xe0);
+        assert_eq!(mmu.read(SC_ADDR) & 0x7c, 0x7c);
+
+        mmu.write(SVBK_ADDR, 0xfa);
+        assert_eq!(mmu.read(SVBK_ADDR), 0xfa);
+        assert_eq!(mmu.read(0xff03), 0xff);
+
+        let io_map = mmu.io_map();
+        let (register, value) = io_map
+            .iter()
+            .find(|(register, _)| register.addr == WX_ADDR)
+            .unwrap();
+        assert_eq!(register.name, "WX");
+        assert_eq!(*value, 0x27);
+    }
+
+    #[test]
+    fn test_battery_events() {
+        static CHANGED: AtomicU8 = AtomicU8::new(0);
+        static SAVED: AtomicU8 = AtomicU8::new(0);
+
+        let mut data = vec![0; 0x8000];
+        data[0x0147] = 0x03;
+        data[0x0149] = 0x02;
+        let mut mmu = Mmu::default();
+        mmu.allocate_default();
+        mmu.set_rom(Cartridge::from_data(&data).unwrap());
+        mmu.set_battery_callback(|event, _| match event {
+            BatteryEvent::Changed => _ = CHANGED.fetch_add(1, Ordering::Relaxed),
+            BatteryEvent::Saved => _ = SAVED.fetch_add(1, Ordering::Relaxed),
+        });
+
+        // writes with the RAM disabled are dropped by the MBC
+        mmu.write(0xa010, 0x41);
+        assert!(!mmu.rom_i().battery_dirty());
+        assert!(!mmu.rom_i().ram_dirty());
+        assert_eq!(CHANGED.load(Ordering::Relaxed), 0);
+
+        mmu.write(0x0000, 0x0a);
+        assert!(!mmu.rom_i().battery_dirty());
+        mmu.write(0xa010, 0x42);
+        mmu.write(0xa011, 0x43);
+        assert_eq!(mmu.rom_i().battery_frame(), Some(0));
+        assert_eq!(CHANGED.load(Ordering::Relaxed), 1);
+
+        mmu.mark_battery_saved();
+        mmu.mark_battery_saved();
+        assert!(!mmu.rom_i().battery_dirty());
+        assert_eq!(SAVED.load(Ordering::Relaxed), 1);
+
+        mmu.write(0xa010, 0x44);
+        assert!(mmu.rom_i().battery_dirty());
+        assert_eq!(CHANGED.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_hdma_hblank() {
+        let mut mmu = Mmu::default();
+        mmu.set_mode(GameBoyMode::Cgb);
+        mmu.allocate_cgb();
+        let data: Vec<u8> = (0..0x30).collect();
+        mmu.write_many(0xc000, &data);
+
+        // starting the transfer with the LCD off transfers
+        // a single block right away
+        mmu.write(LCDC_ADDR, 0x80);
+        mmu.write(LCDC_ADDR, 0x00);
+        mmu.write(HDMA1_ADDR, 0xc0);
+        mmu.write(HDMA2_ADDR, 0x00);
+        mmu.write(HDMA3_ADDR, 0x80);
+        mmu.write(HDMA4_ADDR, 0x00);
+        mmu.write(HDMA5_ADDR, 0x82);
+        mmu.clock_dma(4);
+        mmu.clock_dma(4);
+        assert_eq!(
+            mmu.read_many(0x8000, 0x20),
+            [&data[..0x10], &[0x00; 0x10]].concat()
+        );
+        assert_eq!(mmu.read(HDMA5_ADDR), 0x01);
+        assert_eq!(mmu.dma().take_stall_cycles(), 32);
+        assert_eq!(mmu.dma().take_stall_cycles(), 0);
+
+        // the next block is only transferred on the next HBlank
+        // with the stall doubling (in cycles) in double speed
+        mmu.set_speed(GameBoySpeed::Double);
+        mmu.write(LCDC_ADDR, 0x80);
+        mmu.ppu().clock(204);
+        mmu.clock_dma(4);
+        mmu.ppu().clock(80);
+        mmu.clock_dma(4);
+        assert_eq!(mmu.read(HDMA5_ADDR), 0x01);
+        mmu.ppu().clock(172);
+        mmu.clock_dma(4);
+        mmu.clock_dma(4);
+        assert_eq!(mmu.read_many(0x8010, 0x10), &data[0x10..0x20]);
+        assert_eq!(mmu.read(HDMA5_ADDR), 0x00);
+        assert_eq!(mmu.dma().take_stall_cycles(), 64);
+
+        // stopping the transfer keeps the remaining length
+        mmu.write(HDMA5_ADDR, 0x00);
+        assert!(!mmu.dma().active_hdma());
+        assert_eq!(mmu.read(HDMA5_ADDR), 0x80);
+    }
+
+    #[test]
+    fn test_gdma() {
+        let mut mmu = Mmu::default();
+        mmu.set_mode(GameBoyMode::Cgb);
+        mmu.allocate_cgb();
+        let data: Vec<u8> = (0..0x40).collect();
+        mmu.write_many(0xc000, &data);
+
+        // the transfer of 4 blocks stalls the CPU for 4 * 32 cycles,
+        // taken in chunks so that the other components keep running
+        mmu.write(HDMA1_ADDR, 0xc0);
+        mmu.write(HDMA2_ADDR, 0x00);
+        mmu.write(HDMA3_ADDR, 0x80);
+        mmu.write(HDMA4_ADDR, 0x00);
+        mmu.write(HDMA5_ADDR, 0x03);
+        mmu.clock_dma(4);
+        assert_eq!(mmu.read_many(0x8000, 0x40), data);
+        assert_eq!(mmu.read(HDMA5_ADDR), 0xff);
+        assert_eq!(mmu.dma().source(), 0xc040);
+        assert_eq!(mmu.dma().destination(), 0x8040);
+        assert_eq!(mmu.dma().take_stall_cycles(), 64);
+        assert_eq!(mmu.dma().take_stall_cycles(), 64);
+        assert_eq!(mmu.dma().take_stall_cycles(), 0);
+
+        // the stall doubles in double speed mode, with the VRAM
+        // (an invalid source) being read as 0xFF
+        mmu.set_speed(GameBoySpeed::Double);
+        mmu.write(HDMA1_ADDR, 0x80);
+        mmu.write(HDMA2_ADDR, 0x00);
+        mmu.write(HDMA3_ADDR, 0x81);
+        mmu.write(HDMA4_ADDR, 0x00);
+        mmu.write(HDMA5_ADDR, 0x00);
+        mmu.clock_dma(4);
+        assert_eq!(mmu.read_many(0x8100, 0x10), [0xff; 0x10]);
+        assert_eq!(mmu.dma().stall_cycles(), 64);
+
+        // the transfer stops at the end of the VRAM
+        mmu.dma().set_stall_cycles(0);
+        mmu.write(HDMA1_ADDR, 0xc0);
+        mmu.write(HDMA2_ADDR, 0x00);
+        mmu.write(HDMA3_ADDR, 0x9f);
+        mmu.write(HDMA4_ADDR, 0xf0);
+        mmu.write(HDMA5_ADDR, 0x01);
+        mmu.clock_dma(4);
+        assert_eq!(mmu.read_many(0x9ff0, 0x10), &data[..0x10]);
+        assert_eq!(mmu.dma().destination(), 0xa000);
+        assert_eq!(mmu.dma().stall_cycles(), 64);
+
+        // the VRAM DMA registers are not available in DMG mode
+        mmu.set_mode(GameBoyMode::Dmg);
+        assert_eq!(mmu.read(HDMA5_ADDR), 0xff);
+    }
+}
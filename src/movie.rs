@@ -0,0 +1,296 @@
+//! Input movies, recording the pad inputs of every frame so that
+//! a run can be deterministically replayed (eg: TAS verification).
+//!
+//! A movie starts from a save state (BOS) of the system taken when
+//! the recording started, followed by the mask of the pad keys (see
+//! [`crate::pad::PAD_KEYS`]) of every movie frame. A movie frame ends
+//! with each completed PPU frame or, while the LCD is off, after the
+//! equivalent time of a frame, with the inputs being latched only at
+//! the frame boundaries so that the playback matches the recording.
+//!
+//! The binary format is (little endian):
+//!
+//! | Field     | Size      | Description                                    |
+//! |-----------|-----------|------------------------------------------------|
+//! | Magic     | 4         | `BOYM`                                         |
+//! | Version   | 1         | Version of the format, currently `1`           |
+//! | Title     | 1 + N     | Length and title (UTF-8) of the ROM            |
+//! | ROM CRC32 | 4         | Fingerprint of the ROM the movie was made with |
+//! | Rerecords | 4         | Number of times the recording was rewound      |
+//! | State     | 4 + N     | Length and initial save state (BOS)            |
+//! | Inputs    | 4 + N     | Number of frames and the pad keys of each one  |
+
+use std::{
+    fmt::{self, Display, Formatter},
+    io::Cursor,
+};
+
+use boytacean_common::{
+    data::{read_bytes, read_u32, read_u8, write_bytes, write_u32, write_u8},
+    error::Error,
+};
+
+use crate::gb::GameBoy;
+
+/// The magic string at the start of a movie file.
+pub const MOVIE_MAGIC: &[u8; 4] = b"BOYM";
+
+/// The current version of the movie format.
+pub const MOVIE_VERSION: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MovieMode {
+    /// The inputs of the frontend are latched at the frame
+    /// boundaries and added to the movie.
+    Recording,
+
+    /// The inputs of the movie are applied at the frame boundaries,
+    /// ignoring the inputs of the frontend.
+    Playing,
+
+    /// The playback reached the end of the movie, the inputs of the
+    /// frontend are applied again.
+    Finished,
+}
+
+impl MovieMode {
+    pub fn description(&self) -> &'static str {
+        match self {
+            MovieMode::Recording => "Recording",
+            MovieMode::Playing => "Playing",
+            MovieMode::Finished => "Finished",
+        }
+    }
+}
+
+impl Display for MovieMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Movie {
+    /// The title of the ROM the movie was recorded with.
+    pub title: String,
+
+    /// The fingerprint (CRC32) of the ROM the movie was recorded
+    /// with, verified before playback.
+    pub rom_crc32: u32,
+
+    /// The number of times the recording was rewound (re-recorded).
+    pub rerecords: u32,
+
+    /// The save state (BOS) of the system when the recording started.
+    pub state: Vec<u8>,
+
+    /// The mask of the pad keys pressed in each of the frames.
+    pub inputs: Vec<u8>,
+}
+
+impl Movie {
+    pub fn frames(&self) -> u32 {
+        self.inputs.len() as u32
+    }
+
+    pub fn from_data(data: &[u8]) -> Result<Self, Error> {
+        let mut cursor = Cursor::new(data);
+        if read_bytes(&mut cursor, 4)? != MOVIE_MAGIC {
+            return Err(Error::CustomError(String::from("Invalid movie magic")));
+        }
+        let version = read_u8(&mut cursor)?;
+        if version != MOVIE_VERSION {
+            return Err(Error::CustomError(format!(
+                "Unsupported movie version: {version}"
+            )));
+        }
+        let title_length = read_u8(&mut cursor)? as usize;
+        let title = String::from_utf8(read_bytes(&mut cursor, title_length)?)?;
+        let rom_crc32 = read_u32(&mut cursor)?;
+        let rerecords = read_u32(&mut cursor)?;
+        let state_length = read_u32(&mut cursor)? as usize;
+        let state = read_bytes(&mut cursor, state_length)?;
+        let frames = read_u32(&mut cursor)? as usize;
+        let inputs = read_bytes(&mut cursor, frames)?;
+        Ok(Self {
+            title,
+            rom_crc32,
+            rerecords,
+            state,
+            inputs,
+        })
+    }
+
+    pub fn to_data(&self) -> Result<Vec<u8>, Error> {
+        let mut cursor = Cursor::new(vec![]);
+        let title = &self.title.as_bytes()[..self.title.len().min(u8::MAX as usize)];
+        write_bytes(&mut cursor, MOVIE_MAGIC)?;
+        write_u8(&mut cursor, MOVIE_VERSION)?;
+        write_u8(&mut cursor, title.len() as u8)?;
+        write_bytes(&mut cursor, title)?;
+        write_u32(&mut cursor, self.rom_crc32)?;
+        write_u32(&mut cursor, self.rerecords)?;
+        write_u32(&mut cursor, self.state.len() as u32)?;
+        write_bytes(&mut cursor, &self.state)?;
+        write_u32(&mut cursor, self.inputs.len() as u32)?;
+        write_bytes(&mut cursor, &self.inputs)?;
+        Ok(cursor.into_inner())
+    }
+}
+
+/// A movie being recorded or played, keeping track of the
+/// current movie frame.
+pub struct MovieSession {
+    movie: Movie,
+    mode: MovieMode,
+
+    /// The index of the current movie frame.
+    frame: u32,
+
+    /// The number of cycles since the last frame boundary, used
+    /// to end a movie frame while the LCD is off.
+    cycles: u32,
+
+    /// The keys pressed by the frontend, to be latched at the next
+    /// frame boundary (recording) or once the playback finishes.
+    keys: u8,
+}
+
+impl MovieSession {
+    pub fn new(movie: Movie, mode: MovieMode, keys: u8) -> Self {
+        Self {
+            movie,
+            mode,
+            frame: 0,
+            cycles: 0,
+            keys,
+        }
+    }
+
+    pub fn movie(&self) -> &Movie {
+        &self.movie
+    }
+
+    pub fn into_movie(self) -> Movie {
+        self.movie
+    }
+
+    pub fn mode(&self) -> MovieMode {
+        self.mode
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn keys(&self) -> u8 {
+        self.keys
+    }
+
+    pub fn set_keys(&mut self, value: u8) {
+        self.keys = value;
+    }
+
+    /// The keys to be applied for the current movie frame.
+    pub fn frame_keys(&self) -> u8 {
+        match self.mode {
+            MovieMode::Recording | MovieMode::Playing => self
+                .movie
+                .inputs
+                .get(self.frame as usize)
+                .copied()
+                .unwrap_or(self.keys),
+            MovieMode::Finished => self.keys,
+        }
+    }
+
+    /// Advances the provided number of cycles, returning the keys
+    /// to be applied in case a new movie frame has started.
+    pub fn clock(&mut self, cycles: u32, frame_end: bool) -> Option<u8> {
+        if self.mode == MovieMode::Finished {
+            return None;
+        }
+        self.cycles += cycles;
+        if !frame_end && self.cycles < GameBoy::LCD_CYCLES {
+            return None;
+        }
+        self.cycles = 0;
+        self.frame += 1;
+        match self.mode {
+            MovieMode::Recording => self.movie.inputs.push(self.keys),
+            MovieMode::Playing => {
+                if self.frame >= self.movie.frames() {
+                    self.mode = MovieMode::Finished;
+                }
+            }
+            MovieMode::Finished => (),
+        }
+        Some(self.frame_keys())
+    }
+
+    /// Goes back the provided number of frames in the recording
+    /// (re-record), discarding the inputs of those frames.
+    pub fn rewind(&mut self, n_frames: u32) {
+        if self.mode != MovieMode::Recording {
+            return;
+        }
+        self.frame = self.frame.saturating_sub(n_frames);
+        self.movie.inputs.truncate(self.frame as usize + 1);
+        self.movie.rerecords += 1;
+        self.cycles = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Movie, MovieMode, MovieSession};
+
+    use crate::gb::GameBoy;
+
+    #[test]
+    fn test_movie_data() {
+        let movie = Movie {
+            title: String::from("POCKET"),
+            rom_crc32: 0x12345678,
+            rerecords: 3,
+            state: vec![0x01, 0x02, 0x03],
+            inputs: vec![0x00, 0x40, 0x40, 0x00],
+        };
+        let data = movie.to_data().unwrap();
+        assert_eq!(&data[0..5], b"BOYM\x01");
+        assert_eq!(Movie::from_data(&data).unwrap(), movie);
+        assert!(Movie::from_data(&data[..data.len() - 1]).is_err());
+        assert!(Movie::from_data(b"BOYX").is_err());
+    }
+
+    #[test]
+    fn test_movie_session() {
+        let mut session = MovieSession::new(
+            Movie {
+                inputs: vec![0x01],
+                ..Default::default()
+            },
+            MovieMode::Recording,
+            0x01,
+        );
+        session.set_keys(0x02);
+        assert_eq!(session.clock(4, false), None);
+        assert_eq!(session.clock(4, true), Some(0x02));
+        assert_eq!(session.clock(GameBoy::LCD_CYCLES - 1, false), None);
+        assert_eq!(session.clock(1, false), Some(0x02));
+        assert_eq!(session.movie().inputs, vec![0x01, 0x02, 0x02]);
+
+        session.rewind(1);
+        assert_eq!(session.frame(), 1);
+        assert_eq!(session.movie().inputs, vec![0x01, 0x02]);
+        assert_eq!(session.movie().rerecords, 1);
+
+        let mut session = MovieSession::new(session.into_movie(), MovieMode::Playing, 0x80);
+        assert_eq!(session.frame_keys(), 0x01);
+        assert_eq!(session.clock(4, true), Some(0x02));
+        assert_eq!(session.mode(), MovieMode::Playing);
+        assert_eq!(session.clock(4, true), Some(0x80));
+        assert_eq!(session.mode(), MovieMode::Finished);
+        assert_eq!(session.clock(4, true), None);
+    }
+}
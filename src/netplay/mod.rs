@@ -0,0 +1,10 @@
+//! Netplay support, allowing two peers to play the same game over
+//! the network by exchanging their pad inputs for every frame.
+//!
+//! Both peers run the emulation of the same system (starting from the
+//! state sent by the host) with the inputs of both players merged, see
+//! [`session`] for the synchronization modes (lockstep and rollback)
+//! and [`transport`] for the transports available to exchange messages.
+
+pub mod session;
+pub mod transport;
@@ -0,0 +1,992 @@
+//! Netplay session, synchronizing the pad inputs of both peers.
+//!
+//! The inputs of the local player are scheduled `input_delay` frames
+//! in the future and sent to the peer, with the emulated pad using the
+//! inputs of both players merged. Two synchronization modes are
+//! available, see [`SyncMode`] for more details.
+//!
+//! Spectators can be attached to any of the players, receiving the
+//! confirmed (merged) inputs of every frame in read-only mode, see
+//! [`NetplayRole`] for more details.
+//!
+//! As the hosts of the peers never run at exactly the same rate (timer
+//! precision, display refresh), the players periodically exchange their
+//! frame counts and wall-clock times, negotiating a small adjustment of
+//! the emulation speed (see [`NetplaySession::speed`]) that keeps them
+//! from slowly diverging into constant stalls.
+
+use std::{
+    collections::{btree_map::Entry, BTreeMap, VecDeque},
+    fmt::{self, Display, Formatter},
+    io::Cursor,
+    thread,
+    time::{Duration, Instant},
+};
+
+use boytacean_common::{
+    data::{read_bytes, read_u32, read_u8, write_bytes, write_u32, write_u8},
+    error::Error,
+};
+
+use crate::{
+    gb::GameBoy,
+    netplay::transport::NetplayTransport,
+    state::{FromGbOptions, SaveStateFormat, StateManager, ToGbOptions},
+};
+
+/// The strategy used to keep the emulation of both peers in sync.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyncMode {
+    /// Each frame is only emulated once the inputs of both peers
+    /// for it are known, stalling while waiting for the peer.
+    Lockstep,
+
+    /// Frames are emulated right away predicting the inputs of the
+    /// peer (repeating its last known input), in case the prediction
+    /// turns out to be wrong the system is rolled back to a previous
+    /// state and the frames are re-simulated with the correct inputs.
+    #[default]
+    Rollback,
+}
+
+impl SyncMode {
+    pub fn description(&self) -> &'static str {
+        match self {
+            SyncMode::Lockstep => "Lockstep",
+            SyncMode::Rollback => "Rollback",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "lockstep" => Ok(SyncMode::Lockstep),
+            "rollback" => Ok(SyncMode::Rollback),
+            _ => Err(Error::CustomError(format!("Invalid sync mode: {value}"))),
+        }
+    }
+}
+
+impl Display for SyncMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// The role of the local side of a netplay session.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NetplayRole {
+    /// One of the two players, providing inputs to the session.
+    #[default]
+    Player,
+
+    /// A read-only client that emulates the session using the
+    /// confirmed inputs streamed by one of the players, without
+    /// providing any input (and without any rollback).
+    Spectator,
+}
+
+impl NetplayRole {
+    pub fn description(&self) -> &'static str {
+        match self {
+            NetplayRole::Player => "Player",
+            NetplayRole::Spectator => "Spectator",
+        }
+    }
+}
+
+impl Display for NetplayRole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NetplayConfig {
+    pub sync_mode: SyncMode,
+
+    /// The number of frames by which the local inputs are delayed,
+    /// giving them time to reach the peer before being needed.
+    pub input_delay: u32,
+
+    /// The maximum number of frames that can be emulated ahead of
+    /// the last confirmed input of the peer (rollback mode only),
+    /// the emulation stalls once this limit has been reached.
+    pub max_rollback: u32,
+
+    /// The time to wait for the initial state of the host
+    /// when joining a session.
+    pub timeout: Duration,
+
+    /// The number of frames between each exchange of the clocks
+    /// of the peers (drift compensation), zero to disable it.
+    pub clock_interval: u32,
+
+    /// The maximum adjustment of the emulation speed used to
+    /// compensate the drift, in parts per million.
+    pub max_speed_adjust: u32,
+}
+
+impl Default for NetplayConfig {
+    fn default() -> Self {
+        Self {
+            sync_mode: SyncMode::default(),
+            input_delay: 2,
+            max_rollback: 8,
+            timeout: Duration::from_secs(10),
+            clock_interval: 120,
+            max_speed_adjust: 3000,
+        }
+    }
+}
+
+/// Message exchanged between the peers of a netplay session.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NetplayMessage {
+    /// The (save) state of the system to be used as the starting
+    /// point of the session, sent by the host.
+    State(Vec<u8>),
+
+    /// The mask of the keys pressed by the peer's player in the
+    /// provided frame (see [`crate::pad::PAD_KEYS`]), when sent to
+    /// a spectator the keys of both players are merged.
+    Input { frame: u32, keys: u8 },
+
+    /// The (save) state of the system at the start of the provided
+    /// frame, sent to a spectator as its starting point.
+    Snapshot { frame: u32, state: Vec<u8> },
+
+    /// The clock of the peer, with its current frame, the wall-clock
+    /// time (in milliseconds) elapsed since the start of its session
+    /// and the number of frames it's running ahead of the other peer.
+    Clock {
+        frame: u32,
+        elapsed: u32,
+        advantage: i32,
+    },
+}
+
+impl NetplayMessage {
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        let mut cursor = Cursor::new(vec![]);
+        match self {
+            NetplayMessage::State(state) => {
+                write_u8(&mut cursor, 0x01)?;
+                write_bytes(&mut cursor, state)?;
+            }
+            NetplayMessage::Input { frame, keys } => {
+                write_u8(&mut cursor, 0x02)?;
+                write_u32(&mut cursor, *frame)?;
+                write_u8(&mut cursor, *keys)?;
+            }
+            NetplayMessage::Snapshot { frame, state } => {
+                write_u8(&mut cursor, 0x03)?;
+                write_u32(&mut cursor, *frame)?;
+                write_bytes(&mut cursor, state)?;
+            }
+            NetplayMessage::Clock {
+                frame,
+                elapsed,
+                advantage,
+            } => {
+                write_u8(&mut cursor, 0x04)?;
+                write_u32(&mut cursor, *frame)?;
+                write_u32(&mut cursor, *elapsed)?;
+                write_u32(&mut cursor, *advantage as u32)?;
+            }
+        }
+        Ok(cursor.into_inner())
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        let mut cursor = Cursor::new(data);
+        match read_u8(&mut cursor)? {
+            0x01 => Ok(NetplayMessage::State(read_bytes(
+                &mut cursor,
+                data.len() - 1,
+            )?)),
+            0x02 => Ok(NetplayMessage::Input {
+                frame: read_u32(&mut cursor)?,
+                keys: read_u8(&mut cursor)?,
+            }),
+            0x03 if data.len() >= 5 => Ok(NetplayMessage::Snapshot {
+                frame: read_u32(&mut cursor)?,
+                state: read_bytes(&mut cursor, data.len() - 5)?,
+            }),
+            0x04 => Ok(NetplayMessage::Clock {
+                frame: read_u32(&mut cursor)?,
+                elapsed: read_u32(&mut cursor)?,
+                advantage: read_u32(&mut cursor)? as i32,
+            }),
+            kind => Err(Error::DataError(format!(
+                "Invalid netplay message: 0x{kind:02x}"
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NetplayStats {
+    /// The number of frames emulated (excluding re-simulations).
+    pub frames: u32,
+
+    /// The number of rollbacks caused by mispredicted inputs and the
+    /// total number of frames that have been re-simulated by them.
+    pub rollbacks: u32,
+    pub rollback_frames: u32,
+
+    /// The number of times the emulation stalled waiting
+    /// for the inputs of the peer.
+    pub stalls: u32,
+
+    /// The number of times the speed has been adjusted
+    /// to compensate the clock drift between the peers.
+    pub clock_syncs: u32,
+}
+
+impl Display for NetplayStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} frames, {} rollbacks ({} frames), {} stalls",
+            self.frames, self.rollbacks, self.rollback_frames, self.stalls
+        )
+    }
+}
+
+/// Sample of the clock of one of the peers, see [`NetplayMessage::Clock`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct ClockSample {
+    frame: u32,
+    elapsed: u32,
+    advantage: i32,
+}
+
+pub struct NetplaySession {
+    config: NetplayConfig,
+    role: NetplayRole,
+    transport: Box<dyn NetplayTransport>,
+
+    /// The transports of the attached spectators together with
+    /// the next frame whose inputs are to be sent to each of them.
+    spectators: Vec<(Box<dyn NetplayTransport>, u32)>,
+
+    /// The index of the next frame to be emulated.
+    frame: u32,
+
+    /// The inputs of the local player and the (confirmed) ones
+    /// of the peer, indexed by frame.
+    local_inputs: BTreeMap<u32, u8>,
+    remote_inputs: BTreeMap<u32, u8>,
+
+    /// The inputs of the peer that have been predicted for the frames
+    /// already emulated, pending confirmation by the peer.
+    predicted_inputs: BTreeMap<u32, u8>,
+
+    /// The last confirmed frame of the peer and its input, used
+    /// as the prediction for the following frames.
+    remote_frame: Option<u32>,
+    remote_keys: u8,
+
+    /// Ring of the save states taken at the start of the most
+    /// recent frames (rollback mode only).
+    states: VecDeque<(u32, Vec<u8>)>,
+
+    /// The instant the first frame was emulated, the reference
+    /// of the wall-clock time exchanged with the peer.
+    start: Option<Instant>,
+
+    /// The last two clock samples of the local and remote peers,
+    /// the most recent one being the last element.
+    local_clocks: [Option<ClockSample>; 2],
+    remote_clocks: [Option<ClockSample>; 2],
+
+    /// The accumulated speed adjustment compensating the difference
+    /// in the rates of both hosts, and the current speed (ratio)
+    /// that also corrects the frame offset between them.
+    trim: f64,
+    speed: f64,
+
+    /// The number of frames this peer is ahead of the other one,
+    /// as of the last clock exchange.
+    drift: f64,
+
+    stats: NetplayStats,
+}
+
+impl NetplaySession {
+    pub fn new(transport: Box<dyn NetplayTransport>, config: NetplayConfig) -> Self {
+        let mut session = Self {
+            config,
+            role: NetplayRole::Player,
+            transport,
+            spectators: vec![],
+            frame: 0,
+            local_inputs: BTreeMap::new(),
+            remote_inputs: BTreeMap::new(),
+            predicted_inputs: BTreeMap::new(),
+            remote_frame: None,
+            remote_keys: 0,
+            states: VecDeque::with_capacity(config.max_rollback as usize + 1),
+            start: None,
+            local_clocks: [None; 2],
+            remote_clocks: [None; 2],
+            trim: 0.0,
+            speed: 1.0,
+            drift: 0.0,
+            stats: NetplayStats::default(),
+        };
+
+        // the frames before the input delay has elapsed have no
+        // input from any of the players (nothing pressed)
+        for frame in 0..config.input_delay {
+            session.local_inputs.insert(frame, 0);
+            session.remote_inputs.insert(frame, 0);
+            session.remote_frame = Some(frame);
+        }
+
+        session
+    }
+
+    /// Starts the session as the host, sending the current state
+    /// of the system to the peer as the starting point.
+    pub fn host(&mut self, gb: &mut GameBoy) -> Result<(), Error> {
+        let state = Self::save_state(gb)?;
+        self.send(&NetplayMessage::State(state))
+    }
+
+    /// Joins the session of the host, waiting (blocking) for the
+    /// initial state of the system sent by the host and loading it.
+    pub fn join(&mut self, gb: &mut GameBoy) -> Result<(), Error> {
+        let start = Instant::now();
+        loop {
+            match self.receive()? {
+                Some(NetplayMessage::State(state)) => return Self::load_state(gb, &state),
+                Some(message) => {
+                    self.handle(message);
+                }
+                None if start.elapsed() >= self.config.timeout => {
+                    return Err(Error::CustomError(String::from(
+                        "Timeout waiting for the netplay host state",
+                    )))
+                }
+                None => thread::sleep(Duration::from_millis(1)),
+            }
+        }
+    }
+
+    /// Joins the session as a spectator of the player at the other
+    /// end of the transport, waiting (blocking) for the snapshot
+    /// of the system sent by the player and loading it.
+    pub fn spectate(&mut self, gb: &mut GameBoy) -> Result<(), Error> {
+        self.role = NetplayRole::Spectator;
+        self.local_inputs.clear();
+        self.remote_inputs.clear();
+        self.remote_frame = None;
+        let start = Instant::now();
+        loop {
+            match self.receive()? {
+                Some(NetplayMessage::Snapshot { frame, state }) => {
+                    self.frame = frame;
+                    return Self::load_state(gb, &state);
+                }
+                Some(message) => {
+                    self.handle(message);
+                }
+                None if start.elapsed() >= self.config.timeout => {
+                    return Err(Error::CustomError(String::from(
+                        "Timeout waiting for the netplay snapshot",
+                    )))
+                }
+                None => thread::sleep(Duration::from_millis(1)),
+            }
+        }
+    }
+
+    /// Attaches a spectator to the session, sending it the snapshot
+    /// of the most recent frame for which the inputs of all the
+    /// previous frames are confirmed, the inputs from that frame on
+    /// are then streamed to the spectator as they get confirmed.
+    pub fn add_spectator(
+        &mut self,
+        gb: &mut GameBoy,
+        mut transport: Box<dyn NetplayTransport>,
+    ) -> Result<(), Error> {
+        if self.role == NetplayRole::Spectator {
+            return Err(Error::CustomError(String::from(
+                "Spectators can't be attached to a spectator",
+            )));
+        }
+        let frame = self.frame.min(self.confirmed_frame());
+        let state = if frame == self.frame {
+            Self::save_state(gb)?
+        } else {
+            self.states
+                .iter()
+                .find(|(state_frame, _)| *state_frame == frame)
+                .map(|(_, state)| state.clone())
+                .ok_or(Error::CustomError(format!(
+                    "No state available for frame {frame}"
+                )))?
+        };
+        transport.send(&NetplayMessage::Snapshot { frame, state }.encode()?)?;
+        self.spectators.push((transport, frame));
+        self.update_spectators();
+        Ok(())
+    }
+
+    /// Advances the emulation by one frame using the provided keys
+    /// mask as the input of the local player.
+    ///
+    /// Returns `false` in case the emulation stalled waiting for the
+    /// inputs of the peer, meaning that the same (local) input should
+    /// be provided again on the next call.
+    ///
+    /// For a spectator the keys are ignored, with the frame being
+    /// emulated with the confirmed inputs received from the player.
+    #[allow(clippy::manual_is_multiple_of)]
+    pub fn advance(&mut self, gb: &mut GameBoy, keys: u8) -> Result<bool, Error> {
+        if self.role == NetplayRole::Spectator {
+            return self.advance_spectator(gb);
+        }
+
+        // schedules the local input for the future frame (according
+        // to the input delay), notifying the peer about it
+        let input_frame = self.frame + self.config.input_delay;
+        if let Entry::Vacant(entry) = self.local_inputs.entry(input_frame) {
+            entry.insert(keys);
+            self.send(&NetplayMessage::Input {
+                frame: input_frame,
+                keys,
+            })?;
+        }
+
+        // handles the messages of the peer, rolling back to the first
+        // frame that has been emulated with a mispredicted input
+        let mut rollback_frame: Option<u32> = None;
+        while let Some(message) = self.receive()? {
+            if let Some(frame) = self.handle(message) {
+                rollback_frame = Some(rollback_frame.map_or(frame, |value| value.min(frame)));
+            }
+        }
+        if let Some(frame) = rollback_frame {
+            self.rollback(gb, frame)?;
+        }
+        self.update_spectators();
+
+        if self.stalled() {
+            self.stats.stalls += 1;
+            return Ok(false);
+        }
+
+        if self.config.sync_mode == SyncMode::Rollback {
+            self.push_state(gb)?;
+        }
+        self.start.get_or_insert_with(Instant::now);
+        self.run_frame(gb, self.frame);
+        self.frame += 1;
+        self.stats.frames += 1;
+        self.prune();
+
+        if self.config.clock_interval > 0 && self.frame % self.config.clock_interval == 0 {
+            self.sync_clock()?;
+        }
+
+        Ok(true)
+    }
+
+    pub fn config(&self) -> &NetplayConfig {
+        &self.config
+    }
+
+    pub fn role(&self) -> NetplayRole {
+        self.role
+    }
+
+    pub fn spectators(&self) -> usize {
+        self.spectators.len()
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn stats(&self) -> NetplayStats {
+        self.stats
+    }
+
+    /// The ratio to be applied to the speed of the emulation (frame
+    /// rate) by the frontend, compensating the clock drift between
+    /// the hosts of the peers (eg: 0.998 to run 0.2% slower).
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// The number of frames this peer is running ahead of the
+    /// other one (negative if behind), as of the last clock exchange.
+    pub fn drift(&self) -> f64 {
+        self.drift
+    }
+
+    /// Handles a message from the peer, returning the frame to roll
+    /// back to in case the input of an emulated frame was mispredicted.
+    #[allow(clippy::unnecessary_map_or)]
+    fn handle(&mut self, message: NetplayMessage) -> Option<u32> {
+        match message {
+            NetplayMessage::Input { frame, keys } => {
+                self.remote_inputs.insert(frame, keys);
+                if self.remote_frame.map_or(true, |value| frame > value) {
+                    self.remote_frame = Some(frame);
+                    self.remote_keys = keys;
+                }
+                match self.predicted_inputs.remove(&frame) {
+                    Some(predicted) if predicted != keys => Some(frame),
+                    _ => None,
+                }
+            }
+            NetplayMessage::Clock {
+                frame,
+                elapsed,
+                advantage,
+            } => {
+                self.remote_clocks = [
+                    self.remote_clocks[1],
+                    Some(ClockSample {
+                        frame,
+                        elapsed,
+                        advantage,
+                    }),
+                ];
+                None
+            }
+            NetplayMessage::State(_) | NetplayMessage::Snapshot { .. } => None,
+        }
+    }
+
+    /// Samples the local clock sending it to the peer, adjusting the
+    /// speed of the emulation using the previous samples of both peers.
+    fn sync_clock(&mut self) -> Result<(), Error> {
+        let elapsed = self
+            .start
+            .map_or(0, |start| start.elapsed().as_millis() as u32);
+        let peer_frame = self
+            .remote_frame
+            .map_or(0, |frame| frame as i64 - self.config.input_delay as i64);
+        let sample = ClockSample {
+            frame: self.frame,
+            elapsed,
+            advantage: (self.frame as i64 - peer_frame) as i32,
+        };
+        self.local_clocks = [self.local_clocks[1], Some(sample)];
+        self.send(&NetplayMessage::Clock {
+            frame: sample.frame,
+            elapsed: sample.elapsed,
+            advantage: sample.advantage,
+        })?;
+        self.adjust_speed();
+        Ok(())
+    }
+
+    /// Compares the rates (frames per wall-clock time) of both peers
+    /// since their previous samples, slowing down the fastest one and
+    /// speeding up the slowest one, each compensating half of the
+    /// difference, then corrects the frame offset between them over
+    /// the next interval, always within the maximum adjustment.
+    fn adjust_speed(&mut self) {
+        let ([Some(local_previous), Some(local)], [Some(remote_previous), Some(remote)]) =
+            (self.local_clocks, self.remote_clocks)
+        else {
+            return;
+        };
+        let rate = |previous: ClockSample, current: ClockSample| {
+            let frames = current.frame.saturating_sub(previous.frame);
+            let elapsed = current.elapsed.saturating_sub(previous.elapsed);
+            if frames == 0 || elapsed == 0 {
+                None
+            } else {
+                Some(frames as f64 / elapsed as f64)
+            }
+        };
+        let (Some(local_rate), Some(remote_rate)) =
+            (rate(local_previous, local), rate(remote_previous, remote))
+        else {
+            return;
+        };
+
+        // the advantages of both peers include the latency of the
+        // transport, which cancels out when taking their difference
+        let max_adjust = self.config.max_speed_adjust as f64 / 1_000_000.0;
+        self.drift = (local.advantage - remote.advantage) as f64 / 2.0;
+        self.trim =
+            (self.trim - (local_rate / remote_rate - 1.0) / 2.0).clamp(-max_adjust, max_adjust);
+        let correction = self.drift / self.config.clock_interval.max(1) as f64;
+        self.speed = 1.0 + (self.trim - correction).clamp(-max_adjust, max_adjust);
+        self.stats.clock_syncs += 1;
+    }
+
+    fn advance_spectator(&mut self, gb: &mut GameBoy) -> Result<bool, Error> {
+        while let Some(message) = self.receive()? {
+            self.handle(message);
+        }
+        if !self.remote_inputs.contains_key(&self.frame) {
+            self.stats.stalls += 1;
+            return Ok(false);
+        }
+        self.run_frame(gb, self.frame);
+        self.frame += 1;
+        self.stats.frames += 1;
+        self.prune();
+        Ok(true)
+    }
+
+    /// Obtains the first frame for which the inputs of both
+    /// players are not yet known (confirmed).
+    fn confirmed_frame(&self) -> u32 {
+        let local = self
+            .local_inputs
+            .last_key_value()
+            .map_or(0, |(frame, _)| frame + 1);
+        let remote = self.remote_frame.map_or(0, |frame| frame + 1);
+        local.min(remote)
+    }
+
+    /// Sends the inputs confirmed since the last update to each of
+    /// the spectators, dropping the ones that have disconnected.
+    fn update_spectators(&mut self) {
+        let confirmed = self.confirmed_frame();
+        let local_inputs = &self.local_inputs;
+        let remote_inputs = &self.remote_inputs;
+        self.spectators.retain_mut(|(transport, next_frame)| {
+            while *next_frame < confirmed {
+                let keys = local_inputs.get(next_frame).copied().unwrap_or(0)
+                    | remote_inputs.get(next_frame).copied().unwrap_or(0);
+                let message = NetplayMessage::Input {
+                    frame: *next_frame,
+                    keys,
+                };
+                if message
+                    .encode()
+                    .and_then(|data| transport.send(&data))
+                    .is_err()
+                {
+                    return false;
+                }
+                *next_frame += 1;
+            }
+            true
+        });
+    }
+
+    /// Loads the state taken at the start of the provided frame and
+    /// re-simulates the frames up to the current one.
+    fn rollback(&mut self, gb: &mut GameBoy, frame: u32) -> Result<(), Error> {
+        let index = self
+            .states
+            .iter()
+            .position(|(state_frame, _)| *state_frame == frame)
+            .ok_or(Error::CustomError(format!(
+                "Netplay desync, no state available for frame {frame}"
+            )))?;
+        Self::load_state(gb, &self.states[index].1)?;
+        self.states.truncate(index + 1);
+        self.predicted_inputs
+            .retain(|predicted_frame, _| *predicted_frame < frame);
+
+        for resim_frame in frame..self.frame {
+            if resim_frame > frame {
+                self.push_state(gb)?;
+            }
+            self.run_frame(gb, resim_frame);
+        }
+
+        self.stats.rollbacks += 1;
+        self.stats.rollback_frames += self.frame - frame;
+        Ok(())
+    }
+
+    /// Runs the provided frame with the inputs of both players merged,
+    /// predicting the input of the peer in case it's not yet known.
+    fn run_frame(&mut self, gb: &mut GameBoy, frame: u32) {
+        let local_keys = self.local_inputs.get(&frame).copied().unwrap_or(0);
+        let remote_keys = match self.remote_inputs.get(&frame) {
+            Some(keys) => *keys,
+            None => {
+                self.predicted_inputs.insert(frame, self.remote_keys);
+                self.remote_keys
+            }
+        };
+        gb.set_pad_keys(local_keys | remote_keys);
+        gb.clock_frame();
+    }
+
+    /// Determines if the next frame can't be emulated as the inputs
+    /// of the peer are too far behind (according to the sync mode).
+    fn stalled(&self) -> bool {
+        let confirmed = self.remote_frame.map_or(0, |frame| frame + 1);
+        match self.config.sync_mode {
+            SyncMode::Lockstep => self.frame >= confirmed,
+            SyncMode::Rollback => self.frame >= confirmed + self.config.max_rollback,
+        }
+    }
+
+    fn push_state(&mut self, gb: &mut GameBoy) -> Result<(), Error> {
+        if self.states.len() > self.config.max_rollback as usize {
+            self.states.pop_front();
+        }
+        self.states.push_back((self.frame, Self::save_state(gb)?));
+        Ok(())
+    }
+
+    /// Removes the inputs of the frames that can no longer be
+    /// rolled back to, as they're no longer needed.
+    fn prune(&mut self) {
+        let oldest = self
+            .frame
+            .saturating_sub(self.config.max_rollback + self.config.input_delay + 1);
+        self.local_inputs.retain(|frame, _| *frame >= oldest);
+        self.remote_inputs.retain(|frame, _| *frame >= oldest);
+    }
+
+    fn send(&mut self, message: &NetplayMessage) -> Result<(), Error> {
+        self.transport.send(&message.encode()?)
+    }
+
+    fn receive(&mut self) -> Result<Option<NetplayMessage>, Error> {
+        self.transport
+            .receive()?
+            .map(|data| NetplayMessage::decode(&data))
+            .transpose()
+    }
+
+    fn save_state(gb: &mut GameBoy) -> Result<Vec<u8>, Error> {
+        StateManager::save(
+            gb,
+            Some(SaveStateFormat::Bos),
+            Some(FromGbOptions::new(false, None, None, None)),
+        )
+    }
+
+    fn load_state(gb: &mut GameBoy, state: &[u8]) -> Result<(), Error> {
+        StateManager::load(
+            state,
+            gb,
+            Some(SaveStateFormat::Bos),
+            Some(ToGbOptions::new(false)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{gb::GameBoy, netplay::transport::LocalTransport, test::build_pocket_test};
+
+    use super::{
+        ClockSample, NetplayConfig, NetplayMessage, NetplayRole, NetplaySession, SyncMode,
+    };
+
+    fn build_sessions(
+        config: NetplayConfig,
+    ) -> (
+        (NetplaySession, Box<GameBoy>),
+        (NetplaySession, Box<GameBoy>),
+    ) {
+        let (transport_a, transport_b) = LocalTransport::pair();
+        let mut host = NetplaySession::new(Box::new(transport_a), config);
+        let mut peer = NetplaySession::new(Box::new(transport_b), config);
+        let mut gb_host = build_pocket_test();
+        let mut gb_peer = build_pocket_test();
+        gb_host.next_frame();
+        host.host(&mut gb_host).unwrap();
+        peer.join(&mut gb_peer).unwrap();
+        ((host, gb_host), (peer, gb_peer))
+    }
+
+    fn snapshot(gb: &mut GameBoy) -> (u16, Vec<u8>, Vec<u8>) {
+        (
+            gb.cpu_i().pc(),
+            gb.mmu().ram().clone(),
+            gb.frame_buffer().to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_message() {
+        for message in [
+            NetplayMessage::State(vec![1, 2, 3]),
+            NetplayMessage::Input {
+                frame: 0x12345678,
+                keys: 0x81,
+            },
+            NetplayMessage::Snapshot {
+                frame: 42,
+                state: vec![4, 5],
+            },
+            NetplayMessage::Clock {
+                frame: 120,
+                elapsed: 2009,
+                advantage: -3,
+            },
+        ] {
+            let data = message.encode().unwrap();
+            assert_eq!(NetplayMessage::decode(&data).unwrap(), message);
+        }
+        assert!(NetplayMessage::decode(&[0xff]).is_err());
+    }
+
+    #[test]
+    fn test_lockstep() {
+        let config = NetplayConfig {
+            sync_mode: SyncMode::Lockstep,
+            ..Default::default()
+        };
+        let ((mut host, mut gb_host), (mut peer, mut gb_peer)) = build_sessions(config);
+
+        // the host can only run the frames within the input delay
+        // before stalling waiting for the inputs of the peer
+        assert!(host.advance(&mut gb_host, 0x10).unwrap());
+        assert!(host.advance(&mut gb_host, 0x10).unwrap());
+        assert!(!host.advance(&mut gb_host, 0x10).unwrap());
+        assert_eq!(host.stats().stalls, 1);
+
+        for _ in 0..2 {
+            assert!(peer.advance(&mut gb_peer, 0x01).unwrap());
+        }
+        for _ in 0..10 {
+            assert!(host.advance(&mut gb_host, 0x10).unwrap());
+            assert!(peer.advance(&mut gb_peer, 0x01).unwrap());
+        }
+        assert_eq!(host.frame(), 12);
+        assert_eq!(peer.frame(), 12);
+        assert_eq!(gb_host.pad_keys(), 0x11);
+        assert_eq!(snapshot(&mut gb_host), snapshot(&mut gb_peer));
+    }
+
+    #[test]
+    fn test_rollback() {
+        let ((mut host, mut gb_host), (mut peer, mut gb_peer)) =
+            build_sessions(NetplayConfig::default());
+
+        // the host runs ahead predicting the input of the peer
+        // (nothing pressed) up to the maximum rollback window
+        for _ in 0..10 {
+            assert!(host.advance(&mut gb_host, 0x00).unwrap());
+        }
+        assert!(!host.advance(&mut gb_host, 0x00).unwrap());
+        assert_eq!(host.frame(), 10);
+
+        // the peer catches up pressing a key, making the predictions
+        // of the host wrong and forcing it to roll back
+        for _ in 0..10 {
+            assert!(peer.advance(&mut gb_peer, 0x80).unwrap());
+        }
+        assert!(host.advance(&mut gb_host, 0x00).unwrap());
+        assert_eq!(host.stats().rollbacks, 1);
+        assert_eq!(host.stats().rollback_frames, 8);
+
+        assert!(peer.advance(&mut gb_peer, 0x80).unwrap());
+        assert_eq!(host.frame(), peer.frame());
+        assert_eq!(peer.stats().rollbacks, 0);
+        assert_eq!(snapshot(&mut gb_host), snapshot(&mut gb_peer));
+    }
+
+    #[test]
+    fn test_spectator() {
+        let ((mut host, mut gb_host), (mut peer, mut gb_peer)) =
+            build_sessions(NetplayConfig::default());
+
+        // the host runs ahead (with predicted inputs) so that the
+        // snapshot sent to the spectator is the last confirmed one
+        for _ in 0..5 {
+            assert!(host.advance(&mut gb_host, 0x10).unwrap());
+        }
+        let (transport_a, transport_b) = LocalTransport::pair();
+        host.add_spectator(&mut gb_host, Box::new(transport_a))
+            .unwrap();
+        assert_eq!(host.spectators(), 1);
+
+        let mut spectator = NetplaySession::new(Box::new(transport_b), NetplayConfig::default());
+        let mut gb_spectator = build_pocket_test();
+        spectator.spectate(&mut gb_spectator).unwrap();
+        assert_eq!(spectator.role(), NetplayRole::Spectator);
+        assert_eq!(spectator.frame(), 2);
+        assert!(!spectator.advance(&mut gb_spectator, 0xff).unwrap());
+        assert!(spectator
+            .add_spectator(&mut gb_spectator, Box::new(LocalTransport::pair().0))
+            .is_err());
+
+        for _ in 0..5 {
+            assert!(peer.advance(&mut gb_peer, 0x01).unwrap());
+        }
+        for _ in 0..10 {
+            assert!(host.advance(&mut gb_host, 0x10).unwrap());
+            assert!(peer.advance(&mut gb_peer, 0x01).unwrap());
+        }
+
+        // the spectator follows the confirmed inputs, ending up in
+        // the same state as the players (the local keys are ignored)
+        while spectator.frame() < peer.frame() {
+            assert!(spectator.advance(&mut gb_spectator, 0xff).unwrap());
+        }
+        assert_eq!(gb_spectator.pad_keys(), 0x11);
+        assert_eq!(snapshot(&mut gb_spectator), snapshot(&mut gb_peer));
+
+        drop(spectator);
+        assert!(host.advance(&mut gb_host, 0x10).unwrap());
+        assert_eq!(host.spectators(), 0);
+    }
+
+    #[test]
+    fn test_clock_drift() {
+        let sample = |frame, elapsed, advantage| {
+            Some(ClockSample {
+                frame,
+                elapsed,
+                advantage,
+            })
+        };
+        let ((mut host, _), _) = build_sessions(NetplayConfig::default());
+        assert_eq!(host.speed(), 1.0);
+
+        // the peer's host runs slightly faster (0.1%), with half
+        // of the difference being compensated by each of the peers
+        host.local_clocks = [sample(0, 0, 2), sample(120, 2000, 2)];
+        host.remote_clocks = [sample(0, 0, 2), sample(120, 1998, 2)];
+        host.adjust_speed();
+        assert!((host.speed() - 1.0005).abs() < 1e-6);
+        assert_eq!(host.drift(), 0.0);
+        assert_eq!(host.stats().clock_syncs, 1);
+
+        // a much larger difference is limited to the maximum adjustment
+        host.remote_clocks = [sample(0, 0, 2), sample(120, 2200, 2)];
+        host.adjust_speed();
+        assert!((host.speed() - 0.997).abs() < 1e-9);
+
+        // running ahead of the peer slows down the emulation even
+        // with the rates of both hosts being the same
+        let ((mut host, _), _) = build_sessions(NetplayConfig::default());
+        host.local_clocks = [sample(0, 0, 2), sample(120, 2000, 2)];
+        host.remote_clocks = [sample(0, 0, 2), sample(120, 2000, 1)];
+        host.adjust_speed();
+        assert_eq!(host.drift(), 0.5);
+        assert!(host.speed() < 1.0);
+
+        // both peers exchange their clocks while playing, with the
+        // speed adjustments never affecting the emulation itself
+        let config = NetplayConfig {
+            clock_interval: 4,
+            ..Default::default()
+        };
+        let ((mut host, mut gb_host), (mut peer, mut gb_peer)) = build_sessions(config);
+        for _ in 0..40 {
+            assert!(host.advance(&mut gb_host, 0x10).unwrap());
+            assert!(peer.advance(&mut gb_peer, 0x01).unwrap());
+        }
+        for session in [&host, &peer] {
+            assert!(session.local_clocks[1].is_some());
+            assert!(session.remote_clocks[1].is_some());
+            assert!((0.997..=1.003).contains(&session.speed()));
+        }
+        assert!(peer.advance(&mut gb_peer, 0x01).unwrap());
+        assert!(host.advance(&mut gb_host, 0x10).unwrap());
+        assert_eq!(snapshot(&mut gb_host), snapshot(&mut gb_peer));
+    }
+}
@@ -0,0 +1,236 @@
+//! Transports used to exchange netplay messages between the peers.
+//!
+//! Every transport is message based, meaning that each message sent
+//! by one of the peers is received as a whole by the other one.
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{channel, Receiver, Sender, TryRecvError},
+    thread,
+};
+
+use boytacean_common::error::Error;
+
+/// The size of the length prefix of the messages sent over
+/// stream based transports (eg: TCP).
+const LENGTH_SIZE: usize = 4;
+
+pub trait NetplayTransport {
+    /// Sends the provided message to the peer.
+    fn send(&mut self, data: &[u8]) -> Result<(), Error>;
+
+    /// Receives the next message sent by the peer, without blocking,
+    /// returning `None` in case no (complete) message is available.
+    fn receive(&mut self) -> Result<Option<Vec<u8>>, Error>;
+}
+
+/// Transport over a TCP connection, the messages are sent prefixed
+/// by their length (32 bit little endian).
+pub struct TcpTransport {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+}
+
+impl TcpTransport {
+    /// Listens on the provided port (of every interface) waiting
+    /// (blocking) for a peer to connect.
+    pub fn listen(port: u16) -> Result<Self, Error> {
+        TcpAcceptor::bind(port)?.accept()
+    }
+
+    /// Connects to the peer listening on the provided address
+    /// (eg: `192.168.1.2:7845`).
+    pub fn connect(addr: &str) -> Result<Self, Error> {
+        Self::from_stream(TcpStream::connect(addr)?)
+    }
+
+    pub fn from_stream(stream: TcpStream) -> Result<Self, Error> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            buffer: vec![],
+        })
+    }
+}
+
+impl NetplayTransport for TcpTransport {
+    fn send(&mut self, data: &[u8]) -> Result<(), Error> {
+        let mut message = Vec::with_capacity(LENGTH_SIZE + data.len());
+        message.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        message.extend_from_slice(data);
+        let mut offset = 0;
+        while offset < message.len() {
+            match self.stream.write(&message[offset..]) {
+                Ok(0) => return Err(Error::IoError(String::from("Connection closed"))),
+                Ok(count) => offset += count,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => thread::yield_now(),
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let mut data = [0u8; 4096];
+        loop {
+            match self.stream.read(&mut data) {
+                Ok(0) => return Err(Error::IoError(String::from("Connection closed"))),
+                Ok(count) => self.buffer.extend_from_slice(&data[..count]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        if self.buffer.len() < LENGTH_SIZE {
+            return Ok(None);
+        }
+        let length = u32::from_le_bytes(self.buffer[..LENGTH_SIZE].try_into().unwrap()) as usize;
+        if self.buffer.len() < LENGTH_SIZE + length {
+            return Ok(None);
+        }
+        let message = self.buffer[LENGTH_SIZE..LENGTH_SIZE + length].to_vec();
+        self.buffer.drain(..LENGTH_SIZE + length);
+        Ok(Some(message))
+    }
+}
+
+/// Listener of TCP connections, allowing the host to keep accepting
+/// connections (eg: of spectators) once the session has started.
+pub struct TcpAcceptor {
+    listener: TcpListener,
+}
+
+impl TcpAcceptor {
+    /// Binds the listener to the provided port (of every interface).
+    pub fn bind(port: u16) -> Result<Self, Error> {
+        Ok(Self {
+            listener: TcpListener::bind(("0.0.0.0", port))?,
+        })
+    }
+
+    pub fn from_listener(listener: TcpListener) -> Self {
+        Self { listener }
+    }
+
+    /// Waits (blocking) for a peer to connect.
+    pub fn accept(&self) -> Result<TcpTransport, Error> {
+        self.listener.set_nonblocking(false)?;
+        let (stream, _) = self.listener.accept()?;
+        TcpTransport::from_stream(stream)
+    }
+
+    /// Accepts the connection of a peer without blocking, returning
+    /// `None` in case there's no pending connection.
+    pub fn try_accept(&self) -> Result<Option<TcpTransport>, Error> {
+        self.listener.set_nonblocking(true)?;
+        match self.listener.accept() {
+            Ok((stream, _)) => Ok(Some(TcpTransport::from_stream(stream)?)),
+            Err(err) if err.kind() == ErrorKind::WouldBlock => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// In-process transport backed by channels, mostly useful for
+/// testing or for running both peers in the same process.
+pub struct LocalTransport {
+    sender: Sender<Vec<u8>>,
+    receiver: Receiver<Vec<u8>>,
+}
+
+impl LocalTransport {
+    /// Creates a pair of connected transports, the messages sent
+    /// through one of them are received by the other one.
+    pub fn pair() -> (Self, Self) {
+        let (sender_a, receiver_a) = channel();
+        let (sender_b, receiver_b) = channel();
+        (
+            Self {
+                sender: sender_a,
+                receiver: receiver_b,
+            },
+            Self {
+                sender: sender_b,
+                receiver: receiver_a,
+            },
+        )
+    }
+}
+
+impl NetplayTransport for LocalTransport {
+    fn send(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.sender
+            .send(data.to_vec())
+            .map_err(|_| Error::IoError(String::from("Connection closed")))
+    }
+
+    fn receive(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        match self.receiver.try_recv() {
+            Ok(message) => Ok(Some(message)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => {
+                Err(Error::IoError(String::from("Connection closed")))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{TcpListener, TcpStream};
+
+    use super::{LocalTransport, NetplayTransport, TcpAcceptor, TcpTransport};
+
+    #[test]
+    fn test_local() {
+        let (mut a, mut b) = LocalTransport::pair();
+        assert_eq!(b.receive().unwrap(), None);
+        a.send(&[1, 2, 3]).unwrap();
+        assert_eq!(b.receive().unwrap(), Some(vec![1, 2, 3]));
+        b.send(&[4]).unwrap();
+        assert_eq!(a.receive().unwrap(), Some(vec![4]));
+        drop(a);
+        assert!(b.receive().is_err());
+    }
+
+    #[test]
+    fn test_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut a = TcpTransport::from_stream(TcpStream::connect(addr).unwrap()).unwrap();
+        let mut b = TcpTransport::from_stream(listener.accept().unwrap().0).unwrap();
+
+        a.send(&[1, 2, 3]).unwrap();
+        a.send(&[]).unwrap();
+        let mut messages = vec![];
+        while messages.len() < 2 {
+            if let Some(message) = b.receive().unwrap() {
+                messages.push(message);
+            }
+        }
+        assert_eq!(messages, vec![vec![1, 2, 3], vec![]]);
+        assert_eq!(b.receive().unwrap(), None);
+    }
+
+    #[test]
+    fn test_tcp_acceptor() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let acceptor = TcpAcceptor::from_listener(listener);
+        assert!(acceptor.try_accept().unwrap().is_none());
+
+        let mut a = TcpTransport::from_stream(TcpStream::connect(addr).unwrap()).unwrap();
+        let mut b = acceptor.accept().unwrap();
+        a.send(&[1]).unwrap();
+        while b.receive().unwrap().is_none() {}
+
+        let _c = TcpStream::connect(addr).unwrap();
+        let mut accepted = None;
+        while accepted.is_none() {
+            accepted = acceptor.try_accept().unwrap();
+        }
+    }
+}
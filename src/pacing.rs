@@ -0,0 +1,234 @@
+//! Frame pacing of the emulated LCD on host displays.
+//!
+//! The LCD of the Game Boy refreshes at ~59.7275 Hz (see [`GameBoy::REFRESH_RATE`])
+//! while most of the host displays refresh at a fixed 60 Hz, the [`FramePacer`]
+//! decides how many frames are emulated for each of the display refreshes
+//! according to the [`FramePacing`] policy selected by the frontend.
+
+use std::fmt::{self, Display, Formatter};
+
+use boytacean_common::error::Error;
+
+use crate::gb::GameBoy;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FramePacing {
+    /// Presents the frames at the emulated refresh rate, with the
+    /// frontend being responsible for its timing, meant for variable
+    /// refresh rate (VRR) displays.
+    #[default]
+    Native,
+
+    /// Keeps the emulated speed on a fixed refresh rate display by
+    /// repeating a frame whenever the display gets a refresh ahead of
+    /// the emulation (every ~3.7 seconds at 60 Hz), or skipping one
+    /// for displays slower than the emulated refresh rate.
+    Duplicate,
+
+    /// Runs the emulation slightly faster (or slower) so that exactly
+    /// one frame is presented per refresh of the display, with the audio
+    /// resampled accordingly, falling back to [`FramePacing::Duplicate`]
+    /// for displays too far from the emulated refresh rate.
+    Stretch,
+}
+
+impl FramePacing {
+    pub fn description(&self) -> &'static str {
+        match self {
+            FramePacing::Native => "native",
+            FramePacing::Duplicate => "duplicate",
+            FramePacing::Stretch => "stretch",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "native" => Ok(FramePacing::Native),
+            "duplicate" => Ok(FramePacing::Duplicate),
+            "stretch" => Ok(FramePacing::Stretch),
+            _ => Err(Error::CustomError(format!("Invalid frame pacing: {value}"))),
+        }
+    }
+}
+
+impl Display for FramePacing {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// Resampler of the emulated frames into the refreshes of the
+/// host display, following a [`FramePacing`] policy.
+#[derive(Clone, Debug)]
+pub struct FramePacer {
+    pacing: FramePacing,
+    display_rate: f64,
+
+    /// The fraction of an emulated frame that has accumulated
+    /// over the previous display refreshes.
+    phase: f64,
+}
+
+impl FramePacer {
+    /// The maximum relative difference between the display and the
+    /// emulated refresh rates for which the emulation is stretched.
+    pub const MAX_STRETCH: f64 = 0.01;
+
+    /// Creates a pacer for a display with the provided refresh rate
+    /// (in Hz), the emulated refresh rate is used for invalid rates.
+    pub fn new(pacing: FramePacing, display_rate: f64) -> Self {
+        let display_rate = if display_rate > 0.0 {
+            display_rate
+        } else {
+            GameBoy::REFRESH_RATE
+        };
+        Self {
+            pacing,
+            display_rate,
+            phase: 0.0,
+        }
+    }
+
+    pub fn pacing(&self) -> FramePacing {
+        self.pacing
+    }
+
+    pub fn display_rate(&self) -> f64 {
+        self.display_rate
+    }
+
+    /// If the emulation is being stretched to the display rate.
+    pub fn stretched(&self) -> bool {
+        self.pacing == FramePacing::Stretch
+            && (self.display_rate / GameBoy::REFRESH_RATE - 1.0).abs() <= Self::MAX_STRETCH
+    }
+
+    /// The rate (in Hz) at which the frontend should present frames,
+    /// calling [`Self::refresh`] for each of them.
+    pub fn refresh_rate(&self) -> f64 {
+        match self.pacing {
+            FramePacing::Native => GameBoy::REFRESH_RATE,
+            _ => self.display_rate,
+        }
+    }
+
+    /// The speed of the emulation relative to the real hardware.
+    pub fn speed(&self) -> f64 {
+        match self.stretched() {
+            true => self.display_rate / GameBoy::REFRESH_RATE,
+            false => 1.0,
+        }
+    }
+
+    /// The sampling rate at which the audio should be generated so
+    /// that it's played at the provided rate, compensating the speed
+    /// of the emulation.
+    pub fn sampling_rate(&self, rate: u32) -> u32 {
+        (rate as f64 / self.speed()).round() as u32
+    }
+
+    /// The interval (in seconds) between the refreshes in which a frame
+    /// is repeated (or skipped), `None` if every refresh has a new frame.
+    pub fn duplicate_interval(&self) -> Option<f64> {
+        if self.pacing == FramePacing::Native || self.stretched() {
+            return None;
+        }
+        let difference = (self.display_rate - GameBoy::REFRESH_RATE).abs();
+        if difference == 0.0 {
+            return None;
+        }
+        Some(1.0 / difference)
+    }
+
+    /// Advances the pacer by a refresh of the display, returning the
+    /// number of frames to be emulated before presenting, zero means
+    /// that the previous frame should be presented again.
+    pub fn refresh(&mut self) -> u32 {
+        if self.pacing == FramePacing::Native || self.stretched() {
+            return 1;
+        }
+        self.phase += GameBoy::REFRESH_RATE / self.display_rate;
+        let frames = self.phase.floor();
+        self.phase -= frames;
+        frames as u32
+    }
+
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+    }
+}
+
+impl Default for FramePacer {
+    fn default() -> Self {
+        Self::new(FramePacing::default(), GameBoy::REFRESH_RATE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gb::GameBoy;
+
+    use super::{FramePacer, FramePacing};
+
+    #[test]
+    fn test_refresh_rate() {
+        assert!((GameBoy::REFRESH_RATE - 59.7275).abs() < 0.0001);
+        assert_eq!(
+            GameBoy::REFRESH_RATE * GameBoy::LCD_CYCLES as f64,
+            GameBoy::CPU_FREQ as f64
+        );
+    }
+
+    #[test]
+    fn test_duplicate() {
+        let mut pacer = FramePacer::new(FramePacing::Duplicate, 60.0);
+        assert_eq!(pacer.refresh_rate(), 60.0);
+        assert_eq!(pacer.speed(), 1.0);
+        let interval = pacer.duplicate_interval().unwrap();
+        assert!((interval - 3.67).abs() < 0.01);
+
+        // a minute of refreshes at 60 Hz repeats a
+        // frame every ~3.7 seconds
+        let refreshes = (0..3600).map(|_| pacer.refresh()).collect::<Vec<u32>>();
+        let repeated = refreshes.iter().filter(|frames| **frames == 0).count();
+        assert_eq!(repeated, 17);
+        assert!(refreshes.iter().all(|frames| *frames <= 1));
+        assert_eq!(refreshes.iter().sum::<u32>(), 3600 - repeated as u32);
+
+        let mut pacer = FramePacer::new(FramePacing::Duplicate, 50.0);
+        let frames = (0..500).map(|_| pacer.refresh()).sum::<u32>();
+        assert_eq!(frames, 597);
+    }
+
+    #[test]
+    fn test_stretch() {
+        let mut pacer = FramePacer::new(FramePacing::Stretch, 60.0);
+        assert!(pacer.stretched());
+        assert!((pacer.speed() - 1.004562).abs() < 0.000001);
+        assert_eq!(pacer.sampling_rate(44100), 43900);
+        assert_eq!(pacer.duplicate_interval(), None);
+        assert!((0..600).all(|_| pacer.refresh() == 1));
+
+        let pacer = FramePacer::new(FramePacing::Stretch, 50.0);
+        assert!(!pacer.stretched());
+        assert_eq!(pacer.speed(), 1.0);
+        assert!(pacer.duplicate_interval().is_some());
+    }
+
+    #[test]
+    fn test_native() {
+        let mut pacer = FramePacer::new(FramePacing::Native, 60.0);
+        assert_eq!(pacer.refresh_rate(), GameBoy::REFRESH_RATE);
+        assert_eq!(pacer.duplicate_interval(), None);
+        assert_eq!(pacer.refresh(), 1);
+        assert_eq!(
+            FramePacer::new(FramePacing::Duplicate, 0.0).display_rate(),
+            GameBoy::REFRESH_RATE
+        );
+        assert_eq!(
+            FramePacing::from_string("stretch").unwrap(),
+            FramePacing::Stretch
+        );
+        assert!(FramePacing::from_string("vrr").is_err());
+    }
+}
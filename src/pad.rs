@@ -6,7 +6,10 @@ use std::{
 };
 
 use crate::{
+    consts::P1_ADDR,
+    io::{Io, IoComponent, IoRegister},
     mmu::BusComponent,
+    sgb::{SgbReceiver, SGB_PACKET_SIZE},
     state::{StateComponent, StateFormat},
     warnln,
 };
@@ -14,6 +17,7 @@ use crate::{
 use boytacean_common::{
     data::{read_u8, write_u8},
     error::Error,
+    util::{read_file, write_file},
 };
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
@@ -71,6 +75,7 @@ impl From<PadSelection> for u8 {
 }
 
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum PadKey {
     Up,
     Down,
@@ -82,6 +87,19 @@ pub enum PadKey {
     B,
 }
 
+/// The keys of the pad in the order of their bits in a keys mask,
+/// (eg: bit 0 for Up and bit 7 for B).
+pub const PAD_KEYS: [PadKey; 8] = [
+    PadKey::Up,
+    PadKey::Down,
+    PadKey::Left,
+    PadKey::Right,
+    PadKey::Start,
+    PadKey::Select,
+    PadKey::A,
+    PadKey::B,
+];
+
 impl PadKey {
     pub fn from_u8(value: u8) -> Self {
         match value {
@@ -96,6 +114,43 @@ impl PadKey {
             _ => panic!("Invalid pad key value: {value}"),
         }
     }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            PadKey::Up => "up",
+            PadKey::Down => "down",
+            PadKey::Left => "left",
+            PadKey::Right => "right",
+            PadKey::Start => "start",
+            PadKey::Select => "select",
+            PadKey::A => "a",
+            PadKey::B => "b",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        PAD_KEYS
+            .iter()
+            .find(|key| key.description().eq_ignore_ascii_case(value))
+            .copied()
+            .ok_or_else(|| Error::CustomError(format!("Invalid pad key: {value}")))
+    }
+
+    /// The index of the key in [`PAD_KEYS`].
+    pub fn index(&self) -> usize {
+        PAD_KEYS.iter().position(|value| value == self).unwrap()
+    }
+
+    /// The bit of the key in a keys mask (see [`PAD_KEYS`]).
+    pub fn mask(&self) -> u8 {
+        1 << self.index()
+    }
+}
+
+impl Display for PadKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
 }
 
 impl From<u8> for PadKey {
@@ -104,6 +159,179 @@ impl From<u8> for PadKey {
     }
 }
 
+/// The filtering applied to impossible inputs (Left + Right or
+/// Up + Down), that can't be registered by the real hardware.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PadFilter {
+    /// Neither of the opposing directions is registered.
+    Block,
+
+    /// Only the most recently pressed direction is registered.
+    #[default]
+    LastWins,
+
+    /// Both directions are registered (eg: for TAS usage).
+    Allow,
+}
+
+impl PadFilter {
+    pub fn description(&self) -> &'static str {
+        match self {
+            PadFilter::Block => "Block",
+            PadFilter::LastWins => "Last Wins",
+            PadFilter::Allow => "Allow",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "block" => Ok(PadFilter::Block),
+            "last-wins" => Ok(PadFilter::LastWins),
+            "allow" => Ok(PadFilter::Allow),
+            _ => Err(Error::CustomError(format!("Invalid pad filter: {value}"))),
+        }
+    }
+
+    /// Resolves the state of a pair of opposing directions, with
+    /// `first_last` indicating if the first one was the last pressed.
+    fn resolve(&self, first: bool, second: bool, first_last: bool) -> (bool, bool) {
+        if !(first && second) {
+            return (first, second);
+        }
+        match self {
+            PadFilter::Block => (false, false),
+            PadFilter::LastWins => (first_last, !first_last),
+            PadFilter::Allow => (true, true),
+        }
+    }
+}
+
+impl Display for PadFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// The extension of the files storing the auto-fire patterns
+/// of a game (see [`AutoFire::file_name`]).
+pub const AUTO_FIRE_EXTENSION: &str = "fire";
+
+/// Press and release pattern of a key while it's being held, with
+/// the key pressed for `press` frames out of every `period` frames
+/// (eg: 2/10 presses the key for 2 frames every 10 frames).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AutoFirePattern {
+    press: u16,
+    period: u16,
+}
+
+impl AutoFirePattern {
+    pub fn new(press: u16, period: u16) -> Result<Self, Error> {
+        if press == 0 || press >= period {
+            return Err(Error::CustomError(format!(
+                "Invalid auto-fire pattern: {press}/{period}"
+            )));
+        }
+        Ok(Self { press, period })
+    }
+
+    pub fn press(&self) -> u16 {
+        self.press
+    }
+
+    pub fn period(&self) -> u16 {
+        self.period
+    }
+
+    /// Parses a pattern in the `press/period` format (eg: `2/10`).
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        let invalid = || Error::CustomError(format!("Invalid auto-fire pattern: {value}"));
+        let (press, period) = value.trim().split_once('/').ok_or_else(invalid)?;
+        Self::new(
+            press.trim().parse().map_err(|_| invalid())?,
+            period.trim().parse().map_err(|_| invalid())?,
+        )
+    }
+
+    /// If the key is pressed in the provided frame of the pattern.
+    pub fn pressed(&self, frame: u16) -> bool {
+        frame % self.period < self.press
+    }
+}
+
+impl Display for AutoFirePattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.press, self.period)
+    }
+}
+
+/// The auto-fire patterns of the keys of the pad, meant to help
+/// players that can't (repeatedly) press buttons, with the keys
+/// following their pattern while held.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct AutoFire {
+    patterns: [Option<AutoFirePattern>; 8],
+}
+
+impl AutoFire {
+    pub fn get(&self, key: PadKey) -> Option<AutoFirePattern> {
+        self.patterns[key.index()]
+    }
+
+    pub fn set(&mut self, key: PadKey, pattern: Option<AutoFirePattern>) {
+        self.patterns[key.index()] = pattern;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.iter().all(Option::is_none)
+    }
+
+    /// Parses the patterns in the `key:press/period` format separated
+    /// by commas or lines (eg: `a:2/10,b:1/4`).
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        let mut auto_fire = Self::default();
+        for entry in value.split([',', '\n']).map(str::trim) {
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+            let (key, pattern) = entry
+                .split_once(':')
+                .ok_or_else(|| Error::CustomError(format!("Invalid auto-fire entry: {entry}")))?;
+            auto_fire.set(
+                PadKey::from_string(key.trim())?,
+                Some(AutoFirePattern::from_string(pattern)?),
+            );
+        }
+        Ok(auto_fire)
+    }
+
+    /// Obtains the default file name for the patterns of a game, which
+    /// is based on the fingerprint of the ROM (eg: `0a1b2c3d.fire`).
+    pub fn file_name(fingerprint: u32) -> String {
+        format!("{fingerprint:08x}.{AUTO_FIRE_EXTENSION}")
+    }
+
+    pub fn load_file(path: &str) -> Result<Self, Error> {
+        let data = read_file(path)?;
+        Self::from_string(&String::from_utf8_lossy(&data))
+    }
+
+    pub fn save_file(&self, path: &str) -> Result<(), Error> {
+        write_file(path, format!("{self}\n").as_bytes(), None)
+    }
+}
+
+impl Display for AutoFire {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let entries = PAD_KEYS
+            .iter()
+            .filter_map(|key| self.get(*key).map(|pattern| format!("{key}:{pattern}")))
+            .collect::<Vec<_>>();
+        write!(f, "{}", entries.join(","))
+    }
+}
+
 pub struct Pad {
     down: bool,
     up: bool,
@@ -115,6 +343,33 @@ pub struct Pad {
     a: bool,
     selection: PadSelection,
     int_pad: bool,
+
+    /// The filter applied to opposing directions, together with
+    /// the information on which of them was the last pressed.
+    filter: PadFilter,
+    left_last: bool,
+    up_last: bool,
+
+    /// The receiver of the SGB command packets sent through
+    /// P1 writes, only set when running in SGB mode.
+    sgb: Option<SgbReceiver>,
+
+    /// The number of joypads multiplexed by the SGB (as requested
+    /// with `MLT_REQ`), the one currently selected and the keys
+    /// mask (see [`PAD_KEYS`]) of the joypads of players 2 to 4.
+    players: u8,
+    player: u8,
+    player_keys: [u8; 3],
+
+    /// If P15 was set low by the last P1 write, the selected
+    /// joypad changes on the rising edge of P15.
+    p15_low: bool,
+
+    /// The auto-fire patterns, the mask of the keys with a pattern
+    /// that are being held and the frame of the pattern of each key.
+    auto_fire: AutoFire,
+    auto_fire_held: u8,
+    auto_fire_frames: [u16; 8],
 }
 
 impl Pad {
@@ -130,6 +385,17 @@ impl Pad {
             a: false,
             selection: PadSelection::None,
             int_pad: false,
+            filter: PadFilter::default(),
+            left_last: false,
+            up_last: false,
+            sgb: None,
+            players: 1,
+            player: 0,
+            player_keys: [0x00; 3],
+            p15_low: false,
+            auto_fire: AutoFire::default(),
+            auto_fire_held: 0x00,
+            auto_fire_frames: [0; 8],
         }
     }
 
@@ -138,6 +404,22 @@ impl Pad {
             // 0xFF00 — P1/JOYP: Joypad
             0xff00 => {
                 let mut value = match self.selection {
+                    PadSelection::Action | PadSelection::Direction if self.player > 0 => {
+                        let keys = self.player_keys[self.player as usize - 1];
+                        let lines = match self.selection {
+                            PadSelection::Action => {
+                                [PadKey::A, PadKey::B, PadKey::Select, PadKey::Start]
+                            }
+                            _ => [PadKey::Right, PadKey::Left, PadKey::Up, PadKey::Down],
+                        };
+                        lines.iter().enumerate().fold(0x0f, |value, (bit, key)| {
+                            if keys & key.mask() != 0x00 {
+                                value & !(1 << bit)
+                            } else {
+                                value
+                            }
+                        })
+                    }
                     PadSelection::Action =>
                     {
                         #[allow(clippy::bool_to_int_with_if)]
@@ -146,14 +428,17 @@ impl Pad {
                             | if self.select { 0x00 } else { 0x04 }
                             | if self.start { 0x00 } else { 0x08 })
                     }
-                    PadSelection::Direction =>
-                    {
+                    PadSelection::Direction => {
+                        let (left, right) =
+                            self.filter.resolve(self.left, self.right, self.left_last);
+                        let (up, down) = self.filter.resolve(self.up, self.down, self.up_last);
                         #[allow(clippy::bool_to_int_with_if)]
-                        (if self.right { 0x00 } else { 0x01 }
-                            | if self.left { 0x00 } else { 0x02 }
-                            | if self.up { 0x00 } else { 0x04 }
-                            | if self.down { 0x00 } else { 0x08 })
+                        (if right { 0x00 } else { 0x01 }
+                            | if left { 0x00 } else { 0x02 }
+                            | if up { 0x00 } else { 0x04 }
+                            | if down { 0x00 } else { 0x08 })
                     }
+                    PadSelection::None if self.players > 1 => 0x0f - self.player,
                     PadSelection::None => 0x0f,
                 };
                 value |= match self.selection {
@@ -181,17 +466,55 @@ impl Pad {
                     0x30 => PadSelection::None,
                     _ => PadSelection::None,
                 };
+                if let Some(sgb) = &mut self.sgb {
+                    sgb.write(value);
+                }
+
+                // selects the next of the multiplexed joypads on the
+                // rising edge of P15, wrapping around the last one
+                let p15_low = value & 0x20 == 0x00;
+                if self.players > 1 && self.p15_low && !p15_low {
+                    self.player = (self.player + 1) % self.players;
+                }
+                self.p15_low = p15_low;
             }
             _ => warnln!("Writing to unknown Pad location 0x{:04x}", addr),
         }
     }
 
     pub fn key_press(&mut self, key: PadKey) {
+        // the keys with an auto-fire pattern start it when held,
+        // with the first frame of the pattern being a press
+        if self.auto_fire.get(key).is_some() {
+            self.auto_fire_held |= key.mask();
+            self.auto_fire_frames[key.index()] = 0;
+        }
+        self.press(key);
+    }
+
+    pub fn key_lift(&mut self, key: PadKey) {
+        self.auto_fire_held &= !key.mask();
+        self.lift(key);
+    }
+
+    fn press(&mut self, key: PadKey) {
         match key {
-            PadKey::Up => self.up = true,
-            PadKey::Down => self.down = true,
-            PadKey::Left => self.left = true,
-            PadKey::Right => self.right = true,
+            PadKey::Up => {
+                self.up = true;
+                self.up_last = true;
+            }
+            PadKey::Down => {
+                self.down = true;
+                self.up_last = false;
+            }
+            PadKey::Left => {
+                self.left = true;
+                self.left_last = true;
+            }
+            PadKey::Right => {
+                self.right = true;
+                self.left_last = false;
+            }
             PadKey::Start => self.start = true,
             PadKey::Select => self.select = true,
             PadKey::A => self.a = true,
@@ -203,7 +526,7 @@ impl Pad {
         self.int_pad = true;
     }
 
-    pub fn key_lift(&mut self, key: PadKey) {
+    fn lift(&mut self, key: PadKey) {
         match key {
             PadKey::Up => self.up = false,
             PadKey::Down => self.down = false,
@@ -216,6 +539,170 @@ impl Pad {
         }
     }
 
+    /// Obtains the mask of the keys currently pressed, using one
+    /// bit per key in the order of [`PAD_KEYS`].
+    pub fn keys(&self) -> u8 {
+        [
+            self.up,
+            self.down,
+            self.left,
+            self.right,
+            self.start,
+            self.select,
+            self.a,
+            self.b,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |keys, (index, pressed)| {
+            keys | ((*pressed as u8) << index)
+        })
+    }
+
+    /// Sets the keys pressed from the provided mask (see [`PAD_KEYS`]),
+    /// pressing and lifting only the keys whose state has changed.
+    pub fn set_keys(&mut self, keys: u8) {
+        let current = self.keys();
+        for (index, key) in PAD_KEYS.iter().enumerate() {
+            let mask = 1 << index;
+            if keys & mask == current & mask {
+                continue;
+            }
+            if keys & mask == mask {
+                self.key_press(*key);
+            } else {
+                self.key_lift(*key);
+            }
+        }
+    }
+
+    /// Presses the key in the joypad of the provided player (0 to 3),
+    /// with player 1 (index 0) being the regular joypad.
+    pub fn key_press_player(&mut self, player: usize, key: PadKey) {
+        if player == 0 {
+            return self.key_press(key);
+        }
+        if let Some(keys) = self.player_keys.get_mut(player - 1) {
+            *keys |= key.mask();
+            self.int_pad = true;
+        }
+    }
+
+    pub fn key_lift_player(&mut self, player: usize, key: PadKey) {
+        if player == 0 {
+            return self.key_lift(key);
+        }
+        if let Some(keys) = self.player_keys.get_mut(player - 1) {
+            *keys &= !key.mask();
+        }
+    }
+
+    /// Obtains the mask of the keys currently pressed in the
+    /// joypad of the provided player (0 to 3).
+    pub fn player_keys(&self, player: usize) -> u8 {
+        match player {
+            0 => self.keys(),
+            _ => self.player_keys.get(player - 1).copied().unwrap_or(0x00),
+        }
+    }
+
+    pub fn set_player_keys(&mut self, player: usize, keys: u8) {
+        if player == 0 {
+            return self.set_keys(keys);
+        }
+        if let Some(current) = self.player_keys.get_mut(player - 1) {
+            if keys & !*current != 0x00 {
+                self.int_pad = true;
+            }
+            *current = keys;
+        }
+    }
+
+    /// The number of joypads multiplexed through P1 (1, 2 or 4).
+    pub fn auto_fire(&self) -> &AutoFire {
+        &self.auto_fire
+    }
+
+    /// Sets the auto-fire patterns, the keys being held keep their
+    /// current state until they're pressed again.
+    pub fn set_auto_fire(&mut self, value: AutoFire) {
+        self.auto_fire = value;
+        self.auto_fire_held = 0x00;
+    }
+
+    /// If any of the held keys is following an auto-fire pattern.
+    pub fn auto_fire_active(&self) -> bool {
+        self.auto_fire_held != 0x00
+    }
+
+    /// Advances the auto-fire patterns of the held keys by a frame,
+    /// pressing and lifting the keys according to them.
+    pub fn clock_auto_fire(&mut self) {
+        for (index, key) in PAD_KEYS.iter().enumerate() {
+            if self.auto_fire_held & key.mask() == 0x00 {
+                continue;
+            }
+            let pattern = match self.auto_fire.get(*key) {
+                Some(pattern) => pattern,
+                None => continue,
+            };
+            let frame = (self.auto_fire_frames[index] + 1) % pattern.period();
+            self.auto_fire_frames[index] = frame;
+            if pattern.pressed(frame) {
+                if self.keys() & key.mask() == 0x00 {
+                    self.press(*key);
+                }
+            } else {
+                self.lift(*key);
+            }
+        }
+    }
+
+    pub fn players(&self) -> u8 {
+        self.players
+    }
+
+    /// Sets the number of joypads multiplexed through P1, as
+    /// requested by the SGB `MLT_REQ` command, selecting the
+    /// joypad of player 1.
+    pub fn set_players(&mut self, value: u8) {
+        self.players = value;
+        self.player = 0;
+    }
+
+    /// The index of the joypad currently selected (0 to 3).
+    pub fn player(&self) -> u8 {
+        self.player
+    }
+
+    pub fn filter(&self) -> PadFilter {
+        self.filter
+    }
+
+    pub fn set_filter(&mut self, value: PadFilter) {
+        self.filter = value;
+    }
+
+    pub fn sgb(&self) -> bool {
+        self.sgb.is_some()
+    }
+
+    /// Enables (or disables) the receiving of SGB command
+    /// packets through the P1 register.
+    pub fn set_sgb(&mut self, value: bool) {
+        self.sgb = if value {
+            Some(SgbReceiver::new())
+        } else {
+            None
+        };
+    }
+
+    /// Obtains the next SGB command packet received, if any.
+    #[inline(always)]
+    pub fn sgb_packet(&mut self) -> Option<[u8; SGB_PACKET_SIZE]> {
+        self.sgb.as_mut().and_then(|sgb| sgb.packet())
+    }
+
     #[inline(always)]
     pub fn int_pad(&self) -> bool {
         self.int_pad
@@ -232,6 +719,11 @@ impl Pad {
     }
 }
 
+impl Io for Pad {
+    const IO_REGISTERS: &'static [IoRegister] =
+        &[IoRegister::new(P1_ADDR, "P1", IoComponent::Pad, 0x3f, 0x30)];
+}
+
 impl BusComponent for Pad {
     fn read(&self, addr: u16) -> u8 {
         self.read(addr)
@@ -284,7 +776,7 @@ impl Default for Pad {
 mod tests {
     use crate::state::StateComponent;
 
-    use super::{Pad, PadSelection};
+    use super::{AutoFire, AutoFirePattern, Pad, PadFilter, PadKey, PadSelection};
 
     #[test]
     fn test_state_and_set_state() {
@@ -299,6 +791,17 @@ mod tests {
             a: false,
             selection: PadSelection::Action,
             int_pad: true,
+            filter: PadFilter::Allow,
+            left_last: false,
+            up_last: false,
+            sgb: None,
+            players: 1,
+            player: 0,
+            player_keys: [0x00; 3],
+            p15_low: false,
+            auto_fire: AutoFire::default(),
+            auto_fire_held: 0x00,
+            auto_fire_frames: [0; 8],
         };
 
         let state = pad.state(None).unwrap();
@@ -318,4 +821,124 @@ mod tests {
         assert_eq!(new_pad.selection, PadSelection::Action);
         assert!(new_pad.int_pad);
     }
+
+    #[test]
+    fn test_filter() {
+        let mut pad = Pad::new();
+        pad.write(0xff00, 0x20);
+        pad.key_press(PadKey::Left);
+        pad.key_press(PadKey::Right);
+        pad.key_press(PadKey::Up);
+        assert_eq!(pad.read(0xff00) & 0x0f, 0x0a);
+
+        pad.key_lift(PadKey::Right);
+        assert_eq!(pad.read(0xff00) & 0x0f, 0x09);
+
+        pad.key_press(PadKey::Right);
+        pad.set_filter(PadFilter::Block);
+        assert_eq!(pad.read(0xff00) & 0x0f, 0x0b);
+
+        pad.set_filter(PadFilter::Allow);
+        assert_eq!(pad.read(0xff00) & 0x0f, 0x08);
+
+        assert_eq!(PadFilter::from_string("block").unwrap(), PadFilter::Block);
+        assert!(PadFilter::from_string("other").is_err());
+    }
+
+    #[test]
+    fn test_keys() {
+        let mut pad = Pad::new();
+        assert_eq!(pad.keys(), 0x00);
+
+        pad.set_keys(0x41);
+        assert!(pad.up);
+        assert!(pad.a);
+        assert!(pad.int_pad());
+        assert_eq!(pad.keys(), 0x41);
+
+        pad.ack_pad();
+        pad.set_keys(0x01);
+        assert!(!pad.a);
+        assert!(!pad.int_pad());
+        assert_eq!(pad.keys(), 0x01);
+    }
+
+    #[test]
+    fn test_players() {
+        let mut pad = Pad::new();
+        pad.write(0xff00, 0x30);
+        assert_eq!(pad.read(0xff00) & 0x0f, 0x0f);
+
+        // cycles through the four joypads on the rising
+        // edge of P15, reading the index of the selected one
+        pad.set_players(4);
+        pad.key_press(PadKey::A);
+        pad.key_press_player(2, PadKey::Start);
+        pad.key_press_player(2, PadKey::Down);
+        for player in [1, 2, 3, 0] {
+            pad.write(0xff00, 0x10);
+            pad.write(0xff00, 0x30);
+            assert_eq!(pad.player(), player);
+            assert_eq!(pad.read(0xff00) & 0x0f, 0x0f - player);
+        }
+
+        pad.write(0xff00, 0x10);
+        assert_eq!(pad.read(0xff00) & 0x0f, 0x0e);
+        pad.write(0xff00, 0x30);
+        pad.write(0xff00, 0x10);
+        pad.write(0xff00, 0x30);
+        assert_eq!(pad.player(), 2);
+        pad.write(0xff00, 0x20);
+        assert_eq!(pad.read(0xff00) & 0x0f, 0x07);
+        pad.write(0xff00, 0x10);
+        assert_eq!(pad.read(0xff00) & 0x0f, 0x07);
+        assert_eq!(pad.player(), 2);
+        assert_eq!(pad.player_keys(2), 0x12);
+
+        pad.key_lift_player(2, PadKey::Start);
+        pad.set_player_keys(3, 0x80);
+        assert_eq!(pad.player_keys(2), 0x02);
+        assert_eq!(pad.player_keys(3), 0x80);
+        assert_eq!(pad.player_keys(4), 0x00);
+
+        pad.set_players(1);
+        pad.write(0xff00, 0x30);
+        assert_eq!(pad.player(), 0);
+        assert_eq!(pad.read(0xff00) & 0x0f, 0x0f);
+    }
+
+    #[test]
+    fn test_auto_fire() {
+        let auto_fire = AutoFire::from_string("a:2/5, B:1/2").unwrap();
+        assert_eq!(auto_fire.get(PadKey::A), AutoFirePattern::new(2, 5).ok());
+        assert_eq!(auto_fire.get(PadKey::B), AutoFirePattern::new(1, 2).ok());
+        assert_eq!(auto_fire.get(PadKey::Start), None);
+        assert_eq!(auto_fire.to_string(), "a:2/5,b:1/2");
+        assert_eq!(AutoFire::from_string(&auto_fire.to_string()), Ok(auto_fire));
+        assert!(AutoFire::from_string("a:5/5").is_err());
+        assert!(AutoFire::from_string("x:1/2").is_err());
+        assert!(AutoFire::from_string("a").is_err());
+
+        let mut pad = Pad::new();
+        pad.set_auto_fire(auto_fire);
+        pad.key_press(PadKey::A);
+        pad.key_press(PadKey::Start);
+        assert!(pad.auto_fire_active());
+
+        let mut pressed = vec![];
+        for _ in 0..10 {
+            pressed.push(pad.a);
+            assert!(pad.start);
+            pad.clock_auto_fire();
+        }
+        assert_eq!(
+            pressed,
+            [true, true, false, false, false, true, true, false, false, false]
+        );
+
+        pad.key_lift(PadKey::A);
+        assert!(!pad.auto_fire_active());
+        pad.clock_auto_fire();
+        assert!(!pad.a);
+    }
 }
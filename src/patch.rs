@@ -0,0 +1,354 @@
+//! Soft-patching of ROMs using the IPS and BPS patch formats,
+//! applying the patches in memory when the ROM is loaded so that
+//! no pre-patched ROM file is required (eg: ROM hacks, translations).
+
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use boytacean_common::error::Error;
+use boytacean_hashing::crc32::crc32;
+
+/// The magic string at the start of an IPS patch.
+pub const IPS_MAGIC: &[u8; 5] = b"PATCH";
+
+/// The record offset that marks the end of an IPS patch.
+pub const IPS_EOF: &[u8; 3] = b"EOF";
+
+/// The magic string at the start of a BPS patch.
+pub const BPS_MAGIC: &[u8; 4] = b"BPS1";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PatchFormat {
+    Ips,
+    Bps,
+}
+
+impl PatchFormat {
+    pub fn description(&self) -> &'static str {
+        match self {
+            PatchFormat::Ips => "IPS",
+            PatchFormat::Bps => "BPS",
+        }
+    }
+
+    /// Infers the format of the patch from its magic string.
+    pub fn from_data(patch: &[u8]) -> Result<Self, Error> {
+        if patch.starts_with(IPS_MAGIC) {
+            Ok(PatchFormat::Ips)
+        } else if patch.starts_with(BPS_MAGIC) {
+            Ok(PatchFormat::Bps)
+        } else {
+            Err(Error::DataError(String::from("Unknown patch format")))
+        }
+    }
+}
+
+impl Display for PatchFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// Applies the provided patch (IPS or BPS, inferred from its contents)
+/// to the ROM, returning the patched ROM.
+pub fn apply_patch(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    match PatchFormat::from_data(patch)? {
+        PatchFormat::Ips => apply_ips(rom, patch),
+        PatchFormat::Bps => apply_bps(rom, patch),
+    }
+}
+
+/// Applies an IPS patch to the ROM, the patch is a sequence of records
+/// (big endian 24 bit offset and 16 bit size) each followed by its data
+/// or, when the size is zero, by a run length encoded value.
+///
+/// The ROM grows whenever a record writes past its end, and can be
+/// truncated by the optional (non-standard) length after the EOF marker.
+pub fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    if !patch.starts_with(IPS_MAGIC) {
+        return Err(Error::DataError(String::from("Invalid IPS patch magic")));
+    }
+    let mut reader = PatchReader::new(patch, IPS_MAGIC.len());
+    let mut target = rom.to_vec();
+
+    loop {
+        let offset = reader.read_bytes(3)?;
+        if offset == IPS_EOF {
+            break;
+        }
+        let offset = (offset[0] as usize) << 16 | (offset[1] as usize) << 8 | offset[2] as usize;
+        let size = reader.read_u16_be()? as usize;
+        let (length, data) = if size == 0 {
+            let length = reader.read_u16_be()? as usize;
+            (length, None)
+        } else {
+            (size, Some(reader.read_bytes(size)?))
+        };
+        if target.len() < offset + length {
+            target.resize(offset + length, 0x00);
+        }
+        match data {
+            Some(data) => target[offset..offset + length].copy_from_slice(data),
+            None => target[offset..offset + length].fill(reader.read_u8()?),
+        }
+    }
+
+    if reader.remaining() >= 3 {
+        let length = reader.read_bytes(3)?;
+        let length = (length[0] as usize) << 16 | (length[1] as usize) << 8 | length[2] as usize;
+        target.truncate(length);
+    }
+
+    Ok(target)
+}
+
+/// Applies a BPS patch to the ROM, the patch describes the target
+/// as a sequence of actions that copy data from the source, the
+/// patch itself or the already written target.
+///
+/// The CRC32 checksums of the source, target and patch are verified,
+/// so that a patch is never applied to the wrong ROM.
+pub fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    if !patch.starts_with(BPS_MAGIC) || patch.len() < BPS_MAGIC.len() + 12 {
+        return Err(Error::DataError(String::from("Invalid BPS patch")));
+    }
+
+    let footer = patch.len() - 12;
+    let footer_u32 = |index: usize| {
+        let offset = footer + index * 4;
+        u32::from_le_bytes(patch[offset..offset + 4].try_into().unwrap())
+    };
+    let (source_crc32, target_crc32, patch_crc32) = (footer_u32(0), footer_u32(1), footer_u32(2));
+    if crc32(&patch[..footer + 8]) != patch_crc32 {
+        return Err(Error::DataError(String::from("Invalid BPS patch checksum")));
+    }
+
+    let mut reader = PatchReader::new(&patch[..footer], BPS_MAGIC.len());
+    let source_size = reader.read_varint()?;
+    let target_size = reader.read_varint()?;
+    let metadata_size = reader.read_varint()?;
+    reader.read_bytes(metadata_size)?;
+
+    if rom.len() != source_size || crc32(rom) != source_crc32 {
+        return Err(Error::DataError(String::from(
+            "BPS patch does not match the ROM",
+        )));
+    }
+
+    let mut target = vec![0u8; target_size];
+    let mut output = 0;
+    let mut source_offset = 0isize;
+    let mut target_offset = 0isize;
+
+    while reader.remaining() > 0 {
+        let data = reader.read_varint()?;
+        let command = data & 0x03;
+        let length = (data >> 2) + 1;
+        if output + length > target_size {
+            return Err(Error::DataError(String::from(
+                "BPS action writes past the target",
+            )));
+        }
+        match command {
+            // source read, copies from the same offset in the source
+            0 => {
+                let data =
+                    rom.get(output..output + length)
+                        .ok_or(Error::DataError(String::from(
+                            "BPS source read out of bounds",
+                        )))?;
+                target[output..output + length].copy_from_slice(data);
+            }
+
+            // target read, copies the data that follows in the patch
+            1 => {
+                target[output..output + length].copy_from_slice(reader.read_bytes(length)?);
+            }
+
+            // source copy, copies from a relative offset in the source
+            2 => {
+                source_offset += reader.read_signed_varint()?;
+                let start = usize::try_from(source_offset).ok();
+                let data = start
+                    .and_then(|start| rom.get(start..start + length))
+                    .ok_or(Error::DataError(String::from(
+                        "BPS source copy out of bounds",
+                    )))?;
+                target[output..output + length].copy_from_slice(data);
+                source_offset += length as isize;
+            }
+
+            // target copy, copies from a relative offset in the already
+            // written target, byte by byte as the ranges may overlap
+            _ => {
+                target_offset += reader.read_signed_varint()?;
+                let start = usize::try_from(target_offset)
+                    .ok()
+                    .filter(|start| *start < output)
+                    .ok_or(Error::DataError(String::from(
+                        "BPS target copy out of bounds",
+                    )))?;
+                for index in 0..length {
+                    target[output + index] = target[start + index];
+                }
+                target_offset += length as isize;
+            }
+        }
+        output += length;
+    }
+
+    if crc32(&target) != target_crc32 {
+        return Err(Error::DataError(String::from(
+            "Invalid BPS target checksum",
+        )));
+    }
+
+    Ok(target)
+}
+
+/// Sequential reader of the contents of a patch, failing with
+/// a data error on any attempt to read past its end.
+struct PatchReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    fn new(data: &'a [u8], offset: usize) -> Self {
+        Self { data, offset }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.offset)
+    }
+
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + count)
+            .ok_or(Error::DataError(String::from("Unexpected end of patch")))?;
+        self.offset += count;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u16_be(&mut self) -> Result<u16, Error> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Reads a BPS variable length number, in which each byte holds
+    /// 7 bits of the value with the top bit marking the last byte.
+    fn read_varint(&mut self) -> Result<usize, Error> {
+        let mut value = 0usize;
+        let mut shift = 1usize;
+        loop {
+            let byte = self.read_u8()?;
+            value = (byte as usize & 0x7f)
+                .checked_mul(shift)
+                .and_then(|data| value.checked_add(data))
+                .ok_or(Error::DataError(String::from("Invalid patch number")))?;
+            if byte & 0x80 == 0x80 {
+                return Ok(value);
+            }
+            shift = shift
+                .checked_shl(7)
+                .filter(|shift| *shift != 0)
+                .ok_or(Error::DataError(String::from("Invalid patch number")))?;
+            value += shift;
+        }
+    }
+
+    /// Reads a BPS relative offset, a variable length number with
+    /// its lowest bit as the sign.
+    fn read_signed_varint(&mut self) -> Result<isize, Error> {
+        let data = self.read_varint()?;
+        let value = (data >> 1) as isize;
+        Ok(if data & 0x01 == 0x01 { -value } else { value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use boytacean_hashing::crc32::crc32;
+
+    use super::{apply_bps, apply_ips, apply_patch, PatchFormat};
+
+    fn bps_varint(mut value: usize, buffer: &mut Vec<u8>) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                buffer.push(byte | 0x80);
+                break;
+            }
+            buffer.push(byte);
+            value -= 1;
+        }
+    }
+
+    fn bps_patch(source: &[u8], target: &[u8], actions: &[u8]) -> Vec<u8> {
+        let mut patch = b"BPS1".to_vec();
+        bps_varint(source.len(), &mut patch);
+        bps_varint(target.len(), &mut patch);
+        bps_varint(0, &mut patch);
+        patch.extend_from_slice(actions);
+        patch.extend_from_slice(&crc32(source).to_le_bytes());
+        patch.extend_from_slice(&crc32(target).to_le_bytes());
+        patch.extend_from_slice(&crc32(&patch).to_le_bytes());
+        patch
+    }
+
+    #[test]
+    fn test_ips() {
+        let rom = [0x00, 0x01, 0x02, 0x03, 0x04, 0x05];
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x02, 0xaa, 0xbb]);
+        patch.extend_from_slice(&[0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x03, 0xcc]);
+        patch.extend_from_slice(b"EOF");
+        assert_eq!(PatchFormat::from_data(&patch).unwrap(), PatchFormat::Ips);
+        assert_eq!(
+            apply_ips(&rom, &patch).unwrap(),
+            [0x00, 0xaa, 0xbb, 0x03, 0x04, 0xcc, 0xcc, 0xcc]
+        );
+
+        patch.extend_from_slice(&[0x00, 0x00, 0x04]);
+        assert_eq!(apply_patch(&rom, &patch).unwrap(), [0x00, 0xaa, 0xbb, 0x03]);
+        assert!(apply_ips(&rom, &patch[..10]).is_err());
+        assert!(apply_ips(&rom, b"PATCX").is_err());
+    }
+
+    #[test]
+    fn test_bps() {
+        let source = b"boytacean";
+        let target = b"boyboyxxxcean";
+        let mut actions = vec![];
+
+        // source read of "boy", target copy of "boy" (from offset 0),
+        // target read of "x" and target copy of "xx" (overlapping) and
+        // source copy of "cean" (from offset 5)
+        bps_varint(2 << 2, &mut actions);
+        bps_varint((2 << 2) | 3, &mut actions);
+        bps_varint(0, &mut actions);
+        bps_varint(1, &mut actions);
+        actions.push(b'x');
+        bps_varint((1 << 2) | 3, &mut actions);
+        bps_varint(3 << 1, &mut actions);
+        bps_varint((3 << 2) | 2, &mut actions);
+        bps_varint(5 << 1, &mut actions);
+
+        let patch = bps_patch(source, target, &actions);
+        assert_eq!(PatchFormat::from_data(&patch).unwrap(), PatchFormat::Bps);
+        assert_eq!(apply_bps(source, &patch).unwrap(), target);
+        assert_eq!(apply_patch(source, &patch).unwrap(), target);
+        assert!(apply_bps(b"boytaceaN", &patch).is_err());
+
+        let mut corrupted = patch.clone();
+        corrupted[8] ^= 0xff;
+        assert!(apply_bps(source, &corrupted).is_err());
+        assert!(apply_patch(source, b"XXXX").is_err());
+    }
+}
@@ -5,35 +5,39 @@
 //! backgrounds using tile-based graphics.
 
 use boytacean_common::{
-    data::{read_into, read_u16, read_u8, write_bytes, write_u16, write_u8},
+    data::{read_into, read_u16, read_u32, read_u8, write_bytes, write_u16, write_u32, write_u8},
     error::Error,
     util::SharedThread,
 };
+use boytacean_hashing::crc32c::crc32c;
 use core::fmt;
 use std::{
     borrow::BorrowMut,
-    cmp::max,
+    cmp::{max, min},
     convert::TryInto,
     fmt::{Display, Formatter},
-    io::Cursor,
+    io::{Cursor, Read, Write},
+    ops::Range,
     sync::{Arc, Mutex},
 };
 
 use crate::{
     assert_pedantic_gb,
+    bus::{BusMap, BusRange, BusRegion},
     color::{
-        rgb555_to_rgb888, rgb888_to_rgb1555_array, rgb888_to_rgb1555_u16, rgb888_to_rgb565,
-        rgb888_to_rgb565_u16, Pixel, PixelAlpha, RGB1555_SIZE, RGB565_SIZE, RGB888_SIZE, RGB_SIZE,
-        XRGB8888_SIZE,
+        rgb555_to_rgb888, rgb888_to_rgb1555_u16, rgb888_to_rgb565_u16,
+        rgb888_to_xrgb8888_u32_array, Pixel, PixelAlpha, PixelFormat, RGB1555_SIZE, RGB565_SIZE,
+        RGB888_SIZE, RGB_SIZE, XRGB8888_SIZE,
     },
     consts::{
-        BGP_ADDR, LCDC_ADDR, LYC_ADDR, LY_ADDR, OBP0_ADDR, OBP1_ADDR, SCX_ADDR, SCY_ADDR,
-        STAT_ADDR, WX_ADDR, WY_ADDR,
+        BCPD_ADDR, BCPS_ADDR, BGP_ADDR, LCDC_ADDR, LYC_ADDR, LY_ADDR, OBP0_ADDR, OBP1_ADDR,
+        OCPD_ADDR, OCPS_ADDR, OPRI_ADDR, SCX_ADDR, SCY_ADDR, STAT_ADDR, VBK_ADDR, WX_ADDR, WY_ADDR,
     },
-    gb::{GameBoyConfig, GameBoyMode},
+    gb::{ConfigChange, ConfigListener, GameBoy, GameBoyConfig, GameBoyMode},
+    io::{Io, IoComponent, IoRegister},
     mmu::BusComponent,
     panic_gb,
-    state::{StateComponent, StateFormat},
+    state::{StateComponent, StateFormat, StateMigration},
     warnln,
 };
 
@@ -63,6 +67,14 @@ pub const TILE_COUNT: usize = TILE_COUNT_CGB;
 /// the same time by the Game Boy.
 pub const OBJ_COUNT: usize = 40;
 
+/// The number of tiles per row in the tiles view buffer
+/// (see [`Ppu::render_tiles_buffer`]).
+pub const TILES_VIEW_COLUMNS: usize = 16;
+
+/// The width and height in pixels of a complete (32x32 tiles)
+/// background map (see [`Ppu::render_background_map`]).
+pub const BG_MAP_SIZE: usize = 256;
+
 /// The width of the Game Boy screen in pixels.
 pub const DISPLAY_WIDTH: usize = 160;
 
@@ -235,10 +247,16 @@ impl Tile {
     }
 
     pub fn palette_buffer(&self, palette: Palette) -> Vec<u8> {
-        self.buffer
-            .iter()
-            .flat_map(|p| palette[*p as usize])
-            .collect()
+        let mut buffer = Vec::with_capacity(TILE_WIDTH * TILE_HEIGHT * RGB_SIZE);
+        self.palette_buffer_into(palette, &mut buffer);
+        buffer
+    }
+
+    /// Converts the tile's color indexes into RGB pixels using the
+    /// provided palette, writing them into the (re-usable) buffer.
+    pub fn palette_buffer_into(&self, palette: Palette, buffer: &mut Vec<u8>) {
+        buffer.clear();
+        buffer.extend(self.buffer.iter().flat_map(|p| palette[*p as usize]));
     }
 }
 
@@ -309,6 +327,54 @@ impl ObjectData {
     }
 }
 
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl ObjectData {
+    /// The X position of the object on screen (OAM value minus 8).
+    pub fn x(&self) -> i16 {
+        self.x
+    }
+
+    /// The Y position of the object on screen (OAM value minus 16).
+    pub fn y(&self) -> i16 {
+        self.y
+    }
+
+    pub fn tile(&self) -> u8 {
+        self.tile
+    }
+
+    /// The CGB palette (0-7) of the object (CGB only).
+    pub fn palette_cgb(&self) -> u8 {
+        self.palette_cgb
+    }
+
+    /// The VRAM bank of the tile of the object (CGB only).
+    pub fn tile_bank(&self) -> u8 {
+        self.tile_bank
+    }
+
+    /// The DMG palette (OBP0 or OBP1) of the object.
+    pub fn palette(&self) -> u8 {
+        self.palette
+    }
+
+    pub fn xflip(&self) -> bool {
+        self.xflip
+    }
+
+    pub fn yflip(&self) -> bool {
+        self.yflip
+    }
+
+    pub fn bg_over(&self) -> bool {
+        self.bg_over
+    }
+
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+}
+
 impl Default for ObjectData {
     fn default() -> Self {
         Self::new()
@@ -430,6 +496,238 @@ pub struct PpuRegisters {
     pub lyc: u8,
 }
 
+/// Pixel for which the output of the fast (scanline) renderer
+/// differs from the one of the reference renderer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderMismatch {
+    pub x: u8,
+    pub y: u8,
+    pub expected: Pixel,
+    pub actual: Pixel,
+}
+
+impl Display for RenderMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "({}, {}) expected #{:02x}{:02x}{:02x} got #{:02x}{:02x}{:02x}",
+            self.x,
+            self.y,
+            self.expected[0],
+            self.expected[1],
+            self.expected[2],
+            self.actual[0],
+            self.actual[1],
+            self.actual[2]
+        )
+    }
+}
+
+/// Result of the comparison of a frame rendered by the fast
+/// (scanline) renderer against the reference renderer, used
+/// in the render comparison (pixel-perfect) mode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderDiff {
+    pub frame_index: u16,
+    pub count: u32,
+    pub first: Option<RenderMismatch>,
+}
+
+impl RenderDiff {
+    pub fn new(frame_index: u16) -> Self {
+        Self {
+            frame_index,
+            count: 0,
+            first: None,
+        }
+    }
+
+    pub fn is_match(&self) -> bool {
+        self.count == 0
+    }
+
+    fn register(&mut self, mismatch: RenderMismatch) {
+        if self.first.is_none() {
+            self.first = Some(mismatch);
+        }
+        self.count += 1;
+    }
+}
+
+impl Display for RenderDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.first {
+            Some(first) => write!(
+                f,
+                "Frame {} has {} mismatched pixel(s), first at {}",
+                self.frame_index, self.count, first
+            ),
+            None => write!(f, "Frame {} is pixel-perfect", self.frame_index),
+        }
+    }
+}
+
+/// The strategy used by the PPU to render the lines of the screen.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Each line is rendered at once at the end of the drawing
+    /// (mode 3), which always takes the same number of dots.
+    #[default]
+    Scanline,
+
+    /// Each line is rendered dot by dot through the emulation of
+    /// the pixel FIFO and fetcher, with the length of the drawing
+    /// depending on the scroll, window and objects of the line.
+    Fifo,
+}
+
+impl RenderMode {
+    pub fn description(&self) -> &'static str {
+        match self {
+            RenderMode::Scanline => "Scanline",
+            RenderMode::Fifo => "Pixel FIFO",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "scanline" => Ok(RenderMode::Scanline),
+            "fifo" => Ok(RenderMode::Fifo),
+            _ => Err(Error::CustomError(format!("Invalid render mode: {value}"))),
+        }
+    }
+}
+
+impl Display for RenderMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// Pixel stored in one of the FIFOs of the pixel pipeline, with its
+/// raw color (0 to 3) and attributes, the CGB background map attributes
+/// for background pixels and the OAM flags for object pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct FifoPixel {
+    color: u8,
+    attrs: u8,
+
+    /// The index of the object in OAM, used to resolve the
+    /// priority between objects (object pixels only).
+    index: u8,
+}
+
+/// State of the pixel FIFO renderer for the line currently in drawing.
+///
+/// The background fetcher takes 6 dots to fetch a tile row (tile number,
+/// low and high data), pushing it to the (empty) background FIFO, from
+/// where a pixel is shifted out per dot. Objects pause the shifting while
+/// being fetched and the start of the window restarts the fetcher.
+#[derive(Clone, Copy, Debug, Default)]
+struct PixelFifo {
+    /// If the drawing of a line is in progress.
+    active: bool,
+
+    /// The X coordinate of the next pixel to be output and the
+    /// number of dots elapsed since the start of the drawing.
+    x: u8,
+    dots: u16,
+
+    /// The number of dots before the first fetch of the line and the
+    /// number of pixels still to be discarded (fine scroll or window).
+    idle: u8,
+    discard: u8,
+
+    bg: [FifoPixel; TILE_WIDTH],
+    bg_len: u8,
+    obj: [FifoPixel; TILE_WIDTH],
+
+    /// The state of the background fetcher, with the tile (column)
+    /// in fetching and the data fetched so far.
+    fetch_step: u8,
+    fetch_x: u8,
+    fetch_ready: bool,
+    window: bool,
+    tile: u8,
+    attrs: u8,
+    low: u8,
+    high: u8,
+
+    /// The (up to 10) objects of the line sorted by their X coordinate,
+    /// the next one to be fetched and the dots left in its fetch.
+    objects: [u8; 10],
+    objects_count: u8,
+    object_next: u8,
+    object_fetch: u8,
+}
+
+impl PixelFifo {
+    fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        write_u8(writer, self.active as u8)?;
+        write_u8(writer, self.x)?;
+        write_u16(writer, self.dots)?;
+        write_u8(writer, self.idle)?;
+        write_u8(writer, self.discard)?;
+        for pixel in self.bg.iter().chain(self.obj.iter()) {
+            write_bytes(writer, &[pixel.color, pixel.attrs, pixel.index])?;
+        }
+        write_u8(writer, self.bg_len)?;
+        write_bytes(
+            writer,
+            &[
+                self.fetch_step,
+                self.fetch_x,
+                self.fetch_ready as u8,
+                self.window as u8,
+                self.tile,
+                self.attrs,
+                self.low,
+                self.high,
+            ],
+        )?;
+        write_bytes(writer, &self.objects)?;
+        write_bytes(
+            writer,
+            &[self.objects_count, self.object_next, self.object_fetch],
+        )?;
+        Ok(())
+    }
+
+    fn read<R: Read>(&mut self, reader: &mut R) -> Result<(), Error> {
+        self.active = read_u8(reader)? != 0;
+        self.x = read_u8(reader)?;
+        self.dots = read_u16(reader)?;
+        self.idle = read_u8(reader)?;
+        self.discard = read_u8(reader)?;
+        for pixel in self.bg.iter_mut().chain(self.obj.iter_mut()) {
+            let mut data = [0u8; 3];
+            read_into(reader, &mut data)?;
+            *pixel = FifoPixel {
+                color: data[0],
+                attrs: data[1],
+                index: data[2],
+            };
+        }
+        self.bg_len = read_u8(reader)?;
+        let mut fetch = [0u8; 8];
+        read_into(reader, &mut fetch)?;
+        self.fetch_step = fetch[0];
+        self.fetch_x = fetch[1];
+        self.fetch_ready = fetch[2] != 0;
+        self.window = fetch[3] != 0;
+        self.tile = fetch[4];
+        self.attrs = fetch[5];
+        self.low = fetch[6];
+        self.high = fetch[7];
+        read_into(reader, &mut self.objects)?;
+        self.objects_count = read_u8(reader)?;
+        self.object_next = read_u8(reader)?;
+        self.object_fetch = read_u8(reader)?;
+        Ok(())
+    }
+}
+
 /// Represents the Game Boy PPU (Pixel Processing Unit) and controls
 /// all of the logic behind the graphics processing and presentation.
 /// The PPU is responsible for the rendering of the screen and the
@@ -498,12 +796,31 @@ pub struct Ppu {
     /// to be drawn to the screen,
     obj_data: [ObjectData; OBJ_COUNT],
 
+    /// Index of the objects that intersect each of the lines of the
+    /// display (up to 10 per line, in OAM order), so that rendering a
+    /// line only touches its candidate objects instead of scanning
+    /// the complete OAM.
+    obj_lines: [[u8; 10]; DISPLAY_HEIGHT],
+
+    /// The number of objects in each of the lines of the index.
+    obj_lines_count: [u8; DISPLAY_HEIGHT],
+
+    /// If the index of objects per line is outdated (eg: an object
+    /// has been moved vertically or the object size has changed), the
+    /// index is lazily rebuilt before the next line is rendered.
+    obj_lines_dirty: bool,
+
     /// The base colors that are going to be used in the registration
     /// of the concrete palettes, this value basically controls the
     /// colors that are going to be shown for each of the four base
     /// values - 0x00, 0x01, 0x02, and 0x03.
     palette_colors: Palette,
 
+    /// The base colors set while a frame was being rendered, to be
+    /// applied at the start of the next V-Blank so that no frame is
+    /// ever rendered with a mix of the old and new colors.
+    palette_colors_pending: Option<Palette>,
+
     /// The palette of colors that is currently loaded in Game Boy
     /// and used for background (tiles) and window. The value of
     /// thi field can be computed value from [`Self::palettes[0]`].
@@ -540,6 +857,11 @@ pub struct Ppu {
     /// and [`Self::palettes_color_obj`] .
     palettes_color: [[u8; 64]; 2],
 
+    /// Callback to be called whenever one of the color palettes
+    /// changes (CGB only), receives if the palette is an object one,
+    /// the index of the palette and its new RGB888 colors.
+    palette_callback: fn(obj: bool, index: u8, palette: &Palette),
+
     /// The complete list of attributes for the first background
     /// map that is located in 0x9800-0x9BFF (CGB only).
     bg_map_attrs_0: [TileData; 1024],
@@ -654,6 +976,17 @@ pub struct Ppu {
     /// prevent unnecessary resource usage.
     frame_buffer_index: u16,
 
+    /// The frame buffer converted into the pixel format of the last
+    /// call to [`Ppu::frame_buffer_format`], re-used between frames.
+    frame_conversion: Vec<u8>,
+
+    /// The pixel format of the converted frame buffer.
+    frame_conversion_format: PixelFormat,
+
+    /// Index of the frame of the converted frame buffer, controlling
+    /// the caching of the conversion (just like `frame_buffer_index`).
+    frame_conversion_index: u16,
+
     stat_hblank: bool,
     stat_vblank: bool,
     stat_oam: bool,
@@ -667,6 +1000,30 @@ pub struct Ppu {
     /// the next CPU clock operation.
     int_stat: bool,
 
+    /// The current level of the internal STAT interrupt line, the
+    /// result of OR-ing all of the enabled STAT sources, the interrupt
+    /// is only requested on its rising edge (STAT quirks only).
+    stat_line: bool,
+
+    /// If the STAT quirks are emulated, meaning that the STAT interrupt
+    /// is requested on the rising edge of the STAT line (blocking the
+    /// sources while the line is high) and that the DMG "STAT write bug"
+    /// (spurious interrupt on writes to STAT) is reproduced.
+    stat_quirks: bool,
+
+    /// If blank (synthetic) frames are generated at the normal
+    /// frame rate while the LCD is off, keeping the frame driven
+    /// loops of the frontends flowing.
+    lcd_off_frames: bool,
+
+    /// The number of cycles since the LCD was switched off or since
+    /// the last synthetic frame was generated.
+    lcd_off_clock: u32,
+
+    /// If the last frame was a synthetic (blank) one generated
+    /// while the LCD is off, instead of a rendered one.
+    frame_synthetic: bool,
+
     /// Flag that controls if the DMG compatibility mode is
     /// enabled meaning that some of the PPU decisions will
     /// be made differently to address this special situation
@@ -681,6 +1038,46 @@ pub struct Ppu {
     /// Game Boy emulator, that can be used to control the behaviour
     /// of Game Boy emulation.
     gbc: SharedThread<GameBoyConfig>,
+
+    /// If the render comparison mode is enabled, meaning that every
+    /// line rendered by the fast renderer is verified against the
+    /// (slow) reference renderer and the differences are logged.
+    compare_render: bool,
+
+    /// The differences found so far for the frame currently in
+    /// rendering (render comparison mode only).
+    frame_render_diff: RenderDiff,
+
+    /// The differences found for the last completely rendered
+    /// frame (render comparison mode only).
+    render_diff: RenderDiff,
+
+    /// The number of frames with at least one mismatched pixel
+    /// since the render comparison mode was enabled.
+    mismatched_frames: u32,
+
+    /// If the lines that changed between frames are tracked, so that
+    /// the frontends can update only the changed part of the screen.
+    dirty_tracking: bool,
+
+    /// The lines that changed since the last clear of the dirty
+    /// lines (dirty tracking only).
+    dirty_lines: [bool; DISPLAY_HEIGHT],
+
+    /// The checksum (CRC32C) of the contents of each of the lines
+    /// when they were last rendered (dirty tracking only).
+    line_checksums: [u32; DISPLAY_HEIGHT],
+
+    /// The strategy used to render the lines of the screen.
+    render_mode: RenderMode,
+
+    /// The state of the line in drawing (pixel FIFO render mode only).
+    fifo: PixelFifo,
+
+    /// The length in dots of the H-Blank of the current line, which
+    /// compensates the variable length of the drawing so that every
+    /// line takes 456 dots (pixel FIFO render mode only).
+    hblank_clock: u16,
 }
 
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
@@ -724,7 +1121,11 @@ impl Ppu {
             vram_offset: 0x0000,
             tiles: [Tile { buffer: [0u8; 64] }; TILE_COUNT],
             obj_data: [ObjectData::default(); OBJ_COUNT],
+            obj_lines: [[0u8; 10]; DISPLAY_HEIGHT],
+            obj_lines_count: [0u8; DISPLAY_HEIGHT],
+            obj_lines_dirty: true,
             palette_colors: PALETTE_COLORS,
+            palette_colors_pending: None,
             palette_bg: [[0u8; RGB_SIZE]; PALETTE_SIZE],
             palette_obj_0: [[0u8; RGB_SIZE]; PALETTE_SIZE],
             palette_obj_1: [[0u8; RGB_SIZE]; PALETTE_SIZE],
@@ -732,6 +1133,7 @@ impl Ppu {
             palettes_color_obj: [[[0u8; RGB_SIZE]; PALETTE_SIZE]; 8],
             palettes: [0u8; 3],
             palettes_color: [[0u8; 64]; 2],
+            palette_callback: |_, _, _| {},
             bg_map_attrs_0: [TileData::default(); 1024],
             bg_map_attrs_1: [TileData::default(); 1024],
             obj_priority: false,
@@ -759,15 +1161,33 @@ impl Ppu {
             first_frame: false,
             frame_index: 0,
             frame_buffer_index: u16::MAX,
+            frame_conversion: Vec::new(),
+            frame_conversion_format: PixelFormat::default(),
+            frame_conversion_index: u16::MAX,
             stat_hblank: false,
             stat_vblank: false,
             stat_oam: false,
             stat_lyc: false,
             int_vblank: false,
             int_stat: false,
+            stat_line: false,
+            stat_quirks: true,
+            lcd_off_frames: false,
+            lcd_off_clock: 0,
+            frame_synthetic: false,
             dmg_compat: false,
             gb_mode: mode,
             gbc,
+            compare_render: false,
+            frame_render_diff: RenderDiff::default(),
+            render_diff: RenderDiff::default(),
+            mismatched_frames: 0,
+            dirty_tracking: false,
+            dirty_lines: [false; DISPLAY_HEIGHT],
+            line_checksums: [0; DISPLAY_HEIGHT],
+            render_mode: RenderMode::default(),
+            fifo: PixelFifo::default(),
+            hblank_clock: 204,
         }
     }
 
@@ -782,6 +1202,7 @@ impl Ppu {
         self.vram_offset = 0x0000;
         self.tiles = [Tile { buffer: [0u8; 64] }; TILE_COUNT];
         self.obj_data = [ObjectData::default(); OBJ_COUNT];
+        self.obj_lines_dirty = true;
         self.palette_bg = [[0u8; RGB_SIZE]; PALETTE_SIZE];
         self.palette_obj_0 = [[0u8; RGB_SIZE]; PALETTE_SIZE];
         self.palette_obj_1 = [[0u8; RGB_SIZE]; PALETTE_SIZE];
@@ -814,21 +1235,35 @@ impl Ppu {
         self.first_frame = false;
         self.frame_index = 0;
         self.frame_buffer_index = u16::MAX;
+        self.frame_conversion_index = u16::MAX;
+        self.mark_dirty();
         self.stat_hblank = false;
         self.stat_vblank = false;
         self.stat_oam = false;
         self.stat_lyc = false;
         self.int_vblank = false;
         self.int_stat = false;
+        self.stat_line = false;
+        self.lcd_off_clock = 0;
+        self.frame_synthetic = false;
         self.dmg_compat = false;
+        self.frame_render_diff = RenderDiff::default();
+        self.render_diff = RenderDiff::default();
+        self.mismatched_frames = 0;
+        self.fifo = PixelFifo::default();
+        self.hblank_clock = 204;
+        self.apply_palette_colors();
     }
 
     pub fn clear_screen(&mut self, hard: bool) {
         self.mode = PpuMode::HBlank;
         self.mode_clock = 0;
+        self.fifo.active = false;
+        self.hblank_clock = 204;
         self.ly = 0;
         self.int_vblank = false;
         self.int_stat = false;
+        self.stat_line = false;
         self.window_counter = 0;
         if hard {
             self.first_frame = true;
@@ -838,8 +1273,20 @@ impl Ppu {
 
     pub fn clock(&mut self, cycles: u16) {
         // in case the LCD is currently off then we skip the current
-        // clock operation the PPU should not work
+        // clock operation the PPU should not work, generating blank
+        // frames at the normal rate if requested
         if !self.switch_lcd {
+            if self.palette_colors_pending.is_some() {
+                self.apply_palette_colors();
+            }
+            if self.lcd_off_frames {
+                self.lcd_off_clock += cycles as u32;
+                if self.lcd_off_clock >= GameBoy::LCD_CYCLES {
+                    self.lcd_off_clock -= GameBoy::LCD_CYCLES;
+                    self.frame_synthetic = true;
+                    self.frame_index = self.frame_index.wrapping_add(1);
+                }
+            }
             return;
         }
 
@@ -865,6 +1312,19 @@ impl Ppu {
                     self.mode_clock -= 80;
                 }
             }
+            PpuMode::VramRead if self.render_mode == RenderMode::Fifo => {
+                if !self.fifo.active {
+                    self.start_line_fifo();
+                }
+                while self.mode_clock > 0 && self.fifo.active {
+                    self.clock_fifo();
+                    self.mode_clock -= 1;
+                }
+                if !self.fifo.active {
+                    self.mode = PpuMode::HBlank;
+                    self.update_stat()
+                }
+            }
             PpuMode::VramRead => {
                 if self.mode_clock >= 172 {
                     self.render_line();
@@ -875,7 +1335,7 @@ impl Ppu {
                 }
             }
             PpuMode::HBlank => {
-                if self.mode_clock >= 204 {
+                if self.mode_clock >= self.hblank_clock {
                     // increments the window counter making sure that the
                     // valid is only incremented when both the WX and WY
                     // registers make sense (are within range), the window
@@ -897,11 +1357,14 @@ impl Ppu {
                     if self.ly == 144 {
                         self.int_vblank = true;
                         self.mode = PpuMode::VBlank;
+                        if self.palette_colors_pending.is_some() {
+                            self.apply_palette_colors();
+                        }
                     } else {
                         self.mode = PpuMode::OamRead;
                     }
 
-                    self.mode_clock -= 204;
+                    self.mode_clock -= self.hblank_clock;
                     self.update_stat()
                 }
             }
@@ -912,14 +1375,24 @@ impl Ppu {
                     // scanlines that are virtual and not real (off-screen)
                     self.ly += 1;
 
+                    // the LYC comparison keeps running during V-Blank
+                    // so the STAT line must be re-evaluated (STAT quirks)
+                    if self.stat_quirks && self.ly < 154 {
+                        self.update_stat()
+                    }
+
                     // in case the end of V-Blank has been reached then
                     // we must jump again to the OAM read mode and reset
                     // the scan line counter to the zero value
                     if self.ly == 154 {
+                        if self.compare_render {
+                            self.finish_render_diff();
+                        }
                         self.mode = PpuMode::OamRead;
                         self.ly = 0;
                         self.window_counter = 0;
                         self.first_frame = false;
+                        self.frame_synthetic = false;
                         self.frame_index = self.frame_index.wrapping_add(1);
                         self.update_stat()
                     }
@@ -975,22 +1448,24 @@ impl Ppu {
             OBP0_ADDR => self.palettes[1],
             // 0xFF49 — OBP1 (Non-CGB Mode only)
             OBP1_ADDR => self.palettes[2],
-            // 0xFF4A — WX
-            WX_ADDR => self.wy,
-            // 0xFF4B — WY
-            WY_ADDR => self.wx,
+            // 0xFF4A — WY
+            WY_ADDR => self.wy,
+            // 0xFF4B — WX
+            WX_ADDR => self.wx,
             // 0xFF4F — VBK (CGB only)
-            0xff4f => self.vram_bank | 0xfe,
+            VBK_ADDR => self.vram_bank | 0xfe,
             // 0xFF68 — BCPS/BGPI (CGB only)
-            0xff68 => self.palette_address_bg | if self.auto_increment_bg { 0x80 } else { 0x00 },
+            BCPS_ADDR => self.palette_address_bg | if self.auto_increment_bg { 0x80 } else { 0x00 },
             // 0xFF69 — BCPD/BGPD (CGB only)
-            0xff69 => self.palettes_color[0][self.palette_address_bg as usize],
+            BCPD_ADDR => self.palettes_color[0][self.palette_address_bg as usize],
             // 0xFF6A — OCPS/OBPI (CGB only)
-            0xff6a => self.palette_address_obj | if self.auto_increment_obj { 0x80 } else { 0x00 },
+            OCPS_ADDR => {
+                self.palette_address_obj | if self.auto_increment_obj { 0x80 } else { 0x00 }
+            }
             // 0xFF6B — OCPD/OBPD (CGB only)
-            0xff6b => self.palettes_color[1][self.palette_address_obj as usize],
+            OCPD_ADDR => self.palettes_color[1][self.palette_address_obj as usize],
             // 0xFF6C — OPRI (CGB only)
-            0xff6c => (if self.obj_priority { 0x01 } else { 0x00 }) | 0xfe,
+            OPRI_ADDR => (if self.obj_priority { 0x01 } else { 0x00 }) | 0xfe,
             _ => {
                 warnln!("Reading from unknown PPU location 0x{:04x}", addr);
                 #[allow(unreachable_code)]
@@ -1020,8 +1495,10 @@ impl Ppu {
             // 0xFF80-0xFFFE - High RAM (HRAM)
             0xff80..=0xfffe => self.hram[(addr & 0x007f) as usize] = value,
             LCDC_ADDR => {
+                let lcd_was_on = self.switch_lcd;
                 self.switch_bg = value & 0x01 == 0x01;
                 self.switch_obj = value & 0x02 == 0x02;
+                self.obj_lines_dirty |= self.obj_size != (value & 0x04 == 0x04);
                 self.obj_size = value & 0x04 == 0x04;
                 self.bg_map = value & 0x08 == 0x08;
                 self.bg_tile = value & 0x10 == 0x10;
@@ -1033,21 +1510,45 @@ impl Ppu {
                 // to clear the screen, this is the expected
                 // behaviour for this specific situation
                 if !self.switch_lcd {
+                    if lcd_was_on {
+                        self.lcd_off_clock = 0;
+                    }
                     self.clear_screen(true)
                 }
             }
             STAT_ADDR => {
+                // on the DMG writing to STAT briefly enables all of the
+                // sources, requesting an interrupt in H-Blank, V-Blank or
+                // on a LYC match, in case the line is not already high
+                if self.stat_quirks
+                    && self.gb_mode == GameBoyMode::Dmg
+                    && self.switch_lcd
+                    && !self.stat_line
+                    && (self.lyc == self.ly
+                        || self.mode == PpuMode::HBlank
+                        || self.mode == PpuMode::VBlank)
+                {
+                    self.int_stat = true;
+                }
                 self.stat_hblank = value & 0x08 == 0x08;
                 self.stat_vblank = value & 0x10 == 0x10;
                 self.stat_oam = value & 0x20 == 0x20;
                 self.stat_lyc = value & 0x40 == 0x40;
+                if self.stat_quirks && self.switch_lcd {
+                    self.update_stat();
+                }
             }
             // 0xFF42 — SCY: Background Y position
             SCY_ADDR => self.scy = value,
             // 0xFF43 — SCX: Background X position
             SCX_ADDR => self.scx = value,
             // 0xFF45 — LYC: LY compare
-            LYC_ADDR => self.lyc = value,
+            LYC_ADDR => {
+                self.lyc = value;
+                if self.stat_quirks && self.switch_lcd {
+                    self.update_stat();
+                }
+            }
             // 0xFF47 — BGP (Non-CGB Mode only)
             BGP_ADDR => {
                 if value == self.palettes[0] {
@@ -1092,62 +1593,70 @@ impl Ppu {
                 }
                 self.palettes[2] = value;
             }
-            // 0xFF4A — WX
-            WX_ADDR => self.wy = value,
-            // 0xFF4B — WY
-            WY_ADDR => self.wx = value,
+            // 0xFF4A — WY
+            WY_ADDR => self.wy = value,
+            // 0xFF4B — WX
+            WX_ADDR => self.wx = value,
             // 0xFF4F — VBK (CGB only)
-            0xff4f => {
+            VBK_ADDR => {
                 self.vram_bank = value & 0x01;
                 self.vram_offset = self.vram_bank as u16 * 0x2000;
             }
             // 0xFF68 — BCPS/BGPI (CGB only)
-            0xff68 => {
+            BCPS_ADDR => {
                 self.palette_address_bg = value & 0x3f;
                 self.auto_increment_bg = value & 0x80 == 0x80;
             }
             // 0xFF69 — BCPD/BGPD (CGB only)
-            0xff69 => {
+            BCPD_ADDR => {
                 let palette_index = self.palette_address_bg / 8;
                 let color_index = (self.palette_address_bg % 8) / 2;
 
                 let palette_color = &mut self.palettes_color[0];
+                let changed = palette_color[self.palette_address_bg as usize] != value;
                 palette_color[self.palette_address_bg as usize] = value;
                 let palette = &mut self.palettes_color_bg[palette_index as usize];
                 Self::compute_palette_color(palette, palette_color, palette_index, color_index);
+                if changed {
+                    (self.palette_callback)(false, palette_index, palette);
+                }
 
                 if self.auto_increment_bg {
                     self.palette_address_bg = (self.palette_address_bg + 1) & 0x3f;
                 }
             }
             // 0xFF6A — OCPS/OBPI (CGB only)
-            0xff6a => {
+            OCPS_ADDR => {
                 self.palette_address_obj = value & 0x3f;
                 self.auto_increment_obj = value & 0x80 == 0x80;
             }
             // 0xFF6B — OCPD/OBPD (CGB only)
-            0xff6b => {
+            OCPD_ADDR => {
                 let palette_index = self.palette_address_obj / 8;
                 let color_index = (self.palette_address_obj % 8) / 2;
 
                 let palette_color = &mut self.palettes_color[1];
+                let changed = palette_color[self.palette_address_obj as usize] != value;
                 palette_color[self.palette_address_obj as usize] = value;
                 let palette = &mut self.palettes_color_obj[palette_index as usize];
                 Self::compute_palette_color(palette, palette_color, palette_index, color_index);
+                if changed {
+                    (self.palette_callback)(true, palette_index, palette);
+                }
 
                 if self.auto_increment_obj {
                     self.palette_address_obj = (self.palette_address_obj + 1) & 0x3f;
                 }
             }
             // 0xFF6C — OPRI (CGB only)
-            0xff6c => self.obj_priority = value & 0x01 == 0x01,
+            OPRI_ADDR => self.obj_priority = value & 0x01 == 0x01,
             0xff7f => (),
             _ => warnln!("Writing in unknown PPU location 0x{:04x}", addr),
         }
     }
 
     pub fn frame_buffer(&mut self) -> &[u8; FRAME_BUFFER_SIZE] {
-        if self.gb_mode != GameBoyMode::Dmg {
+        if self.gb_mode == GameBoyMode::Cgb {
             return &self.frame_buffer;
         }
 
@@ -1167,84 +1676,95 @@ impl Ppu {
         &self.frame_buffer
     }
 
-    pub fn frame_buffer_xrgb8888(&mut self) -> [u8; FRAME_BUFFER_XRGB8888_SIZE] {
+    /// Obtains the frame buffer converted into the provided pixel
+    /// format, borrowed from an internal buffer that is re-used
+    /// between calls, with the conversion cached for the frame.
+    ///
+    /// Just like the (lazy) frame buffer the conversion is keyed by
+    /// the frame index, meaning that it should be obtained once the
+    /// frame is completed (eg: at the start of the next frame).
+    pub fn frame_buffer_format(&mut self, format: PixelFormat) -> &[u8] {
+        if self.frame_conversion_index == self.frame_index && self.frame_conversion_format == format
+        {
+            return &self.frame_conversion;
+        }
+
+        self.frame_buffer();
+        self.frame_conversion
+            .resize(DISPLAY_SIZE * format.pixel_size(), 0);
+        convert_frame_buffer(&self.frame_buffer[..], format, &mut self.frame_conversion);
+
+        self.frame_conversion_format = format;
+        self.frame_conversion_index = self.frame_index;
+        &self.frame_conversion
+    }
+
+    /// Converts the frame buffer into the provided pixel format,
+    /// writing it into the provided buffer, avoiding allocations.
+    ///
+    /// # Panics
+    ///
+    /// In case the buffer is smaller than the converted frame buffer,
+    /// that has `DISPLAY_SIZE * format.pixel_size()` bytes.
+    pub fn frame_buffer_into(&mut self, format: PixelFormat, buffer: &mut [u8]) {
         let frame_buffer = self.frame_buffer();
+        convert_frame_buffer(
+            frame_buffer,
+            format,
+            &mut buffer[..DISPLAY_SIZE * format.pixel_size()],
+        );
+    }
+
+    pub fn frame_buffer_xrgb8888_into(&mut self, buffer: &mut [u8]) {
+        self.frame_buffer_into(PixelFormat::Xrgb8888, buffer)
+    }
+
+    pub fn frame_buffer_rgb1555_into(&mut self, buffer: &mut [u8]) {
+        self.frame_buffer_into(PixelFormat::Rgb1555, buffer)
+    }
+
+    pub fn frame_buffer_rgb565_into(&mut self, buffer: &mut [u8]) {
+        self.frame_buffer_into(PixelFormat::Rgb565, buffer)
+    }
+
+    pub fn frame_buffer_xrgb8888(&mut self) -> [u8; FRAME_BUFFER_XRGB8888_SIZE] {
         let mut buffer = [0u8; FRAME_BUFFER_XRGB8888_SIZE];
-        for index in 0..DISPLAY_SIZE {
-            let (r, g, b) = (
-                frame_buffer[index * RGB_SIZE],
-                frame_buffer[index * RGB_SIZE + 1],
-                frame_buffer[index * RGB_SIZE + 2],
-            );
-            buffer[index * XRGB8888_SIZE] = b;
-            buffer[index * XRGB8888_SIZE + 1] = g;
-            buffer[index * XRGB8888_SIZE + 2] = r;
-            buffer[index * XRGB8888_SIZE + 3] = 0xff;
-        }
+        self.frame_buffer_xrgb8888_into(&mut buffer);
         buffer
     }
 
     pub fn frame_buffer_xrgb8888_u32(&mut self) -> [u32; FRAME_BUFFER_SIZE] {
-        let frame_buffer = self.frame_buffer();
         let mut buffer = [0u32; FRAME_BUFFER_SIZE];
-        for (index, pixel) in buffer.iter_mut().enumerate().take(DISPLAY_SIZE) {
-            let (r, g, b) = (
-                frame_buffer[index * RGB_SIZE],
-                frame_buffer[index * RGB_SIZE + 1],
-                frame_buffer[index * RGB_SIZE + 2],
-            );
-            *pixel = ((r as u32) << 16) | ((g as u32) << 8) | b as u32;
-        }
+        rgb888_to_xrgb8888_u32_array(self.frame_buffer(), &mut buffer);
         buffer
     }
 
     pub fn frame_buffer_rgb1555(&mut self) -> [u8; FRAME_BUFFER_RGB1555_SIZE] {
-        let frame_buffer = self.frame_buffer();
         let mut buffer = [0u8; FRAME_BUFFER_RGB1555_SIZE];
-        rgb888_to_rgb1555_array(frame_buffer, &mut buffer);
+        self.frame_buffer_rgb1555_into(&mut buffer);
         buffer
     }
 
     pub fn frame_buffer_rgb1555_u16(&mut self) -> [u16; FRAME_BUFFER_SIZE] {
         let frame_buffer = self.frame_buffer();
         let mut buffer = [0u16; FRAME_BUFFER_SIZE];
-        for (index, pixel) in buffer.iter_mut().enumerate().take(DISPLAY_SIZE) {
-            let (r, g, b) = (
-                frame_buffer[index * RGB_SIZE],
-                frame_buffer[index * RGB_SIZE + 1],
-                frame_buffer[index * RGB_SIZE + 2],
-            );
-            *pixel = rgb888_to_rgb1555_u16(r, g, b);
+        for (pixel, rgb) in buffer.iter_mut().zip(frame_buffer.chunks_exact(RGB_SIZE)) {
+            *pixel = rgb888_to_rgb1555_u16(rgb[0], rgb[1], rgb[2]);
         }
         buffer
     }
 
     pub fn frame_buffer_rgb565(&mut self) -> [u8; FRAME_BUFFER_RGB565_SIZE] {
-        let frame_buffer = self.frame_buffer();
         let mut buffer = [0u8; FRAME_BUFFER_RGB565_SIZE];
-        for index in 0..DISPLAY_SIZE {
-            let (r, g, b) = (
-                frame_buffer[index * RGB_SIZE],
-                frame_buffer[index * RGB_SIZE + 1],
-                frame_buffer[index * RGB_SIZE + 2],
-            );
-            let rgb565 = rgb888_to_rgb565(r, g, b);
-            buffer[index * RGB565_SIZE] = rgb565[0];
-            buffer[index * RGB565_SIZE + 1] = rgb565[1];
-        }
+        self.frame_buffer_rgb565_into(&mut buffer);
         buffer
     }
 
     pub fn frame_buffer_rgb565_u16(&mut self) -> [u16; FRAME_BUFFER_SIZE] {
         let frame_buffer = self.frame_buffer();
         let mut buffer = [0u16; FRAME_BUFFER_SIZE];
-        for (index, pixel) in buffer.iter_mut().enumerate().take(DISPLAY_SIZE) {
-            let (r, g, b) = (
-                frame_buffer[index * RGB_SIZE],
-                frame_buffer[index * RGB_SIZE + 1],
-                frame_buffer[index * RGB_SIZE + 2],
-            );
-            *pixel = rgb888_to_rgb565_u16(r, g, b);
+        for (pixel, rgb) in buffer.iter_mut().zip(frame_buffer.chunks_exact(RGB_SIZE)) {
+            *pixel = rgb888_to_rgb565_u16(rgb[0], rgb[1], rgb[2]);
         }
         buffer
     }
@@ -1262,7 +1782,7 @@ impl Ppu {
     /// This method is very slow and only useful for the DMG mode
     /// which can have its simple colors mapped to palettes.
     pub fn frame_buffer_palette(&self, palette_colors: &Palette) -> [u8; FRAME_BUFFER_SIZE] {
-        if self.gb_mode == GameBoyMode::Dmg {
+        if self.gb_mode != GameBoyMode::Cgb {
             let mut buffer = [0u8; FRAME_BUFFER_SIZE];
             for (index, pixel) in buffer.chunks_mut(RGB_SIZE).enumerate() {
                 let shade_index = self.shade_buffer[index];
@@ -1323,9 +1843,181 @@ impl Ppu {
         &self.tiles
     }
 
+    /// Obtains the size (width, height) in pixels of the buffer
+    /// returned by [`Self::render_tiles_buffer`], with the tiles
+    /// of the second VRAM bank placed to the right (CGB only).
+    pub fn tiles_view_size(&self) -> (usize, usize) {
+        let banks = if self.gb_mode == GameBoyMode::Cgb {
+            2
+        } else {
+            1
+        };
+        (
+            TILES_VIEW_COLUMNS * TILE_WIDTH * banks,
+            TILE_COUNT_DMG / TILES_VIEW_COLUMNS * TILE_HEIGHT,
+        )
+    }
+
+    /// Renders all the tiles in VRAM as an RGB888 buffer, with 16 tiles
+    /// per row (see [`Self::tiles_view_size`]), colored using the current
+    /// background palette (the first color palette in CGB).
+    pub fn render_tiles_buffer(&self) -> Vec<u8> {
+        let (width, height) = self.tiles_view_size();
+        let palette = self.view_palette_bg(0);
+        let mut buffer = vec![0u8; width * height * RGB_SIZE];
+        for (index, tile) in self.tiles[..width / TILE_WIDTH * height / TILE_HEIGHT]
+            .iter()
+            .enumerate()
+        {
+            let bank = index / TILE_COUNT_DMG;
+            let bank_index = index % TILE_COUNT_DMG;
+            let x = (bank * TILES_VIEW_COLUMNS + bank_index % TILES_VIEW_COLUMNS) * TILE_WIDTH;
+            let y = bank_index / TILES_VIEW_COLUMNS * TILE_HEIGHT;
+            Self::draw_tile(&mut buffer, width, x, y, tile, &palette, false, false);
+        }
+        buffer
+    }
+
+    /// Renders the complete (256x256 pixels) background map with the
+    /// provided index (0 for 0x9800 and 1 for 0x9C00) as an RGB888
+    /// buffer, independently of the scroll and of the visible screen,
+    /// using the current tile data addressing mode and CGB attributes.
+    pub fn render_background_map(&self, map_index: u8) -> Vec<u8> {
+        let map = map_index & 0x01 == 0x01;
+        let map_offset: usize = if map { 0x1c00 } else { 0x1800 };
+        let bg_map_attrs = if map {
+            &self.bg_map_attrs_1
+        } else {
+            &self.bg_map_attrs_0
+        };
+        let mut buffer = vec![0u8; BG_MAP_SIZE * BG_MAP_SIZE * RGB_SIZE];
+        for (index, tile_value) in self.vram[map_offset..map_offset + 1024].iter().enumerate() {
+            let mut tile_index = *tile_value as usize;
+            if !self.bg_tile && tile_index < 128 {
+                tile_index += 256;
+            }
+            let tile_attr = if self.gb_mode == GameBoyMode::Cgb && !self.dmg_compat {
+                &bg_map_attrs[index]
+            } else {
+                &DEFAULT_TILE_ATTR
+            };
+            tile_index += tile_attr.vram_bank as usize * TILE_COUNT_DMG;
+            Self::draw_tile(
+                &mut buffer,
+                BG_MAP_SIZE,
+                (index % 32) * TILE_WIDTH,
+                (index / 32) * TILE_HEIGHT,
+                &self.tiles[tile_index],
+                &self.view_palette_bg(tile_attr.palette),
+                tile_attr.xflip,
+                tile_attr.yflip,
+            );
+        }
+        buffer
+    }
+
+    /// Renders the frame as it looks up to the provided scanline as
+    /// an RGB888 buffer, the lines before `ly` are the ones already
+    /// rendered and the remaining ones are rendered using the current
+    /// register state (scroll, window, palettes, etc.).
+    ///
+    /// Useful for debug views that visualize the screen at a specific
+    /// raster split, for instance on mid-frame SCX or WX changes.
+    pub fn render_up_to_line(&self, ly: u8) -> Vec<u8> {
+        let ly = ly.min(DISPLAY_HEIGHT as u8);
+        let mut buffer = self.frame_buffer_palette(&self.palette_colors).to_vec();
+
+        // the window line counter continues from the current one in case
+        // the split is the current line, otherwise it's estimated from
+        // the window position as the window is considered always visible
+        let mut window_counter = if ly == self.ly {
+            self.window_counter
+        } else {
+            ly.saturating_sub(self.wy)
+        };
+        for line in ly..DISPLAY_HEIGHT as u8 {
+            let pixels = self.render_line_reference_at(line, window_counter);
+            let offset = line as usize * DISPLAY_WIDTH * RGB_SIZE;
+            for (index, pixel) in pixels.iter().enumerate() {
+                buffer[offset + index * RGB_SIZE..offset + (index + 1) * RGB_SIZE]
+                    .copy_from_slice(pixel);
+            }
+            if self.switch_window && line >= self.wy && self.wx < 167 {
+                window_counter = window_counter.wrapping_add(1);
+            }
+        }
+        buffer
+    }
+
+    /// Obtains the structured data of the 40 objects (sprites)
+    /// in the OAM, independently of them being visible or not.
+    pub fn object_table(&self) -> Vec<ObjectData> {
+        self.obj_data
+            .iter()
+            .enumerate()
+            .map(|(index, obj)| ObjectData {
+                index: index as u8,
+                ..*obj
+            })
+            .collect()
+    }
+
+    /// Obtains the palette for the rendering of the background views,
+    /// the provided CGB palette index is ignored in DMG (compat) mode.
+    fn view_palette_bg(&self, palette_index: u8) -> Palette {
+        if self.gb_mode == GameBoyMode::Cgb && !self.dmg_compat {
+            self.palettes_color_bg[palette_index as usize]
+        } else {
+            self.palette_bg
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw_tile(
+        buffer: &mut [u8],
+        width: usize,
+        x: usize,
+        y: usize,
+        tile: &Tile,
+        palette: &Palette,
+        xflip: bool,
+        yflip: bool,
+    ) {
+        for tile_y in 0..TILE_HEIGHT {
+            for tile_x in 0..TILE_WIDTH {
+                let color = &palette[tile.get_flipped(tile_x, tile_y, xflip, yflip) as usize];
+                let offset = ((y + tile_y) * width + x + tile_x) * RGB_SIZE;
+                buffer[offset..offset + RGB_SIZE].copy_from_slice(color);
+            }
+        }
+    }
+
+    /// Sets the base colors of the DMG palettes, safe to be called
+    /// at any time (eg: live palette editing), as while a frame is
+    /// being rendered the colors are only applied at the next V-Blank.
     pub fn set_palette_colors(&mut self, value: &Palette) {
-        self.palette_colors = *value;
-        self.compute_palettes()
+        self.palette_colors_pending = Some(*value);
+        if !self.switch_lcd || self.mode == PpuMode::VBlank {
+            self.apply_palette_colors();
+        }
+    }
+
+    pub fn palette_colors(&self) -> &Palette {
+        &self.palette_colors
+    }
+
+    /// If there are base colors waiting for the next
+    /// V-Blank to be applied.
+    pub fn palette_colors_pending(&self) -> bool {
+        self.palette_colors_pending.is_some()
+    }
+
+    fn apply_palette_colors(&mut self) {
+        if let Some(palette_colors) = self.palette_colors_pending.take() {
+            self.palette_colors = palette_colors;
+            self.compute_palettes();
+            self.mark_dirty();
+        }
     }
 
     pub fn palette_bg(&self) -> Palette {
@@ -1350,16 +2042,67 @@ impl Ppu {
             &mut [&mut self.palettes_color_bg, &mut self.palettes_color_obj],
             &self.palettes_color,
         );
+        for (index, palette) in self.palettes_color_bg.iter().enumerate() {
+            (self.palette_callback)(false, index as u8, palette);
+        }
+        for (index, palette) in self.palettes_color_obj.iter().enumerate() {
+            (self.palette_callback)(true, index as u8, palette);
+        }
+    }
+
+    /// Obtains the complete set of CGB color palettes as RGB888
+    /// colors, with the 8 background palettes in the first position
+    /// and the 8 object palettes in the second one.
+    pub fn palettes_color_rgb(&self) -> [[Palette; 8]; 2] {
+        [self.palettes_color_bg, self.palettes_color_obj]
+    }
+
+    /// Sets the RGB555 OBJ0, OBJ1 and BG palettes used by DMG games
+    /// running in CGB compatibility mode, as done by the CGB boot ROM.
+    pub fn set_compat_palettes(&mut self, palettes: &[[u16; 4]; 3]) {
+        // targets as the (BG or OBJ) palettes color index and the
+        // offset of the palette (OBJ0, OBJ1 and BG 0) in it
+        let targets = [(1, 0), (1, 8), (0, 0)];
+        let mut palettes_color = self.palettes_color;
+        for ((kind, start), palette) in targets.into_iter().zip(palettes) {
+            for (index, color) in palette.iter().enumerate() {
+                let offset = start + index * 2;
+                palettes_color[kind][offset..offset + 2].copy_from_slice(&color.to_le_bytes());
+            }
+        }
+        self.set_palettes_color(palettes_color);
+        self.compute_palettes();
+    }
+
+    /// Sets the callback to be called whenever a CGB color palette
+    /// changes, either from a write to the palette data registers
+    /// or from a complete reload of the palettes (eg: state load).
+    pub fn set_palette_callback(&mut self, callback: fn(obj: bool, index: u8, palette: &Palette)) {
+        self.palette_callback = callback;
     }
 
     pub fn ly(&self) -> u8 {
         self.ly
     }
 
+    pub fn wx(&self) -> u8 {
+        self.wx
+    }
+
+    pub fn wy(&self) -> u8 {
+        self.wy
+    }
+
     pub fn mode(&self) -> PpuMode {
         self.mode
     }
 
+    /// If the OAM is currently blocked from CPU access, as the
+    /// PPU is either scanning or reading it (modes 2 and 3).
+    pub fn oam_blocked(&self) -> bool {
+        self.switch_lcd && matches!(self.mode, PpuMode::OamRead | PpuMode::VramRead)
+    }
+
     pub fn frame_index(&self) -> u16 {
         self.frame_index
     }
@@ -1398,6 +2141,18 @@ impl Ppu {
         self.dmg_compat
     }
 
+    /// Whether the background map in use is the one at 0x9C00
+    /// (LCDC bit 3), otherwise it's the one at 0x9800.
+    pub fn bg_map(&self) -> bool {
+        self.bg_map
+    }
+
+    /// Whether the tile data addressing in use for background
+    /// is the unsigned 0x8000 one (LCDC bit 4).
+    pub fn bg_tile(&self) -> bool {
+        self.bg_tile
+    }
+
     pub fn set_dmg_compat(&mut self, value: bool) {
         self.dmg_compat = value;
 
@@ -1422,29 +2177,161 @@ impl Ppu {
         self.gbc = value;
     }
 
-    /// Fills the frame buffer with pixels of the provided color,
-    /// this method should represent the fastest way of achieving
-    /// the fill background with color operation.
-    pub fn fill_frame_buffer(&mut self, shade_index: u8) {
-        let color = &self.palette_colors[shade_index as usize];
-        self.color_buffer.fill(0);
-        self.shade_buffer.fill(shade_index);
-        self.frame_buffer_index = u16::MAX;
-        for pixel in self.frame_buffer.chunks_mut(RGB_SIZE) {
-            pixel[0] = color[0];
-            pixel[1] = color[1];
-            pixel[2] = color[2];
-        }
+    pub fn compare_render(&self) -> bool {
+        self.compare_render
     }
 
-    /// Clears the current frame buffer, setting the background color
-    /// for all the pixels in the frame buffer.
-    pub fn clear_frame_buffer(&mut self) {
-        self.fill_frame_buffer(0);
+    /// Enables or disables the render comparison (pixel-perfect) mode,
+    /// in which every rendered line is verified against the reference
+    /// renderer, this mode is slow and meant for debugging only.
+    pub fn set_compare_render(&mut self, value: bool) {
+        self.compare_render = value;
+        self.frame_render_diff = RenderDiff::new(self.frame_index);
+        self.render_diff = RenderDiff::default();
+        self.mismatched_frames = 0;
     }
 
-    /// Prints the tile data information to the stdout, this is
-    /// useful for debugging purposes.
+    /// Obtains the differences found in the last completely
+    /// rendered frame (render comparison mode only).
+    pub fn render_diff(&self) -> &RenderDiff {
+        &self.render_diff
+    }
+
+    pub fn mismatched_frames(&self) -> u32 {
+        self.mismatched_frames
+    }
+
+    pub fn dirty_tracking(&self) -> bool {
+        self.dirty_tracking
+    }
+
+    /// Enables or disables the tracking of the lines that changed
+    /// between frames, with all the lines considered dirty once
+    /// the tracking is enabled.
+    pub fn set_dirty_tracking(&mut self, value: bool) {
+        self.dirty_tracking = value;
+        self.dirty_lines = [value; DISPLAY_HEIGHT];
+    }
+
+    /// Obtains the lines of the screen that changed since the
+    /// last call to [`Ppu::clear_dirty_lines`], meaning that the
+    /// frames may be skipped without losing any changes.
+    pub fn dirty_lines(&self) -> &[bool; DISPLAY_HEIGHT] {
+        &self.dirty_lines
+    }
+
+    /// Obtains the smallest range of lines that includes all the
+    /// dirty lines, the rectangle (with the full width of the screen)
+    /// to be updated by the frontends, `None` if nothing changed.
+    pub fn dirty_range(&self) -> Option<Range<usize>> {
+        let first = self.dirty_lines.iter().position(|dirty| *dirty)?;
+        let last = self.dirty_lines.iter().rposition(|dirty| *dirty)?;
+        Some(first..last + 1)
+    }
+
+    pub fn clear_dirty_lines(&mut self) {
+        self.dirty_lines = [false; DISPLAY_HEIGHT];
+    }
+
+    /// Marks all the lines as dirty, to be used when the contents of
+    /// the frame buffer change outside of the rendering of the lines
+    /// (eg: new palette colors).
+    fn mark_dirty(&mut self) {
+        if self.dirty_tracking {
+            self.dirty_lines = [true; DISPLAY_HEIGHT];
+        }
+    }
+
+    /// Checks if the line that has just been rendered changed since
+    /// it was last rendered, comparing the checksums of the shades
+    /// (DMG) or of the RGB pixels (CGB), marking it as dirty if so.
+    fn update_dirty_line(&mut self) {
+        if !self.dirty_tracking || self.first_frame {
+            return;
+        }
+        let line = self.ly as usize;
+        if line >= DISPLAY_HEIGHT {
+            return;
+        }
+        let line_offset = line * DISPLAY_WIDTH;
+        let checksum = if self.gb_mode != GameBoyMode::Cgb {
+            crc32c(&self.shade_buffer[line_offset..line_offset + DISPLAY_WIDTH])
+        } else {
+            let frame_offset = line_offset * RGB_SIZE;
+            crc32c(&self.frame_buffer[frame_offset..frame_offset + DISPLAY_WIDTH * RGB_SIZE])
+        };
+        if checksum != self.line_checksums[line] {
+            self.line_checksums[line] = checksum;
+            self.dirty_lines[line] = true;
+        }
+    }
+
+    pub fn stat_quirks(&self) -> bool {
+        self.stat_quirks
+    }
+
+    /// Enables or disables the emulation of the STAT quirks, the
+    /// rising edge triggered STAT interrupt (with blocking) and the
+    /// DMG "STAT write bug".
+    pub fn set_stat_quirks(&mut self, value: bool) {
+        self.stat_quirks = value;
+        self.stat_line = self.switch_lcd && self.stat_level();
+    }
+
+    pub fn lcd_off_frames(&self) -> bool {
+        self.lcd_off_frames
+    }
+
+    /// Enables or disables the generation of blank (synthetic) frames
+    /// at the normal frame rate while the LCD is off.
+    pub fn set_lcd_off_frames(&mut self, value: bool) {
+        self.lcd_off_frames = value;
+        self.lcd_off_clock = 0;
+    }
+
+    pub fn frame_synthetic(&self) -> bool {
+        self.frame_synthetic
+    }
+
+    pub fn render_mode(&self) -> RenderMode {
+        self.render_mode
+    }
+
+    /// Sets the strategy used to render the lines of the screen, the
+    /// pixel FIFO mode is slower but handles the changes to the PPU
+    /// registers in the middle of a line and the variable length of
+    /// the drawing (mode 3).
+    pub fn set_render_mode(&mut self, value: RenderMode) {
+        self.render_mode = value;
+        self.fifo.active = false;
+        self.hblank_clock = 204;
+    }
+
+    /// Fills the frame buffer with pixels of the provided color,
+    /// this method should represent the fastest way of achieving
+    /// the fill background with color operation.
+    pub fn fill_frame_buffer(&mut self, shade_index: u8) {
+        let color = &self.palette_colors[shade_index as usize];
+        self.color_buffer.fill(0);
+        self.shade_buffer.fill(shade_index);
+        self.frame_buffer_index = u16::MAX;
+        self.frame_conversion_index = u16::MAX;
+        for pixel in self.frame_buffer.chunks_mut(RGB_SIZE) {
+            pixel[0] = color[0];
+            pixel[1] = color[1];
+            pixel[2] = color[2];
+        }
+        self.mark_dirty();
+    }
+
+    /// Clears the current frame buffer, setting the background color
+    /// for all the pixels in the frame buffer.
+    pub fn clear_frame_buffer(&mut self) {
+        self.fill_frame_buffer(0);
+    }
+
+    /// Prints the tile data information to the stdout, this is
+    /// useful for debugging purposes.
     pub fn print_tile_stdout(&self, tile_index: usize) {
         println!("{}", self.tiles[tile_index]);
     }
@@ -1533,7 +2420,10 @@ impl Ppu {
         }
         let obj = self.obj_data[obj_index].borrow_mut();
         match addr & 0x03 {
-            0x00 => obj.y = value as i16 - 16,
+            0x00 => {
+                obj.y = value as i16 - 16;
+                self.obj_lines_dirty = true;
+            }
             0x01 => obj.x = value as i16 - 8,
             0x02 => obj.tile = value,
             0x03 => {
@@ -1577,11 +2467,550 @@ impl Ppu {
     }
 
     fn render_line(&mut self) {
-        if self.gb_mode == GameBoyMode::Dmg {
+        if self.gb_mode != GameBoyMode::Cgb {
             self.render_line_dmg();
         } else {
             self.render_line_cgb();
         }
+        if self.compare_render && !self.first_frame {
+            self.compare_line();
+        }
+        self.update_dirty_line();
+    }
+
+    /// Verifies the line that has just been rendered by the fast
+    /// renderer against the output of the reference renderer,
+    /// registering any mismatched pixel in the current diff.
+    fn compare_line(&mut self) {
+        let reference = self.render_line_reference();
+        let line_offset = self.ly as usize * DISPLAY_WIDTH;
+        for (x, expected) in reference.iter().enumerate() {
+            let offset = line_offset + x;
+            let actual = if self.gb_mode != GameBoyMode::Cgb {
+                self.palette_colors[self.shade_buffer[offset] as usize]
+            } else {
+                let frame_offset = offset * RGB_SIZE;
+                [
+                    self.frame_buffer[frame_offset],
+                    self.frame_buffer[frame_offset + 1],
+                    self.frame_buffer[frame_offset + 2],
+                ]
+            };
+            if actual != *expected {
+                self.frame_render_diff.register(RenderMismatch {
+                    x: x as u8,
+                    y: self.ly,
+                    expected: *expected,
+                    actual,
+                });
+            }
+        }
+    }
+
+    /// Closes the diff of the frame that has just been rendered,
+    /// logging it in case there's at least one mismatched pixel.
+    fn finish_render_diff(&mut self) {
+        if !self.frame_render_diff.is_match() {
+            warnln!("{}", self.frame_render_diff);
+            self.mismatched_frames += 1;
+        }
+        self.render_diff = self.frame_render_diff;
+        self.frame_render_diff = RenderDiff::new(self.frame_index.wrapping_add(1));
+    }
+
+    /// Renders the current line pixel by pixel directly from the raw
+    /// VRAM and OAM contents, without any of the tile and object caches
+    /// or buffers used by the fast renderer.
+    ///
+    /// This renderer is slow and meant only to be used as the reference
+    /// in the render comparison mode.
+    fn render_line_reference(&self) -> [Pixel; DISPLAY_WIDTH] {
+        self.render_line_reference_at(self.ly, self.window_counter)
+    }
+
+    /// Renders the provided line with the reference renderer using
+    /// the current register state, `window_counter` being the internal
+    /// window line counter to be used for the line.
+    fn render_line_reference_at(&self, line: u8, window_counter: u8) -> [Pixel; DISPLAY_WIDTH] {
+        let mut line_buffer = [[0u8; RGB_SIZE]; DISPLAY_WIDTH];
+        let ly = line as usize;
+        let cgb = self.gb_mode == GameBoyMode::Cgb && !self.dmg_compat;
+        let switch_bg_window = cgb || self.switch_bg;
+        let obj_priority_mode = self.gb_mode != GameBoyMode::Cgb || self.obj_priority;
+        let bg_master = !cgb || self.switch_bg;
+        let obj_height = if self.obj_size {
+            TILE_DOUBLE_HEIGHT
+        } else {
+            TILE_HEIGHT
+        };
+
+        // selects the (up to 10) objects that intersect the current
+        // line, respecting the OAM order as the hardware does
+        let mut objects = [0usize; 10];
+        let mut objects_count = 0;
+        for index in 0..OBJ_COUNT {
+            if objects_count == objects.len() {
+                break;
+            }
+            let y = self.oam[index * 4] as i16 - 16;
+            if y <= ly as i16 && y + obj_height as i16 > ly as i16 {
+                objects[objects_count] = index;
+                objects_count += 1;
+            }
+        }
+
+        for (x, pixel) in line_buffer.iter_mut().enumerate() {
+            // computes the background or window pixel, taking into
+            // account that both may be disabled (blank pixel)
+            let (bg_pixel, bg_priority, bg_color) = if switch_bg_window {
+                let window =
+                    self.switch_window && line >= self.wy && x as i16 >= self.wx as i16 - 7;
+                let (map, map_x, map_y) = if window {
+                    (
+                        self.window_map,
+                        (x as i16 - (self.wx as i16 - 7)) as usize,
+                        window_counter as usize,
+                    )
+                } else {
+                    (
+                        self.bg_map,
+                        (x + self.scx as usize) & 0xff,
+                        (ly + self.scy as usize) & 0xff,
+                    )
+                };
+                let map_offset = if map { 0x1c00 } else { 0x1800 }
+                    + ((map_y >> 3) & 0x1f) * 32
+                    + ((map_x >> 3) & 0x1f);
+                let tile_number = self.vram[map_offset];
+                let attrs = if cgb {
+                    self.vram[0x2000 + map_offset]
+                } else {
+                    0x00
+                };
+                let tile_addr = if self.bg_tile {
+                    tile_number as usize * 16
+                } else {
+                    (0x1000 + tile_number as i8 as i32 * 16) as usize
+                };
+                let bg_pixel = self.tile_pixel_reference(
+                    tile_addr + if attrs & 0x08 == 0x08 { 0x2000 } else { 0x0000 },
+                    map_x & 0x07,
+                    map_y & 0x07,
+                    attrs & 0x20 == 0x20,
+                    attrs & 0x40 == 0x40,
+                );
+                let color = if cgb {
+                    self.palettes_color_bg[(attrs & 0x07) as usize][bg_pixel as usize]
+                } else {
+                    self.reference_color(0, &self.palette_bg, bg_pixel)
+                };
+                (bg_pixel, attrs & 0x80 == 0x80, color)
+            } else if self.gb_mode != GameBoyMode::Cgb {
+                (0, false, self.palette_colors[0])
+            } else {
+                (0, false, self.palette_bg[0])
+            };
+
+            // determines the object pixel with the highest priority for
+            // the current X position, transparent pixels are ignored
+            let mut obj_pixel: Option<(i16, u8, u8)> = None;
+            for &index in &objects[..objects_count] {
+                let obj_y = self.oam[index * 4] as i16 - 16;
+                let obj_x = self.oam[index * 4 + 1] as i16 - 8;
+                if (x as i16) < obj_x || (x as i16) >= obj_x + TILE_WIDTH as i16 {
+                    continue;
+                }
+                if let Some((winner_x, _, _)) = obj_pixel {
+                    if !obj_priority_mode || obj_x >= winner_x {
+                        continue;
+                    }
+                }
+                let tile = self.oam[index * 4 + 2];
+                let flags = self.oam[index * 4 + 3];
+                let mut row = (ly as i16 - obj_y) as usize;
+                if flags & 0x40 == 0x40 {
+                    row = obj_height - row - 1;
+                }
+                let tile = if self.obj_size {
+                    (tile & 0xfe) as usize + (row >> 3)
+                } else {
+                    tile as usize
+                };
+                let bank_offset = if cgb && flags & 0x08 == 0x08 {
+                    0x2000
+                } else {
+                    0x0000
+                };
+                let pixel = self.tile_pixel_reference(
+                    tile * 16 + bank_offset,
+                    (x as i16 - obj_x) as usize,
+                    row & 0x07,
+                    flags & 0x20 == 0x20,
+                    false,
+                );
+                if pixel != 0 {
+                    obj_pixel = Some((obj_x, pixel, flags));
+                }
+            }
+
+            // composes the final pixel using the object to background
+            // priority rules (both the OAM and the CGB attribute ones)
+            *pixel = match obj_pixel {
+                Some((_, obj_pixel, flags))
+                    if self.switch_obj
+                        && Self::obj_over_bg(
+                            bg_master,
+                            bg_pixel,
+                            bg_priority,
+                            flags & 0x80 == 0x80,
+                        ) =>
+                {
+                    if cgb {
+                        self.palettes_color_obj[(flags & 0x07) as usize][obj_pixel as usize]
+                    } else if flags & 0x10 == 0x10 {
+                        self.reference_color(2, &self.palette_obj_1, obj_pixel)
+                    } else {
+                        self.reference_color(1, &self.palette_obj_0, obj_pixel)
+                    }
+                }
+                _ => bg_color,
+            };
+        }
+
+        line_buffer
+    }
+
+    /// Starts the drawing of the current line by the pixel FIFO renderer,
+    /// selecting the objects of the line (OAM scan) in the order they
+    /// are going to be fetched.
+    fn start_line_fifo(&mut self) {
+        let mut fifo = PixelFifo {
+            active: true,
+            idle: 6,
+            discard: self.scx & 0x07,
+            ..Default::default()
+        };
+        let (objects, objects_count) = self.line_objects(self.ly);
+        fifo.objects = objects;
+        fifo.objects_count = objects_count;
+
+        // objects are fetched from left to right, with the ones at the
+        // same X coordinate fetched in OAM order (stable sort)
+        let oam = &self.oam;
+        fifo.objects[..fifo.objects_count as usize]
+            .sort_by_key(|index| oam[*index as usize * 4 + 1]);
+
+        self.fifo = fifo;
+    }
+
+    /// Runs a single dot of the pixel FIFO renderer, outputting
+    /// at most one pixel of the current line.
+    fn clock_fifo(&mut self) {
+        self.fifo.dots += 1;
+
+        if self.fifo.idle > 0 {
+            self.fifo.idle -= 1;
+            return;
+        }
+
+        // while an object is being fetched the output is paused, once
+        // its fetch is complete its pixels are merged into the FIFO
+        if self.fifo.object_fetch > 0 {
+            self.fifo.object_fetch -= 1;
+            if self.fifo.object_fetch == 0 {
+                self.merge_object_fifo();
+                self.fifo.object_next += 1;
+            }
+            return;
+        }
+
+        // the start of the window clears the background FIFO and
+        // restarts the fetcher using the window map
+        if !self.fifo.window
+            && self.switch_window
+            && self.ly >= self.wy
+            && self.fifo.x as i16 >= self.wx as i16 - 7
+        {
+            self.fifo.window = true;
+            self.fifo.bg_len = 0;
+            self.fifo.fetch_step = 0;
+            self.fifo.fetch_x = 0;
+            self.fifo.fetch_ready = false;
+            self.fifo.discard = 7u8.saturating_sub(self.wx);
+        }
+
+        if self.fifo.fetch_ready && self.fifo.bg_len == 0 {
+            self.push_bg_fifo();
+        }
+        if !self.fifo.fetch_ready {
+            self.fetch_bg_fifo();
+        }
+
+        // an object at the current X coordinate waits for the fetch
+        // of the background tile to complete (with the FIFO filled)
+        // and is then fetched
+        if self.switch_obj && self.fifo.object_next < self.fifo.objects_count {
+            let index = self.fifo.objects[self.fifo.object_next as usize] as usize;
+            let obj_x = self.oam[index * 4 + 1] as i16 - 8;
+            if obj_x <= self.fifo.x as i16 {
+                if self.fifo.fetch_ready && self.fifo.bg_len > 0 {
+                    self.fifo.object_fetch = 5;
+                }
+                return;
+            }
+        }
+
+        if self.fifo.bg_len == 0 {
+            return;
+        }
+        let bg = self.fifo.bg[TILE_WIDTH - self.fifo.bg_len as usize];
+        self.fifo.bg_len -= 1;
+        if self.fifo.discard > 0 {
+            self.fifo.discard -= 1;
+            return;
+        }
+        let obj = self.fifo.obj[0];
+        self.fifo.obj.copy_within(1.., 0);
+        self.fifo.obj[TILE_WIDTH - 1] = FifoPixel::default();
+
+        if !self.first_frame {
+            self.output_fifo(bg, obj);
+        }
+
+        self.fifo.x += 1;
+        if self.fifo.x == DISPLAY_WIDTH as u8 {
+            self.finish_line_fifo();
+        }
+    }
+
+    /// Runs a step of the background fetcher, reading the tile number
+    /// and attributes, the low and then the high data of the tile row
+    /// (2 dots each) from VRAM, using the registers values at each step.
+    fn fetch_bg_fifo(&mut self) {
+        let cgb = self.gb_mode == GameBoyMode::Cgb && !self.dmg_compat;
+        let map_y = if self.fifo.window {
+            self.window_counter as usize
+        } else {
+            (self.ly as usize + self.scy as usize) & 0xff
+        };
+        match self.fifo.fetch_step {
+            1 => {
+                let (map, map_x) = if self.fifo.window {
+                    (self.window_map, self.fifo.fetch_x as usize)
+                } else {
+                    (
+                        self.bg_map,
+                        (self.scx >> 3) as usize + self.fifo.fetch_x as usize,
+                    )
+                };
+                let map_offset =
+                    if map { 0x1c00 } else { 0x1800 } + ((map_y >> 3) & 0x1f) * 32 + (map_x & 0x1f);
+                self.fifo.tile = self.vram[map_offset];
+                self.fifo.attrs = if cgb {
+                    self.vram[0x2000 + map_offset]
+                } else {
+                    0x00
+                };
+            }
+            3 | 5 => {
+                let attrs = self.fifo.attrs;
+                let tile_addr = if self.bg_tile {
+                    self.fifo.tile as usize * 16
+                } else {
+                    (0x1000 + self.fifo.tile as i8 as i32 * 16) as usize
+                };
+                let bank_offset = if attrs & 0x08 == 0x08 { 0x2000 } else { 0x0000 };
+                let row = if attrs & 0x40 == 0x40 {
+                    TILE_HEIGHT_I - (map_y & 0x07)
+                } else {
+                    map_y & 0x07
+                };
+                let addr = bank_offset + tile_addr + row * 2;
+                if self.fifo.fetch_step == 3 {
+                    self.fifo.low = self.vram[addr];
+                } else {
+                    self.fifo.high = self.vram[addr + 1];
+                    self.fifo.fetch_ready = true;
+                }
+            }
+            _ => (),
+        }
+        self.fifo.fetch_step = (self.fifo.fetch_step + 1) % 6;
+    }
+
+    /// Pushes the fetched tile row into the (empty) background FIFO.
+    fn push_bg_fifo(&mut self) {
+        let xflip = self.fifo.attrs & 0x20 == 0x20;
+        for (x, pixel) in self.fifo.bg.iter_mut().enumerate() {
+            let shift = if xflip { x } else { TILE_WIDTH_I - x };
+            *pixel = FifoPixel {
+                color: ((self.fifo.low >> shift) & 0x01)
+                    | (((self.fifo.high >> shift) & 0x01) << 1),
+                attrs: self.fifo.attrs,
+                index: 0,
+            };
+        }
+        self.fifo.bg_len = TILE_WIDTH as u8;
+        self.fifo.fetch_x = self.fifo.fetch_x.wrapping_add(1);
+        self.fifo.fetch_ready = false;
+    }
+
+    /// Merges the row of the object that has just been fetched into the
+    /// object FIFO, where it only replaces the transparent pixels, or in
+    /// the CGB OAM priority mode the pixels of objects that come later
+    /// in OAM.
+    fn merge_object_fifo(&mut self) {
+        let cgb = self.gb_mode == GameBoyMode::Cgb && !self.dmg_compat;
+        let obj_priority_mode = self.gb_mode != GameBoyMode::Cgb || self.obj_priority;
+        let obj_height = if self.obj_size {
+            TILE_DOUBLE_HEIGHT
+        } else {
+            TILE_HEIGHT
+        };
+        let index = self.fifo.objects[self.fifo.object_next as usize];
+        let base = index as usize * 4;
+        let obj_y = self.oam[base] as i16 - 16;
+        let obj_x = self.oam[base + 1] as i16 - 8;
+        let flags = self.oam[base + 3];
+        let mut row = (self.ly as i16 - obj_y) as usize;
+        if flags & 0x40 == 0x40 {
+            row = obj_height - row - 1;
+        }
+        let tile = if self.obj_size {
+            (self.oam[base + 2] & 0xfe) as usize + (row >> 3)
+        } else {
+            self.oam[base + 2] as usize
+        };
+        let bank_offset = if cgb && flags & 0x08 == 0x08 {
+            0x2000
+        } else {
+            0x0000
+        };
+        for x in 0..TILE_WIDTH {
+            let slot = obj_x + x as i16 - self.fifo.x as i16;
+            if !(0..TILE_WIDTH as i16).contains(&slot) {
+                continue;
+            }
+            let color = self.tile_pixel_reference(
+                tile * 16 + bank_offset,
+                x,
+                row & 0x07,
+                flags & 0x20 == 0x20,
+                false,
+            );
+            let current = &mut self.fifo.obj[slot as usize];
+            if color != 0 && (current.color == 0 || (!obj_priority_mode && index < current.index)) {
+                *current = FifoPixel {
+                    color,
+                    attrs: flags,
+                    index,
+                };
+            }
+        }
+    }
+
+    /// Mixes the background and object pixels shifted out of the FIFOs
+    /// and outputs the resulting pixel at the current X coordinate.
+    fn output_fifo(&mut self, bg: FifoPixel, obj: FifoPixel) {
+        let cgb = self.gb_mode == GameBoyMode::Cgb && !self.dmg_compat;
+        let switch_bg_window = cgb || self.switch_bg;
+        let bg_master = !cgb || self.switch_bg;
+        let bg = if switch_bg_window {
+            bg
+        } else {
+            FifoPixel::default()
+        };
+        let obj_visible = self.switch_obj
+            && obj.color != 0
+            && Self::obj_over_bg(
+                bg_master,
+                bg.color,
+                bg.attrs & 0x80 == 0x80,
+                obj.attrs & 0x80 == 0x80,
+            );
+
+        let (color, palette_index) = if obj_visible {
+            (obj.color, if obj.attrs & 0x10 == 0x10 { 2 } else { 1 })
+        } else {
+            (bg.color, 0)
+        };
+        let offset = self.ly as usize * DISPLAY_WIDTH + self.fifo.x as usize;
+        self.color_buffer[offset] = color;
+        self.shade_buffer[offset] = if obj_visible || switch_bg_window {
+            (self.palettes[palette_index] >> (color * 2)) & 3
+        } else {
+            0
+        };
+        self.priority_buffer[offset] = bg.attrs & 0x80 == 0x80;
+
+        if self.gb_mode == GameBoyMode::Cgb {
+            let pixel = match (obj_visible, cgb) {
+                (true, true) => {
+                    self.palettes_color_obj[(obj.attrs & 0x07) as usize][color as usize]
+                }
+                (true, false) if palette_index == 2 => self.palette_obj_1[color as usize],
+                (true, false) => self.palette_obj_0[color as usize],
+                (false, true) => self.palettes_color_bg[(bg.attrs & 0x07) as usize][color as usize],
+                (false, false) => self.palette_bg[color as usize],
+            };
+            let frame_offset = offset * RGB_SIZE;
+            self.frame_buffer[frame_offset..frame_offset + RGB_SIZE].copy_from_slice(&pixel);
+        }
+    }
+
+    /// Completes the drawing of the current line, with the H-Blank
+    /// taking the remaining dots of the 376 dots of modes 3 and 0.
+    fn finish_line_fifo(&mut self) {
+        self.fifo.active = false;
+        self.hblank_clock = 376u16.saturating_sub(self.fifo.dots);
+        if self.compare_render && !self.first_frame {
+            self.compare_line();
+        }
+        self.update_dirty_line();
+    }
+
+    /// Resolves if the (non transparent) pixel of the object that won
+    /// the object priority for a position is drawn over the background
+    /// or window pixel, this is the single source of truth for the
+    /// object to background priority rules in all the modes.
+    ///
+    /// The `bg_master` flag is the LCDC bit 0, which in CGB mode is the
+    /// master priority (when cleared objects are always on top), while
+    /// on DMG (and CGB in DMG compatibility) it's always set as clearing
+    /// LCDC bit 0 blanks the background (`bg_pixel` is zero).
+    #[inline(always)]
+    fn obj_over_bg(bg_master: bool, bg_pixel: u8, bg_priority: bool, obj_bg_over: bool) -> bool {
+        !bg_master || bg_pixel == 0 || (!bg_priority && !obj_bg_over)
+    }
+
+    /// Obtains the color of a non CGB pixel, in DMG mode the color is
+    /// obtained from the shade index (as the fast renderer does) so
+    /// that it's independent from the computed palettes.
+    fn reference_color(&self, palette_index: usize, palette: &Palette, pixel: u8) -> Pixel {
+        if self.gb_mode != GameBoyMode::Cgb {
+            let shade = (self.palettes[palette_index] >> (pixel * 2)) & 0x03;
+            self.palette_colors[shade as usize]
+        } else {
+            palette[pixel as usize]
+        }
+    }
+
+    /// Obtains the (raw) pixel value of the tile at the provided VRAM
+    /// address decoding it directly from the VRAM bit planes.
+    fn tile_pixel_reference(
+        &self,
+        addr: usize,
+        x: usize,
+        y: usize,
+        xflip: bool,
+        yflip: bool,
+    ) -> u8 {
+        let x = if xflip { TILE_WIDTH_I - x } else { x };
+        let y = if yflip { TILE_HEIGHT_I - y } else { y };
+        let low = self.vram[addr + y * 2];
+        let high = self.vram[addr + y * 2 + 1];
+        let shift = TILE_WIDTH_I - x;
+        ((low >> shift) & 0x01) | (((high >> shift) & 0x01) << 1)
     }
 
     fn render_line_dmg(&mut self) {
@@ -1590,6 +3019,8 @@ impl Ppu {
         }
         if self.switch_bg {
             self.render_map_dmg(self.bg_map, self.scx, self.scy, 0, 0, self.ly);
+        } else {
+            self.render_blank_line();
         }
         if self.switch_bg && self.switch_window {
             self.render_map_dmg(self.window_map, 0, 0, self.wx, self.wy, self.window_counter);
@@ -1606,6 +3037,8 @@ impl Ppu {
         let switch_bg_window = (self.gb_mode.is_cgb() && !self.dmg_compat) || self.switch_bg;
         if switch_bg_window {
             self.render_map(self.bg_map, self.scx, self.scy, 0, 0, self.ly);
+        } else {
+            self.render_blank_line();
         }
         if switch_bg_window && self.switch_window {
             self.render_map(self.window_map, 0, 0, self.wx, self.wy, self.window_counter);
@@ -1615,6 +3048,26 @@ impl Ppu {
         }
     }
 
+    /// Renders the current line with both the background and the window
+    /// disabled (LCDC bit 0 cleared outside CGB mode), meaning that the
+    /// line is blank (color 0) and the objects are always drawn over it.
+    fn render_blank_line(&mut self) {
+        let line_offset = self.ly as usize * DISPLAY_WIDTH;
+        let line = line_offset..line_offset + DISPLAY_WIDTH;
+        self.color_buffer[line.clone()].fill(0);
+        self.shade_buffer[line.clone()].fill(0);
+        self.priority_buffer[line].fill(false);
+        if self.gb_mode == GameBoyMode::Cgb {
+            let color = self.palette_bg[0];
+            let frame_offset = line_offset * RGB_SIZE;
+            for pixel in self.frame_buffer[frame_offset..frame_offset + DISPLAY_WIDTH * RGB_SIZE]
+                .chunks_exact_mut(RGB_SIZE)
+            {
+                pixel.copy_from_slice(&color);
+            }
+        }
+    }
+
     fn render_map(&mut self, map: bool, scx: u8, scy: u8, wx: u8, wy: u8, ld: u8) {
         // in case the target window Y position has not yet been reached
         // then there's nothing to be done, returns control flow immediately
@@ -1622,14 +3075,9 @@ impl Ppu {
             return;
         }
 
-        // selects the correct background attributes map based on the bg map flag
-        // because the attributes are separated according to the map they represent
-        // this is only relevant for CGB mode
-        let bg_map_attrs = if map {
-            self.bg_map_attrs_1
-        } else {
-            self.bg_map_attrs_0
-        };
+        // the tile attributes are only used in CGB mode (without the
+        // DMG compatibility flag), otherwise the default ones apply
+        let cgb = self.gb_mode == GameBoyMode::Cgb && !self.dmg_compat;
 
         // obtains the base address of the background map using the bg map flag
         // that control which background map is going to be used
@@ -1645,66 +3093,29 @@ impl Ppu {
         // of tiles in each row (32)
         let row_offset = row_index * 32;
 
+        // copies the row of tile indexes of the map and the matching row of
+        // attributes (from the attributes map selected by the bg map flag),
+        // so that the metadata of each tile is a plain lookup in the loop
+        let mut tile_row = [0u8; 32];
+        tile_row.copy_from_slice(&self.vram[map_offset + row_offset..map_offset + row_offset + 32]);
+        let mut attr_row = [DEFAULT_TILE_ATTR; 32];
+        if cgb {
+            let bg_map_attrs = if map {
+                &self.bg_map_attrs_1
+            } else {
+                &self.bg_map_attrs_0
+            };
+            attr_row.copy_from_slice(&bg_map_attrs[row_offset..row_offset + 32]);
+        }
+
         // calculates the sprite line offset by using the SCX register
         // shifted by 3 meaning that the tiles are 8x8
         let mut line_offset = (scx >> 3) as usize;
 
-        // calculates the index of the initial tile in drawing,
-        // if the tile data set in use is #1, the indexes are
-        // signed, then calculates a real tile offset
-        let mut tile_index = self.vram[map_offset + row_offset + line_offset] as usize;
-        if !self.bg_tile && tile_index < 128 {
-            tile_index += 256;
-        }
-
-        // obtains the reference to the attributes of the new tile in
-        // drawing for meta processing (CGB only)
-        let mut tile_attr = if self.dmg_compat {
-            &DEFAULT_TILE_ATTR
-        } else {
-            &bg_map_attrs[row_offset + line_offset]
-        };
-
-        // retrieves the proper palette for the current tile in drawing
-        // taking into consideration if we're running in CGB mode or not
-        let mut palette = if self.gb_mode == GameBoyMode::Cgb {
-            if self.dmg_compat {
-                &self.palette_bg
-            } else {
-                &self.palettes_color_bg[tile_attr.palette as usize]
-            }
-        } else {
-            &self.palette_bg
-        };
-
         // obtains the current integer value (raw) for the background palette
         // this is going to be used for shade index value computation (DMG only)
         let palette_v = self.palettes[0];
 
-        // obtains the values of both X and Y flips for the current tile
-        // they will be applied by the get tile pixel method
-        let mut xflip = tile_attr.xflip;
-        let mut yflip = tile_attr.yflip;
-
-        // obtains the value the BG-to-OAM priority to be used in the computation
-        // of the final pixel value (CGB only)
-        let mut priority = tile_attr.priority;
-
-        // increments the tile index value by the required offset for the VRAM
-        // bank in which the tile is stored, this is only required for CGB mode
-        tile_index += tile_attr.vram_bank as usize * TILE_COUNT_DMG;
-
-        // obtains the reference to the tile that is going to be drawn
-        let mut tile = &self.tiles[tile_index];
-
-        // calculates the offset that is going to be used in the update of the color buffer
-        // which stores Game Boy colors from 0 to 3
-        let mut color_offset = self.ly as usize * DISPLAY_WIDTH;
-
-        // calculates the frame buffer offset position assuming the proper
-        // Game Boy screen width and RGB pixel (3 bytes) size
-        let mut frame_offset = self.ly as usize * DISPLAY_WIDTH * RGB_SIZE;
-
         // calculates both the current Y and X positions within the tiles
         // using the bitwise and operation as an effective modulus 8
         let y = (ld as usize + scy as usize) & 0x07;
@@ -1713,79 +3124,85 @@ impl Ppu {
         // calculates the initial tile X position in drawing, doing this
         // allows us to position the background map properly in the display
         let initial_index = max(wx as i16 - 7, 0) as usize;
-        color_offset += initial_index;
-        frame_offset += initial_index * RGB_SIZE;
+        let mut remaining = DISPLAY_WIDTH.saturating_sub(initial_index);
 
-        // iterates over all the pixels in the current line of the display
-        // to draw the background map, note that the initial index is used
+        // calculates the offset that is going to be used in the update of the color buffer
+        // which stores Game Boy colors from 0 to 3, and the frame buffer offset position
+        // assuming the proper Game Boy screen width and RGB pixel (3 bytes) size
+        let mut color_offset = self.ly as usize * DISPLAY_WIDTH + initial_index;
+        let mut frame_offset = color_offset * RGB_SIZE;
+
+        // iterates over the tiles visible in the current line of the display,
+        // resolving the metadata (tile, palette, flips and priority) once per
+        // tile and then drawing its pixels, note that the initial index is used
         // to skip the drawing of the tiles that are not visible (WX)
-        for _ in initial_index..DISPLAY_WIDTH {
-            // obtains the current pixel data from the tile
-            let pixel = tile.get_flipped(x, y, xflip, yflip);
-
-            // updates the pixel in the color buffer, which stores
-            // the raw pixel color information (unmapped) and then
-            // updates the shade buffer with the shade index
-            self.color_buffer[color_offset] = pixel;
-            self.shade_buffer[color_offset] = (palette_v >> (pixel * 2)) & 3;
-
-            // re-maps the pixel according to the current palette
-            // and sets the color pixel in the frame buffer
-            let color = &palette[pixel as usize];
-            self.frame_buffer[frame_offset] = color[0];
-            self.frame_buffer[frame_offset + 1] = color[1];
-            self.frame_buffer[frame_offset + 2] = color[2];
-
-            // updates the priority buffer with the current pixel
-            // the priority is only set in case the priority of
-            // the background (over OAM) is set in the attributes
-            // and the pixel is not transparent
-            self.priority_buffer[color_offset] = priority && pixel != 0;
-
-            // increments the current tile X position in drawing
-            x += 1;
-
-            // in case the end of tile width has been reached then
-            // a new tile must be retrieved for rendering
-            if x == TILE_WIDTH {
-                // resets the tile X position to the base value
-                // as a new tile is going to be drawn
-                x = 0;
-
-                // calculates the new line tile offset making sure that
-                // the maximum of 32 is not overflown
-                line_offset = (line_offset + 1) % 32;
+        while remaining > 0 {
+            let tile_attr = &attr_row[line_offset];
+
+            // calculates the tile index taking into consideration the
+            // bg tile value (signed indexes) and the VRAM bank in which
+            // the tile is stored (CGB only)
+            let mut tile_index = tile_row[line_offset] as usize;
+            if !self.bg_tile && tile_index < 128 {
+                tile_index += 256;
+            }
+            tile_index += tile_attr.vram_bank as usize * TILE_COUNT_DMG;
 
-                // calculates the tile index and makes sure the value
-                // takes into consideration the bg tile value
-                tile_index = self.vram[map_offset + row_offset + line_offset] as usize;
-                if !self.bg_tile && tile_index < 128 {
-                    tile_index += 256;
-                }
+            // retrieves the proper palette for the current tile in drawing
+            // taking into consideration if we're running in CGB mode or not
+            let palette = if cgb {
+                &self.palettes_color_bg[tile_attr.palette as usize]
+            } else {
+                &self.palette_bg
+            };
 
-                // in case the current mode is CGB and the DMG compatibility
-                // flag is not set then a series of tile values must be
-                // updated according to the tile attributes field
-                if self.gb_mode == GameBoyMode::Cgb && !self.dmg_compat {
-                    tile_attr = &bg_map_attrs[row_offset + line_offset];
-                    palette = &self.palettes_color_bg[tile_attr.palette as usize];
-                    xflip = tile_attr.xflip;
-                    yflip = tile_attr.yflip;
-                    priority = tile_attr.priority;
-                    tile_index += tile_attr.vram_bank as usize * TILE_COUNT_DMG;
-                }
+            // obtains the row of pixels of the tile to be drawn, with the
+            // Y flip applied, the X flip is applied per pixel
+            let tile_y = if tile_attr.yflip {
+                TILE_HEIGHT_I - y
+            } else {
+                y
+            };
+            let tile_pixels = self.tiles[tile_index].get_row(tile_y);
+
+            // draws the visible pixels of the tile, limited by the
+            // end of the line (the last tile may be partially drawn)
+            let count = min(TILE_WIDTH - x, remaining);
+            for tile_x in x..x + count {
+                let pixel = if tile_attr.xflip {
+                    tile_pixels[TILE_WIDTH_I - tile_x]
+                } else {
+                    tile_pixels[tile_x]
+                };
+
+                // updates the pixel in the color buffer, which stores
+                // the raw pixel color information (unmapped) and then
+                // updates the shade buffer with the shade index
+                self.color_buffer[color_offset] = pixel;
+                self.shade_buffer[color_offset] = (palette_v >> (pixel * 2)) & 3;
+
+                // re-maps the pixel according to the current palette
+                // and sets the color pixel in the frame buffer
+                let color = &palette[pixel as usize];
+                self.frame_buffer[frame_offset] = color[0];
+                self.frame_buffer[frame_offset + 1] = color[1];
+                self.frame_buffer[frame_offset + 2] = color[2];
+
+                // updates the priority buffer with the current pixel
+                // the priority is only set in case the priority of
+                // the background (over OAM) is set in the attributes
+                // and the pixel is not transparent
+                self.priority_buffer[color_offset] = tile_attr.priority && pixel != 0;
 
-                // obtains the reference to the new tile in drawing
-                tile = &self.tiles[tile_index];
+                color_offset += 1;
+                frame_offset += RGB_SIZE;
             }
 
-            // increments the color offset by one, representing
-            // the drawing of one pixel
-            color_offset += 1;
-
-            // increments the offset of the frame buffer by the
-            // size of an RGB pixel (which is 3 bytes)
-            frame_offset += RGB_SIZE;
+            // moves to the next tile of the row, making sure that
+            // the maximum of 32 is not overflown (wraps around)
+            remaining -= count;
+            line_offset = (line_offset + 1) % 32;
+            x = 0;
         }
     }
 
@@ -1908,17 +3325,28 @@ impl Ppu {
         // coordinate takes priority in drawing the pixel
         let mut index_buffer = [-256i16; DISPLAY_WIDTH];
 
-        // determines if the object should always be placed over the
-        // possible background, this is only required for CGB mode
-        let always_over = if self.gb_mode == GameBoyMode::Cgb && !self.dmg_compat {
-            !self.switch_bg
+        // allocates the buffers that store the pixel and the index of the
+        // object that won the object priority for each of the line positions,
+        // a zero pixel value means that no object is present in the position
+        let mut obj_pixels = [0u8; DISPLAY_WIDTH];
+        let mut obj_indexes = [0usize; DISPLAY_WIDTH];
+
+        // determines the master priority of the background, which is
+        // only possible to be cleared (objects always over) in CGB mode
+        let bg_master = if self.gb_mode == GameBoyMode::Cgb && !self.dmg_compat {
+            self.switch_bg
         } else {
-            false
+            true
         };
 
-        // iterates over the complete set of available object to check
-        // the ones that require drawing and draws them
-        for index in 0..OBJ_COUNT {
+        // iterates over the objects that intersect the current line (as
+        // selected by the OAM scan) and resolves the object priority for
+        // each of their pixels (first step)
+        let (objects, objects_count) = self.line_objects(self.ly);
+        for index in objects[..objects_count as usize]
+            .iter()
+            .map(|index| *index as usize)
+        {
             // in case the limit on the number of objects to be draw per
             // line has been reached breaks the loop avoiding more draws
             if draw_count == 10 {
@@ -1944,41 +3372,6 @@ impl Ppu {
                 continue;
             }
 
-            let (palette, palette_index) = if self.gb_mode == GameBoyMode::Cgb {
-                if self.dmg_compat {
-                    if obj.palette == 0 {
-                        (&self.palette_obj_0, 0_u8)
-                    } else if obj.palette == 1 {
-                        (&self.palette_obj_1, 0_u8)
-                    } else {
-                        panic_gb!("Invalid object palette: {:02x}", obj.palette);
-                    }
-                } else {
-                    (&self.palettes_color_obj[obj.palette_cgb as usize], 0_u8)
-                }
-            } else if obj.palette == 0 {
-                (&self.palette_obj_0, 1_u8)
-            } else if obj.palette == 1 {
-                (&self.palette_obj_1, 2_u8)
-            } else {
-                panic_gb!("Invalid object palette: {:02x}", obj.palette);
-            };
-
-            // obtains the current integer value (raw) for the palette in use
-            // this is going to be used for shade index value computation (DMG only)
-            let palette_v = self.palettes[palette_index as usize];
-
-            // calculates the offset in the color buffer (raw color information
-            // from 0 to 3) for the sprit that is going to be drawn, this value
-            // is kept as a signed integer to allow proper negative number math
-            let mut color_offset = self.ly as i32 * DISPLAY_WIDTH as i32 + obj.x as i32;
-
-            // calculates the offset in the frame buffer for the sprite
-            // that is going to be drawn, this is going to be the starting
-            // point for the draw operation to be performed
-            let mut frame_offset =
-                (self.ly as i32 * DISPLAY_WIDTH as i32 + obj.x as i32) * RGB_SIZE as i32;
-
             // the relative title offset should range from 0 to 7 in 8x8
             // objects and from 0 to 15 in 8x16 objects
             let mut tile_offset = self.ly as i16 - obj.y;
@@ -2025,79 +3418,165 @@ impl Ppu {
 
             let tile_row = tile.get_row(tile_offset as usize);
 
-            // determines if the object should always be placed over the
-            // previously placed background or window pixels
-            let obj_over = always_over || !obj.bg_over;
-
             for tile_x in 0..TILE_WIDTH {
                 let x = obj.x + tile_x as i16;
                 let is_contained = (x >= 0) && (x < DISPLAY_WIDTH as i16);
-                if is_contained {
-                    // the object is only considered visible if no background or
-                    // window should be drawn over or if the underlying pixel
-                    // is transparent (zero value) meaning there's no background
-                    // or window for the provided pixel
-                    let mut is_visible = obj_over || self.color_buffer[color_offset as usize] == 0;
-
-                    // additionally (in CCG mode) the object is only considered to
-                    // be visible if the priority buffer is not set for the current
-                    // pixel, this means that the background is capturing priority
-                    // by having the BG-to-OAM priority bit set in the bg map attributes
-                    is_visible &= always_over || !self.priority_buffer[color_offset as usize];
-
-                    // determines if the current pixel has priority over a possible
-                    // one that has been drawn by a previous object, this happens
-                    // in case the current object has a small X coordinate according
-                    // to the MBR algorithm
-                    let has_priority = index_buffer[x as usize] == -256
-                        || (obj_priority_mode && obj.x < index_buffer[x as usize]);
-
-                    let pixel = tile_row[if obj.xflip {
-                        TILE_WIDTH_I - tile_x
-                    } else {
-                        tile_x
-                    }];
-                    if is_visible && has_priority && pixel != 0 {
-                        // marks the current pixel in iteration as "owned"
-                        // by the object with the defined X base position,
-                        // to be used in priority calculus
-                        index_buffer[x as usize] = obj.x;
-
-                        // updates the pixel in the color buffer, which stores
-                        // the raw pixel color information (unmapped) and then
-                        // updates the shade buffer with the shade index
-                        self.color_buffer[color_offset as usize] = pixel;
-                        self.shade_buffer[color_offset as usize] = (palette_v >> (pixel * 2)) & 3;
-
-                        // re-maps the pixel according to the object palette
-                        // and then sets the color pixel in the frame buffer
-                        let color = &palette[pixel as usize];
-                        self.frame_buffer[frame_offset as usize] = color[0];
-                        self.frame_buffer[frame_offset as usize + 1] = color[1];
-                        self.frame_buffer[frame_offset as usize + 2] = color[2];
-                    }
+                if !is_contained {
+                    continue;
                 }
 
-                // increment the color offset by one as this represents
-                // the advance of one color pixel
-                color_offset += 1;
+                // determines if the current pixel has priority over a possible
+                // one that has been resolved for a previous object, this happens
+                // in case the current object has a small X coordinate according
+                // to the MBR algorithm
+                let has_priority = index_buffer[x as usize] == -256
+                    || (obj_priority_mode && obj.x < index_buffer[x as usize]);
 
-                // increments the offset of the frame buffer by the
-                // size of an RGB pixel (which is 3 bytes)
-                frame_offset += RGB_SIZE as i32;
+                let pixel = tile_row[if obj.xflip {
+                    TILE_WIDTH_I - tile_x
+                } else {
+                    tile_x
+                }];
+                if has_priority && pixel != 0 {
+                    // marks the current pixel in iteration as "owned"
+                    // by the object with the defined X base position,
+                    // even if it ends up hidden behind the background,
+                    // as the hardware resolves the object priority first
+                    index_buffer[x as usize] = obj.x;
+                    obj_pixels[x as usize] = pixel;
+                    obj_indexes[x as usize] = index;
+                }
             }
 
             // increments the counter so that we're able to keep
             // track on the number of object drawn
             draw_count += 1;
         }
+
+        // calculates the offset in the color buffer for the start
+        // of the current line
+        let line_offset = self.ly as usize * DISPLAY_WIDTH;
+
+        // composes the pixels of the winning objects with the background
+        // and window ones using the object to background priority rules
+        // (second step), the frame buffer offset is the one of the color
+        // pixel scaled by the size of an RGB pixel (which is 3 bytes)
+        for (x, color_offset) in (line_offset..line_offset + DISPLAY_WIDTH).enumerate() {
+            let frame_offset = color_offset * RGB_SIZE;
+            let pixel = obj_pixels[x];
+            if pixel != 0
+                && Self::obj_over_bg(
+                    bg_master,
+                    self.color_buffer[color_offset],
+                    self.priority_buffer[color_offset],
+                    self.obj_data[obj_indexes[x]].bg_over,
+                )
+            {
+                let (palette, palette_index) = self.obj_palette(obj_indexes[x]);
+
+                // obtains the current integer value (raw) for the palette in use
+                // this is going to be used for shade index value computation (DMG only)
+                let palette_v = self.palettes[palette_index as usize];
+
+                // updates the pixel in the color buffer, which stores
+                // the raw pixel color information (unmapped) and then
+                // updates the shade buffer with the shade index
+                self.color_buffer[color_offset] = pixel;
+                self.shade_buffer[color_offset] = (palette_v >> (pixel * 2)) & 3;
+
+                // re-maps the pixel according to the object palette
+                // and then sets the color pixel in the frame buffer
+                let color = &palette[pixel as usize];
+                self.frame_buffer[frame_offset] = color[0];
+                self.frame_buffer[frame_offset + 1] = color[1];
+                self.frame_buffer[frame_offset + 2] = color[2];
+            }
+        }
+    }
+
+    /// Obtains the (up to 10) objects that intersect the provided line
+    /// in OAM order, as selected by the OAM scan of the hardware, using
+    /// the index of objects per line (rebuilt if outdated).
+    fn line_objects(&mut self, line: u8) -> ([u8; 10], u8) {
+        if line as usize >= DISPLAY_HEIGHT {
+            return ([0u8; 10], 0);
+        }
+        if self.obj_lines_dirty {
+            self.update_obj_lines();
+        }
+        (
+            self.obj_lines[line as usize],
+            self.obj_lines_count[line as usize],
+        )
+    }
+
+    /// Rebuilds the index of the objects per line from the current
+    /// OAM contents, iterating over the objects in OAM order so that
+    /// only the first 10 objects of each line are kept.
+    fn update_obj_lines(&mut self) {
+        let obj_height = if self.obj_size {
+            TILE_DOUBLE_HEIGHT
+        } else {
+            TILE_HEIGHT
+        } as i16;
+        self.obj_lines_count = [0u8; DISPLAY_HEIGHT];
+        for index in 0..OBJ_COUNT {
+            let y = self.oam[index * 4] as i16 - 16;
+            let start = y.max(0);
+            let end = (y + obj_height).min(DISPLAY_HEIGHT as i16);
+            for line in start..end {
+                let count = &mut self.obj_lines_count[line as usize];
+                if *count < 10 {
+                    self.obj_lines[line as usize][*count as usize] = index as u8;
+                    *count += 1;
+                }
+            }
+        }
+        self.obj_lines_dirty = false;
+    }
+
+    /// Obtains the palette to be used for the object with the provided
+    /// index together with the index of its raw palette (DMG only).
+    fn obj_palette(&self, index: usize) -> (Palette, u8) {
+        let obj = &self.obj_data[index];
+        if self.gb_mode == GameBoyMode::Cgb {
+            if self.dmg_compat {
+                if obj.palette == 0 {
+                    (self.palette_obj_0, 0_u8)
+                } else if obj.palette == 1 {
+                    (self.palette_obj_1, 0_u8)
+                } else {
+                    panic_gb!("Invalid object palette: {:02x}", obj.palette);
+                }
+            } else {
+                (self.palettes_color_obj[obj.palette_cgb as usize], 0_u8)
+            }
+        } else if obj.palette == 0 {
+            (self.palette_obj_0, 1_u8)
+        } else if obj.palette == 1 {
+            (self.palette_obj_1, 2_u8)
+        } else {
+            panic_gb!("Invalid object palette: {:02x}", obj.palette);
+        }
     }
 
     /// Runs an update operation on the LCD STAT interrupt meaning
     /// that the flag that controls it will be updated in case the conditions
     /// required for the LCD STAT interrupt to be triggered are met.
+    ///
+    /// With the STAT quirks the interrupt is only requested on the rising
+    /// edge of the STAT line, so a new source becoming active while another
+    /// one keeps the line high is "blocked" (no interrupt is requested).
     fn update_stat(&mut self) {
-        self.int_stat = self.stat_level();
+        if self.stat_quirks {
+            let level = self.stat_level();
+            if level && !self.stat_line {
+                self.int_stat = true;
+            }
+            self.stat_line = level;
+        } else {
+            self.int_stat = self.stat_level();
+        }
     }
 
     /// Obtains the current level of the LCD STAT interrupt by
@@ -2116,6 +3595,16 @@ impl Ppu {
     /// Notice that this is only applicable to the DMG running mode -
     /// either in the original DMG or in CGB with DMG compatibility.
     fn compute_palettes(&mut self) {
+        self.update_palettes();
+
+        // clears the frame buffer to allow the new background
+        // color to be used
+        self.clear_frame_buffer();
+    }
+
+    /// Re-computes the values of the (DMG) palettes from the palette
+    /// indexes, without touching the frame buffer.
+    fn update_palettes(&mut self) {
         if self.dmg_compat {
             Self::compute_palette(
                 &mut self.palette_bg,
@@ -2147,10 +3636,6 @@ impl Ppu {
                 self.palettes[2],
             );
         }
-
-        // clears the frame buffer to allow the new background
-        // color to be used
-        self.clear_frame_buffer();
     }
 
     /// Static method used for the base logic of computation of RGB
@@ -2218,6 +3703,63 @@ impl Ppu {
             );
         }
     }
+
+    /// Migrates a state of the first version of the schema, adding
+    /// the (default) internal state to the full format states, so
+    /// that the line in drawing (if any) is restarted by the renderer.
+    fn migrate_state_v1(mut data: Vec<u8>, format: StateFormat) -> Result<Vec<u8>, Error> {
+        if format != StateFormat::Full {
+            return Ok(data);
+        }
+        let mut cursor = Cursor::new(vec![]);
+        write_u16(&mut cursor, 0)?;
+        write_u32(&mut cursor, 0)?;
+        write_u8(&mut cursor, 0)?;
+        PixelFifo::default().write(&mut cursor)?;
+        write_u8(&mut cursor, 0)?;
+        data.extend(cursor.into_inner());
+        Ok(data)
+    }
+}
+
+impl Io for Ppu {
+    const IO_REGISTERS: &'static [IoRegister] = &[
+        IoRegister::new(LCDC_ADDR, "LCDC", IoComponent::Ppu, 0xff, 0xff),
+        IoRegister::new(STAT_ADDR, "STAT", IoComponent::Ppu, 0x7f, 0x78),
+        IoRegister::new(SCY_ADDR, "SCY", IoComponent::Ppu, 0xff, 0xff),
+        IoRegister::new(SCX_ADDR, "SCX", IoComponent::Ppu, 0xff, 0xff),
+        IoRegister::new(LY_ADDR, "LY", IoComponent::Ppu, 0xff, 0x00),
+        IoRegister::new(LYC_ADDR, "LYC", IoComponent::Ppu, 0xff, 0xff),
+        IoRegister::new(BGP_ADDR, "BGP", IoComponent::Ppu, 0xff, 0xff),
+        IoRegister::new(OBP0_ADDR, "OBP0", IoComponent::Ppu, 0xff, 0xff),
+        IoRegister::new(OBP1_ADDR, "OBP1", IoComponent::Ppu, 0xff, 0xff),
+        IoRegister::new(WY_ADDR, "WY", IoComponent::Ppu, 0xff, 0xff),
+        IoRegister::new(WX_ADDR, "WX", IoComponent::Ppu, 0xff, 0xff),
+        IoRegister::new(VBK_ADDR, "VBK", IoComponent::Ppu, 0x01, 0x01),
+        IoRegister::new(BCPS_ADDR, "BCPS", IoComponent::Ppu, 0xbf, 0xbf),
+        IoRegister::new(BCPD_ADDR, "BCPD", IoComponent::Ppu, 0xff, 0xff),
+        IoRegister::new(OCPS_ADDR, "OCPS", IoComponent::Ppu, 0xbf, 0xbf),
+        IoRegister::new(OCPD_ADDR, "OCPD", IoComponent::Ppu, 0xff, 0xff),
+        IoRegister::new(OPRI_ADDR, "OPRI", IoComponent::Ppu, 0x01, 0x01),
+    ];
+}
+
+impl ConfigListener for Ppu {
+    fn config_changed(&mut self, change: &ConfigChange) {
+        match *change {
+            ConfigChange::Mode(value) => self.set_gb_mode(value),
+            ConfigChange::StatQuirks(value) => self.set_stat_quirks(value),
+            ConfigChange::LcdOffFrames(value) => self.set_lcd_off_frames(value),
+            _ => {}
+        }
+    }
+}
+
+impl BusMap for Ppu {
+    const BUS_RANGES: &'static [BusRange] = &[
+        BusRange::new(0x8000, 0x9fff, BusRegion::Vram),
+        BusRange::new(0xfe00, 0xfeff, BusRegion::Oam),
+    ];
 }
 
 impl BusComponent for Ppu {
@@ -2231,6 +3773,8 @@ impl BusComponent for Ppu {
 }
 
 impl StateComponent for Ppu {
+    const STATE_MIGRATIONS: &'static [StateMigration] = &[Self::migrate_state_v1];
+
     fn state(&self, format: Option<StateFormat>) -> Result<Vec<u8>, Error> {
         let format = format.unwrap_or(StateFormat::Minimal);
 
@@ -2305,6 +3849,22 @@ impl StateComponent for Ppu {
         write_u8(&mut cursor, self.dmg_compat as u8)?;
         write_u8(&mut cursor, self.gb_mode as u8)?;
 
+        // the internal state of the line in drawing and of the mode
+        // timing, required for a pixel exact continuation of a
+        // state saved in the middle of a frame
+        if format == StateFormat::Full {
+            write_u16(&mut cursor, self.hblank_clock)?;
+            write_u32(&mut cursor, self.lcd_off_clock)?;
+            write_u8(&mut cursor, self.frame_synthetic as u8)?;
+            self.fifo.write(&mut cursor)?;
+            write_u8(&mut cursor, self.palette_colors_pending.is_some() as u8)?;
+            if let Some(palette_colors) = &self.palette_colors_pending {
+                for color in palette_colors {
+                    write_bytes(&mut cursor, color)?;
+                }
+            }
+        }
+
         Ok(cursor.into_inner())
     }
 
@@ -2364,6 +3924,7 @@ impl StateComponent for Ppu {
         self.switch_bg = read_u8(&mut cursor)? != 0;
         self.switch_obj = read_u8(&mut cursor)? != 0;
         self.obj_size = read_u8(&mut cursor)? != 0;
+        self.obj_lines_dirty = true;
         self.bg_map = read_u8(&mut cursor)? != 0;
         self.bg_tile = read_u8(&mut cursor)? != 0;
         self.switch_window = read_u8(&mut cursor)? != 0;
@@ -2377,6 +3938,8 @@ impl StateComponent for Ppu {
         self.first_frame = read_u8(&mut cursor)? != 0;
         self.frame_index = read_u16(&mut cursor)?;
         self.frame_buffer_index = read_u16(&mut cursor)?;
+        self.frame_conversion_index = u16::MAX;
+        self.mark_dirty();
         self.stat_hblank = read_u8(&mut cursor)? != 0;
         self.stat_vblank = read_u8(&mut cursor)? != 0;
         self.stat_oam = read_u8(&mut cursor)? != 0;
@@ -2385,6 +3948,34 @@ impl StateComponent for Ppu {
         self.int_stat = read_u8(&mut cursor)? != 0;
         self.dmg_compat = read_u8(&mut cursor)? != 0;
         self.gb_mode = read_u8(&mut cursor)?.into();
+        self.stat_line = self.switch_lcd && self.stat_level();
+
+        if format == StateFormat::Full {
+            self.hblank_clock = read_u16(&mut cursor)?;
+            self.lcd_off_clock = read_u32(&mut cursor)?;
+            self.frame_synthetic = read_u8(&mut cursor)? != 0;
+            self.fifo.read(&mut cursor)?;
+            self.palette_colors_pending = if read_u8(&mut cursor)? != 0 {
+                let mut palette_colors = [[0u8; RGB_SIZE]; PALETTE_SIZE];
+                for color in palette_colors.iter_mut() {
+                    read_into(&mut cursor, color)?;
+                }
+                Some(palette_colors)
+            } else {
+                None
+            };
+
+            // re-computes the values derived from the restored memory
+            // and palettes, keeping the (restored) frame buffer intact
+            if self.gb_mode == GameBoyMode::Cgb {
+                for addr in 0x9800..=0x9fff {
+                    let value = self.vram[0x2000 + (addr & 0x1fff) as usize];
+                    self.update_bg_map_attrs(addr, value);
+                }
+            }
+            self.set_palettes_color(self.palettes_color);
+            self.update_palettes();
+        }
 
         Ok(())
     }
@@ -2399,16 +3990,37 @@ impl Default for Ppu {
     }
 }
 
+/// Converts the (RGB888) frame buffer into the provided pixel format,
+/// one complete row at a time (using the SIMD paths if enabled).
+fn convert_frame_buffer(frame_buffer: &[u8], format: PixelFormat, buffer: &mut [u8]) {
+    let row_size = DISPLAY_WIDTH * format.pixel_size();
+    for (row, target) in frame_buffer
+        .chunks_exact(DISPLAY_WIDTH * RGB_SIZE)
+        .zip(buffer.chunks_exact_mut(row_size))
+    {
+        format.convert(row, target);
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+
     use crate::{
-        gb::GameBoyMode,
+        consts::{BGP_ADDR, LCDC_ADDR, LYC_ADDR, STAT_ADDR},
+        gb::{GameBoy, GameBoyConfig, GameBoyMode},
         state::{StateComponent, StateFormat},
     };
 
+    use crate::color::{PixelFormat, RGB_SIZE};
+
     use super::{
-        ObjectData, Ppu, PpuMode, Tile, COLOR_BUFFER_SIZE, FRAME_BUFFER_SIZE, HRAM_SIZE, OAM_SIZE,
-        OBJ_COUNT, SHADE_BUFFER_SIZE, TILE_COUNT, VRAM_SIZE,
+        ObjectData, PixelFifo, Ppu, PpuMode, RenderMode, Tile, BG_MAP_SIZE, COLOR_BUFFER_SIZE,
+        DISPLAY_HEIGHT, FRAME_BUFFER_SIZE, FRAME_BUFFER_XRGB8888_SIZE, HRAM_SIZE, OAM_SIZE,
+        OBJ_COUNT, PALETTE_COLORS, PALETTE_SIZE, SHADE_BUFFER_SIZE, TILE_COUNT, VRAM_SIZE,
     };
 
     #[test]
@@ -2488,11 +4100,22 @@ mod tests {
             int_stat: true,
             dmg_compat: true,
             gb_mode: GameBoyMode::Dmg,
+            lcd_off_clock: 0x18,
+            hblank_clock: 0x19,
+            fifo: PixelFifo {
+                active: true,
+                x: 0x1a,
+                dots: 0x1b,
+                bg_len: 0x04,
+                objects: [0x1c; 10],
+                ..Default::default()
+            },
+            palette_colors_pending: Some([[0x1d; RGB_SIZE]; PALETTE_SIZE]),
             ..Default::default()
         };
 
         let state = ppu.state(Some(StateFormat::Full)).unwrap();
-        assert_eq!(state.len(), 204714);
+        assert_eq!(state.len(), 204810);
 
         let mut new_ppu = Ppu::default();
         new_ppu.set_state(&state, Some(StateFormat::Full)).unwrap();
@@ -2543,6 +4166,27 @@ mod tests {
         assert!(new_ppu.int_stat);
         assert!(new_ppu.dmg_compat);
         assert_eq!(new_ppu.gb_mode, GameBoyMode::Dmg);
+        assert_eq!(new_ppu.lcd_off_clock, 0x18);
+        assert_eq!(new_ppu.hblank_clock, 0x19);
+        assert!(new_ppu.fifo.active);
+        assert_eq!(new_ppu.fifo.x, 0x1a);
+        assert_eq!(new_ppu.fifo.dots, 0x1b);
+        assert_eq!(new_ppu.fifo.bg_len, 0x04);
+        assert_eq!(new_ppu.fifo.objects, [0x1c; 10]);
+        assert_eq!(
+            new_ppu.palette_colors_pending,
+            Some([[0x1d; RGB_SIZE]; PALETTE_SIZE])
+        );
+
+        // states of the first version of the schema (without the
+        // internal state) are migrated, restarting the line in drawing
+        let legacy = &state[..204714];
+        new_ppu
+            .set_state_versioned(legacy, 1, StateFormat::Full)
+            .unwrap();
+        assert!(!new_ppu.fifo.active);
+        assert_eq!(new_ppu.hblank_clock, 0);
+        assert_eq!(new_ppu.palette_colors_pending, None);
     }
 
     #[test]
@@ -2630,4 +4274,463 @@ mod tests {
         assert!(new_ppu.dmg_compat);
         assert_eq!(new_ppu.gb_mode, GameBoyMode::Dmg);
     }
+
+    #[test]
+    fn test_compare_render() {
+        let mut gb = GameBoy::new(Some(GameBoyMode::Cgb));
+        gb.load(true).unwrap();
+        gb.load_rom_file("res/roms/test/cgb_acid2.gbc", None)
+            .unwrap();
+        gb.set_compare_render(true);
+        for _ in 0..60 {
+            gb.next_frame();
+        }
+        assert!(gb.compare_render());
+        assert!(gb.render_diff().is_match());
+        assert_eq!(gb.ppu_i().mismatched_frames(), 0);
+
+        // corrupts the tile cache used by the fast renderer, so that
+        // its output differs from the (VRAM based) reference renderer
+        gb.ppu().tiles = [Tile { buffer: [0x03; 64] }; TILE_COUNT];
+        gb.next_frame();
+        gb.next_frame();
+
+        let diff = gb.render_diff();
+        assert!(!diff.is_match());
+        assert!(diff.count > 0);
+        assert!(diff.first.is_some());
+        assert!(gb.ppu_i().mismatched_frames() > 0);
+    }
+
+    #[test]
+    fn test_palettes_color_rgb() {
+        static CHANGES: AtomicUsize = AtomicUsize::new(0);
+
+        let mut ppu = Ppu::default();
+        ppu.set_palette_callback(|obj, index, palette| {
+            assert!(obj);
+            assert_eq!(index, 1);
+            assert_eq!(palette[0], [0xf8, 0, 0]);
+            CHANGES.fetch_add(1, Ordering::Relaxed);
+        });
+
+        // writes pure red (0x001f) to the first color of the
+        // second object palette, using auto increment
+        ppu.write(0xff6a, 0x80 | 0x08);
+        ppu.write(0xff6b, 0x1f);
+        ppu.write(0xff6b, 0x00);
+        assert_eq!(CHANGES.load(Ordering::Relaxed), 1);
+
+        let palettes = ppu.palettes_color_rgb();
+        assert_eq!(palettes[1][1][0], [0xf8, 0, 0]);
+        assert_eq!(palettes[1][1][1], [0, 0, 0]);
+        assert_eq!(palettes[0][1][0], [0, 0, 0]);
+
+        // writing the same value again must not trigger a change
+        ppu.write(0xff6a, 0x08);
+        ppu.write(0xff6b, 0x1f);
+        assert_eq!(CHANGES.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_viewer_buffers() {
+        let mut ppu = Ppu::default();
+        ppu.set_palette_colors(&PALETTE_COLORS);
+        ppu.write(0xff47, 0xe4);
+        ppu.write(0xff40, 0x91);
+
+        // fills the first row of the second tile with color 3
+        // and maps the tile to the second position of map 0
+        ppu.write(0x8010, 0xff);
+        ppu.write(0x8011, 0xff);
+        ppu.write(0x9801, 0x01);
+
+        assert_eq!(ppu.tiles_view_size(), (128, 192));
+        let tiles = ppu.render_tiles_buffer();
+        assert_eq!(tiles.len(), 128 * 192 * RGB_SIZE);
+        assert_eq!(tiles[0..3], PALETTE_COLORS[0]);
+        assert_eq!(tiles[8 * RGB_SIZE..9 * RGB_SIZE], PALETTE_COLORS[3]);
+        assert_eq!(
+            tiles[128 * RGB_SIZE + 8 * RGB_SIZE..][..3],
+            PALETTE_COLORS[0]
+        );
+
+        let map = ppu.render_background_map(0);
+        assert_eq!(map.len(), BG_MAP_SIZE * BG_MAP_SIZE * RGB_SIZE);
+        assert_eq!(map[0..3], PALETTE_COLORS[0]);
+        assert_eq!(map[15 * RGB_SIZE..16 * RGB_SIZE], PALETTE_COLORS[3]);
+        assert_eq!(
+            ppu.render_background_map(1)[15 * RGB_SIZE..][..3],
+            PALETTE_COLORS[0]
+        );
+
+        ppu.write(0xfe04, 0x20);
+        ppu.write(0xfe05, 0x18);
+        ppu.write(0xfe06, 0x01);
+        ppu.write(0xfe07, 0x30);
+        let objects = ppu.object_table();
+        assert_eq!(objects.len(), OBJ_COUNT);
+        assert_eq!(objects[1].index(), 1);
+        assert_eq!(objects[1].y(), 0x10);
+        assert_eq!(objects[1].x(), 0x10);
+        assert_eq!(objects[1].tile(), 0x01);
+        assert!(objects[1].xflip());
+        assert_eq!(objects[1].palette(), 1);
+        assert_eq!(objects[2].index(), 2);
+    }
+
+    #[test]
+    fn test_obj_priority() {
+        let mut ppu = Ppu::default();
+        ppu.set_palette_colors(&PALETTE_COLORS);
+        ppu.write(0xff47, 0xe4);
+        ppu.write(0xff48, 0xff);
+        ppu.write(0xff49, 0xaa);
+        ppu.write(0xff40, 0x93);
+
+        // fills the first row of the second tile with color 1
+        // and maps the tile to the first position of map 0
+        ppu.write(0x8010, 0xff);
+        ppu.write(0x8011, 0x00);
+        ppu.write(0x9800, 0x01);
+
+        // places an object (behind the background) at X 0 and an
+        // overlapping one (over the background) at X 4, as the first
+        // one wins the object priority it hides the second one
+        ppu.write(0xfe00, 0x10);
+        ppu.write(0xfe01, 0x08);
+        ppu.write(0xfe02, 0x01);
+        ppu.write(0xfe03, 0x80);
+        ppu.write(0xfe04, 0x10);
+        ppu.write(0xfe05, 0x0c);
+        ppu.write(0xfe06, 0x01);
+        ppu.write(0xfe07, 0x10);
+
+        ppu.first_frame = false;
+        ppu.render_line();
+        assert_eq!(ppu.shade_buffer[0..8], [1; 8]);
+        assert_eq!(ppu.shade_buffer[8..12], [2; 4]);
+        assert_eq!(ppu.shade_buffer[12..16], [0; 4]);
+
+        // with the LCDC bit 0 cleared the background is blank and
+        // both objects are drawn over it
+        ppu.write(0xff40, 0x92);
+        ppu.render_line();
+        assert_eq!(ppu.shade_buffer[0..8], [3; 8]);
+        assert_eq!(ppu.shade_buffer[8..12], [2; 4]);
+        assert_eq!(ppu.shade_buffer[12..16], [0; 4]);
+    }
+
+    #[test]
+    fn test_render_mode_fifo() {
+        for (mode, path) in [
+            (GameBoyMode::Dmg, "res/roms/test/dmg_acid2.gb"),
+            (GameBoyMode::Cgb, "res/roms/test/cgb_acid2.gbc"),
+        ] {
+            let mut gb = GameBoy::new(Some(mode));
+            gb.load(true).unwrap();
+            gb.load_rom_file(path, None).unwrap();
+            gb.ppu().set_render_mode(RenderMode::Fifo);
+            gb.set_compare_render(true);
+            for _ in 0..60 {
+                gb.next_frame();
+            }
+            assert_eq!(gb.ppu_i().render_mode(), RenderMode::Fifo);
+            assert!(gb.render_diff().is_match(), "{}", gb.render_diff());
+            assert_eq!(gb.ppu_i().mismatched_frames(), 0);
+        }
+
+        assert_eq!(RenderMode::from_string("fifo").unwrap(), RenderMode::Fifo);
+        assert!(RenderMode::from_string("other").is_err());
+    }
+
+    #[test]
+    fn test_fifo_timing() {
+        // obtains the length in dots of the drawing (mode 3)
+        // of the first line of the frame
+        fn drawing_dots(ppu: &mut Ppu) -> u16 {
+            ppu.clear_screen(false);
+            ppu.mode = PpuMode::OamRead;
+            let mut dots = 0;
+            while ppu.mode != PpuMode::HBlank {
+                let mode = ppu.mode;
+                ppu.clock(1);
+                if mode == PpuMode::VramRead {
+                    dots += 1;
+                }
+            }
+            dots
+        }
+
+        let mut ppu = Ppu::default();
+        ppu.set_render_mode(RenderMode::Fifo);
+        ppu.write(0xff40, 0x93);
+        assert_eq!(drawing_dots(&mut ppu), 172);
+        assert_eq!(ppu.hblank_clock, 204);
+
+        // the fine scroll discards pixels at the start of the line
+        ppu.write(0xff43, 0x05);
+        assert_eq!(drawing_dots(&mut ppu), 177);
+
+        // an object aligned with the background tiles, and thus
+        // waiting for the complete fetch of a tile, is followed
+        // by an object in the middle of a tile
+        ppu.write(0xff43, 0x00);
+        ppu.write(0xfe00, 0x10);
+        ppu.write(0xfe01, 0x08);
+        assert_eq!(drawing_dots(&mut ppu), 183);
+        ppu.write(0xfe04, 0x10);
+        ppu.write(0xfe05, 0x15);
+        assert_eq!(drawing_dots(&mut ppu), 183 + 6);
+
+        // the start of the window restarts the fetcher
+        ppu.write(0xfe00, 0x00);
+        ppu.write(0xfe04, 0x00);
+        ppu.write(0xff4a, 0x00);
+        ppu.write(0xff4b, 0x57);
+        ppu.write(0xff40, 0xb3);
+        assert_eq!(drawing_dots(&mut ppu), 178);
+        assert_eq!(ppu.hblank_clock, 198);
+    }
+
+    #[test]
+    fn test_stat_quirks() {
+        let mut ppu = Ppu::default();
+        ppu.write(LYC_ADDR, 0x50);
+        ppu.write(LCDC_ADDR, 0x80);
+        ppu.write(LCDC_ADDR, 0x00);
+        ppu.write(LCDC_ADDR, 0x80);
+        assert_eq!(ppu.mode(), PpuMode::HBlank);
+
+        // writing to STAT during H-Blank in the DMG triggers the
+        // STAT write bug, requesting an interrupt
+        ppu.write(STAT_ADDR, 0x08);
+        assert!(ppu.int_stat());
+        ppu.set_int_stat(false);
+
+        // as the line is kept high by the H-Blank source, enabling
+        // the LYC source does not request a new interrupt (blocking)
+        ppu.write(STAT_ADDR, 0x48);
+        ppu.write(LYC_ADDR, 0x00);
+        assert!(!ppu.int_stat());
+
+        // once the line goes low a LYC match is a rising edge
+        ppu.clock(204);
+        assert_eq!(ppu.mode(), PpuMode::OamRead);
+        assert!(!ppu.int_stat());
+        ppu.write(LYC_ADDR, 0x01);
+        assert!(ppu.int_stat());
+
+        // without the quirks writing STAT has no side effects
+        ppu.set_stat_quirks(false);
+        ppu.set_int_stat(false);
+        ppu.write(STAT_ADDR, 0x08);
+        assert!(!ppu.int_stat());
+
+        // the STAT write bug is exclusive to the DMG
+        let mut ppu = Ppu::new(
+            GameBoyMode::Cgb,
+            Arc::new(Mutex::new(GameBoyConfig::default())),
+        );
+        ppu.write(LCDC_ADDR, 0x80);
+        ppu.write(LCDC_ADDR, 0x00);
+        ppu.write(LCDC_ADDR, 0x80);
+        ppu.write(LYC_ADDR, 0x50);
+        ppu.write(STAT_ADDR, 0x00);
+        assert!(!ppu.int_stat());
+    }
+
+    #[test]
+    fn test_lcd_off_frames() {
+        let mut ppu = Ppu::default();
+        ppu.write(LCDC_ADDR, 0x80);
+        ppu.write(LCDC_ADDR, 0x00);
+        for _ in 0..GameBoy::LCD_CYCLES / 72 + 1 {
+            ppu.clock(72);
+        }
+        assert_eq!(ppu.frame_index(), 0);
+
+        ppu.set_lcd_off_frames(true);
+        for _ in 0..GameBoy::LCD_CYCLES / 72 {
+            ppu.clock(72);
+        }
+        assert_eq!(ppu.frame_index(), 0);
+        ppu.clock(72);
+        assert_eq!(ppu.frame_index(), 1);
+        assert!(ppu.frame_synthetic());
+        assert!(ppu.frame_buffer().iter().all(|value| *value == 255));
+
+        // writing LCDC while the LCD is already off keeps the pace
+        // of the synthetic frames (48 cycles carried over)
+        ppu.write(LCDC_ADDR, 0x01);
+        for _ in 0..GameBoy::LCD_CYCLES / 72 - 1 {
+            ppu.clock(72);
+        }
+        assert_eq!(ppu.frame_index(), 1);
+        ppu.clock(72);
+        assert_eq!(ppu.frame_index(), 2);
+
+        // rendered frames are not synthetic
+        ppu.write(LCDC_ADDR, 0x80);
+        for _ in 0..GameBoy::LCD_CYCLES / 72 + 1 {
+            ppu.clock(72);
+        }
+        assert_eq!(ppu.frame_index(), 3);
+        assert!(!ppu.frame_synthetic());
+    }
+
+    #[test]
+    fn test_palette_colors_deferred() {
+        let colors = [
+            [0x10; RGB_SIZE],
+            [0x20; RGB_SIZE],
+            [0x30; RGB_SIZE],
+            [0x40; RGB_SIZE],
+        ];
+
+        // with the LCD off the colors are applied immediately
+        let mut ppu = Ppu::default();
+        ppu.set_palette_colors(&colors);
+        assert!(!ppu.palette_colors_pending());
+        assert_eq!(ppu.palette_colors(), &colors);
+
+        // while rendering a frame the colors are only applied once
+        // the V-Blank is reached, keeping the frame consistent
+        ppu.set_palette_colors(&PALETTE_COLORS);
+        ppu.write(LCDC_ADDR, 0x91);
+        while ppu.ly() < 72 {
+            ppu.clock(4);
+        }
+        ppu.set_palette_colors(&colors);
+        assert!(ppu.palette_colors_pending());
+        assert_eq!(ppu.palette_colors(), &PALETTE_COLORS);
+        while ppu.ly() < 144 {
+            ppu.clock(4);
+        }
+        assert_eq!(ppu.mode(), PpuMode::VBlank);
+        assert!(!ppu.palette_colors_pending());
+        assert_eq!(ppu.palette_colors(), &colors);
+
+        // during the V-Blank the colors are applied immediately
+        ppu.set_palette_colors(&PALETTE_COLORS);
+        assert_eq!(ppu.palette_colors(), &PALETTE_COLORS);
+    }
+
+    #[test]
+    fn test_line_objects() {
+        let mut ppu = Ppu::default();
+
+        // places 12 objects in lines 0 to 7 (only the first 10 of
+        // them are selected) and one partially off-screen at the top
+        for index in 0..12 {
+            ppu.write(0xfe00 + index * 4, 0x10);
+        }
+        ppu.write(0xfe00 + 12 * 4, 0x0c);
+        let (objects, count) = ppu.line_objects(0);
+        assert_eq!(count, 10);
+        assert_eq!(objects, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        let (_, count) = ppu.line_objects(4);
+        assert_eq!(count, 10);
+        let (objects, count) = ppu.line_objects(8);
+        assert_eq!(count, 0);
+        assert_eq!(objects, [0; 10]);
+        assert_eq!(ppu.line_objects(160), ([0; 10], 0));
+
+        // moving an object vertically and changing the object size
+        // both invalidate the index
+        ppu.write(0xfe00, 0x20);
+        let (objects, count) = ppu.line_objects(16);
+        assert_eq!((objects[0], count), (0, 1));
+        let (objects, _) = ppu.line_objects(0);
+        assert_eq!(objects, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        ppu.write(0xff40, 0x04);
+        let (objects, count) = ppu.line_objects(4);
+        assert_eq!(count, 10);
+        assert_eq!(objects[9], 10);
+        let (objects, count) = ppu.line_objects(8);
+        assert_eq!(count, 10);
+        assert_eq!(objects[9], 10);
+        let (_, count) = ppu.line_objects(16);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_frame_buffer_format() {
+        let colors = [
+            [0x10, 0x20, 0x30],
+            [0x40, 0x50, 0x60],
+            [0x70, 0x80, 0x90],
+            [0xa0, 0xb0, 0xc0],
+        ];
+        let mut ppu = Ppu::default();
+        ppu.set_palette_colors(&colors);
+        ppu.fill_frame_buffer(1);
+
+        let rgb565 = ppu.frame_buffer_rgb565();
+        assert_eq!(ppu.frame_buffer_format(PixelFormat::Rgb565), &rgb565[..]);
+
+        // the provided buffer may be larger than the frame buffer
+        let mut buffer = vec![0u8; FRAME_BUFFER_XRGB8888_SIZE + 4];
+        ppu.frame_buffer_xrgb8888_into(&mut buffer);
+        assert_eq!(&buffer[..4], &[0x60, 0x50, 0x40, 0xff]);
+        assert_eq!(&buffer[FRAME_BUFFER_XRGB8888_SIZE..], &[0x00; 4]);
+        assert_eq!(
+            ppu.frame_buffer_format(PixelFormat::Xrgb8888),
+            &buffer[..FRAME_BUFFER_XRGB8888_SIZE]
+        );
+
+        // changes to the frame buffer invalidate the cached conversion
+        ppu.fill_frame_buffer(2);
+        let frame = ppu.frame_buffer_format(PixelFormat::Xrgb8888);
+        assert_eq!(frame.len(), FRAME_BUFFER_XRGB8888_SIZE);
+        assert_eq!(&frame[..4], &[0x90, 0x80, 0x70, 0xff]);
+        let frame_buffer = ppu.frame_buffer().to_vec();
+        assert_eq!(
+            ppu.frame_buffer_format(PixelFormat::Rgb888),
+            &frame_buffer[..]
+        );
+    }
+
+    #[test]
+    fn test_dirty_lines() {
+        let mut ppu = Ppu::default();
+        let next_frame = |ppu: &mut Ppu| {
+            let frame_index = ppu.frame_index();
+            while ppu.frame_index() == frame_index {
+                ppu.clock(4);
+            }
+        };
+
+        ppu.set_dirty_tracking(true);
+        assert_eq!(ppu.dirty_range(), Some(0..DISPLAY_HEIGHT));
+        ppu.clear_dirty_lines();
+        assert_eq!(ppu.dirty_range(), None);
+
+        // a static frame leaves the lines clean
+        ppu.write(BGP_ADDR, 0xe4);
+        ppu.write(LCDC_ADDR, 0x91);
+        next_frame(&mut ppu);
+        ppu.clear_dirty_lines();
+        next_frame(&mut ppu);
+        assert_eq!(ppu.dirty_range(), None);
+
+        // changing the first row of the (only) tile in use changes
+        // every 8th line of the screen
+        ppu.write(0x8000, 0xff);
+        next_frame(&mut ppu);
+        assert_eq!(ppu.dirty_range(), Some(0..DISPLAY_HEIGHT - 7));
+        assert!(ppu.dirty_lines()[8]);
+        assert!(!ppu.dirty_lines()[9]);
+
+        // new palette colors (applied at the V-Blank) change
+        // the whole screen
+        ppu.clear_dirty_lines();
+        ppu.set_palette_colors(&PALETTE_COLORS);
+        assert_eq!(ppu.dirty_range(), None);
+        next_frame(&mut ppu);
+        assert_eq!(ppu.dirty_range(), Some(0..DISPLAY_HEIGHT));
+
+        ppu.set_dirty_tracking(false);
+        assert_eq!(ppu.dirty_range(), None);
+    }
 }
@@ -1,17 +1,61 @@
-use pyo3::{exceptions::PyException, prelude::*, types::PyBytes};
+use pyo3::{buffer::PyBuffer, exceptions::PyException, prelude::*, types::PyBytes};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
 
 use crate::{
+    cheats::search::{CheatSearch as CheatSearchBase, SearchOperator, SearchSize},
     gb::{GameBoy as GameBoyBase, GameBoyMode},
     gen::{COMPILATION_DATE, COMPILATION_TIME, COMPILER, COMPILER_VERSION, NAME, VERSION},
-    info::Info,
+    info::{BuildInfo, Info},
     pad::PadKey,
     ppu::{PaletteInfo, DISPLAY_HEIGHT, DISPLAY_WIDTH},
+    serial::SerialDevice,
     state::StateManager,
 };
 
+/// Disassembled instruction as exposed to Python, a tuple of
+/// address, bytes, mnemonic, operands and cycles.
+type DisasmEntry = (u16, Vec<u8>, String, Vec<String>, u8);
+
+/// Serial device that accumulates the bytes sent by the
+/// system, to be dispatched to the Python hooks.
+struct CaptureDevice {
+    bytes: Arc<Mutex<Vec<u8>>>,
+}
+
+impl SerialDevice for CaptureDevice {
+    fn send(&mut self) -> u8 {
+        0xff
+    }
+
+    fn receive(&mut self, byte: u8) {
+        self.bytes.lock().unwrap().push(byte);
+    }
+
+    fn allow_slave(&self) -> bool {
+        false
+    }
+
+    fn description(&self) -> String {
+        String::from("Capture")
+    }
+
+    fn state(&self) -> String {
+        format!("{} bytes pending", self.bytes.lock().unwrap().len())
+    }
+
+    fn id(&self) -> &'static str {
+        "capture"
+    }
+}
+
 #[pyclass]
 struct GameBoy {
     system: GameBoyBase,
+    breakpoints: HashSet<u16>,
+    serial_bytes: Option<Arc<Mutex<Vec<u8>>>>,
 }
 
 #[pymethods]
@@ -20,6 +64,8 @@ impl GameBoy {
     fn new(mode: u8) -> Self {
         Self {
             system: GameBoyBase::new(Some(GameBoyMode::from_u8(mode))),
+            breakpoints: HashSet::new(),
+            serial_bytes: None,
         }
     }
 
@@ -67,6 +113,36 @@ impl GameBoy {
         self.system.write_memory(addr, value);
     }
 
+    pub fn read_memory_range(&mut self, py: Python, addr: u16, length: usize) -> PyObject {
+        PyBytes::new(py, &self.system.read_memory_range(addr, length)).into()
+    }
+
+    pub fn write_memory_range(&mut self, addr: u16, data: &[u8]) {
+        self.system.write_memory_range(addr, data);
+    }
+
+    pub fn search_memory(&mut self, py: Python) -> PyObject {
+        PyBytes::new(py, &self.system.search_memory()).into()
+    }
+
+    /// Disassembles `count` instructions starting at `addr`, returning
+    /// a tuple of address, bytes, mnemonic, operands and cycles for each.
+    pub fn disassemble(&self, addr: u16, count: usize) -> Vec<DisasmEntry> {
+        self.system
+            .disassemble(addr, count)
+            .into_iter()
+            .map(|inst| {
+                (
+                    inst.address(),
+                    inst.bytes(),
+                    inst.mnemonic(),
+                    inst.operands(),
+                    inst.cycles(),
+                )
+            })
+            .collect()
+    }
+
     pub fn clock(&mut self) -> u16 {
         self.system.clock()
     }
@@ -91,10 +167,77 @@ impl GameBoy {
         self.system.next_frame()
     }
 
+    pub fn run_frame(&mut self, py: Python) -> PyObject {
+        PyBytes::new(py, self.system.run_frame()).into()
+    }
+
+    pub fn run_for_cycles(&mut self, cycles: u32) -> u16 {
+        self.system.run_for_cycles_with(cycles, |_| true).frames
+    }
+
+    pub fn watchdog_triggered(&self) -> bool {
+        self.system.watchdog_triggered()
+    }
+
+    pub fn lcd_off_frames(&self) -> bool {
+        self.system.lcd_off_frames()
+    }
+
+    pub fn set_lcd_off_frames(&mut self, value: bool) {
+        self.system.set_lcd_off_frames(value);
+    }
+
+    pub fn frame_synthetic(&self) -> bool {
+        self.system.frame_synthetic()
+    }
+
     pub fn step_to(&mut self, addr: u16) -> u32 {
         self.system.step_to(addr)
     }
 
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.remove(&addr);
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn breakpoints(&self) -> Vec<u16> {
+        let mut breakpoints = self.breakpoints.iter().copied().collect::<Vec<u16>>();
+        breakpoints.sort();
+        breakpoints
+    }
+
+    /// Runs until the next frame or until a breakpoint is reached,
+    /// returning the cycles run and the breakpoint (if any).
+    pub fn next_frame_breakpoints(&mut self, resume: bool) -> (u32, Option<u16>) {
+        self.system
+            .next_frame_breakpoints(&self.breakpoints, resume)
+    }
+
+    /// Attaches a serial device that captures the bytes sent by
+    /// the system, retrieved with `take_serial_bytes()`.
+    pub fn capture_serial(&mut self) {
+        let bytes = Arc::new(Mutex::new(vec![]));
+        self.system.attach_serial(Box::new(CaptureDevice {
+            bytes: bytes.clone(),
+        }));
+        self.serial_bytes = Some(bytes);
+    }
+
+    pub fn take_serial_bytes(&mut self, py: Python) -> PyObject {
+        let bytes = match &self.serial_bytes {
+            Some(bytes) => std::mem::take(&mut *bytes.lock().unwrap()),
+            None => vec![],
+        };
+        PyBytes::new(py, &bytes).into()
+    }
+
     pub fn key_press(&mut self, key: u8) {
         self.system.key_press(PadKey::from_u8(key))
     }
@@ -108,6 +251,30 @@ impl GameBoy {
         pybytes.into()
     }
 
+    /// Copies the frame buffer into the provided writable buffer
+    /// (eg: a pre-allocated numpy array of `(144, 160, 3)` bytes),
+    /// avoiding the allocation of a new object for every frame.
+    pub fn frame_buffer_into(&mut self, py: Python, buffer: &PyAny) -> PyResult<()> {
+        let buffer = PyBuffer::<u8>::get(buffer)?;
+        buffer.copy_from_slice(py, self.system.frame_buffer())
+    }
+
+    pub fn screenshot_png(&mut self, py: Python) -> PyObject {
+        PyBytes::new(py, &self.system.screenshot_png()).into()
+    }
+
+    pub fn start_recording(&mut self) {
+        self.system.start_recording()
+    }
+
+    pub fn stop_recording(&mut self, py: Python) -> PyObject {
+        PyBytes::new(py, &self.system.stop_recording()).into()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.system.is_recording()
+    }
+
     pub fn set_palette_colors(&mut self, colors_hex: &str) {
         let palette = PaletteInfo::from_colors_hex("default", colors_hex);
         self.system.ppu().set_palette_colors(palette.colors());
@@ -187,11 +354,96 @@ impl GameBoy {
     pub fn load_state(&mut self, data: &[u8]) -> PyResult<()> {
         StateManager::load(data, &mut self.system, None, None).map_err(PyErr::new::<PyException, _>)
     }
+
+    pub fn save_state_bytes(&mut self, py: Python) -> PyResult<PyObject> {
+        match self.system.save_state_bytes() {
+            Ok(data) => Ok(PyBytes::new(py, &data).into()),
+            Err(e) => Err(PyErr::new::<PyException, _>(e)),
+        }
+    }
+
+    pub fn load_state_bytes(&mut self, data: &[u8]) -> PyResult<()> {
+        self.system
+            .load_state_bytes(data)
+            .map_err(PyErr::new::<PyException, _>)
+    }
+
+    pub fn sram_bytes(&mut self, py: Python) -> PyObject {
+        PyBytes::new(py, &self.system.sram_bytes()).into()
+    }
+
+    pub fn set_sram_bytes(&mut self, data: &[u8]) {
+        self.system.set_sram_bytes(data);
+    }
+}
+
+#[pyclass]
+struct CheatSearch {
+    search: CheatSearchBase,
+}
+
+#[pymethods]
+impl CheatSearch {
+    #[new]
+    fn new(size: &str) -> PyResult<Self> {
+        let size = SearchSize::from_string(size).map_err(PyErr::new::<PyException, _>)?;
+        Ok(Self {
+            search: CheatSearchBase::new(size),
+        })
+    }
+
+    pub fn reset(&mut self) {
+        self.search.reset();
+    }
+
+    pub fn first_scan(
+        &mut self,
+        system: &mut GameBoy,
+        operator: &str,
+        value: Option<i32>,
+    ) -> PyResult<usize> {
+        let operator =
+            SearchOperator::from_string(operator).map_err(PyErr::new::<PyException, _>)?;
+        self.search
+            .first_scan(&system.system.search_memory(), operator, value)
+            .map_err(PyErr::new::<PyException, _>)
+    }
+
+    pub fn next_scan(
+        &mut self,
+        system: &mut GameBoy,
+        operator: &str,
+        value: Option<i32>,
+    ) -> PyResult<usize> {
+        let operator =
+            SearchOperator::from_string(operator).map_err(PyErr::new::<PyException, _>)?;
+        self.search
+            .next_scan(&system.system.search_memory(), operator, value)
+            .map_err(PyErr::new::<PyException, _>)
+    }
+
+    pub fn count(&self) -> usize {
+        self.search.count()
+    }
+
+    pub fn results(&self) -> Vec<u16> {
+        self.search.results()
+    }
+
+    pub fn value(&self, addr: u16) -> Option<u16> {
+        self.search.value(addr)
+    }
+
+    #[staticmethod]
+    pub fn shark_code(addr: u16, value: u8) -> String {
+        CheatSearchBase::shark_code(addr, value)
+    }
 }
 
 #[pymodule]
 fn boytacean(_py: Python, module: &PyModule) -> PyResult<()> {
     module.add_class::<GameBoy>()?;
+    module.add_class::<CheatSearch>()?;
     module.add("__version__", VERSION)?;
     module.add("COMPILATION_DATE", COMPILATION_DATE)?;
     module.add("COMPILATION_TIME", COMPILATION_TIME)?;
@@ -199,10 +451,18 @@ fn boytacean(_py: Python, module: &PyModule) -> PyResult<()> {
     module.add("COMPILER_VERSION", COMPILER_VERSION)?;
     module.add("NAME", NAME)?;
     module.add("VERSION", VERSION)?;
+    module.add(
+        "BUILD_INFO",
+        BuildInfo::current()
+            .entries()
+            .into_iter()
+            .collect::<HashMap<&str, String>>(),
+    )?;
     module.add("DISPLAY_WIDTH", DISPLAY_WIDTH)?;
     module.add("DISPLAY_HEIGHT", DISPLAY_HEIGHT)?;
     module.add("CPU_FREQ", GameBoyBase::CPU_FREQ)?;
     module.add("VISUAL_FREQ", GameBoyBase::VISUAL_FREQ)?;
+    module.add("REFRESH_RATE", GameBoyBase::REFRESH_RATE)?;
     module.add("LCD_CYCLES", GameBoyBase::LCD_CYCLES)?;
     Ok(())
 }
@@ -1,4 +1,4 @@
-from .gb import GameBoyMode, GameBoy
+from .gb import GameBoyMode, GameBoy, PadKey
 from .palettes import PALETTES
 from .video import VideoCapture
 
@@ -22,6 +22,9 @@ class GameBoy:
     def load_rom_file(self, path: str): ...
     def read_memory(self, addr: int) -> int: ...
     def write_memory(self, addr: int, value: int): ...
+    def disassemble(
+        self, addr: int, count: int
+    ) -> list[tuple[int, list[int], str, list[str], int]]: ...
     def clock(self) -> int: ...
     def clock_many(self, count: int) -> int: ...
     def clock_step(self, addr: int) -> int: ...
@@ -51,3 +54,7 @@ class GameBoy:
     def set_timer_div(self, value: int): ...
     def save_state(self) -> bytes: ...
     def load_state(self, data: bytes): ...
+    def save_state_bytes(self) -> bytes: ...
+    def load_state_bytes(self, data: bytes): ...
+    def sram_bytes(self) -> bytes: ...
+    def set_sram_bytes(self, data: bytes): ...
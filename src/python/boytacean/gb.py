@@ -1,6 +1,6 @@
 from enum import Enum
 from contextlib import contextmanager
-from typing import Any, Iterable, Union, cast
+from typing import Any, Callable, Iterable, Union, cast
 
 try:
     from PIL.Image import Image, frombytes
@@ -8,6 +8,11 @@ except ImportError:
     Image = Any
     frombytes = Any
 
+try:
+    from numpy import ndarray
+except ImportError:
+    ndarray = Any
+
 from .palettes import PALETTES
 from .video import VideoCapture
 
@@ -35,6 +40,10 @@ class GameBoy:
     _frame_index: int = 0
     _video: Union[VideoCapture, None] = None
     _display: Union[Any, None] = None
+    _frame_array: Union[ndarray, None] = None
+    _frame_hooks: list[Callable[["GameBoy"], None]] = []
+    _serial_hooks: list[Callable[["GameBoy", int], None]] = []
+    _breakpoint_hooks: dict[int, list[Callable[["GameBoy", int], None]]] = {}
 
     def __init__(
         self,
@@ -53,6 +62,10 @@ class GameBoy:
         self._frame_index = 0
         self._video = None
         self._display = None
+        self._frame_array = None
+        self._frame_hooks = []
+        self._serial_hooks = []
+        self._breakpoint_hooks = {}
         self._system = GameBoyRust(mode.value)
         self._system.set_ppu_enabled(ppu_enabled)
         self._system.set_apu_enabled(apu_enabled)
@@ -94,11 +107,79 @@ This is a [Game Boy](https://en.wikipedia.org/wiki/Game_Boy) emulator built usin
     def load_rom_data(self, data: bytes):
         self._system.load_rom(data)
 
-    def read_memory(self, addr: int) -> int:
-        return self._system.read_memory(addr)
-
-    def write_memory(self, addr: int, value: int):
-        self._system.write_memory(addr, value)
+    def read_memory(self, addr: int, length: Union[int, None] = None) -> Union[int, bytes]:
+        """
+        Reads the byte at the provided address or, in case a length
+        is provided, the bytes starting at that address.
+        """
+
+        if length is None:
+            return self._system.read_memory(addr)
+        return self._system.read_memory_range(addr, length)
+
+    def write_memory(self, addr: int, value: Union[int, bytes]):
+        """
+        Writes a single byte (int) or a sequence of bytes
+        starting at the provided address.
+        """
+
+        if isinstance(value, int):
+            self._system.write_memory(addr, value)
+        else:
+            self._system.write_memory_range(addr, bytes(value))
+
+    def on_frame(self, callback: Callable[["GameBoy"], None]):
+        """
+        Registers a callback called at the end of every frame
+        run with `next_frame()` (or any of its variants).
+        """
+
+        self._frame_hooks.append(callback)
+
+    def on_serial_byte(self, callback: Callable[["GameBoy", int], None]):
+        """
+        Registers a callback called with every byte sent through
+        the serial port, replacing the attached serial device, the
+        bytes are dispatched at the end of each frame.
+        """
+
+        if not self._serial_hooks:
+            self._system.capture_serial()
+        self._serial_hooks.append(callback)
+
+    def on_breakpoint(self, addr: int, callback: Callable[["GameBoy", int], None]):
+        """
+        Registers a callback called whenever the PC reaches the
+        provided address (before executing the instruction) while
+        running frames, the system state may be inspected and changed.
+        """
+
+        self._breakpoint_hooks.setdefault(addr, []).append(callback)
+        self._system.add_breakpoint(addr)
+
+    def remove_breakpoint(self, addr: int):
+        self._breakpoint_hooks.pop(addr, None)
+        self._system.remove_breakpoint(addr)
+
+    def clear_hooks(self):
+        self._frame_hooks = []
+        self._serial_hooks = []
+        self._breakpoint_hooks = {}
+        self._system.clear_breakpoints()
+
+    def disassemble(self, addr: int, count: int = 16) -> list[dict[str, Any]]:
+        return [
+            dict(
+                address=address,
+                bytes=bytes(data),
+                mnemonic=mnemonic,
+                operands=operands,
+                cycles=cycles,
+            )
+            for address, data, mnemonic, operands, cycles in self._system.disassemble(
+                addr, count
+            )
+        ]
 
     def clock(self) -> int:
         return self._system.clock()
@@ -116,11 +197,47 @@ This is a [Game Boy](https://en.wikipedia.org/wiki/Game_Boy) emulator built usin
         return self._system.clocks_cycles(limit)
 
     def next_frame(self) -> int:
-        cycles = self._system.next_frame()
+        if self._breakpoint_hooks:
+            cycles = self._next_frame_breakpoints()
+        else:
+            cycles = self._system.next_frame()
         self._frame_index += 1
         self._on_next_frame()
         return cycles
 
+    def run_frame(self) -> bytes:
+        """
+        Runs the emulation until a new video frame is produced,
+        returning the frame buffer of that frame.
+        """
+
+        self.next_frame()
+        return self.frame_buffer()
+
+    def run_for_cycles(self, cycles: int) -> int:
+        """
+        Runs the emulation for the provided number of cycles, with
+        the cycles run in excess carried over to the next call,
+        returning the number of video frames produced.
+
+        The frame hooks are called once, after the run, in case
+        at least one frame has been produced.
+        """
+
+        frames = self._system.run_for_cycles(cycles)
+        if frames > 0:
+            self._frame_index += frames
+            self._on_next_frame()
+        return frames
+
+    def step_frame(self, count: int = 1) -> int:
+        """
+        Runs the emulation for exactly the provided number of
+        video frames, returning the number of cycles run.
+        """
+
+        return self.skip_frames(count)
+
     def step_to(self, addr: int) -> int:
         return self._system.step_to(addr)
 
@@ -136,9 +253,29 @@ This is a [Game Boy](https://en.wikipedia.org/wiki/Game_Boy) emulator built usin
     def key_lift(self, key: PadKey):
         self._system.key_lift(key.value)
 
+    def press(self, key: Union[PadKey, str]):
+        self.key_press(self._pad_key(key))
+
+    def release(self, key: Union[PadKey, str]):
+        self.key_lift(self._pad_key(key))
+
     def frame_buffer(self) -> bytes:
         return self._system.frame_buffer()
 
+    def frame_ndarray(self) -> ndarray:
+        """
+        Obtains the frame buffer as a `(144, 160, 3)` numpy array of
+        RGB bytes, the same array is re-used (and overwritten) on
+        every call, meaning that it should be copied if kept around.
+        """
+
+        if self._frame_array is None:
+            from numpy import empty, uint8
+
+            self._frame_array = empty((DISPLAY_HEIGHT, DISPLAY_WIDTH, 3), dtype=uint8)
+        self._system.frame_buffer_into(self._frame_array)
+        return self._frame_array
+
     def image(self) -> Image:
         frame_buffer = cast(bytes, self._system.frame_buffer())
         image = frombytes("RGB", (DISPLAY_WIDTH, DISPLAY_HEIGHT), frame_buffer, "raw")
@@ -183,6 +320,18 @@ This is a [Game Boy](https://en.wikipedia.org/wiki/Game_Boy) emulator built usin
     def load_state(self, data: bytes):
         self._system.load_state(data)
 
+    def save_state_bytes(self) -> bytes:
+        return self._system.save_state_bytes()
+
+    def load_state_bytes(self, data: bytes):
+        self._system.load_state_bytes(data)
+
+    def sram_bytes(self) -> bytes:
+        return self._system.sram_bytes()
+
+    def set_sram_bytes(self, data: bytes):
+        self._system.set_sram_bytes(data)
+
     @property
     def ppu_enabled(self) -> bool:
         return self._system.ppu_enabled()
@@ -276,7 +425,33 @@ This is a [Game Boy](https://en.wikipedia.org/wiki/Game_Boy) emulator built usin
         finally:
             self._stop_capture()
 
+    def _pad_key(self, key: Union[PadKey, str]) -> PadKey:
+        if isinstance(key, PadKey):
+            return key
+        for pad_key in PadKey:
+            if pad_key.name.lower() == key.lower():
+                return pad_key
+        raise ValueError(f"Invalid pad key: {key}")
+
+    def _next_frame_breakpoints(self) -> int:
+        cycles, resume = 0, False
+        while True:
+            cycles_run, addr = self._system.next_frame_breakpoints(resume)
+            cycles += cycles_run
+            if addr is None:
+                return cycles
+            for callback in self._breakpoint_hooks.get(addr, []):
+                callback(self, addr)
+            resume = True
+
     def _on_next_frame(self):
+        if self._serial_hooks:
+            for byte in self._system.take_serial_bytes():
+                for callback in self._serial_hooks:
+                    callback(self, byte)
+        for callback in self._frame_hooks:
+            callback(self)
+
         if self._video is not None and self._video.should_capture(self._frame_index):
             self._video.save_frame(self.image(), self._frame_index)
             self._video.compute_next(self._frame_index)
@@ -2,7 +2,7 @@ import unittest
 
 from os.path import dirname, realpath, join
 
-from boytacean import GameBoy
+from boytacean import DISPLAY_HEIGHT, DISPLAY_WIDTH, GameBoy, PadKey
 
 CURRENT_DIR = dirname(realpath(__file__))
 POCKET_ROM_PATH = join(CURRENT_DIR, "../../../../res/roms/demo/pocket.gb")
@@ -19,3 +19,46 @@ class BaseTest(unittest.TestCase):
         self.assertEqual(gb.rom_title, "POCKET-DEMO")
         self.assertEqual(gb.boot_rom_s, "DMG Bootix")
         self.assertEqual(gb.clock_freq_s, "4.19 Mhz")
+
+    def test_step_frame(self):
+        gb = GameBoy(apu_enabled=False, serial_enabled=False, load_graphics=False)
+        gb.load_rom(POCKET_ROM_PATH)
+        self.assertGreater(gb.step_frame(), 0)
+        self.assertEqual(gb.frame_count, 1)
+        gb.step_frame(59)
+        self.assertEqual(gb.frame_count, 60)
+
+        gb.press("a")
+        gb.release(PadKey.A)
+        self.assertRaises(ValueError, gb.press, "turbo")
+
+        try:
+            import numpy
+        except ImportError:
+            return
+        frame = gb.frame_ndarray()
+        self.assertEqual(frame.shape, (DISPLAY_HEIGHT, DISPLAY_WIDTH, 3))
+        self.assertEqual(frame.dtype, numpy.uint8)
+        self.assertEqual(frame.tobytes(), gb.frame_buffer())
+
+    def test_hooks(self):
+        gb = GameBoy(apu_enabled=False, serial_enabled=False, load_graphics=False)
+        gb.load_rom(POCKET_ROM_PATH)
+        gb.step_frame(60)
+
+        frames = []
+        gb.on_frame(lambda gb: frames.append(gb.frame_count))
+        hits = []
+        gb.on_breakpoint(0x0150, lambda gb, addr: hits.append(addr))
+        gb.step_frame(2)
+        self.assertEqual(frames, [61, 62])
+        self.assertTrue(all(hit == 0x0150 for hit in hits))
+
+        gb.clear_hooks()
+        gb.step_frame()
+        self.assertEqual(len(frames), 2)
+
+        gb.write_memory(0xC000, b"\x01\x02\x03")
+        self.assertEqual(gb.read_memory(0xC000, 3), b"\x01\x02\x03")
+        gb.write_memory(0xC000, 0xFF)
+        self.assertEqual(gb.read_memory(0xC000), 0xFF)
@@ -3,29 +3,182 @@
 //! Includes the implementation of the Memory Bank Controllers (MBCs)
 //! that are used to handle the memory access for the cartridge.
 
-use boytacean_common::{error::Error, util::read_file};
+use boytacean_common::{error::Error, util::timestamp};
+use boytacean_hashing::crc32::{crc32, Crc32};
 use core::fmt;
 use std::{
     cmp::max,
     fmt::{Display, Formatter},
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    sync::{Arc, Mutex},
     vec,
 };
 
 use crate::{
+    bus::{BusMap, BusRange, BusRegion},
     cheats::{genie::GameGenie, shark::GameShark},
+    data::{
+        CGB_COMPAT_CHECKSUMS, CGB_COMPAT_COLORS, CGB_COMPAT_COMBINATIONS, CGB_COMPAT_DUPS_START,
+        CGB_COMPAT_LETTERS, CGB_COMPAT_PALETTES,
+    },
     debugln,
-    gb::GameBoyMode,
+    gb::{GameBoy, GameBoyMode},
     licensee::Licensee,
     mmu::BusComponent,
-    panic_gb, warnln,
+    panic_gb,
+    patch::apply_patch,
+    warnln,
 };
 
+#[cfg(feature = "archive")]
+use crate::archive::{extract_rom, is_archive_path};
+
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
 pub const ROM_BANK_SIZE: usize = 16384;
 pub const RAM_BANK_SIZE: usize = 8192;
 
+/// The size of the MBC2 built-in RAM, made of 512 half-bytes
+/// (4-bit values), stored as one nibble per byte.
+pub const MBC2_RAM_SIZE: usize = 512;
+
+/// The number of switchable ROM banks kept in memory (LRU cache)
+/// when the ROM is loaded in the streamed mode.
+pub const ROM_STREAM_CACHE_BANKS: usize = 8;
+
+/// Reads the ROM file in the provided path, extracting the ROM in
+/// case the file is a ZIP or 7z archive.
+#[cfg(feature = "archive")]
+pub fn read_rom_file(path: &str) -> Result<Vec<u8>, Error> {
+    crate::archive::read_rom_file(path)
+}
+
+/// Reads the ROM file in the provided path.
+#[cfg(not(feature = "archive"))]
+pub fn read_rom_file(path: &str) -> Result<Vec<u8>, Error> {
+    boytacean_common::util::read_file(path)
+}
+
+/// Controls how the ROM data is loaded into the cartridge.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum RomLoadMode {
+    /// The complete ROM is loaded into memory.
+    #[default]
+    Full,
+
+    /// Only the first bank is kept in memory, with the switchable
+    /// banks being read on demand from the source (eg: file) into
+    /// an LRU cache, keeping memory usage low for very large ROMs.
+    Streamed,
+}
+
+/// Events related with the battery backed RAM of the cartridge,
+/// allowing frontends to display a saving indicator and to
+/// schedule the flushing of the RAM to the file system.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BatteryEvent {
+    /// The battery backed RAM has been changed after having been
+    /// (last) saved, meaning that there are unsaved changes.
+    Changed,
+
+    /// The battery backed RAM has been saved, with all of its
+    /// changes having been flushed.
+    Saved,
+}
+
+impl BatteryEvent {
+    pub fn description(&self) -> &'static str {
+        match self {
+            BatteryEvent::Changed => "Changed",
+            BatteryEvent::Saved => "Saved",
+        }
+    }
+}
+
+impl Display for BatteryEvent {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// Source of ROM data for the streamed load mode, allowing the
+/// ROM banks to be read on demand.
+pub trait RomSource: Send {
+    /// The total size of the ROM in bytes.
+    fn size(&self) -> usize;
+
+    /// Reads `buffer.len()` bytes of ROM data starting at `offset`.
+    fn read_at(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), Error>;
+}
+
+/// ROM source that reads the ROM data from a file in the file system.
+pub struct FileRomSource {
+    file: File,
+    size: usize,
+}
+
+impl FileRomSource {
+    pub fn open(path: &str) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        let size = file.metadata()?.len() as usize;
+        Ok(Self { file, size })
+    }
+}
+
+impl RomSource for FileRomSource {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn read_at(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), Error> {
+        self.file.seek(SeekFrom::Start(offset as u64))?;
+        self.file.read_exact(buffer)?;
+        Ok(())
+    }
+}
+
+impl RomSource for Vec<u8> {
+    fn size(&self) -> usize {
+        self.len()
+    }
+
+    fn read_at(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), Error> {
+        let data = self
+            .get(offset..offset + buffer.len())
+            .ok_or(Error::InvalidParameter(format!(
+                "Invalid ROM offset: 0x{offset:06x}"
+            )))?;
+        buffer.copy_from_slice(data);
+        Ok(())
+    }
+}
+
+/// State of the streamed ROM load mode, the ROM data of the cartridge
+/// is made of the first bank followed by the cache slots, so that
+/// switching a bank just points the ROM offset to its cache slot.
+#[derive(Clone)]
+struct RomStream {
+    source: Arc<Mutex<dyn RomSource>>,
+
+    /// The ROM bank loaded in each of the cache slots.
+    slots: Vec<Option<u16>>,
+
+    /// The "time" of the last access to each of the cache
+    /// slots, used to find the least recently used one.
+    used: Vec<u64>,
+
+    tick: u64,
+
+    /// The currently selected (switchable) ROM bank.
+    bank: u16,
+
+    /// The number of banks that have been read from the source.
+    loads: u64,
+}
+
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum MbcType {
@@ -44,7 +197,7 @@ impl MbcType {
         match self {
             MbcType::NoMbc => 0x00,
             MbcType::Mbc1 => 0x03,
-            MbcType::Mbc2 => unimplemented!("MBC2 is not supported"),
+            MbcType::Mbc2 => 0x00,
             MbcType::Mbc3 => 0x03,
             MbcType::Mbc5 => 0x0f,
             MbcType::Mbc6 => unimplemented!("MBC6 is not supported"),
@@ -209,7 +362,7 @@ impl Display for RomSize {
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum RamSize {
     NoRam,
-    Unused,
+    Size2K,
     Size8K,
     Size16K,
     Size32K,
@@ -222,7 +375,7 @@ impl RamSize {
     pub fn description(&self) -> &'static str {
         match self {
             RamSize::NoRam => "No RAM",
-            RamSize::Unused => "Unused",
+            RamSize::Size2K => "2 KB",
             RamSize::Size8K => "8 KB",
             RamSize::Size16K => "16 KB",
             RamSize::Size32K => "32 KB",
@@ -235,7 +388,7 @@ impl RamSize {
     pub fn ram_banks(&self) -> u16 {
         match self {
             RamSize::NoRam => 0,
-            RamSize::Unused => 0,
+            RamSize::Size2K => 1,
             RamSize::Size8K => 1,
             RamSize::Size16K => 2,
             RamSize::Size32K => 4,
@@ -244,6 +397,15 @@ impl RamSize {
             RamSize::SizeUnknown => 0,
         }
     }
+
+    /// Obtains the size of the RAM in bytes, note that RAM
+    /// sizes smaller than a bank (eg: 2 KB) use a partial bank.
+    pub fn bytes(&self) -> usize {
+        match self {
+            RamSize::Size2K => 2048,
+            _ => self.ram_banks() as usize * RAM_BANK_SIZE,
+        }
+    }
 }
 
 impl Display for RamSize {
@@ -323,6 +485,77 @@ impl Display for CgbMode {
     }
 }
 
+/// Compatibility issue found while loading a cartridge, that may
+/// explain why a title misbehaves, see [`Cartridge::diagnostics`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RomWarning {
+    /// The cartridge type code (0x0147) is not a known one, with
+    /// the cartridge being emulated using a fallback MBC.
+    UnknownMbc(u8),
+
+    /// The MBC of the cartridge is known but not emulated, with
+    /// the cartridge being emulated using a fallback MBC.
+    UnsupportedMbc(RomType),
+
+    /// The header checksum (0x014d) does not match the one computed
+    /// from the header, the boot ROM of a real device would lock up.
+    InvalidHeaderChecksum { expected: u8, found: u8 },
+
+    /// The ROM size in the header does not match the size of the
+    /// ROM data (eg: overdumped or truncated dumps).
+    RomSizeMismatch { expected: usize, found: usize },
+
+    /// The RAM size code (0x0149) is not a known one.
+    InvalidRamSize(u8),
+
+    /// The RAM size in the header is larger than the one that the
+    /// MBC of the cartridge is able to address.
+    OversizedRam(RamSize, RomType),
+
+    /// The cartridge only runs on a CGB but is being run in
+    /// another mode (eg: DMG).
+    CgbOnly(GameBoyMode),
+
+    /// The cartridge makes use of the SGB functions which are
+    /// not available in the current mode.
+    SgbUnsupported(GameBoyMode),
+}
+
+impl RomWarning {
+    pub fn description(&self) -> String {
+        match self {
+            RomWarning::UnknownMbc(code) => {
+                format!("Unknown cartridge type (0x{code:02x}), emulated with a fallback MBC")
+            }
+            RomWarning::UnsupportedMbc(rom_type) => {
+                format!("Unsupported cartridge type ({rom_type}), emulated with a fallback MBC")
+            }
+            RomWarning::InvalidHeaderChecksum { expected, found } => {
+                format!("Invalid header checksum (expected 0x{expected:02x}, found 0x{found:02x})")
+            }
+            RomWarning::RomSizeMismatch { expected, found } => {
+                format!("ROM size mismatch (header has {expected} bytes, data has {found} bytes)")
+            }
+            RomWarning::InvalidRamSize(code) => format!("Invalid RAM size code (0x{code:02x})"),
+            RomWarning::OversizedRam(ram_size, rom_type) => {
+                format!("RAM size ({ram_size}) larger than supported by {rom_type}")
+            }
+            RomWarning::CgbOnly(mode) => {
+                format!("CGB only cartridge running in {} mode", mode.description())
+            }
+            RomWarning::SgbUnsupported(mode) => {
+                format!("SGB functions not available in {} mode", mode.description())
+            }
+        }
+    }
+}
+
+impl Display for RomWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
 /// Structure that defines the ROM and ROM contents
 /// of a Game Boy cartridge. Should correctly address
 /// the specifics of all the major MBCs (Memory Bank
@@ -371,11 +604,25 @@ pub struct Cartridge {
     /// control of memory access to avoid corruption.
     ram_enabled: bool,
 
+    /// If the RAM has been written (with the write accepted by the
+    /// MBC) since the flag has been last cleared, used to detect
+    /// changes (eg: for RAM sharing).
+    ram_dirty: bool,
+
+    /// The (PPU) frame index at which the battery backed RAM has
+    /// been first changed since it was last saved, `None` in case
+    /// there are no unsaved changes.
+    battery_frame: Option<u16>,
+
     /// The final offset of the last character of the title
     /// that is considered to be non zero (0x0) so that a
     /// proper safe conversion to UTF-8 string can be done.
     title_offset: usize,
 
+    /// The CRC32 of the complete ROM data, computed once when
+    /// the ROM is loaded (as it's used repeatedly).
+    fingerprint: u32,
+
     /// The current rumble state of the cartridge, this
     /// boolean value controls if vibration is currently active.
     rumble_active: bool,
@@ -393,6 +640,27 @@ pub struct Cartridge {
     /// would be used for the "cheating" by patching the
     /// current ROM's cartridge data.
     game_shark: Option<GameShark>,
+
+    /// The Real Time Clock of the cartridge, only used by
+    /// the MBC3 cartridges that include a timer.
+    rtc: Rtc,
+
+    /// The RTC register currently mapped into the 0xA000-0xBFFF
+    /// range (0x08-0x0C), zero if RAM is mapped instead.
+    rtc_register: u8,
+
+    /// The deterministic (emulated) UNIX time used by the RTC
+    /// instead of the wall clock, advanced with the emulated
+    /// cycles, `None` to use the wall clock.
+    clock: Option<u64>,
+
+    /// The number of (normal speed) cycles accumulated since the
+    /// last second of the deterministic clock.
+    clock_cycles: u32,
+
+    /// The state of the ROM streaming, only set when the ROM
+    /// has been loaded in the streamed mode.
+    stream: Option<RomStream>,
 }
 
 impl Cartridge {
@@ -407,11 +675,19 @@ impl Cartridge {
             rom_offset: 0x4000,
             ram_offset: 0x0000,
             ram_enabled: false,
+            ram_dirty: false,
+            battery_frame: None,
             title_offset: 0x0143,
+            fingerprint: 0,
             rumble_active: false,
             rumble_cb: |_| {},
             game_genie: None,
             game_shark: None,
+            rtc: Rtc::default(),
+            rtc_register: 0x00,
+            clock: None,
+            clock_cycles: 0,
+            stream: None,
         }
     }
 
@@ -421,11 +697,93 @@ impl Cartridge {
         Ok(cartridge)
     }
 
+    /// Creates a cartridge from the ROM file in the provided path,
+    /// which may be a ZIP or 7z archive (with the `archive` feature).
     pub fn from_file(path: &str) -> Result<Self, Error> {
-        let data = read_file(path).unwrap();
+        let data = read_rom_file(path)?;
         Self::from_data(&data)
     }
 
+    /// Creates a cartridge from the ROM in the provided archive (ZIP
+    /// or 7z), the entry with the provided name or the first one with
+    /// a Game Boy ROM extension.
+    #[cfg(feature = "archive")]
+    pub fn from_archive(data: &[u8], entry: Option<&str>) -> Result<Self, Error> {
+        Self::from_data(&extract_rom(data, entry)?)
+    }
+
+    /// Creates a cartridge from the provided ROM data after applying
+    /// the provided IPS or BPS patch to it (in memory).
+    pub fn from_data_patched(data: &[u8], patch: &[u8]) -> Result<Self, Error> {
+        Self::from_data(&apply_patch(data, patch)?)
+    }
+
+    /// Opens the ROM file in the provided path using the provided
+    /// load mode, in the streamed mode the file is kept open until
+    /// the cartridge is closed.
+    pub fn open(path: &str, mode: RomLoadMode) -> Result<Self, Error> {
+        match mode {
+            RomLoadMode::Full => Self::from_file(path),
+            // archives can't be streamed, the ROM is extracted into memory
+            #[cfg(feature = "archive")]
+            RomLoadMode::Streamed if is_archive_path(path) => Self::from_file(path),
+            RomLoadMode::Streamed => {
+                Self::from_source(FileRomSource::open(path)?, ROM_STREAM_CACHE_BANKS)
+            }
+        }
+    }
+
+    /// Creates a cartridge in the streamed mode, that reads the
+    /// switchable ROM banks on demand from the provided source,
+    /// keeping up to `cache_banks` of them in memory.
+    #[allow(clippy::manual_is_multiple_of)]
+    pub fn from_source(
+        mut source: impl RomSource + 'static,
+        cache_banks: usize,
+    ) -> Result<Self, Error> {
+        let size = source.size();
+        if size < 0x7fff || size % ROM_BANK_SIZE != 0 {
+            return Err(Error::RomSize);
+        }
+
+        // computes the fingerprint of the complete ROM, reading it
+        // bank by bank so that it's never completely in memory
+        let mut bank = vec![0u8; ROM_BANK_SIZE];
+        let mut crc = Crc32::new();
+        for offset in (0..size).step_by(ROM_BANK_SIZE) {
+            source.read_at(offset, &mut bank)?;
+            crc.update(&bank);
+        }
+
+        let cache_banks = cache_banks.max(1);
+        let mut cartridge = Cartridge::new();
+        cartridge.rom_data = vec![0u8; (cache_banks + 1) * ROM_BANK_SIZE];
+        source.read_at(0, &mut cartridge.rom_data[..ROM_BANK_SIZE])?;
+        cartridge.stream = Some(RomStream {
+            source: Arc::new(Mutex::new(source)),
+            slots: vec![None; cache_banks],
+            used: vec![0; cache_banks],
+            tick: 0,
+            bank: 1,
+            loads: 0,
+        });
+        cartridge.fingerprint = crc.finalize();
+        cartridge.ram_offset = 0x0000;
+        cartridge.set_mbc()?;
+        cartridge.set_computed();
+        cartridge.set_title_offset();
+        cartridge.allocate_ram();
+        cartridge.set_rom_bank(1);
+        cartridge.set_ram_bank(0);
+        Ok(cartridge)
+    }
+
+    /// Closes the cartridge, releasing the ROM data and source
+    /// (eg: file) of a streamed cartridge.
+    pub fn close(&mut self) {
+        self.reset();
+    }
+
     pub fn read(&self, addr: u16) -> u8 {
         match addr {
             // 0x0000-0x7FFF: 16 KiB ROM bank 00 & 16 KiB ROM Bank 01–NN
@@ -444,11 +802,29 @@ impl Cartridge {
             // 0x0000-0x7FFF: 16 KiB ROM bank 00 & 16 KiB ROM Bank 01–NN
             0x0000..=0x7fff => (self.handler.write_rom)(self, addr, value),
             // 0xA000-0xBFFF: 8 KiB External RAM
-            0xa000..=0xbfff => (self.handler.write_ram)(self, addr, value),
+            0xa000..=0xbfff => _ = self.write_ram(addr, value),
             _ => debugln!("Writing to unknown Cartridge address 0x{:04x}", addr),
         }
     }
 
+    /// Writes to the external RAM (0xA000-0xBFFF) returning if the
+    /// write has been accepted by the MBC (eg: the RAM is enabled),
+    /// in which case the RAM is marked as dirty.
+    pub fn write_ram(&mut self, addr: u16, value: u8) -> bool {
+        (self.handler.write_ram)(self, addr, value);
+        let accepted = self.ram_writable();
+        if accepted {
+            self.ram_dirty = true;
+        }
+        accepted
+    }
+
+    /// If the writes to the external RAM are currently accepted,
+    /// cartridges without MBC have no way to disable their RAM.
+    pub fn ram_writable(&self) -> bool {
+        self.ram_enabled || std::ptr::eq(self.mbc, &NO_MBC)
+    }
+
     pub fn reset(&mut self) {
         self.rom_data = vec![];
         self.ram_data = vec![];
@@ -458,18 +834,19 @@ impl Cartridge {
         self.rom_offset = 0x4000;
         self.ram_offset = 0x0000;
         self.ram_enabled = false;
+        self.ram_dirty = false;
+        self.battery_frame = None;
         self.title_offset = 0x0143;
+        self.fingerprint = 0;
         self.rumble_active = false;
         self.rumble_cb = |_| {};
+        self.rtc = Rtc::default();
+        self.rtc_register = 0x00;
+        self.stream = None;
     }
 
-    pub fn vblank(&mut self) -> Option<Vec<(u16, u16, u8)>> {
-        if let Some(game_shark) = &mut self.game_shark {
-            return Some(game_shark.writes());
-        }
-        None
-    }
-
+    /// The ROM data of the cartridge, in the streamed mode this
+    /// is only the first bank followed by the bank cache slots.
     pub fn data(&self) -> &Vec<u8> {
         &self.rom_data
     }
@@ -565,14 +942,97 @@ impl Cartridge {
         self.ram_offset = ram_bank as usize * RAM_BANK_SIZE;
     }
 
+    /// Obtains the mask to be applied to the RAM bank number, so
+    /// that selecting a bank beyond the available ones wraps around
+    /// as the unused bank lines are not connected in the cartridge.
+    pub fn ram_bank_mask(&self) -> u8 {
+        (max(self.ram_bank_count, 1) - 1) as u8
+    }
+
+    /// Obtains the index in the RAM data for the provided address
+    /// (0xA000-0xBFFF) in the current RAM bank, RAM smaller than the
+    /// 8 KB window (eg: 2 KB) is mirrored across the window.
+    #[inline(always)]
+    fn ram_index(&self, addr: u16) -> usize {
+        (self.ram_offset + (addr - 0xa000) as usize) % max(self.ram_data.len(), 1)
+    }
+
+    /// Obtains the size in bytes of the RAM data expected for the
+    /// cartridge, cartridges with no (or unknown) RAM size still
+    /// have a bank allocated to cope with unexpected accesses.
+    pub fn ram_data_size(&self) -> usize {
+        // the MBC2 has its own built-in RAM and the header
+        // RAM size is expected to be zero for its cartridges
+        if self.rom_type().mbc_type() == MbcType::Mbc2 {
+            return MBC2_RAM_SIZE;
+        }
+        match self.ram_size() {
+            RamSize::NoRam | RamSize::SizeUnknown => RAM_BANK_SIZE,
+            ram_size => ram_size.bytes(),
+        }
+    }
+
     pub fn rom_bank(&self) -> u16 {
-        (self.rom_offset / ROM_BANK_SIZE) as u16
+        match &self.stream {
+            Some(stream) => stream.bank,
+            None => (self.rom_offset / ROM_BANK_SIZE) as u16,
+        }
     }
 
     pub fn set_rom_bank(&mut self, rom_bank: u16) {
+        if self.stream.is_some() {
+            self.set_rom_bank_streamed(rom_bank);
+            return;
+        }
         self.rom_offset = rom_bank as usize * ROM_BANK_SIZE;
     }
 
+    /// Selects the ROM bank in the streamed mode, pointing the ROM
+    /// offset to the cache slot of the bank, loading the bank from
+    /// the source into the least recently used slot if required.
+    fn set_rom_bank_streamed(&mut self, rom_bank: u16) {
+        let stream = self.stream.as_mut().unwrap();
+        stream.tick += 1;
+        stream.bank = rom_bank;
+
+        let slot = match stream.slots.iter().position(|bank| *bank == Some(rom_bank)) {
+            Some(slot) => slot,
+            None => {
+                let slot = (0..stream.slots.len())
+                    .min_by_key(|slot| stream.used[*slot])
+                    .unwrap();
+                let start = (slot + 1) * ROM_BANK_SIZE;
+                let buffer = &mut self.rom_data[start..start + ROM_BANK_SIZE];
+                let offset = rom_bank as usize * ROM_BANK_SIZE;
+                let mut source = stream.source.lock().unwrap();
+                if offset + ROM_BANK_SIZE > source.size() {
+                    buffer.fill(0x00);
+                } else if let Err(err) = source.read_at(offset, buffer) {
+                    warnln!("Failed to read ROM bank {}: {}", rom_bank, err);
+                    buffer.fill(0xff);
+                }
+                stream.slots[slot] = Some(rom_bank);
+                stream.loads += 1;
+                slot
+            }
+        };
+
+        stream.used[slot] = stream.tick;
+        self.rom_offset = (slot + 1) * ROM_BANK_SIZE;
+    }
+
+    /// If the ROM has been loaded in the streamed mode, with the
+    /// switchable banks being read on demand.
+    pub fn streamed(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// The number of ROM banks read from the source in the streamed
+    /// mode, useful to evaluate the efficiency of the bank cache.
+    pub fn stream_loads(&self) -> u64 {
+        self.stream.as_ref().map_or(0, |stream| stream.loads)
+    }
+
     pub fn set_rumble_cb(&mut self, rumble_cb: fn(active: bool)) {
         self.rumble_cb = rumble_cb;
     }
@@ -583,7 +1043,9 @@ impl Cartridge {
 
     fn set_data(&mut self, data: &[u8]) -> Result<(), Error> {
         self.ensure_data(data)?;
+        self.stream = None;
         self.rom_data = data.to_vec();
+        self.fingerprint = crc32(&self.rom_data);
         self.rom_offset = 0x4000;
         self.ram_offset = 0x0000;
         self.set_mbc()?;
@@ -592,11 +1054,19 @@ impl Cartridge {
         self.allocate_ram();
         self.set_rom_bank(1);
         self.set_ram_bank(0);
+        self.rtc = Rtc::default();
+        self.rtc_register = 0x00;
         Ok(())
     }
 
+    /// Sets the MBC of the cartridge from its type, unknown or
+    /// unsupported types fall back to MBC5 (or no MBC for 32 KB
+    /// cartridges), which is reported by the diagnostics.
     fn set_mbc(&mut self) -> Result<(), Error> {
-        self.mbc = self.mbc()?;
+        self.mbc = self.mbc().unwrap_or(match self.rom_size().rom_banks() {
+            0..=2 => &NO_MBC,
+            _ => &MBC5,
+        });
         self.handler = self.mbc;
         Ok(())
     }
@@ -607,27 +1077,39 @@ impl Cartridge {
     }
 
     pub fn set_title_offset(&mut self) {
-        if self.rom_data.len() < 0x0143 {
+        if self.rom_data.len() <= 0x0143 {
             return;
         }
-        let mut offset: usize = 0;
-        for byte in &self.rom_data[0x0134..=0x0143] {
-            if *byte == 0u8 {
-                break;
-            }
-
-            // in we're at the final byte of the title and the value
-            // is one that is reserved for CGB compatibility testing
-            // then we must ignore it for title processing purposes
-            if offset > 14
-                && (*byte == CgbMode::CgbCompatible as u8 || *byte == CgbMode::CgbOnly as u8)
-            {
-                break;
-            }
-
-            offset += 1;
+        self.title_offset = 0x0134 + Self::title_length(&self.rom_data[0x0134..=0x0143]);
+    }
+
+    /// Obtains the length in bytes of the title contained in the
+    /// provided header title area (0x0134-0x0143), the title ends at
+    /// the first NULL byte or at the last byte in case it has the CGB
+    /// flag bit set (newer cartridges overlap the title with the flag).
+    pub fn title_length(data: &[u8]) -> usize {
+        let data = &data[..data.len().min(16)];
+        let mut length = data
+            .iter()
+            .position(|byte| *byte == 0x00)
+            .unwrap_or(data.len());
+        if length == 16 && data[15] & 0x80 == 0x80 {
+            length = 15;
         }
-        self.title_offset = 0x0134 + offset;
+        length
+    }
+
+    /// Canonical extraction of the title from the provided header
+    /// title area (0x0134-0x0143), shared by the cartridge and the
+    /// save states, so that both always produce the same title.
+    ///
+    /// Invalid (non UTF-8) bytes are decoded lossily and any leading
+    /// or trailing whitespace and control characters are removed.
+    pub fn title_from_data(data: &[u8]) -> String {
+        let length = Self::title_length(data);
+        String::from_utf8_lossy(&data[..length])
+            .trim_matches(|value: char| value.is_whitespace() || value.is_control())
+            .to_string()
     }
 
     pub fn game_genie(&self) -> &Option<GameGenie> {
@@ -654,13 +1136,49 @@ impl Cartridge {
         self.game_shark = game_shark;
     }
 
+    /// Obtains the OBJ0, OBJ1 and BG RGB555 palettes that the CGB boot
+    /// ROM uses to colorize this (DMG) cartridge, selected from the
+    /// checksum of the title for Nintendo licensed games, with the
+    /// default palettes being used for any other cartridge.
+    pub fn cgb_compat_palettes(&self) -> [[u16; 4]; 3] {
+        let nintendo = self.rom_data[0x014b] == 0x01
+            || (self.rom_data[0x014b] == 0x33 && &self.rom_data[0x0144..=0x0145] == b"01");
+        let checksum = self.rom_data[0x0134..=0x0143]
+            .iter()
+            .fold(0u8, |sum, value| sum.wrapping_add(*value));
+        let letter = self.rom_data[0x0137];
+        let index = if nintendo {
+            CGB_COMPAT_CHECKSUMS
+                .iter()
+                .enumerate()
+                .position(|(index, value)| {
+                    *value == checksum
+                        && (index < CGB_COMPAT_DUPS_START
+                            || CGB_COMPAT_LETTERS[index - CGB_COMPAT_DUPS_START] == letter)
+                })
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        let combination = CGB_COMPAT_COMBINATIONS[CGB_COMPAT_PALETTES[index] as usize];
+        combination.map(|start| {
+            let start = start as usize;
+            [
+                CGB_COMPAT_COLORS[start],
+                CGB_COMPAT_COLORS[start + 1],
+                CGB_COMPAT_COLORS[start + 2],
+                CGB_COMPAT_COLORS[start + 3],
+            ]
+        })
+    }
+
     fn allocate_ram(&mut self) {
-        let ram_banks = max(self.ram_size().ram_banks(), 1);
-        self.ram_data = vec![0u8; ram_banks as usize * RAM_BANK_SIZE];
+        self.ram_data = vec![0u8; self.ram_data_size()];
     }
 
     /// Ensures that the data provided is of a valid Game Boy ROM
     /// and that it's size is within the expected range.
+    #[allow(clippy::manual_is_multiple_of)]
     fn ensure_data(&self, data: &[u8]) -> Result<(), Error> {
         if data.len() < 0x7fff {
             return Err(Error::RomSize);
@@ -675,11 +1193,7 @@ impl Cartridge {
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl Cartridge {
     pub fn title(&self) -> String {
-        String::from(
-            std::str::from_utf8(&self.rom_data[0x0134..self.title_offset])
-                .unwrap_or("")
-                .trim(),
-        )
+        Self::title_from_data(&self.rom_data[0x0134..self.title_offset])
     }
 
     pub fn licensee(&self) -> Licensee {
@@ -778,7 +1292,7 @@ impl Cartridge {
         }
         match self.rom_data[0x0149] {
             0x00 => RamSize::NoRam,
-            0x01 => RamSize::Unused,
+            0x01 => RamSize::Size2K,
             0x02 => RamSize::Size8K,
             0x03 => RamSize::Size32K,
             0x04 => RamSize::Size128K,
@@ -838,6 +1352,19 @@ impl Cartridge {
         )
     }
 
+    /// If the cartridge has RAM (either external or built-in into
+    /// the MBC), according to its header.
+    pub fn has_ram(&self) -> bool {
+        self.rom_type().mbc_type() == MbcType::Mbc2 || self.ram_size() != RamSize::NoRam
+    }
+
+    pub fn has_rtc(&self) -> bool {
+        matches!(
+            self.rom_type(),
+            RomType::Mbc3TimerBattery | RomType::Mbc3TimerRamBattery
+        )
+    }
+
     pub fn rom_data_eager(&self) -> Vec<u8> {
         self.rom_data.clone()
     }
@@ -846,32 +1373,100 @@ impl Cartridge {
         self.ram_data.clone()
     }
 
+    /// Sets the RAM data of the cartridge, the data is resized to
+    /// the expected RAM size so that padded or truncated files (eg:
+    /// from other emulators) are handled gracefully.
     pub fn set_ram_data(&mut self, data: &[u8]) {
-        self.ram_data = data.to_vec();
+        let mut ram_data = data.to_vec();
+        if !self.rom_data.is_empty() {
+            ram_data.resize(self.ram_data_size(), 0);
+        }
+        self.ram_data = ram_data;
+    }
+
+    /// Obtains the data to be persisted in the `.sav` file, made
+    /// of the RAM data followed by the RTC footer, if the cartridge
+    /// includes a Real Time Clock.
+    pub fn save_data(&self) -> Vec<u8> {
+        if !self.has_rtc() {
+            return self.ram_data.clone();
+        }
+        let mut data = if self.ram_size() == RamSize::NoRam {
+            vec![]
+        } else {
+            self.ram_data.clone()
+        };
+        data.extend(self.rtc_data());
+        data
+    }
+
+    /// Obtains the RTC footer of the `.sav` file (see [`Rtc::to_data`])
+    /// with the clock brought up to date with the current time.
+    pub fn rtc_data(&self) -> Vec<u8> {
+        let mut rtc = self.rtc.clone();
+        rtc.update(self.now());
+        rtc.to_data()
+    }
+
+    /// Restores the contents of a `.sav` file, splitting the RTC
+    /// footer (if any) from the RAM data for cartridges with a
+    /// Real Time Clock.
+    pub fn set_save_data(&mut self, data: &[u8]) {
+        if self.has_rtc() {
+            // RAM sizes are always multiples of 512 bytes, so the
+            // remainder is the size of the footer (if present)
+            let footer_size = data.len() % 0x200;
+            if footer_size == RTC_FOOTER_SIZE || footer_size == RTC_FOOTER_SIZE_LEGACY {
+                let (ram_data, footer) = data.split_at(data.len() - footer_size);
+                if let Ok(rtc) = Rtc::from_data(footer) {
+                    self.rtc = rtc;
+                    if let Some(clock) = self.clock {
+                        self.rtc.set_timestamp(clock);
+                    }
+                }
+                if !ram_data.is_empty() {
+                    self.set_ram_data(ram_data);
+                }
+                return;
+            }
+        }
+        self.set_ram_data(data);
     }
 
     pub fn clear_ram_data(&mut self) {
         self.ram_data = vec![0u8; self.ram_data.len()];
     }
 
+    /// If any cheats (Game Genie or GameShark) are attached,
+    /// patching the reads of the ROM.
+    pub fn has_cheats(&self) -> bool {
+        self.game_genie.is_some() || self.game_shark.is_some()
+    }
+
     pub fn attach_genie(&mut self, game_genie: GameGenie) {
         self.game_genie = Some(game_genie);
-        self.handler = &GAME_GENIE;
+        self.handler = &CHEATS;
     }
 
     pub fn detach_genie(&mut self) {
         self.game_genie = None;
-        self.handler = self.mbc;
+        if self.game_shark.is_none() {
+            self.handler = self.mbc;
+        }
     }
 
     pub fn attach_shark(&mut self, game_shark: GameShark) {
         let rom_type = self.rom_type();
         self.game_shark = Some(game_shark);
         self.game_shark.as_mut().unwrap().set_rom_type(rom_type);
+        self.handler = &CHEATS;
     }
 
     pub fn detach_shark(&mut self) {
         self.game_shark = None;
+        if self.game_genie.is_none() {
+            self.handler = self.mbc;
+        }
     }
 
     pub fn checksum(&self) -> u8 {
@@ -882,6 +1477,16 @@ impl Cartridge {
         sum
     }
 
+    /// Obtains the fingerprint (CRC32) of the ROM data, useful
+    /// to uniquely identify the ROM (eg: for per-game files).
+    ///
+    /// The value is computed once when the ROM is loaded, so
+    /// changes made afterwards (eg: via [`Self::rom_data_mut`])
+    /// are not reflected on it.
+    pub fn fingerprint(&self) -> u32 {
+        self.fingerprint
+    }
+
     pub fn valid_checksum(&self) -> bool {
         self.rom_data[0x014d] == self.checksum()
     }
@@ -919,6 +1524,48 @@ impl Cartridge {
         &self.rom_data
     }
 
+    /// Obtains the compatibility issues of the cartridge found from
+    /// its header and data, the ones that depend on the mode of the
+    /// system are obtained with [`GameBoy::rom_warnings`].
+    pub fn diagnostics(&self) -> Vec<RomWarning> {
+        let mut warnings = vec![];
+        let rom_type = self.rom_type();
+        if rom_type == RomType::Unknown {
+            warnings.push(RomWarning::UnknownMbc(self.rom_data[0x0147]));
+        } else if self.mbc().is_err() {
+            warnings.push(RomWarning::UnsupportedMbc(rom_type));
+        }
+        if !self.valid_checksum() {
+            warnings.push(RomWarning::InvalidHeaderChecksum {
+                expected: self.checksum(),
+                found: self.rom_data[0x014d],
+            });
+        }
+        let expected = self.rom_size().rom_banks() as usize * ROM_BANK_SIZE;
+        let found = match &self.stream {
+            Some(stream) => stream.source.lock().unwrap().size(),
+            None => self.rom_data.len(),
+        };
+        if expected != found {
+            warnings.push(RomWarning::RomSizeMismatch { expected, found });
+        }
+        let ram_size = self.ram_size();
+        let ram_limit = match rom_type.mbc_type() {
+            MbcType::NoMbc => RAM_BANK_SIZE,
+            MbcType::Mbc1 => 0x8000,
+            MbcType::Mbc2 => 0,
+            MbcType::Mbc3 => 0x10000,
+            MbcType::Mbc5 => 0x20000,
+            _ => usize::MAX,
+        };
+        if ram_size == RamSize::SizeUnknown {
+            warnings.push(RomWarning::InvalidRamSize(self.rom_data[0x0149]));
+        } else if ram_size.bytes() > ram_limit {
+            warnings.push(RomWarning::OversizedRam(ram_size, rom_type));
+        }
+        warnings
+    }
+
     pub fn rom_data_mut(&mut self) -> &mut Vec<u8> {
         &mut self.rom_data
     }
@@ -930,6 +1577,87 @@ impl Cartridge {
     pub fn ram_data_mut(&mut self) -> &mut Vec<u8> {
         &mut self.ram_data
     }
+
+    pub fn ram_dirty(&self) -> bool {
+        self.ram_dirty
+    }
+
+    pub fn set_ram_dirty(&mut self, value: bool) {
+        self.ram_dirty = value;
+    }
+
+    /// If the battery backed RAM has changes that have not
+    /// been saved yet.
+    pub fn battery_dirty(&self) -> bool {
+        self.battery_frame.is_some()
+    }
+
+    pub fn battery_frame(&self) -> Option<u16> {
+        self.battery_frame
+    }
+
+    pub fn set_battery_frame(&mut self, value: Option<u16>) {
+        self.battery_frame = value;
+    }
+
+    pub fn rtc(&self) -> &Rtc {
+        &self.rtc
+    }
+
+    pub fn rtc_mut(&mut self) -> &mut Rtc {
+        &mut self.rtc
+    }
+
+    pub fn set_rtc(&mut self, rtc: Rtc) {
+        if self.clock.is_some() {
+            self.clock = Some(rtc.timestamp());
+            self.clock_cycles = 0;
+        }
+        self.rtc = rtc;
+    }
+
+    /// The current UNIX time as seen by the cartridge, either the
+    /// deterministic (emulated) clock or the wall clock.
+    pub fn now(&self) -> u64 {
+        self.clock.unwrap_or_else(timestamp)
+    }
+
+    pub fn clock(&self) -> Option<u64> {
+        self.clock
+    }
+
+    /// Sets the deterministic clock to the provided UNIX time (or
+    /// back to the wall clock with `None`), re-basing the RTC so
+    /// that no time is accounted for the change.
+    pub fn set_clock(&mut self, value: Option<u64>) {
+        self.clock = value;
+        self.clock_cycles = 0;
+        self.rtc.set_timestamp(self.now());
+    }
+
+    /// Advances the deterministic clock (if any) by the provided
+    /// number of (normal speed) cycles.
+    #[inline(always)]
+    pub fn clock_time(&mut self, cycles: u16) {
+        if let Some(clock) = self.clock.as_mut() {
+            self.clock_cycles += cycles as u32;
+            if self.clock_cycles >= GameBoy::CPU_FREQ {
+                self.clock_cycles -= GameBoy::CPU_FREQ;
+                *clock += 1;
+            }
+        }
+    }
+
+    pub fn rtc_register(&self) -> u8 {
+        self.rtc_register
+    }
+}
+
+impl BusMap for Cartridge {
+    const BUS_RANGES: &'static [BusRange] = &[
+        BusRange::new(0x0000, 0x7fff, BusRegion::Rom),
+        BusRange::new(0xa000, 0xbfff, BusRegion::ExtRam),
+    ];
 }
 
 impl BusComponent for Cartridge {
@@ -954,6 +1682,222 @@ impl Display for Cartridge {
     }
 }
 
+/// The size in bytes of the RTC footer appended to the RAM
+/// data in `.sav` files, following the format used by BGB and
+/// VBA-M (5 current registers, 5 latched registers, each as a
+/// little endian 32 bit value, and a 64 bit UNIX timestamp).
+pub const RTC_FOOTER_SIZE: usize = 48;
+
+/// The size of the legacy RTC footer, used by older emulators
+/// that store the UNIX timestamp as a 32 bit value.
+pub const RTC_FOOTER_SIZE_LEGACY: usize = 44;
+
+/// Real Time Clock (RTC) of the MBC3 cartridges, keeps track
+/// of the elapsed time using the wall-clock time of the host,
+/// so that time keeps advancing while the emulator is closed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rtc {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+
+    /// The 9 bit day counter, the upper bit is stored
+    /// in bit 0 of the DH register.
+    days: u16,
+
+    /// If the clock is halted, meaning that no time
+    /// is accounted while this flag is set.
+    halt: bool,
+
+    /// Set when the day counter overflows (more than
+    /// 511 days), only cleared by an explicit write.
+    carry: bool,
+
+    /// The latched values of the RTC registers (S, M, H, DL
+    /// and DH), the ones visible to the game when reading.
+    latched: [u8; 5],
+
+    /// The last value written to the latch register, the
+    /// latch happens on a 0x00 to 0x01 transition.
+    latch: u8,
+
+    /// The UNIX timestamp (in seconds) up to which the
+    /// time has already been accounted in the registers.
+    timestamp: u64,
+}
+
+impl Rtc {
+    pub fn new(timestamp: u64) -> Self {
+        Self {
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+            days: 0,
+            halt: false,
+            carry: false,
+            latched: [0x00; 5],
+            latch: 0xff,
+            timestamp,
+        }
+    }
+
+    /// Advances the clock registers by the number of seconds
+    /// that have elapsed since the last update, no time is
+    /// accounted while the clock is halted.
+    pub fn update(&mut self, now: u64) {
+        let elapsed = now.saturating_sub(self.timestamp);
+        self.timestamp = now;
+        if self.halt || elapsed == 0 {
+            return;
+        }
+
+        let total = self.seconds as u64
+            + self.minutes as u64 * 60
+            + self.hours as u64 * 3600
+            + self.days as u64 * 86400
+            + elapsed;
+
+        self.seconds = (total % 60) as u8;
+        self.minutes = (total / 60 % 60) as u8;
+        self.hours = (total / 3600 % 24) as u8;
+        let days = total / 86400;
+        if days > 0x1ff {
+            self.carry = true;
+        }
+        self.days = (days & 0x1ff) as u16;
+    }
+
+    /// Copies the current value of the clock registers into
+    /// the latched registers, visible to the game.
+    pub fn latch(&mut self, now: u64) {
+        self.update(now);
+        self.latched = self.registers();
+    }
+
+    /// Handles a write to the latch register (0x6000-0x7FFF),
+    /// latching the clock on a 0x00 to 0x01 transition.
+    pub fn write_latch(&mut self, value: u8, now: u64) {
+        if self.latch == 0x00 && value == 0x01 {
+            self.latch(now);
+        }
+        self.latch = value;
+    }
+
+    /// Reads the latched value of the provided RTC register
+    /// (0x08-0x0C), as selected in 0x4000-0x5FFF.
+    pub fn read(&self, register: u8) -> u8 {
+        match register {
+            0x08 => self.latched[0] | 0xc0,
+            0x09 => self.latched[1] | 0xc0,
+            0x0a => self.latched[2] | 0xe0,
+            0x0b => self.latched[3],
+            0x0c => self.latched[4] | 0x3e,
+            _ => 0xff,
+        }
+    }
+
+    /// Writes the value to the provided RTC register (0x08-0x0C),
+    /// the time elapsed so far is accounted before the write.
+    pub fn write(&mut self, register: u8, value: u8, now: u64) {
+        self.update(now);
+        match register {
+            0x08 => self.seconds = value & 0x3f,
+            0x09 => self.minutes = value & 0x3f,
+            0x0a => self.hours = value & 0x1f,
+            0x0b => self.days = (self.days & 0x100) | value as u16,
+            0x0c => {
+                self.days = (self.days & 0x00ff) | ((value as u16 & 0x01) << 8);
+                self.halt = value & 0x40 == 0x40;
+                self.carry = value & 0x80 == 0x80;
+            }
+            _ => return,
+        }
+        self.latched[register as usize - 0x08] = self.registers()[register as usize - 0x08];
+    }
+
+    /// Obtains the current (non latched) values of the clock
+    /// registers as (S, M, H, DL, DH).
+    pub fn registers(&self) -> [u8; 5] {
+        [
+            self.seconds,
+            self.minutes,
+            self.hours,
+            (self.days & 0xff) as u8,
+            (self.days >> 8) as u8 & 0x01
+                | if self.halt { 0x40 } else { 0x00 }
+                | if self.carry { 0x80 } else { 0x00 },
+        ]
+    }
+
+    pub fn latched(&self) -> [u8; 5] {
+        self.latched
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Changes the time up to which the time has been accounted
+    /// in the registers, without advancing them.
+    pub fn set_timestamp(&mut self, value: u64) {
+        self.timestamp = value;
+    }
+
+    pub fn halt(&self) -> bool {
+        self.halt
+    }
+
+    pub fn carry(&self) -> bool {
+        self.carry
+    }
+
+    pub fn days(&self) -> u16 {
+        self.days
+    }
+
+    /// Serializes the RTC into the 48 bytes footer format used
+    /// in `.sav` files and in the BESS RTC block.
+    pub fn to_data(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(RTC_FOOTER_SIZE);
+        for value in self.registers().iter().chain(self.latched.iter()) {
+            data.extend_from_slice(&(*value as u32).to_le_bytes());
+        }
+        data.extend_from_slice(&self.timestamp.to_le_bytes());
+        data
+    }
+
+    /// Builds an RTC from the provided footer data, supporting
+    /// both the 48 bytes and the legacy 44 bytes formats.
+    pub fn from_data(data: &[u8]) -> Result<Self, Error> {
+        let timestamp = match data.len() {
+            RTC_FOOTER_SIZE => u64::from_le_bytes(data[40..48].try_into().unwrap()),
+            RTC_FOOTER_SIZE_LEGACY => u32::from_le_bytes(data[40..44].try_into().unwrap()) as u64,
+            _ => {
+                return Err(Error::InvalidData);
+            }
+        };
+        let register =
+            |index: usize| u32::from_le_bytes(data[index * 4..index * 4 + 4].try_into().unwrap());
+        let mut rtc = Self::new(timestamp);
+        rtc.seconds = register(0) as u8 & 0x3f;
+        rtc.minutes = register(1) as u8 & 0x3f;
+        rtc.hours = register(2) as u8 & 0x1f;
+        rtc.days = ((register(4) as u16 & 0x01) << 8) | (register(3) as u16 & 0xff);
+        rtc.halt = register(4) & 0x40 == 0x40;
+        rtc.carry = register(4) & 0x80 == 0x80;
+        for index in 0..5 {
+            rtc.latched[index] = register(index + 5) as u8;
+        }
+        Ok(rtc)
+    }
+}
+
+impl Default for Rtc {
+    fn default() -> Self {
+        Self::new(timestamp())
+    }
+}
+
 pub struct Mbc {
     pub name: &'static str,
     pub read_rom: fn(rom: &Cartridge, addr: u16) -> u8,
@@ -974,9 +1918,10 @@ pub static NO_MBC: Mbc = Mbc {
             _ => panic_gb!("Writing to unknown Cartridge ROM location 0x{:04x}", addr),
         };
     },
-    read_ram: |rom: &Cartridge, addr: u16| -> u8 { rom.ram_data[(addr - 0xa000) as usize] },
+    read_ram: |rom: &Cartridge, addr: u16| -> u8 { rom.ram_data[rom.ram_index(addr)] },
     write_ram: |rom: &mut Cartridge, addr: u16, value: u8| {
-        rom.ram_data[(addr - 0xa000) as usize] = value;
+        let index = rom.ram_index(addr);
+        rom.ram_data[index] = value;
     },
 };
 
@@ -1016,10 +1961,7 @@ pub static MBC1: Mbc = Mbc {
             // 0x4000-0x5FFF - RAM bank selection and ROM bank selection upper bits
             0x4000..=0x5fff => {
                 let ram_bank = value & 0x03;
-                if ram_bank as u16 >= rom.ram_bank_count {
-                    return;
-                }
-                rom.set_ram_bank(ram_bank);
+                rom.set_ram_bank(ram_bank & rom.ram_bank_mask());
             }
             // 0x6000-0x7FFF - ROM mode selection
             0x6000..=0x7fff => {
@@ -1034,7 +1976,7 @@ pub static MBC1: Mbc = Mbc {
         if !rom.ram_enabled {
             return 0xff;
         }
-        rom.ram_data[rom.ram_offset + (addr - 0xa000) as usize]
+        rom.ram_data[rom.ram_index(addr)]
     },
     write_ram: |rom: &mut Cartridge, addr: u16, value: u8| {
         if !rom.ram_enabled {
@@ -1044,7 +1986,8 @@ pub static MBC1: Mbc = Mbc {
                 return;
             }
         }
-        rom.ram_data[rom.ram_offset + (addr - 0xa000) as usize] = value;
+        let index = rom.ram_index(addr);
+        rom.ram_data[index] = value;
     },
 };
 
@@ -1068,19 +2011,23 @@ pub static MBC2: Mbc = Mbc {
     },
     write_rom: |rom: &mut Cartridge, addr: u16, value: u8| {
         match addr {
-            // 0x0000–0x3FFF - RAM Enable, ROM Bank Number
+            // 0x0000–0x3FFF - RAM Enable, ROM Bank Number, the register
+            // is selected by bit 8 of the address and aliased across
+            // the whole range
             0x0000..=0x3fff => {
                 if addr & 0x0100 == 0x0000 {
                     rom.ram_enabled = (value & 0x0f) == 0x0a;
                 } else {
                     let mut rom_bank = value as u16 & 0x0f;
-                    rom_bank &= rom.rom_bank_count * 2 - 1;
                     if rom_bank == 0 {
                         rom_bank = 1;
                     }
+                    rom_bank &= max(rom.rom_bank_count, 1) - 1;
                     rom.set_rom_bank(rom_bank);
                 }
             }
+            // 0x4000-0x7FFF - No registers mapped
+            0x4000..=0x7fff => (),
             _ => warnln!("Writing to unknown Cartridge ROM location 0x{:04x}", addr),
         }
     },
@@ -1088,17 +2035,11 @@ pub static MBC2: Mbc = Mbc {
         if !rom.ram_enabled {
             return 0xff;
         }
-        match addr {
-            // 0xA000–0xA1FF — Built-in RAM
-            0xa000..=0xa1ff => rom.ram_data[rom.ram_offset + (addr - 0xa000) as usize],
-            // 0xA200–0xBFFF — 15 "echoes" of A000–A1FF
-            0xa200..=0xbfff => rom.ram_data[rom.ram_offset + (addr - 0xa200) as usize],
-            _ => {
-                warnln!("Reading from unknown Cartridge RAM location 0x{:04x}", addr);
-                #[allow(unreachable_code)]
-                0xff
-            }
-        }
+        // 0xA000–0xA1FF — Built-in RAM, with 0xA200–0xBFFF being
+        // 15 "echoes" of it, only the lower nibble is stored and
+        // the upper nibble is read as set (open bus)
+        let index = (addr as usize - 0xa000) & (MBC2_RAM_SIZE - 1);
+        0xf0 | (rom.ram_data.get(index).unwrap_or(&0x0f) & 0x0f)
     },
     write_ram: |rom: &mut Cartridge, addr: u16, value: u8| {
         if !rom.ram_enabled {
@@ -1108,12 +2049,11 @@ pub static MBC2: Mbc = Mbc {
                 return;
             }
         }
-        match addr {
-            // 0xA000–0xA1FF — Built-in RAM
-            0xa000..=0xa1ff => rom.ram_data[rom.ram_offset + (addr - 0xa000) as usize] = value,
-            // 0xA200–0xBFFF — 15 "echoes" of A000–A1FF
-            0xa200..=0xbfff => rom.ram_data[rom.ram_offset + (addr - 0xa200) as usize] = value,
-            _ => warnln!("Writing to unknown Cartridge RAM location 0x{:04x}", addr),
+        // 0xA000–0xA1FF — Built-in RAM, with 0xA200–0xBFFF being
+        // 15 "echoes" of it, only the lower nibble is stored
+        let index = (addr as usize - 0xa000) & (MBC2_RAM_SIZE - 1);
+        if let Some(byte) = rom.ram_data.get_mut(index) {
+            *byte = value & 0x0f;
         }
     },
 };
@@ -1151,13 +2091,22 @@ pub static MBC3: Mbc = Mbc {
                 }
                 rom.set_rom_bank(rom_bank);
             }
-            // 0x4000-0x5FFF - RAM bank selection
-            0x4000..=0x5fff => {
-                let ram_bank = value & 0x03;
-                if ram_bank as u16 >= rom.ram_bank_count {
-                    return;
+            // 0x4000-0x5FFF - RAM bank or RTC register selection
+            0x4000..=0x5fff => match value {
+                0x00..=0x07 => {
+                    let ram_bank = value & 0x03;
+                    rom.set_ram_bank(ram_bank & rom.ram_bank_mask());
+                    rom.rtc_register = 0x00;
+                }
+                0x08..=0x0c => rom.rtc_register = value,
+                _ => warnln!("Selecting unknown RAM bank or RTC register 0x{:02x}", value),
+            },
+            // 0x6000-0x7FFF - Latch clock data
+            0x6000..=0x7fff => {
+                if rom.has_rtc() {
+                    let now = rom.now();
+                    rom.rtc.write_latch(value, now);
                 }
-                rom.set_ram_bank(ram_bank);
             }
             _ => warnln!("Writing to unknown Cartridge ROM location 0x{:04x}", addr),
         }
@@ -1166,7 +2115,13 @@ pub static MBC3: Mbc = Mbc {
         if !rom.ram_enabled {
             return 0xff;
         }
-        rom.ram_data[rom.ram_offset + (addr - 0xa000) as usize]
+        if rom.rtc_register != 0x00 {
+            if !rom.has_rtc() {
+                return 0xff;
+            }
+            return rom.rtc.read(rom.rtc_register);
+        }
+        rom.ram_data[rom.ram_index(addr)]
     },
     write_ram: |rom: &mut Cartridge, addr: u16, value: u8| {
         if !rom.ram_enabled {
@@ -1176,7 +2131,16 @@ pub static MBC3: Mbc = Mbc {
                 return;
             }
         }
-        rom.ram_data[rom.ram_offset + (addr - 0xa000) as usize] = value;
+        if rom.rtc_register != 0x00 {
+            if rom.has_rtc() {
+                let register = rom.rtc_register;
+                let now = rom.now();
+                rom.rtc.write(register, value, now);
+            }
+            return;
+        }
+        let index = rom.ram_index(addr);
+        rom.ram_data[index] = value;
     },
 };
 
@@ -1229,11 +2193,7 @@ pub static MBC5: Mbc = Mbc {
                     }
                 }
 
-                if ram_bank as u16 >= rom.ram_bank_count {
-                    return;
-                }
-
-                rom.set_ram_bank(ram_bank);
+                rom.set_ram_bank(ram_bank & rom.ram_bank_mask());
             }
             _ => warnln!("Writing to unknown Cartridge ROM location 0x{:04x}", addr),
         }
@@ -1242,7 +2202,7 @@ pub static MBC5: Mbc = Mbc {
         if !rom.ram_enabled {
             return 0xff;
         }
-        rom.ram_data[rom.ram_offset + (addr - 0xa000) as usize]
+        rom.ram_data[rom.ram_index(addr)]
     },
     write_ram: |rom: &mut Cartridge, addr: u16, value: u8| {
         if !rom.ram_enabled {
@@ -1252,14 +2212,28 @@ pub static MBC5: Mbc = Mbc {
                 return;
             }
         }
-        rom.ram_data[rom.ram_offset + (addr - 0xa000) as usize] = value;
+        let index = rom.ram_index(addr);
+        rom.ram_data[index] = value;
     },
 };
 
-pub static GAME_GENIE: Mbc = Mbc {
-    name: "GameGenie",
+/// Handler used while cheats (Game Genie or GameShark) are attached,
+/// patching the ROM reads before delegating to the MBC.
+pub static CHEATS: Mbc = Mbc {
+    name: "Cheats",
     read_rom: |rom: &Cartridge, addr: u16| -> u8 {
-        let game_genie = rom.game_genie.as_ref().unwrap();
+        // the ROM patch codes of the GameShark replace the byte
+        // unconditionally, taking precedence over the Game Genie
+        if let Some(data) = rom
+            .game_shark
+            .as_ref()
+            .and_then(|game_shark| game_shark.patch_rom(addr))
+        {
+            return data;
+        }
+        let Some(game_genie) = rom.game_genie.as_ref() else {
+            return (rom.mbc.read_rom)(rom, addr);
+        };
         if game_genie.contains_addr(addr) {
             // retrieves the Game Genie code that matches the current address
             // keep in mind that this assumes that no more that one code is
@@ -1288,7 +2262,13 @@ pub static GAME_GENIE: Mbc = Mbc {
 
 #[cfg(test)]
 mod tests {
-    use super::{Cartridge, RomType};
+    use boytacean_hashing::crc32::crc32;
+
+    use crate::gb::{GameBoy, GameBoyMode};
+
+    use super::{
+        Cartridge, RamSize, RomType, RomWarning, Rtc, MBC2_RAM_SIZE, MBC5, RTC_FOOTER_SIZE,
+    };
 
     #[test]
     fn test_has_rumble() {
@@ -1308,4 +2288,365 @@ mod tests {
         rom.set_rom_type(RomType::Mbc1).unwrap();
         assert!(!rom.has_rumble());
     }
+
+    #[test]
+    fn test_title() {
+        let mut data = vec![0; 0x8000];
+        data[0x0134..0x013a].copy_from_slice(b"TETRIS");
+        let mut rom = Cartridge::new();
+        rom.set_data(&data).unwrap();
+        assert_eq!(rom.title(), "TETRIS");
+
+        // a full 16 bytes DMG title is kept complete
+        data[0x0134..=0x0143].copy_from_slice(b"SIXTEEN BYTES GB");
+        rom.set_data(&data).unwrap();
+        assert_eq!(rom.title(), "SIXTEEN BYTES GB");
+
+        // the CGB flag overlapping the last byte of the title is ignored
+        data[0x0143] = 0xc0;
+        rom.set_data(&data).unwrap();
+        assert_eq!(rom.title(), "SIXTEEN BYTES G");
+
+        // invalid UTF-8 bytes are decoded lossily, and the trailing
+        // spaces and control characters are removed
+        data[0x0134..=0x0143].copy_from_slice(b"\xb2\xc1ZELDA  \x01\x02\x00\x00\x00\x00\x80");
+        rom.set_data(&data).unwrap();
+        assert_eq!(rom.title(), "\u{fffd}\u{fffd}ZELDA");
+        assert_eq!(
+            Cartridge::title_from_data(&data[0x0134..=0x0143]),
+            rom.title()
+        );
+        assert_eq!(Cartridge::title_length(&data[0x0134..=0x0143]), 11);
+        assert_eq!(Cartridge::title_length(&[0x41; 16]), 16);
+        assert_eq!(Cartridge::title_length(&[0x41; 4]), 4);
+    }
+
+    #[test]
+    fn test_cgb_compat_palettes() {
+        let mut data = vec![0; 0x8000];
+        data[0x0134..0x013a].copy_from_slice(b"TETRIS");
+        data[0x014b] = 0x01;
+        let mut rom = Cartridge::new();
+        rom.set_data(&data).unwrap();
+        assert_eq!(
+            rom.cgb_compat_palettes(),
+            [[0x7fff, 0x03ff, 0x001f, 0x0000]; 3]
+        );
+
+        // the checksum is ambiguous and the 4th letter is used, with
+        // the OBJ0 palette starting in the middle of a palette
+        data[0x0134..0x0143].copy_from_slice(b"SUPER MARIOLAND");
+        rom.set_data(&data).unwrap();
+        assert_eq!(
+            rom.cgb_compat_palettes(),
+            [
+                [0x0000, 0x7fff, 0x421f, 0x1cf2],
+                [0x0000, 0x7fff, 0x421f, 0x1cf2],
+                [0x7ed6, 0x4bff, 0x2175, 0x0000]
+            ]
+        );
+
+        // non Nintendo games always use the default palettes
+        data[0x014b] = 0x00;
+        rom.set_data(&data).unwrap();
+        assert_eq!(
+            rom.cgb_compat_palettes(),
+            [
+                [0x7fff, 0x421f, 0x1cf2, 0x0000],
+                [0x7fff, 0x421f, 0x1cf2, 0x0000],
+                [0x7fff, 0x1bef, 0x6180, 0x0000]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mbc2() {
+        let mut data = vec![0; 0x40000];
+        for (index, bank) in data.chunks_mut(0x4000).enumerate() {
+            bank[0x0000] = index as u8;
+        }
+        data[0x0147] = 0x06;
+        data[0x0148] = 0x03;
+        let mut rom = Cartridge::from_data(&data).unwrap();
+        assert_eq!(rom.rom_type(), RomType::Mbc2Battery);
+        assert_eq!(rom.ram_data().len(), MBC2_RAM_SIZE);
+
+        rom.write(0x2100, 0x05);
+        assert_eq!(rom.rom_bank(), 5);
+        assert_eq!(rom.read(0x4000), 5);
+        rom.write(0x3fff, 0x00);
+        assert_eq!(rom.rom_bank(), 1);
+        rom.write(0x2100, 0x1f);
+        assert_eq!(rom.rom_bank(), 15);
+        rom.write(0x2000, 0x03);
+        assert_eq!(rom.rom_bank(), 15);
+
+        assert_eq!(rom.read(0xa000), 0xff);
+        rom.write(0x0000, 0x0a);
+        assert!(rom.ram_enabled());
+        rom.write(0xa001, 0x5c);
+        assert_eq!(rom.read(0xa001), 0xfc);
+        assert_eq!(rom.read(0xa201), 0xfc);
+        assert_eq!(rom.read(0xbe01), 0xfc);
+        rom.write(0xbfff, 0x03);
+        assert_eq!(rom.read(0xa1ff), 0xf3);
+        assert_eq!(rom.ram_data()[0x0001], 0x0c);
+        rom.write(0x0100, 0x00);
+        assert!(rom.ram_enabled());
+        rom.write(0x0000, 0x00);
+        assert_eq!(rom.read(0xa001), 0xff);
+    }
+
+    #[test]
+    fn test_ram_sizes() {
+        let sizes = [
+            (0x00, RamSize::NoRam, 0x2000),
+            (0x01, RamSize::Size2K, 0x0800),
+            (0x02, RamSize::Size8K, 0x2000),
+            (0x03, RamSize::Size32K, 0x8000),
+            (0x04, RamSize::Size128K, 0x20000),
+            (0x05, RamSize::Size64K, 0x10000),
+        ];
+        for (code, ram_size, size) in sizes {
+            let mut data = vec![0; 0x8000];
+            data[0x0147] = 0x1b;
+            data[0x0149] = code;
+            let mut rom = Cartridge::from_data(&data).unwrap();
+            assert_eq!(rom.ram_size(), ram_size);
+            assert_eq!(rom.ram_data().len(), size);
+
+            // writes a marker in the last bank and then selects a bank
+            // beyond the available ones, which should wrap around
+            let banks = rom.ram_size().ram_banks().max(1) as u8;
+            rom.write(0x0000, 0x0a);
+            rom.write(0x4000, banks - 1);
+            rom.write(0xa000, code + 0x10);
+            rom.write(0x4000, banks * 2 - 1);
+            assert_eq!(rom.ram_bank(), banks - 1);
+            assert_eq!(rom.read(0xa000), code + 0x10);
+
+            rom.set_ram_data(&vec![0xaa; size / 2]);
+            assert_eq!(rom.ram_data().len(), size);
+            rom.set_ram_data(&vec![0xaa; size * 2]);
+            assert_eq!(rom.ram_data().len(), size);
+        }
+    }
+
+    #[test]
+    fn test_ram_2k_mirror() {
+        let mut data = vec![0; 0x8000];
+        data[0x0147] = 0x03;
+        data[0x0149] = 0x01;
+        let mut rom = Cartridge::from_data(&data).unwrap();
+        rom.write(0x0000, 0x0a);
+        rom.write(0xa010, 0x42);
+        assert_eq!(rom.read(0xa810), 0x42);
+        assert_eq!(rom.read(0xb810), 0x42);
+        rom.write(0xbfff, 0x24);
+        assert_eq!(rom.read(0xa7ff), 0x24);
+        assert_eq!(rom.ram_data().len(), 0x0800);
+    }
+
+    #[test]
+    fn test_rtc() {
+        let mut rtc = Rtc::new(1000);
+        rtc.update(1000 + 86400 + 3600 * 2 + 60 * 3 + 4);
+        assert_eq!(rtc.registers(), [4, 3, 2, 1, 0]);
+        assert_eq!(rtc.latched(), [0, 0, 0, 0, 0]);
+
+        rtc.write_latch(0x00, 1000 + 86400 + 3600 * 2 + 60 * 3 + 4);
+        rtc.write_latch(0x01, 1000 + 86400 + 3600 * 2 + 60 * 3 + 5);
+        assert_eq!(rtc.latched(), [5, 3, 2, 1, 0]);
+        assert_eq!(rtc.read(0x08), 0xc5);
+        assert_eq!(rtc.read(0x0b), 0x01);
+
+        rtc.write(0x0c, 0x40, 2000);
+        assert!(rtc.halt());
+        rtc.update(100000);
+        assert_eq!(rtc.registers()[0..4], [5, 3, 2, 1]);
+
+        rtc.write(0x0b, 0xff, 100000);
+        rtc.write(0x0c, 0x01, 100000);
+        rtc.update(100000 + 86400);
+        assert_eq!(rtc.days(), 0x000);
+        assert!(rtc.carry());
+        assert_eq!(rtc.read(0x0c) & 0x80, 0x00);
+        rtc.latch(100000 + 86400);
+        assert_eq!(rtc.read(0x0c) & 0x80, 0x80);
+    }
+
+    #[test]
+    fn test_rtc_data() {
+        let mut rtc = Rtc::new(1234567890);
+        rtc.write(0x0a, 0x17, 1234567890);
+        rtc.write(0x0c, 0x41, 1234567890);
+        let data = rtc.to_data();
+        assert_eq!(data.len(), RTC_FOOTER_SIZE);
+        assert_eq!(Rtc::from_data(&data).unwrap(), rtc);
+        assert_eq!(
+            Rtc::from_data(&data[0..44]).unwrap().registers(),
+            rtc.registers()
+        );
+        assert!(Rtc::from_data(&data[0..40]).is_err());
+    }
+
+    #[test]
+    fn test_mbc3_rtc() {
+        let mut data = vec![0; 0x8000];
+        data[0x0147] = 0x10;
+        data[0x0149] = 0x02;
+        let mut rom = Cartridge::from_data(&data).unwrap();
+        assert!(rom.has_rtc());
+
+        rom.write(0x0000, 0x0a);
+        rom.write(0xa000, 0x42);
+        rom.write(0x4000, 0x0a);
+        rom.write(0xa000, 0x05);
+        assert_eq!(rom.rtc().registers()[2], 0x05);
+        rom.write(0x6000, 0x00);
+        rom.write(0x6000, 0x01);
+        assert_eq!(rom.read(0xa000), 0xe5);
+        rom.write(0x4000, 0x00);
+        assert_eq!(rom.read(0xa000), 0x42);
+
+        let save_data = rom.save_data();
+        assert_eq!(save_data.len(), 0x2000 + RTC_FOOTER_SIZE);
+
+        let mut other = Cartridge::from_data(&data).unwrap();
+        other.set_save_data(&save_data);
+        assert_eq!(other.ram_data().len(), 0x2000);
+        assert_eq!(other.ram_data()[0], 0x42);
+        assert_eq!(other.rtc().registers()[2], 0x05);
+
+        other.set_save_data(&vec![0x11; 0x2000]);
+        assert_eq!(other.ram_data()[0], 0x11);
+        assert_eq!(other.rtc().registers()[2], 0x05);
+    }
+
+    #[test]
+    fn test_rom_streamed() {
+        // builds a 1 MB MBC5 ROM with the index of each
+        // bank written in the first bytes of the bank
+        let mut data = vec![0; 0x100000];
+        for (index, bank) in data.chunks_mut(0x4000).enumerate() {
+            bank[0x0000] = index as u8;
+            bank[0x3fff] = !(index as u8);
+        }
+        data[0x0147] = 0x19;
+        data[0x0148] = 0x05;
+
+        let mut rom = Cartridge::from_source(data.clone(), 2).unwrap();
+        assert!(rom.streamed());
+        assert_eq!(rom.rom_type(), RomType::Mbc5);
+        assert_eq!(rom.fingerprint(), crc32(&data));
+        assert_eq!(rom.data().len(), 0x4000 * 3);
+        assert_eq!(rom.rom_bank(), 1);
+        assert_eq!(rom.read(0x0000), 0);
+        assert_eq!(rom.read(0x4000), 1);
+        assert_eq!(rom.stream_loads(), 1);
+
+        rom.write(0x2000, 0x20);
+        assert_eq!(rom.rom_bank(), 0x20);
+        assert_eq!(rom.read(0x4000), 0x20);
+        assert_eq!(rom.read(0x7fff), !0x20);
+        assert_eq!(rom.stream_loads(), 2);
+
+        // switching back to a cached bank does not require
+        // a new load, while a third bank evicts the least
+        // recently used one (bank 0x20)
+        rom.write(0x2000, 0x01);
+        assert_eq!(rom.read(0x4000), 1);
+        assert_eq!(rom.stream_loads(), 2);
+        rom.write(0x2000, 0x3f);
+        assert_eq!(rom.read(0x4000), 0x3f);
+        assert_eq!(rom.stream_loads(), 3);
+        rom.write(0x2000, 0x01);
+        assert_eq!(rom.read(0x4000), 1);
+        assert_eq!(rom.stream_loads(), 3);
+        rom.write(0x2000, 0x20);
+        assert_eq!(rom.read(0x4000), 0x20);
+        assert_eq!(rom.stream_loads(), 4);
+
+        rom.close();
+        assert!(!rom.streamed());
+        assert!(rom.data().is_empty());
+
+        assert!(Cartridge::from_source(vec![0u8; 0x5000], 2).is_err());
+    }
+
+    #[test]
+    fn test_from_data_patched() {
+        let mut data = vec![0; 0x8000];
+        data[0x0134..0x0138].copy_from_slice(b"ROM1");
+
+        // patches the title of the ROM, leaving the original intact
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&[0x00, 0x01, 0x37, 0x00, 0x01, b'2']);
+        patch.extend_from_slice(b"EOF");
+        let rom = Cartridge::from_data_patched(&data, &patch).unwrap();
+        assert_eq!(rom.title(), "ROM2");
+        assert_eq!(&data[0x0134..0x0138], b"ROM1");
+        assert!(Cartridge::from_data_patched(&data, b"INVALID").is_err());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        // unsupported MBC (MBC6) with an overdumped ROM, an invalid
+        // RAM size code and an invalid header checksum, still loads
+        let mut data = vec![0; 0x20000];
+        data[0x0147] = 0x20;
+        data[0x0148] = 0x01;
+        data[0x0149] = 0x09;
+        let rom = Cartridge::from_data(&data).unwrap();
+        assert_eq!(rom.mbc.name, MBC5.name);
+        assert_eq!(
+            rom.diagnostics(),
+            vec![
+                RomWarning::UnsupportedMbc(RomType::Mbc6),
+                RomWarning::InvalidHeaderChecksum {
+                    expected: rom.checksum(),
+                    found: 0x00
+                },
+                RomWarning::RomSizeMismatch {
+                    expected: 0x10000,
+                    found: 0x20000
+                },
+                RomWarning::InvalidRamSize(0x09),
+            ]
+        );
+
+        // MBC1 cartridge declaring more RAM than the MBC can address
+        data.truncate(0x10000);
+        data[0x0147] = 0x03;
+        data[0x0149] = 0x04;
+        data[0x014d] = Cartridge::from_data(&data).unwrap().checksum();
+        let rom = Cartridge::from_data(&data).unwrap();
+        assert_eq!(
+            rom.diagnostics(),
+            vec![RomWarning::OversizedRam(
+                RamSize::Size128K,
+                RomType::Mbc1RamBattery
+            )]
+        );
+
+        // CGB only and SGB cartridge running in DMG mode
+        data[0x0143] = 0xc0;
+        data[0x0146] = 0x03;
+        data[0x0149] = 0x03;
+        data[0x014d] = Cartridge::from_data(&data).unwrap().checksum();
+        let mut gb = GameBoy::new(Some(GameBoyMode::Dmg));
+        gb.load(true).unwrap();
+        gb.load_rom(&data, None).unwrap();
+        assert_eq!(
+            gb.rom_warnings(),
+            vec![
+                RomWarning::CgbOnly(GameBoyMode::Dmg),
+                RomWarning::SgbUnsupported(GameBoyMode::Dmg),
+            ]
+        );
+        assert_eq!(
+            gb.rom_warnings()[0].to_string(),
+            "CGB only cartridge running in Game Boy (DMG) mode"
+        );
+    }
 }
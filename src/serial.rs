@@ -1,8 +1,17 @@
 //! Serial transfer (Link Cable) functions and structures.
 
+use std::io::Cursor;
+
+use boytacean_common::{
+    data::{read_i16, read_u16, read_u8, write_i16, write_u16, write_u8},
+    error::Error,
+};
+
 use crate::{
     consts::{SB_ADDR, SC_ADDR},
+    io::{Io, IoComponent, IoRegister},
     mmu::BusComponent,
+    state::{StateComponent, StateFormat},
     warnln,
 };
 
@@ -25,6 +34,26 @@ pub trait SerialDevice {
     /// Returns a string describing the current state of the
     /// serial device. Could be used for debugging purposes.
     fn state(&self) -> String;
+
+    /// Returns a stable identifier of the type of device, used
+    /// to key the device state blocks in the save states so that
+    /// they are only restored into the same type of device.
+    fn id(&self) -> &'static str {
+        "unknown"
+    }
+
+    /// Obtains the binary representation of the internal state
+    /// of the device, to be stored in a save state, devices with
+    /// no relevant state should return `None`.
+    fn device_state(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Restores the internal state of the device from the binary
+    /// representation previously obtained using `device_state()`.
+    fn set_device_state(&mut self, _data: &[u8]) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
 }
 
 pub struct Serial {
@@ -186,6 +215,31 @@ impl Serial {
         self.transferring
     }
 
+    /// If a transfer has been requested using the clock of the
+    /// attached device (slave mode) and is waiting for it.
+    pub fn waiting_external(&self) -> bool {
+        self.transferring && !self.shift_clock
+    }
+
+    /// Completes at once the transfer clocked by the attached device
+    /// (eg: another linked Game Boy), with the provided byte shifted
+    /// in, returns `false` if no such transfer is waiting.
+    pub fn complete_external(&mut self, byte: u8) -> bool {
+        if !self.waiting_external() {
+            return false;
+        }
+        self.data = byte;
+        self.transferring = false;
+        self.bit_count = 0;
+        self.int_serial = true;
+        true
+    }
+
+    /// The byte currently in the transfer register (SB).
+    pub fn data(&self) -> u8 {
+        self.data
+    }
+
     pub fn set_transferring(&mut self, value: bool) {
         self.transferring = value;
     }
@@ -194,6 +248,10 @@ impl Serial {
         self.device.as_ref()
     }
 
+    pub fn device_mut(&mut self) -> &mut dyn SerialDevice {
+        self.device.as_mut()
+    }
+
     pub fn set_device(&mut self, device: Box<dyn SerialDevice>) {
         self.device = device;
     }
@@ -217,6 +275,13 @@ impl Serial {
     }
 }
 
+impl Io for Serial {
+    const IO_REGISTERS: &'static [IoRegister] = &[
+        IoRegister::new(SB_ADDR, "SB", IoComponent::Serial, 0xff, 0xff),
+        IoRegister::new(SC_ADDR, "SC", IoComponent::Serial, 0x83, 0x83),
+    ];
+}
+
 impl BusComponent for Serial {
     fn read(&self, addr: u16) -> u8 {
         self.read(addr)
@@ -227,6 +292,38 @@ impl BusComponent for Serial {
     }
 }
 
+impl StateComponent for Serial {
+    fn state(&self, _format: Option<StateFormat>) -> Result<Vec<u8>, Error> {
+        let mut cursor = Cursor::new(vec![]);
+        write_u8(&mut cursor, self.data)?;
+        write_u8(&mut cursor, self.shift_clock as u8)?;
+        write_u8(&mut cursor, self.clock_speed as u8)?;
+        write_u8(&mut cursor, self.transferring as u8)?;
+        write_i16(&mut cursor, self.timer)?;
+        write_u16(&mut cursor, self.length)?;
+        write_u8(&mut cursor, self.bit_count)?;
+        write_u8(&mut cursor, self.byte_send)?;
+        write_u8(&mut cursor, self.byte_receive)?;
+        write_u8(&mut cursor, self.int_serial as u8)?;
+        Ok(cursor.into_inner())
+    }
+
+    fn set_state(&mut self, data: &[u8], _format: Option<StateFormat>) -> Result<(), Error> {
+        let mut cursor = Cursor::new(data);
+        self.data = read_u8(&mut cursor)?;
+        self.shift_clock = read_u8(&mut cursor)? != 0;
+        self.clock_speed = read_u8(&mut cursor)? != 0;
+        self.transferring = read_u8(&mut cursor)? != 0;
+        self.timer = read_i16(&mut cursor)?;
+        self.length = read_u16(&mut cursor)?;
+        self.bit_count = read_u8(&mut cursor)?;
+        self.byte_send = read_u8(&mut cursor)?;
+        self.byte_receive = read_u8(&mut cursor)?;
+        self.int_serial = read_u8(&mut cursor)? != 0;
+        Ok(())
+    }
+}
+
 impl Default for Serial {
     fn default() -> Self {
         Self::new()
@@ -261,6 +358,10 @@ impl SerialDevice for NullDevice {
     fn state(&self) -> String {
         String::from("")
     }
+
+    fn id(&self) -> &'static str {
+        "null"
+    }
 }
 
 impl Default for NullDevice {
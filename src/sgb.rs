@@ -0,0 +1,679 @@
+//! Super Game Boy (SGB) command decoding, palettes and border rendering.
+//!
+//! The SGB commands are sent by the game as 16 bytes packets through
+//! the P1 (joypad) register, these packets are collected by the [`SgbReceiver`]
+//! in the pad and then processed by the [`Sgb`] component.
+
+use std::{
+    collections::VecDeque,
+    fmt::{self, Display, Formatter},
+};
+
+use crate::{
+    color::{rgb555_to_rgb888, Pixel, RGB_SIZE},
+    ppu::{Palette, Ppu, DISPLAY_HEIGHT, DISPLAY_WIDTH, SHADE_BUFFER_SIZE},
+};
+
+/// The size in bytes of an SGB command packet.
+pub const SGB_PACKET_SIZE: usize = 16;
+
+/// The width in pixels of the SGB screen (including border).
+pub const SGB_WIDTH: usize = 256;
+
+/// The height in pixels of the SGB screen (including border).
+pub const SGB_HEIGHT: usize = 224;
+
+/// The horizontal offset of the Game Boy screen inside the SGB screen.
+pub const SGB_SCREEN_X: usize = 48;
+
+/// The vertical offset of the Game Boy screen inside the SGB screen.
+pub const SGB_SCREEN_Y: usize = 40;
+
+/// The size of the RGB888 frame buffer of the SGB screen.
+pub const FRAME_BUFFER_SGB_SIZE: usize = SGB_WIDTH * SGB_HEIGHT * RGB_SIZE;
+
+/// The size in bytes of a VRAM transfer (`*_TRN` commands).
+pub const SGB_TRANSFER_SIZE: usize = 4096;
+
+/// The number of 8x8 cells (horizontal and vertical) in the
+/// attribute map of the Game Boy screen.
+const ATTR_WIDTH: usize = DISPLAY_WIDTH / 8;
+const ATTR_HEIGHT: usize = DISPLAY_HEIGHT / 8;
+const ATTR_SIZE: usize = ATTR_WIDTH * ATTR_HEIGHT;
+
+/// The size in bytes of an attribute file transferred with `ATTR_TRN`.
+const ATTR_FILE_SIZE: usize = 90;
+
+/// The number of attribute files transferred with `ATTR_TRN`.
+const ATTR_FILE_COUNT: usize = 45;
+
+/// The default SGB palette (1-A), used for all the four
+/// palettes until the game defines its own.
+pub const SGB_DEFAULT_PALETTE: Palette = [
+    [0xf8, 0xe8, 0xc8],
+    [0xd8, 0x90, 0x48],
+    [0xa8, 0x28, 0x20],
+    [0x30, 0x18, 0x50],
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SgbCommand {
+    Pal01,
+    Pal23,
+    Pal03,
+    Pal12,
+    AttrBlk,
+    AttrLin,
+    AttrDiv,
+    AttrChr,
+    PalSet,
+    PalTrn,
+    MltReq,
+    ChrTrn,
+    PctTrn,
+    AttrTrn,
+    AttrSet,
+    MaskEn,
+    Other(u8),
+}
+
+impl SgbCommand {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            0x00 => SgbCommand::Pal01,
+            0x01 => SgbCommand::Pal23,
+            0x02 => SgbCommand::Pal03,
+            0x03 => SgbCommand::Pal12,
+            0x04 => SgbCommand::AttrBlk,
+            0x05 => SgbCommand::AttrLin,
+            0x06 => SgbCommand::AttrDiv,
+            0x07 => SgbCommand::AttrChr,
+            0x0a => SgbCommand::PalSet,
+            0x0b => SgbCommand::PalTrn,
+            0x11 => SgbCommand::MltReq,
+            0x13 => SgbCommand::ChrTrn,
+            0x14 => SgbCommand::PctTrn,
+            0x15 => SgbCommand::AttrTrn,
+            0x16 => SgbCommand::AttrSet,
+            0x17 => SgbCommand::MaskEn,
+            value => SgbCommand::Other(value),
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            SgbCommand::Pal01 => "PAL01",
+            SgbCommand::Pal23 => "PAL23",
+            SgbCommand::Pal03 => "PAL03",
+            SgbCommand::Pal12 => "PAL12",
+            SgbCommand::AttrBlk => "ATTR_BLK",
+            SgbCommand::AttrLin => "ATTR_LIN",
+            SgbCommand::AttrDiv => "ATTR_DIV",
+            SgbCommand::AttrChr => "ATTR_CHR",
+            SgbCommand::PalSet => "PAL_SET",
+            SgbCommand::PalTrn => "PAL_TRN",
+            SgbCommand::MltReq => "MLT_REQ",
+            SgbCommand::ChrTrn => "CHR_TRN",
+            SgbCommand::PctTrn => "PCT_TRN",
+            SgbCommand::AttrTrn => "ATTR_TRN",
+            SgbCommand::AttrSet => "ATTR_SET",
+            SgbCommand::MaskEn => "MASK_EN",
+            SgbCommand::Other(_) => "Other",
+        }
+    }
+}
+
+impl Display for SgbCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// The masking mode of the Game Boy screen, set with `MASK_EN`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SgbMask {
+    None,
+    Freeze,
+    Black,
+    Color0,
+}
+
+impl SgbMask {
+    pub fn from_u8(value: u8) -> Self {
+        match value & 0x03 {
+            0x01 => SgbMask::Freeze,
+            0x02 => SgbMask::Black,
+            0x03 => SgbMask::Color0,
+            _ => SgbMask::None,
+        }
+    }
+}
+
+/// Bit level receiver of the SGB packets sent through writes
+/// to the P1 register, a reset pulse (P14 and P15 low) starts
+/// a packet, each bit is then a P14 (0) or P15 (1) low pulse,
+/// separated by both lines high, with a final stop (0) bit.
+#[derive(Default)]
+pub struct SgbReceiver {
+    active: bool,
+    ready: bool,
+    bit: usize,
+    packet: [u8; SGB_PACKET_SIZE],
+    packets: VecDeque<[u8; SGB_PACKET_SIZE]>,
+}
+
+impl SgbReceiver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles a write to the P1 register, with the selection
+    /// lines (bits 4 and 5) driving the transfer.
+    pub fn write(&mut self, value: u8) {
+        match value & 0x30 {
+            0x00 => {
+                self.active = true;
+                self.ready = false;
+                self.bit = 0;
+                self.packet = [0u8; SGB_PACKET_SIZE];
+            }
+            0x30 => self.ready = true,
+            lines => {
+                if !self.active || !self.ready {
+                    return;
+                }
+                self.ready = false;
+                if self.bit == SGB_PACKET_SIZE * 8 {
+                    self.active = false;
+                    self.packets.push_back(self.packet);
+                    return;
+                }
+                if lines == 0x10 {
+                    self.packet[self.bit / 8] |= 1 << (self.bit % 8);
+                }
+                self.bit += 1;
+            }
+        }
+    }
+
+    /// Obtains the next completely received packet, if any.
+    pub fn packet(&mut self) -> Option<[u8; SGB_PACKET_SIZE]> {
+        self.packets.pop_front()
+    }
+
+    pub fn pending(&self) -> bool {
+        !self.packets.is_empty()
+    }
+}
+
+pub struct Sgb {
+    /// The bytes of the multi packet command currently being
+    /// received, with the number of packets still missing.
+    command: Vec<u8>,
+    remaining: usize,
+
+    palettes: [Palette; 4],
+    attrs: [u8; ATTR_SIZE],
+    mask: SgbMask,
+    frozen: Box<[u8; SHADE_BUFFER_SIZE]>,
+
+    /// The number of joypads requested with `MLT_REQ` (1, 2 or 4).
+    players: u8,
+
+    /// The 512 system palettes transferred with `PAL_TRN`
+    /// and the 45 attribute files transferred with `ATTR_TRN`.
+    system_palettes: Box<[u8; SGB_TRANSFER_SIZE]>,
+    attr_files: Box<[u8; SGB_TRANSFER_SIZE]>,
+
+    /// The border data, 256 tiles in SNES 4bpp format, the 32x32 map
+    /// (with 16 bits entries) and the four border palettes (4-7).
+    border_tiles: Box<[u8; SGB_TRANSFER_SIZE * 2]>,
+    border_map: Box<[u8; 2048]>,
+    border_palettes: [[Pixel; 16]; 4],
+
+    frame_buffer: Box<[u8; FRAME_BUFFER_SGB_SIZE]>,
+}
+
+impl Sgb {
+    pub fn new() -> Self {
+        Self {
+            command: Vec::with_capacity(SGB_PACKET_SIZE * 7),
+            remaining: 0,
+            palettes: [SGB_DEFAULT_PALETTE; 4],
+            attrs: [0u8; ATTR_SIZE],
+            mask: SgbMask::None,
+            frozen: Box::new([0u8; SHADE_BUFFER_SIZE]),
+            players: 1,
+            system_palettes: Box::new([0u8; SGB_TRANSFER_SIZE]),
+            attr_files: Box::new([0u8; SGB_TRANSFER_SIZE]),
+            border_tiles: Box::new([0u8; SGB_TRANSFER_SIZE * 2]),
+            border_map: Box::new([0u8; 2048]),
+            border_palettes: [[[0u8; RGB_SIZE]; 16]; 4],
+            frame_buffer: Box::new([0u8; FRAME_BUFFER_SGB_SIZE]),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Processes a packet received through the pad, multi packet
+    /// commands are only executed once all of their packets have
+    /// been received, VRAM transfers read the data from the PPU.
+    pub fn process(&mut self, packet: &[u8; SGB_PACKET_SIZE], ppu: &Ppu) {
+        if self.remaining == 0 {
+            let length = (packet[0] & 0x07).max(1) as usize;
+            self.command.clear();
+            self.remaining = length;
+        }
+        self.command.extend_from_slice(packet);
+        self.remaining -= 1;
+        if self.remaining > 0 {
+            return;
+        }
+
+        let command = std::mem::take(&mut self.command);
+        self.execute(&command, ppu);
+        self.command = command;
+    }
+
+    fn execute(&mut self, data: &[u8], ppu: &Ppu) {
+        match SgbCommand::from_u8(data[0] >> 3) {
+            SgbCommand::Pal01 => self.set_palette_pair(data, 0, 1),
+            SgbCommand::Pal23 => self.set_palette_pair(data, 2, 3),
+            SgbCommand::Pal03 => self.set_palette_pair(data, 0, 3),
+            SgbCommand::Pal12 => self.set_palette_pair(data, 1, 2),
+            SgbCommand::AttrBlk => self.attr_blk(data),
+            SgbCommand::AttrLin => self.attr_lin(data),
+            SgbCommand::AttrDiv => self.attr_div(data),
+            SgbCommand::AttrChr => self.attr_chr(data),
+            SgbCommand::PalSet => self.pal_set(data),
+            SgbCommand::PalTrn => *self.system_palettes = Self::transfer(ppu),
+            SgbCommand::ChrTrn => {
+                let offset = (data[1] & 0x01) as usize * SGB_TRANSFER_SIZE;
+                self.border_tiles[offset..offset + SGB_TRANSFER_SIZE]
+                    .copy_from_slice(&Self::transfer(ppu));
+            }
+            SgbCommand::PctTrn => self.pct_trn(&Self::transfer(ppu)),
+            SgbCommand::AttrTrn => *self.attr_files = Self::transfer(ppu),
+            SgbCommand::AttrSet => self.attr_set(data[1]),
+            SgbCommand::MaskEn => {
+                self.mask = SgbMask::from_u8(data[1]);
+                if self.mask == SgbMask::Freeze {
+                    self.frozen.copy_from_slice(&ppu.shade_buffer[..]);
+                }
+            }
+            SgbCommand::MltReq => {
+                self.players = match data[1] & 0x03 {
+                    0x01 => 2,
+                    0x03 => 4,
+                    _ => 1,
+                }
+            }
+            SgbCommand::Other(_) => (),
+        }
+    }
+
+    /// Renders the complete SGB screen (256x224) as an RGB888 buffer,
+    /// with the provided Game Boy shade buffer colorized according to
+    /// the SGB palettes and attributes and surrounded by the border.
+    pub fn frame_buffer(
+        &mut self,
+        shade_buffer: &[u8; SHADE_BUFFER_SIZE],
+    ) -> &[u8; FRAME_BUFFER_SGB_SIZE] {
+        let backdrop = self.palettes[0][0];
+        for pixel in self.frame_buffer.chunks_mut(RGB_SIZE) {
+            pixel.copy_from_slice(&backdrop);
+        }
+
+        let shade_buffer = if self.mask == SgbMask::Freeze {
+            &*self.frozen
+        } else {
+            shade_buffer
+        };
+        for y in 0..DISPLAY_HEIGHT {
+            for x in 0..DISPLAY_WIDTH {
+                let color = match self.mask {
+                    SgbMask::Black => [0u8; RGB_SIZE],
+                    SgbMask::Color0 => backdrop,
+                    SgbMask::None | SgbMask::Freeze => {
+                        let palette = self.attrs[(y / 8) * ATTR_WIDTH + x / 8] as usize;
+                        self.palettes[palette][shade_buffer[y * DISPLAY_WIDTH + x] as usize]
+                    }
+                };
+                let offset = ((y + SGB_SCREEN_Y) * SGB_WIDTH + x + SGB_SCREEN_X) * RGB_SIZE;
+                self.frame_buffer[offset..offset + RGB_SIZE].copy_from_slice(&color);
+            }
+        }
+
+        // draws the border on top of the screen, with the color 0
+        // of the border palettes being transparent
+        for index in 0..(SGB_WIDTH / 8) * (SGB_HEIGHT / 8) {
+            let entry =
+                u16::from_le_bytes([self.border_map[index * 2], self.border_map[index * 2 + 1]]);
+            let tile = &self.border_tiles[(entry & 0xff) as usize * 32..][..32];
+            let palette = &self.border_palettes[((entry >> 10) & 0x03) as usize];
+            let (xflip, yflip) = (entry & 0x4000 == 0x4000, entry & 0x8000 == 0x8000);
+            for tile_y in 0..8 {
+                let row = if yflip { 7 - tile_y } else { tile_y };
+                for tile_x in 0..8 {
+                    let bit = if xflip { tile_x } else { 7 - tile_x };
+                    let color = ((tile[row * 2] >> bit) & 0x01)
+                        | ((tile[row * 2 + 1] >> bit) & 0x01) << 1
+                        | ((tile[16 + row * 2] >> bit) & 0x01) << 2
+                        | ((tile[16 + row * 2 + 1] >> bit) & 0x01) << 3;
+                    if color == 0 {
+                        continue;
+                    }
+                    let x = (index % 32) * 8 + tile_x;
+                    let y = (index / 32) * 8 + tile_y;
+                    let offset = (y * SGB_WIDTH + x) * RGB_SIZE;
+                    self.frame_buffer[offset..offset + RGB_SIZE]
+                        .copy_from_slice(&palette[color as usize]);
+                }
+            }
+        }
+
+        &self.frame_buffer
+    }
+
+    pub fn palettes(&self) -> &[Palette; 4] {
+        &self.palettes
+    }
+
+    /// Obtains the palette index of each of the 20x18 cells
+    /// of the Game Boy screen.
+    pub fn attrs(&self) -> &[u8; ATTR_SIZE] {
+        &self.attrs
+    }
+
+    pub fn mask(&self) -> SgbMask {
+        self.mask
+    }
+
+    /// The number of joypads to be multiplexed through P1.
+    pub fn players(&self) -> u8 {
+        self.players
+    }
+
+    /// Obtains the 4KB of data of a VRAM transfer, the data being
+    /// the first 256 tiles displayed in the screen (following the
+    /// background map order), as it's captured by the SGB.
+    fn transfer(ppu: &Ppu) -> [u8; SGB_TRANSFER_SIZE] {
+        let vram = ppu.vram();
+        let map_offset = if ppu.bg_map() { 0x1c00 } else { 0x1800 };
+        let mut data = [0u8; SGB_TRANSFER_SIZE];
+        for (index, chunk) in data.chunks_mut(16).enumerate() {
+            let tile_index = vram[map_offset + (index / ATTR_WIDTH) * 32 + index % ATTR_WIDTH];
+            let tile_offset = if ppu.bg_tile() {
+                tile_index as usize * 16
+            } else {
+                (0x1000 + (tile_index as i8 as isize) * 16) as usize
+            };
+            chunk.copy_from_slice(&vram[tile_offset..tile_offset + 16]);
+        }
+        data
+    }
+
+    fn color(data: &[u8], offset: usize) -> Pixel {
+        rgb555_to_rgb888(data[offset], data[offset + 1])
+    }
+
+    fn set_palette_pair(&mut self, data: &[u8], first: usize, second: usize) {
+        let color0 = Self::color(data, 1);
+        for palette in self.palettes.iter_mut() {
+            palette[0] = color0;
+        }
+        for index in 0..3 {
+            self.palettes[first][index + 1] = Self::color(data, 3 + index * 2);
+            self.palettes[second][index + 1] = Self::color(data, 9 + index * 2);
+        }
+    }
+
+    fn attr_blk(&mut self, data: &[u8]) {
+        let count = (data[1] & 0x1f) as usize;
+        for set in data[2..].chunks_exact(6).take(count) {
+            let control = set[0] & 0x07;
+            let (inside, border, outside) =
+                (set[1] & 0x03, (set[1] >> 2) & 0x03, (set[1] >> 4) & 0x03);
+
+            // in case only the inside or outside areas are set
+            // the border area is set with the same palette
+            let border = match control {
+                0x01 => Some(inside),
+                0x04 => Some(outside),
+                _ if control & 0x02 == 0x02 => Some(border),
+                _ => None,
+            };
+            let (x1, y1, x2, y2) = (set[2] & 0x1f, set[3] & 0x1f, set[4] & 0x1f, set[5] & 0x1f);
+            for y in 0..ATTR_HEIGHT as u8 {
+                for x in 0..ATTR_WIDTH as u8 {
+                    let within = x >= x1 && x <= x2 && y >= y1 && y <= y2;
+                    let on_border = within && (x == x1 || x == x2 || y == y1 || y == y2);
+                    let palette = if on_border {
+                        border
+                    } else if within && control & 0x01 == 0x01 {
+                        Some(inside)
+                    } else if !within && control & 0x04 == 0x04 {
+                        Some(outside)
+                    } else {
+                        None
+                    };
+                    if let Some(palette) = palette {
+                        self.attrs[y as usize * ATTR_WIDTH + x as usize] = palette;
+                    }
+                }
+            }
+        }
+    }
+
+    fn attr_lin(&mut self, data: &[u8]) {
+        let count = data[1] as usize;
+        for &line in data[2..].iter().take(count) {
+            let index = (line & 0x1f) as usize;
+            let palette = (line >> 5) & 0x03;
+            if line & 0x80 == 0x80 {
+                if index < ATTR_HEIGHT {
+                    self.attrs[index * ATTR_WIDTH..(index + 1) * ATTR_WIDTH].fill(palette);
+                }
+            } else if index < ATTR_WIDTH {
+                for y in 0..ATTR_HEIGHT {
+                    self.attrs[y * ATTR_WIDTH + index] = palette;
+                }
+            }
+        }
+    }
+
+    fn attr_div(&mut self, data: &[u8]) {
+        let after = data[1] & 0x03;
+        let before = (data[1] >> 2) & 0x03;
+        let on_line = (data[1] >> 4) & 0x03;
+        let horizontal = data[1] & 0x40 == 0x40;
+        let line = (data[2] & 0x1f) as usize;
+        for y in 0..ATTR_HEIGHT {
+            for x in 0..ATTR_WIDTH {
+                let position = if horizontal { y } else { x };
+                self.attrs[y * ATTR_WIDTH + x] = match position.cmp(&line) {
+                    std::cmp::Ordering::Less => before,
+                    std::cmp::Ordering::Equal => on_line,
+                    std::cmp::Ordering::Greater => after,
+                };
+            }
+        }
+    }
+
+    fn attr_chr(&mut self, data: &[u8]) {
+        let mut x = (data[1] as usize).min(ATTR_WIDTH - 1);
+        let mut y = (data[2] as usize).min(ATTR_HEIGHT - 1);
+        let count = (u16::from_le_bytes([data[3], data[4]]) as usize).min(ATTR_SIZE);
+        let vertical = data[5] == 0x01;
+        for index in 0..count {
+            let Some(&byte) = data.get(6 + index / 4) else {
+                break;
+            };
+            self.attrs[y * ATTR_WIDTH + x] = (byte >> (6 - (index % 4) * 2)) & 0x03;
+            if vertical {
+                y += 1;
+                if y == ATTR_HEIGHT {
+                    y = 0;
+                    x = (x + 1) % ATTR_WIDTH;
+                }
+            } else {
+                x += 1;
+                if x == ATTR_WIDTH {
+                    x = 0;
+                    y = (y + 1) % ATTR_HEIGHT;
+                }
+            }
+        }
+    }
+
+    fn pal_set(&mut self, data: &[u8]) {
+        for index in 0..4 {
+            let palette =
+                u16::from_le_bytes([data[1 + index * 2], data[2 + index * 2]]) as usize & 0x1ff;
+            for color in 0..4 {
+                self.palettes[index][color] =
+                    Self::color(&self.system_palettes[..], palette * 8 + color * 2);
+            }
+        }
+
+        // the color 0 is shared among all the palettes and
+        // is defined by the first palette
+        let color0 = self.palettes[0][0];
+        for palette in self.palettes.iter_mut() {
+            palette[0] = color0;
+        }
+
+        if data[9] & 0x80 == 0x80 {
+            self.attr_set(data[9]);
+        } else if data[9] & 0x40 == 0x40 {
+            self.mask = SgbMask::None;
+        }
+    }
+
+    fn attr_set(&mut self, value: u8) {
+        let file = (value & 0x3f) as usize;
+        if file < ATTR_FILE_COUNT {
+            let data = &self.attr_files[file * ATTR_FILE_SIZE..(file + 1) * ATTR_FILE_SIZE];
+            for (index, attr) in self.attrs.iter_mut().enumerate() {
+                *attr = (data[index / 4] >> (6 - (index % 4) * 2)) & 0x03;
+            }
+        }
+        if value & 0x40 == 0x40 {
+            self.mask = SgbMask::None;
+        }
+    }
+
+    fn pct_trn(&mut self, data: &[u8; SGB_TRANSFER_SIZE]) {
+        self.border_map.copy_from_slice(&data[..2048]);
+        for (index, palette) in self.border_palettes.iter_mut().enumerate() {
+            for (color, pixel) in palette.iter_mut().enumerate() {
+                *pixel = Self::color(data, 0x800 + index * 32 + color * 2);
+            }
+        }
+    }
+}
+
+impl Default for Sgb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ppu::Ppu;
+
+    use super::{Sgb, SgbMask, SgbReceiver, SGB_DEFAULT_PALETTE, SGB_PACKET_SIZE};
+
+    fn send(receiver: &mut SgbReceiver, packet: &[u8; SGB_PACKET_SIZE]) {
+        receiver.write(0x00);
+        receiver.write(0x30);
+        for bit in 0..SGB_PACKET_SIZE * 8 {
+            let value = (packet[bit / 8] >> (bit % 8)) & 0x01;
+            receiver.write(if value == 1 { 0x10 } else { 0x20 });
+            receiver.write(0x30);
+        }
+        receiver.write(0x20);
+        receiver.write(0x30);
+    }
+
+    #[test]
+    fn test_receiver() {
+        let mut receiver = SgbReceiver::new();
+        let packet = [
+            0x01, 0xff, 0x7f, 0x1f, 0x00, 0xe0, 0x03, 0x00, 0x7c, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ];
+        send(&mut receiver, &packet);
+        assert!(receiver.pending());
+        assert_eq!(receiver.packet(), Some(packet));
+        assert_eq!(receiver.packet(), None);
+
+        // writes outside of a transfer are ignored
+        receiver.write(0x10);
+        receiver.write(0x30);
+        assert!(!receiver.pending());
+    }
+
+    #[test]
+    fn test_commands() {
+        let ppu = Ppu::default();
+        let mut sgb = Sgb::new();
+        assert_eq!(sgb.palettes()[2], SGB_DEFAULT_PALETTE);
+
+        // PAL01 with white as color 0 and red, green and blue
+        // as the first colors of both palettes
+        sgb.process(
+            &[
+                0x01, 0xff, 0x7f, 0x1f, 0x00, 0xe0, 0x03, 0x00, 0x7c, 0x1f, 0x00, 0xe0, 0x03, 0x00,
+                0x7c, 0x00,
+            ],
+            &ppu,
+        );
+        assert_eq!(sgb.palettes()[0][0], [0xf8, 0xf8, 0xf8]);
+        assert_eq!(sgb.palettes()[3][0], [0xf8, 0xf8, 0xf8]);
+        assert_eq!(sgb.palettes()[0][1], [0xf8, 0x00, 0x00]);
+        assert_eq!(sgb.palettes()[1][3], [0x00, 0x00, 0xf8]);
+
+        // ATTR_BLK setting palette 2 inside of the (1, 1) to (4, 4)
+        // block and palette 1 outside of it, keeping the border
+        sgb.process(
+            &[
+                0x21, 0x01, 0x05, 0x12, 0x01, 0x01, 0x04, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00,
+            ],
+            &ppu,
+        );
+        assert_eq!(sgb.attrs()[0], 1);
+        assert_eq!(sgb.attrs()[21], 0);
+        assert_eq!(sgb.attrs()[42], 2);
+        assert_eq!(sgb.attrs()[63], 2);
+        assert_eq!(sgb.attrs()[65], 1);
+
+        // MASK_EN with black screen
+        sgb.process(
+            &[0xb9, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            &ppu,
+        );
+        assert_eq!(sgb.mask(), SgbMask::Black);
+
+        // MLT_REQ with four players and then back to one
+        sgb.process(
+            &[0x89, 0x03, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            &ppu,
+        );
+        assert_eq!(sgb.players(), 4);
+        sgb.process(
+            &[0x89, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            &ppu,
+        );
+        assert_eq!(sgb.players(), 1);
+
+        let buffer = sgb.frame_buffer(&ppu.shade_buffer);
+        assert_eq!(buffer[0..3], [0xf8, 0xf8, 0xf8]);
+        let offset = (40 * 256 + 48) * 3;
+        assert_eq!(buffer[offset..offset + 3], [0x00, 0x00, 0x00]);
+    }
+}
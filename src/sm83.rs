@@ -0,0 +1,617 @@
+//! Runner for the SM83 single instruction tests (JSON).
+//!
+//! Consumes the community SM83 test suite, made of one JSON file per
+//! opcode (eg: `00.json`, `cb 11.json`) each with a list of cases that
+//! specify the initial state of the CPU registers and memory, the
+//! expected final state after running a single instruction and the
+//! memory cycles taken by it:
+//!
+//! ```json
+//! [{
+//!     "name": "04 0000",
+//!     "initial": {"pc": 256, "sp": 0, "a": 0, "b": 15, "c": 0, "d": 0,
+//!         "e": 0, "f": 0, "h": 0, "l": 0, "ime": 0, "ram": [[256, 4]]},
+//!     "final": {"pc": 257, "sp": 0, "a": 0, "b": 16, "c": 0, "d": 0,
+//!         "e": 0, "f": 32, "h": 0, "l": 0, "ime": 0, "ram": [[256, 4]]},
+//!     "cycles": [[256, 4, "r-m"]]
+//! }]
+//! ```
+//!
+//! The instructions are run by the CPU with the MMU in flat memory
+//! mode (the complete address space is RAM), with the outcome of each
+//! of the opcodes reported as a [`ConformanceResult`].
+
+use std::{
+    fs::read_dir,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use boytacean_common::{error::Error, util::read_file};
+
+use crate::{
+    conformance::{ConformanceReport, ConformanceResult},
+    gb::{GameBoy, GameBoyMode},
+    info::Info,
+};
+
+/// The maximum number of failed cases described in the
+/// result of each of the opcodes.
+const MAX_FAILURES: usize = 3;
+
+/// State of the CPU registers and of the memory, either the
+/// initial or the (expected) final state of a test case.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Sm83State {
+    pub pc: u16,
+    pub sp: u16,
+    pub a: u8,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub f: u8,
+    pub h: u8,
+    pub l: u8,
+    pub ime: bool,
+
+    /// The (address, value) pairs of the relevant memory.
+    pub ram: Vec<(u16, u8)>,
+}
+
+impl Sm83State {
+    fn from_json(value: &Json) -> Result<Self, Error> {
+        let register = |name: &str| {
+            value
+                .get(name)
+                .and_then(Json::as_u64)
+                .ok_or_else(|| Error::DataError(format!("Missing register: {name}")))
+        };
+        let ram = value
+            .get("ram")
+            .and_then(Json::as_array)
+            .ok_or_else(|| Error::DataError(String::from("Missing RAM")))?
+            .iter()
+            .map(
+                |entry| match entry.as_array().map(|values| values.as_slice()) {
+                    Some([addr, value]) => match (addr.as_u64(), value.as_u64()) {
+                        (Some(addr), Some(value)) => Ok((addr as u16, value as u8)),
+                        _ => Err(Error::DataError(String::from("Invalid RAM entry"))),
+                    },
+                    _ => Err(Error::DataError(String::from("Invalid RAM entry"))),
+                },
+            )
+            .collect::<Result<Vec<(u16, u8)>, Error>>()?;
+        Ok(Self {
+            pc: register("pc")? as u16,
+            sp: register("sp")? as u16,
+            a: register("a")? as u8,
+            b: register("b")? as u8,
+            c: register("c")? as u8,
+            d: register("d")? as u8,
+            e: register("e")? as u8,
+            f: register("f")? as u8,
+            h: register("h")? as u8,
+            l: register("l")? as u8,
+            ime: value.get("ime").and_then(Json::as_u64).unwrap_or(0) != 0,
+            ram,
+        })
+    }
+
+    fn registers(&self) -> [(&'static str, u16); 11] {
+        [
+            ("pc", self.pc),
+            ("sp", self.sp),
+            ("a", self.a as u16),
+            ("b", self.b as u16),
+            ("c", self.c as u16),
+            ("d", self.d as u16),
+            ("e", self.e as u16),
+            ("f", self.f as u16),
+            ("h", self.h as u16),
+            ("l", self.l as u16),
+            ("ime", self.ime as u16),
+        ]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sm83Case {
+    pub name: String,
+    pub initial: Sm83State,
+    pub expected: Sm83State,
+
+    /// The number of memory (M) cycles taken by the instruction.
+    pub cycles: usize,
+}
+
+impl Sm83Case {
+    /// Parses the cases of a JSON test file (a list of cases).
+    pub fn from_json(data: &str) -> Result<Vec<Self>, Error> {
+        let value = JsonParser::new(data).parse()?;
+        value
+            .as_array()
+            .ok_or_else(|| Error::DataError(String::from("Expected list of cases")))?
+            .iter()
+            .map(|case| {
+                Ok(Self {
+                    name: case
+                        .get("name")
+                        .and_then(Json::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                    initial: Sm83State::from_json(
+                        case.get("initial")
+                            .ok_or_else(|| Error::DataError(String::from("Missing initial")))?,
+                    )?,
+                    expected: Sm83State::from_json(
+                        case.get("final")
+                            .ok_or_else(|| Error::DataError(String::from("Missing final")))?,
+                    )?,
+                    cycles: case
+                        .get("cycles")
+                        .and_then(Json::as_array)
+                        .map_or(0, Vec::len),
+                })
+            })
+            .collect()
+    }
+
+    /// Runs the instruction of the case, returning the differences
+    /// to the expected final state (if any) as the failure reason.
+    pub fn run(&self, gb: &mut GameBoy) -> Result<(), String> {
+        let initial = &self.initial;
+        let cpu = gb.cpu();
+        cpu.pc = initial.pc;
+        cpu.sp = initial.sp;
+        cpu.a = initial.a;
+        cpu.b = initial.b;
+        cpu.c = initial.c;
+        cpu.d = initial.d;
+        cpu.e = initial.e;
+        cpu.set_f(initial.f);
+        cpu.h = initial.h;
+        cpu.l = initial.l;
+        cpu.set_ime(initial.ime);
+        cpu.set_halted(false);
+        for (addr, value) in &initial.ram {
+            cpu.mmu().write(*addr, *value);
+        }
+
+        let cycles = cpu.clock() as usize;
+
+        let actual = Sm83State {
+            pc: cpu.pc,
+            sp: cpu.sp,
+            a: cpu.a,
+            b: cpu.b,
+            c: cpu.c,
+            d: cpu.d,
+            e: cpu.e,
+            f: cpu.f(),
+            h: cpu.h,
+            l: cpu.l,
+            ime: cpu.ime(),
+            ram: vec![],
+        };
+        let mut differences = vec![];
+        for ((name, expected), (_, value)) in
+            self.expected.registers().iter().zip(actual.registers())
+        {
+            if *expected != value {
+                differences.push(format!("{name} 0x{value:02x} != 0x{expected:02x}"));
+            }
+        }
+        for (addr, expected) in &self.expected.ram {
+            let value = cpu.mmu().read(*addr);
+            if value != *expected {
+                differences.push(format!("[0x{addr:04x}] 0x{value:02x} != 0x{expected:02x}"));
+            }
+        }
+        if self.cycles > 0 && cycles != self.cycles * 4 {
+            differences.push(format!("cycles {} != {}", cycles / 4, self.cycles));
+        }
+
+        if differences.is_empty() {
+            Ok(())
+        } else {
+            Err(differences.join(", "))
+        }
+    }
+
+    /// The opcode of the case, obtained from its name (eg: "cb 11").
+    pub fn opcode(&self) -> &str {
+        self.name
+            .rsplit_once(' ')
+            .map_or(self.name.as_str(), |(opcode, _)| opcode)
+    }
+}
+
+/// Suite of SM83 test files, with one file per opcode.
+pub struct Sm83Suite {
+    pub paths: Vec<PathBuf>,
+}
+
+impl Sm83Suite {
+    /// Builds the suite from a directory with the JSON test
+    /// files or from a single JSON test file.
+    pub fn from_path(path: &str) -> Result<Self, Error> {
+        let path = Path::new(path);
+        if !path.is_dir() {
+            return Ok(Self {
+                paths: vec![path.to_path_buf()],
+            });
+        }
+        let mut paths = vec![];
+        for entry in read_dir(path).map_err(|e| Error::IoError(e.to_string()))? {
+            let path = entry.map_err(|e| Error::IoError(e.to_string()))?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        Ok(Self { paths })
+    }
+
+    /// Builds the system used to run the test cases, with
+    /// the MMU in flat memory mode.
+    pub fn build() -> Box<GameBoy> {
+        let mut gb = Box::new(GameBoy::new(Some(GameBoyMode::Dmg)));
+        gb.load(false).unwrap();
+        gb.mmu().set_flat(true);
+        gb
+    }
+
+    /// Runs every case of every file, with each of the opcodes
+    /// (files) producing a result of the report.
+    pub fn run(&self, mut on_result: impl FnMut(&ConformanceResult)) -> ConformanceReport {
+        let start = Instant::now();
+        let mut gb = Self::build();
+        let results = self
+            .paths
+            .iter()
+            .map(|path| {
+                let result = Self::run_file(&mut gb, path);
+                on_result(&result);
+                result
+            })
+            .collect();
+        ConformanceReport {
+            version: Info::version(),
+            results,
+            duration: start.elapsed(),
+        }
+    }
+
+    fn run_file(gb: &mut GameBoy, path: &Path) -> ConformanceResult {
+        let start = Instant::now();
+        let name = path
+            .file_stem()
+            .map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+        let cases = read_file(&path.to_string_lossy())
+            .and_then(|data| String::from_utf8(data).map_err(|e| Error::DataError(e.to_string())))
+            .and_then(|data| Sm83Case::from_json(&data));
+        let detail = match cases {
+            Ok(cases) => Self::run_cases(gb, &cases),
+            Err(err) => Some(err.to_string()),
+        };
+        ConformanceResult {
+            name,
+            rom_path: String::from(path.to_string_lossy()),
+            passed: detail.is_none(),
+            detail,
+            duration: start.elapsed(),
+        }
+    }
+
+    /// Runs the cases, describing the first failures (if any).
+    pub fn run_cases(gb: &mut GameBoy, cases: &[Sm83Case]) -> Option<String> {
+        let failures: Vec<String> = cases
+            .iter()
+            .filter_map(|case| {
+                case.run(gb)
+                    .err()
+                    .map(|detail| format!("{}: {detail}", case.name))
+            })
+            .collect();
+        if failures.is_empty() {
+            return None;
+        }
+        let mut detail = format!("{}/{} cases failed", failures.len(), cases.len());
+        for failure in failures.iter().take(MAX_FAILURES) {
+            detail.push_str(&format!("; {failure}"));
+        }
+        Some(detail)
+    }
+}
+
+/// Minimal JSON value, enough for the test files.
+#[derive(Clone, Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        match self {
+            Json::Number(value) if *value >= 0.0 => Some(*value as u64),
+            Json::Bool(value) => Some(*value as u64),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn as_array(&self) -> Option<&Vec<Json>> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+struct JsonParser<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn new(data: &'a str) -> Self {
+        Self {
+            data: data.as_bytes(),
+            position: 0,
+        }
+    }
+
+    fn parse(&mut self) -> Result<Json, Error> {
+        let value = self.value()?;
+        self.skip_whitespace();
+        if self.position < self.data.len() {
+            return Err(self.error("trailing data"));
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, Error> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => Ok(Json::String(self.string()?)),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(self.error("unexpected character")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, Error> {
+        self.position += 1;
+        let mut entries = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Ok(Json::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(b':')?;
+            entries.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.next() {
+                Some(b',') => continue,
+                Some(b'}') => return Ok(Json::Object(entries)),
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, Error> {
+        self.position += 1;
+        let mut values = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(b',') => continue,
+                Some(b']') => return Ok(Json::Array(values)),
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.expect(b'"')?;
+        let mut buffer = vec![];
+        loop {
+            match self.next() {
+                Some(b'"') => break,
+                Some(b'\\') => match self.next() {
+                    Some(b'n') => buffer.push(b'\n'),
+                    Some(b't') => buffer.push(b'\t'),
+                    Some(b'r') => buffer.push(b'\r'),
+                    Some(b'b') => buffer.push(0x08),
+                    Some(b'f') => buffer.push(0x0c),
+                    Some(b'u') => {
+                        let digits = self
+                            .data
+                            .get(self.position..self.position + 4)
+                            .and_then(|digits| std::str::from_utf8(digits).ok())
+                            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+                            .ok_or_else(|| self.error("invalid unicode escape"))?;
+                        self.position += 4;
+                        let char = char::from_u32(digits).unwrap_or(char::REPLACEMENT_CHARACTER);
+                        buffer.extend_from_slice(char.to_string().as_bytes());
+                    }
+                    Some(byte) => buffer.push(byte),
+                    None => return Err(self.error("unterminated string")),
+                },
+                Some(byte) => buffer.push(byte),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+        String::from_utf8(buffer).map_err(|_| self.error("invalid string"))
+    }
+
+    fn number(&mut self) -> Result<Json, Error> {
+        let start = self.position;
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') = self.peek() {
+            self.position += 1;
+        }
+        std::str::from_utf8(&self.data[start..self.position])
+            .ok()
+            .and_then(|value| value.parse::<f64>().ok())
+            .map(Json::Number)
+            .ok_or_else(|| self.error("invalid number"))
+    }
+
+    fn literal(&mut self, literal: &str, value: Json) -> Result<Json, Error> {
+        if self.data[self.position..].starts_with(literal.as_bytes()) {
+            self.position += literal.len();
+            Ok(value)
+        } else {
+            Err(self.error("invalid literal"))
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), Error> {
+        if self.next() == Some(byte) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", byte as char)))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\n' | b'\r' | b'\t') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let byte = self.peek();
+        self.position += 1;
+        byte
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::DataError(format!("Invalid JSON at {}: {message}", self.position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Json, JsonParser, Sm83Case, Sm83Suite};
+
+    const CASES: &str = r#"[
+        {
+            "name": "04 0000",
+            "initial": {"pc": 256, "sp": 0, "a": 0, "b": 15, "c": 0, "d": 0, "e": 0,
+                "f": 16, "h": 0, "l": 0, "ime": 0, "ie": 0, "ram": [[256, 4]]},
+            "final": {"pc": 257, "sp": 0, "a": 0, "b": 16, "c": 0, "d": 0, "e": 0,
+                "f": 48, "h": 0, "l": 0, "ime": 0, "ie": 0, "ram": [[256, 4]]},
+            "cycles": [[256, 4, "r-m"]]
+        },
+        {
+            "name": "04 0001",
+            "initial": {"pc": 512, "sp": 0, "a": 0, "b": 255, "c": 0, "d": 0, "e": 0,
+                "f": 0, "h": 0, "l": 0, "ime": 0, "ram": [[512, 4]]},
+            "final": {"pc": 513, "sp": 0, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0,
+                "f": 160, "h": 0, "l": 0, "ime": 0, "ram": [[512, 4]]},
+            "cycles": [[512, 4, "r-m"]]
+        },
+        {
+            "name": "70 0000",
+            "initial": {"pc": 49152, "sp": 0, "a": 0, "b": 66, "c": 0, "d": 0, "e": 0,
+                "f": 0, "h": 128, "l": 16, "ime": 0, "ram": [[49152, 112], [32784, 0]]},
+            "final": {"pc": 49153, "sp": 0, "a": 0, "b": 66, "c": 0, "d": 0, "e": 0,
+                "f": 0, "h": 128, "l": 16, "ime": 0, "ram": [[49152, 112], [32784, 66]]},
+            "cycles": [[49152, 112, "r-m"], [32784, 66, "-wm"]]
+        }
+    ]"#;
+
+    #[test]
+    fn test_json() {
+        let value = JsonParser::new(r#" {"a": [1, -2.5e1, true, null], "b\"c": "dA"} "#)
+            .parse()
+            .unwrap();
+        assert_eq!(
+            value.get("a"),
+            Some(&Json::Array(vec![
+                Json::Number(1.0),
+                Json::Number(-25.0),
+                Json::Bool(true),
+                Json::Null
+            ]))
+        );
+        assert_eq!(value.get("b\"c").and_then(Json::as_str), Some("dA"));
+        assert!(JsonParser::new("[1, 2").parse().is_err());
+        assert!(JsonParser::new("{} x").parse().is_err());
+    }
+
+    #[test]
+    fn test_cases() {
+        let cases = Sm83Case::from_json(CASES).unwrap();
+        assert_eq!(cases.len(), 3);
+        assert_eq!(cases[0].opcode(), "04");
+        assert_eq!(cases[0].initial.b, 0x0f);
+        assert_eq!(cases[2].expected.ram, vec![(0xc000, 0x70), (0x8010, 0x42)]);
+        assert_eq!(cases[2].cycles, 2);
+
+        let mut gb = Sm83Suite::build();
+        for case in &cases {
+            assert_eq!(case.run(&mut gb), Ok(()), "{}", case.name);
+        }
+        assert_eq!(Sm83Suite::run_cases(&mut gb, &cases), None);
+
+        // a wrong expectation is reported with the differences
+        let mut case = cases[1].clone();
+        case.expected.f = 0x80;
+        case.expected.ram[0].1 = 0x05;
+        case.cycles = 2;
+        assert_eq!(
+            case.run(&mut gb),
+            Err(String::from(
+                "f 0xa0 != 0x80, [0x0200] 0x04 != 0x05, cycles 1 != 2"
+            ))
+        );
+        assert_eq!(
+            Sm83Suite::run_cases(&mut gb, &[case]).unwrap(),
+            "1/1 cases failed; 04 0001: f 0xa0 != 0x80, [0x0200] 0x04 != 0x05, cycles 1 != 2"
+        );
+
+        assert!(Sm83Case::from_json("[{\"name\": \"00 0000\"}]").is_err());
+    }
+}
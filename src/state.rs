@@ -12,24 +12,34 @@ use boytacean_common::{
         write_u32, write_u64, write_u8,
     },
     error::Error,
-    util::{save_bmp, timestamp},
+    util::{read_file, save_bmp, write_file},
+};
+#[cfg(feature = "zstd")]
+use boytacean_encoding::zstd::{decode_zstd, encode_zstd};
+use boytacean_encoding::{
+    deflate::{decode_deflate, encode_deflate},
+    rle::{decode_rle, encode_rle},
+    zippy::{decode_zippy, encode_zippy},
 };
-use boytacean_encoding::zippy::{decode_zippy, encode_zippy};
 use std::{
+    collections::{HashMap, VecDeque},
     convert::TryInto,
     fmt::{self, Display, Formatter},
-    fs::File,
+    fs::{create_dir_all, remove_file, File},
     io::{Cursor, Read, Seek, SeekFrom, Write},
     mem::size_of,
+    path::Path,
     vec,
 };
 
 use crate::{
     disable_pedantic, enable_pedantic,
-    gb::{GameBoy, GameBoyDevice, GameBoyMode, GameBoySpeed},
+    gb::{GameBoy, GameBoyDevice, GameBoyMode, GameBoySpeed, StartupMemory},
     info::Info,
     ppu::{DISPLAY_HEIGHT, DISPLAY_WIDTH, FRAME_BUFFER_SIZE},
-    rom::{CgbMode, MbcType},
+    rom::{Cartridge, MbcType, Rtc, RTC_FOOTER_SIZE},
+    storage::SavePaths,
+    warnln,
 };
 
 #[cfg(feature = "wasm")]
@@ -47,8 +57,9 @@ pub const BOS_MAGIC: &str = "BOS\0";
 /// Magic string ("BOS\0") in little endian unsigned 32 bit format.
 pub const BOS_MAGIC_UINT: u32 = 0x00534f42;
 
-/// Current version of the BOS (Boytacean Save Compressed) format.
-pub const BOSC_VERSION: u8 = 1;
+/// Current version of the BOSC (Boytacean Save Compressed) format,
+/// the version 2 adds the codec to the header (version 1 is Zippy).
+pub const BOSC_VERSION: u8 = 2;
 
 /// Current version of the BOS (Boytacean Save) format.
 pub const BOS_VERSION: u8 = 1;
@@ -100,17 +111,68 @@ impl From<u8> for StateFormat {
 /// Ideally each of Game Boy's components should implement
 /// this trait to allow the state to be saved and restored
 /// in a consistent way.
+///
+/// The layout of the serialized state is versioned, every
+/// change to it must be followed by a new entry in the
+/// `STATE_MIGRATIONS` list of the component, converting the
+/// states of the previous version, so that the states saved
+/// by older versions of the emulator keep loading.
 pub trait StateComponent {
+    /// The ordered migrations of the state schema of the
+    /// component, with the migration at index `n` converting
+    /// a state of version `n + 1` into version `n + 2`.
+    const STATE_MIGRATIONS: &'static [StateMigration] = &[];
+
     fn state(&self, format: Option<StateFormat>) -> Result<Vec<u8>, Error>;
     fn set_state(&mut self, data: &[u8], format: Option<StateFormat>) -> Result<(), Error>;
+
+    /// The current version of the state schema of the component,
+    /// the first version of every schema is `1`.
+    fn state_version() -> u16 {
+        Self::STATE_MIGRATIONS.len() as u16 + 1
+    }
+
+    /// Migrates the provided state, serialized with the given
+    /// version of the schema, into the current version, failing
+    /// for states that are newer than the current version.
+    fn migrate_state(data: Vec<u8>, version: u16, format: StateFormat) -> Result<Vec<u8>, Error> {
+        let current = Self::state_version();
+        if version == 0 || version > current {
+            return Err(Error::CustomError(format!(
+                "Unsupported state version {version}, expected up to {current}"
+            )));
+        }
+        Self::STATE_MIGRATIONS[version as usize - 1..]
+            .iter()
+            .try_fold(data, |data, migration| migration(data, format))
+    }
+
+    /// Restores the state serialized with the given version of
+    /// the schema, migrating it into the current version first.
+    fn set_state_versioned(
+        &mut self,
+        data: &[u8],
+        version: u16,
+        format: StateFormat,
+    ) -> Result<(), Error> {
+        if version == Self::state_version() {
+            return self.set_state(data, Some(format));
+        }
+        let data = Self::migrate_state(data.to_vec(), version, format)?;
+        self.set_state(&data, Some(format))
+    }
 }
 
+/// Migration of the serialized state of a component from
+/// a version of its schema into the following one.
+pub type StateMigration = fn(data: Vec<u8>, format: StateFormat) -> Result<Vec<u8>, Error>;
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub enum SaveStateFormat {
     /// Boytacean Save Compressed format (BOSC).
-    /// This format uses the Zippy compression algorithm
-    /// to compress the underlying BOS contents.
+    /// This format compresses the underlying BOS contents
+    /// using one of the [`BoscCodec`] (Zippy by default).
     Bosc = 1,
 
     /// Boytacean Save format (uncompressed) (BOS).
@@ -151,11 +213,102 @@ impl Display for SaveStateFormat {
     }
 }
 
+/// The codecs used to compress the BOS contents of the BOSC
+/// format, the one in use is recorded in the BOSC header.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub enum BoscCodec {
+    /// The custom Zippy (RLE and Huffman) codec.
+    #[default]
+    Zippy = 1,
+
+    /// Deflate in the zlib container, that can be decoded by
+    /// standard tools (eg: `zlib.decompress()` in Python).
+    Deflate = 2,
+
+    /// Zstandard, only available with the `zstd` feature, states
+    /// using it can't be saved or loaded otherwise.
+    Zstd = 3,
+}
+
+impl BoscCodec {
+    pub fn description(&self) -> String {
+        match self {
+            Self::Zippy => String::from("Zippy"),
+            Self::Deflate => String::from("Deflate"),
+            Self::Zstd => String::from("Zstd"),
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "zippy" => Ok(Self::Zippy),
+            "deflate" => Ok(Self::Deflate),
+            "zstd" => Ok(Self::Zstd),
+            _ => Err(Error::CustomError(format!("Invalid BOSC codec: {value}"))),
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Result<Self, Error> {
+        match value {
+            1 => Ok(Self::Zippy),
+            2 => Ok(Self::Deflate),
+            3 => Ok(Self::Zstd),
+            _ => Err(Error::DataError(format!("Invalid BOSC codec: {value}"))),
+        }
+    }
+
+    pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::Zippy => encode_zippy(data, None, None),
+            Self::Deflate => encode_deflate(data),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => encode_zstd(data),
+            #[cfg(not(feature = "zstd"))]
+            Self::Zstd => Err(Self::unsupported()),
+        }
+    }
+
+    pub fn decode(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::Zippy => decode_zippy(data, None),
+            Self::Deflate => decode_deflate(data),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => decode_zstd(data),
+            #[cfg(not(feature = "zstd"))]
+            Self::Zstd => Err(Self::unsupported()),
+        }
+    }
+
+    /// Checks if the codec is available in the current build.
+    pub fn available(&self) -> bool {
+        match self {
+            Self::Zippy | Self::Deflate => true,
+            Self::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    fn unsupported() -> Error {
+        Error::CustomError(String::from(
+            "Zstd codec not available, build with the zstd feature",
+        ))
+    }
+}
+
+impl Display for BoscCodec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
 #[derive(Clone, Copy)]
 pub enum BosBlockKind {
     Info = 0x01,
     ImageBuffer = 0x02,
     DeviceState = 0x03,
+    SerialDevice = 0x04,
+    Startup = 0x05,
     Unknown = 0xff,
 }
 
@@ -165,6 +318,8 @@ impl BosBlockKind {
             0x01 => Self::Info,
             0x02 => Self::ImageBuffer,
             0x03 => Self::DeviceState,
+            0x04 => Self::SerialDevice,
+            0x05 => Self::Startup,
             _ => Self::Unknown,
         }
     }
@@ -174,6 +329,8 @@ impl BosBlockKind {
             Self::Info => String::from("Info"),
             Self::ImageBuffer => String::from("ImageBuffer"),
             Self::DeviceState => String::from("DeviceState"),
+            Self::SerialDevice => String::from("SerialDevice"),
+            Self::Startup => String::from("Startup"),
             Self::Unknown => String::from("Unknown"),
         }
     }
@@ -197,6 +354,9 @@ pub struct FromGbOptions {
     state_format: Option<StateFormat>,
     agent: Option<String>,
     agent_version: Option<String>,
+
+    /// The codec used by the BOSC format, Zippy if not set.
+    codec: Option<BoscCodec>,
 }
 
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
@@ -212,8 +372,14 @@ impl FromGbOptions {
             state_format,
             agent,
             agent_version,
+            codec: None,
         }
     }
+
+    pub fn with_codec(mut self, codec: BoscCodec) -> Self {
+        self.codec = Some(codec);
+        self
+    }
 }
 
 impl Default for FromGbOptions {
@@ -223,6 +389,7 @@ impl Default for FromGbOptions {
             state_format: None,
             agent: None,
             agent_version: None,
+            codec: None,
         }
     }
 }
@@ -296,6 +463,7 @@ pub trait StateInfo {
 pub struct BoscState {
     magic: u32,
     version: u8,
+    codec: BoscCodec,
     bos: BosState,
 }
 
@@ -314,26 +482,33 @@ impl BoscState {
         if self.magic != BOSC_MAGIC_UINT {
             return Err(Error::DataError(String::from("Invalid magic")));
         }
-        if self.version != BOSC_VERSION {
+        if self.version == 0 || self.version > BOSC_VERSION {
             return Err(Error::DataError(format!(
-                "Invalid version, expected {BOS_VERSION}, got {}",
+                "Invalid version, expected up to {BOSC_VERSION}, got {}",
                 self.version
             )));
         }
         self.bos.verify()?;
         Ok(())
     }
+
+    pub fn codec(&self) -> BoscCodec {
+        self.codec
+    }
 }
 
 impl Serialize for BoscState {
     fn write<W: Write + Seek>(&mut self, writer: &mut W) -> Result<(), Error> {
+        // always writes the latest version of the format, as
+        // older versions (eg: version 1) have no codec in the header
         write_u32(writer, self.magic)?;
-        write_u8(writer, self.version)?;
+        write_u8(writer, BOSC_VERSION)?;
+        write_u8(writer, self.codec as u8)?;
 
         let mut cursor = Cursor::new(vec![]);
         self.bos.write(&mut cursor)?;
 
-        let bos_compressed = encode_zippy(&cursor.into_inner(), None, None)?;
+        let bos_compressed = self.codec.encode(&cursor.into_inner())?;
         write_bytes(writer, &bos_compressed)?;
 
         Ok(())
@@ -343,9 +518,17 @@ impl Serialize for BoscState {
         self.magic = read_u32(reader)?;
         self.version = read_u8(reader)?;
 
+        // the version 1 of the format has no codec in the
+        // header, always using Zippy
+        self.codec = if self.version > 1 {
+            BoscCodec::from_u8(read_u8(reader)?)?
+        } else {
+            BoscCodec::Zippy
+        };
+
         let mut bos_compressed = vec![];
         reader.read_to_end(&mut bos_compressed)?;
-        let bos_buffer = decode_zippy(&bos_compressed, None)?;
+        let bos_buffer = self.codec.decode(&bos_compressed)?;
         let mut bos_cursor = Cursor::new(bos_buffer);
 
         self.bos.read(&mut bos_cursor)?;
@@ -359,6 +542,7 @@ impl StateBox for BoscState {
         Ok(Box::new(Self {
             magic: BOSC_MAGIC_UINT,
             version: BOSC_VERSION,
+            codec: options.codec.unwrap_or_default(),
             bos: *BosState::from_gb(gb, options)?,
         }))
     }
@@ -385,6 +569,8 @@ pub struct BosState {
     info: Option<BosInfo>,
     image_buffer: Option<BosImageBuffer>,
     device_states: Vec<BosDeviceState>,
+    serial_device: Option<BosSerialDevice>,
+    startup: Option<BosStartup>,
     bess: BessState,
 }
 
@@ -430,6 +616,12 @@ impl BosState {
             count += 1;
         }
         count += self.device_states.len() as u8;
+        if self.serial_device.is_some() {
+            count += 1;
+        }
+        if self.startup.is_some() {
+            count += 1;
+        }
         count
     }
 }
@@ -521,6 +713,12 @@ impl Serialize for BosState {
         for device_state in &mut self.device_states {
             device_state.write(writer)?;
         }
+        if let Some(serial_device) = &mut self.serial_device {
+            serial_device.write(writer)?;
+        }
+        if let Some(startup) = &mut self.startup {
+            startup.write(writer)?;
+        }
 
         self.bess.write(writer)?;
 
@@ -547,6 +745,12 @@ impl Serialize for BosState {
                 BosBlockKind::DeviceState => {
                     self.device_states.push(BosDeviceState::from_data(reader)?);
                 }
+                BosBlockKind::SerialDevice => {
+                    self.serial_device = Some(BosSerialDevice::from_data(reader)?);
+                }
+                BosBlockKind::Startup => {
+                    self.startup = Some(BosStartup::from_data(reader)?);
+                }
                 _ => {
                     reader.seek(SeekFrom::Current(-offset))?;
                     reader.seek(SeekFrom::Current(block.size as i64))?;
@@ -581,17 +785,28 @@ impl StateBox for BosState {
                 BosDeviceState::from_gb(gb, GameBoyDevice::Dma, options)?,
                 BosDeviceState::from_gb(gb, GameBoyDevice::Pad, options)?,
                 BosDeviceState::from_gb(gb, GameBoyDevice::Timer, options)?,
+                BosDeviceState::from_gb(gb, GameBoyDevice::Serial, options)?,
             ],
+            serial_device: BosSerialDevice::from_gb(gb)?,
+            startup: Some(BosStartup::from_gb(gb)),
             bess: *BessState::from_gb(gb, options)?,
         }))
     }
 
     fn to_gb(&self, gb: &mut GameBoy, options: &ToGbOptions) -> Result<(), Error> {
         self.verify()?;
+        // the startup configuration is restored first as changing
+        // the seed resets the RTC, which is then restored by BESS
+        if let Some(startup) = &self.startup {
+            startup.to_gb(gb);
+        }
         self.bess.to_gb(gb, options)?;
         for device_state in &self.device_states {
             device_state.to_gb(gb, options)?;
         }
+        if let Some(serial_device) = &self.serial_device {
+            serial_device.to_gb(gb)?;
+        }
         Ok(())
     }
 }
@@ -707,13 +922,13 @@ impl Serialize for BosInfo {
         write_u32(writer, size_of::<u64>() as u32)?;
         write_u64(writer, self.timestamp)?;
 
-        write_u32(writer, self.agent.as_bytes().len() as u32)?;
+        write_u32(writer, self.agent.len() as u32)?;
         write_bytes(writer, self.agent.as_bytes())?;
 
-        write_u32(writer, self.agent_version.as_bytes().len() as u32)?;
+        write_u32(writer, self.agent_version.len() as u32)?;
         write_bytes(writer, self.agent_version.as_bytes())?;
 
-        write_u32(writer, self.model.as_bytes().len() as u32)?;
+        write_u32(writer, self.model.len() as u32)?;
         write_bytes(writer, self.model.as_bytes())?;
 
         Ok(())
@@ -740,7 +955,7 @@ impl Serialize for BosInfo {
 
 impl State for BosInfo {
     fn from_gb(gb: &mut GameBoy) -> Result<Self, Error> {
-        let timestamp = timestamp();
+        let timestamp = gb.rom_i().now();
         Ok(Self::new(
             gb.mode().to_string(Some(true)),
             timestamp,
@@ -759,7 +974,7 @@ impl StateBox for BosInfo {
     where
         Self: Sized,
     {
-        let timestamp = timestamp();
+        let timestamp = gb.rom_i().now();
         Ok(Box::new(Self::new(
             gb.mode().to_string(Some(true)),
             timestamp,
@@ -851,11 +1066,13 @@ pub struct BosDeviceState {
 }
 
 impl BosDeviceState {
-    pub fn new(device: GameBoyDevice, format: StateFormat, state: Vec<u8>) -> Self {
+    /// Creates a new device state block, with the `version`
+    /// being the version of the state schema of the device.
+    pub fn new(device: GameBoyDevice, format: StateFormat, version: u16, state: Vec<u8>) -> Self {
         Self {
             header: BosBlock::new(
                 BosBlockKind::DeviceState,
-                1,
+                version,
                 (size_of::<u8>() + size_of::<u8>() + state.len()) as u32,
             ),
             device,
@@ -877,29 +1094,49 @@ impl BosDeviceState {
     ) -> Result<Self, Error> {
         let format: StateFormat = options.state_format.unwrap_or(StateFormat::Partial);
         match device {
-            GameBoyDevice::Cpu => Ok(Self::new(device, format, gb.cpu_i().state(Some(format))?)),
-            GameBoyDevice::Ppu => Ok(Self::new(device, format, gb.ppu_i().state(Some(format))?)),
-            GameBoyDevice::Apu => Ok(Self::new(device, format, gb.apu_i().state(Some(format))?)),
-            GameBoyDevice::Dma => Ok(Self::new(device, format, gb.dma_i().state(Some(format))?)),
-            GameBoyDevice::Pad => Ok(Self::new(device, format, gb.pad_i().state(Some(format))?)),
-            GameBoyDevice::Timer => {
-                Ok(Self::new(device, format, gb.timer_i().state(Some(format))?))
-            }
+            GameBoyDevice::Cpu => Self::from_component(device, format, gb.cpu_i()),
+            GameBoyDevice::Ppu => Self::from_component(device, format, gb.ppu_i()),
+            GameBoyDevice::Apu => Self::from_component(device, format, gb.apu_i()),
+            GameBoyDevice::Dma => Self::from_component(device, format, gb.dma_i()),
+            GameBoyDevice::Pad => Self::from_component(device, format, gb.pad_i()),
+            GameBoyDevice::Timer => Self::from_component(device, format, gb.timer_i()),
+            GameBoyDevice::Serial => Self::from_component(device, format, gb.serial_i()),
             _ => Err(Error::NotImplemented),
         }
     }
 
+    fn from_component<T: StateComponent>(
+        device: GameBoyDevice,
+        format: StateFormat,
+        component: &T,
+    ) -> Result<Self, Error> {
+        Ok(Self::new(
+            device,
+            format,
+            T::state_version(),
+            component.state(Some(format))?,
+        ))
+    }
+
     fn to_gb(&self, gb: &mut GameBoy, _options: &ToGbOptions) -> Result<(), Error> {
         match self.device {
-            GameBoyDevice::Cpu => gb.cpu().set_state(&self.state, Some(self.format))?,
-            GameBoyDevice::Ppu => gb.ppu().set_state(&self.state, Some(self.format))?,
-            GameBoyDevice::Apu => gb.apu().set_state(&self.state, Some(self.format))?,
-            GameBoyDevice::Dma => gb.dma().set_state(&self.state, Some(self.format))?,
-            GameBoyDevice::Pad => gb.pad().set_state(&self.state, Some(self.format))?,
-            GameBoyDevice::Timer => gb.timer().set_state(&self.state, Some(self.format))?,
-            _ => return Err(Error::NotImplemented),
+            GameBoyDevice::Cpu => self.to_component(gb.cpu()),
+            GameBoyDevice::Ppu => self.to_component(gb.ppu()),
+            GameBoyDevice::Apu => self.to_component(gb.apu()),
+            GameBoyDevice::Dma => self.to_component(gb.dma()),
+            GameBoyDevice::Pad => self.to_component(gb.pad()),
+            GameBoyDevice::Timer => self.to_component(gb.timer()),
+            GameBoyDevice::Serial => self.to_component(gb.serial()),
+            _ => Err(Error::NotImplemented),
         }
-        Ok(())
+    }
+
+    fn to_component<T: StateComponent>(&self, component: &mut T) -> Result<(), Error> {
+        component
+            .set_state_versioned(&self.state, self.header.version, self.format)
+            .map_err(|error| {
+                Error::CustomError(format!("Failed to restore {} state: {error}", self.device))
+            })
     }
 }
 
@@ -924,7 +1161,158 @@ impl Serialize for BosDeviceState {
 
 impl Default for BosDeviceState {
     fn default() -> Self {
-        Self::new(GameBoyDevice::Unknown, StateFormat::Partial, vec![])
+        // the version is left unset (no check on read) as each
+        // device has its own schema version, validated on restore
+        Self::new(GameBoyDevice::Unknown, StateFormat::Partial, 0, vec![])
+    }
+}
+
+/// State of the device attached to the serial port (eg: printer),
+/// keyed by the device identifier so that it's only restored into
+/// the same type of device.
+pub struct BosSerialDevice {
+    header: BosBlock,
+    id: String,
+    state: Vec<u8>,
+}
+
+impl BosSerialDevice {
+    pub fn new(id: &str, state: Vec<u8>) -> Self {
+        Self {
+            header: BosBlock::new(
+                BosBlockKind::SerialDevice,
+                1,
+                (size_of::<u8>() + id.len() + state.len()) as u32,
+            ),
+            id: String::from(id),
+            state,
+        }
+    }
+
+    pub fn from_data<R: Read + Seek>(reader: &mut R) -> Result<Self, Error> {
+        let mut instance = Self::default();
+        instance.read(reader)?;
+        Ok(instance)
+    }
+
+    /// Obtains the state of the device attached to the serial
+    /// port, returning `None` if the device has no state to store.
+    fn from_gb(gb: &mut GameBoy) -> Result<Option<Self>, Error> {
+        let device = gb.serial_i().device();
+        Ok(device
+            .device_state()
+            .map(|state| Self::new(device.id(), state)))
+    }
+
+    fn to_gb(&self, gb: &mut GameBoy) -> Result<(), Error> {
+        let device = gb.serial().device_mut();
+        if device.id() != self.id {
+            warnln!(
+                "Skipping serial device state, expected '{}' device got '{}'",
+                self.id,
+                device.id()
+            );
+            #[allow(unreachable_code)]
+            {
+                return Ok(());
+            }
+        }
+        device.set_device_state(&self.state)
+    }
+}
+
+impl Serialize for BosSerialDevice {
+    fn write<W: Write + Seek>(&mut self, writer: &mut W) -> Result<(), Error> {
+        self.header.write(writer)?;
+        write_u8(writer, self.id.len() as u8)?;
+        write_bytes(writer, self.id.as_bytes())?;
+        write_bytes(writer, &self.state)?;
+        Ok(())
+    }
+
+    fn read<R: Read + Seek>(&mut self, reader: &mut R) -> Result<(), Error> {
+        self.header.read(reader)?;
+        let id_len = read_u8(reader)? as usize;
+        self.id = String::from_utf8(read_bytes(reader, id_len)?)?;
+        let state_len = (self.header.size as usize)
+            .checked_sub(size_of::<u8>() + id_len)
+            .ok_or(Error::InvalidData)?;
+        self.state = read_bytes(reader, state_len)?;
+        Ok(())
+    }
+}
+
+impl Default for BosSerialDevice {
+    fn default() -> Self {
+        Self::new("", vec![])
+    }
+}
+
+/// Configuration of the contents of the RAM at power on (pattern
+/// and seed), so that reloading the system after the state is
+/// loaded produces the same contents.
+pub struct BosStartup {
+    header: BosBlock,
+    memory: StartupMemory,
+    seed: Option<u64>,
+}
+
+impl BosStartup {
+    pub fn new(memory: StartupMemory, seed: Option<u64>) -> Self {
+        Self {
+            header: BosBlock::new(
+                BosBlockKind::Startup,
+                1,
+                (size_of::<u8>() * 2 + size_of::<u64>()) as u32,
+            ),
+            memory,
+            seed,
+        }
+    }
+
+    pub fn from_data<R: Read + Seek>(reader: &mut R) -> Result<Self, Error> {
+        let mut instance = Self::default();
+        instance.read(reader)?;
+        Ok(instance)
+    }
+
+    fn from_gb(gb: &GameBoy) -> Self {
+        Self::new(gb.startup_memory(), gb.seed())
+    }
+
+    fn to_gb(&self, gb: &mut GameBoy) {
+        gb.set_startup_memory(self.memory);
+        if gb.seed() != self.seed {
+            match self.seed {
+                Some(seed) => gb.set_seed(seed),
+                None => gb.clear_seed(),
+            }
+        }
+    }
+}
+
+impl Serialize for BosStartup {
+    fn write<W: Write + Seek>(&mut self, writer: &mut W) -> Result<(), Error> {
+        self.header.write(writer)?;
+        write_u8(writer, self.memory as u8)?;
+        write_u8(writer, self.seed.is_some() as u8)?;
+        write_u64(writer, self.seed.unwrap_or(0))?;
+        Ok(())
+    }
+
+    fn read<R: Read + Seek>(&mut self, reader: &mut R) -> Result<(), Error> {
+        self.header.read(reader)?;
+        self.memory = StartupMemory::from_u8(read_u8(reader)?)?;
+        let has_seed = read_u8(reader)? != 0;
+        let seed = read_u64(reader)?;
+        self.seed = if has_seed { Some(seed) } else { None };
+        Ok(())
+    }
+}
+
+impl Default for BosStartup {
+    fn default() -> Self {
+        Self::new(StartupMemory::default(), None)
     }
 }
 
@@ -936,6 +1324,7 @@ pub struct BessState {
     info: BessInfo,
     core: BessCore,
     mbc: BessMbc,
+    rtc: Option<BessRtc>,
     end: BessBlock,
 }
 
@@ -1072,6 +1461,9 @@ impl Serialize for BessState {
         self.info.write(writer)?;
         self.core.write(writer)?;
         self.mbc.write(writer)?;
+        if let Some(rtc) = &mut self.rtc {
+            rtc.write(writer)?;
+        }
         self.end.write(writer)?;
         self.footer.write(writer)?;
         Ok(())
@@ -1099,6 +1491,7 @@ impl Serialize for BessState {
                 "INFO" => self.info = BessInfo::from_data(reader)?,
                 "CORE" => self.core = BessCore::from_data(reader)?,
                 "MBC " => self.mbc = BessMbc::from_data(reader)?,
+                "RTC " => self.rtc = Some(BessRtc::from_data(reader)?),
                 "END " => self.end = BessBlock::from_data(reader)?,
                 _ => {
                     BessBlock::from_data(reader)?;
@@ -1122,6 +1515,11 @@ impl StateBox for BessState {
             info: BessInfo::from_gb(gb)?,
             core: BessCore::from_gb(gb)?,
             mbc: BessMbc::from_gb(gb)?,
+            rtc: if gb.rom_i().has_rtc() {
+                Some(BessRtc::from_gb(gb)?)
+            } else {
+                None
+            },
             end: BessBlock::from_magic(String::from("END ")),
         }))
     }
@@ -1132,6 +1530,9 @@ impl StateBox for BessState {
         self.info.to_gb(gb)?;
         self.core.to_gb(gb)?;
         self.mbc.to_gb(gb)?;
+        if let Some(rtc) = &self.rtc {
+            rtc.to_gb(gb)?;
+        }
         Ok(())
     }
 }
@@ -1442,29 +1843,7 @@ impl BessInfo {
     }
 
     pub fn title(&self) -> String {
-        let mut final_index = 16;
-        for (offset, byte) in self.title.iter().enumerate() {
-            if *byte == 0u8 {
-                final_index = offset;
-                break;
-            }
-
-            // in we're at the final byte of the title and the value
-            // is one that is reserved for CGB compatibility testing
-            // then we must ignore it for title processing purposes
-            if offset > 14
-                && (*byte == CgbMode::CgbCompatible as u8 || *byte == CgbMode::CgbOnly as u8)
-            {
-                final_index = offset;
-                break;
-            }
-        }
-        String::from(
-            String::from_utf8(Vec::from(&self.title[..final_index]))
-                .unwrap()
-                .trim_matches(char::from(0))
-                .trim(),
-        )
+        Cartridge::title_from_data(&self.title)
     }
 }
 
@@ -1974,6 +2353,17 @@ impl State for BessMbc {
                 registers.push(BessMbrRegister::new(0x4000, gb.rom().ram_bank()));
                 registers.push(BessMbrRegister::new(0x6000, 0x00_u8));
             }
+            MbcType::Mbc2 => {
+                registers.push(BessMbrRegister::new(
+                    0x0000,
+                    if gb.rom().ram_enabled() {
+                        0x0a_u8
+                    } else {
+                        0x00_u8
+                    },
+                ));
+                registers.push(BessMbrRegister::new(0x0100, gb.rom().rom_bank() as u8));
+            }
             MbcType::Mbc3 => {
                 registers.push(BessMbrRegister::new(
                     0x0000,
@@ -1984,7 +2374,13 @@ impl State for BessMbc {
                     },
                 ));
                 registers.push(BessMbrRegister::new(0x2000, gb.rom().rom_bank() as u8));
-                registers.push(BessMbrRegister::new(0x4000, gb.rom().ram_bank()));
+                registers.push(BessMbrRegister::new(
+                    0x4000,
+                    match gb.rom().rtc_register() {
+                        0x00 => gb.rom().ram_bank(),
+                        rtc_register => rtc_register,
+                    },
+                ));
             }
             MbcType::Mbc5 => {
                 registers.push(BessMbrRegister::new(
@@ -2022,6 +2418,63 @@ impl Default for BessMbc {
     }
 }
 
+/// The RTC block of the BESS format, stores the MBC3 Real
+/// Time Clock registers (current and latched) and the UNIX
+/// timestamp of the moment the state was saved.
+pub struct BessRtc {
+    header: BessBlockHeader,
+    rtc: Rtc,
+}
+
+impl BessRtc {
+    pub fn new(rtc: Rtc) -> Self {
+        Self {
+            header: BessBlockHeader::new(String::from("RTC "), RTC_FOOTER_SIZE as u32),
+            rtc,
+        }
+    }
+
+    pub fn from_data<R: Read + Seek>(reader: &mut R) -> Result<Self, Error> {
+        let mut instance = Self::default();
+        instance.read(reader)?;
+        Ok(instance)
+    }
+}
+
+impl Serialize for BessRtc {
+    fn write<W: Write + Seek>(&mut self, writer: &mut W) -> Result<(), Error> {
+        self.header.write(writer)?;
+        write_bytes(writer, &self.rtc.to_data())?;
+        Ok(())
+    }
+
+    fn read<R: Read + Seek>(&mut self, reader: &mut R) -> Result<(), Error> {
+        self.header.read(reader)?;
+        let data = read_bytes(reader, self.header.size as usize)?;
+        self.rtc = Rtc::from_data(&data)?;
+        Ok(())
+    }
+}
+
+impl State for BessRtc {
+    fn from_gb(gb: &mut GameBoy) -> Result<Self, Error> {
+        let mut rtc = gb.rom_i().rtc().clone();
+        rtc.update(gb.rom_i().now());
+        Ok(Self::new(rtc))
+    }
+
+    fn to_gb(&self, gb: &mut GameBoy) -> Result<(), Error> {
+        gb.rom().set_rtc(self.rtc.clone());
+        Ok(())
+    }
+}
+
+impl Default for BessRtc {
+    fn default() -> Self {
+        Self::new(Rtc::default())
+    }
+}
+
 /// Top level manager structure containing the
 /// entrypoint static methods for saving and loading
 /// [BESS](https://github.com/LIJI32/SameBoy/blob/master/BESS.md) state
@@ -2087,6 +2540,24 @@ impl StateManager {
         Ok(data.into_inner())
     }
 
+    /// Takes a snapshot of the current state of the system in
+    /// the BOSC format, safe to be taken between any two clock
+    /// steps (mid-frame included), as the full state of the
+    /// devices (eg: PPU pixel FIFO) is stored, resuming the
+    /// emulation at exactly the same cycle once loaded.
+    pub fn snapshot(gb: &mut GameBoy) -> Result<Vec<u8>, Error> {
+        Self::save(
+            gb,
+            Some(SaveStateFormat::Bosc),
+            Some(FromGbOptions::new(
+                true,
+                Some(StateFormat::Full),
+                None,
+                None,
+            )),
+        )
+    }
+
     pub fn load(
         data: &[u8],
         gb: &mut GameBoy,
@@ -2262,6 +2733,10 @@ impl StateManager {
 
         state.to_gb(gb, options)?;
 
+        // the memory has been replaced so any code cached
+        // from it must be decoded again
+        gb.mmu().invalidate_code();
+
         Ok(())
     }
 }
@@ -2319,30 +2794,550 @@ impl StateManager {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use boytacean_encoding::zippy::{decode_zippy, encode_zippy};
+/// Default number of frames between each full (keyframe)
+/// state stored in the rewind buffer.
+pub const REWIND_KEYFRAME_INTERVAL: usize = 60;
 
-    use crate::{
-        gb::GameBoy,
-        state::{FromGbOptions, State},
-    };
+/// Default number of in-memory quick-save slots.
+pub const QUICK_SLOT_COUNT: usize = 10;
 
-    use super::{BessCore, SaveStateFormat, StateManager};
+/// The default number of save state slots of a [`StateCatalog`].
+pub const STATE_SLOT_COUNT: usize = 10;
 
-    #[test]
-    fn test_bess_core() {
-        let mut gb = GameBoy::default();
-        gb.load(true).unwrap();
-        gb.load_rom_file("res/roms/test/firstwhite.gb", None)
-            .unwrap();
-        let bess_core = BessCore::from_gb(&mut gb).unwrap();
-        assert_eq!(bess_core.model, "GDB ");
-        assert_eq!(bess_core.pc, 0x0000);
-        assert_eq!(bess_core.af, 0x0000);
-        assert_eq!(bess_core.bc, 0x0000);
-        assert_eq!(bess_core.de, 0x0000);
-        assert_eq!(bess_core.hl, 0x0000);
+/// Single entry of the rewind buffer, either a keyframe (full
+/// state compressed using Zippy) or a delta (XOR against the
+/// previous keyframe compressed using RLE).
+struct RewindEntry {
+    keyframe: bool,
+    size: usize,
+    data: Vec<u8>,
+}
+
+/// Ring buffer of delta compressed save states meant to be
+/// used for rewind and frame-level replay operations.
+///
+/// Every state is stored as the delta against the most recent
+/// keyframe, as consecutive frames share most of their state
+/// the resulting delta is mostly zeros and compresses well.
+pub struct RewindBuffer {
+    /// The maximum number of states (frames) stored in the buffer,
+    /// the oldest states are discarded once this limit is reached.
+    capacity: usize,
+
+    /// The number of states between each keyframe.
+    keyframe_interval: usize,
+
+    entries: VecDeque<RewindEntry>,
+
+    /// The uncompressed contents of the most recent keyframe used
+    /// as the base for the delta computation of new entries.
+    reference: Vec<u8>,
+
+    /// The number of entries pushed since the most recent keyframe.
+    since_keyframe: usize,
+}
+
+impl RewindBuffer {
+    pub fn new(capacity: usize, keyframe_interval: Option<usize>) -> Self {
+        // the keyframe interval is limited to half of the capacity so that
+        // evicting the oldest keyframe never empties most of the buffer
+        let keyframe_interval = keyframe_interval
+            .unwrap_or(REWIND_KEYFRAME_INTERVAL)
+            .min(capacity / 2)
+            .max(1);
+        Self {
+            capacity: capacity.max(1),
+            keyframe_interval,
+            entries: VecDeque::new(),
+            reference: vec![],
+            since_keyframe: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Obtains the total number of bytes used by the (compressed)
+    /// entries of the buffer.
+    pub fn size(&self) -> usize {
+        self.entries.iter().map(|entry| entry.data.len()).sum()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.reference.clear();
+        self.since_keyframe = 0;
+    }
+
+    /// Pushes a new (uncompressed) state into the buffer, discarding
+    /// the oldest states in case the capacity has been reached.
+    pub fn push(&mut self, state: &[u8]) -> Result<(), Error> {
+        if self.reference.is_empty() || self.since_keyframe >= self.keyframe_interval {
+            self.entries.push_back(RewindEntry {
+                keyframe: true,
+                size: state.len(),
+                data: encode_zippy(state, None, None)?,
+            });
+            self.reference = state.to_vec();
+            self.since_keyframe = 0;
+        } else {
+            self.entries.push_back(RewindEntry {
+                keyframe: false,
+                size: state.len(),
+                data: encode_rle(&Self::delta(&self.reference, state))?,
+            });
+            self.since_keyframe += 1;
+        }
+
+        // removes the oldest entries, making sure that no delta entry
+        // is left at the front of the buffer without its keyframe
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+            while matches!(self.entries.front(), Some(entry) if !entry.keyframe) {
+                self.entries.pop_front();
+            }
+        }
+        if self.entries.is_empty() {
+            self.reference.clear();
+        }
+
+        Ok(())
+    }
+
+    /// Discards the most recent `count` states from the buffer and
+    /// returns the (uncompressed) state that is then the most recent,
+    /// which is kept in the buffer.
+    ///
+    /// In case there are not enough states, the oldest state is
+    /// returned, `None` is returned if the buffer is empty.
+    pub fn rewind(&mut self, count: usize) -> Result<Option<Vec<u8>>, Error> {
+        let count = count.min(self.entries.len().saturating_sub(1));
+        for _ in 0..count {
+            self.entries.pop_back();
+        }
+
+        let keyframe_index = match self.entries.iter().rposition(|entry| entry.keyframe) {
+            Some(index) => index,
+            None => {
+                self.clear();
+                return Ok(None);
+            }
+        };
+        let keyframe = &self.entries[keyframe_index];
+        self.reference = decode_zippy(&keyframe.data, None)?;
+        self.since_keyframe = self.entries.len() - 1 - keyframe_index;
+
+        let entry = self.entries.back().unwrap();
+        if entry.keyframe {
+            return Ok(Some(self.reference.clone()));
+        }
+        let mut state = decode_rle(&entry.data)?;
+        state.truncate(entry.size);
+        Self::apply_delta(&self.reference, &mut state);
+        Ok(Some(state))
+    }
+
+    /// Computes the XOR delta of the provided state against the
+    /// provided reference, the delta has the size of the state.
+    fn delta(reference: &[u8], state: &[u8]) -> Vec<u8> {
+        state
+            .iter()
+            .enumerate()
+            .map(|(index, byte)| byte ^ reference.get(index).unwrap_or(&0))
+            .collect()
+    }
+
+    fn apply_delta(reference: &[u8], delta: &mut [u8]) {
+        for (byte, reference) in delta.iter_mut().zip(reference.iter()) {
+            *byte ^= reference;
+        }
+    }
+}
+
+/// Set of in-memory save state slots meant for instant state
+/// cycling (eg: speedrun practice), no filesystem is used.
+///
+/// The states are stored uncompressed in the minimal format and
+/// without thumbnail, keeping both the save and the load latency
+/// to a minimum.
+pub struct QuickSlots {
+    slots: Vec<Option<Vec<u8>>>,
+
+    /// The index of the slot used by the save and load operations
+    /// that don't explicitly provide one.
+    selected: usize,
+}
+
+impl QuickSlots {
+    pub fn new(count: usize) -> Self {
+        Self {
+            slots: vec![None; count.max(1)],
+            selected: 0,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn select(&mut self, index: usize) -> Result<(), Error> {
+        self.check(index)?;
+        self.selected = index;
+        Ok(())
+    }
+
+    /// Selects the next slot, wrapping around after the last one,
+    /// returning the index of the newly selected slot.
+    pub fn select_next(&mut self) -> usize {
+        self.selected = (self.selected + 1) % self.slots.len();
+        self.selected
+    }
+
+    /// Selects the previous slot, wrapping around before the first
+    /// one, returning the index of the newly selected slot.
+    pub fn select_previous(&mut self) -> usize {
+        self.selected = (self.selected + self.slots.len() - 1) % self.slots.len();
+        self.selected
+    }
+
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        self.slots.get(index)?.as_deref()
+    }
+
+    pub fn set(&mut self, index: usize, state: Vec<u8>) -> Result<(), Error> {
+        self.check(index)?;
+        self.slots[index] = Some(state);
+        Ok(())
+    }
+
+    pub fn is_used(&self, index: usize) -> bool {
+        self.get(index).is_some()
+    }
+
+    /// Obtains the total number of bytes used by the stored states.
+    pub fn size(&self) -> usize {
+        self.slots.iter().flatten().map(|state| state.len()).sum()
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|slot| *slot = None);
+    }
+
+    fn check(&self, index: usize) -> Result<(), Error> {
+        if index >= self.slots.len() {
+            return Err(Error::CustomError(format!(
+                "Invalid quick slot: {index} (of {})",
+                self.slots.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for QuickSlots {
+    fn default() -> Self {
+        Self::new(QUICK_SLOT_COUNT)
+    }
+}
+
+/// Backend used by the [`StateCatalog`] to store the save state
+/// files, allowing the slots to live either in the file system or
+/// in a virtual one (eg: the browser storage under WASM).
+pub trait StateStore {
+    /// Reads the contents of the file in the provided path,
+    /// `None` in case the file does not exist.
+    fn read(&self, path: &str) -> Result<Option<Vec<u8>>, Error>;
+
+    /// Writes the contents of the file in the provided path,
+    /// creating any missing parent directory.
+    fn write(&mut self, path: &str, data: &[u8]) -> Result<(), Error>;
+
+    /// Removes the file in the provided path, if it exists.
+    fn remove(&mut self, path: &str) -> Result<(), Error>;
+}
+
+/// Store backed by the file system, paths are file paths.
+#[derive(Default)]
+pub struct FileStateStore;
+
+impl StateStore for FileStateStore {
+    fn read(&self, path: &str) -> Result<Option<Vec<u8>>, Error> {
+        if !Path::new(path).is_file() {
+            return Ok(None);
+        }
+        Ok(Some(read_file(path)?))
+    }
+
+    fn write(&mut self, path: &str, data: &[u8]) -> Result<(), Error> {
+        if let Some(parent) = Path::new(path).parent() {
+            create_dir_all(parent)
+                .map_err(|_| Error::IoError(format!("Failed to create directory for: {path}")))?;
+        }
+        write_file(path, data, None)
+    }
+
+    fn remove(&mut self, path: &str) -> Result<(), Error> {
+        if !Path::new(path).is_file() {
+            return Ok(());
+        }
+        remove_file(path).map_err(|_| Error::IoError(format!("Failed to remove file: {path}")))
+    }
+}
+
+/// Virtual (in-memory) store, meant for environments without a
+/// file system, where the files are persisted by the embedder.
+#[derive(Default)]
+pub struct MemoryStateStore {
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl MemoryStateStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn files(&self) -> &HashMap<String, Vec<u8>> {
+        &self.files
+    }
+
+    pub fn files_mut(&mut self) -> &mut HashMap<String, Vec<u8>> {
+        &mut self.files
+    }
+}
+
+impl StateStore for MemoryStateStore {
+    fn read(&self, path: &str) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.files.get(path).cloned())
+    }
+
+    fn write(&mut self, path: &str, data: &[u8]) -> Result<(), Error> {
+        self.files.insert(String::from(path), data.to_vec());
+        Ok(())
+    }
+
+    fn remove(&mut self, path: &str) -> Result<(), Error> {
+        self.files.remove(path);
+        Ok(())
+    }
+}
+
+/// Metadata of a used slot of a [`StateCatalog`], the values that
+/// are not supported by the format of the state are `None`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlotInfo {
+    pub index: usize,
+    pub path: String,
+    pub size: usize,
+    pub format: SaveStateFormat,
+    pub title: Option<String>,
+    pub timestamp: Option<u64>,
+    pub thumbnail: Option<Vec<u8>>,
+}
+
+/// Catalog of the numbered save state slots of a ROM, with the
+/// paths of the slots built from the state template of the provided
+/// [`SavePaths`] (and its saves directory) and the files kept in a
+/// [`StateStore`].
+///
+/// Loading a slot verifies that the state has been saved for the
+/// ROM currently loaded (by its title).
+pub struct StateCatalog {
+    store: Box<dyn StateStore>,
+    paths: SavePaths,
+    rom_path: String,
+    count: usize,
+    format: SaveStateFormat,
+}
+
+impl StateCatalog {
+    pub fn new(store: Box<dyn StateStore>, paths: SavePaths, rom_path: &str) -> Self {
+        Self {
+            store,
+            paths,
+            rom_path: String::from(rom_path),
+            count: STATE_SLOT_COUNT,
+            format: SaveStateFormat::Bosc,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn set_count(&mut self, count: usize) {
+        self.count = count.clamp(1, u8::MAX as usize + 1);
+    }
+
+    pub fn format(&self) -> SaveStateFormat {
+        self.format
+    }
+
+    pub fn set_format(&mut self, format: SaveStateFormat) {
+        self.format = format;
+    }
+
+    pub fn rom_path(&self) -> &str {
+        &self.rom_path
+    }
+
+    pub fn set_rom_path(&mut self, rom_path: &str) {
+        self.rom_path = String::from(rom_path);
+    }
+
+    pub fn paths(&self) -> &SavePaths {
+        &self.paths
+    }
+
+    pub fn set_paths(&mut self, paths: SavePaths) {
+        self.paths = paths;
+    }
+
+    pub fn slot_path(&self, index: usize) -> Result<String, Error> {
+        self.check(index)?;
+        Ok(self
+            .paths
+            .state_path(&self.rom_path, index as u8)
+            .to_string_lossy()
+            .to_string())
+    }
+
+    /// Saves the state of the system into the slot, replacing any
+    /// state previously stored in it, returning the path of the slot.
+    pub fn save_slot(&mut self, index: usize, gb: &mut GameBoy) -> Result<String, Error> {
+        let path = self.slot_path(index)?;
+        let data = StateManager::save(gb, Some(self.format), None)?;
+        self.store.write(&path, &data)?;
+        Ok(path)
+    }
+
+    /// Loads the state stored in the slot into the system, failing
+    /// in case the slot is empty or the state has been saved for
+    /// another ROM, returning the path of the slot.
+    pub fn load_slot(&mut self, index: usize, gb: &mut GameBoy) -> Result<String, Error> {
+        let path = self.slot_path(index)?;
+        let data = self
+            .store
+            .read(&path)?
+            .ok_or_else(|| Error::CustomError(format!("Empty save state slot: {index}")))?;
+        let title = gb.rom_i().title();
+        if StateManager::validate(&data, Some(title.clone())).is_err() {
+            return Err(Error::CustomError(format!(
+                "Save state in slot {index} is not for the loaded ROM ({title})"
+            )));
+        }
+        StateManager::load(&data, gb, None, None)?;
+        Ok(path)
+    }
+
+    pub fn delete_slot(&mut self, index: usize) -> Result<(), Error> {
+        let path = self.slot_path(index)?;
+        self.store.remove(&path)
+    }
+
+    pub fn is_used(&self, index: usize) -> Result<bool, Error> {
+        let path = self.slot_path(index)?;
+        Ok(self.store.read(&path)?.is_some())
+    }
+
+    /// Obtains the metadata of each of the used slots, in order,
+    /// with the slots that can't be read being skipped.
+    pub fn list_slots(&self) -> Result<Vec<SlotInfo>, Error> {
+        let mut slots = vec![];
+        for index in 0..self.count {
+            let path = self.slot_path(index)?;
+            let data = match self.store.read(&path)? {
+                Some(data) => data,
+                None => continue,
+            };
+            let format = match StateManager::format(&data) {
+                Ok(format) => format,
+                Err(_) => continue,
+            };
+            let info: Option<Box<dyn StateInfo>> = match format {
+                SaveStateFormat::Bosc | SaveStateFormat::Bos => StateManager::read_bos_auto(&data)
+                    .ok()
+                    .map(|state| Box::new(state) as Box<dyn StateInfo>),
+                SaveStateFormat::Bess => StateManager::read_bess(&data)
+                    .ok()
+                    .map(|state| Box::new(state) as Box<dyn StateInfo>),
+            };
+            slots.push(SlotInfo {
+                index,
+                path,
+                size: data.len(),
+                format,
+                title: info.as_ref().and_then(|info| info.title().ok()),
+                timestamp: info.as_ref().and_then(|info| info.timestamp().ok()),
+                thumbnail: info
+                    .as_ref()
+                    .filter(|info| info.has_image())
+                    .and_then(|info| info.image_eager().ok()),
+            });
+        }
+        Ok(slots)
+    }
+
+    fn check(&self, index: usize) -> Result<(), Error> {
+        if index >= self.count {
+            return Err(Error::CustomError(format!(
+                "Invalid save state slot: {index} (of {})",
+                self.count
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use boytacean_common::util::read_file;
+    use boytacean_encoding::zippy::{decode_zippy, encode_zippy};
+    use boytacean_hashing::crc32::crc32;
+
+    use std::io::Cursor;
+
+    use crate::{
+        devices::{buffer::BufferDevice, printer::PrinterDevice},
+        dma::Dma,
+        gb::{GameBoy, GameBoyDevice, GameBoyMode, StartupMemory},
+        ppu::{PpuMode, RenderMode},
+        serial::SerialDevice,
+        state::{FromGbOptions, State},
+        storage::{SaveLayout, SavePaths},
+    };
+
+    use super::{
+        BessCore, BessInfo, BosDeviceState, BoscCodec, MemoryStateStore, RewindBuffer,
+        SaveStateFormat, Serialize, StateCatalog, StateComponent, StateFormat, StateManager,
+        ToGbOptions, BOSC_MAGIC_UINT, BOSC_VERSION,
+    };
+
+    #[test]
+    fn test_bess_core() {
+        let mut gb = GameBoy::default();
+        gb.load(true).unwrap();
+        gb.load_rom_file("res/roms/test/firstwhite.gb", None)
+            .unwrap();
+        let bess_core = BessCore::from_gb(&mut gb).unwrap();
+        assert_eq!(bess_core.model, "GDB ");
+        assert_eq!(bess_core.pc, 0x0000);
+        assert_eq!(bess_core.af, 0x0000);
+        assert_eq!(bess_core.bc, 0x0000);
+        assert_eq!(bess_core.de, 0x0000);
+        assert_eq!(bess_core.hl, 0x0000);
         assert_eq!(bess_core.sp, 0x0000);
         assert!(!bess_core.ime);
         assert_eq!(bess_core.ie, 0x00);
@@ -2351,12 +3346,12 @@ mod tests {
         assert_eq!(
             bess_core.io_registers,
             [
-                63, 0, 0, 255, 0, 0, 0, 248, 255, 255, 255, 255, 255, 255, 255, 224, 128, 0, 0,
+                255, 0, 124, 255, 0, 0, 0, 248, 255, 255, 255, 255, 255, 255, 255, 224, 128, 0, 0,
                 255, 56, 255, 0, 0, 255, 56, 127, 255, 159, 255, 56, 255, 0, 0, 0, 63, 0, 0, 240,
                 255, 255, 255, 255, 255, 255, 255, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
                 0, 0, 0, 0, 134, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 126, 255, 254, 0, 255, 255, 255,
                 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
-                255, 255, 255, 255, 0, 0, 0, 0, 255, 255, 255, 255, 249, 255, 255, 255, 255, 255,
+                255, 255, 255, 255, 64, 0, 64, 0, 254, 255, 255, 255, 249, 255, 255, 255, 255, 255,
                 255, 255, 255, 255, 255, 255, 255, 255, 255, 255
             ]
         );
@@ -2380,6 +3375,114 @@ mod tests {
         StateManager::load(&data, &mut gb, None, None).unwrap();
     }
 
+    #[test]
+    fn test_bosc_codec() {
+        let mut gb = GameBoy::default();
+        gb.load(true).unwrap();
+        gb.load_rom_file("res/roms/test/firstwhite.gb", None)
+            .unwrap();
+        gb.step_to(0x0100);
+
+        let data = StateManager::save(&mut gb, Some(SaveStateFormat::Bosc), None).unwrap();
+        assert_eq!(&data[4..6], &[2, BoscCodec::Zippy as u8]);
+        assert_eq!(
+            StateManager::read_bosc(&data).unwrap().codec(),
+            BoscCodec::Zippy
+        );
+
+        // the deflate payload (after the header) is a zlib stream
+        let data = StateManager::save(
+            &mut gb,
+            Some(SaveStateFormat::Bosc),
+            Some(FromGbOptions::default().with_codec(BoscCodec::Deflate)),
+        )
+        .unwrap();
+        assert_eq!(&data[4..7], &[2, BoscCodec::Deflate as u8, 0x78]);
+        let state = StateManager::read_bosc(&data).unwrap();
+        assert_eq!(state.codec(), BoscCodec::Deflate);
+        assert_eq!(StateManager::format(&data).unwrap(), SaveStateFormat::Bosc);
+        StateManager::load(&data, &mut gb, None, None).unwrap();
+        assert_eq!(gb.cpu_i().pc(), 0x0100);
+
+        // the version 1 of the format (no codec) is still loaded
+        let bos = StateManager::save(&mut gb, Some(SaveStateFormat::Bos), None).unwrap();
+        let mut data = BOSC_MAGIC_UINT.to_le_bytes().to_vec();
+        data.push(1);
+        data.extend(encode_zippy(&bos, None, None).unwrap());
+        let state = StateManager::read_bosc(&data).unwrap();
+        assert_eq!(state.codec(), BoscCodec::Zippy);
+        StateManager::load(&data, &mut gb, None, None).unwrap();
+
+        // an unknown codec is reported as invalid
+        let mut data = StateManager::save(&mut gb, Some(SaveStateFormat::Bosc), None).unwrap();
+        data[5] = 0xff;
+        assert!(StateManager::read_bosc(&data).is_err());
+    }
+
+    #[test]
+    fn test_bosc_round_trip() {
+        let data = read_file("res/states/pocket-v0.10.14.bosc").unwrap();
+        let mut state = StateManager::read_bosc(&data).unwrap();
+        assert_eq!(state.codec(), BoscCodec::Zippy);
+
+        // a state read from an older version is written
+        // back using the latest version of the format
+        let mut cursor = Cursor::new(vec![]);
+        state.write(&mut cursor).unwrap();
+        let data = cursor.into_inner();
+        assert_eq!(&data[4..6], &[BOSC_VERSION, BoscCodec::Zippy as u8]);
+
+        let state = StateManager::read_bosc(&data).unwrap();
+        assert_eq!(state.codec(), BoscCodec::Zippy);
+        state.verify().unwrap();
+
+        let mut gb = GameBoy::default();
+        gb.load(true).unwrap();
+        gb.load_rom_file("res/roms/demo/pocket.gb", None).unwrap();
+        StateManager::load(&data, &mut gb, None, None).unwrap();
+    }
+
+    #[test]
+    fn test_bosc_codec_string() {
+        assert_eq!(BoscCodec::from_string("zippy").unwrap(), BoscCodec::Zippy);
+        assert_eq!(
+            BoscCodec::from_string("deflate").unwrap(),
+            BoscCodec::Deflate
+        );
+        assert_eq!(BoscCodec::from_string("zstd").unwrap(), BoscCodec::Zstd);
+        assert!(BoscCodec::from_string("lzma").is_err());
+        assert_eq!(BoscCodec::Deflate.to_string(), "Deflate");
+        assert_eq!(BoscCodec::from_u8(3).unwrap(), BoscCodec::Zstd);
+    }
+
+    #[test]
+    fn test_bosc_zstd() {
+        let mut gb = GameBoy::default();
+        gb.load(true).unwrap();
+        gb.load_rom_file("res/roms/test/firstwhite.gb", None)
+            .unwrap();
+        gb.step_to(0x0100);
+
+        let result = StateManager::save(
+            &mut gb,
+            Some(SaveStateFormat::Bosc),
+            Some(FromGbOptions::default().with_codec(BoscCodec::Zstd)),
+        );
+        assert_eq!(BoscCodec::Zstd.available(), cfg!(feature = "zstd"));
+        if !BoscCodec::Zstd.available() {
+            assert!(result.is_err());
+            return;
+        }
+
+        // the zstd payload (after the header) is a zstd frame
+        let data = result.unwrap();
+        assert_eq!(&data[4..7], &[2, BoscCodec::Zstd as u8, 0x28]);
+        let state = StateManager::read_bosc(&data).unwrap();
+        assert_eq!(state.codec(), BoscCodec::Zstd);
+        StateManager::load(&data, &mut gb, None, None).unwrap();
+        assert_eq!(gb.cpu_i().pc(), 0x0100);
+    }
+
     #[test]
     fn test_load_bos() {
         let mut gb = GameBoy::default();
@@ -2424,6 +3527,25 @@ mod tests {
         assert_eq!(info.agent_version, "1.2.3");
     }
 
+    #[test]
+    fn test_bess_info_title() {
+        // title with invalid UTF-8 bytes overlapping the CGB flag, that
+        // must be extracted the same way by both the ROM and the state
+        let mut data = vec![0; 0x8000];
+        data[0x0134..=0x0143].copy_from_slice(b"POK\xe9MON  CARD \x8a\xc0");
+        let mut gb = GameBoy::default();
+        gb.load(true).unwrap();
+        gb.load_rom(&data, None).unwrap();
+
+        let info = BessInfo::from_gb(&mut gb).unwrap();
+        assert_eq!(info.title(), "POK\u{fffd}MON  CARD \u{fffd}");
+        assert_eq!(info.title(), gb.rom_i().title());
+        info.to_gb(&mut gb).unwrap();
+
+        let data = StateManager::save(&mut gb, Some(SaveStateFormat::Bess), None).unwrap();
+        StateManager::validate(&data, Some(gb.rom_i().title())).unwrap();
+    }
+
     #[test]
     fn test_bess_agent_version() {
         let mut gb = GameBoy::default();
@@ -2463,7 +3585,453 @@ mod tests {
         let encoded = encode_zippy(&data, None, None).unwrap();
         let decoded = decode_zippy(&encoded, None).unwrap();
         assert_eq!(data, decoded);
-        assert_eq!(encoded.len(), 841);
+        assert_eq!(encoded.len(), 1467);
         assert_eq!(decoded.len(), 25153);
     }
+
+    #[test]
+    fn test_rewind_buffer() {
+        let mut buffer = RewindBuffer::new(4, Some(2));
+        assert!(buffer.rewind(1).unwrap().is_none());
+
+        for index in 0..6_u8 {
+            buffer.push(&[index, 0x10, 0x20, index]).unwrap();
+        }
+        assert_eq!(buffer.len(), 3);
+
+        assert_eq!(buffer.rewind(0).unwrap().unwrap(), vec![5, 0x10, 0x20, 5]);
+        assert_eq!(buffer.rewind(1).unwrap().unwrap(), vec![4, 0x10, 0x20, 4]);
+        buffer.push(&[7, 0x10, 0x20, 7, 0x30]).unwrap();
+        assert_eq!(
+            buffer.rewind(0).unwrap().unwrap(),
+            vec![7, 0x10, 0x20, 7, 0x30]
+        );
+        assert_eq!(buffer.rewind(10).unwrap().unwrap(), vec![3, 0x10, 0x20, 3]);
+        assert_eq!(buffer.len(), 1);
+    }
+
+    #[test]
+    fn test_rewind_gb() {
+        let mut gb = GameBoy::default();
+        gb.load(true).unwrap();
+        gb.load_rom_file("res/roms/demo/pocket.gb", None).unwrap();
+        gb.enable_rewind(30);
+
+        let mut frames = Vec::new();
+        for _ in 0..120 {
+            gb.next_frame();
+            frames.push((gb.ppu_frame(), gb.cpu_i().pc()));
+        }
+        assert!(gb.rewind_buffer().unwrap().len() > 10);
+        assert!(gb.rewind_buffer().unwrap().len() <= 30);
+
+        assert!(gb.rewind(10).unwrap());
+        assert_eq!((gb.ppu_frame(), gb.cpu_i().pc()), frames[109]);
+
+        gb.next_frame();
+        assert_eq!((gb.ppu_frame(), gb.cpu_i().pc()), frames[110]);
+
+        gb.disable_rewind();
+        assert!(gb.rewind(1).is_err());
+    }
+
+    #[test]
+    fn test_quick_slots() {
+        let mut gb = GameBoy::default();
+        gb.load(true).unwrap();
+        gb.load_rom_file("res/roms/demo/pocket.gb", None).unwrap();
+        gb.set_quick_slot_count(4);
+
+        for _ in 0..30 {
+            gb.next_frame();
+        }
+        gb.quick_save(None).unwrap();
+        let saved = (gb.ppu_frame(), gb.cpu_i().pc());
+        for _ in 0..30 {
+            gb.next_frame();
+        }
+        gb.quick_save(Some(2)).unwrap();
+        assert!(gb.quick_slots().is_used(0));
+        assert!(!gb.quick_slots().is_used(1));
+        assert!(gb.quick_slots().is_used(2));
+
+        assert!(gb.quick_load(None).unwrap());
+        assert_eq!((gb.ppu_frame(), gb.cpu_i().pc()), saved);
+        assert!(!gb.quick_load(Some(1)).unwrap());
+        assert!(gb.quick_save(Some(4)).is_err());
+
+        assert_eq!(gb.quick_slots_mut().select_previous(), 3);
+        assert_eq!(gb.quick_slots_mut().select_next(), 0);
+
+        gb.load_rom_file("res/roms/demo/pocket.gb", None).unwrap();
+        assert!(!gb.quick_slots().is_used(0));
+        assert_eq!(gb.quick_slots().size(), 0);
+    }
+
+    #[test]
+    fn test_state_catalog() {
+        let mut gb = GameBoy::default();
+        gb.load(true).unwrap();
+        gb.load_rom_file("res/roms/demo/pocket.gb", None).unwrap();
+        let paths = SavePaths::from_layout(SaveLayout::Folders);
+        let mut catalog =
+            StateCatalog::new(Box::new(MemoryStateStore::new()), paths, "roms/pocket.gb");
+        catalog.set_count(4);
+
+        for _ in 0..30 {
+            gb.next_frame();
+        }
+        let path = catalog.save_slot(2, &mut gb).unwrap();
+        assert_eq!(path, "roms/pocket/2.state");
+        let saved = (gb.ppu_frame(), gb.cpu_i().pc());
+        for _ in 0..30 {
+            gb.next_frame();
+        }
+
+        let slots = catalog.list_slots().unwrap();
+        assert_eq!(slots.len(), 1);
+        assert_eq!(slots[0].index, 2);
+        assert_eq!(slots[0].format, SaveStateFormat::Bosc);
+        assert_eq!(slots[0].title, Some(gb.rom_i().title()));
+        assert!(slots[0].timestamp.is_some());
+        assert!(slots[0].thumbnail.is_some());
+
+        catalog.load_slot(2, &mut gb).unwrap();
+        assert_eq!((gb.ppu_frame(), gb.cpu_i().pc()), saved);
+        assert!(catalog.load_slot(1, &mut gb).is_err());
+        assert!(catalog.save_slot(4, &mut gb).is_err());
+
+        // the states saved for another ROM are not loaded
+        let mut other = GameBoy::default();
+        other.load(true).unwrap();
+        other
+            .load_rom_file("res/roms/test/firstwhite.gb", None)
+            .unwrap();
+        assert!(catalog.load_slot(2, &mut other).is_err());
+
+        catalog.delete_slot(2).unwrap();
+        assert!(!catalog.is_used(2).unwrap());
+        assert!(catalog.list_slots().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_bess_mbc2() {
+        let mut data = vec![0; 0x40000];
+        data[0x0147] = 0x06;
+        data[0x0148] = 0x03;
+
+        let mut gb = GameBoy::default();
+        gb.load(true).unwrap();
+        gb.load_rom(&data, None).unwrap();
+        gb.mmu().write(0x0000, 0x0a);
+        gb.mmu().write(0x2100, 0x07);
+        gb.mmu().write(0xa010, 0x09);
+
+        let state = StateManager::save(&mut gb, Some(SaveStateFormat::Bess), None).unwrap();
+
+        gb.mmu().write(0x0000, 0x00);
+        gb.mmu().write(0x2100, 0x02);
+        gb.mmu().write(0xa010, 0x01);
+        StateManager::load(&state, &mut gb, Some(SaveStateFormat::Bess), None).unwrap();
+
+        assert!(gb.rom_i().ram_enabled());
+        assert_eq!(gb.rom_i().rom_bank(), 7);
+        assert_eq!(gb.mmu().read(0xa010), 0xf9);
+        assert_eq!(gb.rom_i().ram_data().len(), 512);
+    }
+
+    #[test]
+    fn test_bess_rtc() {
+        let mut data = vec![0; 0x8000];
+        data[0x0147] = 0x10;
+        data[0x0149] = 0x02;
+
+        let mut gb = GameBoy::default();
+        gb.load(true).unwrap();
+        gb.load_rom(&data, None).unwrap();
+        gb.mmu().write(0x0000, 0x0a);
+        gb.mmu().write(0x4000, 0x0a);
+        gb.mmu().write(0xa000, 0x0c);
+        gb.mmu().write(0x4000, 0x0c);
+        gb.mmu().write(0xa000, 0x41);
+
+        let state = StateManager::save(&mut gb, Some(SaveStateFormat::Bess), None).unwrap();
+
+        gb.mmu().write(0xa000, 0x00);
+        gb.mmu().write(0x4000, 0x0a);
+        gb.mmu().write(0xa000, 0x02);
+        gb.mmu().write(0x4000, 0x00);
+        StateManager::load(&state, &mut gb, Some(SaveStateFormat::Bess), None).unwrap();
+
+        assert_eq!(gb.rom_i().rtc_register(), 0x0c);
+        assert_eq!(gb.rom_i().rtc().registers()[2], 0x0c);
+        assert!(gb.rom_i().rtc().halt());
+        assert_eq!(gb.rom_i().rtc().days(), 0x100);
+    }
+
+    #[test]
+    fn test_bos_full_mid_frame() {
+        let build = |rom_path: &str, mode: GameBoyMode, render_mode: RenderMode| {
+            let mut gb = GameBoy::new(Some(mode));
+            gb.load(true).unwrap();
+            gb.load_rom_file(rom_path, None).unwrap();
+            gb.set_render_mode(render_mode);
+            gb
+        };
+
+        for (rom_path, mode) in [
+            ("res/roms/test/dmg_acid2.gb", GameBoyMode::Dmg),
+            ("res/roms/test/cgb_acid2.gbc", GameBoyMode::Cgb),
+        ] {
+            for render_mode in [RenderMode::Scanline, RenderMode::Fifo] {
+                let mut gb = build(rom_path, mode, render_mode);
+                for _ in 0..200 {
+                    gb.next_frame();
+                }
+
+                // stops in the middle of a line being drawn, halfway
+                // through the frame, and saves the complete state
+                while gb.ppu_i().ly() != 70 || gb.ppu_i().mode() != PpuMode::VramRead {
+                    gb.clock();
+                }
+                gb.clocks_cycles(40);
+                let state = StateManager::save(
+                    &mut gb,
+                    Some(SaveStateFormat::Bos),
+                    Some(FromGbOptions::new(
+                        false,
+                        Some(StateFormat::Full),
+                        None,
+                        None,
+                    )),
+                )
+                .unwrap();
+                let trace = |gb: &mut GameBoy| {
+                    (0..2000)
+                        .map(|_| {
+                            gb.clock();
+                            (gb.cpu_i().pc(), gb.ppu_i().ly(), gb.ppu_i().mode())
+                        })
+                        .collect::<Vec<_>>()
+                };
+                let expected = trace(&mut gb);
+                let mut frames = vec![];
+                for _ in 0..2 {
+                    gb.next_frame();
+                    frames.push(gb.frame_buffer().to_vec());
+                }
+
+                // the restored system (that was already running) must
+                // continue the frame in progress producing exactly
+                // the same pixels
+                let mut other = build(rom_path, mode, render_mode);
+                for _ in 0..30 {
+                    other.next_frame();
+                }
+                while other.ppu_i().ly() != 20 || other.ppu_i().mode() != PpuMode::VramRead {
+                    other.clock();
+                }
+                other.clocks_cycles(100);
+                StateManager::load(
+                    &state,
+                    &mut other,
+                    Some(SaveStateFormat::Bos),
+                    Some(ToGbOptions::new(false)),
+                )
+                .unwrap();
+                assert_eq!(trace(&mut other), expected, "{rom_path} ({render_mode})");
+                for frame in frames {
+                    other.next_frame();
+                    assert_eq!(
+                        other.frame_buffer().to_vec(),
+                        frame,
+                        "{rom_path} ({render_mode})"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut gb = GameBoy::default();
+        gb.load(true).unwrap();
+        gb.load_rom_file("res/roms/demo/pocket.gb", None).unwrap();
+        for _ in 0..60 {
+            gb.next_frame();
+        }
+        gb.clocks_cycles(20000);
+
+        let snapshot = StateManager::snapshot(&mut gb).unwrap();
+        assert_eq!(
+            StateManager::format(&snapshot).unwrap(),
+            SaveStateFormat::Bosc
+        );
+        gb.next_frame();
+        let frame_buffer = gb.frame_buffer().to_vec();
+
+        // resumes the emulation from the snapshot (taken mid-frame)
+        // ending up in exactly the same frame
+        let mut other = GameBoy::default();
+        other.load(true).unwrap();
+        other
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+        StateManager::load(&snapshot, &mut other, None, None).unwrap();
+        other.next_frame();
+        assert_eq!(other.frame_buffer().to_vec(), frame_buffer);
+    }
+
+    #[test]
+    fn test_bos_serial_device() {
+        let mut gb = GameBoy::default();
+        gb.load(true).unwrap();
+        gb.load_rom_file("res/roms/demo/pocket.gb", None).unwrap();
+        gb.attach_serial(Box::<BufferDevice>::default());
+        gb.serial().device_mut().receive(0x42);
+        gb.serial().write(0xff01, 0x13);
+        gb.serial().write(0xff02, 0x81);
+
+        let state = StateManager::save(&mut gb, Some(SaveStateFormat::Bos), None).unwrap();
+
+        gb.attach_serial(Box::<BufferDevice>::default());
+        gb.serial().set_transferring(false);
+        StateManager::load(&state, &mut gb, Some(SaveStateFormat::Bos), None).unwrap();
+
+        assert!(gb.serial_i().transferring());
+        assert_eq!(gb.serial_i().device().device_state(), Some(vec![0x42]));
+
+        gb.attach_serial(Box::<PrinterDevice>::default());
+        StateManager::load(&state, &mut gb, Some(SaveStateFormat::Bos), None).unwrap();
+        assert_eq!(gb.serial_i().device().id(), "printer");
+    }
+
+    #[test]
+    fn test_bos_startup() {
+        let mut gb = GameBoy::default();
+        gb.set_startup_memory(StartupMemory::Random);
+        gb.set_seed(1234);
+        gb.load(true).unwrap();
+        gb.load_rom_file("res/roms/demo/pocket.gb", None).unwrap();
+        let state = StateManager::save(&mut gb, Some(SaveStateFormat::Bos), None).unwrap();
+
+        let mut other = GameBoy::default();
+        other.load(true).unwrap();
+        other
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+        StateManager::load(&state, &mut other, Some(SaveStateFormat::Bos), None).unwrap();
+        assert_eq!(other.startup_memory(), StartupMemory::Random);
+        assert_eq!(other.seed(), Some(1234));
+
+        // the block is not available in BESS, keeping the configuration
+        let state = StateManager::save(&mut gb, Some(SaveStateFormat::Bess), None).unwrap();
+        let mut other = GameBoy::default();
+        other.load(true).unwrap();
+        other
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+        StateManager::load(&state, &mut other, Some(SaveStateFormat::Bess), None).unwrap();
+        assert_eq!(other.startup_memory(), StartupMemory::Hardware);
+        assert_eq!(other.seed(), None);
+    }
+
+    #[test]
+    fn test_device_state_versions() {
+        let mut gb = GameBoy::default();
+        gb.load(true).unwrap();
+        gb.load_rom_file("res/roms/test/firstwhite.gb", None)
+            .unwrap();
+        let options = FromGbOptions::default();
+
+        // the version of the schema is stored in the block header
+        let mut state = BosDeviceState::from_gb(&mut gb, GameBoyDevice::Dma, &options).unwrap();
+        assert_eq!(state.header.version, Dma::state_version());
+        let mut cursor = Cursor::new(vec![]);
+        state.write(&mut cursor).unwrap();
+        cursor.set_position(0);
+        let state = BosDeviceState::from_data(&mut cursor).unwrap();
+        assert_eq!(state.header.version, Dma::state_version());
+
+        // states of older versions are migrated on restore
+        gb.dma().set_stall_cycles(32);
+        let legacy = BosDeviceState::new(
+            GameBoyDevice::Dma,
+            StateFormat::Partial,
+            1,
+            state.state[..14].to_vec(),
+        );
+        legacy.to_gb(&mut gb, &ToGbOptions::default()).unwrap();
+        assert_eq!(gb.dma_i().stall_cycles(), 0);
+
+        // states of newer versions are rejected
+        let newer = BosDeviceState::new(
+            GameBoyDevice::Dma,
+            StateFormat::Partial,
+            Dma::state_version() + 1,
+            state.state.clone(),
+        );
+        assert!(newer.to_gb(&mut gb, &ToGbOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_printer_device_state() {
+        let mut printer = PrinterDevice::default();
+        for byte in [0x88, 0x33, 0x04, 0x00, 0x80, 0x02, 0xab] {
+            printer.receive(byte);
+        }
+        let state = printer.device_state().unwrap();
+
+        let mut other = PrinterDevice::default();
+        other.set_device_state(&state).unwrap();
+        assert_eq!(other.device_state().unwrap(), state);
+        assert_eq!(other.description(), "Printer [Data]");
+    }
+
+    #[test]
+    fn test_golden_states() {
+        // states generated by previous versions that must still be loaded
+        // by the current one, running the same number of frames after the
+        // load must produce the same frame buffer (behaviour hash)
+        for (state_path, rom_path, mode, pc, hash) in [
+            (
+                "res/states/pocket-v0.10.14.bos",
+                "res/roms/demo/pocket.gb",
+                GameBoyMode::Dmg,
+                0x025e,
+                0xd222879d,
+            ),
+            (
+                "res/states/pocket-v0.10.14.bosc",
+                "res/roms/demo/pocket.gb",
+                GameBoyMode::Dmg,
+                0x025e,
+                0xd222879d,
+            ),
+            (
+                "res/states/pocket-v0.10.14.bess",
+                "res/roms/demo/pocket.gb",
+                GameBoyMode::Dmg,
+                0x025e,
+                0xd222879d,
+            ),
+            (
+                "res/states/cgb_acid2-v0.10.14.bosc",
+                "res/roms/test/cgb_acid2.gbc",
+                GameBoyMode::Cgb,
+                0x0239,
+                0x8a3564b8,
+            ),
+        ] {
+            let mut gb = GameBoy::new(Some(mode));
+            gb.load(true).unwrap();
+            gb.load_rom_file(rom_path, None).unwrap();
+            StateManager::load_file(state_path, &mut gb, None, None).unwrap();
+            assert_eq!(gb.cpu_i().pc(), pc, "{state_path}");
+            for _ in 0..60 {
+                gb.next_frame();
+            }
+            assert_eq!(crc32(gb.frame_buffer()), hash, "{state_path}");
+        }
+    }
 }
@@ -0,0 +1,471 @@
+//! Storage paths for battery backed RAM and save states.
+//!
+//! Paths are described using templates (eg: `{saves_dir}/{rom_name}/{slot}.state`)
+//! with presets matching the layouts of other emulators, so that
+//! files can be imported from and exported to any of them.
+
+use boytacean_common::{
+    error::Error,
+    util::{read_file, write_file},
+};
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::create_dir_all,
+    path::{Path, PathBuf},
+};
+
+/// The placeholders supported in a path template.
+pub const PATH_PLACEHOLDERS: [&str; 4] = ["saves_dir", "rom_dir", "rom_name", "slot"];
+
+/// Characters that are replaced when rendering a ROM name
+/// as part of a path, to avoid escaping the target folder.
+const INVALID_CHARS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// The known save file layouts, named after the emulator
+/// that uses them by default.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SaveLayout {
+    /// Files stored next to the ROM (`{rom_name}.sav` and `{rom_name}.s0`).
+    #[default]
+    Boytacean,
+
+    /// Files stored in the saves folder (`{rom_name}.srm` and `{rom_name}.state0`).
+    RetroArch,
+
+    /// Files stored next to the ROM (`{rom_name}.sav` and `{rom_name}.ss0`).
+    Mgba,
+
+    /// Files stored in a per-ROM sub folder of the saves folder.
+    Folders,
+}
+
+impl SaveLayout {
+    pub const ALL: [SaveLayout; 4] = [
+        SaveLayout::Boytacean,
+        SaveLayout::RetroArch,
+        SaveLayout::Mgba,
+        SaveLayout::Folders,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            SaveLayout::Boytacean => "Boytacean",
+            SaveLayout::RetroArch => "RetroArch",
+            SaveLayout::Mgba => "mGBA",
+            SaveLayout::Folders => "Folders",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "boytacean" => Ok(SaveLayout::Boytacean),
+            "retroarch" => Ok(SaveLayout::RetroArch),
+            "mgba" => Ok(SaveLayout::Mgba),
+            "folders" => Ok(SaveLayout::Folders),
+            _ => Err(Error::CustomError(format!("Invalid save layout: {value}"))),
+        }
+    }
+
+    pub fn ram_template(&self) -> &'static str {
+        match self {
+            SaveLayout::Boytacean => "{rom_dir}/{rom_name}.sav",
+            SaveLayout::RetroArch => "{saves_dir}/{rom_name}.srm",
+            SaveLayout::Mgba => "{rom_dir}/{rom_name}.sav",
+            SaveLayout::Folders => "{saves_dir}/{rom_name}/{rom_name}.srm",
+        }
+    }
+
+    pub fn state_template(&self) -> &'static str {
+        match self {
+            SaveLayout::Boytacean => "{rom_dir}/{rom_name}.s{slot}",
+            SaveLayout::RetroArch => "{saves_dir}/{rom_name}.state{slot}",
+            SaveLayout::Mgba => "{rom_dir}/{rom_name}.ss{slot}",
+            SaveLayout::Folders => "{saves_dir}/{rom_name}/{slot}.state",
+        }
+    }
+}
+
+impl Display for SaveLayout {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// What to do when exporting to a path that already exists.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CollisionPolicy {
+    /// The existing file is replaced.
+    #[default]
+    Overwrite,
+
+    /// A numeric suffix is added to the file name (eg: `name-1.sav`)
+    /// until a free path is found.
+    Rename,
+
+    /// The export fails, leaving the existing file untouched.
+    Fail,
+}
+
+impl CollisionPolicy {
+    pub fn description(&self) -> &'static str {
+        match self {
+            CollisionPolicy::Overwrite => "Overwrite",
+            CollisionPolicy::Rename => "Rename",
+            CollisionPolicy::Fail => "Fail",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "overwrite" => Ok(CollisionPolicy::Overwrite),
+            "rename" => Ok(CollisionPolicy::Rename),
+            "fail" => Ok(CollisionPolicy::Fail),
+            _ => Err(Error::CustomError(format!(
+                "Invalid collision policy: {value}"
+            ))),
+        }
+    }
+
+    /// Resolves the path to be used for writing according to
+    /// the policy, taking into account the existing files.
+    pub fn resolve(&self, path: &Path) -> Result<PathBuf, Error> {
+        if !path.exists() {
+            return Ok(path.to_path_buf());
+        }
+        match self {
+            CollisionPolicy::Overwrite => Ok(path.to_path_buf()),
+            CollisionPolicy::Rename => {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                let ext = path.extension().map(|ext| ext.to_string_lossy());
+                let mut index = 0_usize;
+                loop {
+                    index += 1;
+                    let name = match &ext {
+                        Some(ext) => format!("{stem}-{index}.{ext}"),
+                        None => format!("{stem}-{index}"),
+                    };
+                    let candidate = path.with_file_name(name);
+                    if !candidate.exists() {
+                        return Ok(candidate);
+                    }
+                }
+            }
+            CollisionPolicy::Fail => Err(Error::CustomError(format!(
+                "File already exists: {}",
+                path.display()
+            ))),
+        }
+    }
+}
+
+impl Display for CollisionPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// Template used to build a file path, validated on creation so
+/// that unknown or unterminated placeholders are caught early.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PathTemplate {
+    template: String,
+}
+
+impl PathTemplate {
+    pub fn new(template: &str) -> Result<Self, Error> {
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..].find('}').ok_or_else(|| {
+                Error::InvalidParameter(format!("Unterminated placeholder in: {template}"))
+            })?;
+            let name = &rest[start + 1..start + end];
+            if !PATH_PLACEHOLDERS.contains(&name) {
+                return Err(Error::InvalidParameter(format!(
+                    "Unknown placeholder {{{name}}} in: {template}"
+                )));
+            }
+            rest = &rest[start + end + 1..];
+        }
+        Ok(Self {
+            template: String::from(template),
+        })
+    }
+
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// Renders the template for the provided ROM, the `{saves_dir}`
+    /// placeholder falls back to the ROM directory when not set.
+    pub fn render(&self, rom_path: &str, saves_dir: Option<&str>, slot: u8) -> PathBuf {
+        let rom_path = Path::new(rom_path);
+        let rom_dir = rom_path
+            .parent()
+            .map(|dir| dir.to_string_lossy().to_string())
+            .filter(|dir| !dir.is_empty())
+            .unwrap_or_else(|| String::from("."));
+        let rom_name = rom_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .replace(INVALID_CHARS, "_");
+        PathBuf::from(
+            self.template
+                .replace("{saves_dir}", saves_dir.unwrap_or(&rom_dir))
+                .replace("{rom_dir}", &rom_dir)
+                .replace("{rom_name}", &rom_name)
+                .replace("{slot}", &slot.to_string()),
+        )
+    }
+}
+
+impl Display for PathTemplate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.template)
+    }
+}
+
+/// Resolves the storage paths of the battery backed RAM and of
+/// the save states of a ROM, using the configured templates.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SavePaths {
+    ram_template: PathTemplate,
+    state_template: PathTemplate,
+    saves_dir: Option<String>,
+}
+
+impl SavePaths {
+    pub fn new(ram_template: &str, state_template: &str) -> Result<Self, Error> {
+        Ok(Self {
+            ram_template: PathTemplate::new(ram_template)?,
+            state_template: PathTemplate::new(state_template)?,
+            saves_dir: None,
+        })
+    }
+
+    pub fn from_layout(layout: SaveLayout) -> Self {
+        Self::new(layout.ram_template(), layout.state_template()).unwrap()
+    }
+
+    pub fn ram_template(&self) -> &PathTemplate {
+        &self.ram_template
+    }
+
+    pub fn set_ram_template(&mut self, template: &str) -> Result<(), Error> {
+        self.ram_template = PathTemplate::new(template)?;
+        Ok(())
+    }
+
+    pub fn state_template(&self) -> &PathTemplate {
+        &self.state_template
+    }
+
+    pub fn set_state_template(&mut self, template: &str) -> Result<(), Error> {
+        self.state_template = PathTemplate::new(template)?;
+        Ok(())
+    }
+
+    pub fn saves_dir(&self) -> Option<&str> {
+        self.saves_dir.as_deref()
+    }
+
+    pub fn set_saves_dir(&mut self, saves_dir: Option<&str>) {
+        self.saves_dir = saves_dir.map(String::from);
+    }
+
+    pub fn ram_path(&self, rom_path: &str) -> PathBuf {
+        self.ram_template
+            .render(rom_path, self.saves_dir.as_deref(), 0)
+    }
+
+    pub fn state_path(&self, rom_path: &str, slot: u8) -> PathBuf {
+        self.state_template
+            .render(rom_path, self.saves_dir.as_deref(), slot)
+    }
+
+    /// Finds an existing RAM file for the ROM, looking first at
+    /// the configured path and then at the paths of every known
+    /// layout, allowing saves from other emulators to be picked up.
+    pub fn find_ram(&self, rom_path: &str) -> Option<PathBuf> {
+        let saves_dir = self.saves_dir.as_deref();
+        std::iter::once(self.ram_path(rom_path))
+            .chain(SaveLayout::ALL.iter().map(|layout| {
+                PathTemplate::new(layout.ram_template())
+                    .unwrap()
+                    .render(rom_path, saves_dir, 0)
+            }))
+            .find(|path| path.is_file())
+    }
+
+    /// Imports the RAM contents of the ROM from any of the known
+    /// layouts, returning the path it has been read from.
+    pub fn import_ram(&self, rom_path: &str) -> Result<Option<(PathBuf, Vec<u8>)>, Error> {
+        match self.find_ram(rom_path) {
+            Some(path) => {
+                let data = read_file(&path.to_string_lossy())?;
+                Ok(Some((path, data)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Exports the RAM contents of the ROM to the configured path,
+    /// creating the parent folders as required.
+    pub fn export_ram(
+        &self,
+        rom_path: &str,
+        data: &[u8],
+        policy: CollisionPolicy,
+    ) -> Result<PathBuf, Error> {
+        Self::export(&self.ram_path(rom_path), data, policy)
+    }
+
+    /// Exports a save state of the ROM to the configured path
+    /// for the slot, creating the parent folders as required.
+    pub fn export_state(
+        &self,
+        rom_path: &str,
+        slot: u8,
+        data: &[u8],
+        policy: CollisionPolicy,
+    ) -> Result<PathBuf, Error> {
+        Self::export(&self.state_path(rom_path, slot), data, policy)
+    }
+
+    fn export(path: &Path, data: &[u8], policy: CollisionPolicy) -> Result<PathBuf, Error> {
+        let path = policy.resolve(path)?;
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            create_dir_all(parent).map_err(|_| {
+                Error::CustomError(format!("Failed to create folder: {}", parent.display()))
+            })?;
+        }
+        write_file(&path.to_string_lossy(), data, None)?;
+        Ok(path)
+    }
+}
+
+impl Default for SavePaths {
+    fn default() -> Self {
+        Self::from_layout(SaveLayout::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs::remove_dir_all, path::PathBuf};
+
+    use super::{CollisionPolicy, PathTemplate, SaveLayout, SavePaths};
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = temp_dir().join(format!("boytacean-storage-{name}-{}", std::process::id()));
+        let _ = remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_template() {
+        let template = PathTemplate::new("{saves_dir}/{rom_name}/{slot}.state").unwrap();
+        assert_eq!(
+            template.render("roms/tetris.gb", Some("saves"), 3),
+            PathBuf::from("saves/tetris/3.state")
+        );
+        assert_eq!(
+            template.render("tetris.gb", None, 0),
+            PathBuf::from("./tetris/0.state")
+        );
+        assert_eq!(
+            PathTemplate::new("{rom_name}.srm")
+                .unwrap()
+                .render("roms/a:b.gb", None, 0),
+            PathBuf::from("a_b.srm")
+        );
+        assert!(PathTemplate::new("{rom}.sav").is_err());
+        assert!(PathTemplate::new("{rom_name.sav").is_err());
+    }
+
+    #[test]
+    fn test_layouts() {
+        let mut paths = SavePaths::from_layout(SaveLayout::RetroArch);
+        paths.set_saves_dir(Some("saves"));
+        assert_eq!(
+            paths.ram_path("roms/tetris.gb"),
+            PathBuf::from("saves/tetris.srm")
+        );
+        assert_eq!(
+            paths.state_path("roms/tetris.gb", 1),
+            PathBuf::from("saves/tetris.state1")
+        );
+        let paths = SavePaths::default();
+        assert_eq!(
+            paths.ram_path("roms/tetris.gb"),
+            PathBuf::from("roms/tetris.sav")
+        );
+        assert_eq!(
+            paths.state_path("roms/tetris.gb", 2),
+            PathBuf::from("roms/tetris.s2")
+        );
+    }
+
+    #[test]
+    fn test_import_export() {
+        let dir = test_dir("import");
+        let rom_path = dir.join("tetris.gb").to_string_lossy().to_string();
+        let saves_dir = dir.join("saves").to_string_lossy().to_string();
+
+        let mut retroarch = SavePaths::from_layout(SaveLayout::RetroArch);
+        retroarch.set_saves_dir(Some(&saves_dir));
+        retroarch
+            .export_ram(&rom_path, &[0x01, 0x02], CollisionPolicy::Overwrite)
+            .unwrap();
+
+        let mut folders = SavePaths::from_layout(SaveLayout::Folders);
+        folders.set_saves_dir(Some(&saves_dir));
+        let (path, data) = folders.import_ram(&rom_path).unwrap().unwrap();
+        assert_eq!(path, retroarch.ram_path(&rom_path));
+        assert_eq!(data, vec![0x01, 0x02]);
+
+        let path = folders
+            .export_ram(&rom_path, &data, CollisionPolicy::Overwrite)
+            .unwrap();
+        assert_eq!(path, folders.ram_path(&rom_path));
+        assert!(path.is_file());
+        assert_eq!(folders.find_ram(&rom_path), Some(path));
+
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_collision() {
+        let dir = test_dir("collision");
+        let rom_path = dir.join("tetris.gb").to_string_lossy().to_string();
+        let paths = SavePaths::default();
+
+        let path = paths
+            .export_state(&rom_path, 0, &[0x01], CollisionPolicy::Fail)
+            .unwrap();
+        assert_eq!(path, dir.join("tetris.s0"));
+        assert!(paths
+            .export_state(&rom_path, 0, &[0x02], CollisionPolicy::Fail)
+            .is_err());
+
+        let renamed = paths
+            .export_state(&rom_path, 0, &[0x02], CollisionPolicy::Rename)
+            .unwrap();
+        assert_eq!(renamed, dir.join("tetris-1.s0"));
+        let renamed = paths
+            .export_state(&rom_path, 0, &[0x03], CollisionPolicy::Rename)
+            .unwrap();
+        assert_eq!(renamed, dir.join("tetris-2.s0"));
+
+        let path = paths
+            .export_state(&rom_path, 0, &[0x04], CollisionPolicy::Overwrite)
+            .unwrap();
+        assert_eq!(path, dir.join("tetris.s0"));
+        assert_eq!(std::fs::read(&path).unwrap(), vec![0x04]);
+
+        remove_dir_all(&dir).unwrap();
+    }
+}
@@ -13,6 +13,12 @@
 //! based serial protocol that can be used to communicate with the emulator
 //! and obtain the results of the tests.
 //!
+//! The frame buffer can also be compared against golden (reference)
+//! images or hashes, with an adjustable tolerance, using either
+//! [`run_golden_test`] or the [`golden_test!`](crate::golden_test) macro.
+//! Setting the `BOYTACEAN_BLESS` environment variable (re)writes the
+//! golden images with the current output instead of comparing them.
+//!
 //! # Examples
 //!
 //! Runs the CPU instruction tests from the Blargg's test ROMs and
@@ -33,15 +39,29 @@
 //! assert_eq!(game_boy.rom_i().gb_mode(), GameBoyMode::Cgb);
 //! ```
 
-use boytacean_common::error::Error;
+use boytacean_common::{
+    error::Error,
+    util::{read_file, write_file},
+};
+use boytacean_hashing::crc32::crc32;
+use std::{
+    env,
+    fmt::{self, Display, Formatter},
+};
 
 use crate::{
+    capture::{decode_png, encode_png},
+    cpu::CpuBackend,
     data::BootRom,
     devices::buffer::BufferDevice,
     gb::{GameBoy, GameBoyMode},
-    ppu::FRAME_BUFFER_SIZE,
+    ppu::{DISPLAY_HEIGHT, DISPLAY_WIDTH, FRAME_BUFFER_SIZE},
 };
 
+/// The environment variable that when set makes the golden
+/// tests write their reference images instead of comparing them.
+pub const BLESS_ENV: &str = "BOYTACEAN_BLESS";
+
 #[derive(Default)]
 pub struct TestOptions {
     pub mode: Option<GameBoyMode>,
@@ -50,6 +70,7 @@ pub struct TestOptions {
     pub dma_enabled: Option<bool>,
     pub timer_enabled: Option<bool>,
     pub boot_rom: Option<BootRom>,
+    pub cpu_backend: Option<CpuBackend>,
 }
 
 pub fn build_test(options: TestOptions) -> Box<GameBoy> {
@@ -59,12 +80,23 @@ pub fn build_test(options: TestOptions) -> Box<GameBoy> {
     game_boy.set_apu_enabled(options.apu_enabled.unwrap_or(true));
     game_boy.set_dma_enabled(options.dma_enabled.unwrap_or(true));
     game_boy.set_timer_enabled(options.timer_enabled.unwrap_or(true));
+    game_boy.set_cpu_backend(options.cpu_backend.unwrap_or_default());
     game_boy.attach_serial(device);
     game_boy.load(false).unwrap();
     game_boy.load_boot_smart(options.boot_rom).unwrap();
     game_boy
 }
 
+/// Builds a system with the pocket demo ROM loaded, for the tests
+/// that only need a running system (eg: debugger and netplay).
+pub fn build_pocket_test() -> Box<GameBoy> {
+    let mut game_boy = build_test(TestOptions::default());
+    game_boy
+        .load_rom_file("res/roms/demo/pocket.gb", None)
+        .unwrap();
+    game_boy
+}
+
 pub fn run_test(
     rom_path: &str,
     max_cycles: Option<u64>,
@@ -106,21 +138,277 @@ pub fn run_image_test(
     Ok((*game_boy.frame_buffer(), game_boy))
 }
 
+pub fn run_frames_test(
+    rom_path: &str,
+    frames: u32,
+    options: TestOptions,
+) -> Result<Box<GameBoy>, Error> {
+    let mut game_boy = build_test(options);
+    game_boy.load_rom_file(rom_path, None)?;
+    for _ in 0..frames {
+        game_boy.next_frame();
+    }
+    Ok(game_boy)
+}
+
+/// The reference a frame buffer is compared against.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Golden {
+    /// Path to a PNG image with the expected frame.
+    Image(String),
+
+    /// The CRC32 of the expected (RGB) frame buffer, only
+    /// exact matches are accepted.
+    Crc32(u32),
+}
+
+impl Golden {
+    pub fn image(path: &str) -> Self {
+        Golden::Image(String::from(path))
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            Golden::Image(path) => format!("image {path}"),
+            Golden::Crc32(crc) => format!("CRC32 0x{crc:08x}"),
+        }
+    }
+}
+
+impl Display for Golden {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+impl From<&str> for Golden {
+    fn from(value: &str) -> Self {
+        Golden::image(value)
+    }
+}
+
+impl From<u32> for Golden {
+    fn from(value: u32) -> Self {
+        Golden::Crc32(value)
+    }
+}
+
+/// The differences allowed between a frame and its golden
+/// image, the default only accepts exact matches.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Tolerance {
+    /// Maximum difference allowed for each of the color
+    /// channels of a pixel for it to be considered equal.
+    pub channel: u8,
+
+    /// Number of (different) pixels allowed.
+    pub pixels: usize,
+}
+
+impl Tolerance {
+    pub fn new(channel: u8, pixels: usize) -> Self {
+        Self { channel, pixels }
+    }
+}
+
+/// Result of the pixel by pixel comparison of two frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameDiff {
+    /// Number of pixels that differ beyond the channel tolerance.
+    pub pixels: usize,
+
+    /// Maximum difference found in any of the color channels.
+    pub max_delta: u8,
+
+    /// Coordinates (x, y) of the first different pixel.
+    pub first: Option<(usize, usize)>,
+}
+
+impl FrameDiff {
+    pub fn is_within(&self, tolerance: &Tolerance) -> bool {
+        self.pixels <= tolerance.pixels
+    }
+}
+
+impl Display for FrameDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} different pixels (max delta {})",
+            self.pixels, self.max_delta
+        )?;
+        if let Some((x, y)) = self.first {
+            write!(f, ", first at ({x}, {y})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares two RGB888 frames of the display size, pixel by pixel,
+/// ignoring the channel differences within the tolerance.
+pub fn compare_frames(frame: &[u8], expected: &[u8], tolerance: &Tolerance) -> FrameDiff {
+    let mut diff = FrameDiff::default();
+    for (index, (pixel, expected)) in frame.chunks(3).zip(expected.chunks(3)).enumerate() {
+        let delta = pixel
+            .iter()
+            .zip(expected)
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap_or(0);
+        diff.max_delta = diff.max_delta.max(delta);
+        if delta > tolerance.channel {
+            diff.pixels += 1;
+            diff.first
+                .get_or_insert((index % DISPLAY_WIDTH, index / DISPLAY_WIDTH));
+        }
+    }
+    diff
+}
+
+/// Checks the frame against the golden reference, returning an
+/// error describing the differences in case it does not match.
+///
+/// In case the bless environment variable is set (see [`BLESS_ENV`])
+/// the golden image is written with the frame instead.
+pub fn check_golden(frame: &[u8], golden: &Golden, tolerance: &Tolerance) -> Result<(), Error> {
+    match golden {
+        Golden::Image(path) => {
+            if env::var_os(BLESS_ENV).is_some() {
+                let data = encode_png(frame, DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32);
+                return write_file(path, &data, None);
+            }
+            let (expected, width, height) = decode_png(&read_file(path)?)?;
+            if (width as usize, height as usize) != (DISPLAY_WIDTH, DISPLAY_HEIGHT) {
+                return Err(Error::CustomError(format!(
+                    "Golden image {path} is {width}x{height}, expected {DISPLAY_WIDTH}x{DISPLAY_HEIGHT}"
+                )));
+            }
+            let diff = compare_frames(frame, &expected, tolerance);
+            if !diff.is_within(tolerance) {
+                return Err(Error::CustomError(format!(
+                    "Frame does not match {golden}: {diff}"
+                )));
+            }
+        }
+        Golden::Crc32(crc) => {
+            let frame_crc = crc32(frame);
+            if frame_crc != *crc {
+                return Err(Error::CustomError(format!(
+                    "Frame does not match {golden}: got 0x{frame_crc:08x}"
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs the ROM for the number of frames and checks the resulting
+/// frame buffer against the golden reference.
+pub fn run_golden_test(
+    rom_path: &str,
+    frames: u32,
+    golden: &Golden,
+    tolerance: &Tolerance,
+    options: TestOptions,
+) -> Result<Box<GameBoy>, Error> {
+    let mut game_boy = run_frames_test(rom_path, frames, options)?;
+    check_golden(game_boy.frame_buffer(), golden, tolerance)?;
+    Ok(game_boy)
+}
+
+/// Declares a test that runs a ROM for a number of frames and
+/// compares the frame buffer against a golden image (path) or hash.
+///
+/// ```rust,ignore
+/// golden_test!(test_dmg_acid2, "res/roms/test/dmg_acid2.gb", 60, "res/roms/test/golden/dmg_acid2.png");
+/// golden_test!(test_cgb_acid2, "res/roms/test/cgb_acid2.gbc", 60, 0x12345678, mode = GameBoyMode::Cgb);
+/// golden_test!(test_pocket, "res/roms/demo/pocket.gb", 120, "pocket.png", tolerance = Tolerance::new(8, 16));
+/// ```
+#[macro_export]
+macro_rules! golden_test {
+    ($name:ident, $rom:expr, $frames:expr, $golden:expr $(, mode = $mode:expr)? $(, tolerance = $tolerance:expr)? $(,)?) => {
+        #[test]
+        fn $name() {
+            #[allow(unused_mut)]
+            let mut options = $crate::test::TestOptions::default();
+            $(options.mode = Some($mode);)?
+            #[allow(unused_mut)]
+            let mut tolerance = $crate::test::Tolerance::default();
+            $(tolerance = $tolerance;)?
+            let golden = $crate::test::Golden::from($golden);
+            if let Err(err) =
+                $crate::test::run_golden_test($rom, $frames, &golden, &tolerance, options)
+            {
+                panic!("{}", err);
+            }
+        }
+    };
+}
+
 #[cfg(test)]
 mod tests {
+    use boytacean_hashing::crc32::crc32;
+    use std::{
+        collections::HashSet,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+    };
+
     use crate::{
+        apu::{HighPassFilter, Resampler},
+        cheats::search::{CheatSearch, SearchOperator, SearchSize, SEARCH_MEMORY_SIZE},
+        color::RGB_SIZE,
         consts::{
-            BGP_ADDR, DIV_ADDR, DMA_ADDR, IF_ADDR, LCDC_ADDR, LYC_ADDR, LY_ADDR, OBP0_ADDR,
-            OBP1_ADDR, SCX_ADDR, SCY_ADDR, STAT_ADDR, TAC_ADDR, TIMA_ADDR, TMA_ADDR, WX_ADDR,
-            WY_ADDR,
+            BGP_ADDR, DIV_ADDR, DMA_ADDR, IF_ADDR, LCDC_ADDR, LYC_ADDR, LY_ADDR, NR11_ADDR,
+            NR12_ADDR, NR14_ADDR, NR52_ADDR, OBP0_ADDR, OBP1_ADDR, SCX_ADDR, SCY_ADDR, STAT_ADDR,
+            SVBK_ADDR, TAC_ADDR, TIMA_ADDR, TMA_ADDR, WX_ADDR, WY_ADDR,
         },
+        cpu::CpuBackend,
         data::BootRom,
-        gb::GameBoyMode,
+        gb::{
+            AccuracyProfile, AudioProvider, ClockDomain, ConfigChange, GameBoy, GameBoyMode,
+            GameBoyModel, GameBoySpeed, StartupMemory, SyncPolicy,
+        },
         licensee::Licensee,
+        movie::MovieMode,
+        pad::PadKey,
+        ppu::{RenderMode, DISPLAY_HEIGHT, DISPLAY_WIDTH, FRAME_BUFFER_SIZE},
         rom::{RamSize, Region, RomSize},
+        sgb::FRAME_BUFFER_SGB_SIZE,
+        state::{SaveStateFormat, StateManager},
+        video::filters::{VideoFilter, VideoOverlay},
+    };
+
+    use super::{
+        build_test, check_golden, compare_frames, run_golden_test, run_serial_test, run_step_test,
+        Golden, TestOptions, Tolerance,
     };
 
-    use super::{run_serial_test, run_step_test, TestOptions};
+    golden_test!(
+        test_golden_dmg_acid2,
+        "res/roms/test/dmg_acid2.gb",
+        300,
+        "res/roms/test/golden/dmg_acid2.png",
+        mode = GameBoyMode::Dmg
+    );
+
+    golden_test!(
+        test_golden_cgb_acid2,
+        "res/roms/test/cgb_acid2.gbc",
+        300,
+        "res/roms/test/golden/cgb_acid2.png",
+        mode = GameBoyMode::Cgb
+    );
+
+    golden_test!(
+        test_golden_sprite_priority,
+        "res/roms/test/sprite_priority.gb",
+        300,
+        "res/roms/test/golden/sprite_priority.png",
+        mode = GameBoyMode::Dmg
+    );
 
     #[test]
     fn test_boot_state() {
@@ -181,6 +469,22 @@ mod tests {
         assert!(game_boy.rom_i().valid_checksum());
     }
 
+    #[test]
+    fn test_blargg_cpu_instrs_cached() {
+        let (result, game_boy) = run_serial_test(
+            "res/roms/test/blargg/cpu/cpu_instrs.gb",
+            Some(300000000),
+            TestOptions {
+                cpu_backend: Some(CpuBackend::Cached),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(result, "cpu_instrs\n\n01:ok  02:ok  03:ok  04:ok  05:ok  06:ok  07:ok  08:ok  09:ok  10:ok  11:ok  \n\nPassed all tests\n");
+        assert_eq!(game_boy.cpu_backend(), CpuBackend::Cached);
+        assert!(game_boy.cpu_i().blocks().decoded() > 0);
+    }
+
     #[test]
     fn test_blargg_instr_timing() {
         let (result, game_boy) = run_serial_test(
@@ -198,4 +502,1005 @@ mod tests {
         assert_eq!(game_boy.rom_i().ram_size(), RamSize::NoRam);
         assert!(game_boy.rom_i().valid_checksum());
     }
+
+    #[test]
+    fn test_av_stats() {
+        let mut game_boy = GameBoy::default();
+        game_boy.load(true).unwrap();
+        game_boy
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+
+        for index in 0..10 {
+            game_boy.next_frame();
+            game_boy.take_audio_frame();
+            let frame_index = game_boy.ppu_frame();
+            if index != 4 {
+                game_boy.present_frame(frame_index);
+            }
+        }
+        let frame_index = game_boy.ppu_frame();
+        game_boy.present_frame(frame_index);
+
+        let stats = game_boy.av_stats();
+        assert_eq!(stats.video_frames, 10);
+        assert_eq!(stats.video_presented, 10);
+        assert_eq!(stats.video_dropped, 1);
+        assert_eq!(stats.video_duplicated, 1);
+        assert_eq!(stats.audio_taken, 10);
+        assert!(stats.audio_frames >= stats.audio_taken);
+        assert_eq!(stats.audio_overruns, 0);
+        assert!(stats.has_issues());
+
+        game_boy.report_audio_underrun();
+        game_boy.report_audio_overrun(128);
+        let delta = game_boy.av_stats().delta(&stats);
+        assert_eq!(delta.audio_underruns, 1);
+        assert_eq!(delta.audio_overruns, 1);
+        assert_eq!(delta.audio_dropped_samples, 128);
+        assert_eq!(delta.video_frames, 0);
+
+        game_boy.reset_av_stats();
+        assert!(!game_boy.av_stats().has_issues());
+    }
+
+    #[test]
+    fn test_av_stats_reset() {
+        let mut game_boy = GameBoy::default();
+        game_boy.load(true).unwrap();
+        game_boy
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+
+        for _ in 0..10 {
+            game_boy.next_frame();
+            game_boy.take_audio_frame();
+            let frame_index = game_boy.ppu_frame();
+            game_boy.present_frame(frame_index);
+        }
+        game_boy.report_audio_underrun();
+        let stats = game_boy.av_stats();
+        assert_eq!(stats.video_frames, 10);
+
+        game_boy.reset();
+        game_boy.load(true).unwrap();
+        game_boy
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+        game_boy.next_frame();
+        let delta = game_boy.av_stats().delta(&stats);
+        assert_eq!(delta.video_frames, 0);
+        assert_eq!(delta.video_presented, 0);
+        assert_eq!(delta.audio_taken, 0);
+        assert_eq!(delta.audio_underruns, 0);
+        assert!(!delta.has_issues());
+    }
+
+    #[test]
+    fn test_audio_batch_registers() {
+        // polls NR52 in a loop (LDH A,(0x26); JR -4) until the length
+        // timer of channel 1 expires, returning the cycles it took
+        let poll = |batch: bool| {
+            let mut game_boy = build_test(TestOptions::default());
+            game_boy
+                .load_rom_file("res/roms/demo/pocket.gb", None)
+                .unwrap();
+            game_boy.step_to(0x0100);
+            game_boy.set_audio_batch(batch);
+            for (index, byte) in [0xf0, 0x26, 0x18, 0xfc].into_iter().enumerate() {
+                game_boy.mmu().write(0xc000 + index as u16, byte);
+            }
+            game_boy.mmu().write(NR52_ADDR, 0x80);
+            game_boy.mmu().write(NR12_ADDR, 0xf0);
+            game_boy.mmu().write(NR11_ADDR, 0x3e);
+            game_boy.mmu().write(NR14_ADDR, 0xc0);
+            game_boy.cpu().set_pc(0xc000);
+            game_boy.cpu().a = 0xff;
+
+            let mut cycles = 0u32;
+            while game_boy.cpu_i().a & 0x01 == 0x01 {
+                cycles += game_boy.clock() as u32;
+                assert!(cycles < GameBoy::LCD_CYCLES);
+            }
+            cycles
+        };
+
+        // the value read by the CPU in batch mode is the same
+        // as the one read with the step by step clock
+        assert_eq!(poll(true), poll(false));
+    }
+
+    #[test]
+    fn test_av_callbacks() {
+        static FRAMES: AtomicUsize = AtomicUsize::new(0);
+        static SAMPLES: AtomicUsize = AtomicUsize::new(0);
+
+        let mut game_boy = GameBoy::default();
+        game_boy.load(true).unwrap();
+        game_boy
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+        game_boy.set_frame_callback(|frame_buffer| {
+            assert_eq!(frame_buffer.len(), FRAME_BUFFER_SIZE);
+            FRAMES.fetch_add(1, Ordering::Relaxed);
+        });
+        game_boy.set_audio_callback(|samples| {
+            SAMPLES.fetch_add(samples.len(), Ordering::Relaxed);
+        });
+
+        // the audio frames are consumed by the audio callback so
+        // they are no longer available to be polled
+        for _ in 0..10 {
+            game_boy.next_frame();
+            assert!(game_boy.take_audio_frame().is_none());
+        }
+        assert_eq!(FRAMES.load(Ordering::Relaxed), 10);
+        assert!(SAMPLES.load(Ordering::Relaxed) > 0);
+
+        game_boy.clear_frame_callback();
+        game_boy.clear_audio_callback();
+        game_boy.next_frame();
+        assert_eq!(FRAMES.load(Ordering::Relaxed), 10);
+        assert!(game_boy.take_audio_frame().is_some());
+    }
+
+    #[test]
+    fn test_video_filter() {
+        let mut game_boy = GameBoy::default();
+        game_boy.load(true).unwrap();
+        game_boy
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+        for _ in 0..120 {
+            game_boy.next_frame();
+        }
+        assert_eq!(game_boy.video_size(), (DISPLAY_WIDTH, DISPLAY_HEIGHT));
+        let frame_buffer = game_boy.frame_buffer().to_vec();
+        assert_eq!(game_boy.video_frame(), &frame_buffer[..]);
+
+        // the ghosting blends the frames while the frame buffer
+        // of the PPU is kept untouched
+        game_boy.set_video_filter(VideoFilter::ghosting(1.0));
+        let video_frame = game_boy.video_frame().to_vec();
+        game_boy.next_frame();
+        assert_eq!(game_boy.video_frame(), &video_frame[..]);
+
+        game_boy.set_video_filter(VideoFilter::new(0.0, VideoOverlay::Grid));
+        game_boy.next_frame();
+        let (width, height) = game_boy.video_size();
+        assert_eq!((width, height), (DISPLAY_WIDTH * 3, DISPLAY_HEIGHT * 3));
+        assert_eq!(game_boy.video_frame().len(), width * height * RGB_SIZE);
+
+        game_boy.set_video_filter(VideoFilter::default());
+        assert_eq!(game_boy.video_filter(), VideoFilter::default());
+        let frame_buffer = game_boy.frame_buffer().to_vec();
+        assert_eq!(game_boy.video_frame(), &frame_buffer[..]);
+    }
+
+    #[test]
+    fn test_sgb_mode() {
+        let mut game_boy = GameBoy::new(Some(GameBoyMode::Sgb));
+        game_boy.load(true).unwrap();
+        game_boy
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+        for _ in 0..240 {
+            game_boy.next_frame();
+        }
+        assert!(game_boy.pad_i().sgb());
+        assert!(!game_boy.mmu_i().boot_active());
+
+        let buffer = game_boy.frame_buffer_sgb().unwrap();
+        assert_eq!(buffer.len(), FRAME_BUFFER_SGB_SIZE);
+
+        let mut game_boy = GameBoy::new(Some(GameBoyMode::Dmg));
+        game_boy.load(true).unwrap();
+        assert!(!game_boy.pad_i().sgb());
+        assert!(game_boy.frame_buffer_sgb().is_none());
+    }
+
+    #[test]
+    fn test_acid2_priority() {
+        // the fast renderer must produce exactly the same output as the
+        // reference one (pixel by pixel) for the priority test ROMs, in
+        // all of the modes that they're able to run on (DMG on CGB as well)
+        for (rom_path, mode) in [
+            ("res/roms/test/dmg_acid2.gb", GameBoyMode::Dmg),
+            ("res/roms/test/dmg_acid2.gb", GameBoyMode::Cgb),
+            ("res/roms/test/sprite_priority.gb", GameBoyMode::Dmg),
+            ("res/roms/test/sprite_priority.gb", GameBoyMode::Cgb),
+            ("res/roms/test/cgb_acid2.gbc", GameBoyMode::Cgb),
+        ] {
+            let mut game_boy = GameBoy::new(Some(mode));
+            game_boy.load(true).unwrap();
+            game_boy.load_rom_file(rom_path, None).unwrap();
+            game_boy.set_compare_render(true);
+            for _ in 0..300 {
+                game_boy.next_frame();
+            }
+            assert_eq!(
+                game_boy.ppu_i().mismatched_frames(),
+                0,
+                "{rom_path} ({mode})"
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_up_to_line() {
+        let mut game_boy = GameBoy::new(Some(GameBoyMode::Dmg));
+        game_boy.load(true).unwrap();
+        game_boy
+            .load_rom_file("res/roms/test/sprite_priority.gb", None)
+            .unwrap();
+        for _ in 0..300 {
+            game_boy.next_frame();
+        }
+        let frame = game_boy.frame_buffer().to_vec();
+        assert_eq!(game_boy.render_up_to_line_eager(144), frame);
+
+        // a palette change is only visible on the lines after the split
+        let bgp = game_boy.ppu().read(BGP_ADDR);
+        game_boy.ppu().write(BGP_ADDR, bgp ^ 0xff);
+        let split = game_boy.render_up_to_line_eager(72);
+        let offset = 72 * DISPLAY_WIDTH * 3;
+        assert_eq!(split.len(), frame.len());
+        assert_eq!(split[..offset], frame[..offset]);
+        assert_ne!(split[offset..], frame[offset..]);
+    }
+
+    #[test]
+    fn test_capture() {
+        let mut game_boy = GameBoy::default();
+        game_boy.load(true).unwrap();
+        game_boy
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+        assert!(game_boy.stop_recording().is_empty());
+
+        game_boy.start_recording();
+        for _ in 0..120 {
+            game_boy.next_frame();
+        }
+        assert!(game_boy.is_recording());
+        let apng = game_boy.stop_recording();
+        assert!(!game_boy.is_recording());
+        assert_eq!(apng[..8], [0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a]);
+        assert_eq!(&apng[37..41], b"acTL");
+
+        let png = game_boy.screenshot_png();
+        assert_eq!(png[..8], [0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a]);
+        assert_eq!(&png[12..16], b"IHDR");
+    }
+
+    #[test]
+    fn test_movie() {
+        let mut game_boy = GameBoy::default();
+        game_boy.load(true).unwrap();
+        game_boy
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+        for _ in 0..60 {
+            game_boy.next_frame();
+        }
+
+        // the inputs are provided mid-frame (between instructions)
+        // and latched by the movie at the next frame boundary
+        game_boy.start_movie_recording().unwrap();
+        for frame in 0..300 {
+            game_boy.clock();
+            match frame {
+                100 | 200 => game_boy.key_press(PadKey::Start),
+                110 | 210 => game_boy.key_lift(PadKey::Start),
+                150 => game_boy.key_press(PadKey::A),
+                160 => game_boy.key_lift(PadKey::A),
+                _ => (),
+            }
+            game_boy.next_frame();
+        }
+        let frame_buffer = game_boy.frame_buffer().to_vec();
+        let pc = game_boy.cpu_i().pc();
+
+        let movie = game_boy.stop_movie().unwrap();
+        assert_eq!(movie.frames(), 301);
+        assert_eq!(
+            movie.inputs.iter().filter(|keys| **keys == 0x10).count(),
+            20
+        );
+        assert_eq!(
+            movie.inputs.iter().filter(|keys| **keys == 0x40).count(),
+            10
+        );
+
+        game_boy.play_movie_data(movie.clone()).unwrap();
+        for _ in 0..300 {
+            game_boy.key_press(PadKey::B);
+            game_boy.next_frame();
+        }
+        assert_eq!(game_boy.frame_buffer().to_vec(), frame_buffer);
+        assert_eq!(game_boy.cpu_i().pc(), pc);
+        assert_eq!(game_boy.movie().unwrap().mode(), MovieMode::Playing);
+        game_boy.next_frame();
+        assert_eq!(game_boy.movie().unwrap().mode(), MovieMode::Finished);
+
+        let mut other = movie;
+        other.rom_crc32 ^= 1;
+        assert!(game_boy.play_movie_data(other).is_err());
+    }
+
+    #[test]
+    fn test_watchdog() {
+        let mut game_boy = GameBoy::default();
+        game_boy.load(true).unwrap();
+        game_boy
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+        // the boot ROM keeps the LCD off for more than five frames
+        // which must not trigger the watchdog
+        assert!(game_boy.next_frame() > GameBoy::LCD_CYCLES * 5);
+        assert!(!game_boy.watchdog_triggered());
+
+        // with the LCD off no frame is ever produced, the watchdog
+        // must stop the execution instead of running forever
+        game_boy.cpu().halt();
+        game_boy.ppu().write(LCDC_ADDR, 0x00);
+        let cycles = game_boy.next_frame();
+        assert!(game_boy.watchdog_triggered());
+        assert!(cycles >= GameBoy::WATCHDOG_CYCLES);
+        assert!(cycles < GameBoy::WATCHDOG_CYCLES + 32);
+
+        game_boy.set_watchdog_cycles(GameBoy::LCD_CYCLES);
+        let cycles = game_boy.next_frame();
+        assert!(game_boy.watchdog_triggered());
+        assert!(cycles < GameBoy::LCD_CYCLES + 32);
+    }
+
+    #[test]
+    fn test_lcd_off_frames() {
+        let mut game_boy = GameBoy::default();
+        game_boy.set_lcd_off_frames(true);
+        game_boy.load(true).unwrap();
+        game_boy
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+
+        // the boot ROM keeps the LCD off for more than three frames
+        // which are now generated as synthetic (blank) frames
+        for _ in 0..3 {
+            let cycles = game_boy.next_frame();
+            assert!(cycles.abs_diff(GameBoy::LCD_CYCLES) < 32);
+            assert!(game_boy.frame_synthetic());
+        }
+        for _ in 0..60 {
+            game_boy.next_frame();
+        }
+        assert!(!game_boy.frame_synthetic());
+
+        game_boy.cpu().halt();
+        game_boy.ppu().write(LCDC_ADDR, 0x00);
+        game_boy.next_frame();
+        assert!(!game_boy.watchdog_triggered());
+        assert!(game_boy.frame_synthetic());
+        assert!(game_boy.frame_buffer().iter().all(|value| *value == 255));
+    }
+
+    #[test]
+    fn test_cheat_search() {
+        let mut game_boy = GameBoy::default();
+        game_boy.load(true).unwrap();
+        game_boy
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+        for _ in 0..60 {
+            game_boy.next_frame();
+        }
+
+        let mut search = CheatSearch::new(SearchSize::Byte);
+        let memory = game_boy.search_memory();
+        assert_eq!(memory.len(), SEARCH_MEMORY_SIZE);
+        search
+            .first_scan(&memory, SearchOperator::Equal, None)
+            .unwrap();
+
+        let value = game_boy.read_memory(0xc123);
+        game_boy.write_memory(0xc123, value.wrapping_add(5));
+        let hram = game_boy.read_memory(0xff90);
+        game_boy.write_memory(0xff90, hram.wrapping_sub(1));
+        search
+            .next_scan(
+                &game_boy.search_memory(),
+                SearchOperator::ChangedBy,
+                Some(5),
+            )
+            .unwrap();
+        assert_eq!(search.results(), vec![0xc123]);
+        assert_eq!(search.value(0xc123), Some(value.wrapping_add(5) as u16));
+
+        search
+            .first_scan(
+                &game_boy.search_memory(),
+                SearchOperator::Equal,
+                Some(hram.wrapping_sub(1) as i32),
+            )
+            .unwrap();
+        assert!(search.results().contains(&0xff90));
+    }
+
+    #[test]
+    fn test_seed() {
+        let run = || {
+            let mut game_boy = GameBoy::default();
+            game_boy.set_seed(7);
+            game_boy.load(true).unwrap();
+            game_boy
+                .load_rom_file("res/roms/demo/pocket.gb", None)
+                .unwrap();
+            assert!(game_boy.is_deterministic());
+            assert_eq!(game_boy.rom_i().clock(), Some(GameBoy::seed_epoch(7)));
+
+            // the clock advances with the emulated time, 120 frames
+            // being slightly above two seconds
+            for _ in 0..120 {
+                game_boy.next_frame();
+            }
+            assert_eq!(game_boy.rom_i().now(), GameBoy::seed_epoch(7) + 2);
+            StateManager::save(&mut game_boy, Some(SaveStateFormat::Bos), None).unwrap()
+        };
+
+        // the save states (including their timestamps) of two runs
+        // with the same seed must be exactly the same
+        assert_eq!(run(), run());
+        assert_ne!(GameBoy::seed_epoch(7), GameBoy::seed_epoch(8));
+
+        let mut game_boy = GameBoy::default();
+        game_boy.set_seed(7);
+        game_boy.clear_seed();
+        assert!(!game_boy.is_deterministic());
+        assert_eq!(game_boy.rom_i().clock(), None);
+    }
+
+    #[test]
+    fn test_sync_recovery() {
+        let mut game_boy = GameBoy::default();
+        game_boy.load(true).unwrap();
+        game_boy
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+        game_boy.set_sync_policy(SyncPolicy::new(3, 50));
+
+        // on time (or slightly late) no frames are skipped
+        assert_eq!(game_boy.recover_sync(1), 1);
+        assert_eq!(game_boy.recover_sync(3), 3);
+        assert_eq!(game_boy.av_stats().sync_recoveries, 0);
+
+        // runs until audio is being produced, then stalls for 10
+        // frames so that only 3 are emulated and 7 skipped
+        for _ in 0..120 {
+            game_boy.next_frame();
+        }
+        game_boy.take_audio_frame();
+        game_boy.next_frame();
+        assert_eq!(game_boy.recover_sync(10), 3);
+        let stats = game_boy.av_stats();
+        assert_eq!(stats.sync_recoveries, 1);
+        assert_eq!(stats.sync_skipped_frames, 7);
+        assert!(stats.has_issues());
+
+        // the pending audio is discarded and the first samples
+        // after the recovery are faded in (starting in silence)
+        assert!(game_boy.take_audio_frame().is_none());
+        game_boy.next_frame();
+        let audio_frame = game_boy.take_audio_frame().unwrap();
+        assert_eq!(audio_frame[0], 0);
+        assert_eq!(audio_frame[1], 0);
+
+        let frame_cycles = GameBoy::LCD_CYCLES as u64;
+        assert_eq!(
+            game_boy.recover_sync_cycles(frame_cycles / 2),
+            frame_cycles / 2
+        );
+        assert_eq!(
+            game_boy.recover_sync_cycles(frame_cycles * 10),
+            frame_cycles * 3
+        );
+        assert_eq!(game_boy.av_stats().sync_recoveries, 2);
+    }
+
+    #[test]
+    fn test_boot_state_for() {
+        let mut game_boy = GameBoy::new(Some(GameBoyMode::Dmg));
+        game_boy.load(false).unwrap();
+        game_boy
+            .load_rom_file("res/roms/test/blargg/cpu/cpu_instrs.gb", None)
+            .unwrap();
+
+        game_boy.load_boot_state_for(GameBoyModel::Dmg);
+        assert_eq!(game_boy.cpu_i().pc(), 0x0100);
+        assert_eq!(game_boy.cpu_i().sp(), 0xfffe);
+        assert_eq!(game_boy.cpu_i().af(), 0x01b0);
+        assert_eq!(game_boy.cpu_i().bc(), 0x0013);
+        assert_eq!(game_boy.cpu_i().de(), 0x00d8);
+        assert_eq!(game_boy.cpu_i().hl(), 0x014d);
+        assert_eq!(game_boy.mmu().read(DIV_ADDR), 0xab);
+        assert_eq!(game_boy.mmu().read(TAC_ADDR), 0xf8);
+        assert_eq!(game_boy.mmu().read(IF_ADDR), 0xe1);
+        assert_eq!(game_boy.ppu().read(LCDC_ADDR), 0x91);
+        assert_eq!(game_boy.ppu().read(BGP_ADDR), 0xfc);
+
+        game_boy.load_boot_state_for(GameBoyModel::Mgb);
+        assert_eq!(game_boy.cpu_i().af(), 0xffb0);
+
+        game_boy.load_boot_state_for(GameBoyModel::Sgb);
+        assert_eq!(game_boy.cpu_i().af(), 0x0100);
+        assert_eq!(game_boy.cpu_i().bc(), 0x0014);
+        assert_eq!(game_boy.cpu_i().hl(), 0xc060);
+
+        // the cartridge is CGB compatible so no DMG compatibility values
+        game_boy.load_boot_state_for(GameBoyModel::Cgb);
+        assert_eq!(game_boy.cpu_i().af(), 0x1180);
+        assert_eq!(game_boy.cpu_i().de(), 0xff56);
+        assert_eq!(game_boy.cpu_i().hl(), 0x000d);
+        assert_eq!(
+            GameBoyModel::Cgb.boot_registers(true, 0x00),
+            (0x1180, 0x0000, 0x0008, 0x007c)
+        );
+
+        game_boy.load_boot_state_for(GameBoyModel::Agb);
+        assert_eq!(game_boy.cpu_i().af(), 0x1100);
+        assert_eq!(game_boy.cpu_i().bc(), 0x0100);
+
+        // the default model follows the running mode
+        game_boy.load_boot_state();
+        assert_eq!(game_boy.cpu_i().af(), 0x01b0);
+
+        // a DMG only cartridge in CGB mode is colorized, with the
+        // default palettes as it's not a Nintendo licensed game
+        let mut game_boy = GameBoy::new(Some(GameBoyMode::Cgb));
+        game_boy.load(false).unwrap();
+        game_boy
+            .load_rom_file("res/roms/test/dmg_acid2.gb", None)
+            .unwrap();
+        game_boy.load_boot_state();
+        assert!(game_boy.ppu_i().dmg_compat());
+        assert_eq!(
+            game_boy.ppu_i().palettes_color()[0][0..8],
+            [0xff, 0x7f, 0xef, 0x1b, 0x80, 0x61, 0x00, 0x00]
+        );
+
+        game_boy.set_auto_colorize(false);
+        game_boy.load_boot_state();
+        assert_eq!(
+            game_boy.ppu_i().palettes_color()[0][0..8],
+            [0xff, 0x7f, 0x94, 0x52, 0x4a, 0x29, 0x00, 0x00]
+        );
+
+        assert_eq!(GameBoyModel::from_string("agb").unwrap(), GameBoyModel::Agb);
+        assert!(GameBoyModel::from_string("gba").is_err());
+    }
+
+    #[test]
+    fn test_startup_memory() {
+        let mut game_boy = GameBoy::new(Some(GameBoyMode::Dmg));
+        assert_eq!(game_boy.startup_memory(), StartupMemory::Hardware);
+        game_boy.load(false).unwrap();
+        assert_eq!(game_boy.mmu().read_many(0xc000, 4), vec![0x00; 4]);
+        assert_eq!(game_boy.mmu().read_many(0xc008, 4), vec![0xff; 4]);
+        assert_eq!(game_boy.mmu().read_many(0xc100, 4), vec![0xff; 4]);
+        assert_eq!(game_boy.mmu().read_many(0xff80, 4), vec![0x00; 4]);
+
+        game_boy.set_startup_memory(StartupMemory::Ones);
+        game_boy.reset();
+        game_boy.load(false).unwrap();
+        assert_eq!(game_boy.mmu().read_many(0xc000, 4), vec![0xff; 4]);
+        assert_eq!(game_boy.mmu().read_many(0xfff0, 4), vec![0xff; 4]);
+
+        // the random pattern of the CGB is derived from the seed
+        let build = |seed: Option<u64>| {
+            let mut game_boy = GameBoy::new(Some(GameBoyMode::Cgb));
+            if let Some(seed) = seed {
+                game_boy.set_seed(seed);
+            }
+            game_boy.load(true).unwrap();
+            game_boy.mmu().read_many(0xc000, 0x2000)
+        };
+        let ram = build(None);
+        assert_eq!(ram, build(None));
+        assert_eq!(build(Some(42)), build(Some(42)));
+        assert_ne!(build(Some(42)), ram);
+        assert!(ram.iter().filter(|byte| **byte == 0x00).count() < 0x100);
+
+        let mut game_boy = GameBoy::new(Some(GameBoyMode::Cgb));
+        game_boy.set_startup_memory(StartupMemory::Zeros);
+        game_boy.load(true).unwrap();
+        assert_eq!(game_boy.mmu().read_many(0xc000, 0x2000), vec![0x00; 0x2000]);
+
+        assert_eq!(
+            StartupMemory::from_string("stripes").unwrap(),
+            StartupMemory::Stripes
+        );
+        assert!(StartupMemory::from_string("noise").is_err());
+        assert_eq!(
+            StartupMemory::Hardware.resolve(GameBoyMode::Sgb),
+            StartupMemory::Stripes
+        );
+    }
+
+    #[test]
+    fn test_update_config() {
+        static CHANGES: Mutex<Vec<ConfigChange>> = Mutex::new(Vec::new());
+
+        let mut game_boy = GameBoy::new(Some(GameBoyMode::Dmg));
+        game_boy.load(true).unwrap();
+        game_boy.load_rom_empty().unwrap();
+        game_boy.set_config_callback(|change| CHANGES.lock().unwrap().push(*change));
+
+        assert!(game_boy.update_config(ConfigChange::LcdOffFrames(true)));
+        assert!(game_boy.lcd_off_frames());
+        assert!(game_boy.config().lcd_off_frames());
+        assert!(!game_boy.update_config(ConfigChange::LcdOffFrames(true)));
+
+        game_boy.update_config(ConfigChange::ClockFreq(GameBoy::CPU_FREQ * 2));
+        assert_eq!(game_boy.clock_freq(), GameBoy::CPU_FREQ * 2);
+        assert_eq!(game_boy.apu_i().clock_freq(), GameBoy::CPU_FREQ * 2);
+
+        game_boy.update_config(ConfigChange::AutoColorize(false));
+        assert!(!game_boy.auto_colorize());
+
+        // the accuracy profile is applied as a whole, with the changes
+        // of the toggles it controls notified as well
+        game_boy.set_accuracy(AccuracyProfile::Fast);
+        assert!(!game_boy.stat_quirks());
+        assert!(!game_boy.config().stat_quirks());
+
+        game_boy.set_seed(42);
+        game_boy.clear_seed();
+        assert!(!game_boy.is_deterministic());
+
+        assert_eq!(
+            *CHANGES.lock().unwrap(),
+            vec![
+                ConfigChange::LcdOffFrames(true),
+                ConfigChange::ClockFreq(GameBoy::CPU_FREQ * 2),
+                ConfigChange::AutoColorize(false),
+                ConfigChange::StatQuirks(false),
+                ConfigChange::OpenBus(false),
+                ConfigChange::Accuracy(AccuracyProfile::Fast),
+                ConfigChange::Seed(Some(42)),
+                ConfigChange::Seed(None),
+            ]
+        );
+        assert_eq!(
+            format!("{}", ConfigChange::ClockFreq(1024)),
+            "Clock Frequency: 1024 Hz"
+        );
+    }
+
+    #[test]
+    fn test_open_bus() {
+        let mut game_boy = GameBoy::new(Some(GameBoyMode::Cgb));
+        game_boy.load(false).unwrap();
+        game_boy.load_rom_empty().unwrap();
+        assert!(game_boy.open_bus());
+
+        // the echo RAM mirrors the currently selected WRAM bank
+        game_boy.mmu().write(SVBK_ADDR, 0x03);
+        game_boy.mmu().write(0xd010, 0x42);
+        game_boy.mmu().write(0xc010, 0x24);
+        assert_eq!(game_boy.mmu().read(0xf010), 0x42);
+        assert_eq!(game_boy.mmu().read(0xe010), 0x24);
+        game_boy.mmu().write(0xf020, 0x43);
+        assert_eq!(game_boy.mmu().read(0xd020), 0x43);
+        game_boy.mmu().write(SVBK_ADDR, 0x01);
+        assert_ne!(game_boy.mmu().read(0xf020), 0x43);
+
+        assert_eq!(game_boy.mmu().read(0xfea0), 0xaa);
+        assert_eq!(game_boy.mmu().read(0xfef7), 0xff);
+        assert_eq!(game_boy.mmu().read(0xfeb3), 0xbb);
+
+        // the cartridge has no RAM, so the area is unmapped
+        game_boy.mmu().write(0xa000, 0x12);
+        assert_eq!(game_boy.mmu().read(0xa000), 0xff);
+
+        game_boy.set_open_bus(false);
+        assert_eq!(game_boy.mmu().read(0xfea0), 0xff);
+
+        let mut game_boy = GameBoy::new(Some(GameBoyMode::Dmg));
+        game_boy.load(false).unwrap();
+        game_boy.load_rom_empty().unwrap();
+        assert_eq!(game_boy.mmu().read(0xfea0), 0x00);
+        game_boy.set_accuracy(AccuracyProfile::Fast);
+        assert!(!game_boy.open_bus());
+        assert_eq!(game_boy.mmu().read(0xfea0), 0xff);
+    }
+
+    #[test]
+    fn test_accuracy_profile() {
+        let mut game_boy = GameBoy::new(Some(GameBoyMode::Cgb));
+        game_boy.load(true).unwrap();
+        game_boy
+            .load_rom_file("res/roms/test/cgb_acid2.gbc", None)
+            .unwrap();
+        assert_eq!(game_boy.accuracy(), AccuracyProfile::Balanced);
+
+        game_boy.set_accuracy(AccuracyProfile::Accurate);
+        assert_eq!(game_boy.accuracy(), AccuracyProfile::Accurate);
+        assert_eq!(game_boy.render_mode(), RenderMode::Fifo);
+        assert_eq!(game_boy.audio_high_pass(), HighPassFilter::Cgb);
+        assert!(game_boy.stat_quirks());
+
+        game_boy.set_accuracy(AccuracyProfile::Fast);
+        assert_eq!(game_boy.render_mode(), RenderMode::Scanline);
+        assert_eq!(game_boy.audio_resampler(), Resampler::Nearest);
+        assert_eq!(game_boy.audio_high_pass(), HighPassFilter::Off);
+        assert!(!game_boy.stat_quirks());
+        assert!(!game_boy.dma().cycle_stealing());
+
+        // every profile must still be able to run the system
+        for _ in 0..10 {
+            game_boy.next_frame();
+        }
+
+        assert_eq!(
+            AccuracyProfile::from_string("accurate").unwrap(),
+            AccuracyProfile::Accurate
+        );
+        assert!(AccuracyProfile::from_string("exact").is_err());
+    }
+
+    #[test]
+    fn test_clock_domains() {
+        let mut game_boy = GameBoy::new(Some(GameBoyMode::Cgb));
+        game_boy.load(true).unwrap();
+        game_boy
+            .load_rom_file("res/roms/test/cgb_acid2.gbc", None)
+            .unwrap();
+        game_boy.next_frame();
+
+        // in double speed the CPU runs twice the cycles for the
+        // same (system domain) cycles, with the frame duration
+        // remaining the same in the system domain
+        game_boy.mmu().set_speed(GameBoySpeed::Double);
+        game_boy.mmu().write(0xffff, 0x00);
+        game_boy.cpu().halt();
+        assert_eq!(game_boy.clock(), 2);
+
+        let frame = game_boy.ppu_frame();
+        let cycles = game_boy.clock_frame();
+        assert!(cycles >= GameBoy::LCD_CYCLES);
+        assert!(cycles < GameBoy::LCD_CYCLES + 32);
+        assert_eq!(game_boy.ppu_frame(), frame.wrapping_add(1));
+
+        assert_eq!(GameBoySpeed::Double.system_cycles(8), 4);
+        assert_eq!(GameBoySpeed::Double.cpu_cycles(4), 8);
+        assert_eq!(
+            ClockDomain::Cpu.freq(GameBoySpeed::Double),
+            GameBoy::CPU_FREQ * 2
+        );
+        assert_eq!(
+            ClockDomain::System.freq(GameBoySpeed::Double),
+            GameBoy::CPU_FREQ
+        );
+    }
+
+    #[test]
+    fn test_run_for_cycles() {
+        let mut game_boy = GameBoy::default();
+        game_boy.load(true).unwrap();
+        game_boy
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+
+        // the cycles run in excess are carried over so that the
+        // total matches the requested cycles (plus the carry)
+        let mut cycles = 0;
+        for _ in 0..120 {
+            cycles += game_boy.run_for_cycles(1000).cycles;
+        }
+        assert_eq!(cycles, 120_000 + game_boy.pending_cycles() as u64);
+        assert!(game_boy.pending_cycles() < 32);
+
+        // waits for the LCD to be turned on by the boot ROM
+        game_boy.next_frame();
+        let mut clock_frame = game_boy.run_for_cycles(GameBoy::LCD_CYCLES * 2);
+        assert!(clock_frame.frames >= 1);
+        assert!(clock_frame.frame_buffer_eager().is_some());
+
+        // stopping on the first frame leaves no cycles to carry over
+        let frame = game_boy.ppu_frame();
+        let clock_frame = game_boy.run_for_cycles_with(GameBoy::LCD_CYCLES * 4, |_| false);
+        assert_eq!(clock_frame.frames, 1);
+        assert_eq!(game_boy.ppu_frame(), frame.wrapping_add(1));
+        assert_eq!(game_boy.pending_cycles(), 0);
+
+        let frame = game_boy.ppu_frame();
+        let frame_buffer = game_boy.run_frame().to_vec();
+        assert_eq!(game_boy.ppu_frame(), frame.wrapping_add(1));
+        assert_eq!(frame_buffer, game_boy.frame_buffer().to_vec());
+
+        game_boy.reset();
+        assert_eq!(game_boy.pending_cycles(), 0);
+    }
+
+    #[test]
+    fn test_state_bytes() {
+        let mut game_boy = GameBoy::default();
+        game_boy.load(true).unwrap();
+        game_boy
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+        for _ in 0..60 {
+            game_boy.next_frame();
+        }
+
+        let state = game_boy.save_state_bytes().unwrap();
+        let pc = game_boy.cpu_i().pc();
+        for _ in 0..60 {
+            game_boy.next_frame();
+        }
+        game_boy.load_state_bytes(&state).unwrap();
+        assert_eq!(game_boy.cpu_i().pc(), pc);
+        assert!(game_boy.load_state_bytes(&state[..16]).is_err());
+
+        let mut sram = game_boy.sram_bytes();
+        sram[0] = !sram[0];
+        game_boy.set_sram_bytes(&sram);
+        assert_eq!(game_boy.sram_bytes(), sram);
+    }
+
+    #[test]
+    fn test_cpu_backend() {
+        let run = |backend: CpuBackend| {
+            let mut game_boy = GameBoy::default();
+            game_boy.set_cpu_backend(backend);
+            game_boy.load(true).unwrap();
+            game_boy
+                .load_rom_file("res/roms/demo/pocket.gb", None)
+                .unwrap();
+            for _ in 0..120 {
+                game_boy.next_frame();
+            }
+            game_boy
+        };
+
+        // the cached backend runs the exact same instructions
+        // as the interpreter, producing the same frames
+        let mut interpreter = run(CpuBackend::Interpreter);
+        let mut cached = run(CpuBackend::Cached);
+        assert_eq!(
+            interpreter.frame_buffer().to_vec(),
+            cached.frame_buffer().to_vec()
+        );
+        assert_eq!(interpreter.cpu_i().pc(), cached.cpu_i().pc());
+        assert_eq!(interpreter.cpu_i().af(), cached.cpu_i().af());
+        assert_eq!(interpreter.cpu_i().hl(), cached.cpu_i().hl());
+        assert!(cached.cpu_i().blocks().decoded() > 0);
+        assert!(interpreter.cpu_i().blocks().is_empty());
+
+        // loading a state drops the cached code
+        let state = cached.save_state_bytes().unwrap();
+        cached.load_state_bytes(&state).unwrap();
+        assert!(cached.cpu_i().blocks().is_empty());
+        cached.next_frame();
+        assert!(!cached.cpu_i().blocks().is_empty());
+
+        assert_eq!(
+            CpuBackend::from_string("cached").unwrap(),
+            CpuBackend::Cached
+        );
+        assert!(CpuBackend::from_string("jit").is_err());
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut game_boy = GameBoy::default();
+        game_boy.load(true).unwrap();
+        game_boy
+            .load_rom_file("res/roms/demo/pocket.gb", None)
+            .unwrap();
+        for _ in 0..60 {
+            game_boy.next_frame();
+        }
+
+        // the breakpoint is reached before executing the instruction,
+        // and resuming from it continues until the end of the frame
+        let pc = game_boy.cpu_i().pc();
+        let breakpoints = HashSet::from([pc]);
+        assert_eq!(
+            game_boy.next_frame_breakpoints(&breakpoints, false),
+            (0, Some(pc))
+        );
+        let (mut frames, mut hits, mut resume) = (0, 0, true);
+        while frames < 10 {
+            match game_boy.next_frame_breakpoints(&breakpoints, resume) {
+                (_, Some(hit)) => {
+                    assert_eq!(hit, pc);
+                    assert_eq!(game_boy.cpu_i().pc(), pc);
+                    hits += 1;
+                    resume = true;
+                }
+                (_, None) => {
+                    frames += 1;
+                    resume = false;
+                }
+            }
+        }
+        assert!(hits >= 10);
+
+        let breakpoints = HashSet::new();
+        let frame = game_boy.ppu_frame();
+        assert_eq!(game_boy.next_frame_breakpoints(&breakpoints, false).1, None);
+        assert_eq!(game_boy.ppu_frame(), frame.wrapping_add(1));
+
+        game_boy.write_memory_range(0xc000, &[0x01, 0x02, 0x03]);
+        assert_eq!(
+            game_boy.read_memory_range(0xc000, 3),
+            vec![0x01, 0x02, 0x03]
+        );
+        assert_eq!(game_boy.read_memory_range(0xffff, 2).len(), 2);
+    }
+
+    #[test]
+    fn test_parallel_audio() {
+        let mut samples = [vec![], vec![]];
+        for (parallel, samples) in samples.iter_mut().enumerate() {
+            let mut game_boy = GameBoy::default();
+            game_boy.set_parallel_audio(parallel == 1).unwrap();
+            game_boy.load(true).unwrap();
+            game_boy
+                .load_rom_file("res/roms/demo/pocket.gb", None)
+                .unwrap();
+            for _ in 0..120 {
+                game_boy.next_frame();
+                game_boy.flush_parallel_audio();
+                if let Some(audio_frame) = game_boy.take_audio_frame() {
+                    samples.extend(audio_frame);
+                }
+            }
+            assert_eq!(game_boy.parallel_audio(), parallel == 1);
+            game_boy.set_parallel_audio(false).unwrap();
+            assert!(!game_boy.parallel_audio());
+        }
+        assert!(samples[0].iter().any(|sample| *sample != samples[0][0]));
+        assert_eq!(samples[0], samples[1]);
+    }
+
+    #[test]
+    fn test_golden() {
+        let golden = Golden::image("res/roms/test/golden/dmg_acid2.png");
+        let mut game_boy = run_golden_test(
+            "res/roms/test/dmg_acid2.gb",
+            300,
+            &golden,
+            &Tolerance::default(),
+            TestOptions {
+                mode: Some(GameBoyMode::Dmg),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let frame = game_boy.frame_buffer().to_vec();
+        check_golden(&frame, &Golden::Crc32(crc32(&frame)), &Tolerance::default()).unwrap();
+        assert!(check_golden(&frame, &Golden::Crc32(0), &Tolerance::default()).is_err());
+
+        // slightly changes a couple of pixels, which must only be
+        // accepted when within the tolerance
+        let mut changed = frame.clone();
+        changed[0] ^= 0x04;
+        changed[(DISPLAY_WIDTH + 2) * 3 + 1] ^= 0x02;
+        let diff = compare_frames(&changed, &frame, &Tolerance::default());
+        assert_eq!(diff.pixels, 2);
+        assert_eq!(diff.max_delta, 4);
+        assert_eq!(diff.first, Some((0, 0)));
+        assert_eq!(
+            compare_frames(&changed, &frame, &Tolerance::new(2, 0)).pixels,
+            1
+        );
+        assert!(check_golden(&changed, &golden, &Tolerance::default()).is_err());
+        check_golden(&changed, &golden, &Tolerance::new(4, 0)).unwrap();
+        check_golden(&changed, &golden, &Tolerance::new(0, 2)).unwrap();
+
+        assert!(
+            check_golden(&frame, &Golden::image("missing.png"), &Tolerance::default()).is_err()
+        );
+    }
 }
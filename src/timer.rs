@@ -9,6 +9,7 @@ use boytacean_common::{
 
 use crate::{
     consts::{DIV_ADDR, TAC_ADDR, TIMA_ADDR, TMA_ADDR},
+    io::{Io, IoComponent, IoRegister},
     mmu::BusComponent,
     panic_gb,
     state::{StateComponent, StateFormat},
@@ -160,6 +161,15 @@ impl Timer {
     }
 }
 
+impl Io for Timer {
+    const IO_REGISTERS: &'static [IoRegister] = &[
+        IoRegister::new(DIV_ADDR, "DIV", IoComponent::Timer, 0xff, 0xff),
+        IoRegister::new(TIMA_ADDR, "TIMA", IoComponent::Timer, 0xff, 0xff),
+        IoRegister::new(TMA_ADDR, "TMA", IoComponent::Timer, 0xff, 0xff),
+        IoRegister::new(TAC_ADDR, "TAC", IoComponent::Timer, 0x07, 0x07),
+    ];
+}
+
 impl BusComponent for Timer {
     fn read(&self, addr: u16) -> u8 {
         self.read(addr)
@@ -0,0 +1,341 @@
+//! Filters simulating the look of the original LCD screens.
+//!
+//! The LCD of the Game Boy has a slow response time, meaning that
+//! the pixels take a few frames to fully change, something that many
+//! games rely on for transparency effects (eg: flickering sprites).
+//! The ghosting is simulated by blending each new frame with the
+//! previous (blended) one, according to a persistence factor.
+//!
+//! The visible gaps between the pixels of the LCD can also be
+//! simulated with an overlay, that upscales the frame, and the
+//! frame can be upscaled with one of the [`Scaler`]s.
+
+use std::fmt::{self, Display, Formatter};
+
+use boytacean_common::error::Error;
+
+use crate::{
+    color::RGB_SIZE,
+    ppu::{DISPLAY_HEIGHT, DISPLAY_WIDTH, FRAME_BUFFER_SIZE},
+    video::scalers::Scaler,
+};
+
+/// The default scale of the frames with an overlay, as the
+/// gaps between the pixels require an upscaled frame.
+pub const OVERLAY_SCALE: usize = 3;
+
+/// The shade (out of 256) of the edges of the upscaled pixels,
+/// applied once per edge (corners shaded twice).
+const EDGE_SHADE: u16 = 192;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VideoOverlay {
+    /// No overlay, the frame keeps its native resolution.
+    #[default]
+    None,
+
+    /// Grid of dark lines between the pixels, like the ones
+    /// visible in the LCD of the DMG.
+    Grid,
+
+    /// Pixels drawn as rounded dots, with darker edges and
+    /// corners, like a dot-matrix display.
+    DotMatrix,
+}
+
+impl VideoOverlay {
+    pub fn description(&self) -> &'static str {
+        match self {
+            VideoOverlay::None => "none",
+            VideoOverlay::Grid => "grid",
+            VideoOverlay::DotMatrix => "dot-matrix",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "none" => Ok(VideoOverlay::None),
+            "grid" => Ok(VideoOverlay::Grid),
+            "dot-matrix" => Ok(VideoOverlay::DotMatrix),
+            _ => Err(Error::CustomError(format!(
+                "Invalid video overlay: {value}"
+            ))),
+        }
+    }
+}
+
+impl Display for VideoOverlay {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// Configuration of the filters applied to the video, the
+/// default configuration leaves the frames untouched.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VideoFilter {
+    /// The weight (from `0.0` to `1.0`) of the previous frame on
+    /// the blended one, `0.0` disables the ghosting.
+    pub persistence: f32,
+
+    /// The overlay drawn on top of the (blended) frame.
+    pub overlay: VideoOverlay,
+
+    /// The scale of the frame when an overlay is used, must be
+    /// at least 2 for the gaps between the pixels to be visible.
+    pub scale: usize,
+
+    /// The upscaler of the (blended) frame, that overrides the
+    /// scale of the overlay (if any) with its own factor.
+    pub scaler: Scaler,
+}
+
+impl VideoFilter {
+    pub fn new(persistence: f32, overlay: VideoOverlay) -> Self {
+        Self {
+            persistence: persistence.clamp(0.0, 1.0),
+            overlay,
+            scale: OVERLAY_SCALE,
+            scaler: Scaler::None,
+        }
+    }
+
+    /// Filter upscaling the frames with the provided scaler.
+    pub fn scaled(scaler: Scaler) -> Self {
+        Self::new(0.0, VideoOverlay::None).with_scaler(scaler)
+    }
+
+    pub fn with_scaler(mut self, scaler: Scaler) -> Self {
+        self.scaler = scaler;
+        self
+    }
+
+    /// Filter simulating the ghosting of the LCD, with the
+    /// provided persistence of the previous frame.
+    pub fn ghosting(persistence: f32) -> Self {
+        Self::new(persistence, VideoOverlay::None)
+    }
+
+    /// If the filter leaves the frames untouched.
+    pub fn is_identity(&self) -> bool {
+        self.persistence <= 0.0 && self.output_scale() == 1
+    }
+
+    /// The scale of the filtered frames, relative to the
+    /// native resolution of the display.
+    pub fn output_scale(&self) -> usize {
+        match (self.scaler, self.overlay) {
+            (Scaler::None, VideoOverlay::None) => 1,
+            (Scaler::None, _) => self.scale.max(2),
+            (scaler, _) => scaler.factor(),
+        }
+    }
+
+    pub fn output_size(&self) -> (usize, usize) {
+        let scale = self.output_scale();
+        (DISPLAY_WIDTH * scale, DISPLAY_HEIGHT * scale)
+    }
+}
+
+impl Default for VideoFilter {
+    fn default() -> Self {
+        Self::new(0.0, VideoOverlay::None)
+    }
+}
+
+/// Processor of the frames according to a [`VideoFilter`],
+/// keeping the previous (blended) frame used by the ghosting.
+pub struct FrameFilter {
+    filter: VideoFilter,
+    blended: Vec<u8>,
+    output: Vec<u8>,
+    primed: bool,
+}
+
+impl FrameFilter {
+    pub fn new(filter: VideoFilter) -> Self {
+        let (width, height) = filter.output_size();
+        Self {
+            filter,
+            blended: vec![0u8; FRAME_BUFFER_SIZE],
+            output: vec![0u8; width * height * RGB_SIZE],
+            primed: false,
+        }
+    }
+
+    pub fn filter(&self) -> &VideoFilter {
+        &self.filter
+    }
+
+    /// The last filtered frame (RGB), with the size given
+    /// by the [`VideoFilter::output_size()`] of the filter.
+    pub fn output(&self) -> &[u8] {
+        match self.filter.output_scale() {
+            1 => &self.blended,
+            _ => &self.output,
+        }
+    }
+
+    /// Discards the previous frame, so that the next frame
+    /// is not blended (eg: after a reset or a state load).
+    pub fn reset(&mut self) {
+        self.primed = false;
+    }
+
+    /// Filters the provided (RGB) frame, with the native resolution
+    /// of the display, returning the filtered frame.
+    pub fn process(&mut self, frame_buffer: &[u8]) -> &[u8] {
+        let weight = (self.filter.persistence * 256.0) as u16;
+        if !self.primed || weight == 0 {
+            self.blended
+                .copy_from_slice(&frame_buffer[..FRAME_BUFFER_SIZE]);
+            self.primed = true;
+        } else {
+            for (blended, value) in self.blended.iter_mut().zip(frame_buffer) {
+                let current = *value as u16 * (256 - weight);
+                let previous = *blended as u16 * weight;
+                *blended = ((current + previous) >> 8) as u8;
+            }
+        }
+
+        match self.filter.scaler {
+            Scaler::None => {
+                if self.filter.output_scale() > 1 {
+                    self.upscale();
+                }
+            }
+            scaler => scaler.scale(
+                &self.blended,
+                DISPLAY_WIDTH,
+                DISPLAY_HEIGHT,
+                &mut self.output,
+            ),
+        }
+
+        if self.filter.overlay != VideoOverlay::None {
+            self.draw_overlay();
+        }
+
+        self.output()
+    }
+
+    /// Upscales the blended frame into the output, repeating
+    /// each of the pixels (nearest neighbour).
+    fn upscale(&mut self) {
+        let scale = self.filter.output_scale();
+        let width = DISPLAY_WIDTH * scale;
+        for (index, target) in self.output.chunks_exact_mut(RGB_SIZE).enumerate() {
+            let (x, y) = (index % width, index / width);
+            let source = ((y / scale) * DISPLAY_WIDTH + x / scale) * RGB_SIZE;
+            target.copy_from_slice(&self.blended[source..source + RGB_SIZE]);
+        }
+    }
+
+    /// Shades the edges of the (upscaled) pixels of the output.
+    fn draw_overlay(&mut self) {
+        let scale = self.filter.output_scale();
+        let width = DISPLAY_WIDTH * scale;
+        for (index, target) in self.output.chunks_exact_mut(RGB_SIZE).enumerate() {
+            let (x, y) = (index % width, index / width);
+            let (edge_x, top_x) = (x % scale == scale - 1, x % scale == 0);
+            let (edge_y, top_y) = (y % scale == scale - 1, y % scale == 0);
+            let edges = match self.filter.overlay {
+                VideoOverlay::Grid => (edge_x || edge_y) as u8,
+                VideoOverlay::DotMatrix => (edge_x || top_x) as u8 + (edge_y || top_y) as u8,
+                VideoOverlay::None => 0,
+            };
+            for value in target.iter_mut() {
+                for _ in 0..edges {
+                    *value = ((*value as u16 * EDGE_SHADE) >> 8) as u8;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        color::RGB_SIZE,
+        ppu::{DISPLAY_HEIGHT, DISPLAY_WIDTH, FRAME_BUFFER_SIZE},
+        video::scalers::Scaler,
+    };
+
+    use super::{FrameFilter, VideoFilter, VideoOverlay, OVERLAY_SCALE};
+
+    #[test]
+    fn test_ghosting() {
+        let mut filter = FrameFilter::new(VideoFilter::ghosting(0.5));
+        assert_eq!(filter.process(&[0xff; FRAME_BUFFER_SIZE])[0], 0xff);
+        assert_eq!(filter.process(&[0x00; FRAME_BUFFER_SIZE])[0], 0x7f);
+        assert_eq!(filter.process(&[0x00; FRAME_BUFFER_SIZE])[0], 0x3f);
+
+        // the first frame after a reset is not blended
+        filter.reset();
+        assert_eq!(filter.process(&[0x00; FRAME_BUFFER_SIZE])[0], 0x00);
+    }
+
+    #[test]
+    fn test_identity() {
+        let filter = VideoFilter::default();
+        assert!(filter.is_identity());
+        assert_eq!(filter.output_size(), (DISPLAY_WIDTH, DISPLAY_HEIGHT));
+
+        let mut filter = FrameFilter::new(filter);
+        let frame_buffer: Vec<u8> = (0..FRAME_BUFFER_SIZE).map(|v| v as u8).collect();
+        assert_eq!(filter.process(&frame_buffer), &frame_buffer[..]);
+    }
+
+    #[test]
+    fn test_overlay() {
+        let scale = OVERLAY_SCALE;
+        let width = DISPLAY_WIDTH * scale;
+        let at = |x: usize, y: usize| (y * width + x) * RGB_SIZE;
+
+        let filter = VideoFilter::new(0.0, VideoOverlay::Grid);
+        assert_eq!(
+            filter.output_size(),
+            (DISPLAY_WIDTH * scale, DISPLAY_HEIGHT * scale)
+        );
+        let mut filter = FrameFilter::new(filter);
+        let output = filter.process(&[0xff; FRAME_BUFFER_SIZE]);
+        assert_eq!(output.len(), width * DISPLAY_HEIGHT * scale * RGB_SIZE);
+        assert_eq!(output[at(0, 0)], 0xff);
+        assert_eq!(output[at(scale - 1, 0)], 0xbf);
+        assert_eq!(output[at(scale - 1, scale - 1)], 0xbf);
+
+        let mut filter = FrameFilter::new(VideoFilter::new(0.0, VideoOverlay::DotMatrix));
+        let output = filter.process(&[0xff; FRAME_BUFFER_SIZE]);
+        assert_eq!(output[at(1, 1)], 0xff);
+        assert_eq!(output[at(0, 1)], 0xbf);
+        assert_eq!(output[at(0, 0)], 0x8f);
+
+        assert_eq!(
+            VideoOverlay::from_string("dot-matrix").unwrap(),
+            VideoOverlay::DotMatrix
+        );
+    }
+
+    #[test]
+    fn test_scaler() {
+        let filter = VideoFilter::scaled(Scaler::Scale2x);
+        assert!(!filter.is_identity());
+        assert_eq!(
+            filter.output_size(),
+            (DISPLAY_WIDTH * 2, DISPLAY_HEIGHT * 2)
+        );
+        let mut filter = FrameFilter::new(filter);
+        let output = filter.process(&[0x80; FRAME_BUFFER_SIZE]);
+        assert_eq!(output.len(), FRAME_BUFFER_SIZE * 4);
+        assert!(output.iter().all(|value| *value == 0x80));
+
+        // the scaler factor overrides the scale of the overlay
+        let filter = VideoFilter::new(0.0, VideoOverlay::Grid).with_scaler(Scaler::Scale2x);
+        assert_eq!(filter.output_scale(), 2);
+        let mut filter = FrameFilter::new(filter);
+        let output = filter.process(&[0xff; FRAME_BUFFER_SIZE]);
+        assert_eq!(output[0], 0xff);
+        assert_eq!(output[RGB_SIZE], 0xbf);
+        assert!(VideoOverlay::from_string("invalid").is_err());
+    }
+}
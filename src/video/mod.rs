@@ -0,0 +1,9 @@
+//! Post-processing of the video produced by the emulated LCD.
+//!
+//! Includes the [`filters`] applied to every completed frame before
+//! it's presented (eg: LCD ghosting, dot-matrix overlay) and the
+//! [`scalers`] that upscale it (eg: Scale2x), exposed to the frontends
+//! through [`GameBoy::video_frame()`](crate::gb::GameBoy::video_frame).
+
+pub mod filters;
+pub mod scalers;
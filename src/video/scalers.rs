@@ -0,0 +1,228 @@
+//! Integer upscalers of the frames, for frontends without shader support.
+//!
+//! Implements the [Scale2x and Scale3x](https://www.scale2x.it/algorithm)
+//! algorithms (also known as AdvMAME2x/3x), that enlarge the pixel art
+//! while smoothing the diagonal edges, with no blurring nor new colors.
+
+use std::fmt::{self, Display, Formatter};
+
+use boytacean_common::error::Error;
+
+use crate::color::RGB_SIZE;
+
+type Rgb = [u8; RGB_SIZE];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Scaler {
+    /// No scaling, the frame keeps its native resolution.
+    #[default]
+    None,
+
+    /// Doubles the resolution, smoothing the diagonal edges.
+    Scale2x,
+
+    /// Triples the resolution, smoothing the diagonal edges.
+    Scale3x,
+}
+
+impl Scaler {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Scaler::None => "none",
+            Scaler::Scale2x => "scale2x",
+            Scaler::Scale3x => "scale3x",
+        }
+    }
+
+    pub fn from_string(value: &str) -> Result<Self, Error> {
+        match value {
+            "none" => Ok(Scaler::None),
+            "scale2x" => Ok(Scaler::Scale2x),
+            "scale3x" => Ok(Scaler::Scale3x),
+            _ => Err(Error::CustomError(format!("Invalid scaler: {value}"))),
+        }
+    }
+
+    /// The factor by which the width and height are multiplied.
+    pub fn factor(&self) -> usize {
+        match self {
+            Scaler::None => 1,
+            Scaler::Scale2x => 2,
+            Scaler::Scale3x => 3,
+        }
+    }
+
+    /// Upscales the provided (RGB) frame, with the given dimensions,
+    /// into the target buffer, that must be `factor()` times larger
+    /// in both dimensions.
+    pub fn scale(&self, source: &[u8], width: usize, height: usize, target: &mut [u8]) {
+        match self {
+            Scaler::None => target.copy_from_slice(&source[..width * height * RGB_SIZE]),
+            Scaler::Scale2x => scale2x(source, width, height, target),
+            Scaler::Scale3x => scale3x(source, width, height, target),
+        }
+    }
+}
+
+impl Display for Scaler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// Upscales the frame by 2x using the Scale2x algorithm.
+pub fn scale2x(source: &[u8], width: usize, height: usize, target: &mut [u8]) {
+    for y in 0..height {
+        for x in 0..width {
+            let [_, b, _, d, e, f, _, h, _] = neighbours(source, width, height, x, y);
+            let mut block = [e; 4];
+            if b != h && d != f {
+                if d == b {
+                    block[0] = d;
+                }
+                if b == f {
+                    block[1] = f;
+                }
+                if d == h {
+                    block[2] = d;
+                }
+                if h == f {
+                    block[3] = f;
+                }
+            }
+            write_block(target, width, x, y, 2, &block);
+        }
+    }
+}
+
+/// Upscales the frame by 3x using the Scale3x algorithm.
+pub fn scale3x(source: &[u8], width: usize, height: usize, target: &mut [u8]) {
+    for y in 0..height {
+        for x in 0..width {
+            let [a, b, c, d, e, f, g, h, i] = neighbours(source, width, height, x, y);
+            let mut block = [e; 9];
+            if b != h && d != f {
+                if d == b {
+                    block[0] = d;
+                }
+                if (d == b && e != c) || (b == f && e != a) {
+                    block[1] = b;
+                }
+                if b == f {
+                    block[2] = f;
+                }
+                if (d == b && e != g) || (d == h && e != a) {
+                    block[3] = d;
+                }
+                if (b == f && e != i) || (h == f && e != c) {
+                    block[5] = f;
+                }
+                if d == h {
+                    block[6] = d;
+                }
+                if (d == h && e != i) || (h == f && e != g) {
+                    block[7] = h;
+                }
+                if h == f {
+                    block[8] = f;
+                }
+            }
+            write_block(target, width, x, y, 3, &block);
+        }
+    }
+}
+
+/// Obtains the 3x3 neighbourhood of the pixel (row by row), with
+/// the pixels beyond the edges of the frame clamped to them.
+fn neighbours(source: &[u8], width: usize, height: usize, x: usize, y: usize) -> [Rgb; 9] {
+    let pixel = |x: usize, y: usize| -> Rgb {
+        let index = (y * width + x) * RGB_SIZE;
+        [source[index], source[index + 1], source[index + 2]]
+    };
+    let (left, right) = (x.saturating_sub(1), (x + 1).min(width - 1));
+    let (up, down) = (y.saturating_sub(1), (y + 1).min(height - 1));
+    [
+        pixel(left, up),
+        pixel(x, up),
+        pixel(right, up),
+        pixel(left, y),
+        pixel(x, y),
+        pixel(right, y),
+        pixel(left, down),
+        pixel(x, down),
+        pixel(right, down),
+    ]
+}
+
+/// Writes the (row by row) block of pixels of the source pixel
+/// at the given position into the upscaled target.
+fn write_block(target: &mut [u8], width: usize, x: usize, y: usize, factor: usize, block: &[Rgb]) {
+    let target_width = width * factor;
+    for (index, pixel) in block.iter().enumerate() {
+        let (block_x, block_y) = (index % factor, index / factor);
+        let offset = ((y * factor + block_y) * target_width + x * factor + block_x) * RGB_SIZE;
+        target[offset..offset + RGB_SIZE].copy_from_slice(pixel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::RGB_SIZE;
+
+    use super::Scaler;
+
+    const W: [u8; 3] = [0xff, 0xff, 0xff];
+    const K: [u8; 3] = [0x00, 0x00, 0x00];
+
+    fn frame(pixels: &[[u8; 3]]) -> Vec<u8> {
+        pixels.iter().flatten().copied().collect()
+    }
+
+    fn pixel(buffer: &[u8], width: usize, x: usize, y: usize) -> [u8; 3] {
+        let index = (y * width + x) * RGB_SIZE;
+        buffer[index..index + RGB_SIZE].try_into().unwrap()
+    }
+
+    #[test]
+    fn test_scale2x() {
+        // diagonal edge, the corner of the center pixel that touches
+        // the matching neighbours is smoothed
+        let source = frame(&[K, W, W, K, K, W, K, K, K]);
+        let mut target = vec![0u8; 9 * 4 * RGB_SIZE];
+        Scaler::Scale2x.scale(&source, 3, 3, &mut target);
+        assert_eq!(pixel(&target, 6, 2, 2), K);
+        assert_eq!(pixel(&target, 6, 3, 2), W);
+        assert_eq!(pixel(&target, 6, 2, 3), K);
+        assert_eq!(pixel(&target, 6, 3, 3), K);
+
+        // a flat frame remains flat
+        let source = vec![0x80; 4 * 4 * RGB_SIZE];
+        let mut target = vec![0u8; 16 * 4 * RGB_SIZE];
+        Scaler::Scale2x.scale(&source, 4, 4, &mut target);
+        assert!(target.iter().all(|value| *value == 0x80));
+    }
+
+    #[test]
+    fn test_scale3x() {
+        let source = frame(&[K, W, W, K, K, W, K, K, K]);
+        let mut target = vec![0u8; 9 * 9 * RGB_SIZE];
+        Scaler::Scale3x.scale(&source, 3, 3, &mut target);
+        assert_eq!(pixel(&target, 9, 4, 4), K);
+        assert_eq!(pixel(&target, 9, 5, 3), W);
+        assert_eq!(pixel(&target, 9, 3, 3), K);
+        assert_eq!(pixel(&target, 9, 3, 5), K);
+    }
+
+    #[test]
+    fn test_scaler() {
+        assert_eq!(Scaler::from_string("scale3x").unwrap(), Scaler::Scale3x);
+        assert!(Scaler::from_string("invalid").is_err());
+        assert_eq!(Scaler::Scale2x.factor(), 2);
+        assert_eq!(Scaler::None.factor(), 1);
+
+        let source = frame(&[K, W, W, K]);
+        let mut target = vec![0u8; source.len()];
+        Scaler::None.scale(&source, 2, 2, &mut target);
+        assert_eq!(target, source);
+    }
+}